@@ -1,48 +1,93 @@
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
 use futures::executor;
-use wgpu::{BufferUsages, CommandEncoderDescriptor};
+use wgpu::{BufferDescriptor, BufferUsages, CommandEncoder, CommandEncoderDescriptor};
 
 use crate::{
     compute::{
-        block_jacobi_exec::BlockJacobiExecutor,
-        buffers::encode_write_f32_into_storage_buffer_at_index, dot_scalar_exec::DotScalarExecutor,
-        pcg_update_scalars_exec::PcgUpdateScalarsExecutor, spmv_exec::SpmvExecutor,
+        buffers::encode_write_f32_into_storage_buffer_at_index,
+        custom_metric::{CustomMetricExecutor, CustomStoppingMetric},
+        dot_scalar_exec::DotScalarExecutor,
+        lanczos::LanczosTridiagonal,
+        observer::SolverObserver,
+        occupancy::{KernelLaunch, compute_unit_hint},
+        operator::{LinearOperator, ReleaseBuffersOnDrop},
+        pcg_update_scalars_exec::PcgUpdateScalarsExecutor,
+        reorth::{DirectionWindow, REORTH_DIRECTIONS_PER_SUBMIT, ReorthPolicy},
+        spmv_exec::SpmvExecutor,
         vec_ops_exec::VecOpsExecutor,
     },
-    gpu::context::GpuContext,
+    gpu::{
+        context::GpuContext,
+        readback::try_read_mapped_buffer_to_vec,
+        submit::{DEFAULT_MAX_IN_FLIGHT_SUBMISSIONS, SubmissionWindow},
+        timer::GpuTimer,
+    },
+    io::npy::write_npy_f32,
 };
 
+pub mod additive_schwarz;
+pub mod additive_schwarz_exec;
+pub mod axpy;
+pub mod batched_dot;
 pub mod block_jacobi;
 pub mod block_jacobi_exec;
 pub mod buffers;
+pub mod custom_metric;
+pub mod diagonal_jacobi;
+pub mod diagonal_jacobi_exec;
+pub mod dot;
 pub mod dot_partials;
 pub mod dot_reduce;
 pub mod dot_scalar_exec;
+pub mod fixed_point_dot;
+pub mod fixed_point_dot_exec;
+pub mod gmres;
+pub mod ilu0_exec;
+pub mod lanczos;
+pub mod norms;
+pub mod observer;
+pub mod occupancy;
+pub mod operator;
 pub mod pcg_update_scalars;
 pub mod pcg_update_scalars_exec;
+pub mod precision_bench;
+pub mod reorth;
+pub mod scale;
+pub mod shaders;
 pub mod spmv;
 pub mod spmv_exec;
+pub mod ssor;
+pub mod ssor_exec;
+pub mod ssor_sweep;
+pub mod triangular_solve;
 pub mod vec_ops;
 pub mod vec_ops_exec;
+pub mod xpby;
 
-/// In-place LU factorization (no pivoting) for a small dense matrix stored in a fixed 6x6 buffer.
+/// In-place LU factorization (no pivoting) for a small dense matrix stored in a fixed
+/// `stride` x `stride` buffer.
 ///
 /// Storage / layout:
-/// - `mat` is a row-major 6x6 buffer (length must be >= 36).
-/// - `n` is the active dimension (we factor only the leading n×n block, where n <= 6).
+/// - `mat` is a row-major stride x stride buffer (length must be >= stride * stride).
+/// - `n` is the active dimension (we factor only the leading n×n block, where n <= stride).
 ///
 /// Output convention (IMPORTANT: must match block_jacobi.wgsl):
 /// - Strict lower triangle (i > j): stores L(i,j)
 /// - Diagonal + upper (i <= j): stores U(i,j)
 /// - L has implicit unit diagonal (L(i,i) == 1.0 not stored)
-pub fn lu_factor_inplace_6(mat: &mut [f32], n: usize) -> Result<(), String> {
+pub fn lu_factor_inplace(mat: &mut [f32], stride: usize, n: usize) -> Result<(), String> {
     let zero = 0.0f32;
-    let stride = 6usize;
 
-    if mat.len() < 36 {
-        return Err("lu_factor_inplace_6: mat must have len >= 36".into());
+    if mat.len() < stride * stride {
+        return Err(format!(
+            "lu_factor_inplace: mat must have len >= {}",
+            stride * stride
+        ));
     }
-    if n > 6 {
-        return Err(format!("lu_factor_inplace_6: n must be <= 6, got {n}"));
+    if n > stride {
+        return Err(format!("lu_factor_inplace: n must be <= {stride}, got {n}"));
     }
 
     for k in 0..n {
@@ -70,6 +115,11 @@ pub fn lu_factor_inplace_6(mat: &mut [f32], n: usize) -> Result<(), String> {
     Ok(())
 }
 
+/// [`lu_factor_inplace`] on a 6x6 buffer (the layout of the `_6` builders).
+pub fn lu_factor_inplace_6(mat: &mut [f32], n: usize) -> Result<(), String> {
+    lu_factor_inplace(mat, 6, n)
+}
+
 /// Build LU blocks (fixed 6x6 storage, active size m<=6) from CSR + block_starts.
 ///
 /// This is the native port of fea_app's builder and MUST match block_jacobi.wgsl.
@@ -88,12 +138,69 @@ pub fn build_lu_blocks_from_csr_block_starts_6(
     values: &[f32],
     block_starts: &[u32],
 ) -> Result<Vec<f32>, String> {
-    let block_size = 6usize;
+    build_lu_blocks_from_csr_block_starts(n, 6, row_ptr, col_idx, values, block_starts)
+}
+
+/// [`build_lu_blocks_from_csr_block_starts_6`] for `block_size` x `block_size` storage:
+/// each block is block_size² floats (row-major packed LU, leading m×m factored with
+/// m = min(block length, block_size)), the layout `BlockJacobiExecutor::create` takes
+/// for that `block_size`.
+pub fn build_lu_blocks_from_csr_block_starts(
+    n: usize,
+    block_size: usize,
+    row_ptr: &[u32],
+    col_idx: &[u32],
+    values: &[f32],
+    block_starts: &[u32],
+) -> Result<Vec<f32>, String> {
+    let mut out = gather_diagonal_blocks(n, block_size, row_ptr, col_idx, values, block_starts)?;
+
+    for (slab, w) in out
+        .chunks_exact_mut(block_size * block_size)
+        .zip(block_starts.windows(2))
+    {
+        // Factor only the leading m×m
+        let m = ((w[1] - w[0]) as usize).min(block_size);
+        lu_factor_inplace(slab, block_size, m)?;
+    }
+
+    Ok(out)
+}
+
+/// Gather the dense diagonal blocks A[block, block] from CSR + block_starts, unfactored, in
+/// the 6x6 slab layout of [`build_lu_blocks_from_csr_block_starts_6`] (36 floats per block,
+/// row-major, leading m×m filled, identity elsewhere).
+///
+/// Entries outside the diagonal blocks are ignored: block Jacobi only ever sees A[b, b].
+/// A block longer than 6 keeps its first 6 rows/cols.
+pub fn gather_diagonal_blocks_6(
+    n: usize,
+    row_ptr: &[u32],
+    col_idx: &[u32],
+    values: &[f32],
+    block_starts: &[u32],
+) -> Result<Vec<f32>, String> {
+    gather_diagonal_blocks(n, 6, row_ptr, col_idx, values, block_starts)
+}
+
+/// [`gather_diagonal_blocks_6`] for `block_size` x `block_size` slabs.
+pub fn gather_diagonal_blocks(
+    n: usize,
+    block_size: usize,
+    row_ptr: &[u32],
+    col_idx: &[u32],
+    values: &[f32],
+    block_starts: &[u32],
+) -> Result<Vec<f32>, String> {
+    if block_size == 0 {
+        return Err("build_lu_blocks_from_csr_block_starts: block_size must be >= 1".into());
+    }
+    let slab = block_size * block_size;
 
     // Basic CSR sanity
     if row_ptr.len() != n + 1 {
         return Err(format!(
-            "build_lu_blocks_from_csr_block_starts_6: row_ptr len must be n+1 ({}), got {}",
+            "build_lu_blocks_from_csr_block_starts: row_ptr len must be n+1 ({}), got {}",
             n + 1,
             row_ptr.len()
         ));
@@ -101,7 +208,7 @@ pub fn build_lu_blocks_from_csr_block_starts_6(
     let nnz = *row_ptr.last().unwrap() as usize;
     if col_idx.len() != nnz || values.len() != nnz {
         return Err(format!(
-            "build_lu_blocks_from_csr_block_starts_6: nnz mismatch: row_ptr says {}, col_idx {}, values {}",
+            "build_lu_blocks_from_csr_block_starts: nnz mismatch: row_ptr says {}, col_idx {}, values {}",
             nnz,
             col_idx.len(),
             values.len()
@@ -112,11 +219,11 @@ pub fn build_lu_blocks_from_csr_block_starts_6(
         return Ok(vec![]);
     }
     if block_starts[0] != 0 {
-        return Err("build_lu_blocks_from_csr_block_starts_6: block_starts must start at 0".into());
+        return Err("build_lu_blocks_from_csr_block_starts: block_starts must start at 0".into());
     }
     if *block_starts.last().unwrap() as usize != n {
         return Err(format!(
-            "build_lu_blocks_from_csr_block_starts_6: block_starts last must equal n ({}), got {}",
+            "build_lu_blocks_from_csr_block_starts: block_starts last must equal n ({}), got {}",
             n,
             block_starts.last().unwrap()
         ));
@@ -124,29 +231,24 @@ pub fn build_lu_blocks_from_csr_block_starts_6(
     for w in block_starts.windows(2) {
         if w[1] <= w[0] {
             return Err(
-                "build_lu_blocks_from_csr_block_starts_6: block_starts must be strictly increasing"
+                "build_lu_blocks_from_csr_block_starts: block_starts must be strictly increasing"
                     .into(),
             );
         }
     }
 
     let num_blocks = block_starts.len() - 1;
-    let mut out = vec![0.0f32; num_blocks * 36];
+    let mut out = vec![0.0f32; num_blocks * slab];
 
     for block in 0..num_blocks {
         let offset = block_starts[block] as usize;
         let end = block_starts[block + 1] as usize;
 
-        // Invalid/empty: keep zeros (or identity), but we follow fea_app: skip.
-        if end > n || offset >= end {
-            continue;
-        }
-
-        // Active size m <= 6
+        // Active size m <= block_size
         let m = (end - offset).min(block_size);
 
-        // Local dense 6x6, row-major
-        let mut mat = [0.0f32; 36];
+        // Local dense block_size x block_size, row-major
+        let mat = &mut out[block * slab..(block + 1) * slab];
 
         // Identity fill (critical for missing diagonals / partial blocks)
         for i in 0..block_size {
@@ -169,23 +271,843 @@ pub fn build_lu_blocks_from_csr_block_starts_6(
                 }
             }
         }
+    }
 
-        // Factor only the leading m×m
-        lu_factor_inplace_6(&mut mat, m)?;
+    Ok(out)
+}
+
+/// Floats per block in the QR layout of [`build_qr_blocks_from_csr_6`]: a 6x6 slab for Q
+/// followed by a 6x6 slab for R.
+pub const QR_BLOCK_STRIDE: usize = 72;
 
-        // Copy full 6x6 slab into out
-        out[block * 36..block * 36 + 36].copy_from_slice(&mat);
+/// Build thin QR factors of rectangular diagonal blocks for the least-squares block apply
+/// (block_lsq.wgsl).
+///
+/// Block b is A[rows_b, cols_b] with rows_b = [row_starts[b], row_starts[b+1]) (m rows)
+/// and cols_b = [col_starts[b], col_starts[b+1]) (k columns); it must be tall or square,
+/// 1 <= k <= m <= 6. It is factored A_b = Q R by modified Gram-Schmidt in f64 (Q: m x k,
+/// orthonormal columns; R: k x k upper triangular), which turns the least-squares problem
+/// min ||A_b z - r_b|| into the triangular solve R z = Q^T r_b.
+///
+/// As with [`gather_diagonal_blocks_6`], entries outside the blocks are ignored. A column
+/// that is (numerically) a combination of the previous ones makes the block rank-deficient,
+/// which is an error: the least-squares solution would not be unique.
+///
+/// Output: concatenated blocks of [`QR_BLOCK_STRIDE`] floats, Q then R, each a 6x6
+/// row-major slab with zeros outside the leading m x k / k x k.
+pub fn build_qr_blocks_from_csr_6(
+    n_rows: usize,
+    n_cols: usize,
+    row_ptr: &[u32],
+    col_idx: &[u32],
+    values: &[f32],
+    row_starts: &[u32],
+    col_starts: &[u32],
+) -> Result<Vec<f32>, String> {
+    let block_size = 6usize;
+
+    if row_ptr.len() != n_rows + 1 {
+        return Err(format!(
+            "build_qr_blocks_from_csr_6: row_ptr len must be n_rows+1 ({}), got {}",
+            n_rows + 1,
+            row_ptr.len()
+        ));
+    }
+    let nnz = *row_ptr.last().unwrap() as usize;
+    if col_idx.len() != nnz || values.len() != nnz {
+        return Err(format!(
+            "build_qr_blocks_from_csr_6: nnz mismatch: row_ptr says {}, col_idx {}, values {}",
+            nnz,
+            col_idx.len(),
+            values.len()
+        ));
+    }
+    if row_starts.len() != col_starts.len() {
+        return Err(format!(
+            "build_qr_blocks_from_csr_6: row_starts and col_starts must have the same length, got {} and {}",
+            row_starts.len(),
+            col_starts.len()
+        ));
+    }
+    for (name, starts, len) in [
+        ("row_starts", row_starts, n_rows),
+        ("col_starts", col_starts, n_cols),
+    ] {
+        if starts.len() < 2 {
+            return Ok(vec![]);
+        }
+        if starts[0] != 0 || *starts.last().unwrap() as usize != len {
+            return Err(format!(
+                "build_qr_blocks_from_csr_6: {name} must run from 0 to {len}"
+            ));
+        }
+        if starts.windows(2).any(|w| w[1] <= w[0]) {
+            return Err(format!(
+                "build_qr_blocks_from_csr_6: {name} must be strictly increasing"
+            ));
+        }
+    }
+
+    let num_blocks = row_starts.len() - 1;
+    let mut out = vec![0.0f32; num_blocks * QR_BLOCK_STRIDE];
+
+    for block in 0..num_blocks {
+        let row0 = row_starts[block] as usize;
+        let col0 = col_starts[block] as usize;
+        let m = row_starts[block + 1] as usize - row0;
+        let k = col_starts[block + 1] as usize - col0;
+        if m > block_size || k > m {
+            return Err(format!(
+                "build_qr_blocks_from_csr_6: block {block} is {m}x{k}; need 1 <= cols <= rows <= 6"
+            ));
+        }
+
+        // Dense A_b (row-major); Gram-Schmidt overwrites its columns with Q.
+        let mut a = [[0.0f64; 6]; 6];
+        for (i_local, a_row) in a.iter_mut().enumerate().take(m) {
+            let i = row0 + i_local;
+            for idx in row_ptr[i] as usize..row_ptr[i + 1] as usize {
+                let j = col_idx[idx] as usize;
+                if j >= col0 && j < col0 + k {
+                    a_row[j - col0] = values[idx] as f64;
+                }
+            }
+        }
+        let rows = &mut a[..m];
+
+        let mut r = [[0.0f64; 6]; 6];
+        for j in 0..k {
+            let scale = rows.iter().fold(0.0f64, |acc, row| acc.max(row[j].abs()));
+            for l in 0..j {
+                let r_lj: f64 = rows.iter().map(|row| row[l] * row[j]).sum();
+                r[l][j] = r_lj;
+                for row in rows.iter_mut() {
+                    row[j] -= r_lj * row[l];
+                }
+            }
+            let norm = rows.iter().map(|row| row[j] * row[j]).sum::<f64>().sqrt();
+            if norm <= 1e-10 * scale {
+                return Err(format!(
+                    "build_qr_blocks_from_csr_6: block {block} (rows {}..{}, cols {}..{}) is rank-deficient at column {j}",
+                    row0,
+                    row0 + m,
+                    col0,
+                    col0 + k
+                ));
+            }
+            r[j][j] = norm;
+            for row in rows.iter_mut() {
+                row[j] /= norm;
+            }
+        }
+
+        let slab = &mut out[block * QR_BLOCK_STRIDE..(block + 1) * QR_BLOCK_STRIDE];
+        let (q_slab, r_slab) = slab.split_at_mut(36);
+        for i in 0..m {
+            for j in 0..k {
+                q_slab[i * block_size + j] = a[i][j] as f32;
+            }
+        }
+        for i in 0..k {
+            for j in i..k {
+                r_slab[i * block_size + j] = r[i][j] as f32;
+            }
+        }
     }
 
     Ok(out)
 }
 
+/// Scalar slots a [`DotScalarExecutor`] needs for [`pcg_block_jacobi_csr_wgpu`]: the seven
+/// PCG scalars plus one each for `PcgOptions::custom_stopping_metric` and the
+/// `PcgOptions::reorthogonalize` coefficients.
+pub const PCG_SCALAR_RESULTS_LEN: usize = 9;
+
+/// How the residual r is kept up to date (see `PcgOptions::residual_strategy`).
+///
+/// Each iteration updates r by the recurrence r <- r - alpha A p, which needs no extra
+/// work but in f32 drifts away from the true residual b - A x. Past the attainable
+/// accuracy the recurrence keeps shrinking while b - A x stalls, so a tight tolerance can
+/// be "reached" by a solution that does not satisfy it. Recomputing r from x closes the gap.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResidualStrategy {
+    /// Recurrence only. Cheapest; the stopping test can be fooled by drift.
+    #[default]
+    Recurrence,
+    /// r <- b - A x after every m-th iteration (residual replacement), recurrence in
+    /// between. Costs one SpMV + one axpy every m iterations; the drift is reset at those
+    /// points but can build up again between them, where the test still sees the
+    /// recurrence.
+    TrueEvery(u32),
+    /// r <- b - A x after every iteration: one extra SpMV + axpy per iteration (SpMV work
+    /// doubles). The stopping test always sees the true residual, so the solve never
+    /// reports a tolerance it did not reach -- below the attainable accuracy it fails to
+    /// converge instead. Replacing r every step also perturbs the recurrences, so close to
+    /// that accuracy it tends to progress more slowly than `TrueEvery`.
+    AlwaysTrue,
+}
+
+impl ResidualStrategy {
+    /// Whether r is recomputed from x after `iteration` (counting from 1).
+    pub fn recompute_after(self, iteration: usize) -> bool {
+        match self {
+            ResidualStrategy::Recurrence => false,
+            ResidualStrategy::TrueEvery(m) => m > 0 && iteration.is_multiple_of(m as usize),
+            ResidualStrategy::AlwaysTrue => true,
+        }
+    }
+}
+
+/// When a solve counts as converged (see `PcgOptions::stopping_criterion`). Either way the
+/// solve also stops once ||r|| <= abs_tol.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum StoppingCriterion {
+    /// ||r|| <= rel_tol * ||b||: a target for the solution itself, independent of x0.
+    #[default]
+    Relative,
+    /// ||r|| / ||r0|| < factor, with r0 = b - A x0 computed once at the start; rel_tol is
+    /// ignored. The target moves with the starting residual, which is what the inner
+    /// solve of an inexact Newton method needs: with an Eisenstat-Walker forcing term
+    /// eta_k, `ReductionFactor(eta_k)` asks for exactly ||b - A x|| < eta_k ||r0||,
+    /// however close x0 already is. `Relative` on the same system would over-solve from
+    /// a good x0 (or under-solve from a bad one). Costs one extra dot at setup.
+    ReductionFactor(f32),
+}
+
+impl StoppingCriterion {
+    /// Whether a residual with r^T r = `r_norm2` meets the criterion, given ||b||^2 and
+    /// ||r0||^2 (the latter only read by `ReductionFactor`).
+    pub fn is_met(
+        self,
+        r_norm2: f32,
+        b_norm2: f32,
+        r0_norm2: f32,
+        rel_tol: f32,
+        abs_tol: f32,
+    ) -> bool {
+        r_norm2 <= abs_tol * abs_tol
+            || match self {
+                StoppingCriterion::Relative => r_norm2 <= rel_tol * rel_tol * b_norm2,
+                StoppingCriterion::ReductionFactor(factor) => r_norm2 < factor * factor * r0_norm2,
+            }
+    }
+
+    /// The relative tolerance (w.r.t. ||b||) the criterion amounts to, for the
+    /// precision-floor check.
+    pub fn equivalent_rel_tol(self, b_norm2: f32, r0_norm2: f32, rel_tol: f32) -> f32 {
+        match self {
+            StoppingCriterion::Relative => rel_tol,
+            StoppingCriterion::ReductionFactor(factor) => factor * (r0_norm2 / b_norm2).sqrt(),
+        }
+    }
+
+    pub(crate) fn validate(self) -> Result<(), String> {
+        match self {
+            StoppingCriterion::ReductionFactor(factor) if !(factor.is_finite() && factor > 0.0) => {
+                Err(format!(
+                    "reduction factor must be finite and > 0, got {factor}"
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The first `PcgOptions` field set away from its default, other than
+/// `stopping_criterion`: the only one the host-driven loops (`ssor::ssor_cg_csr_wgpu`,
+/// `gmres::gmres_block_jacobi_csr_wgpu`) read.
+pub(crate) fn unsupported_option(options: &PcgOptions) -> Option<&'static str> {
+    let default = PcgOptions::default();
+    [
+        ("timing", options.timing),
+        ("timestamp_period_ns", options.timestamp_period_ns.is_some()),
+        ("compute_units", options.compute_units.is_some()),
+        ("snapshot_interval", options.snapshot_interval.is_some()),
+        ("snapshot_dir", options.snapshot_dir != default.snapshot_dir),
+        ("exact_solution", options.exact_solution.is_some()),
+        (
+            "initial_preconditioned_residual",
+            options.initial_preconditioned_residual.is_some(),
+        ),
+        (
+            "readback_buffering",
+            options.readback_buffering != default.readback_buffering,
+        ),
+        (
+            "dot_precision",
+            options.dot_precision != default.dot_precision,
+        ),
+        ("dot_subgroup_size", options.dot_subgroup_size.is_some()),
+        ("dot_workgroup_sizes", options.dot_workgroup_sizes.is_some()),
+        (
+            "max_in_flight_submissions",
+            options.max_in_flight_submissions != default.max_in_flight_submissions,
+        ),
+        (
+            "estimate_condition_number",
+            options.estimate_condition_number,
+        ),
+        ("ritz_value_history", options.ritz_value_history),
+        (
+            "custom_stopping_metric",
+            options.custom_stopping_metric.is_some(),
+        ),
+        (
+            "capture_at_iteration",
+            options.capture_at_iteration.is_some(),
+        ),
+        (
+            "reorthogonalize",
+            options.reorthogonalize != default.reorthogonalize,
+        ),
+        (
+            "residual_strategy",
+            options.residual_strategy != default.residual_strategy,
+        ),
+        ("trace_iterations", options.trace_iterations),
+        ("submit_every", options.submit_every != default.submit_every),
+    ]
+    .into_iter()
+    .find_map(|(field, set)| set.then_some(field))
+}
+
+/// Floating-point type a GPU computation runs in where it may be wider than the f32
+/// vectors it reads (today: the dot-product accumulator, `PcgOptions::dot_precision`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Precision {
+    #[default]
+    F32,
+    /// Needs a device with SHADER_F64 (see [`Precision::required_features`]).
+    F64,
+}
+
+impl Precision {
+    /// WGSL type name.
+    pub fn wgsl_type(self) -> &'static str {
+        match self {
+            Precision::F32 => "f32",
+            Precision::F64 => "f64",
+        }
+    }
+
+    /// Size of one value in bytes.
+    pub fn size_bytes(self) -> u32 {
+        match self {
+            Precision::F32 => 4,
+            Precision::F64 => 8,
+        }
+    }
+
+    /// Device features needed besides the defaults.
+    pub fn required_features(self) -> wgpu::Features {
+        match self {
+            Precision::F32 => wgpu::Features::empty(),
+            Precision::F64 => wgpu::Features::SHADER_F64,
+        }
+    }
+
+    /// Whether `ctx` can run kernels in this precision.
+    pub fn supported_by(self, ctx: &GpuContext) -> bool {
+        ctx.features.contains(self.required_features())
+    }
+}
+
+/// Optional knobs for [`pcg_block_jacobi_csr_wgpu`].
+///
+/// `Default` reproduces the plain loop (no extra work per iteration).
+#[derive(Debug, Clone)]
+pub struct PcgOptions {
+    /// Collect a per-operation timing breakdown ([`PcgTimings`]).
+    ///
+    /// The GPU buckets need TIMESTAMP_QUERY; without it the timings are wall-clock only
+    /// and flagged [`PcgTimings::approximate`]. With TIMESTAMP_QUERY_INSIDE_ENCODERS as well (`gpu::timer::GPU_TIMER_FEATURES`) the
+    /// sections are exact; otherwise they come from pass boundaries and are coarser (see
+    /// `gpu::timer::TimestampMode`).
+    /// Costs a few timestamp writes and one extra small readback per iteration.
+    pub timing: bool,
+
+    /// Nanoseconds per timestamp tick for `timing`, overriding the queue's reported
+    /// period on drivers that get it wrong (see `GpuTimer::set_timestamp_period`). The
+    /// period in effect is returned in [`PcgTimings::timestamp_period_ns`].
+    pub timestamp_period_ns: Option<f32>,
+
+    /// Compute units of the device for the occupancy hints in
+    /// [`PcgTimings::kernel_launches`], overriding `occupancy::compute_unit_hint` (which
+    /// only knows CPU adapters; wgpu does not report SM / CU counts).
+    pub compute_units: Option<u32>,
+
+    /// Dump x to `snapshot_dir` every `snapshot_interval` iterations (and once more at
+    /// convergence) as `x_iter_<iteration>.npy`.
+    ///
+    /// Each snapshot costs a full readback of x plus a file write, outside the
+    /// single-readback-per-iteration budget; meant for debugging, not production runs.
+    pub snapshot_interval: Option<u32>,
+    pub snapshot_dir: PathBuf,
+
+    /// Known exact solution (manufactured-solution studies). When set, the A-norm of the
+    /// error ||x - x_exact||_A is computed after every iteration into
+    /// [`PcgResult::error_a_norm_history`].
+    ///
+    /// Costs per iteration: one extra axpy, SpMV and dot (e = x - x_exact, A e, e^T A e)
+    /// in a second submit, plus one more scalar readback.
+    pub exact_solution: Option<Vec<f32>>,
+
+    /// Precomputed z0 = M^{-1} (b - A x0), e.g. from an outer solve that already applied
+    /// the preconditioner to this residual: uploaded instead of running the first
+    /// preconditioner apply. Only valid for the exact b, x0 and preconditioner of the solve;
+    /// nothing checks that, and a wrong z0 silently changes the whole iteration (the search
+    /// directions stop being conjugate). Supplying the z0 the solve would compute gives the
+    /// same trajectory. Saves one apply per solve, so it only pays off in tight nesting.
+    pub initial_preconditioned_residual: Option<Vec<f32>>,
+
+    /// Scalar readback staging: one buffer or two alternating ones (no faster in this
+    /// loop, which waits for every readback). Applied when the solve's executors are
+    /// built, e.g. by `device::SolverDevice`; see [`dot_scalar_exec::ReadbackBuffering`].
+    pub readback_buffering: dot_scalar_exec::ReadbackBuffering,
+
+    /// Accumulator of the GPU dot products (alpha, beta and the residual norm), independent
+    /// of the f32 vectors and kernels everywhere else: with [`Precision::F64`] each product
+    /// and every partial sum is formed in f64 and only the final scalar is rounded to f32.
+    /// This removes the summation error of long dots, which in ill-conditioned solves
+    /// feeds the recurrence drift behind false convergence (see [`ResidualStrategy`]).
+    ///
+    /// Modest cost: the dots read the same f32 inputs; only the partials (and the shared
+    /// memory of the partials/reduce passes) double in size, plus one single-thread
+    /// narrowing pass per dot. It does nothing about the f32 SpMV and vector updates.
+    /// Needs SHADER_F64; building the executors fails without it. Applied like
+    /// `readback_buffering`. The CPU reference path always accumulates its dots in f64.
+    pub dot_precision: Precision,
+
+    /// Subgroup size the subgroup-reduce dot assumes, for drivers that mis-report theirs
+    /// (which silently corrupts the sums): `Some(0)` forces the shared-memory tree reduce,
+    /// `Some(n)` assumes n, `None` takes [`dot_partials::SUBGROUP_SIZE_ENV_VAR`] or else
+    /// the adapter's report (tree reduce when it gives a range or lacks SUBGROUP). An
+    /// override that is too small only wastes a little shared memory; one that is too
+    /// large loses sums. Applied like `readback_buffering`; f32 dots only.
+    pub dot_subgroup_size: Option<u32>,
+
+    /// WORKGROUP_SIZE overrides (partials, reduce) of the dot pipelines; `None` keeps
+    /// 256 for both. The best value varies a lot between devices (an integrated GPU
+    /// often wants less, a large discrete one more). Both must be powers of two >= 2,
+    /// since the shared-memory tree reduce halves the active range each step, and within
+    /// the device's workgroup limits (`dot_partials::check_reduce_workgroup_size`);
+    /// building the executors fails otherwise. Applied like `readback_buffering`.
+    pub dot_workgroup_sizes: Option<(u32, u32)>,
+
+    /// Max submissions the solve keeps unfinished on the GPU (>= 1, default
+    /// [`DEFAULT_MAX_IN_FLIGHT_SUBMISSIONS`]). A submit beyond it first waits for the oldest
+    /// one to complete (see [`SubmissionWindow`]). The loop reads scalars back after every
+    /// submit, so today it stays at 1; the cap guards deeper pipelining.
+    pub max_in_flight_submissions: usize,
+
+    /// Estimate the condition number of the preconditioned operator M^{-1} A from the CG
+    /// coefficients (Lanczos connection, see [`lanczos::LanczosTridiagonal`]).
+    ///
+    /// Free on the GPU side (alpha and beta are already in the per-iteration readback);
+    /// the host solves a k x k tridiagonal eigenproblem once at the end. The estimate
+    /// improves with the number of iterations and approaches kappa from below.
+    pub estimate_condition_number: bool,
+
+    /// Diagnostic: record the extreme Ritz values (lambda_min, lambda_max of the Lanczos
+    /// tridiagonal T_k) after every iteration k in [`PcgResult::ritz_value_history`], to
+    /// watch the spectrum estimate of M^{-1} A converge. Run with an
+    /// [`operator::IdentityOperator`] preconditioner for the spectrum of A itself.
+    ///
+    /// Nothing extra on the GPU; the host redoes the eigenvalue bisection of
+    /// `estimate_condition_number` every iteration, O(k^2) in total (see
+    /// [`lanczos::LanczosTridiagonal`]).
+    pub ritz_value_history: bool,
+
+    /// Extra stopping test on a scalar computed on the GPU every iteration by a custom
+    /// kernel (see [`custom_metric`]): the solve also counts as converged once the metric
+    /// is <= its threshold, whatever the residual. Values go to
+    /// [`PcgResult::custom_metric_history`].
+    ///
+    /// The kernel is compiled at the start of each solve. Per iteration it costs the
+    /// kernel pass plus its reduce; the scalar rides along in the existing readback.
+    /// GPU only: the CPU reference path rejects it.
+    pub custom_stopping_metric: Option<CustomStoppingMetric>,
+
+    /// Debugger hook: encode an empty compute pass labeled [`capture_marker_label`] (plus a
+    /// debug marker of the same name, both behind the context's label prefix) at the start
+    /// of this iteration's command buffer.
+    ///
+    /// Capture the whole run in RenderDoc / Xcode / PIX and search the event list for the
+    /// label to land on the iteration that goes wrong; GPU only, no dispatch, no cost when
+    /// unset. Iterations count from 1 like [`PcgResult::iterations`].
+    pub capture_at_iteration: Option<u32>,
+
+    /// Reorthogonalize each new search direction against the last k ones in the A inner
+    /// product (see [`reorth`]), for badly conditioned problems where f32 CG loses
+    /// conjugacy and stalls.
+    ///
+    /// `Window(k)` keeps 2 k vectors of length n on the GPU and adds k dots + k axpys per
+    /// iteration, in extra submits of [`REORTH_DIRECTIONS_PER_SUBMIT`] directions each.
+    pub reorthogonalize: ReorthPolicy,
+
+    /// Recurrence residual, true residual b - A x every m iterations, or every iteration
+    /// (see [`ResidualStrategy`] for the cost / accuracy of each). The recomputation runs
+    /// in the iteration's submit, between the x / r update and ||r||^2; with `timing` its
+    /// SpMV lands in `vec_ops_ms`.
+    pub residual_strategy: ResidualStrategy,
+
+    /// Residual target relative to ||b|| (`rel_tol`) or to ||r0|| (a reduction factor for
+    /// inexact inner solves), see [`StoppingCriterion`].
+    pub stopping_criterion: StoppingCriterion,
+
+    /// Record one [`IterationTrace`] per iteration into [`PcgResult::iteration_trace`]
+    /// (e.g. for a JSON Lines trace file). Host-side only: everything it holds is already
+    /// in the per-iteration scalar readback.
+    pub trace_iterations: bool,
+
+    /// Iterations recorded into one command buffer per submit (>= 1, default 1).
+    ///
+    /// The solve never builds up one long command buffer: with 1, every iteration is its
+    /// own submit followed by a blocking scalar readback, so at most one iteration of work
+    /// is queued and the GPU is handed back (to the compositor, other apps) between
+    /// iterations. That is the most responsive setting, and it pays one submit +
+    /// map/readback round trip per iteration. N > 1 records N iterations back to back,
+    /// each copying its scalars into a batch staging buffer, and syncs once per batch:
+    /// fewer round trips (throughput, noticeable when n is small and the round trip
+    /// dominates) for command buffers N times longer, during which a desktop GPU stays
+    /// busy. The arithmetic is identical; only the stopping test moves to batch ends, so
+    /// a solve may run up to N - 1 iterations past the one that met the tolerance (they
+    /// are counted in [`PcgResult::iterations`] and the histories).
+    ///
+    /// N > 1 needs every iteration to record the same passes without host input, so it
+    /// is rejected together with `timing`, `snapshot_interval`, `exact_solution`,
+    /// `reorthogonalize` and `ResidualStrategy::TrueEvery`. The CPU reference ignores it.
+    pub submit_every: u32,
+}
+
+impl Default for PcgOptions {
+    fn default() -> Self {
+        Self {
+            timing: false,
+            timestamp_period_ns: None,
+            compute_units: None,
+            snapshot_interval: None,
+            snapshot_dir: PathBuf::new(),
+            exact_solution: None,
+            initial_preconditioned_residual: None,
+            readback_buffering: dot_scalar_exec::ReadbackBuffering::default(),
+            dot_precision: Precision::F32,
+            dot_subgroup_size: None,
+            dot_workgroup_sizes: None,
+            max_in_flight_submissions: DEFAULT_MAX_IN_FLIGHT_SUBMISSIONS,
+            estimate_condition_number: false,
+            ritz_value_history: false,
+            custom_stopping_metric: None,
+            capture_at_iteration: None,
+            reorthogonalize: ReorthPolicy::None,
+            residual_strategy: ResidualStrategy::Recurrence,
+            stopping_criterion: StoppingCriterion::Relative,
+            trace_iterations: false,
+            submit_every: 1,
+        }
+    }
+}
+
+/// Everything a solve is configured with: when to stop (`max_iters`, `rel_tol`,
+/// `abs_tol`) and the [`PcgOptions`] knobs. Taken by the solver entry points
+/// ([`PcgSolver::new`](crate::solver::pcg::PcgSolver::new), the `SolverDevice` PCG
+/// methods, [`ssor::ssor_cg_csr_wgpu`], [`gmres::gmres_block_jacobi_csr_wgpu`]); the
+/// executor-level PCG core loop ([`pcg_block_jacobi_csr_wgpu`]) keeps explicit
+/// arguments.
+///
+/// Set only what differs from the defaults:
+/// `SolveConfig::default().tol(1e-6).max_iters(500).relative()`.
+#[derive(Debug, Clone)]
+pub struct SolveConfig {
+    /// Iteration cap; the solve fails with "did not converge" past it. Default 1000.
+    pub max_iters: usize,
+    /// Relative tolerance, read by the `Relative` criterion: ||r|| <= rel_tol * ||b||.
+    /// Default 1e-5, which stays above the f32 floor sqrt(n) * eps
+    /// ([`f32_residual_floor`]) up to n of about 7000.
+    pub rel_tol: f32,
+    /// Absolute tolerance: the solve also stops once ||r|| <= abs_tol, whatever the
+    /// criterion. Default 0 (off).
+    pub abs_tol: f32,
+    /// The remaining knobs; default [`PcgOptions::default`] (the plain loop).
+    pub options: PcgOptions,
+}
+
+impl Default for SolveConfig {
+    fn default() -> Self {
+        Self {
+            max_iters: 1000,
+            rel_tol: 1e-5,
+            abs_tol: 0.0,
+            options: PcgOptions::default(),
+        }
+    }
+}
+
+impl SolveConfig {
+    /// Relative tolerance (`rel_tol`).
+    pub fn tol(mut self, rel_tol: f32) -> Self {
+        self.rel_tol = rel_tol;
+        self
+    }
+
+    pub fn abs_tol(mut self, abs_tol: f32) -> Self {
+        self.abs_tol = abs_tol;
+        self
+    }
+
+    pub fn max_iters(mut self, max_iters: usize) -> Self {
+        self.max_iters = max_iters;
+        self
+    }
+
+    /// Stop on ||r|| <= rel_tol * ||b|| ([`StoppingCriterion::Relative`], the default).
+    pub fn relative(mut self) -> Self {
+        self.options.stopping_criterion = StoppingCriterion::Relative;
+        self
+    }
+
+    /// Stop on ||r|| / ||r0|| < factor ([`StoppingCriterion::ReductionFactor`]).
+    pub fn reduction_factor(mut self, factor: f32) -> Self {
+        self.options.stopping_criterion = StoppingCriterion::ReductionFactor(factor);
+        self
+    }
+
+    /// Dot-product accumulator ([`PcgOptions::dot_precision`]).
+    pub fn precision(mut self, precision: Precision) -> Self {
+        self.options.dot_precision = precision;
+        self
+    }
+
+    pub fn residual_strategy(mut self, strategy: ResidualStrategy) -> Self {
+        self.options.residual_strategy = strategy;
+        self
+    }
+
+    /// Replace all [`PcgOptions`] at once (for the knobs without a method here).
+    pub fn options(mut self, options: PcgOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+/// Where the solve time went, accumulated over all iterations (milliseconds).
+///
+/// GPU buckets come from timestamps written between the passes of each iteration:
+/// - `spmv_ms`: A*p (including the copy into the SpMV input)
+/// - `preconditioner_ms`: z = M^{-1} r
+/// - `dots_ms`: the three dot products + their reductions
+/// - `vec_ops_ms`: scalar updates (alpha/beta) and the AXPY/scale passes
+///
+/// Host buckets are wall-clock:
+/// - `readback_ms`: submit -> scalars on the host, minus the GPU compute above
+///   (queue latency, the scalar copy, map/unmap), plus the final x readback, snapshots
+///   and the optional error A-norm pass
+/// - `host_encode_ms`: recording commands (bind groups, uniform writes)
+/// - `setup_ms`: ||b||, uploads and the r0/z0/p0 initialization
+///
+/// These partition the solve, so their sum is close to `total_ms`.
+///
+/// `timestamp_period_ns` is the tick length the GPU buckets were converted with.
+///
+/// Without TIMESTAMP_QUERY the GPU buckets stay 0, `readback_ms` takes the whole
+/// submit -> scalars time of every iteration and `approximate` is set. The partition (and
+/// `iteration_ms`) still holds, but GPU work can no longer be told apart from queue
+/// latency and driver overhead, which on a discrete GPU can be a large part of a short
+/// iteration; treat the numbers as upper bounds on the GPU time.
+#[derive(Debug, Clone, Default)]
+pub struct PcgTimings {
+    pub spmv_ms: f64,
+    pub preconditioner_ms: f64,
+    pub dots_ms: f64,
+    pub vec_ops_ms: f64,
+    pub readback_ms: f64,
+    pub host_encode_ms: f64,
+    pub setup_ms: f64,
+    pub total_ms: f64,
+    /// 0 when `approximate`.
+    pub timestamp_period_ns: f32,
+    /// Wall-clock only (no GPU timestamps); see above.
+    pub approximate: bool,
+    /// Wall-clock time of every iteration, from recording its commands to having its
+    /// scalars on the host. Measured the same way with or without timestamps.
+    pub iteration_ms: Vec<f64>,
+    /// Launch shape of every kernel of one plain iteration (SpMV, preconditioner, dots,
+    /// vector updates, scalar updates; `dispatches` per iteration). Optional passes
+    /// (custom metric, reorthogonalization, true residuals) are not listed, and a
+    /// preconditioner that does not report its launches is missing.
+    pub kernel_launches: Vec<KernelLaunch>,
+    /// Compute units the launches can be compared with (`PcgOptions::compute_units` or
+    /// `occupancy::compute_unit_hint`); see `compute/occupancy.rs` for what the
+    /// comparison can and cannot tell.
+    pub compute_units: Option<u32>,
+}
+
+impl PcgTimings {
+    /// Sum of all buckets (everything except `total_ms`).
+    pub fn sum_ms(&self) -> f64 {
+        self.spmv_ms
+            + self.preconditioner_ms
+            + self.dots_ms
+            + self.vec_ops_ms
+            + self.readback_ms
+            + self.host_encode_ms
+            + self.setup_ms
+    }
+}
+
+/// Kernels of one plain PCG iteration (see [`PcgTimings::kernel_launches`]): SpMV, the
+/// preconditioner, three dots, 3 AXPYs + 1 scale and 2 scalar updates.
+fn pcg_kernel_launches(
+    n: u32,
+    spmv_exec: &SpmvExecutor,
+    preconditioner: &dyn LinearOperator,
+    dot_scalar_exec: &DotScalarExecutor,
+    sizes: WorkgroupSizes,
+) -> Vec<KernelLaunch> {
+    let mut launches = vec![spmv_exec.kernel_launch()];
+    launches.extend(preconditioner.kernel_launches());
+    launches.extend(
+        dot_scalar_exec
+            .dot_kernel_launches(n)
+            .into_iter()
+            .map(|launch| launch.times(3)),
+    );
+    launches.push(KernelLaunch::new("vec_ops", sizes.vec_ops, n.div_ceil(sizes.vec_ops)).times(3));
+    launches.push(KernelLaunch::new("pcg_update_scalars", sizes.pcg_update_scalars, 1).times(2));
+    launches
+}
+
+/// Smallest residual norm f32 arithmetic can be expected to reach: ~ sqrt(n) * eps * ||b||.
+///
+/// Heuristic: each of the n entries of r = b - A x carries a rounding error of about
+/// eps * |b_i|, and those add up like a random walk, so ||r|| stalls around
+/// sqrt(n) * eps * ||b|| (eps = f32::EPSILON ~ 1.2e-7). Ill-conditioned problems can
+/// stall earlier; this only catches requests that are hopeless even in the best case.
+///
+/// This bounds the TRUE residual. PCG's recursively updated r drifts away from b - A x
+/// and may keep shrinking past the floor, which is why a flagged solve can still
+/// report convergence.
+pub fn f32_residual_floor(n: usize, b_norm: f32) -> f32 {
+    (n as f32).sqrt() * f32::EPSILON * b_norm
+}
+
+/// The error of a PCG iteration that found p^T A p <= 0 (A not SPD).
+pub(crate) fn negative_curvature_error(solver: &str, iteration: usize, p_ap: f32) -> String {
+    format!(
+        "{solver}: negative curvature at iteration {iteration}: dot(p,Ap) = {p_ap:e} <= 0, \
+         the matrix is not symmetric positive definite"
+    )
+}
+
+/// Whether the stopping target max(abs_tol, rel_tol * ||b||) lies below
+/// [`f32_residual_floor`], i.e. the requested tolerance is likely unreachable in f32.
+pub fn tolerance_below_precision_floor(n: usize, b_norm: f32, rel_tol: f32, abs_tol: f32) -> bool {
+    let target = abs_tol.max(rel_tol * b_norm);
+    target < f32_residual_floor(n, b_norm)
+}
+
+/// Workgroup sizes the GPU kernels of a solve ran with.
+///
+/// The dot sizes come from the pipelines (default 256 or the creation override);
+/// SpMV / vec ops are fixed at 256 and the per-block kernels use 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkgroupSizes {
+    pub dot_partials: u32,
+    pub dot_reduce: u32,
+    pub spmv: u32,
+    pub vec_ops: u32,
+    pub block_jacobi: u32,
+    pub pcg_update_scalars: u32,
+}
+
+impl WorkgroupSizes {
+    /// Sizes of a block-Jacobi PCG solve using `dot_scalar_exec` for its dots.
+    pub(crate) fn for_pcg(dot_scalar_exec: &DotScalarExecutor) -> Self {
+        Self {
+            dot_partials: dot_scalar_exec.dot_partials_workgroup_size(),
+            dot_reduce: dot_scalar_exec.dot_reduce_workgroup_size(),
+            spmv: 256,
+            vec_ops: 256,
+            block_jacobi: 1,
+            pcg_update_scalars: 1,
+        }
+    }
+}
+
+/// One PCG iteration, as recorded with [`PcgOptions::trace_iterations`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IterationTrace {
+    /// 1-based, like [`PcgResult::iterations`].
+    pub iteration: usize,
+    /// ||r|| after the iteration's update.
+    pub residual_norm: f32,
+    /// Step length used for x and r.
+    pub alpha: f32,
+    /// Coefficient of the next search direction; None on the final iteration, which
+    /// builds no next direction.
+    pub beta: Option<f32>,
+    /// Host time from the start of the solve until the iteration's scalars were read
+    /// back, in ms.
+    pub elapsed_ms: f64,
+}
+
+/// Outcome of a converged PCG solve.
+#[derive(Debug, Clone)]
+pub struct PcgResult {
+    pub iterations: usize,
+    /// ||r|| at exit, from the last scalar readback (0 for a zero rhs).
+    pub residual_norm: f32,
+    /// Compute dispatches recorded over the whole solve (setup + iterations).
+    pub dispatches: u64,
+    /// Workgroup sizes in effect (None for the CPU reference path).
+    pub workgroup_sizes: Option<WorkgroupSizes>,
+    /// The requested tolerance was below the f32 precision floor (see
+    /// [`tolerance_below_precision_floor`]). The recursively updated residual can still
+    /// get there, but the true residual b - A x is unlikely to be that small.
+    pub tolerance_below_precision_floor: bool,
+    /// Present when `PcgOptions::timing` was requested and the device supports it.
+    pub timings: Option<PcgTimings>,
+    /// ||r|| after each iteration (from the per-iteration scalar readback).
+    pub residual_history: Vec<f32>,
+    /// ||x - x_exact||_A after each iteration; present when `PcgOptions::exact_solution` was set.
+    pub error_a_norm_history: Option<Vec<f32>>,
+    /// Most submissions that were unfinished at once (0 for the CPU reference path).
+    pub max_in_flight_submissions: usize,
+    /// Extreme eigenvalue estimates (lambda_min, lambda_max) of M^{-1} A and their ratio,
+    /// when `PcgOptions::estimate_condition_number` was set and at least one iteration ran.
+    pub estimated_eigenvalue_range: Option<(f64, f64)>,
+    pub estimated_condition_number: Option<f64>,
+    /// Extreme Ritz values (lambda_min, lambda_max) after each iteration; present when
+    /// `PcgOptions::ritz_value_history` was set.
+    pub ritz_value_history: Option<Vec<(f64, f64)>>,
+    /// The custom metric after each iteration; present when
+    /// `PcgOptions::custom_stopping_metric` was set.
+    pub custom_metric_history: Option<Vec<f32>>,
+    /// Iteration whose command buffer got the `PcgOptions::capture_at_iteration` marker
+    /// (None if the solve stopped before it, and always on the CPU reference path).
+    pub capture_marker_iteration: Option<usize>,
+    /// Per-iteration trace; present when `PcgOptions::trace_iterations` was set.
+    pub iteration_trace: Option<Vec<IterationTrace>>,
+}
+
 /// Native wgpu port of fea_app's `pcg_block_jacobi_csr_webgpu`.
 ///
 /// IMPORTANT: this is a *core loop* only:
 /// - assumes all inputs are already prepared (CSR arrays, block preconditioner already built)
 /// - executors are created outside and passed in (no hidden allocations)
-/// - 1 submit + 1 scalar readback per iteration (same design)
+/// - the preconditioner is any n x n [`LinearOperator`] z = M^{-1} r: a
+///   `BlockJacobiExecutor` (the name's origin), a `DiagonalJacobiExecutor`, ...
+/// - 1 submit + 1 scalar readback per iteration (same design; per `submit_every`
+///   iterations when batched, see `PcgOptions::submit_every`)
+/// - `observer`, when given, receives ||r_k|| after every iteration's readback (see
+///   [`observer`]); it sees the same values as [`PcgResult::residual_history`]
+///
+/// A must be SPD. An iteration whose direction has p^T A p <= 0 (the pAp the step length
+/// is computed from) fails the solve with "negative curvature at iteration k", instead of
+/// iterating on with a meaningless alpha: an indefinite (or negative definite) matrix is
+/// reported as such rather than as a stall or NaNs. The check is on the host, after the
+/// iteration's readback, so x on the GPU already holds that iteration's update.
+#[allow(clippy::too_many_arguments)]
 pub fn pcg_block_jacobi_csr_wgpu(
     // sizes
     n: usize,
@@ -201,9 +1123,11 @@ pub fn pcg_block_jacobi_csr_wgpu(
     spmv_exec: &SpmvExecutor,
     vec_ops_exec: &VecOpsExecutor,
     dot_scalar_exec: &DotScalarExecutor,
-    block_jacobi_exec: &BlockJacobiExecutor,
+    preconditioner: &dyn LinearOperator,
     pcg_update_scalars_exec: &PcgUpdateScalarsExecutor,
-) -> Result<usize, String> {
+    options: &PcgOptions,
+    mut observer: Option<&mut dyn SolverObserver>,
+) -> Result<PcgResult, String> {
     // -------------------------------------------------------------------------
     // 0) Validate dimensions and precompute common constants
     // -------------------------------------------------------------------------
@@ -215,21 +1139,139 @@ pub fn pcg_block_jacobi_csr_wgpu(
             x.len()
         ));
     }
+    if preconditioner.n_rows() as usize != n || preconditioner.n_cols() as usize != n {
+        return Err(format!(
+            "PCG(BlockJacobiGpu): preconditioner is {}x{}, expected n={}",
+            preconditioner.n_rows(),
+            preconditioner.n_cols(),
+            n
+        ));
+    }
+    if let Some(x_exact) = &options.exact_solution
+        && x_exact.len() != n
+    {
+        return Err(format!(
+            "PCG(BlockJacobiGpu): exact_solution len {} != n={}",
+            x_exact.len(),
+            n
+        ));
+    }
+    if let Some(z0) = &options.initial_preconditioned_residual
+        && z0.len() != n
+    {
+        return Err(format!(
+            "PCG(BlockJacobiGpu): initial_preconditioned_residual len {} != n={}",
+            z0.len(),
+            n
+        ));
+    }
+    if options.reorthogonalize == ReorthPolicy::Window(0) {
+        return Err("PCG(BlockJacobiGpu): reorthogonalize window must be > 0".into());
+    }
+    if (options.custom_stopping_metric.is_some() || options.reorthogonalize != ReorthPolicy::None)
+        && dot_scalar_exec.scalar_results_len() < PCG_SCALAR_RESULTS_LEN
+    {
+        return Err(format!(
+            "PCG(BlockJacobiGpu): custom_stopping_metric and reorthogonalize need {} scalar slots, executor has {}",
+            PCG_SCALAR_RESULTS_LEN,
+            dot_scalar_exec.scalar_results_len()
+        ));
+    }
+    if options.capture_at_iteration == Some(0) {
+        return Err("PCG(BlockJacobiGpu): capture_at_iteration counts from 1".into());
+    }
+    if options.residual_strategy == ResidualStrategy::TrueEvery(0) {
+        return Err("PCG(BlockJacobiGpu): residual_strategy interval must be > 0".into());
+    }
+    options
+        .stopping_criterion
+        .validate()
+        .map_err(|e| format!("PCG(BlockJacobiGpu): {e}"))?;
+    if options.max_in_flight_submissions == 0 {
+        return Err("PCG(BlockJacobiGpu): max_in_flight_submissions must be >= 1".into());
+    }
+    if options.submit_every == 0 {
+        return Err("PCG(BlockJacobiGpu): submit_every must be >= 1".into());
+    }
+    if options.submit_every > 1
+        && (options.timing
+            || options.snapshot_interval.is_some()
+            || options.exact_solution.is_some()
+            || options.reorthogonalize != ReorthPolicy::None
+            || matches!(options.residual_strategy, ResidualStrategy::TrueEvery(_)))
+    {
+        return Err(
+            "PCG(BlockJacobiGpu): submit_every > 1 cannot be combined with timing, snapshots, \
+             exact_solution, reorthogonalize or ResidualStrategy::TrueEvery"
+                .into(),
+        );
+    }
+    let mut window = SubmissionWindow::new(options.max_in_flight_submissions);
 
     let zero: f32 = 0.0;
     let n_u32: u32 = n as u32;
     let n_bytes: u64 = (n * 4) as u64;
 
+    let solve_start = Instant::now();
+
+    // A failed readback almost always means the device is gone; say so explicitly.
+    let readback_err = |e: String| {
+        if ctx.is_lost() {
+            format!("PCG(BlockJacobiGpu): device lost: {e}")
+        } else {
+            format!("PCG(BlockJacobiGpu): readback failed: {e}")
+        }
+    };
+
+    // Last x read back mid-solve (the snapshots). On device loss it is written to `x`
+    // so a retry can restart from it; without one, `x` still holds x0.
+    let mut checkpoint: Option<Vec<f32>> = None;
+
+    if let Some(interval) = options.snapshot_interval {
+        if interval == 0 {
+            return Err("PCG(BlockJacobiGpu): snapshot_interval must be > 0".into());
+        }
+        std::fs::create_dir_all(&options.snapshot_dir).map_err(|e| {
+            format!(
+                "PCG(BlockJacobiGpu): create snapshot dir {}: {e}",
+                options.snapshot_dir.display()
+            )
+        })?;
+    }
+
+    // Timestamps written per iteration (section boundaries, see step comments below).
+    const TIMESTAMPS_PER_ITERATION: u32 = 8;
+    let mut timer = if options.timing {
+        GpuTimer::create(ctx, TIMESTAMPS_PER_ITERATION)
+    } else {
+        None
+    };
+    if let (Some(timer), Some(period)) = (timer.as_mut(), options.timestamp_period_ns) {
+        timer
+            .set_timestamp_period(period)
+            .map_err(|e| format!("PCG(BlockJacobiGpu): {e}"))?;
+    }
+    let mut timings = options.timing.then(|| PcgTimings {
+        timestamp_period_ns: timer.as_ref().map_or(0.0, GpuTimer::timestamp_period),
+        approximate: timer.is_none(),
+        ..Default::default()
+    });
+    let mark = |encoder: &mut CommandEncoder| {
+        if let Some(timer) = &timer {
+            timer.write_timestamp(encoder);
+        }
+    };
+
     // -------------------------------------------------------------------------
     // 1) Compute ||b||^2 once (GPU), same as fea_app
     // -------------------------------------------------------------------------
-    let b_gpu = ctx.create_storage_buffer("pcg b", b, wgpu::BufferUsages::COPY_SRC);
+    let b_gpu = ctx.acquire_storage_buffer("pcg b", b);
 
     let b_norm2: f32 = {
         let mut encoder = ctx
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("pcg b_norm2 encoder"),
+                label: Some(&ctx.label("pcg b_norm2 encoder")),
             });
 
         dot_scalar_exec.encode_dot_scalar_into(
@@ -242,31 +1284,94 @@ pub fn pcg_block_jacobi_csr_wgpu(
         );
         dot_scalar_exec.encode_copy_scalar_results_to_readback(&mut encoder);
 
-        ctx.queue.submit(Some(encoder.finish()));
+        window.submit(ctx, encoder.finish())?;
 
-        let scalar_results = executor::block_on(dot_scalar_exec.readback_scalar_results(ctx));
+        let scalar_results = executor::block_on(dot_scalar_exec.try_readback_scalar_results(ctx))
+            .map_err(readback_err)?;
 
         scalar_results[0]
     };
 
+    // Dispatches per dot product of length n (partials + reduce passes).
+    let dot_dispatches = dot_scalar_exec.dispatches_per_dot(n_u32) as u64;
+    let mut dispatches: u64 = dot_dispatches;
+
+    let workgroup_sizes = Some(WorkgroupSizes::for_pcg(dot_scalar_exec));
+
     if b_norm2 == zero {
-        return Ok(0);
+        return Ok(PcgResult {
+            iterations: 0,
+            residual_norm: 0.0,
+            dispatches,
+            workgroup_sizes,
+            tolerance_below_precision_floor: false,
+            timings: timings.map(|mut t| {
+                t.setup_ms = elapsed_ms(solve_start);
+                t.total_ms = t.setup_ms;
+                t
+            }),
+            residual_history: Vec::new(),
+            error_a_norm_history: options.exact_solution.as_ref().map(|_| Vec::new()),
+            max_in_flight_submissions: window.max_observed(),
+            estimated_eigenvalue_range: None,
+            estimated_condition_number: None,
+            ritz_value_history: options.ritz_value_history.then(Vec::new),
+            custom_metric_history: options.custom_stopping_metric.as_ref().map(|_| Vec::new()),
+            capture_marker_iteration: None,
+            iteration_trace: options.trace_iterations.then(Vec::new),
+        });
     }
 
-    let rel_tol2: f32 = rel_tol * rel_tol;
-    let abs_tol2: f32 = abs_tol * abs_tol;
-
     // -------------------------------------------------------------------------
     // 2) Upload initial vectors to GPU
     // -------------------------------------------------------------------------
-    let x_gpu = ctx.create_storage_buffer("pcg x", x, BufferUsages::COPY_SRC);
-    let r_gpu = ctx.create_storage_buffer_uninit::<f32>("pcg r", n, BufferUsages::COPY_SRC);
-    let p_gpu = ctx.create_storage_buffer_uninit::<f32>("pcg p", n, BufferUsages::COPY_SRC);
-    let z_gpu = ctx.create_storage_buffer_uninit::<f32>("pcg z", n, BufferUsages::COPY_SRC);
+    // From the context's buffer pool when one is installed (see `GpuContext::set_buffer_pool`).
+    let x_gpu = ctx.acquire_storage_buffer("pcg x", x);
+    let r_gpu = ctx.acquire_storage_buffer_uninit::<f32>("pcg r", n);
+    let p_gpu = ctx.acquire_storage_buffer_uninit::<f32>("pcg p", n);
+    let z_gpu = match &options.initial_preconditioned_residual {
+        Some(z0) => ctx.acquire_storage_buffer("pcg z", z0),
+        None => ctx.acquire_storage_buffer_uninit::<f32>("pcg z", n),
+    };
+    // The preconditioner's cached bind group over r / z goes with the vectors.
+    let _release = ReleaseBuffersOnDrop(preconditioner);
+
+    // Optional: exact solution + error buffer for ||x - x_exact||_A
+    let exact_gpu = options.exact_solution.as_ref().map(|x_exact| {
+        (
+            ctx.acquire_storage_buffer("pcg x_exact", x_exact),
+            ctx.acquire_storage_buffer_uninit::<f32>("pcg error", n),
+        )
+    });
+    let mut residual_history: Vec<f32> = Vec::new();
+    let mut lanczos = (options.estimate_condition_number || options.ritz_value_history)
+        .then(|| LanczosTridiagonal::new(options.ritz_value_history));
+    let mut error_a_norm_history: Option<Vec<f32>> = exact_gpu.as_ref().map(|_| Vec::new());
+    let mut iteration_trace: Option<Vec<IterationTrace>> = options.trace_iterations.then(Vec::new);
+
+    // Optional: custom stopping metric, bound to x (its state starts as x0)
+    let custom_metric = options
+        .custom_stopping_metric
+        .as_ref()
+        .map(|metric| {
+            CustomMetricExecutor::create(ctx, &metric.kernel, metric.reduction, &x_gpu.buffer, x)
+                .map(|exec| (exec, metric.threshold))
+        })
+        .transpose()
+        .map_err(|e| format!("PCG(BlockJacobiGpu): {e}"))?;
+    let mut custom_metric_history: Option<Vec<f32>> = custom_metric.as_ref().map(|_| Vec::new());
+    let mut capture_marker_iteration: Option<usize> = None;
+
+    // Optional: window of scaled past directions for reorthogonalization
+    let mut directions = match options.reorthogonalize.window_len() {
+        0 => None,
+        k => Some(DirectionWindow::create(ctx, n, k)),
+    };
 
     // -------------------------------------------------------------------------
     // 3) Scalar slot layout (local, identical concept to fea_app)
-    // DotScalarExecutor must have scalar_results_len >= 7.
+    // DotScalarExecutor must have scalar_results_len >= 7 (9 with a custom metric or
+    // reorthogonalization).
     // -------------------------------------------------------------------------
     let scalar_results_index_for_p_ap: u32 = 0; // p^T (A p)
     let scalar_results_index_for_r_norm2: u32 = 1; // r^T r
@@ -275,19 +1380,25 @@ pub fn pcg_block_jacobi_csr_wgpu(
     let scalar_results_index_for_alpha: u32 = 4; // alpha
     let scalar_results_index_for_minus_alpha: u32 = 5; // -alpha
     let scalar_results_index_for_beta: u32 = 6; // beta
+    let scalar_results_index_for_custom_metric: u32 = 7; // custom stopping metric
+    let scalar_results_index_for_reorth: u32 = 8; // reorthogonalization coefficient
 
     // -------------------------------------------------------------------------
     // 4) Initialize r0 = b - A*x0, z0 = M^-1 r0, p0 = z0, rz_old
     // We do this init on GPU (native core should be self-contained).
     // -------------------------------------------------------------------------
-    let mut rz_old: f32 = {
+    let reduction_target = matches!(
+        options.stopping_criterion,
+        StoppingCriterion::ReductionFactor(_)
+    );
+    let (mut rz_old, r0_norm2): (f32, f32) = {
         vec_ops_exec.reset_params_cursor();
         pcg_update_scalars_exec.reset_params_cursor();
 
         let mut encoder = ctx
             .device
             .create_command_encoder(&CommandEncoderDescriptor {
-                label: Some("pcg init encoder"),
+                label: Some(&ctx.label("pcg init encoder")),
             });
 
         // r <- b
@@ -307,8 +1418,10 @@ pub fn pcg_block_jacobi_csr_wgpu(
             -1.0,
         );
 
-        // z = M^-1 r
-        block_jacobi_exec.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+        // z = M^-1 r (unless the caller supplied it)
+        if options.initial_preconditioned_residual.is_none() {
+            preconditioner.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+        }
 
         // p = z
         encoder.copy_buffer_to_buffer(&z_gpu.buffer, 0, &p_gpu.buffer, 0, n_bytes);
@@ -323,31 +1436,153 @@ pub fn pcg_block_jacobi_csr_wgpu(
             scalar_results_index_for_rz_old,
         );
 
-        dot_scalar_exec.encode_copy_scalar_results_to_readback(&mut encoder);
-        ctx.queue.submit(Some(encoder.finish()));
+        // ||r0||^2 for a reduction-factor target (slot [r_norm2] is rewritten in F)
+        if reduction_target {
+            dot_scalar_exec.encode_dot_scalar_into(
+                ctx,
+                &mut encoder,
+                &r_gpu.buffer,
+                &r_gpu.buffer,
+                n_u32,
+                scalar_results_index_for_r_norm2,
+            );
+        }
 
-        let scalar_results = executor::block_on(dot_scalar_exec.readback_scalar_results(ctx));
-        scalar_results[scalar_results_index_for_rz_old as usize]
+        dot_scalar_exec.encode_copy_scalar_results_to_readback(&mut encoder);
+        window.submit(ctx, encoder.finish())?;
+
+        let scalar_results = executor::block_on(dot_scalar_exec.try_readback_scalar_results(ctx))
+            .map_err(readback_err)?;
+        (
+            scalar_results[scalar_results_index_for_rz_old as usize],
+            if reduction_target {
+                scalar_results[scalar_results_index_for_r_norm2 as usize]
+            } else {
+                zero
+            },
+        )
     };
 
+    // spmv + axpy + preconditioner + dot(r,z) (+ dot(r,r))
+    let preconditioner_dispatches = preconditioner.dispatches();
+    dispatches += 2 + dot_dispatches;
+    if options.initial_preconditioned_residual.is_none() {
+        dispatches += preconditioner_dispatches;
+    }
+    if reduction_target {
+        dispatches += dot_dispatches;
+    }
+
+    let below_floor = tolerance_below_precision_floor(
+        n,
+        b_norm2.sqrt(),
+        options
+            .stopping_criterion
+            .equivalent_rel_tol(b_norm2, r0_norm2, rel_tol),
+        abs_tol,
+    );
+
+    // spmv, 2x update_scalars, 2x axpy, xpby, preconditioner + 3 dots
+    // (+ kernel and reduce of the custom metric, + storing a direction and dot(p, r)
+    // with reorthogonalization)
+    let dispatches_per_iteration: u64 = 6
+        + preconditioner_dispatches
+        + 3 * dot_dispatches
+        + custom_metric
+            .as_ref()
+            .map_or(0, |(exec, _)| exec.dispatches())
+        + directions.as_ref().map_or(0, |_| 3 + dot_dispatches);
+    // reorthogonalization: dot + axpy per stored direction
+    let dispatches_per_reorth_direction: u64 = dot_dispatches + 1;
+    // error tracking: axpy + spmv + dot
+    let dispatches_per_error_norm: u64 = 2 + dot_dispatches;
+    // true residual: spmv + axpy
+    let dispatches_per_true_residual: u64 = 2;
+
+    if let Some(t) = timings.as_mut() {
+        t.setup_ms = elapsed_ms(solve_start);
+        t.kernel_launches = pcg_kernel_launches(
+            n_u32,
+            spmv_exec,
+            preconditioner,
+            dot_scalar_exec,
+            WorkgroupSizes::for_pcg(dot_scalar_exec),
+        );
+        t.compute_units = options.compute_units.or_else(|| compute_unit_hint(ctx));
+    }
+
+    // Batched submits (submit_every > 1): the encoder still being recorded, the iteration
+    // it started at, the staging buffer that collects every iteration's scalars, and a
+    // 4-byte stash for carrying rz_new over to the next iteration's rz_old on the GPU.
+    let submit_every = options.submit_every as usize;
+    let scalar_results_len = dot_scalar_exec.scalar_results_len();
+    let scalar_results_bytes = (scalar_results_len * 4) as u64;
+    let mut batch_encoder: Option<CommandEncoder> = None;
+    let mut batch_start = 0usize;
+    let batch_readback = (submit_every > 1).then(|| {
+        ctx.create_buffer(&BufferDescriptor {
+            label: Some(&ctx.label("pcg batch scalar readback")),
+            size: submit_every.min(max_iter).max(1) as u64 * scalar_results_bytes,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    });
+    let rz_stash = (submit_every > 1).then(|| {
+        ctx.create_buffer(&BufferDescriptor {
+            label: Some(&ctx.label("pcg rz stash")),
+            size: 4,
+            usage: BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    });
+
     // -------------------------------------------------------------------------
-    // 5) Main PCG loop (single submit + scalar readback per iteration)
+    // 5) Main PCG loop (one submit + scalar readback per iteration, or per
+    //    submit_every iterations)
     // -------------------------------------------------------------------------
     for k in 0..max_iter {
         let iterations = k + 1;
+        let encode_start = Instant::now();
+
+        // Creating buffers on a lost device panics in wgpu: stop before encoding.
+        if ctx.is_lost() {
+            return Err(restore_checkpoint(
+                x,
+                checkpoint.as_deref(),
+                "PCG(BlockJacobiGpu): device lost".into(),
+            ));
+        }
 
         vec_ops_exec.reset_params_cursor();
         pcg_update_scalars_exec.reset_params_cursor();
+        if let Some(timer) = &timer {
+            timer.reset();
+        }
 
-        let mut encoder = ctx
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor {
-                label: Some("pcg single-submit iteration encoder"),
-            });
+        let first_in_batch = batch_encoder.is_none();
+        if first_in_batch {
+            batch_start = k;
+        }
+        let mut encoder = batch_encoder.take().unwrap_or_else(|| {
+            ctx.device
+                .create_command_encoder(&CommandEncoderDescriptor {
+                    label: Some(&ctx.label("pcg single-submit iteration encoder")),
+                })
+        });
+
+        // Debugger capture marker (before any real work of this iteration)
+        if options.capture_at_iteration.map(|i| i as usize) == Some(iterations) {
+            encode_capture_marker(ctx, &mut encoder, iterations);
+            capture_marker_iteration = Some(iterations);
+        }
+
+        // Timestamps (when timing): t0 | A | t1 | B | t2 | C-E | t3 | F | t4 | G | t5 | H | t6 | I-J | t7
+        mark(&mut encoder);
 
         // A) Ap = A * p
         spmv_exec.encode_copy_x_from(&mut encoder, &p_gpu.buffer, n_bytes);
         spmv_exec.encode_spmv(&mut encoder);
+        mark(&mut encoder);
 
         // B) pAp = dot(p, Ap)
         dot_scalar_exec.encode_dot_scalar_into(
@@ -358,16 +1593,50 @@ pub fn pcg_block_jacobi_csr_wgpu(
             n_u32,
             scalar_results_index_for_p_ap,
         );
-
-        // C) Write rz_old into scalar_results[rz_old]
-        encode_write_f32_into_storage_buffer_at_index(
-            &ctx.device,
-            &mut encoder,
-            dot_scalar_exec.scalar_results_buffer(),
-            scalar_results_index_for_rz_old,
-            rz_old,
-            "pcg rz_old staging",
-        );
+        mark(&mut encoder);
+
+        // C) Write rz_old into scalar_results[rz_old]. With reorthogonalization p is no
+        //    longer z + beta*p_old, so alpha needs p^T r (= rz_old in exact arithmetic):
+        //    that goes into the slot for D), and rz_old is written back before I).
+        if directions.is_some() {
+            dot_scalar_exec.encode_dot_scalar_into(
+                ctx,
+                &mut encoder,
+                &p_gpu.buffer,
+                &r_gpu.buffer,
+                n_u32,
+                scalar_results_index_for_rz_old,
+            );
+        } else if first_in_batch {
+            encode_write_f32_into_storage_buffer_at_index(
+                ctx,
+                &mut encoder,
+                dot_scalar_exec.scalar_results_buffer(),
+                scalar_results_index_for_rz_old,
+                rz_old,
+                &ctx.label("pcg rz_old staging"),
+            );
+        } else {
+            // Later iteration of a batch: the host has not seen the last rz_new yet, but it
+            // is still in its slot (rewritten only in H). Through the stash, since a copy
+            // cannot have the same buffer on both ends.
+            let stash = rz_stash.as_ref().expect("batched submits have a stash");
+            let scalar_results_buffer = dot_scalar_exec.scalar_results_buffer();
+            encoder.copy_buffer_to_buffer(
+                scalar_results_buffer,
+                scalar_results_index_for_rz_new as u64 * 4,
+                stash,
+                0,
+                4,
+            );
+            encoder.copy_buffer_to_buffer(
+                stash,
+                0,
+                scalar_results_buffer,
+                scalar_results_index_for_rz_old as u64 * 4,
+                4,
+            );
+        }
 
         // D) compute alpha / -alpha (early)
         pcg_update_scalars_exec.encode_update_scalars(
@@ -402,6 +1671,46 @@ pub fn pcg_block_jacobi_csr_wgpu(
             scalar_results_index_for_minus_alpha,
         );
 
+        // E'') store (p, Ap) scaled by 1/sqrt(pAp) for reorthogonalization
+        if let Some(directions) = directions.as_mut() {
+            directions.encode_push(
+                ctx,
+                &mut encoder,
+                vec_ops_exec,
+                &p_gpu.buffer,
+                spmv_exec.y_buffer(),
+                dot_scalar_exec.scalar_results_buffer(),
+                scalar_results_index_for_p_ap,
+            );
+        }
+
+        // E') custom metric on the updated x
+        if let Some((exec, _)) = &custom_metric {
+            exec.encode_into(
+                &mut encoder,
+                dot_scalar_exec.scalar_results_buffer(),
+                scalar_results_index_for_custom_metric,
+            );
+        }
+
+        // E''') r = b - A x (replaces the recurrence residual; A p in the SpMV output is
+        //       no longer needed)
+        let recompute_residual = options.residual_strategy.recompute_after(iterations);
+        if recompute_residual {
+            spmv_exec.encode_copy_x_from(&mut encoder, &x_gpu.buffer, n_bytes);
+            spmv_exec.encode_spmv(&mut encoder);
+            encoder.copy_buffer_to_buffer(&b_gpu.buffer, 0, &r_gpu.buffer, 0, n_bytes);
+            vec_ops_exec.encode_axpy_inplace(
+                ctx,
+                &mut encoder,
+                spmv_exec.y_buffer(),
+                &r_gpu.buffer,
+                n_u32,
+                -1.0,
+            );
+        }
+        mark(&mut encoder);
+
         // F) r_norm2 = dot(r,r)
         dot_scalar_exec.encode_dot_scalar_into(
             ctx,
@@ -411,9 +1720,11 @@ pub fn pcg_block_jacobi_csr_wgpu(
             n_u32,
             scalar_results_index_for_r_norm2,
         );
+        mark(&mut encoder);
 
         // G) z = M^-1 r
-        block_jacobi_exec.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+        preconditioner.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+        mark(&mut encoder);
 
         // H) rz_new = dot(r,z)
         dot_scalar_exec.encode_dot_scalar_into(
@@ -424,8 +1735,19 @@ pub fn pcg_block_jacobi_csr_wgpu(
             n_u32,
             scalar_results_index_for_rz_new,
         );
+        mark(&mut encoder);
 
         // I) compute beta (late)
+        if directions.is_some() {
+            encode_write_f32_into_storage_buffer_at_index(
+                ctx,
+                &mut encoder,
+                dot_scalar_exec.scalar_results_buffer(),
+                scalar_results_index_for_rz_old,
+                rz_old,
+                &ctx.label("pcg rz_old staging"),
+            );
+        }
         pcg_update_scalars_exec.encode_update_scalars(
             ctx,
             &mut encoder,
@@ -438,58 +1760,346 @@ pub fn pcg_block_jacobi_csr_wgpu(
             scalar_results_index_for_beta,
         );
 
-        // J) p = z + beta*p
-        vec_ops_exec.encode_scale_inplace_from_scalar_results(
+        // J) p = z + beta*p (one fused xpby dispatch)
+        vec_ops_exec.encode_xpby_inplace_from_scalar_results(
             ctx,
             &mut encoder,
+            &z_gpu.buffer,
             &p_gpu.buffer,
             n_u32,
             dot_scalar_exec.scalar_results_buffer(),
             scalar_results_index_for_beta,
         );
-        vec_ops_exec.encode_axpy_inplace(
-            ctx,
-            &mut encoder,
-            &z_gpu.buffer,
-            &p_gpu.buffer,
-            n_u32,
-            1.0,
-        );
-
-        // K) scalar_results -> readback
-        dot_scalar_exec.encode_copy_scalar_results_to_readback(&mut encoder);
+        mark(&mut encoder);
+
+        // K) scalar_results -> readback (batched: this iteration's slice of the batch buffer)
+        match &batch_readback {
+            None => dot_scalar_exec.encode_copy_scalar_results_to_readback(&mut encoder),
+            Some(batch) => encoder.copy_buffer_to_buffer(
+                dot_scalar_exec.scalar_results_buffer(),
+                0,
+                batch,
+                (k - batch_start) as u64 * scalar_results_bytes,
+                scalar_results_bytes,
+            ),
+        }
+        dispatches += dispatches_per_iteration;
+        if recompute_residual {
+            dispatches += dispatches_per_true_residual;
+        }
 
-        // Submit once
-        ctx.queue.submit(Some(encoder.finish()));
+        // Batched: keep recording until the batch is full or the iterations run out.
+        if iterations - batch_start < submit_every && iterations < max_iter {
+            batch_encoder = Some(encoder);
+            continue;
+        }
 
-        // Read scalars once
-        let scalar_results = executor::block_on(dot_scalar_exec.readback_scalar_results(ctx));
+        if let Some(timer) = &timer {
+            timer.encode_resolve(&mut encoder);
+        }
 
-        let p_ap = scalar_results[scalar_results_index_for_p_ap as usize];
-        let r_norm2 = scalar_results[scalar_results_index_for_r_norm2 as usize];
-        let rz_new = scalar_results[scalar_results_index_for_rz_new as usize];
+        let submit_start = Instant::now();
 
-        // breakdown checks
-        if p_ap == zero {
-            return Err("PCG(BlockJacobiGpu): dot(p,Ap) is zero (breakdown)".into());
+        // Submit once
+        window.submit(ctx, encoder.finish())?;
+
+        // Read scalars once (all of the batch's iterations, in order)
+        let batch_scalars = match &batch_readback {
+            None => executor::block_on(dot_scalar_exec.try_readback_scalar_results(ctx)),
+            Some(batch) => executor::block_on(try_read_mapped_buffer_to_vec::<f32>(
+                &ctx.device,
+                batch,
+                (iterations - batch_start) * scalar_results_len,
+            )),
         }
-        if rz_old == zero {
-            return Err("PCG(BlockJacobiGpu): rz_old is zero (breakdown)".into());
+        .map_err(|e| restore_checkpoint(x, checkpoint.as_deref(), readback_err(e)))?;
+
+        if let (Some(timer), Some(t)) = (&timer, timings.as_mut()) {
+            let ticks = executor::block_on(timer.read_ticks(ctx));
+            let section = |i: usize| timer.ticks_to_ms(ticks[i], ticks[i + 1]);
+
+            t.spmv_ms += section(0);
+            t.dots_ms += section(1) + section(3) + section(5);
+            t.vec_ops_ms += section(2) + section(6);
+            t.preconditioner_ms += section(4);
+
+            let gpu_compute_ms = timer.ticks_to_ms(ticks[0], ticks[7]);
+            t.host_encode_ms += (submit_start - encode_start).as_secs_f64() * 1e3;
+            t.readback_ms += (elapsed_ms(submit_start) - gpu_compute_ms).max(0.0);
+            t.iteration_ms.push(elapsed_ms(encode_start));
+        } else if let Some(t) = timings.as_mut() {
+            t.host_encode_ms += (submit_start - encode_start).as_secs_f64() * 1e3;
+            t.readback_ms += elapsed_ms(submit_start);
+            t.iteration_ms.push(elapsed_ms(encode_start));
         }
 
-        // stopping condition
-        if r_norm2 <= abs_tol2 || r_norm2 <= rel_tol2 * b_norm2 {
-            let x_out = executor::block_on(ctx.readback(&x_gpu));
-            x.copy_from_slice(&x_out);
-            return Ok(iterations);
-        }
+        // Host side of each iteration of the batch. Once one of them has met the
+        // tolerance the rest still ran on the GPU: record them, skip the breakdown checks
+        // (converged r may give 0 / 0, which the scalar kernel maps to alpha = beta = 0)
+        // and return at the end of the batch: the x on the GPU is that iteration's.
+        let batch_end = iterations;
+        let mut stopped = false;
+        for (offset, scalar_results) in batch_scalars
+            .chunks(scalar_results_len)
+            .take(batch_end - batch_start)
+            .enumerate()
+        {
+            let iterations = batch_start + offset + 1;
+
+            let p_ap = scalar_results[scalar_results_index_for_p_ap as usize];
+            let r_norm2 = scalar_results[scalar_results_index_for_r_norm2 as usize];
+            let rz_new = scalar_results[scalar_results_index_for_rz_new as usize];
+
+            // breakdown checks
+            if !stopped && p_ap <= zero {
+                return Err(negative_curvature_error(
+                    "PCG(BlockJacobiGpu)",
+                    iterations,
+                    p_ap,
+                ));
+            }
+            if !stopped && rz_old == zero {
+                return Err("PCG(BlockJacobiGpu): rz_old is zero (breakdown)".into());
+            }
+
+            residual_history.push(r_norm2.sqrt());
+            if let Some(observer) = observer.as_deref_mut() {
+                observer.on_iteration(iterations as u32, r_norm2.sqrt());
+            }
+            if let Some(lanczos) = lanczos.as_mut() {
+                lanczos.push(
+                    scalar_results[scalar_results_index_for_alpha as usize],
+                    scalar_results[scalar_results_index_for_beta as usize],
+                );
+            }
+
+            let custom_metric_converged = match (&custom_metric, custom_metric_history.as_mut()) {
+                (Some((_, threshold)), Some(history)) => {
+                    let value = scalar_results[scalar_results_index_for_custom_metric as usize];
+                    history.push(value);
+                    value <= *threshold
+                }
+                _ => false,
+            };
+
+            // optional ||x - x_exact||_A (second submit + readback). Slot p_ap is free here:
+            // it was read above and is rewritten in B) before its next use.
+            if let (Some((exact_gpu, error_gpu)), Some(history)) =
+                (&exact_gpu, error_a_norm_history.as_mut())
+            {
+                let error_start = Instant::now();
+                vec_ops_exec.reset_params_cursor();
+
+                let mut encoder = ctx
+                    .device
+                    .create_command_encoder(&CommandEncoderDescriptor {
+                        label: Some(&ctx.label("pcg error A-norm encoder")),
+                    });
+
+                // e = x - x_exact
+                encoder.copy_buffer_to_buffer(&x_gpu.buffer, 0, &error_gpu.buffer, 0, n_bytes);
+                vec_ops_exec.encode_axpy_inplace(
+                    ctx,
+                    &mut encoder,
+                    &exact_gpu.buffer,
+                    &error_gpu.buffer,
+                    n_u32,
+                    -1.0,
+                );
+
+                // e^T (A e)
+                spmv_exec.encode_copy_x_from(&mut encoder, &error_gpu.buffer, n_bytes);
+                spmv_exec.encode_spmv(&mut encoder);
+                dot_scalar_exec.encode_dot_scalar_into(
+                    ctx,
+                    &mut encoder,
+                    &error_gpu.buffer,
+                    spmv_exec.y_buffer(),
+                    n_u32,
+                    scalar_results_index_for_p_ap,
+                );
+                dot_scalar_exec.encode_copy_scalar_results_to_readback(&mut encoder);
+
+                window.submit(ctx, encoder.finish())?;
+                dispatches += dispatches_per_error_norm;
+
+                let error_results = executor::block_on(
+                    dot_scalar_exec.try_readback_scalar_results(ctx),
+                )
+                .map_err(|e| restore_checkpoint(x, checkpoint.as_deref(), readback_err(e)))?;
+                history.push(
+                    error_results[scalar_results_index_for_p_ap as usize]
+                        .max(0.0)
+                        .sqrt(),
+                );
+
+                if let Some(t) = timings.as_mut() {
+                    t.readback_ms += elapsed_ms(error_start);
+                }
+            }
+
+            // stopping condition
+            let converged = options
+                .stopping_criterion
+                .is_met(r_norm2, b_norm2, r0_norm2, rel_tol, abs_tol)
+                || custom_metric_converged;
+
+            if let Some(trace) = iteration_trace.as_mut() {
+                trace.push(IterationTrace {
+                    iteration: iterations,
+                    residual_norm: r_norm2.sqrt(),
+                    alpha: scalar_results[scalar_results_index_for_alpha as usize],
+                    beta: (!converged)
+                        .then(|| scalar_results[scalar_results_index_for_beta as usize]),
+                    elapsed_ms: elapsed_ms(solve_start),
+                });
+            }
+
+            stopped |= converged;
+            if stopped && iterations == batch_end {
+                let readback_start = Instant::now();
+                let x_out = executor::block_on(ctx.try_readback(&x_gpu))
+                    .map_err(|e| restore_checkpoint(x, checkpoint.as_deref(), readback_err(e)))?;
+                x.copy_from_slice(&x_out);
+
+                if options.snapshot_interval.is_some() {
+                    write_snapshot(&options.snapshot_dir, iterations, x)?;
+                }
+
+                if let Some(t) = timings.as_mut() {
+                    t.readback_ms += elapsed_ms(readback_start);
+                    t.total_ms = elapsed_ms(solve_start);
+                }
+
+                // T may exist for the Ritz history alone: report the estimate only if asked.
+                let condition_lanczos = lanczos
+                    .as_ref()
+                    .filter(|_| options.estimate_condition_number);
+                return Ok(PcgResult {
+                    iterations,
+                    residual_norm: r_norm2.sqrt(),
+                    dispatches,
+                    workgroup_sizes,
+                    tolerance_below_precision_floor: below_floor,
+                    timings,
+                    residual_history,
+                    error_a_norm_history,
+                    max_in_flight_submissions: window.max_observed(),
+                    estimated_eigenvalue_range: condition_lanczos
+                        .and_then(LanczosTridiagonal::extreme_eigenvalues),
+                    estimated_condition_number: condition_lanczos
+                        .and_then(LanczosTridiagonal::condition_number),
+                    ritz_value_history: lanczos
+                        .as_ref()
+                        .and_then(|l| l.ritz_history().map(<[_]>::to_vec)),
+                    custom_metric_history,
+                    capture_marker_iteration,
+                    iteration_trace,
+                });
+            }
+
+            // optional snapshot of x (extra full readback)
+            if let Some(interval) = options.snapshot_interval
+                && iterations.is_multiple_of(interval as usize)
+            {
+                let snapshot_start = Instant::now();
+                let x_snapshot = executor::block_on(ctx.try_readback(&x_gpu))
+                    .map_err(|e| restore_checkpoint(x, checkpoint.as_deref(), readback_err(e)))?;
+                write_snapshot(&options.snapshot_dir, iterations, &x_snapshot)?;
+                checkpoint = Some(x_snapshot);
+
+                if let Some(t) = timings.as_mut() {
+                    t.readback_ms += elapsed_ms(snapshot_start);
+                }
+            }
+
+            // optional: project the stored directions out of the new p (extra submits,
+            // REORTH_DIRECTIONS_PER_SUBMIT at a time so the uniform pools never wrap)
+            if let Some(directions) = &directions {
+                let reorth_start = Instant::now();
+                for chunk_start in (0..directions.len()).step_by(REORTH_DIRECTIONS_PER_SUBMIT) {
+                    vec_ops_exec.reset_params_cursor();
+                    dot_scalar_exec.reset_params_cursor();
+
+                    let mut encoder =
+                        ctx.device
+                            .create_command_encoder(&CommandEncoderDescriptor {
+                                label: Some(&ctx.label("pcg reorthogonalization encoder")),
+                            });
+                    directions.encode_project_out(
+                        ctx,
+                        &mut encoder,
+                        vec_ops_exec,
+                        dot_scalar_exec,
+                        &p_gpu.buffer,
+                        scalar_results_index_for_reorth,
+                        chunk_start,
+                        REORTH_DIRECTIONS_PER_SUBMIT,
+                    );
+                    window.submit(ctx, encoder.finish())?;
+                }
+                dispatches += directions.len() as u64 * dispatches_per_reorth_direction;
+
+                // The GPU time lands in the next iteration's readback bucket.
+                if let Some(t) = timings.as_mut() {
+                    t.host_encode_ms += elapsed_ms(reorth_start);
+                }
+            }
 
-        // update rz_old (CPU) for next iteration
-        rz_old = rz_new;
+            // update rz_old (CPU) for next iteration
+            rz_old = rz_new;
+        }
     }
 
     Err(format!(
-        "PCG(BlockJacobiGpu): did not converge in {} iterations",
-        max_iter
+        "PCG(BlockJacobiGpu): did not converge in {} iterations{}",
+        max_iter,
+        precision_floor_hint(below_floor)
     ))
 }
+
+/// Suffix for non-convergence errors when the tolerance was flagged as unreachable.
+pub(crate) fn precision_floor_hint(below_floor: bool) -> &'static str {
+    if below_floor {
+        " (requested tolerance is below the f32 precision floor)"
+    } else {
+        ""
+    }
+}
+
+/// Hand the last checkpoint (if any) back through `x` before failing, so a caller can
+/// restart from it. Returns `err` unchanged.
+fn restore_checkpoint(x: &mut [f32], checkpoint: Option<&[f32]>, err: String) -> String {
+    if let Some(c) = checkpoint {
+        x.copy_from_slice(c);
+    }
+    err
+}
+
+/// Label of the empty pass `PcgOptions::capture_at_iteration` inserts.
+pub fn capture_marker_label(iteration: usize) -> String {
+    format!("pcg capture marker: iteration {iteration}")
+}
+
+/// Debug marker + empty compute pass, both labeled `capture_marker_label(iteration)`
+/// (with the context's label prefix). The pass goes through
+/// [`GpuContext::begin_compute_pass`], so an enabled pass timer records it.
+fn encode_capture_marker(ctx: &GpuContext, encoder: &mut CommandEncoder, iteration: usize) {
+    let name = capture_marker_label(iteration);
+    encoder.insert_debug_marker(&ctx.label(&name));
+    let _pass = ctx.begin_compute_pass(encoder, &name);
+}
+
+fn elapsed_ms(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1e3
+}
+
+/// File name used for the snapshot taken after `iteration` iterations.
+pub fn snapshot_file_name(iteration: usize) -> String {
+    format!("x_iter_{iteration:06}.npy")
+}
+
+fn write_snapshot(dir: &Path, iteration: usize, x: &[f32]) -> Result<(), String> {
+    write_npy_f32(&dir.join(snapshot_file_name(iteration)), x)
+        .map_err(|e| format!("PCG(BlockJacobiGpu): snapshot: {e}"))
+}
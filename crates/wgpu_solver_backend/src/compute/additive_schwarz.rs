@@ -0,0 +1,162 @@
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, ComputePipeline,
+    ComputePipelineDescriptor, Device, PipelineLayoutDescriptor, ShaderModuleDescriptor,
+    ShaderSource, ShaderStages,
+};
+
+use crate::gpu::context::GpuContext;
+
+/// Workgroup size of additive_schwarz_solve.wgsl (one invocation per subdomain).
+pub const ADDITIVE_SCHWARZ_SOLVE_WORKGROUP_SIZE: u32 = 64;
+
+/// Workgroup size of additive_schwarz_combine.wgsl (one invocation per DOF).
+pub const ADDITIVE_SCHWARZ_COMBINE_WORKGROUP_SIZE: u32 = 256;
+
+/// Both passes of the additive Schwarz apply: local solves, then the per-DOF combine.
+pub struct AdditiveSchwarzPipelines {
+    pub solve_pipeline: ComputePipeline,
+    pub solve_bind_group_layout: BindGroupLayout,
+    pub combine_pipeline: ComputePipeline,
+    pub combine_bind_group_layout: BindGroupLayout,
+}
+
+fn create_uniform_entry(binding: u32) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn create_storage_entry(binding: u32, is_read_only: bool) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Storage {
+                read_only: is_read_only,
+            },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn create_pipeline(
+    ctx: &GpuContext,
+    name: &str,
+    source: &'static str,
+    layout: &BindGroupLayout,
+) -> ComputePipeline {
+    let device = &ctx.device;
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(&ctx.label(&format!("{name}.wgsl"))),
+        source: ShaderSource::Wgsl(source.into()),
+    });
+
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&ctx.label(&format!("{name} pipeline layout"))),
+        bind_group_layouts: &[layout],
+        immediate_size: 0,
+    });
+
+    device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(&ctx.label(&format!("{name} pipeline"))),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("compute_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    })
+}
+
+pub fn create_additive_schwarz_pipelines(ctx: &GpuContext) -> AdditiveSchwarzPipelines {
+    let device = &ctx.device;
+
+    // Bind group layout (group 0), matches additive_schwarz_solve.wgsl:
+    //  0: params (uniform)
+    //  1: lu_blocks (RO storage)
+    //  2: dof_ptr (RO storage)
+    //  3: dofs (RO storage)
+    //  4: r (RO storage)
+    //  5: local_z (RW storage)
+    let solve_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some(&ctx.label("additive_schwarz_solve bgl0")),
+        entries: &[
+            create_uniform_entry(0),
+            create_storage_entry(1, true),
+            create_storage_entry(2, true),
+            create_storage_entry(3, true),
+            create_storage_entry(4, true),
+            create_storage_entry(5, false),
+        ],
+    });
+
+    // Bind group layout (group 0), matches additive_schwarz_combine.wgsl:
+    //  0: params (uniform)
+    //  1: occ_ptr (RO storage)
+    //  2: occ (RO storage)
+    //  3: local_z (RO storage)
+    //  4: z (RW storage)
+    let combine_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some(&ctx.label("additive_schwarz_combine bgl0")),
+        entries: &[
+            create_uniform_entry(0),
+            create_storage_entry(1, true),
+            create_storage_entry(2, true),
+            create_storage_entry(3, true),
+            create_storage_entry(4, false),
+        ],
+    });
+
+    let solve_pipeline = create_pipeline(
+        ctx,
+        "additive_schwarz_solve",
+        include_str!("wgsl/additive_schwarz_solve.wgsl"),
+        &solve_bind_group_layout,
+    );
+    let combine_pipeline = create_pipeline(
+        ctx,
+        "additive_schwarz_combine",
+        include_str!("wgsl/additive_schwarz_combine.wgsl"),
+        &combine_bind_group_layout,
+    );
+
+    AdditiveSchwarzPipelines {
+        solve_pipeline,
+        solve_bind_group_layout,
+        combine_pipeline,
+        combine_bind_group_layout,
+    }
+}
+
+/// Bind group for either pass: `buffers` in binding order, starting at binding 0.
+pub fn create_additive_schwarz_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    label: &str,
+    buffers: &[&Buffer],
+) -> BindGroup {
+    let entries: Vec<BindGroupEntry> = buffers
+        .iter()
+        .enumerate()
+        .map(|(i, buffer)| BindGroupEntry {
+            binding: i as u32,
+            resource: buffer.as_entire_binding(),
+        })
+        .collect();
+
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some(label),
+        layout,
+        entries: &entries,
+    })
+}
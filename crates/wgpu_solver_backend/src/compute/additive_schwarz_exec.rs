@@ -0,0 +1,389 @@
+use wgpu::util::BufferInitDescriptor;
+use wgpu::{Buffer, BufferUsages, CommandEncoder, ComputePassDescriptor};
+
+use crate::compute::additive_schwarz::{
+    ADDITIVE_SCHWARZ_COMBINE_WORKGROUP_SIZE, ADDITIVE_SCHWARZ_SOLVE_WORKGROUP_SIZE,
+    AdditiveSchwarzPipelines, create_additive_schwarz_bind_group,
+    create_additive_schwarz_pipelines,
+};
+use crate::compute::lu_factor_inplace;
+use crate::gpu::context::GpuContext;
+use crate::matrix::Csr;
+
+// Additive Schwarz preconditioner: block Jacobi with overlapping blocks.
+//
+//   z = M^{-1} r = sum_b R_b^T A_b^{-1} R_b r,   A_b = A[S_b, S_b]
+//
+// S_b is subdomain b's DOF index set and R_b restricts a vector to it. Unlike block
+// Jacobi the sets may overlap, so a DOF can receive a correction from several local
+// solves; the overlap lets information cross subdomain boundaries within one apply,
+// which is what cuts PCG iterations on PDE problems with local coupling.
+//
+// Overlap handling: the apply is two passes. Pass 1 solves every subdomain into its own
+// slice of a scratch vector (one invocation per subdomain, nothing shared); pass 2
+// gathers, for each DOF, the entries of the subdomains containing it (see
+// `SchwarzCombine`). No float atomics are involved and the sum order is fixed, so the
+// apply is deterministic.
+//
+// The local factors are computed on the host (`lu_factor_inplace`, no pivoting) from A
+// itself; entries of A coupling S_b to DOFs outside it are ignored, as in block Jacobi.
+
+/// Upper bound on a subdomain's DOF count (the kernel's fixed-size per-invocation
+/// temporaries, `MAX_BLOCK_SIZE` in additive_schwarz_solve.wgsl).
+pub const MAX_BLOCK_SIZE: u32 = 32;
+
+/// How pass 2 combines the local solutions at DOFs shared by several subdomains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchwarzCombine {
+    /// z_i = sum of the local solutions at i: classical additive Schwarz. M^{-1} is
+    /// symmetric positive definite for SPD A, so this is the one to use with PCG.
+    #[default]
+    Add,
+    /// z_i = mean of the local solutions at i. Avoids over-correcting overlapped DOFs
+    /// (the added variant scales them by up to their multiplicity), but M^{-1} is no
+    /// longer symmetric in general; use it with GMRES or as a smoother, not with PCG.
+    Average,
+}
+
+impl SchwarzCombine {
+    fn shader_code(self) -> u32 {
+        match self {
+            SchwarzCombine::Add => 0,
+            SchwarzCombine::Average => 1,
+        }
+    }
+}
+
+/// DOF index sets of the subdomains, CSR-like: subdomain `b` is
+/// `dofs[dof_ptr[b]..dof_ptr[b + 1]]`.
+///
+/// Within a set the order is free (it only fixes the local numbering) but an index may
+/// not repeat; across sets indices may repeat, that is the overlap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchwarzSubdomains {
+    pub dof_ptr: Vec<u32>,
+    pub dofs: Vec<u32>,
+}
+
+impl SchwarzSubdomains {
+    /// Contiguous subdomains for 1D-ordered unknowns: [0, n) is cut into cores of
+    /// `core_size` indices (the last one takes the remainder) and each core is extended
+    /// by `overlap` indices on both sides, clipped to [0, n).
+    ///
+    /// `overlap == 0` gives exactly the blocks of block Jacobi with `core_size`.
+    pub fn contiguous(n: usize, core_size: usize, overlap: usize) -> Self {
+        let mut dof_ptr = vec![0u32];
+        let mut dofs = Vec::new();
+        if core_size > 0 {
+            for core_start in (0..n).step_by(core_size) {
+                let start = core_start.saturating_sub(overlap);
+                let end = (core_start + core_size + overlap).min(n);
+                dofs.extend(start as u32..end as u32);
+                dof_ptr.push(dofs.len() as u32);
+            }
+        }
+        Self { dof_ptr, dofs }
+    }
+
+    pub fn num_blocks(&self) -> usize {
+        self.dof_ptr.len().saturating_sub(1)
+    }
+
+    /// DOFs of subdomain `b`.
+    pub fn block(&self, b: usize) -> &[u32] {
+        &self.dofs[self.dof_ptr[b] as usize..self.dof_ptr[b + 1] as usize]
+    }
+
+    /// Structure checks for `n` unknowns: well-formed `dof_ptr`, every set non-empty,
+    /// at most [`MAX_BLOCK_SIZE`] long, in range and free of repeats, and every DOF
+    /// covered by at least one set.
+    pub fn validate(&self, n: usize) -> Result<(), String> {
+        let err = |msg: String| Err(format!("SchwarzSubdomains: {msg}"));
+
+        if self.dof_ptr.first() != Some(&0)
+            || self.dof_ptr.windows(2).any(|w| w[0] > w[1])
+            || self.dof_ptr.last().map(|&e| e as usize) != Some(self.dofs.len())
+        {
+            return err("dof_ptr must start at 0, be non-decreasing and end at dofs.len()".into());
+        }
+
+        let mut covered = vec![false; n];
+        let mut last_seen = vec![usize::MAX; n];
+        for b in 0..self.num_blocks() {
+            let set = self.block(b);
+            if set.is_empty() || set.len() > MAX_BLOCK_SIZE as usize {
+                return err(format!(
+                    "subdomain {b} has {} DOFs, expected 1..={MAX_BLOCK_SIZE}",
+                    set.len()
+                ));
+            }
+            for &dof in set {
+                let dof = dof as usize;
+                if dof >= n {
+                    return err(format!("subdomain {b}: DOF {dof} out of range (n = {n})"));
+                }
+                if last_seen[dof] == b {
+                    return err(format!("subdomain {b}: DOF {dof} listed twice"));
+                }
+                last_seen[dof] = b;
+                covered[dof] = true;
+            }
+        }
+
+        if let Some(dof) = covered.iter().position(|&c| !c) {
+            return err(format!("DOF {dof} is not in any subdomain"));
+        }
+        Ok(())
+    }
+
+    /// Transpose: for each DOF, the positions in `dofs` (= in the local-solution vector)
+    /// where it occurs, in increasing subdomain order. Returns (occ_ptr, occ).
+    fn occurrences(&self, n: usize) -> (Vec<u32>, Vec<u32>) {
+        let mut occ_ptr = vec![0u32; n + 1];
+        for &dof in &self.dofs {
+            occ_ptr[dof as usize + 1] += 1;
+        }
+        for i in 0..n {
+            occ_ptr[i + 1] += occ_ptr[i];
+        }
+
+        let mut next = occ_ptr.clone();
+        let mut occ = vec![0u32; self.dofs.len()];
+        for (pos, &dof) in self.dofs.iter().enumerate() {
+            occ[next[dof as usize] as usize] = pos as u32;
+            next[dof as usize] += 1;
+        }
+        (occ_ptr, occ)
+    }
+}
+
+/// AdditiveSchwarzExecutor
+///
+/// z = sum_b R_b^T A_b^{-1} R_b r on the GPU (see the top of this file). Owns the
+/// immutable GPU resources:
+///   - `lu_buffer`: one bs x bs LU slab per subdomain, bs = the longest subdomain
+///   - `dof_ptr_buffer` / `dofs_buffer`: the subdomain index sets
+///   - `occ_ptr_buffer` / `occ_buffer`: their transpose, read by the combine pass
+///   - `local_z`: scratch for the local solutions (`dofs.len()` f32)
+///
+/// Apply usage per iteration:
+///   encode_apply(ctx, encoder, r_gpu, z_gpu)
+///
+/// Dispatch: two passes, ceil(num_blocks / 64) workgroups for the local solves, then
+/// ceil(n / 256) for the combine.
+pub struct AdditiveSchwarzExecutor {
+    n: u32,
+    num_blocks: u32,
+    combine: SchwarzCombine,
+
+    // Pipelines + layouts (immutable)
+    pipelines: AdditiveSchwarzPipelines,
+
+    // Persistent GPU buffers (immutable)
+    params_buffer: Buffer,
+    lu_buffer: Buffer,
+    dof_ptr_buffer: Buffer,
+    dofs_buffer: Buffer,
+    occ_ptr_buffer: Buffer,
+    occ_buffer: Buffer,
+
+    // Written by pass 1, read by pass 2 of every apply
+    local_z: Buffer,
+}
+
+impl AdditiveSchwarzExecutor {
+    /// Gather every local matrix A[S_b, S_b] from `csr` (duplicate entries summed as in
+    /// SpMV), LU-factor it on the host and upload everything.
+    ///
+    /// Fails when `csr` is not square, `subdomains` do not pass
+    /// [`SchwarzSubdomains::validate`], or a local matrix hits a zero pivot.
+    pub fn from_csr(
+        ctx: &GpuContext,
+        csr: &Csr,
+        subdomains: &SchwarzSubdomains,
+        combine: SchwarzCombine,
+    ) -> Result<Self, String> {
+        if csr.n_rows != csr.n_cols {
+            return Err(format!(
+                "AdditiveSchwarzExecutor::from_csr: matrix must be square, got {}x{}",
+                csr.n_rows, csr.n_cols
+            ));
+        }
+        let n = csr.n_rows as usize;
+        if n == 0 {
+            return Err("AdditiveSchwarzExecutor::from_csr: empty matrix".to_string());
+        }
+        subdomains
+            .validate(n)
+            .map_err(|e| format!("AdditiveSchwarzExecutor::from_csr: {e}"))?;
+
+        let num_blocks = subdomains.num_blocks();
+        let bs = (0..num_blocks)
+            .map(|b| subdomains.block(b).len())
+            .max()
+            .unwrap_or(1);
+
+        // Local factors: slab b holds A[S_b, S_b] in the local numbering, then its LU.
+        let mut lu_host = vec![0.0f32; num_blocks * bs * bs];
+        let mut local_index = vec![u32::MAX; n];
+        for (b, slab) in lu_host.chunks_exact_mut(bs * bs).enumerate() {
+            let set = subdomains.block(b);
+            for (li, &dof) in set.iter().enumerate() {
+                local_index[dof as usize] = li as u32;
+            }
+            for (li, &row) in set.iter().enumerate() {
+                let row = row as usize;
+                for k in csr.row_ptr[row] as usize..csr.row_ptr[row + 1] as usize {
+                    let lj = local_index[csr.col_idx[k] as usize];
+                    if lj != u32::MAX {
+                        slab[li * bs + lj as usize] += csr.values[k];
+                    }
+                }
+            }
+            for &dof in set {
+                local_index[dof as usize] = u32::MAX;
+            }
+
+            lu_factor_inplace(slab, bs, set.len())
+                .map_err(|e| format!("AdditiveSchwarzExecutor::from_csr: subdomain {b}: {e}"))?;
+        }
+
+        let (occ_ptr, occ) = subdomains.occurrences(n);
+
+        let pipelines = create_additive_schwarz_pipelines(ctx);
+
+        let params_words: [u32; 4] = [
+            n as u32,
+            num_blocks as u32,
+            bs as u32,
+            combine.shader_code(),
+        ];
+        let params_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("additive_schwarz params")),
+            contents: bytemuck::cast_slice(&params_words),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let storage = |label: &str, contents: &[u8]| {
+            ctx.create_buffer_init(&BufferInitDescriptor {
+                label: Some(&ctx.label(label)),
+                contents,
+                usage: BufferUsages::STORAGE,
+            })
+        };
+        let lu_buffer = storage("additive_schwarz lu_blocks", bytemuck::cast_slice(&lu_host));
+        let dof_ptr_buffer = storage(
+            "additive_schwarz dof_ptr",
+            bytemuck::cast_slice(&subdomains.dof_ptr),
+        );
+        let dofs_buffer = storage(
+            "additive_schwarz dofs",
+            bytemuck::cast_slice(&subdomains.dofs),
+        );
+        let occ_ptr_buffer = storage("additive_schwarz occ_ptr", bytemuck::cast_slice(&occ_ptr));
+        let occ_buffer = storage("additive_schwarz occ", bytemuck::cast_slice(&occ));
+        let local_z = ctx
+            .create_storage_buffer_uninit::<f32>(
+                "additive_schwarz local_z",
+                subdomains.dofs.len(),
+                BufferUsages::empty(),
+            )
+            .buffer;
+
+        Ok(Self {
+            n: n as u32,
+            num_blocks: num_blocks as u32,
+            combine,
+            pipelines,
+            params_buffer,
+            lu_buffer,
+            dof_ptr_buffer,
+            dofs_buffer,
+            occ_ptr_buffer,
+            occ_buffer,
+            local_z,
+        })
+    }
+
+    /// Encode: z = M^{-1} r (local solves, then the combine)
+    ///
+    /// `r_gpu` and `z_gpu` are per-call because they vary per iteration.
+    pub fn encode_apply(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        r_gpu: &Buffer,
+        z_gpu: &Buffer,
+    ) {
+        // Bind groups depend on per-call buffers r/z.
+        let solve_bind_group = create_additive_schwarz_bind_group(
+            &ctx.device,
+            &self.pipelines.solve_bind_group_layout,
+            "additive_schwarz_solve bind group 0",
+            &[
+                &self.params_buffer,
+                &self.lu_buffer,
+                &self.dof_ptr_buffer,
+                &self.dofs_buffer,
+                r_gpu,
+                &self.local_z,
+            ],
+        );
+        let combine_bind_group = create_additive_schwarz_bind_group(
+            &ctx.device,
+            &self.pipelines.combine_bind_group_layout,
+            "additive_schwarz_combine bind group 0",
+            &[
+                &self.params_buffer,
+                &self.occ_ptr_buffer,
+                &self.occ_buffer,
+                &self.local_z,
+                z_gpu,
+            ],
+        );
+
+        {
+            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some(&ctx.label("additive_schwarz local solves")),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipelines.solve_pipeline);
+            pass.set_bind_group(0, &solve_bind_group, &[]);
+            pass.dispatch_workgroups(
+                self.num_blocks
+                    .div_ceil(ADDITIVE_SCHWARZ_SOLVE_WORKGROUP_SIZE),
+                1,
+                1,
+            );
+        }
+
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some(&ctx.label("additive_schwarz combine")),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&self.pipelines.combine_pipeline);
+        pass.set_bind_group(0, &combine_bind_group, &[]);
+        pass.dispatch_workgroups(
+            self.n.div_ceil(ADDITIVE_SCHWARZ_COMBINE_WORKGROUP_SIZE),
+            1,
+            1,
+        );
+    }
+
+    pub fn n(&self) -> u32 {
+        self.n
+    }
+
+    pub fn num_blocks(&self) -> u32 {
+        self.num_blocks
+    }
+
+    pub fn combine(&self) -> SchwarzCombine {
+        self.combine
+    }
+
+    /// Compute dispatches one `encode_apply` records (local solves + combine).
+    pub fn dispatches(&self) -> u64 {
+        2
+    }
+}
@@ -0,0 +1,75 @@
+use wgpu::{Buffer, CommandEncoder};
+
+use crate::{compute::vec_ops_exec::VecOpsExecutor, gpu::context::GpuContext};
+
+/// y = alpha * x + y on two length-n GPU vectors, fixed n.
+///
+/// - `encode` reads alpha from `alpha_gpu[0]`, so a scalar a previous pass computed
+///   (e.g. `DotProduct::result_buffer()`) is used without a host roundtrip
+///   (axpy_from_scalar_results.wgsl).
+/// - `encode_scalar` takes a host alpha through the params uniform (axpy.wgsl).
+///
+/// Both record one pass of ceil(n / 256) workgroups and do NOT submit. This is the
+/// fixed-length front end of the AXPY kernels in `VecOpsExecutor`, which also offers
+/// other scalar slots and sub-ranges (`encode_axpy_range`).
+pub struct AxpyExecutor {
+    n: u32,
+    vec_ops: VecOpsExecutor,
+}
+
+impl AxpyExecutor {
+    pub fn create(ctx: &GpuContext, n: u32) -> Self {
+        Self {
+            n,
+            vec_ops: VecOpsExecutor::create(ctx),
+        }
+    }
+
+    pub fn n(&self) -> u32 {
+        self.n
+    }
+
+    /// Encode y = alpha_gpu[0] * x + y. `alpha_gpu` must have STORAGE usage.
+    ///
+    /// Panics if x or y holds fewer than n f32 or `alpha_gpu` is empty.
+    pub fn encode(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        alpha_gpu: &Buffer,
+        x_gpu: &Buffer,
+        y_gpu: &Buffer,
+    ) {
+        self.check_buffers(x_gpu, y_gpu);
+        assert!(alpha_gpu.size() >= 4, "AxpyExecutor: alpha buffer is empty");
+        self.vec_ops.encode_axpy_inplace_from_scalar_results(
+            ctx, encoder, x_gpu, y_gpu, self.n, alpha_gpu, 0,
+        );
+    }
+
+    /// Encode y = alpha * x + y with a host alpha.
+    ///
+    /// Panics if x or y holds fewer than n f32.
+    pub fn encode_scalar(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        alpha: f32,
+        x_gpu: &Buffer,
+        y_gpu: &Buffer,
+    ) {
+        self.check_buffers(x_gpu, y_gpu);
+        self.vec_ops
+            .encode_axpy_inplace(ctx, encoder, x_gpu, y_gpu, self.n, alpha);
+    }
+
+    fn check_buffers(&self, x: &Buffer, y: &Buffer) {
+        for (name, buffer) in [("x", x), ("y", y)] {
+            assert!(
+                buffer.size() >= self.n as u64 * 4,
+                "AxpyExecutor: {name} holds fewer than n = {} f32",
+                self.n
+            );
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use std::num::NonZeroU64;
+
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, Buffer, BufferBinding, BufferBindingType,
+    BufferUsages, CommandEncoder, ComputePipeline, ComputePipelineDescriptor,
+    PipelineLayoutDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+    util::BufferInitDescriptor,
+};
+
+use crate::gpu::context::GpuContext;
+
+/// Threads per workgroup of both batched_dot.wgsl entry points (= entries folded per
+/// partial).
+pub const BATCHED_DOT_WORKGROUP_SIZE: u32 = 256;
+
+/// Bytes of one pass's `Params` (the window bound at each dynamic offset).
+const PARAMS_SIZE: u64 = 16;
+
+/// One pass of the tree: where its params sit and the workgroups it launches per batch.
+struct Pass {
+    params_offset: u32,
+    out_len: u32,
+}
+
+/// k dot products a_j · b_j of length-n vector pairs in one pass tree, fixed n and k
+/// (batched_dot.wgsl).
+///
+/// The pairs are packed contiguously: `a_gpu` holds a_0, a_1, ..., a_{k-1} back to back
+/// (k * n f32), `b_gpu` likewise, and `encode` writes a_j · b_j to `results_gpu[j]`.
+/// Every pass launches k rows of workgroups (`workgroup_id.y` is the batch), so the k
+/// dots cost the passes of one: ceil(n / 256) x k partials, laid out [batch][partial],
+/// then one reduce pass per tree level, the last writing the results. A block-Krylov
+/// step with a handful of short dots thus launches two dispatches instead of two per
+/// dot, and each of them has k times the workgroups.
+///
+/// The sums follow the f32 tree of `DotScalarExecutor` (partials of 256, then folds of
+/// 256); there is no f64 or subgroup variant. Scratch buffers and params are built at
+/// creation; `encode` records one compute pass and does NOT submit.
+pub struct BatchedDotProduct {
+    n: u32,
+    k: u32,
+    partials_pipeline: ComputePipeline,
+    reduce_pipeline: ComputePipeline,
+    bind_group_layout: BindGroupLayout,
+    params_buffer: Buffer,
+    // Ping-pong scratch for the partials and intermediate reduce levels (k * partials f32)
+    scratch: [Buffer; 2],
+    passes: Vec<Pass>,
+}
+
+fn storage_entry(binding: u32, read_only: bool) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+impl BatchedDotProduct {
+    /// Executor for k >= 1 pairs of length n. Fails when the k * n f32 of a batch exceed
+    /// the device's storage binding size, or a pass would need more workgroups per
+    /// dimension than the device allows.
+    pub fn create(ctx: &GpuContext, n: u32, k: u32) -> Result<Self, String> {
+        let device = &ctx.device;
+        let limits = device.limits();
+        if k == 0 {
+            return Err("BatchedDotProduct: k must be >= 1".into());
+        }
+        let batch_bytes = n as u64 * k as u64 * 4;
+        if batch_bytes > limits.max_storage_buffer_binding_size as u64 {
+            return Err(format!(
+                "BatchedDotProduct: k * n = {k} * {n} f32 exceed the device's storage binding size ({} bytes)",
+                limits.max_storage_buffer_binding_size
+            ));
+        }
+        let partials = n.div_ceil(BATCHED_DOT_WORKGROUP_SIZE).max(1);
+        let max_groups = limits.max_compute_workgroups_per_dimension;
+        if partials > max_groups || k > max_groups {
+            return Err(format!(
+                "BatchedDotProduct: {partials} x {k} workgroups exceed the device's {max_groups} per dimension"
+            ));
+        }
+
+        // Passes: partials (n -> partials per batch), then folds of 256 down to 1. n = 0
+        // has none; `encode` clears the results instead.
+        let stride = limits.min_uniform_buffer_offset_alignment;
+        let mut params_words: Vec<u32> = Vec::new();
+        let mut passes = Vec::new();
+        let (mut len, mut out_len) = (n, partials);
+        if n > 0 {
+            loop {
+                let params_offset = (params_words.len() * 4) as u32;
+                params_words.extend([len, k, out_len, 0]);
+                params_words.resize((params_offset + stride) as usize / 4, 0);
+                passes.push(Pass {
+                    params_offset,
+                    out_len,
+                });
+                if out_len == 1 {
+                    break;
+                }
+                len = out_len;
+                out_len = len.div_ceil(BATCHED_DOT_WORKGROUP_SIZE);
+            }
+        }
+        if params_words.is_empty() {
+            params_words.resize(PARAMS_SIZE as usize / 4, 0);
+        }
+        let params_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("batched_dot params")),
+            contents: bytemuck::cast_slice(&params_words),
+            usage: BufferUsages::UNIFORM,
+        });
+        let scratch = ["batched_dot partials a", "batched_dot partials b"].map(|label| {
+            ctx.create_storage_buffer_uninit::<f32>(
+                label,
+                (partials * k) as usize,
+                BufferUsages::empty(),
+            )
+            .buffer
+        });
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some(&ctx.label("batched_dot.wgsl")),
+            source: ShaderSource::Wgsl(include_str!("wgsl/batched_dot.wgsl").into()),
+        });
+        // Bind group layout (group 0), matches batched_dot.wgsl:
+        //  0: params (uniform, dynamic offset)
+        //  1: a (RO storage)
+        //  2: b (RO storage)
+        //  3: input (RO storage)
+        //  4: output (RW storage)
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&ctx.label("batched_dot bgl0")),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: NonZeroU64::new(PARAMS_SIZE),
+                    },
+                    count: None,
+                },
+                storage_entry(1, true),
+                storage_entry(2, true),
+                storage_entry(3, true),
+                storage_entry(4, false),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some(&ctx.label("batched_dot pipeline layout")),
+            bind_group_layouts: &[&bind_group_layout],
+            immediate_size: 0,
+        });
+        let pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some(&ctx.label(&format!("batched_dot {entry_point} pipeline"))),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        Ok(Self {
+            n,
+            k,
+            partials_pipeline: pipeline("partials_main"),
+            reduce_pipeline: pipeline("reduce_main"),
+            bind_group_layout,
+            params_buffer,
+            scratch,
+            passes,
+        })
+    }
+
+    pub fn n(&self) -> u32 {
+        self.n
+    }
+
+    pub fn k(&self) -> u32 {
+        self.k
+    }
+
+    /// Compute dispatches one `encode` records: the partials pass plus one per reduce
+    /// level (1 for n <= 256, 2 up to 256^2, ...), independent of k; 0 for n = 0.
+    pub fn dispatches(&self) -> u32 {
+        self.passes.len() as u32
+    }
+
+    /// Encode results[j] = a[j*n..(j+1)*n] · b[j*n..(j+1)*n] for j < k. Does NOT submit.
+    /// For n = 0 the k results are cleared to 0.0 (an empty sum; needs `COPY_DST`).
+    ///
+    /// `a_gpu` and `b_gpu` may be the same buffer (k squared norms). Panics if either
+    /// holds fewer than k * n f32, `results_gpu` holds fewer than k, or `results_gpu` is
+    /// `a_gpu` or `b_gpu`.
+    pub fn encode(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        a_gpu: &Buffer,
+        b_gpu: &Buffer,
+        results_gpu: &Buffer,
+    ) {
+        let (n, k) = (self.n as u64, self.k as u64);
+        for (name, buffer) in [("a", a_gpu), ("b", b_gpu)] {
+            assert!(
+                buffer.size() >= k * n * 4,
+                "BatchedDotProduct: {name} holds fewer than k * n = {k} * {n} f32"
+            );
+        }
+        assert!(
+            results_gpu.size() >= k * 4,
+            "BatchedDotProduct: results holds fewer than k = {k} f32"
+        );
+        assert!(
+            results_gpu != a_gpu && results_gpu != b_gpu,
+            "BatchedDotProduct: results must differ from a and b"
+        );
+
+        if self.passes.is_empty() {
+            encoder.clear_buffer(results_gpu, 0, Some(k * 4));
+            return;
+        }
+
+        // Pass p reads scratch[(p + 1) % 2] (the previous output) and writes
+        // scratch[p % 2], the last pass writes the results.
+        let last = self.passes.len() - 1;
+        let bind_groups: Vec<_> = (0..self.passes.len())
+            .map(|p| {
+                let output = if p == last {
+                    results_gpu
+                } else {
+                    &self.scratch[p % 2]
+                };
+                let input = &self.scratch[(p + 1) % 2];
+                ctx.device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("batched_dot bind group 0"),
+                    layout: &self.bind_group_layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::Buffer(BufferBinding {
+                                buffer: &self.params_buffer,
+                                offset: 0,
+                                size: NonZeroU64::new(PARAMS_SIZE),
+                            }),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: a_gpu.as_entire_binding(),
+                        },
+                        BindGroupEntry {
+                            binding: 2,
+                            resource: b_gpu.as_entire_binding(),
+                        },
+                        BindGroupEntry {
+                            binding: 3,
+                            resource: input.as_entire_binding(),
+                        },
+                        BindGroupEntry {
+                            binding: 4,
+                            resource: output.as_entire_binding(),
+                        },
+                    ],
+                })
+            })
+            .collect();
+
+        let mut pass = ctx.begin_compute_pass(encoder, "batched_dot");
+        for (p, (step, bind_group)) in self.passes.iter().zip(&bind_groups).enumerate() {
+            pass.set_pipeline(if p == 0 {
+                &self.partials_pipeline
+            } else {
+                &self.reduce_pipeline
+            });
+            pass.set_bind_group(0, bind_group, &[step.params_offset]);
+            pass.dispatch_workgroups(step.out_len, self.k, 1);
+        }
+    }
+}
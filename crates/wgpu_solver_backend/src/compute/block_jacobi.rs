@@ -1,17 +1,78 @@
 use wgpu::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
     BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, ComputePipeline,
-    ComputePipelineDescriptor, Device, PipelineLayoutDescriptor, ShaderModuleDescriptor,
-    ShaderSource, ShaderStages,
+    ComputePipelineDescriptor, Device, PipelineCompilationOptions, PipelineLayoutDescriptor,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages,
 };
 
 use crate::gpu::context::GpuContext;
 
+/// Element type of the packed LU blocks on the GPU (`lu_t` in block_jacobi.wgsl).
+///
+/// The substitution runs in f32 either way; f16 halves the block buffer at the cost of
+/// rounding the factors to 11 significant bits, which is usually harmless for a
+/// preconditioner.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LuStorage {
+    #[default]
+    F32,
+    /// Needs a device with SHADER_F16 (see [`LuStorage::required_features`]).
+    F16,
+}
+
+impl LuStorage {
+    /// Size of one stored entry in bytes.
+    pub fn size_bytes(self) -> u32 {
+        match self {
+            LuStorage::F32 => 4,
+            LuStorage::F16 => 2,
+        }
+    }
+
+    /// Device features needed besides the defaults.
+    pub fn required_features(self) -> wgpu::Features {
+        match self {
+            LuStorage::F32 => wgpu::Features::empty(),
+            LuStorage::F16 => wgpu::Features::SHADER_F16,
+        }
+    }
+
+    /// Whether `ctx` can run the apply with blocks stored this way.
+    pub fn supported_by(self, ctx: &GpuContext) -> bool {
+        ctx.features.contains(self.required_features())
+    }
+}
+
 pub struct BlockJacobiPipeline {
     pub pipeline: ComputePipeline,
     pub block_jacobi_bind_group_layout: BindGroupLayout,
 }
 
+/// Relative pivot threshold of the GPU block factorization: a block is singular when some
+/// pivot |U(k,k)| <= `BLOCK_LU_PIVOT_TOLERANCE` * max |A_bb| (block_lu_factor.wgsl).
+///
+/// About 8 f32 ulps: a pivot that small has lost essentially all its digits to
+/// cancellation, and dividing by it would only amplify rounding noise.
+pub const BLOCK_LU_PIVOT_TOLERANCE: f32 = 1e-6;
+
+/// Pipeline for the in-place LU factorization of the block slabs (block_lu_factor.wgsl).
+pub struct BlockLuFactorPipeline {
+    pub pipeline: ComputePipeline,
+    pub block_lu_factor_bind_group_layout: BindGroupLayout,
+}
+
+/// Pipeline for the rectangular (least-squares) block apply (block_lsq.wgsl).
+pub struct BlockLsqPipeline {
+    pub pipeline: ComputePipeline,
+    pub block_lsq_bind_group_layout: BindGroupLayout,
+}
+
+/// Pipeline for the per-block 2-norms of a vector (block_norms.wgsl).
+pub struct BlockNormsPipeline {
+    pub pipeline: ComputePipeline,
+    pub block_norms_bind_group_layout: BindGroupLayout,
+}
+
 fn create_uniform_entry(binding: u32) -> BindGroupLayoutEntry {
     BindGroupLayoutEntry {
         binding,
@@ -41,12 +102,29 @@ fn create_storage_entry(binding: u32, is_read_only: bool) -> BindGroupLayoutEntr
 }
 
 pub fn create_block_jacobi_pipeline(ctx: &GpuContext) -> BlockJacobiPipeline {
+    create_block_jacobi_pipeline_with_storage(ctx, LuStorage::F32)
+}
+
+/// block_jacobi.wgsl with its LU blocks stored as `storage`.
+pub fn block_jacobi_wgsl(storage: LuStorage) -> String {
+    let source = include_str!("wgsl/block_jacobi.wgsl");
+    match storage {
+        LuStorage::F32 => source.to_string(),
+        LuStorage::F16 => source.replacen("alias lu_t = f32;", "enable f16;\nalias lu_t = f16;", 1),
+    }
+}
+
+/// Apply pipeline reading `storage` LU blocks. `LuStorage::F16` needs SHADER_F16.
+pub fn create_block_jacobi_pipeline_with_storage(
+    ctx: &GpuContext,
+    storage: LuStorage,
+) -> BlockJacobiPipeline {
     let device = &ctx.device;
 
     // Shader module
     let shader = device.create_shader_module(ShaderModuleDescriptor {
-        label: Some("block_jacobi.wgsl"),
-        source: ShaderSource::Wgsl(include_str!("wgsl/block_jacobi.wgsl").into()),
+        label: Some(&ctx.label("block_jacobi.wgsl")),
+        source: ShaderSource::Wgsl(block_jacobi_wgsl(storage).into()),
     });
 
     // Bind group layout (group 0), matches block_jacobi.wgsl:
@@ -55,27 +133,29 @@ pub fn create_block_jacobi_pipeline(ctx: &GpuContext) -> BlockJacobiPipeline {
     //  2: block_starts (RO storage)
     //  3: r (RO storage)
     //  4: z (RW storage)
+    //  5: block_mask (RO storage)
     let block_jacobi_bind_group_layout =
         device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: Some("block_jacobi bgl0"),
+            label: Some(&ctx.label("block_jacobi bgl0")),
             entries: &[
                 create_uniform_entry(0),
                 create_storage_entry(1, true),
                 create_storage_entry(2, true),
                 create_storage_entry(3, true),
                 create_storage_entry(4, false),
+                create_storage_entry(5, true),
             ],
         });
 
     // Pipeline layout (newer wgpu uses immediate_size)
     let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-        label: Some("block_jacobi pipeline layout"),
+        label: Some(&ctx.label("block_jacobi pipeline layout")),
         bind_group_layouts: &[&block_jacobi_bind_group_layout],
         immediate_size: 0,
     });
 
     let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
-        label: Some("block_jacobi pipeline"),
+        label: Some(&ctx.label("block_jacobi pipeline")),
         layout: Some(&pipeline_layout),
         module: &shader,
         entry_point: Some("compute_main"),
@@ -89,6 +169,87 @@ pub fn create_block_jacobi_pipeline(ctx: &GpuContext) -> BlockJacobiPipeline {
     }
 }
 
+pub fn create_block_lu_factor_pipeline(ctx: &GpuContext) -> BlockLuFactorPipeline {
+    let device = &ctx.device;
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(&ctx.label("block_lu_factor.wgsl")),
+        source: ShaderSource::Wgsl(include_str!("wgsl/block_lu_factor.wgsl").into()),
+    });
+
+    // Bind group layout (group 0), matches block_lu_factor.wgsl:
+    //  0: params (uniform)
+    //  1: lu_blocks (RW storage)
+    //  2: block_starts (RO storage)
+    //  3: status (RW storage)
+    let block_lu_factor_bind_group_layout =
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&ctx.label("block_lu_factor bgl0")),
+            entries: &[
+                create_uniform_entry(0),
+                create_storage_entry(1, false),
+                create_storage_entry(2, true),
+                create_storage_entry(3, false),
+            ],
+        });
+
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&ctx.label("block_lu_factor pipeline layout")),
+        bind_group_layouts: &[&block_lu_factor_bind_group_layout],
+        immediate_size: 0,
+    });
+
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(&ctx.label("block_lu_factor pipeline")),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("compute_main"),
+        compilation_options: PipelineCompilationOptions {
+            constants: &[("PIVOT_TOLERANCE", BLOCK_LU_PIVOT_TOLERANCE as f64)],
+            ..Default::default()
+        },
+        cache: None,
+    });
+
+    BlockLuFactorPipeline {
+        pipeline,
+        block_lu_factor_bind_group_layout,
+    }
+}
+
+pub fn create_block_lu_factor_bind_group(
+    device: &Device,
+    block_lu_factor_bind_group_layout: &BindGroupLayout,
+    params_buffer: &Buffer,
+    lu_blocks_buffer: &Buffer,
+    block_starts_buffer: &Buffer,
+    status_buffer: &Buffer,
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("block_lu_factor bind group 0"),
+        layout: block_lu_factor_bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: lu_blocks_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: block_starts_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 3,
+                resource: status_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn create_block_jacobi_bind_group(
     device: &Device,
     block_jacobi_bind_group_layout: &BindGroupLayout,
@@ -97,6 +258,7 @@ pub fn create_block_jacobi_bind_group(
     block_starts_buffer: &Buffer,
     r_buffer: &Buffer,
     z_buffer: &Buffer,
+    block_mask_buffer: &Buffer,
 ) -> BindGroup {
     device.create_bind_group(&BindGroupDescriptor {
         label: Some("block_jacobi bind group 0"),
@@ -122,6 +284,185 @@ pub fn create_block_jacobi_bind_group(
                 binding: 4,
                 resource: z_buffer.as_entire_binding(),
             },
+            BindGroupEntry {
+                binding: 5,
+                resource: block_mask_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+pub fn create_block_lsq_pipeline(ctx: &GpuContext) -> BlockLsqPipeline {
+    let device = &ctx.device;
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(&ctx.label("block_lsq.wgsl")),
+        source: ShaderSource::Wgsl(include_str!("wgsl/block_lsq.wgsl").into()),
+    });
+
+    // Bind group layout (group 0), matches block_lsq.wgsl:
+    //  0: params (uniform)
+    //  1: qr_blocks (RO storage)
+    //  2: row_starts (RO storage)
+    //  3: col_starts (RO storage)
+    //  4: r (RO storage)
+    //  5: z (RW storage)
+    //  6: block_mask (RO storage)
+    let block_lsq_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some(&ctx.label("block_lsq bgl0")),
+        entries: &[
+            create_uniform_entry(0),
+            create_storage_entry(1, true),
+            create_storage_entry(2, true),
+            create_storage_entry(3, true),
+            create_storage_entry(4, true),
+            create_storage_entry(5, false),
+            create_storage_entry(6, true),
+        ],
+    });
+
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&ctx.label("block_lsq pipeline layout")),
+        bind_group_layouts: &[&block_lsq_bind_group_layout],
+        immediate_size: 0,
+    });
+
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(&ctx.label("block_lsq pipeline")),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("compute_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    BlockLsqPipeline {
+        pipeline,
+        block_lsq_bind_group_layout,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_block_lsq_bind_group(
+    device: &Device,
+    block_lsq_bind_group_layout: &BindGroupLayout,
+    params_buffer: &Buffer,
+    qr_blocks_buffer: &Buffer,
+    row_starts_buffer: &Buffer,
+    col_starts_buffer: &Buffer,
+    r_buffer: &Buffer,
+    z_buffer: &Buffer,
+    block_mask_buffer: &Buffer,
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("block_lsq bind group 0"),
+        layout: block_lsq_bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: qr_blocks_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: row_starts_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 3,
+                resource: col_starts_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 4,
+                resource: r_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 5,
+                resource: z_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 6,
+                resource: block_mask_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+pub fn create_block_norms_pipeline(ctx: &GpuContext) -> BlockNormsPipeline {
+    let device = &ctx.device;
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(&ctx.label("block_norms.wgsl")),
+        source: ShaderSource::Wgsl(include_str!("wgsl/block_norms.wgsl").into()),
+    });
+
+    // Bind group layout (group 0), matches block_norms.wgsl:
+    //  0: params (uniform)
+    //  1: block_starts (RO storage)
+    //  2: r (RO storage)
+    //  3: norms (RW storage)
+    let block_norms_bind_group_layout =
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&ctx.label("block_norms bgl0")),
+            entries: &[
+                create_uniform_entry(0),
+                create_storage_entry(1, true),
+                create_storage_entry(2, true),
+                create_storage_entry(3, false),
+            ],
+        });
+
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&ctx.label("block_norms pipeline layout")),
+        bind_group_layouts: &[&block_norms_bind_group_layout],
+        immediate_size: 0,
+    });
+
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(&ctx.label("block_norms pipeline")),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("compute_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    BlockNormsPipeline {
+        pipeline,
+        block_norms_bind_group_layout,
+    }
+}
+
+pub fn create_block_norms_bind_group(
+    device: &Device,
+    block_norms_bind_group_layout: &BindGroupLayout,
+    params_buffer: &Buffer,
+    block_starts_buffer: &Buffer,
+    r_buffer: &Buffer,
+    norms_buffer: &Buffer,
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("block_norms bind group 0"),
+        layout: block_norms_bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: block_starts_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: r_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 3,
+                resource: norms_buffer.as_entire_binding(),
+            },
         ],
     })
 }
@@ -1,17 +1,74 @@
-use wgpu::util::{BufferInitDescriptor, DeviceExt};
-use wgpu::{Buffer, BufferUsages, CommandEncoder, ComputePassDescriptor};
+use std::sync::Mutex;
+
+use futures::executor;
+use wgpu::util::BufferInitDescriptor;
+use wgpu::{BindGroup, Buffer, BufferUsages, CommandEncoder, CommandEncoderDescriptor};
 
 use crate::compute::block_jacobi::{
-    BlockJacobiPipeline, create_block_jacobi_bind_group, create_block_jacobi_pipeline,
+    BlockJacobiPipeline, BlockLsqPipeline, LuStorage, create_block_jacobi_bind_group,
+    create_block_jacobi_pipeline_with_storage, create_block_lsq_bind_group,
+    create_block_lsq_pipeline, create_block_lu_factor_bind_group, create_block_lu_factor_pipeline,
 };
+use crate::compute::{QR_BLOCK_STRIDE, build_qr_blocks_from_csr_6, gather_diagonal_blocks_6};
 use crate::gpu::context::GpuContext;
+use crate::matrix::Csr;
+
+/// Floats per block in the dense (unpadded) LU layout for 6x6 blocks.
+pub const DENSE_LU_STRIDE: u32 = 36;
+
+/// Largest `block_size` the square apply kernel supports (the size of its per-invocation
+/// temporaries, `MAX_BLOCK_SIZE` in block_jacobi.wgsl).
+pub const MAX_BLOCK_SIZE: u32 = 32;
+
+/// How the blocks of a [`BlockJacobiExecutor`] are stored and applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKind {
+    /// Square diagonal blocks A[b, b], stored as LU: z_b = A_bb^{-1} r_b.
+    Square,
+    /// Tall (or square) blocks A[rows_b, cols_b], stored as a thin QR:
+    /// z_b = argmin ||A_b z_b - r_b||_2 = R^{-1} Q^T r_b (block_lsq.wgsl).
+    Rectangular,
+}
+
+/// What the GPU block factorization of [`BlockJacobiExecutor::from_csr_with_setup`] found.
+///
+/// A block is singular when one of its pivots falls below the relative threshold
+/// [`BLOCK_LU_PIVOT_TOLERANCE`](crate::compute::block_jacobi::BLOCK_LU_PIVOT_TOLERANCE);
+/// in a FEM matrix that usually points at a degenerate element. Its slab is reset to the
+/// identity, so the preconditioner stays finite (z_b = r_b on that block).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockJacobiSetup {
+    /// Every block factored.
+    pub preconditioner_setup_ok: bool,
+    /// Indices of the singular blocks, ascending.
+    pub singular_blocks: Vec<u32>,
+    /// Step k of the offending pivot, parallel to `singular_blocks`.
+    pub singular_pivots: Vec<u32>,
+}
+
+// Apply kernel per kind; rectangular blocks also need their column ranges.
+enum ApplyPipeline {
+    Square(BlockJacobiPipeline),
+    Rectangular {
+        pipeline: BlockLsqPipeline,
+        col_starts_buffer: Buffer,
+    },
+}
+
+// Bind group of the most recent apply, keyed by its (r, z, mask) buffers.
+#[derive(Default)]
+struct BindGroupCache {
+    entry: Option<([Buffer; 3], BindGroup)>,
+    created: u64,
+}
 
 /// BlockJacobiExecutor
 ///
 /// Owns the immutable GPU resources for the Block-Jacobi preconditioner:
-///   - `lu_blocks_buffer`: packed LU blocks (one dense 6x6 per block, row-major)
+///   - `lu_blocks_buffer`: packed LU blocks (one block_size x block_size per block,
+///     row-major, `lu_stride` apart)
 ///   - `block_starts_buffer`: block ranges (length num_blocks + 1)
-///   - `params_buffer`: uniform [n, num_blocks, 0, 0]
+///   - `params_buffer`: uniform [n, num_blocks, lu_stride, block_size]
 ///
 /// Apply usage per iteration:
 ///   encode_apply(ctx, encoder, r_gpu, z_gpu)
@@ -22,17 +79,107 @@ use crate::gpu::context::GpuContext;
 /// Dispatch:
 ///   - 1 workgroup per block (WGSL workgroup_size = 1),
 ///     so dispatch `num_blocks` workgroups in X.
+///
+/// A [`BlockKind::Rectangular`] executor (`create_rectangular` / `from_csr_rectangular`)
+/// has the same apply interface, but r has `n` (= n_rows) entries and z has `n_cols`.
+///
+/// The factor and apply passes are timed while the context has pass timing enabled
+/// (`GpuContext::enable_pass_timing`).
+///
+/// The apply bind group is cached for the most recent (r, z, mask) buffer triple, so a
+/// solver loop that applies to the same r/z every iteration builds it once. A different
+/// triple replaces the entry. The cache holds handles to those buffers, which keeps
+/// their memory alive until the next apply with other buffers or
+/// `clear_bind_group_cache`; the solvers clear it when they release a solve's vectors
+/// (`LinearOperator::release_buffers`).
 pub struct BlockJacobiExecutor {
     n: u32,
+    n_cols: u32,
     num_blocks: u32,
 
+    // Slab dimension of each block (6 for rectangular blocks)
+    block_size: u32,
+
+    // Entries between consecutive blocks on the GPU (block_size^2 unless padded,
+    // QR_BLOCK_STRIDE for rectangular blocks)
+    lu_stride: u32,
+
+    // Element type of lu_blocks_buffer (always f32 for rectangular blocks)
+    lu_storage: LuStorage,
+
     // Pipeline + layout (immutable)
-    block_jacobi_pipeline: BlockJacobiPipeline,
+    apply_pipeline: ApplyPipeline,
 
     // Persistent GPU buffers (immutable)
     params_buffer: Buffer,
     lu_blocks_buffer: Buffer,
     block_starts_buffer: Buffer,
+
+    // All-ones block mask bound by the plain `encode_apply`
+    all_active_mask_buffer: Buffer,
+
+    bind_group_cache: Mutex<BindGroupCache>,
+}
+
+/// Active-block mask for [`BlockJacobiExecutor::encode_apply_masked`]: 1 for every block
+/// with a non-zero entry of r in [starts[b], starts[b+1]), 0 where that slice is all zero.
+///
+/// `starts` is the executor's block_starts (row_starts for rectangular blocks). Scanning r
+/// on the host only pays off when r is already there or its sparsity is known up front
+/// (e.g. a localized load: mark the blocks the load touches and skip the scan).
+pub fn active_block_mask(starts: &[u32], r: &[f32]) -> Vec<u32> {
+    starts
+        .windows(2)
+        .map(|w| r[w[0] as usize..w[1] as usize].iter().any(|&v| v != 0.0) as u32)
+        .collect()
+}
+
+/// The block_starts rules of [`BlockJacobiExecutor::create`].
+fn check_block_starts(n: u32, block_size: u32, block_starts: &[u32]) -> Result<(), String> {
+    let Some((&first, &last)) = block_starts.first().zip(block_starts.last()) else {
+        return Err(
+            "BlockJacobiExecutor: block_starts is empty, needs num_blocks + 1 entries".into(),
+        );
+    };
+    if first != 0 {
+        return Err(format!(
+            "BlockJacobiExecutor: block_starts[0] = {first}, must be 0"
+        ));
+    }
+    for (i, w) in block_starts.windows(2).enumerate() {
+        if w[1] <= w[0] {
+            return Err(format!(
+                "BlockJacobiExecutor: block_starts not strictly increasing at index {}: {} after {}",
+                i + 1,
+                w[1],
+                w[0]
+            ));
+        }
+        if w[1] - w[0] > block_size {
+            return Err(format!(
+                "BlockJacobiExecutor: block {i} ([{}, {})) has {} rows, more than block_size = {block_size}",
+                w[0],
+                w[1],
+                w[1] - w[0]
+            ));
+        }
+    }
+    if last != n {
+        return Err(format!(
+            "BlockJacobiExecutor: block_starts ends at {last}, must equal n = {n}"
+        ));
+    }
+    Ok(())
+}
+
+fn create_all_active_mask_buffer(ctx: &GpuContext, num_blocks: u32) -> Buffer {
+    // At least one entry: zero-sized storage bindings are invalid.
+    let ones = vec![1u32; num_blocks.max(1) as usize];
+    ctx.create_buffer_init(&BufferInitDescriptor {
+        label: Some(&ctx.label("block_jacobi all-active mask")),
+        contents: bytemuck::cast_slice(&ones),
+        usage: BufferUsages::STORAGE,
+    })
 }
 
 impl BlockJacobiExecutor {
@@ -40,59 +187,387 @@ impl BlockJacobiExecutor {
     ///
     /// Inputs:
     /// - `n` length of vectors r/z (in f32)
-    /// - `lu_blocks_host`: packed LU blocks, one 6x6 per block (36 f32 per block)
+    /// - `block_size`: slab dimension bs, 1..=[`MAX_BLOCK_SIZE`]; a block may be shorter
+    ///   than bs and then uses the leading m x m of its slab
+    /// - `lu_blocks_host`: packed LU blocks, one bs x bs per block (bs^2 f32 per block,
+    ///   e.g. from `build_lu_blocks_from_csr_block_starts`)
     /// - `block_starts_u32`: length num_blocks + 1, defines offsets into vector (in entries)
     ///
-    /// NOTE:
-    /// This executor assumes BLOCK_SIZE = 6 and LU_STRIDE = 36 in WGSL.
+    /// block_starts must start at 0, increase strictly, end at n, and no block may be
+    /// longer than bs; otherwise this fails with the first offending index (the kernel
+    /// would skip or truncate such blocks and leave parts of z unwritten).
+    ///
+    /// The kernel reads bs from the params uniform, so one pipeline serves every block
+    /// size. Blocks are uploaded densely (bs^2 floats apart); see `create_with_alignment`
+    /// for a padded layout and `create_with_storage` for f16 blocks.
     pub fn create(
         ctx: &GpuContext,
         n: u32,
+        block_size: u32,
         lu_blocks_host: &[f32],
         block_starts_u32: &[u32],
-    ) -> Self {
-        let device = &ctx.device;
+    ) -> Result<Self, String> {
+        Self::create_with_alignment(ctx, n, block_size, lu_blocks_host, block_starts_u32, 4)
+    }
+
+    /// Same as `create`, but the blocks are stored on the GPU as `storage`. The host input
+    /// stays f32 and is rounded (to nearest) while it is uploaded; the apply widens every
+    /// entry back to f32, so only the factors lose precision, not the substitution.
+    ///
+    /// `LuStorage::F16` needs SHADER_F16. Without it the blocks are kept in f32 and a
+    /// warning goes to stderr; [`BlockJacobiExecutor::lu_storage`] tells which one is used.
+    pub fn create_with_storage(
+        ctx: &GpuContext,
+        n: u32,
+        block_size: u32,
+        lu_blocks_host: &[f32],
+        block_starts_u32: &[u32],
+        storage: LuStorage,
+    ) -> Result<Self, String> {
+        let storage = if storage.supported_by(ctx) {
+            storage
+        } else {
+            eprintln!(
+                "warning: {storage:?} LU block storage needs SHADER_F16, which the device does \
+                 not have; storing the blocks in f32"
+            );
+            LuStorage::F32
+        };
+        Self::create_impl(
+            ctx,
+            n,
+            block_size,
+            lu_blocks_host,
+            block_starts_u32,
+            storage.size_bytes(),
+            storage,
+        )
+    }
+
+    /// Same as `create`, but every block on the GPU starts at a multiple of
+    /// `block_alignment_bytes` (power of two, >= 4). Host input stays densely packed.
+    ///
+    /// A dense 6x6 block is 144 bytes, which is already a multiple of 16, so vec4 (16-byte)
+    /// alignment keeps the dense layout; 32 / 64 / 256 pad each block to 160 / 192 / 256
+    /// bytes. Only worth it on drivers that penalize accesses straddling those boundaries;
+    /// on llvmpipe it makes no measurable difference.
+    pub fn create_with_alignment(
+        ctx: &GpuContext,
+        n: u32,
+        block_size: u32,
+        lu_blocks_host: &[f32],
+        block_starts_u32: &[u32],
+        block_alignment_bytes: u32,
+    ) -> Result<Self, String> {
+        if block_alignment_bytes < 4 || !block_alignment_bytes.is_power_of_two() {
+            return Err(format!(
+                "BlockJacobiExecutor: block alignment must be a power of two >= 4, got {}",
+                block_alignment_bytes
+            ));
+        }
+        Self::create_impl(
+            ctx,
+            n,
+            block_size,
+            lu_blocks_host,
+            block_starts_u32,
+            block_alignment_bytes,
+            LuStorage::F32,
+        )
+    }
+
+    fn create_impl(
+        ctx: &GpuContext,
+        n: u32,
+        block_size: u32,
+        lu_blocks_host: &[f32],
+        block_starts_u32: &[u32],
+        block_alignment_bytes: u32,
+        storage: LuStorage,
+    ) -> Result<Self, String> {
+        if !(1..=MAX_BLOCK_SIZE).contains(&block_size) {
+            return Err(format!(
+                "BlockJacobiExecutor: block_size must be in 1..={MAX_BLOCK_SIZE}, got {block_size}"
+            ));
+        }
+        check_block_starts(n, block_size, block_starts_u32)?;
 
         let num_blocks = (block_starts_u32.len() as u32).saturating_sub(1);
+        let dense_stride = block_size * block_size;
+        let expected_len = num_blocks as usize * dense_stride as usize;
+        if lu_blocks_host.len() != expected_len {
+            return Err(format!(
+                "BlockJacobiExecutor: lu_blocks has {} floats, expected num_blocks * block_size^2 = {num_blocks} * {dense_stride} = {expected_len}",
+                lu_blocks_host.len()
+            ));
+        }
+        let lu_stride = dense_stride.next_multiple_of(block_alignment_bytes / storage.size_bytes());
+
+        // Repack into the padded layout (padding stays zero, the kernel never reads it).
+        let padded;
+        let lu_blocks_gpu: &[f32] = if lu_stride == dense_stride {
+            lu_blocks_host
+        } else {
+            let dense = dense_stride as usize;
+            let stride = lu_stride as usize;
+            let mut out = vec![0.0f32; num_blocks as usize * stride];
+            for (dst, src) in out
+                .chunks_exact_mut(stride)
+                .zip(lu_blocks_host.chunks_exact(dense))
+            {
+                dst[..dense].copy_from_slice(src);
+            }
+            padded = out;
+            &padded
+        };
 
         // 1) Pipeline (once)
-        let block_jacobi_pipeline = create_block_jacobi_pipeline(ctx);
+        let block_jacobi_pipeline = create_block_jacobi_pipeline_with_storage(ctx, storage);
 
-        // 2) Params uniform (once): [n, num_blocks, 0, 0]
-        let params_words: [u32; 4] = [n, num_blocks, 0, 0];
-        let params_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("block_jacobi params"),
+        // 2) Params uniform (once): [n, num_blocks, lu_stride, block_size]
+        let params_words: [u32; 4] = [n, num_blocks, lu_stride, block_size];
+        let params_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("block_jacobi params")),
             contents: bytemuck::cast_slice(&params_words),
             usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         });
 
-        // 3) LU blocks buffer (once)
-        let lu_blocks_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("block_jacobi lu_blocks"),
-            contents: bytemuck::cast_slice(lu_blocks_host),
+        // 3) LU blocks buffer (once); f16 is padded to whole 4-byte words
+        let half;
+        let contents: &[u8] = match storage {
+            LuStorage::F32 => bytemuck::cast_slice(lu_blocks_gpu),
+            LuStorage::F16 => {
+                let mut bits: Vec<u16> =
+                    lu_blocks_gpu.iter().map(|&v| f32_to_f16_bits(v)).collect();
+                if bits.len() % 2 == 1 {
+                    bits.push(0);
+                }
+                half = bits;
+                bytemuck::cast_slice(&half)
+            }
+        };
+        let lu_blocks_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("block_jacobi lu_blocks")),
+            contents,
             usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
         });
 
         // 4) block_starts buffer (once)
-        let block_starts_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("block_jacobi block_starts"),
+        let block_starts_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("block_jacobi block_starts")),
             contents: bytemuck::cast_slice(block_starts_u32),
             usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
         });
 
-        Self {
+        Ok(Self {
             n,
+            n_cols: n,
             num_blocks,
-            block_jacobi_pipeline,
+            block_size,
+            lu_stride,
+            lu_storage: storage,
+            apply_pipeline: ApplyPipeline::Square(block_jacobi_pipeline),
             params_buffer,
             lu_blocks_buffer,
             block_starts_buffer,
+            all_active_mask_buffer: create_all_active_mask_buffer(ctx, num_blocks),
+            bind_group_cache: Mutex::default(),
+        })
+    }
+
+    /// Build the preconditioner straight from the matrix: split [0, n) into blocks of
+    /// `block_size` rows (1..=6; the last block takes the remainder), gather each dense
+    /// diagonal block A[b, b] from the CSR and LU-factor all of them on the GPU
+    /// (block_lu_factor.wgsl, one invocation per block, no pivoting).
+    ///
+    /// Entries of A outside the diagonal blocks are ignored, as in block Jacobi generally:
+    /// they only make M a worse approximation of A, never an invalid one. A singular block
+    /// (see [`BlockJacobiSetup`]) is an error naming the first one; the factors match
+    /// [`crate::compute::build_lu_blocks_from_csr_block_starts_6`] up to rounding.
+    pub fn from_csr(ctx: &GpuContext, csr: &Csr, block_size: usize) -> Result<Self, String> {
+        let (exec, setup) = Self::from_csr_with_setup(ctx, csr, block_size)?;
+        if let (Some(&block), Some(&k)) =
+            (setup.singular_blocks.first(), setup.singular_pivots.first())
+        {
+            let start = block as usize * block_size;
+            return Err(format!(
+                "BlockJacobiExecutor::from_csr: LU zero pivot at k = {k} in block {block} (rows {start}..{})",
+                (start + block_size).min(csr.n_rows as usize)
+            ));
+        }
+        Ok(exec)
+    }
+
+    /// Same as `from_csr`, but singular blocks are not an error: they are listed in the
+    /// returned [`BlockJacobiSetup`] and applied as the identity.
+    pub fn from_csr_with_setup(
+        ctx: &GpuContext,
+        csr: &Csr,
+        block_size: usize,
+    ) -> Result<(Self, BlockJacobiSetup), String> {
+        if !(1..=6).contains(&block_size) {
+            return Err(format!(
+                "BlockJacobiExecutor::from_csr: block_size must be in 1..=6, got {block_size}"
+            ));
+        }
+        if csr.n_rows != csr.n_cols {
+            return Err(format!(
+                "BlockJacobiExecutor::from_csr: matrix must be square, got {}x{}",
+                csr.n_rows, csr.n_cols
+            ));
+        }
+
+        let n = csr.n_rows as usize;
+        let mut block_starts: Vec<u32> = (0..n).step_by(block_size).map(|s| s as u32).collect();
+        block_starts.push(n as u32);
+
+        let blocks =
+            gather_diagonal_blocks_6(n, &csr.row_ptr, &csr.col_idx, &csr.values, &block_starts)?;
+        let exec = Self::create(ctx, n as u32, 6, &blocks, &block_starts)?;
+        let mut setup = BlockJacobiSetup {
+            preconditioner_setup_ok: true,
+            ..Default::default()
+        };
+        if exec.num_blocks == 0 {
+            return Ok((exec, setup));
         }
+
+        // Factor the uploaded slabs in place.
+        let factor_pipeline = create_block_lu_factor_pipeline(ctx);
+        let status = ctx.create_storage_buffer_uninit::<u32>(
+            "block_lu_factor status",
+            exec.num_blocks as usize,
+            BufferUsages::empty(),
+        );
+        let bind_group = create_block_lu_factor_bind_group(
+            &ctx.device,
+            &factor_pipeline.block_lu_factor_bind_group_layout,
+            &exec.params_buffer,
+            &exec.lu_blocks_buffer,
+            &exec.block_starts_buffer,
+            &status.buffer,
+        );
+
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some(&ctx.label("block_lu_factor encoder")),
+            });
+        {
+            let mut pass = ctx.begin_compute_pass(&mut encoder, "block_lu_factor pass");
+            pass.set_pipeline(&factor_pipeline.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(exec.num_blocks.div_ceil(64), 1, 1);
+        }
+        ctx.queue.submit(Some(encoder.finish()));
+
+        let status = executor::block_on(ctx.try_readback(&status))?;
+        for (block, &s) in status.iter().enumerate() {
+            if s != 0 {
+                setup.singular_blocks.push(block as u32);
+                setup.singular_pivots.push(s - 1);
+            }
+        }
+        setup.preconditioner_setup_ok = setup.singular_blocks.is_empty();
+
+        Ok((exec, setup))
+    }
+
+    /// Rectangular blocks: block b maps r[row_starts[b]..row_starts[b+1]] (m entries) to
+    /// z[col_starts[b]..col_starts[b+1]] (k entries) by a least-squares solve against
+    /// A[rows_b, cols_b] = Q R, given as `qr_blocks_host` in the layout of
+    /// [`build_qr_blocks_from_csr_6`] (QR_BLOCK_STRIDE floats per block).
+    ///
+    /// r has `n_rows` entries and z has `n_cols`. Every column of z belongs to exactly one
+    /// block, so the apply writes all of z.
+    pub fn create_rectangular(
+        ctx: &GpuContext,
+        n_rows: u32,
+        n_cols: u32,
+        qr_blocks_host: &[f32],
+        row_starts_u32: &[u32],
+        col_starts_u32: &[u32],
+    ) -> Self {
+        let num_blocks = (row_starts_u32.len() as u32).saturating_sub(1);
+        let qr_stride = QR_BLOCK_STRIDE as u32;
+
+        let pipeline = create_block_lsq_pipeline(ctx);
+
+        // Same uniform shape as the square kernel: [n_rows, num_blocks, qr_stride, n_cols]
+        let params_words: [u32; 4] = [n_rows, num_blocks, qr_stride, n_cols];
+        let params_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("block_lsq params")),
+            contents: bytemuck::cast_slice(&params_words),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let lu_blocks_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("block_lsq qr_blocks")),
+            contents: bytemuck::cast_slice(qr_blocks_host),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        });
+
+        let block_starts_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("block_lsq row_starts")),
+            contents: bytemuck::cast_slice(row_starts_u32),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        });
+
+        let col_starts_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("block_lsq col_starts")),
+            contents: bytemuck::cast_slice(col_starts_u32),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        });
+
+        Self {
+            n: n_rows,
+            n_cols,
+            num_blocks,
+            block_size: 6,
+            lu_stride: qr_stride,
+            lu_storage: LuStorage::F32,
+            apply_pipeline: ApplyPipeline::Rectangular {
+                pipeline,
+                col_starts_buffer,
+            },
+            params_buffer,
+            lu_blocks_buffer,
+            block_starts_buffer,
+            all_active_mask_buffer: create_all_active_mask_buffer(ctx, num_blocks),
+            bind_group_cache: Mutex::default(),
+        }
+    }
+
+    /// Rectangular counterpart of `from_csr`: gather A[rows_b, cols_b] for each block and
+    /// QR-factor it on the host ([`build_qr_blocks_from_csr_6`]; blocks must satisfy
+    /// 1 <= cols <= rows <= 6 and have full column rank).
+    ///
+    /// Square blocks may be mixed in (a square block's least-squares solution is its exact
+    /// solve); the matrix itself may be rectangular.
+    pub fn from_csr_rectangular(
+        ctx: &GpuContext,
+        csr: &Csr,
+        row_starts: &[u32],
+        col_starts: &[u32],
+    ) -> Result<Self, String> {
+        let qr_blocks = build_qr_blocks_from_csr_6(
+            csr.n_rows as usize,
+            csr.n_cols as usize,
+            &csr.row_ptr,
+            &csr.col_idx,
+            &csr.values,
+            row_starts,
+            col_starts,
+        )?;
+        Ok(Self::create_rectangular(
+            ctx, csr.n_rows, csr.n_cols, &qr_blocks, row_starts, col_starts,
+        ))
     }
 
     /// Encode: z = M^{-1} r
     ///
-    /// `r_gpu` and `z_gpu` are per-call because they vary per iteration.
+    /// `r_gpu` and `z_gpu` are per-call; repeating the previous pair reuses its bind
+    /// group (see the type docs).
     pub fn encode_apply(
         &self,
         ctx: &GpuContext,
@@ -100,34 +575,196 @@ impl BlockJacobiExecutor {
         r_gpu: &Buffer,
         z_gpu: &Buffer,
     ) {
-        // Bind group depends on per-call buffers r/z.
-        let bind_group = create_block_jacobi_bind_group(
-            &ctx.device,
-            &self.block_jacobi_pipeline.block_jacobi_bind_group_layout,
-            &self.params_buffer,
-            &self.lu_blocks_buffer,
-            &self.block_starts_buffer,
-            r_gpu,
-            z_gpu,
-        );
+        self.encode_apply_masked(ctx, encoder, r_gpu, z_gpu, &self.all_active_mask_buffer);
+    }
 
-        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
-            label: Some("block_jacobi apply pass"),
-            timestamp_writes: None,
-        });
+    /// Same as `encode_apply`, but blocks with `block_mask_gpu[b] == 0` skip their dense
+    /// solve and write z_b = 0.
+    ///
+    /// `block_mask_gpu` holds one u32 per block (`num_blocks()` entries), e.g. uploaded
+    /// from [`active_block_mask`]. The mask is trusted, not checked: it must only clear
+    /// blocks whose slice of r is zero, where z_b = 0 is also what the solve returns, so
+    /// the result matches the full apply exactly.
+    pub fn encode_apply_masked(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        r_gpu: &Buffer,
+        z_gpu: &Buffer,
+        block_mask_gpu: &Buffer,
+    ) {
+        let key = [r_gpu.clone(), z_gpu.clone(), block_mask_gpu.clone()];
+        let mut cache = self.bind_group_cache.lock().unwrap();
+        let bind_group = match &cache.entry {
+            Some((cached_key, bind_group)) if *cached_key == key => bind_group.clone(),
+            _ => {
+                let bind_group = self.create_apply_bind_group(ctx, r_gpu, z_gpu, block_mask_gpu);
+                cache.entry = Some((key, bind_group.clone()));
+                cache.created += 1;
+                bind_group
+            }
+        };
+        drop(cache);
+
+        let pipeline = match &self.apply_pipeline {
+            ApplyPipeline::Square(p) => &p.pipeline,
+            ApplyPipeline::Rectangular { pipeline, .. } => &pipeline.pipeline,
+        };
 
-        pass.set_pipeline(&self.block_jacobi_pipeline.pipeline);
+        let mut pass = ctx.begin_compute_pass(encoder, "block_jacobi apply pass");
+
+        pass.set_pipeline(pipeline);
         pass.set_bind_group(0, &bind_group, &[]);
 
         // One workgroup per block (WGSL workgroup_size = 1).
         pass.dispatch_workgroups(self.num_blocks, 1, 1);
     }
 
-    // pub fn n(&self) -> u32 {
-    //     self.n
-    // }
+    fn create_apply_bind_group(
+        &self,
+        ctx: &GpuContext,
+        r_gpu: &Buffer,
+        z_gpu: &Buffer,
+        block_mask_gpu: &Buffer,
+    ) -> BindGroup {
+        match &self.apply_pipeline {
+            ApplyPipeline::Square(p) => create_block_jacobi_bind_group(
+                &ctx.device,
+                &p.block_jacobi_bind_group_layout,
+                &self.params_buffer,
+                &self.lu_blocks_buffer,
+                &self.block_starts_buffer,
+                r_gpu,
+                z_gpu,
+                block_mask_gpu,
+            ),
+            ApplyPipeline::Rectangular {
+                pipeline,
+                col_starts_buffer,
+            } => create_block_lsq_bind_group(
+                &ctx.device,
+                &pipeline.block_lsq_bind_group_layout,
+                &self.params_buffer,
+                &self.lu_blocks_buffer,
+                &self.block_starts_buffer,
+                col_starts_buffer,
+                r_gpu,
+                z_gpu,
+                block_mask_gpu,
+            ),
+        }
+    }
+
+    /// Drop the cached apply bind group (and with it the executor's handles to the last
+    /// r/z/mask buffers).
+    pub fn clear_bind_group_cache(&self) {
+        self.bind_group_cache.lock().unwrap().entry = None;
+    }
+
+    /// Whether an apply bind group, and with it handles to its r/z/mask buffers, is cached.
+    pub fn has_cached_bind_group(&self) -> bool {
+        self.bind_group_cache.lock().unwrap().entry.is_some()
+    }
 
-    // pub fn num_blocks(&self) -> u32 {
-    //     self.num_blocks
-    // }
+    /// Apply bind groups built so far (cache misses); one per distinct buffer triple in
+    /// a loop that keeps its r/z.
+    pub fn bind_groups_created(&self) -> u64 {
+        self.bind_group_cache.lock().unwrap().created
+    }
+
+    /// Length of r: the rows covered by the blocks.
+    pub fn n(&self) -> u32 {
+        self.n
+    }
+
+    /// Length of z; equals `n()` unless the blocks are rectangular.
+    pub fn n_cols(&self) -> u32 {
+        self.n_cols
+    }
+
+    pub fn kind(&self) -> BlockKind {
+        match self.apply_pipeline {
+            ApplyPipeline::Square(_) => BlockKind::Square,
+            ApplyPipeline::Rectangular { .. } => BlockKind::Rectangular,
+        }
+    }
+
+    /// Number of diagonal blocks; the apply runs one workgroup per block.
+    pub fn num_blocks(&self) -> u32 {
+        self.num_blocks
+    }
+
+    /// Slab dimension of each block (the `block_size` given to `create`).
+    pub fn block_size(&self) -> u32 {
+        self.block_size
+    }
+
+    /// Entries between consecutive blocks in the GPU LU buffer.
+    pub fn lu_stride(&self) -> u32 {
+        self.lu_stride
+    }
+
+    /// Element type of the GPU LU buffer (see `create_with_storage`).
+    pub fn lu_storage(&self) -> LuStorage {
+        self.lu_storage
+    }
+}
+
+/// IEEE 754 binary16 bits of `v`, rounded to nearest even; overflows to +-inf, NaN stays
+/// NaN.
+pub fn f32_to_f16_bits(v: f32) -> u16 {
+    let bits = v.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let mant = bits & 0x007f_ffff;
+
+    if exp == 0xff {
+        // inf / NaN (keep a quiet-NaN bit so a NaN never turns into inf)
+        return sign | 0x7c00 | if mant != 0 { 0x0200 } else { 0 };
+    }
+
+    let e = exp - 127 + 15;
+    if e >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if e <= 0 {
+        // Subnormal (or zero) in f16: shift the implicit-one mantissa into place.
+        if e < -10 {
+            return sign;
+        }
+        let m = mant | 0x0080_0000;
+        let shift = (14 - e) as u32;
+        let half = 1u32 << (shift - 1);
+        let rest = m & ((1u32 << shift) - 1);
+        let mut out = m >> shift;
+        if rest > half || (rest == half && out & 1 == 1) {
+            out += 1;
+        }
+        return sign | out as u16;
+    }
+
+    let mut out = ((e as u32) << 10) | (mant >> 13);
+    let rest = mant & 0x1fff;
+    if rest > 0x1000 || (rest == 0x1000 && out & 1 == 1) {
+        // May carry into the exponent, up to inf, which is the right rounding.
+        out += 1;
+    }
+    sign | out as u16
+}
+
+/// The f32 value of binary16 `bits` (exact).
+pub fn f16_bits_to_f32(bits: u16) -> f32 {
+    let sign = ((bits & 0x8000) as u32) << 16;
+    let exp = ((bits >> 10) & 0x1f) as u32;
+    let mant = (bits & 0x03ff) as u32;
+    let magnitude = match exp {
+        0 => {
+            // Subnormal or zero: mant * 2^-24
+            let v = mant as f32 * 2f32.powi(-24);
+            return if sign != 0 { -v } else { v };
+        }
+        0x1f => 0x7f80_0000 | (mant << 13),
+        _ => ((exp + 127 - 15) << 23) | (mant << 13),
+    };
+    f32::from_bits(sign | magnitude)
 }
@@ -1,7 +1,6 @@
-use wgpu::{
-    Buffer, BufferUsages, CommandEncoder, Device,
-    util::{BufferInitDescriptor, DeviceExt},
-};
+use wgpu::{Buffer, BufferUsages, CommandEncoder, util::BufferInitDescriptor};
+
+use crate::gpu::context::GpuContext;
 
 /// Encode a tiny CPU->GPU write into a *storage* buffer at element index `slot`.
 /// This is the native wgpu equivalent of your WebGPU "staging write into storage buffer index".
@@ -11,14 +10,14 @@ use wgpu::{
 /// - buffer has COPY_DST usage.
 /// - we keep this explicit and tiny (one 4-byte staging buffer).
 pub fn encode_write_f32_into_storage_buffer_at_index(
-    device: &Device,
+    ctx: &GpuContext,
     encoder: &mut CommandEncoder,
     dst_storage_buffer: &Buffer,
     slot: u32,
     value: f32,
     label: &str,
 ) {
-    let staging = device.create_buffer_init(&BufferInitDescriptor {
+    let staging = ctx.create_buffer_init(&BufferInitDescriptor {
         label: Some(label),
         contents: bytemuck::bytes_of(&value),
         usage: BufferUsages::COPY_SRC,
@@ -0,0 +1,337 @@
+use bytemuck::cast_slice;
+use futures::executor;
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, BufferDescriptor, BufferUsages,
+    CommandEncoder, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, ErrorFilter,
+    PipelineLayoutDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+};
+
+use crate::{
+    compute::dot_reduce::{
+        DEFAULT_WORKGROUP_SIZE, DotReducePipeline, ReduceOp, create_dot_reduce_bind_group,
+        create_reduce_pipeline,
+    },
+    gpu::{buffer::GpuBuffer, context::GpuContext},
+};
+
+// Custom per-iteration stopping metrics.
+//
+// A metric is a user-supplied WGSL kernel (a `CustomKernel`) that maps the current
+// iterate to one f32 per element; the solver folds those values into a single scalar
+// with the built-in reduce (sum or max), writes it into a scalar slot and reads it
+// back together with the PCG scalars. No extra readback and no copy of x to the host.
+//
+// Kernel interface (bind group 0, entry point `CustomKernel::entry_point`, which is
+// `compute_main` unless set with `CustomKernel::with_entry_point`; the built-in
+// kernels all use `compute_main`):
+//
+//   struct Params { n: u32, _pad0: u32, _pad1: u32, _pad2: u32 };
+//   @group(0) @binding(0) var<uniform> params: Params;
+//   @group(0) @binding(1) var<storage, read> x: array<f32>;            // current x
+//   @group(0) @binding(2) var<storage, read_write> state: array<f32>;  // persistent
+//   @group(0) @binding(3) var<storage, read_write> value: array<f32>;  // output
+//
+//   @compute @workgroup_size(256)  // CUSTOM_KERNEL_WORKGROUP_SIZE
+//
+// - Dispatched with ceil(n / 256) workgroups after x is updated, once per iteration;
+//   invocation i must write value[i] for every i < params.n.
+// - `state` (length n) starts as a copy of x0 and keeps whatever the kernel writes
+//   between iterations (e.g. the previous iterate).
+// - `value` is clobbered by the reduce, so the kernel must not keep data in it.
+//
+// See wgsl/max_abs_change.wgsl for a complete example.
+
+/// Workgroup size the custom kernel must declare (the dispatch assumes it).
+pub const CUSTOM_KERNEL_WORKGROUP_SIZE: u32 = 256;
+
+/// A user-supplied WGSL kernel following the interface above.
+#[derive(Debug, Clone)]
+pub struct CustomKernel {
+    /// Used for the wgpu labels and in error messages.
+    pub label: String,
+    pub wgsl: String,
+    /// Name of the `@compute` function to run.
+    pub entry_point: String,
+}
+
+impl CustomKernel {
+    /// Kernel with the conventional `compute_main` entry point.
+    pub fn new(label: impl Into<String>, wgsl: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            wgsl: wgsl.into(),
+            entry_point: "compute_main".to_string(),
+        }
+    }
+
+    /// Run `entry_point` instead of `compute_main`. A name the module does not define
+    /// is reported by `CustomMetricExecutor::create` like any other WGSL error.
+    pub fn with_entry_point(mut self, entry_point: impl Into<String>) -> Self {
+        self.entry_point = entry_point.into();
+        self
+    }
+}
+
+/// Stop the solve once a custom scalar drops to `threshold` (see `PcgOptions`).
+#[derive(Debug, Clone)]
+pub struct CustomStoppingMetric {
+    pub kernel: CustomKernel,
+    /// How the per-element values are folded into the scalar.
+    pub reduction: ReduceOp,
+    /// Converged when the scalar is <= threshold.
+    pub threshold: f32,
+}
+
+impl CustomStoppingMetric {
+    /// max_i |x_k[i] - x_{k-1}[i]| <= threshold (wgsl/max_abs_change.wgsl).
+    pub fn max_abs_change(threshold: f32) -> Self {
+        Self {
+            kernel: CustomKernel::new(
+                "max_abs_change.wgsl",
+                include_str!("wgsl/max_abs_change.wgsl"),
+            ),
+            reduction: ReduceOp::Max,
+            threshold,
+        }
+    }
+}
+
+fn uniform_entry(binding: u32) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn storage_entry(binding: u32, read_only: bool) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+/// Runs one [`CustomKernel`] plus its reduce against a fixed x buffer.
+///
+/// The length is fixed at creation, so every uniform and bind group is built once and
+/// encoding records only passes (nothing is written through the queue per iteration).
+pub struct CustomMetricExecutor {
+    kernel_pipeline: ComputePipeline,
+    kernel_bind_group: BindGroup,
+    reduce_pipeline: DotReducePipeline,
+
+    // One bind group per reduce pass, ping-ponging between `value` and `scratch`
+    reduce_bind_groups: Vec<BindGroup>,
+    reduce_out_lens: Vec<u32>,
+
+    // Buffer holding the final scalar at offset 0
+    result_buffer_is_scratch: bool,
+
+    n: u32,
+
+    // Kept alive for the bind groups above
+    _params_buffers: Vec<Buffer>,
+    _state: GpuBuffer<f32>,
+    value: GpuBuffer<f32>,
+    scratch: GpuBuffer<f32>,
+}
+
+impl CustomMetricExecutor {
+    /// Compile `kernel` and bind it to `x_buffer` (length n = `x0.len()`); `state`
+    /// starts as a copy of `x0`.
+    ///
+    /// WGSL or binding errors in the kernel are returned, not raised on the device.
+    pub fn create(
+        ctx: &GpuContext,
+        kernel: &CustomKernel,
+        reduction: ReduceOp,
+        x_buffer: &Buffer,
+        x0: &[f32],
+    ) -> Result<Self, String> {
+        let device = &ctx.device;
+        let n = x0.len() as u32;
+        if n == 0 {
+            return Err(format!("custom kernel {}: empty x", kernel.label));
+        }
+
+        let scope = device.push_error_scope(ErrorFilter::Validation);
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some(&ctx.label(&kernel.label)),
+            source: ShaderSource::Wgsl(kernel.wgsl.as_str().into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&ctx.label("custom kernel bgl0")),
+            entries: &[
+                uniform_entry(0),
+                storage_entry(1, true),
+                storage_entry(2, false),
+                storage_entry(3, false),
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some(&ctx.label("custom kernel pipeline layout")),
+            bind_group_layouts: &[&bind_group_layout],
+            immediate_size: 0,
+        });
+
+        let kernel_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some(&ctx.label(&kernel.label)),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some(&kernel.entry_point),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        if let Some(e) = executor::block_on(scope.pop()) {
+            return Err(format!("custom kernel {}: {e}", kernel.label));
+        }
+
+        let reduce_pipeline = create_reduce_pipeline(ctx, DEFAULT_WORKGROUP_SIZE, reduction);
+
+        let params_buffer = |label: &str, len: u32| {
+            let buffer = ctx.create_buffer(&BufferDescriptor {
+                label: Some(&ctx.label(label)),
+                size: 16, // [len,0,0,0]
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let words: [u32; 4] = [len, 0, 0, 0];
+            ctx.queue.write_buffer(&buffer, 0, cast_slice(&words));
+            buffer
+        };
+
+        let state = ctx.create_storage_buffer("custom kernel state", x0, BufferUsages::empty());
+        let value = ctx.create_storage_buffer_uninit::<f32>(
+            "custom kernel value",
+            n as usize,
+            BufferUsages::empty(),
+        );
+        let scratch = ctx.create_storage_buffer_uninit::<f32>(
+            "custom kernel reduce scratch",
+            n.div_ceil(DEFAULT_WORKGROUP_SIZE) as usize,
+            BufferUsages::empty(),
+        );
+
+        let kernel_params = params_buffer("custom kernel params", n);
+        let kernel_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some(&ctx.label("custom kernel bind group 0")),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: kernel_params.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: x_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: state.buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: value.buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        // Tree reduce value -> scratch -> value -> ... until one element is left.
+        let mut params_buffers = vec![kernel_params];
+        let mut reduce_bind_groups = Vec::new();
+        let mut reduce_out_lens = Vec::new();
+        let mut current_len = n;
+        let mut output_is_scratch = true;
+        while current_len > 1 {
+            let (input, output) = if output_is_scratch {
+                (&value, &scratch)
+            } else {
+                (&scratch, &value)
+            };
+            let params = params_buffer("custom kernel reduce params", current_len);
+            reduce_bind_groups.push(create_dot_reduce_bind_group(
+                device,
+                &reduce_pipeline.dot_reduce_bind_group_layout,
+                &params,
+                &input.buffer,
+                &output.buffer,
+            ));
+            params_buffers.push(params);
+
+            current_len = current_len.div_ceil(DEFAULT_WORKGROUP_SIZE);
+            reduce_out_lens.push(current_len);
+            output_is_scratch = !output_is_scratch;
+        }
+
+        Ok(Self {
+            kernel_pipeline,
+            kernel_bind_group,
+            reduce_pipeline,
+            reduce_bind_groups,
+            reduce_out_lens,
+            // after the last pass the flag points at the *next* output
+            result_buffer_is_scratch: !output_is_scratch,
+            n,
+            _params_buffers: params_buffers,
+            _state: state,
+            value,
+            scratch,
+        })
+    }
+
+    /// Compute dispatches one `encode_into` records (kernel + reduce passes).
+    pub fn dispatches(&self) -> u64 {
+        1 + self.reduce_bind_groups.len() as u64
+    }
+
+    /// Encode kernel + reduce and copy the scalar into `scalar_results[out_index]`.
+    pub fn encode_into(
+        &self,
+        encoder: &mut CommandEncoder,
+        scalar_results: &Buffer,
+        out_index: u32,
+    ) {
+        {
+            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("custom kernel pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.kernel_pipeline);
+            pass.set_bind_group(0, &self.kernel_bind_group, &[]);
+            pass.dispatch_workgroups(self.n.div_ceil(CUSTOM_KERNEL_WORKGROUP_SIZE), 1, 1);
+        }
+
+        for (bind_group, out_len) in self.reduce_bind_groups.iter().zip(&self.reduce_out_lens) {
+            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("custom kernel reduce pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.reduce_pipeline.pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.dispatch_workgroups(*out_len, 1, 1);
+        }
+
+        let result = if self.result_buffer_is_scratch {
+            &self.scratch
+        } else {
+            &self.value
+        };
+        encoder.copy_buffer_to_buffer(&result.buffer, 0, scalar_results, (out_index as u64) * 4, 4);
+    }
+}
@@ -0,0 +1,121 @@
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, ComputePipeline,
+    ComputePipelineDescriptor, Device, PipelineLayoutDescriptor, ShaderModuleDescriptor,
+    ShaderSource, ShaderStages,
+};
+
+use crate::gpu::context::GpuContext;
+
+/// Workgroup size of diagonal_jacobi.wgsl (one invocation per entry).
+pub const DIAGONAL_JACOBI_WORKGROUP_SIZE: u32 = 256;
+
+pub struct DiagonalJacobiPipeline {
+    pub pipeline: ComputePipeline,
+    pub diagonal_jacobi_bind_group_layout: BindGroupLayout,
+}
+
+fn create_uniform_entry(binding: u32) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn create_storage_entry(binding: u32, is_read_only: bool) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Storage {
+                read_only: is_read_only,
+            },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+pub fn create_diagonal_jacobi_pipeline(ctx: &GpuContext) -> DiagonalJacobiPipeline {
+    let device = &ctx.device;
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(&ctx.label("diagonal_jacobi.wgsl")),
+        source: ShaderSource::Wgsl(include_str!("wgsl/diagonal_jacobi.wgsl").into()),
+    });
+
+    // Bind group layout (group 0), matches diagonal_jacobi.wgsl:
+    //  0: params (uniform)
+    //  1: inv_diag (RO storage)
+    //  2: r (RO storage)
+    //  3: z (RW storage)
+    let diagonal_jacobi_bind_group_layout =
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&ctx.label("diagonal_jacobi bgl0")),
+            entries: &[
+                create_uniform_entry(0),
+                create_storage_entry(1, true),
+                create_storage_entry(2, true),
+                create_storage_entry(3, false),
+            ],
+        });
+
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&ctx.label("diagonal_jacobi pipeline layout")),
+        bind_group_layouts: &[&diagonal_jacobi_bind_group_layout],
+        immediate_size: 0,
+    });
+
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(&ctx.label("diagonal_jacobi pipeline")),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("compute_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    DiagonalJacobiPipeline {
+        pipeline,
+        diagonal_jacobi_bind_group_layout,
+    }
+}
+
+pub fn create_diagonal_jacobi_bind_group(
+    device: &Device,
+    diagonal_jacobi_bind_group_layout: &BindGroupLayout,
+    params_buffer: &Buffer,
+    inv_diag_buffer: &Buffer,
+    r_buffer: &Buffer,
+    z_buffer: &Buffer,
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("diagonal_jacobi bind group 0"),
+        layout: diagonal_jacobi_bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: inv_diag_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: r_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 3,
+                resource: z_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
@@ -0,0 +1,150 @@
+use wgpu::util::BufferInitDescriptor;
+use wgpu::{Buffer, BufferUsages, CommandEncoder, ComputePassDescriptor};
+
+use crate::compute::diagonal_jacobi::{
+    DIAGONAL_JACOBI_WORKGROUP_SIZE, DiagonalJacobiPipeline, create_diagonal_jacobi_bind_group,
+    create_diagonal_jacobi_pipeline,
+};
+use crate::gpu::context::GpuContext;
+use crate::matrix::Csr;
+
+/// DiagonalJacobiExecutor
+///
+/// Point-Jacobi preconditioner z = D^{-1} r, a lighter alternative to
+/// [`BlockJacobiExecutor`](crate::compute::block_jacobi_exec::BlockJacobiExecutor) when
+/// the 6x6 blocks do not pay off. Owns the immutable GPU resources:
+///   - `inv_diag_buffer`: 1 / A(i,i), computed by the caller (length n)
+///   - `params_buffer`: uniform [n, 0, 0, 0]
+///
+/// Apply usage per iteration:
+///   encode_apply(ctx, encoder, r_gpu, z_gpu)
+///
+/// Dispatch:
+///   - one invocation per entry (WGSL workgroup_size = 256),
+///     so dispatch ceil(n / 256) workgroups in X.
+pub struct DiagonalJacobiExecutor {
+    n: u32,
+
+    // Pipeline + layout (immutable)
+    pipeline: DiagonalJacobiPipeline,
+
+    // Persistent GPU buffers (immutable)
+    params_buffer: Buffer,
+    inv_diag_buffer: Buffer,
+}
+
+impl DiagonalJacobiExecutor {
+    /// Create a diagonal Jacobi executor.
+    ///
+    /// Inputs:
+    /// - `n` length of vectors r/z (in f32)
+    /// - `inv_diag_host`: the inverse diagonal 1 / A(i,i), `n` entries
+    ///
+    /// Panics when `inv_diag_host` does not have `n` entries.
+    pub fn create(ctx: &GpuContext, n: u32, inv_diag_host: &[f32]) -> Self {
+        if inv_diag_host.len() != n as usize {
+            panic!(
+                "DiagonalJacobiExecutor: inv_diag has {} entries, expected n = {}",
+                inv_diag_host.len(),
+                n
+            );
+        }
+
+        // 1) Pipeline (once)
+        let pipeline = create_diagonal_jacobi_pipeline(ctx);
+
+        // 2) Params uniform (once): [n, 0, 0, 0]
+        let params_words: [u32; 4] = [n, 0, 0, 0];
+        let params_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("diagonal_jacobi params")),
+            contents: bytemuck::cast_slice(&params_words),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        // 3) Inverse diagonal (once). At least one entry: zero-sized storage bindings
+        // are invalid.
+        let padded;
+        let inv_diag_gpu: &[f32] = if inv_diag_host.is_empty() {
+            padded = [0.0f32];
+            &padded
+        } else {
+            inv_diag_host
+        };
+        let inv_diag_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("diagonal_jacobi inv_diag")),
+            contents: bytemuck::cast_slice(inv_diag_gpu),
+            usage: BufferUsages::STORAGE,
+        });
+
+        Self {
+            n,
+            pipeline,
+            params_buffer,
+            inv_diag_buffer,
+        }
+    }
+
+    /// Build the preconditioner straight from the matrix: 1 / A(i,i) per row, with
+    /// duplicate diagonal entries summed as SpMV would. A zero (or missing) diagonal
+    /// entry is an error naming the row.
+    pub fn from_csr(ctx: &GpuContext, csr: &Csr) -> Result<Self, String> {
+        if csr.n_rows != csr.n_cols {
+            return Err(format!(
+                "DiagonalJacobiExecutor::from_csr: matrix must be square, got {}x{}",
+                csr.n_rows, csr.n_cols
+            ));
+        }
+
+        let n = csr.n_rows as usize;
+        let mut inv_diag = Vec::with_capacity(n);
+        for i in 0..n {
+            let range = csr.row_ptr[i] as usize..csr.row_ptr[i + 1] as usize;
+            let d: f32 = range
+                .filter(|&k| csr.col_idx[k] as usize == i)
+                .map(|k| csr.values[k])
+                .sum();
+            if d == 0.0 {
+                return Err(format!(
+                    "DiagonalJacobiExecutor::from_csr: zero diagonal in row {i}"
+                ));
+            }
+            inv_diag.push(1.0 / d);
+        }
+
+        Ok(Self::create(ctx, csr.n_rows, &inv_diag))
+    }
+
+    /// Encode: z = D^{-1} r
+    ///
+    /// `r_gpu` and `z_gpu` are per-call because they vary per iteration.
+    pub fn encode_apply(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        r_gpu: &Buffer,
+        z_gpu: &Buffer,
+    ) {
+        // Bind group depends on per-call buffers r/z.
+        let bind_group = create_diagonal_jacobi_bind_group(
+            &ctx.device,
+            &self.pipeline.diagonal_jacobi_bind_group_layout,
+            &self.params_buffer,
+            &self.inv_diag_buffer,
+            r_gpu,
+            z_gpu,
+        );
+
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some(&ctx.label("diagonal_jacobi apply pass")),
+            timestamp_writes: None,
+        });
+
+        pass.set_pipeline(&self.pipeline.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(self.n.div_ceil(DIAGONAL_JACOBI_WORKGROUP_SIZE), 1, 1);
+    }
+
+    pub fn n(&self) -> u32 {
+        self.n
+    }
+}
@@ -0,0 +1,132 @@
+use wgpu::{Buffer, CommandEncoder, CommandEncoderDescriptor};
+
+use crate::{
+    compute::dot_scalar_exec::DotScalarExecutor,
+    gpu::{context::GpuContext, readback::try_readback_to_vec},
+};
+
+/// Length below which the submitting helpers (`DotProduct::compute`, `norms::weighted_norm`)
+/// skip the GPU reduction and finish on the host.
+///
+/// Under it they read the raw input vectors back (2n floats) and sum them in f32 on the
+/// CPU: one readback of a few hundred bytes instead of the partials/reduce dispatches
+/// plus a scalar readback, whose fixed per-pass cost dominates at these sizes. The
+/// `encode*` entry points are never affected, as they cannot read back mid-encoder.
+pub const DEFAULT_HOST_REDUCE_THRESHOLD: u32 = 64;
+
+/// sum_i a[i] * b[i] in f32, in index order (the host half of the small-n path).
+pub(crate) fn host_dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(&x, &y)| x * y).sum()
+}
+
+/// One dot product a·b of two length-n GPU vectors, as a single call.
+///
+/// `encode` records the whole partials + reduce tree (see `DotScalarExecutor` for how
+/// many passes that is) and leaves the scalar in the 1-element `result_buffer()`;
+/// `encode_into` additionally copies it into a caller buffer. The intermediate partial
+/// buffers are sized once at construction for `n`.
+///
+/// For several dots per submit (as PCG does) use `DotScalarExecutor` directly and give
+/// every dot its own scalar slot; here the slot is rewritten by each `encode`, so read
+/// (or `encode_into` away) one result before encoding the next.
+///
+/// `compute` reduces on the host for n below `host_reduce_threshold()`
+/// (see [`DEFAULT_HOST_REDUCE_THRESHOLD`]).
+pub struct DotProduct {
+    n: u32,
+    exec: DotScalarExecutor,
+    host_reduce_threshold: u32,
+}
+
+impl DotProduct {
+    pub fn create(ctx: &GpuContext, n: u32) -> Self {
+        Self {
+            n,
+            exec: DotScalarExecutor::create(ctx, n as usize, 1),
+            host_reduce_threshold: DEFAULT_HOST_REDUCE_THRESHOLD,
+        }
+    }
+
+    /// n below which `compute` sums on the host; 0 always uses the GPU reduction.
+    pub fn set_host_reduce_threshold(&mut self, threshold: u32) {
+        self.host_reduce_threshold = threshold;
+    }
+
+    pub fn host_reduce_threshold(&self) -> u32 {
+        self.host_reduce_threshold
+    }
+
+    /// Compute dispatches one `compute` issues: 0 on the host path, otherwise those of
+    /// the partials + reduce tree.
+    pub fn compute_dispatches(&self) -> u32 {
+        if self.n < self.host_reduce_threshold {
+            0
+        } else {
+            self.exec.dispatches_per_dot(self.n)
+        }
+    }
+
+    pub fn n(&self) -> u32 {
+        self.n
+    }
+
+    /// The 1-element f32 buffer the last `encode` wrote a·b into (STORAGE | COPY_SRC).
+    pub fn result_buffer(&self) -> &Buffer {
+        self.exec.scalar_results_buffer()
+    }
+
+    /// Encode result = a·b over the first n entries of `a` and `b`. Does NOT submit.
+    ///
+    /// Panics if `a` or `b` holds fewer than n f32.
+    pub fn encode(&self, ctx: &GpuContext, encoder: &mut CommandEncoder, a: &Buffer, b: &Buffer) {
+        for (name, buffer) in [("a", a), ("b", b)] {
+            assert!(
+                buffer.size() >= self.n as u64 * 4,
+                "DotProduct: {name} holds fewer than n = {} f32",
+                self.n
+            );
+        }
+        self.exec
+            .encode_dot_scalar_into(ctx, encoder, a, b, self.n, 0);
+    }
+
+    /// `encode`, then copy the scalar into `result[0]` (needs COPY_DST).
+    pub fn encode_into(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        a: &Buffer,
+        b: &Buffer,
+        result: &Buffer,
+    ) {
+        self.encode(ctx, encoder, a, b);
+        encoder.copy_buffer_to_buffer(self.result_buffer(), 0, result, 0, 4);
+    }
+
+    /// Encode, submit and read back a·b.
+    ///
+    /// For n below `host_reduce_threshold()` no compute pass runs: `a` and `b` are read
+    /// back (they need COPY_SRC) and summed on the host, and `result_buffer()` is left
+    /// untouched.
+    pub async fn compute(&self, ctx: &GpuContext, a: &Buffer, b: &Buffer) -> Result<f32, String> {
+        if self.n < self.host_reduce_threshold {
+            let n = self.n as usize;
+            ctx.count_buffer_created();
+            let a_host = try_readback_to_vec::<f32>(&ctx.device, &ctx.queue, a, n, None).await?;
+            ctx.count_buffer_created();
+            let b_host = try_readback_to_vec::<f32>(&ctx.device, &ctx.queue, b, n, None).await?;
+            return Ok(host_dot(&a_host, &b_host));
+        }
+
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some(&ctx.label("dot product encoder")),
+            });
+        self.encode(ctx, &mut encoder, a, b);
+        self.exec
+            .encode_copy_scalar_results_to_readback(&mut encoder);
+        ctx.queue.submit(Some(encoder.finish()));
+        Ok(self.exec.try_readback_scalar_results(ctx).await?[0])
+    }
+}
@@ -1,15 +1,92 @@
 use wgpu::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
     BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, ComputePipeline,
-    ComputePipelineDescriptor, Device, PipelineLayoutDescriptor, ShaderModuleDescriptor,
-    ShaderSource, ShaderStages,
+    ComputePipelineDescriptor, Device, Features, PipelineCompilationOptions,
+    PipelineLayoutDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages,
 };
 
-use crate::gpu::context::GpuContext;
+use crate::{compute::Precision, gpu::context::GpuContext};
+
+/// WORKGROUP_SIZE used when no override is given (the WGSL default).
+pub const DEFAULT_WORKGROUP_SIZE: u32 = 256;
+
+/// Environment variable consulted for the subgroup size the subgroup dot assumes when
+/// none is configured (see [`resolve_dot_subgroup_size`]); `0` selects the tree reduce.
+pub const SUBGROUP_SIZE_ENV_VAR: &str = "WGPU_SOLVER_SUBGROUP_SIZE";
 
 pub struct DotPartialsPipeline {
     pub pipeline: ComputePipeline,
     pub dot_partials_bind_group_layout: BindGroupLayout,
+    workgroup_size: u32,
+    accumulator: Precision,
+    subgroup_size: u32,
+}
+
+impl DotPartialsPipeline {
+    /// WORKGROUP_SIZE the pipeline was created with (default or override).
+    pub fn workgroup_size(&self) -> u32 {
+        self.workgroup_size
+    }
+
+    /// Precision the products and partial sums are formed in (the type of `partial`).
+    pub fn accumulator(&self) -> Precision {
+        self.accumulator
+    }
+
+    /// Subgroup size the subgroup reduce assumes; 0 for the shared-memory tree reduce.
+    pub fn subgroup_size(&self) -> u32 {
+        self.subgroup_size
+    }
+}
+
+/// `source` with its `alias accum = f32;` line switched to `accumulator` (a no-op for f32).
+/// Shared by the dot kernels, which are all written against that alias.
+pub fn with_accumulator(source: &str, accumulator: Precision) -> String {
+    source.replacen(
+        "alias accum = f32;",
+        &format!("alias accum = {};", accumulator.wgsl_type()),
+        1,
+    )
+}
+
+/// Check a WORKGROUP_SIZE override for `kernel`, a tree reduce over a workgroup array of
+/// one `shared_bytes` value per invocation (`array<accum, WORKGROUP_SIZE>`, sized by the
+/// override itself).
+///
+/// The size must be a power of two of at least 2 (each tree step halves the active
+/// range, so any other size drops partial sums; at 1 the shader's tree loop never ends
+/// and a reduce pass never shortens its input) and fit the device's `max_compute_workgroup_size_x`,
+/// `max_compute_invocations_per_workgroup` and `max_compute_workgroup_storage_size`
+/// (the array); `GpuContext` requests the adapter's own values for these.
+pub fn check_reduce_workgroup_size(
+    ctx: &GpuContext,
+    kernel: &str,
+    workgroup_size: u32,
+    shared_bytes: u32,
+) -> Result<(), String> {
+    if workgroup_size < 2 || !workgroup_size.is_power_of_two() {
+        return Err(format!(
+            "{kernel}: workgroup size must be a power of two >= 2, got {workgroup_size}"
+        ));
+    }
+    let limits = ctx.device.limits();
+    let max_size = limits
+        .max_compute_workgroup_size_x
+        .min(limits.max_compute_invocations_per_workgroup);
+    if workgroup_size > max_size {
+        return Err(format!(
+            "{kernel}: workgroup size {workgroup_size} exceeds the device limit of {max_size} invocations"
+        ));
+    }
+    let storage = workgroup_size as u64 * shared_bytes as u64;
+    if storage > limits.max_compute_workgroup_storage_size as u64 {
+        return Err(format!(
+            "{kernel}: workgroup size {workgroup_size} needs {storage} bytes of workgroup memory, \
+             the device allows {}",
+            limits.max_compute_workgroup_storage_size
+        ));
+    }
+    Ok(())
 }
 
 fn uniform_entry(binding: u32) -> BindGroupLayoutEntry {
@@ -39,11 +116,52 @@ fn storage_entry(binding: u32, read_only: bool) -> BindGroupLayoutEntry {
 }
 
 pub fn create_dot_partials_pipeline(ctx: &GpuContext) -> DotPartialsPipeline {
+    create_dot_partials_pipeline_with_workgroup_size(ctx, DEFAULT_WORKGROUP_SIZE)
+}
+
+/// Same as `create_dot_partials_pipeline`, overriding the WGSL `WORKGROUP_SIZE` constant.
+///
+/// Panics unless `workgroup_size` passes [`check_reduce_workgroup_size`].
+pub fn create_dot_partials_pipeline_with_workgroup_size(
+    ctx: &GpuContext,
+    workgroup_size: u32,
+) -> DotPartialsPipeline {
+    create_dot_partials_pipeline_with_accumulator(ctx, workgroup_size, Precision::F32)
+}
+
+/// Same as `create_dot_partials_pipeline_with_workgroup_size`, accumulating in
+/// `accumulator`: the inputs stay f32, `partial` holds that type. `Precision::F64` needs a
+/// device with SHADER_F64 (see [`Precision::required_features`]).
+pub fn create_dot_partials_pipeline_with_accumulator(
+    ctx: &GpuContext,
+    workgroup_size: u32,
+    accumulator: Precision,
+) -> DotPartialsPipeline {
+    try_create_dot_partials_pipeline_with_accumulator(ctx, workgroup_size, accumulator)
+        .unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Fallible [`create_dot_partials_pipeline_with_accumulator`]: a workgroup size the
+/// device cannot run (see [`check_reduce_workgroup_size`]) is an error.
+pub fn try_create_dot_partials_pipeline_with_accumulator(
+    ctx: &GpuContext,
+    workgroup_size: u32,
+    accumulator: Precision,
+) -> Result<DotPartialsPipeline, String> {
+    check_reduce_workgroup_size(
+        ctx,
+        "dot_partials",
+        workgroup_size,
+        accumulator.size_bytes(),
+    )?;
+
     let device = &ctx.device;
 
     let shader = device.create_shader_module(ShaderModuleDescriptor {
-        label: Some("dot_partials.wgsl"),
-        source: ShaderSource::Wgsl(include_str!("wgsl/dot_partials.wgsl").into()),
+        label: Some(&ctx.label("dot_partials.wgsl")),
+        source: ShaderSource::Wgsl(
+            with_accumulator(include_str!("wgsl/dot_partials.wgsl"), accumulator).into(),
+        ),
     });
 
     // WGSL bindings:
@@ -53,7 +171,7 @@ pub fn create_dot_partials_pipeline(ctx: &GpuContext) -> DotPartialsPipeline {
     //  @binding(3) partial (storage write)
     let dot_partials_bind_group_layout =
         device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: Some("dot_partials bgl0"),
+            label: Some(&ctx.label("dot_partials bgl0")),
             entries: &[
                 uniform_entry(0),
                 storage_entry(1, true),
@@ -63,24 +181,162 @@ pub fn create_dot_partials_pipeline(ctx: &GpuContext) -> DotPartialsPipeline {
         });
 
     let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-        label: Some("dot_partials pipeline layout"),
+        label: Some(&ctx.label("dot_partials pipeline layout")),
         bind_group_layouts: &[&dot_partials_bind_group_layout],
         immediate_size: 0,
     });
 
     let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
-        label: Some("dot_partials pipeline"),
+        label: Some(&ctx.label("dot_partials pipeline")),
         layout: Some(&pipeline_layout),
         module: &shader,
         entry_point: Some("compute_main"),
-        compilation_options: Default::default(),
+        compilation_options: PipelineCompilationOptions {
+            constants: &[("WORKGROUP_SIZE", workgroup_size as f64)],
+            ..Default::default()
+        },
         cache: None,
     });
 
-    DotPartialsPipeline {
+    Ok(DotPartialsPipeline {
         pipeline,
         dot_partials_bind_group_layout,
+        workgroup_size,
+        accumulator,
+        subgroup_size: 0,
+    })
+}
+
+/// Subgroup size for the partials pass, from (first match wins):
+///
+/// 1. `configured` (e.g. `PcgOptions::dot_subgroup_size`)
+/// 2. `env_value`, the contents of [`SUBGROUP_SIZE_ENV_VAR`]
+/// 3. the adapter's `(subgroup_min_size, subgroup_max_size)` when they agree; a range
+///    leaves the actual size unknown, so it gets the tree reduce
+///
+/// 0 means the tree reduce, and so does any automatic choice on a device without
+/// SUBGROUP. An explicit non-zero size on such a device, or one that is not a power of
+/// two, is an error.
+pub fn select_dot_subgroup_size(
+    configured: Option<u32>,
+    env_value: Option<&str>,
+    has_subgroups: bool,
+    reported: (u32, u32),
+) -> Result<u32, String> {
+    let explicit = match (configured, env_value) {
+        (Some(size), _) => Some(size),
+        (None, Some(value)) => Some(value.trim().parse::<u32>().map_err(|e| {
+            format!("{SUBGROUP_SIZE_ENV_VAR}={value:?}: expected a subgroup size or 0 ({e})")
+        })?),
+        (None, None) => None,
+    };
+
+    match explicit {
+        Some(0) => Ok(0),
+        Some(size) if !has_subgroups => Err(format!(
+            "subgroup size {size} requested, but the device has no SUBGROUP support (use 0 for the tree reduce)"
+        )),
+        Some(size) if !size.is_power_of_two() => Err(format!(
+            "subgroup size must be a power of two or 0, got {size}"
+        )),
+        Some(size) => Ok(size),
+        None => {
+            let (min, max) = reported;
+            Ok(if has_subgroups && min == max && min.is_power_of_two() {
+                min
+            } else {
+                0
+            })
+        }
+    }
+}
+
+/// [`select_dot_subgroup_size`] for `ctx`, reading [`SUBGROUP_SIZE_ENV_VAR`].
+pub fn resolve_dot_subgroup_size(ctx: &GpuContext, configured: Option<u32>) -> Result<u32, String> {
+    let env_value = std::env::var(SUBGROUP_SIZE_ENV_VAR).ok();
+    let info = ctx.adapter.get_info();
+    select_dot_subgroup_size(
+        configured,
+        env_value.as_deref(),
+        ctx.features.contains(Features::SUBGROUP),
+        (info.subgroup_min_size, info.subgroup_max_size),
+    )
+}
+
+/// Partials pass for f32 dots reduced with subgroup adds (`dot_partials_subgroup.wgsl`),
+/// assuming subgroups of `subgroup_size` invocations; same bindings and output as the
+/// tree variant, so it is a drop-in for it. `subgroup_size == 0` returns the tree variant.
+///
+/// Needs SUBGROUP; `subgroup_size` must be a power of two <= `workgroup_size`.
+pub fn create_dot_partials_subgroup_pipeline(
+    ctx: &GpuContext,
+    workgroup_size: u32,
+    subgroup_size: u32,
+) -> Result<DotPartialsPipeline, String> {
+    if subgroup_size == 0 {
+        return try_create_dot_partials_pipeline_with_accumulator(
+            ctx,
+            workgroup_size,
+            Precision::F32,
+        );
+    }
+    if !ctx.features.contains(Features::SUBGROUP) {
+        return Err("dot_partials: the subgroup reduce needs SUBGROUP support".into());
     }
+    check_reduce_workgroup_size(ctx, "dot_partials", workgroup_size, 4)?;
+    if !subgroup_size.is_power_of_two() || subgroup_size > workgroup_size {
+        return Err(format!(
+            "dot_partials: subgroup size {subgroup_size} must be a power of two <= workgroup size {workgroup_size}"
+        ));
+    }
+
+    let device = &ctx.device;
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(&ctx.label("dot_partials_subgroup.wgsl")),
+        source: ShaderSource::Wgsl(include_str!("wgsl/dot_partials_subgroup.wgsl").into()),
+    });
+
+    // Same bindings as dot_partials.wgsl.
+    let dot_partials_bind_group_layout =
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&ctx.label("dot_partials_subgroup bgl0")),
+            entries: &[
+                uniform_entry(0),
+                storage_entry(1, true),
+                storage_entry(2, true),
+                storage_entry(3, false),
+            ],
+        });
+
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&ctx.label("dot_partials_subgroup pipeline layout")),
+        bind_group_layouts: &[&dot_partials_bind_group_layout],
+        immediate_size: 0,
+    });
+
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(&ctx.label("dot_partials_subgroup pipeline")),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("compute_main"),
+        compilation_options: PipelineCompilationOptions {
+            constants: &[
+                ("WORKGROUP_SIZE", workgroup_size as f64),
+                ("SUBGROUP_SIZE", subgroup_size as f64),
+            ],
+            ..Default::default()
+        },
+        cache: None,
+    });
+
+    Ok(DotPartialsPipeline {
+        pipeline,
+        dot_partials_bind_group_layout,
+        workgroup_size,
+        accumulator: Precision::F32,
+        subgroup_size,
+    })
 }
 
 pub fn create_dot_partials_bind_group(
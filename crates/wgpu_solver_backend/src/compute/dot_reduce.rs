@@ -1,15 +1,57 @@
 use wgpu::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
     BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, ComputePipeline,
-    ComputePipelineDescriptor, Device, PipelineLayoutDescriptor, ShaderModuleDescriptor,
-    ShaderSource, ShaderStages,
+    ComputePipelineDescriptor, Device, PipelineCompilationOptions, PipelineLayoutDescriptor,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages,
 };
 
-use crate::gpu::context::GpuContext;
+use crate::{
+    compute::{
+        Precision,
+        dot_partials::{check_reduce_workgroup_size, with_accumulator},
+    },
+    gpu::context::GpuContext,
+};
+
+/// WORKGROUP_SIZE used when no override is given (the WGSL default).
+pub const DEFAULT_WORKGROUP_SIZE: u32 = 256;
+
+/// How `dot_reduce.wgsl` folds its inputs (the `REDUCE_MAX` override).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReduceOp {
+    #[default]
+    Sum,
+    Max,
+}
 
 pub struct DotReducePipeline {
     pub pipeline: ComputePipeline,
     pub dot_reduce_bind_group_layout: BindGroupLayout,
+    workgroup_size: u32,
+    op: ReduceOp,
+    accumulator: Precision,
+}
+
+impl DotReducePipeline {
+    /// WORKGROUP_SIZE the pipeline was created with (default or override).
+    pub fn workgroup_size(&self) -> u32 {
+        self.workgroup_size
+    }
+
+    pub fn op(&self) -> ReduceOp {
+        self.op
+    }
+
+    /// Element type of the input / output arrays.
+    pub fn accumulator(&self) -> Precision {
+        self.accumulator
+    }
+}
+
+/// `dot_narrow.wgsl`: output[0] = f32(input[0]) after an f64 reduce.
+pub struct DotNarrowPipeline {
+    pub pipeline: ComputePipeline,
+    pub dot_narrow_bind_group_layout: BindGroupLayout,
 }
 
 fn uniform_entry(binding: u32) -> BindGroupLayoutEntry {
@@ -39,11 +81,66 @@ fn storage_entry(binding: u32, read_only: bool) -> BindGroupLayoutEntry {
 }
 
 pub fn create_dot_reduce_pipeline(ctx: &GpuContext) -> DotReducePipeline {
+    create_dot_reduce_pipeline_with_workgroup_size(ctx, DEFAULT_WORKGROUP_SIZE)
+}
+
+/// Same as `create_dot_reduce_pipeline`, overriding the WGSL `WORKGROUP_SIZE` constant.
+///
+/// Panics unless `workgroup_size` passes
+/// [`check_reduce_workgroup_size`](crate::compute::dot_partials::check_reduce_workgroup_size).
+pub fn create_dot_reduce_pipeline_with_workgroup_size(
+    ctx: &GpuContext,
+    workgroup_size: u32,
+) -> DotReducePipeline {
+    create_reduce_pipeline(ctx, workgroup_size, ReduceOp::Sum)
+}
+
+/// Sum reduce over `accumulator` partials, the counterpart of
+/// `create_dot_partials_pipeline_with_accumulator`. `Precision::F64` needs SHADER_F64.
+pub fn create_dot_reduce_pipeline_with_accumulator(
+    ctx: &GpuContext,
+    workgroup_size: u32,
+    accumulator: Precision,
+) -> DotReducePipeline {
+    create_reduce_pipeline_impl(ctx, workgroup_size, ReduceOp::Sum, accumulator)
+        .unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Fallible [`create_dot_reduce_pipeline_with_accumulator`]: a workgroup size the device
+/// cannot run is an error.
+pub fn try_create_dot_reduce_pipeline_with_accumulator(
+    ctx: &GpuContext,
+    workgroup_size: u32,
+    accumulator: Precision,
+) -> Result<DotReducePipeline, String> {
+    create_reduce_pipeline_impl(ctx, workgroup_size, ReduceOp::Sum, accumulator)
+}
+
+/// `dot_reduce.wgsl` folding with `op` (sum for dots, max for e.g. max-norm metrics).
+pub fn create_reduce_pipeline(
+    ctx: &GpuContext,
+    workgroup_size: u32,
+    op: ReduceOp,
+) -> DotReducePipeline {
+    create_reduce_pipeline_impl(ctx, workgroup_size, op, Precision::F32)
+        .unwrap_or_else(|e| panic!("{e}"))
+}
+
+fn create_reduce_pipeline_impl(
+    ctx: &GpuContext,
+    workgroup_size: u32,
+    op: ReduceOp,
+    accumulator: Precision,
+) -> Result<DotReducePipeline, String> {
+    check_reduce_workgroup_size(ctx, "dot_reduce", workgroup_size, accumulator.size_bytes())?;
+
     let device = &ctx.device;
 
     let shader = device.create_shader_module(ShaderModuleDescriptor {
-        label: Some("dot_reduce.wgsl"),
-        source: ShaderSource::Wgsl(include_str!("wgsl/dot_reduce.wgsl").into()),
+        label: Some(&ctx.label("dot_reduce.wgsl")),
+        source: ShaderSource::Wgsl(
+            with_accumulator(include_str!("wgsl/dot_reduce.wgsl"), accumulator).into(),
+        ),
     });
 
     // WGSL bindings:
@@ -52,7 +149,7 @@ pub fn create_dot_reduce_pipeline(ctx: &GpuContext) -> DotReducePipeline {
     //  @binding(2) output (storage write)
     let dot_reduce_bind_group_layout =
         device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: Some("dot_reduce bgl0"),
+            label: Some(&ctx.label("dot_reduce bgl0")),
             entries: &[
                 uniform_entry(0),
                 storage_entry(1, true),
@@ -61,24 +158,33 @@ pub fn create_dot_reduce_pipeline(ctx: &GpuContext) -> DotReducePipeline {
         });
 
     let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-        label: Some("dot_reduce pipeline layout"),
+        label: Some(&ctx.label("dot_reduce pipeline layout")),
         bind_group_layouts: &[&dot_reduce_bind_group_layout],
         immediate_size: 0,
     });
 
     let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
-        label: Some("dot_reduce pipeline"),
+        label: Some(&ctx.label("dot_reduce pipeline")),
         layout: Some(&pipeline_layout),
         module: &shader,
         entry_point: Some("compute_main"),
-        compilation_options: Default::default(),
+        compilation_options: PipelineCompilationOptions {
+            constants: &[
+                ("WORKGROUP_SIZE", workgroup_size as f64),
+                ("REDUCE_MAX", f64::from(u8::from(op == ReduceOp::Max))),
+            ],
+            ..Default::default()
+        },
         cache: None,
     });
 
-    DotReducePipeline {
+    Ok(DotReducePipeline {
         pipeline,
         dot_reduce_bind_group_layout,
-    }
+        workgroup_size,
+        op,
+        accumulator,
+    })
 }
 
 pub fn create_dot_reduce_bind_group(
@@ -107,3 +213,84 @@ pub fn create_dot_reduce_bind_group(
         ],
     })
 }
+
+/// The f64 -> f32 narrowing pass ending an f64-accumulated dot. Needs SHADER_F64.
+pub fn create_dot_narrow_pipeline(ctx: &GpuContext) -> DotNarrowPipeline {
+    create_dot_narrow_pipeline_impl(ctx, Precision::F64, false)
+}
+
+/// The same single-invocation pass over an f32 sum, writing sqrt(max(sum, 0)): the last
+/// step of an L2 norm that stays on the GPU (`norms::L2Norm`). Same bindings.
+pub fn create_dot_sqrt_pipeline(ctx: &GpuContext) -> DotNarrowPipeline {
+    create_dot_narrow_pipeline_impl(ctx, Precision::F32, true)
+}
+
+fn create_dot_narrow_pipeline_impl(
+    ctx: &GpuContext,
+    accumulator: Precision,
+    take_sqrt: bool,
+) -> DotNarrowPipeline {
+    let device = &ctx.device;
+    let name = if take_sqrt { "dot_sqrt" } else { "dot_narrow" };
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(&ctx.label("dot_narrow.wgsl")),
+        source: ShaderSource::Wgsl(
+            with_accumulator(include_str!("wgsl/dot_narrow.wgsl"), accumulator).into(),
+        ),
+    });
+
+    // WGSL bindings:
+    //  @binding(0) input  (storage read, accumulator)
+    //  @binding(1) output (storage write, f32)
+    let dot_narrow_bind_group_layout =
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&ctx.label(&format!("{name} bgl0"))),
+            entries: &[storage_entry(0, true), storage_entry(1, false)],
+        });
+
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&ctx.label(&format!("{name} pipeline layout"))),
+        bind_group_layouts: &[&dot_narrow_bind_group_layout],
+        immediate_size: 0,
+    });
+
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(&ctx.label(&format!("{name} pipeline"))),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("compute_main"),
+        compilation_options: PipelineCompilationOptions {
+            constants: &[("TAKE_SQRT", if take_sqrt { 1.0 } else { 0.0 })],
+            ..Default::default()
+        },
+        cache: None,
+    });
+
+    DotNarrowPipeline {
+        pipeline,
+        dot_narrow_bind_group_layout,
+    }
+}
+
+pub fn create_dot_narrow_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    input_buffer: &Buffer,  // binding(0)
+    output_buffer: &Buffer, // binding(1)
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("dot_narrow bind group 0"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: input_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: output_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
@@ -1,18 +1,73 @@
 use std::cell::Cell;
 
 use bytemuck::{bytes_of, cast_slice};
-use wgpu::{Buffer, BufferDescriptor, BufferUsages, CommandEncoder, ComputePassDescriptor};
+use wgpu::{Buffer, BufferDescriptor, BufferUsages, CommandEncoder};
 
 use crate::{
     compute::{
+        Precision,
         dot_partials::{
-            DotPartialsPipeline, create_dot_partials_bind_group, create_dot_partials_pipeline,
+            DEFAULT_WORKGROUP_SIZE, DotPartialsPipeline, create_dot_partials_bind_group,
+            create_dot_partials_subgroup_pipeline, resolve_dot_subgroup_size,
+            try_create_dot_partials_pipeline_with_accumulator,
         },
-        dot_reduce::{DotReducePipeline, create_dot_reduce_bind_group, create_dot_reduce_pipeline},
+        dot_reduce::{
+            DotNarrowPipeline, DotReducePipeline, create_dot_narrow_bind_group,
+            create_dot_narrow_pipeline, create_dot_reduce_bind_group,
+            try_create_dot_reduce_pipeline_with_accumulator,
+        },
+        occupancy::KernelLaunch,
+    },
+    gpu::{
+        context::GpuContext,
+        readback::{read_mapped_buffer_to_vec, try_read_mapped_buffer_to_vec},
     },
-    gpu::{context::GpuContext, readback::read_mapped_buffer_to_vec},
 };
 
+/// Partials count above which the reduce switches from the iterative tree to a
+/// two-level (double-dispatch) reduce.
+///
+/// With the default reduce workgroup size (256) the tree needs ceil(log256(len))
+/// passes, so up to 256*256 partials it never issues more than two dispatches and
+/// its passes are as wide as possible. Past that point every extra tree level costs
+/// another pass; the two-level path always finishes in exactly two: a grid-stride
+/// pass folding everything into <= 256 partials, then a single-workgroup pass.
+pub const DEFAULT_TWO_LEVEL_REDUCE_THRESHOLD: u32 = 256 * 256;
+
+/// How many mappable staging buffers the scalar readback cycles through.
+///
+/// - `Single`: one buffer. Lowest memory; the next copy into it must wait until the
+///   previous readback has been mapped, read and unmapped.
+/// - `Double`: two buffers used alternately, so the copy for readback k+1 never targets
+///   the buffer still mapped (or being unmapped) for readback k, at twice the staging
+///   memory.
+///
+/// `Double` currently gives no latency benefit: the PCG loop blocks on readback k
+/// (map, read, unmap) before it encodes iteration k+1, so the two never overlap. It only
+/// pays off for a caller that submits readback k+1 before reading back k. The scalar
+/// staging buffer is small (scalar_results_len f32s), so its memory cost only matters on
+/// very constrained devices.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReadbackBuffering {
+    #[default]
+    Single,
+    Double,
+}
+
+/// The f64-accumulating dot passes (see [`DotScalarExecutor::set_dot_precision`]).
+struct F64Dots {
+    dot_partials_pipeline: DotPartialsPipeline,
+    dot_reduce_pipeline: DotReducePipeline,
+    dot_narrow_pipeline: DotNarrowPipeline,
+
+    // f64 scratch buffers for the reduction ping-pong (max_partials each)
+    input_buffer: Buffer,
+    output_buffer: Buffer,
+
+    // f32 result of the narrowing pass, copied into the scalar slot
+    narrow_buffer: Buffer,
+}
+
 pub struct DotScalarExecutor {
     dot_partials_pipeline: DotPartialsPipeline,
     dot_reduce_pipeline: DotReducePipeline,
@@ -27,8 +82,10 @@ pub struct DotScalarExecutor {
     scalar_results_buffer: Buffer,
     scalar_results_len: usize,
 
-    // Mappable readback buffer (small)
+    // Mappable readback buffer (small); a second one with ReadbackBuffering::Double
     scalar_readback_buffer: Buffer,
+    scalar_readback_buffer_second: Option<Buffer>,
+    readback_cursor: Cell<usize>,
 
     // Uniform pools
     dot_partials_params_buffers: Vec<Buffer>,
@@ -36,30 +93,78 @@ pub struct DotScalarExecutor {
 
     dot_reduce_params_buffers: Vec<Buffer>,
     dot_reduce_params_cursor: Cell<usize>,
+
+    two_level_reduce_threshold: u32,
+
+    // Some(..) while the dots accumulate in f64
+    f64_dots: Option<F64Dots>,
 }
 
 impl DotScalarExecutor {
     pub fn create(ctx: &GpuContext, n_max: usize, scalar_results_len: usize) -> Self {
-        let device = &ctx.device;
+        Self::create_with_workgroup_sizes(
+            ctx,
+            n_max,
+            scalar_results_len,
+            DEFAULT_WORKGROUP_SIZE,
+            DEFAULT_WORKGROUP_SIZE,
+        )
+    }
 
-        let dot_partials_pipeline = create_dot_partials_pipeline(ctx);
-        let dot_reduce_pipeline = create_dot_reduce_pipeline(ctx);
+    /// Same as `create`, with WORKGROUP_SIZE overrides for the partials and reduce
+    /// pipelines (powers of two; see `create_dot_partials_pipeline_with_workgroup_size`).
+    pub fn create_with_workgroup_sizes(
+        ctx: &GpuContext,
+        n_max: usize,
+        scalar_results_len: usize,
+        dot_partials_workgroup_size: u32,
+        dot_reduce_workgroup_size: u32,
+    ) -> Self {
+        Self::try_create_with_workgroup_sizes(
+            ctx,
+            n_max,
+            scalar_results_len,
+            dot_partials_workgroup_size,
+            dot_reduce_workgroup_size,
+        )
+        .unwrap_or_else(|e| panic!("{e}"))
+    }
 
-        let workgroup_size = 256usize;
+    /// Fallible `create_with_workgroup_sizes`: a size the device cannot run (see
+    /// `dot_partials::check_reduce_workgroup_size`) is an error.
+    pub fn try_create_with_workgroup_sizes(
+        ctx: &GpuContext,
+        n_max: usize,
+        scalar_results_len: usize,
+        dot_partials_workgroup_size: u32,
+        dot_reduce_workgroup_size: u32,
+    ) -> Result<Self, String> {
+        let dot_partials_pipeline = try_create_dot_partials_pipeline_with_accumulator(
+            ctx,
+            dot_partials_workgroup_size,
+            Precision::F32,
+        )?;
+        let dot_reduce_pipeline = try_create_dot_reduce_pipeline_with_accumulator(
+            ctx,
+            dot_reduce_workgroup_size,
+            Precision::F32,
+        )?;
+
+        let workgroup_size = dot_partials_workgroup_size as usize;
         let max_partials = n_max.div_ceil(workgroup_size);
 
         // Scratch buffers: f32 arrays of length max_partials
         let scratch_bytes = (max_partials * std::mem::size_of::<f32>()) as u64;
 
-        let input_buffer = device.create_buffer(&BufferDescriptor {
-            label: Some("dot scratch input"),
+        let input_buffer = ctx.create_buffer(&BufferDescriptor {
+            label: Some(&ctx.label("dot scratch input")),
             size: scratch_bytes,
             usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
-        let output_buffer = device.create_buffer(&BufferDescriptor {
-            label: Some("dot scratch output"),
+        let output_buffer = ctx.create_buffer(&BufferDescriptor {
+            label: Some(&ctx.label("dot scratch output")),
             size: scratch_bytes,
             usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
             mapped_at_creation: false,
@@ -68,16 +173,16 @@ impl DotScalarExecutor {
         // Scalar results GPU buffer (f32[scalar_results_len])
         let scalar_bytes = (scalar_results_len * std::mem::size_of::<f32>()) as u64;
 
-        let scalar_results_buffer = device.create_buffer(&BufferDescriptor {
-            label: Some("scalar results buffer"),
+        let scalar_results_buffer = ctx.create_buffer(&BufferDescriptor {
+            label: Some(&ctx.label("scalar results buffer")),
             size: scalar_bytes,
             usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
         // Mappable readback buffer
-        let scalar_readback_buffer = device.create_buffer(&BufferDescriptor {
-            label: Some("scalar readback buffer"),
+        let scalar_readback_buffer = ctx.create_buffer(&BufferDescriptor {
+            label: Some(&ctx.label("scalar readback buffer")),
             size: scalar_bytes,
             usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
             mapped_at_creation: false,
@@ -88,8 +193,8 @@ impl DotScalarExecutor {
 
         let mut dot_partials_params_buffers = Vec::with_capacity(pool_size);
         for i in 0..pool_size {
-            dot_partials_params_buffers.push(device.create_buffer(&BufferDescriptor {
-                label: Some(&format!("dot_partials params {}", i)),
+            dot_partials_params_buffers.push(ctx.create_buffer(&BufferDescriptor {
+                label: Some(&ctx.label(&format!("dot_partials params {}", i))),
                 size: 16, // [n,0,0,0] as u32
                 usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
                 mapped_at_creation: false,
@@ -98,15 +203,15 @@ impl DotScalarExecutor {
 
         let mut dot_reduce_params_buffers = Vec::with_capacity(pool_size);
         for i in 0..pool_size {
-            dot_reduce_params_buffers.push(device.create_buffer(&BufferDescriptor {
-                label: Some(&format!("dot_reduce params {}", i)),
+            dot_reduce_params_buffers.push(ctx.create_buffer(&BufferDescriptor {
+                label: Some(&ctx.label(&format!("dot_reduce params {}", i))),
                 size: 16, // [current_len,0,0,0]
                 usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
                 mapped_at_creation: false,
             }));
         }
 
-        Self {
+        Ok(Self {
             dot_partials_pipeline,
             dot_reduce_pipeline,
             input_buffer,
@@ -115,17 +220,240 @@ impl DotScalarExecutor {
             scalar_results_buffer,
             scalar_results_len,
             scalar_readback_buffer,
+            scalar_readback_buffer_second: None,
+            readback_cursor: Cell::new(0),
             dot_partials_params_buffers,
             dot_partials_params_cursor: Cell::new(0),
             dot_reduce_params_buffers,
             dot_reduce_params_cursor: Cell::new(0),
+            two_level_reduce_threshold: DEFAULT_TWO_LEVEL_REDUCE_THRESHOLD,
+            f64_dots: None,
+        })
+    }
+
+    /// Override the partials count above which the two-level reduce is used.
+    ///
+    /// `u32::MAX` forces the iterative tree; `1` forces the two-level reduce for
+    /// every input that needs reducing at all.
+    pub fn set_two_level_reduce_threshold(&mut self, threshold: u32) {
+        self.two_level_reduce_threshold = threshold;
+    }
+
+    pub fn two_level_reduce_threshold(&self) -> u32 {
+        self.two_level_reduce_threshold
+    }
+
+    /// Switch between one and two scalar staging buffers (see [`ReadbackBuffering`]).
+    /// Call between iterations, never between an encoded copy and its readback.
+    pub fn set_readback_buffering(&mut self, ctx: &GpuContext, buffering: ReadbackBuffering) {
+        self.scalar_readback_buffer_second = match buffering {
+            ReadbackBuffering::Single => None,
+            ReadbackBuffering::Double => Some(ctx.create_buffer(&BufferDescriptor {
+                label: Some(&ctx.label("scalar readback buffer (second)")),
+                size: (self.scalar_results_len * std::mem::size_of::<f32>()) as u64,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })),
+        };
+        self.readback_cursor.set(0);
+    }
+
+    pub fn readback_buffering(&self) -> ReadbackBuffering {
+        match self.scalar_readback_buffer_second {
+            None => ReadbackBuffering::Single,
+            Some(_) => ReadbackBuffering::Double,
+        }
+    }
+
+    /// Accumulate the dot products in `precision` (see `PcgOptions::dot_precision`).
+    ///
+    /// The inputs and the stored scalar stay f32 either way. `Precision::F64` builds f64
+    /// variants of the partials/reduce pipelines plus a narrowing pass (same workgroup
+    /// sizes, so `dispatches_per_dot` only grows by that one pass) and fails when the
+    /// device lacks SHADER_F64. Call between iterations, like `set_readback_buffering`.
+    pub fn set_dot_precision(
+        &mut self,
+        ctx: &GpuContext,
+        precision: Precision,
+    ) -> Result<(), String> {
+        if !precision.supported_by(ctx) {
+            // Only F64 has required features.
+            return Err(format!(
+                "DotScalarExecutor: {} dot accumulation needs SHADER_F64, which the device does not support",
+                precision.wgsl_type()
+            ));
         }
+
+        self.f64_dots = match precision {
+            Precision::F32 => None,
+            Precision::F64 => {
+                let scratch = |label: &str, size: u64| {
+                    ctx.create_buffer(&BufferDescriptor {
+                        label: Some(&ctx.label(label)),
+                        size,
+                        usage: BufferUsages::STORAGE
+                            | BufferUsages::COPY_SRC
+                            | BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                    })
+                };
+                let scratch_bytes = (self.max_partials * std::mem::size_of::<f64>()) as u64;
+
+                Some(F64Dots {
+                    dot_partials_pipeline: try_create_dot_partials_pipeline_with_accumulator(
+                        ctx,
+                        self.dot_partials_workgroup_size(),
+                        Precision::F64,
+                    )?,
+                    dot_reduce_pipeline: try_create_dot_reduce_pipeline_with_accumulator(
+                        ctx,
+                        self.dot_reduce_workgroup_size(),
+                        Precision::F64,
+                    )?,
+                    dot_narrow_pipeline: create_dot_narrow_pipeline(ctx),
+                    input_buffer: scratch("dot scratch input (f64)", scratch_bytes),
+                    output_buffer: scratch("dot scratch output (f64)", scratch_bytes),
+                    narrow_buffer: scratch(
+                        "dot narrowed scalar",
+                        std::mem::size_of::<f32>() as u64,
+                    ),
+                })
+            }
+        };
+        Ok(())
+    }
+
+    pub fn dot_precision(&self) -> Precision {
+        match self.f64_dots {
+            None => Precision::F32,
+            Some(_) => Precision::F64,
+        }
+    }
+
+    /// Reduce the f32 partials pass with subgroup adds assuming `configured` invocations
+    /// per subgroup, 0 for the shared-memory tree, or `None` for the environment /
+    /// adapter default (see `dot_partials::resolve_dot_subgroup_size`). The reduce
+    /// passes and the f64 path (`set_dot_precision`) always use the tree.
+    pub fn set_dot_subgroup_size(
+        &mut self,
+        ctx: &GpuContext,
+        configured: Option<u32>,
+    ) -> Result<(), String> {
+        let subgroup_size = resolve_dot_subgroup_size(ctx, configured)?;
+        self.dot_partials_pipeline = create_dot_partials_subgroup_pipeline(
+            ctx,
+            self.dot_partials_workgroup_size(),
+            subgroup_size,
+        )?;
+        Ok(())
+    }
+
+    /// Subgroup size the f32 partials pass assumes; 0 for the tree reduce.
+    pub fn dot_subgroup_size(&self) -> u32 {
+        self.dot_partials_pipeline.subgroup_size()
+    }
+
+    /// Staging buffer the next copy/readback pair uses.
+    fn current_readback_buffer(&self) -> &Buffer {
+        match (
+            &self.scalar_readback_buffer_second,
+            self.readback_cursor.get(),
+        ) {
+            (Some(second), 1) => second,
+            _ => &self.scalar_readback_buffer,
+        }
+    }
+
+    /// After a readback: move on to the other staging buffer (no-op when single-buffered).
+    fn advance_readback_buffer(&self) {
+        if self.scalar_readback_buffer_second.is_some() {
+            self.readback_cursor.set(1 - self.readback_cursor.get());
+        }
+    }
+
+    /// WORKGROUP_SIZE in effect for the partials pass.
+    pub fn dot_partials_workgroup_size(&self) -> u32 {
+        self.dot_partials_pipeline.workgroup_size()
+    }
+
+    /// WORKGROUP_SIZE in effect for the reduce passes.
+    pub fn dot_reduce_workgroup_size(&self) -> u32 {
+        self.dot_reduce_pipeline.workgroup_size()
+    }
+
+    /// Number of partials the first pass produces for length `n`.
+    fn partials_len(&self, n: u32) -> u32 {
+        n.div_ceil(self.dot_partials_workgroup_size())
+    }
+
+    /// Number of workgroups (= output length) for one reduce pass over `current_len` partials.
+    fn reduce_out_len(&self, current_len: u32) -> u32 {
+        let workgroup_size = self.dot_reduce_workgroup_size();
+        let tree_len = current_len.div_ceil(workgroup_size);
+        if current_len > self.two_level_reduce_threshold {
+            // Grid-stride pass: never more than one workgroup's worth of outputs,
+            // so the next pass finishes in a single workgroup.
+            tree_len.min(workgroup_size)
+        } else {
+            tree_len
+        }
+    }
+
+    /// Number of compute dispatches one `encode_dot_scalar_into` records for length `n`
+    /// (partials pass + reduce passes, + the narrowing pass with f64 accumulation; 0 for
+    /// n == 0, which is a plain buffer write).
+    pub fn dispatches_per_dot(&self, n: u32) -> u32 {
+        if n == 0 {
+            return 0;
+        }
+
+        let mut dispatches = 1;
+        let mut current_len = self.partials_len(n);
+        while current_len > 1 {
+            current_len = self.reduce_out_len(current_len);
+            dispatches += 1;
+        }
+        if self.f64_dots.is_some() {
+            dispatches += 1;
+        }
+        dispatches
+    }
+
+    /// Launch shape of every pass of one `encode_dot_scalar_into` for length `n`, in
+    /// recording order (one entry per pass, `dispatches_per_dot(n)` entries).
+    pub fn dot_kernel_launches(&self, n: u32) -> Vec<KernelLaunch> {
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let mut current_len = self.partials_len(n);
+        let mut launches = vec![KernelLaunch::new(
+            "dot_partials",
+            self.dot_partials_workgroup_size(),
+            current_len,
+        )];
+        while current_len > 1 {
+            current_len = self.reduce_out_len(current_len);
+            launches.push(KernelLaunch::new(
+                "dot_reduce",
+                self.dot_reduce_workgroup_size(),
+                current_len,
+            ));
+        }
+        if self.f64_dots.is_some() {
+            launches.push(KernelLaunch::new("dot_narrow", 1, 1));
+        }
+        launches
     }
 
     pub fn scalar_results_buffer(&self) -> &Buffer {
         &self.scalar_results_buffer
     }
 
+    pub fn scalar_results_len(&self) -> usize {
+        self.scalar_results_len
+    }
+
     fn next_dot_partials_params_buffer(&self) -> &Buffer {
         let i = self.dot_partials_params_cursor.get();
         self.dot_partials_params_cursor
@@ -156,56 +484,100 @@ impl DotScalarExecutor {
         b_buffer: &Buffer,
         n: u32,
         out_index: u32,
+    ) {
+        self.encode_dot_into(ctx, encoder, a_buffer, b_buffer, n, false, out_index);
+    }
+
+    /// Same as `encode_dot_scalar_into` for two length-n vectors stored interleaved in one
+    /// buffer, `ab = [a0, b0, a1, b1, ...]` (2n floats): stores sum_i ab[2i] * ab[2i+1].
+    pub fn encode_dot_scalar_interleaved_into(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        ab_buffer: &Buffer,
+        n: u32,
+        out_index: u32,
+    ) {
+        // The kernel ignores binding 2 in interleaved mode; bind the same buffer there.
+        self.encode_dot_into(ctx, encoder, ab_buffer, ab_buffer, n, true, out_index);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn encode_dot_into(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        a_buffer: &Buffer,
+        b_buffer: &Buffer,
+        n: u32,
+        interleaved: bool,
+        out_index: u32,
     ) {
         if (out_index as usize) >= self.scalar_results_len {
             panic!("DotScalarExecutor: out_index out of range");
         }
+        if (self.partials_len(n) as usize) > self.max_partials {
+            panic!("DotScalarExecutor: n exceeds n_max given at create()");
+        }
 
         // n==0: just write 0.0 into the output slot.
         if n == 0 {
             let zero: f32 = 0.0;
             let offset = (out_index as u64) * 4;
-            ctx.queue.write_buffer(
-                &self.scalar_results_buffer,
-                offset,
-                bytes_of(&zero),
-            );
+            ctx.queue
+                .write_buffer(&self.scalar_results_buffer, offset, bytes_of(&zero));
             return;
         }
 
+        // f32 or f64 accumulation: same passes, different pipelines and scratch.
+        let (dot_partials_pipeline, dot_reduce_pipeline, input_buffer, output_buffer) =
+            match &self.f64_dots {
+                None => (
+                    &self.dot_partials_pipeline,
+                    &self.dot_reduce_pipeline,
+                    &self.input_buffer,
+                    &self.output_buffer,
+                ),
+                Some(f64_dots) => (
+                    &f64_dots.dot_partials_pipeline,
+                    &f64_dots.dot_reduce_pipeline,
+                    &f64_dots.input_buffer,
+                    &f64_dots.output_buffer,
+                ),
+            };
+
         // ---- Pass 1: partial sums into input_buffer ----
         let dot_partials_params = self.next_dot_partials_params_buffer();
-        let words: [u32; 4] = [n, 0, 0, 0];
+        let words: [u32; 4] = [n, interleaved as u32, 0, 0];
         ctx.queue
             .write_buffer(dot_partials_params, 0, cast_slice(&words));
 
         let dot_partials_bg = create_dot_partials_bind_group(
             &ctx.device,
-            &self.dot_partials_pipeline.dot_partials_bind_group_layout,
+            &dot_partials_pipeline.dot_partials_bind_group_layout,
             dot_partials_params,
             a_buffer,
             b_buffer,
-            &self.input_buffer,
+            input_buffer,
         );
 
         {
-            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
-                label: Some("dot_partials pass"),
-                timestamp_writes: None,
-            });
-            pass.set_pipeline(&self.dot_partials_pipeline.pipeline);
+            let mut pass = ctx.begin_compute_pass(encoder, "dot_partials pass");
+            pass.set_pipeline(&dot_partials_pipeline.pipeline);
             pass.set_bind_group(0, &dot_partials_bg, &[]);
 
-            let groups = n.div_ceil(256u32);
+            let groups = self.partials_len(n);
             pass.dispatch_workgroups(groups, 1, 1);
         }
 
         // Number of partials produced by pass 1
-        let mut current_len: u32 = n.div_ceil(256u32);
+        let mut current_len: u32 = self.partials_len(n);
 
         // ---- Pass 2..k: reduce partials until length=1 ----
-        let mut current_input = &self.input_buffer;
-        let mut current_output = &self.output_buffer;
+        // Small inputs walk the tree one level per pass; large ones take the
+        // two-level path (see DEFAULT_TWO_LEVEL_REDUCE_THRESHOLD).
+        let mut current_input = input_buffer;
+        let mut current_output = output_buffer;
 
         while current_len > 1 {
             let reduce_params = self.next_dot_reduce_params_buffer();
@@ -214,20 +586,17 @@ impl DotScalarExecutor {
 
             let bg = create_dot_reduce_bind_group(
                 &ctx.device,
-                &self.dot_reduce_pipeline.dot_reduce_bind_group_layout,
+                &dot_reduce_pipeline.dot_reduce_bind_group_layout,
                 reduce_params,
                 current_input,
                 current_output,
             );
 
-            let out_len = current_len.div_ceil(256u32);
+            let out_len = self.reduce_out_len(current_len);
 
             {
-                let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
-                    label: Some("dot_reduce pass"),
-                    timestamp_writes: None,
-                });
-                pass.set_pipeline(&self.dot_reduce_pipeline.pipeline);
+                let mut pass = ctx.begin_compute_pass(encoder, "dot_reduce pass");
+                pass.set_pipeline(&dot_reduce_pipeline.pipeline);
                 pass.set_bind_group(0, &bg, &[]);
                 pass.dispatch_workgroups(out_len, 1, 1);
             }
@@ -236,9 +605,28 @@ impl DotScalarExecutor {
             std::mem::swap(&mut current_input, &mut current_output);
         }
 
+        // ---- f64 only: round the sum to f32 into narrow_buffer ----
+        if let Some(f64_dots) = &self.f64_dots {
+            let bg = create_dot_narrow_bind_group(
+                &ctx.device,
+                &f64_dots.dot_narrow_pipeline.dot_narrow_bind_group_layout,
+                current_input,
+                &f64_dots.narrow_buffer,
+            );
+
+            {
+                let mut pass = ctx.begin_compute_pass(encoder, "dot_narrow pass");
+                pass.set_pipeline(&f64_dots.dot_narrow_pipeline.pipeline);
+                pass.set_bind_group(0, &bg, &[]);
+                pass.dispatch_workgroups(1, 1, 1);
+            }
+
+            current_input = &f64_dots.narrow_buffer;
+        }
+
         // ---- Copy final scalar into scalar_results_buffer[out_index] ----
         encoder.copy_buffer_to_buffer(
-            current_input, // scalar at offset 0
+            current_input, // f32 scalar at offset 0
             0,
             &self.scalar_results_buffer,
             (out_index as u64) * 4,
@@ -253,7 +641,7 @@ impl DotScalarExecutor {
         encoder.copy_buffer_to_buffer(
             &self.scalar_results_buffer,
             0,
-            &self.scalar_readback_buffer,
+            self.current_readback_buffer(),
             0,
             bytes,
         );
@@ -261,11 +649,25 @@ impl DotScalarExecutor {
 
     /// After submit, map and read back all scalar slots.
     pub async fn readback_scalar_results(&self, ctx: &GpuContext) -> Vec<f32> {
-        read_mapped_buffer_to_vec::<f32>(
+        let out = read_mapped_buffer_to_vec::<f32>(
+            &ctx.device,
+            self.current_readback_buffer(),
+            self.scalar_results_len,
+        )
+        .await;
+        self.advance_readback_buffer();
+        out
+    }
+
+    /// Fallible [`DotScalarExecutor::readback_scalar_results`] (errors e.g. on device loss).
+    pub async fn try_readback_scalar_results(&self, ctx: &GpuContext) -> Result<Vec<f32>, String> {
+        let out = try_read_mapped_buffer_to_vec::<f32>(
             &ctx.device,
-            &self.scalar_readback_buffer,
+            self.current_readback_buffer(),
             self.scalar_results_len,
         )
-        .await
+        .await?;
+        self.advance_readback_buffer();
+        Ok(out)
     }
 }
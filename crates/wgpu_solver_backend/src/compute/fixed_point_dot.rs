@@ -0,0 +1,160 @@
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, ComputePipeline,
+    ComputePipelineDescriptor, Device, PipelineLayoutDescriptor, ShaderModuleDescriptor,
+    ShaderSource, ShaderStages,
+};
+
+use crate::gpu::context::GpuContext;
+
+/// Workgroup size of fixed_point_dot.wgsl (one invocation per entry).
+pub const FIXED_POINT_DOT_WORKGROUP_SIZE: u32 = 256;
+
+/// Fractional bits used when the caller has no better choice: products are quantized
+/// to multiples of 2^-20 (about 1e-6) and must stay within ±2^11 = ±2048.
+pub const DEFAULT_FIXED_POINT_FRAC_BITS: u32 = 20;
+
+/// Largest accepted `frac_bits` (products then have to stay within ±1).
+pub const MAX_FIXED_POINT_FRAC_BITS: u32 = 30;
+
+/// u32 words of the accumulator buffer: [lo, hi, saturated, unused].
+pub const FIXED_POINT_ACCUM_WORDS: usize = 4;
+
+pub struct FixedPointDotPipeline {
+    pub pipeline: ComputePipeline,
+    pub fixed_point_dot_bind_group_layout: BindGroupLayout,
+}
+
+/// 2^frac_bits as f32 (exact for every accepted `frac_bits`).
+pub fn fixed_point_scale(frac_bits: u32) -> f32 {
+    (1u64 << frac_bits) as f32
+}
+
+pub(crate) fn check_frac_bits(frac_bits: u32) -> Result<(), String> {
+    if frac_bits > MAX_FIXED_POINT_FRAC_BITS {
+        return Err(format!(
+            "fixed-point dot: frac_bits = {frac_bits} exceeds the maximum of {MAX_FIXED_POINT_FRAC_BITS}"
+        ));
+    }
+    Ok(())
+}
+
+/// The f32 value of an accumulated fixed-point sum: sum / 2^frac_bits, rounded once.
+///
+/// Fails when any product was clamped (`saturated` > 0): the sum is then no longer a
+/// faithful approximation of the dot product.
+pub fn fixed_point_sum_to_f32(sum: i64, saturated: u32, frac_bits: u32) -> Result<f32, String> {
+    if saturated > 0 {
+        return Err(format!(
+            "fixed-point dot: {saturated} product(s) outside ±2^{} (frac_bits = {frac_bits}) were clamped; \
+             use fewer fractional bits or scale the inputs",
+            31 - frac_bits
+        ));
+    }
+    Ok((sum as f64 / (1u64 << frac_bits) as f64) as f32)
+}
+
+fn create_uniform_entry(binding: u32) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn create_storage_entry(binding: u32, is_read_only: bool) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Storage {
+                read_only: is_read_only,
+            },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+pub fn create_fixed_point_dot_pipeline(ctx: &GpuContext) -> FixedPointDotPipeline {
+    let device = &ctx.device;
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(&ctx.label("fixed_point_dot.wgsl")),
+        source: ShaderSource::Wgsl(include_str!("wgsl/fixed_point_dot.wgsl").into()),
+    });
+
+    // Bind group layout (group 0), matches fixed_point_dot.wgsl:
+    //  0: params (uniform)
+    //  1: a (RO storage)
+    //  2: b (RO storage)
+    //  3: accum (RW storage, atomics)
+    let fixed_point_dot_bind_group_layout =
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&ctx.label("fixed_point_dot bgl0")),
+            entries: &[
+                create_uniform_entry(0),
+                create_storage_entry(1, true),
+                create_storage_entry(2, true),
+                create_storage_entry(3, false),
+            ],
+        });
+
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&ctx.label("fixed_point_dot pipeline layout")),
+        bind_group_layouts: &[&fixed_point_dot_bind_group_layout],
+        immediate_size: 0,
+    });
+
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(&ctx.label("fixed_point_dot pipeline")),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("compute_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    FixedPointDotPipeline {
+        pipeline,
+        fixed_point_dot_bind_group_layout,
+    }
+}
+
+pub fn create_fixed_point_dot_bind_group(
+    device: &Device,
+    fixed_point_dot_bind_group_layout: &BindGroupLayout,
+    params_buffer: &Buffer,
+    a_buffer: &Buffer,
+    b_buffer: &Buffer,
+    accum_buffer: &Buffer,
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("fixed_point_dot bind group 0"),
+        layout: fixed_point_dot_bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: a_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: b_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 3,
+                resource: accum_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
@@ -0,0 +1,151 @@
+use wgpu::util::BufferInitDescriptor;
+use wgpu::{Buffer, BufferUsages, CommandEncoder, CommandEncoderDescriptor, ComputePassDescriptor};
+
+use crate::compute::fixed_point_dot::{
+    FIXED_POINT_ACCUM_WORDS, FIXED_POINT_DOT_WORKGROUP_SIZE, FixedPointDotPipeline,
+    check_frac_bits, create_fixed_point_dot_bind_group, create_fixed_point_dot_pipeline,
+    fixed_point_scale, fixed_point_sum_to_f32,
+};
+use crate::gpu::{context::GpuContext, readback::try_readback_to_vec};
+
+/// FixedPointDotExecutor (experimental)
+///
+/// Dot product a·b of two length-n GPU vectors whose result has the same bits on every
+/// device and for every workgroup schedule. Each product a[i] * b[i] is computed in f32,
+/// scaled by 2^frac_bits and rounded to an i32; the i32s are summed exactly into a 64-bit
+/// integer with u32 atomics (fixed_point_dot.wgsl). Integer addition does not depend on
+/// order, so neither does the result. `reference::fixed_point_dot` is the bit-identical
+/// host version.
+///
+/// Caveats:
+///   - approximate: every product is rounded to a multiple of 2^-frac_bits, so the
+///     result is NOT the float dot product; the absolute error is up to
+///     n * 2^-(frac_bits + 1), and small products vanish entirely.
+///   - range: each product must satisfy |a[i] * b[i]| < 2^(31 - frac_bits). Larger ones
+///     (and inf) are clamped and `compute` returns an error. NaN inputs are unspecified:
+///     WGSL lets implementations assume there are none. The 64-bit sum itself cannot
+///     overflow for any u32 n.
+///   - reproducibility rests on the f32 multiply being correctly rounded (IEEE 754),
+///     which WGSL requires; subnormal products may be flushed, but those round to 0 at
+///     any accepted `frac_bits` anyway.
+///
+/// Owns:
+///   - `params_buffer`: uniform [n, scale (f32 bits), 0, 0]
+///   - `accum_buffer`: [lo, hi, saturated, unused] u32, cleared by every `encode`
+///
+/// Dispatch:
+///   - one invocation per entry (WGSL workgroup_size = 256),
+///     so dispatch ceil(n / 256) workgroups in X.
+pub struct FixedPointDotExecutor {
+    n: u32,
+    frac_bits: u32,
+
+    pipeline: FixedPointDotPipeline,
+
+    params_buffer: Buffer,
+    accum_buffer: Buffer,
+}
+
+impl FixedPointDotExecutor {
+    /// Fails when `frac_bits` exceeds `MAX_FIXED_POINT_FRAC_BITS`.
+    pub fn create(ctx: &GpuContext, n: u32, frac_bits: u32) -> Result<Self, String> {
+        check_frac_bits(frac_bits)?;
+
+        let pipeline = create_fixed_point_dot_pipeline(ctx);
+
+        let params_words: [u32; 4] = [n, fixed_point_scale(frac_bits).to_bits(), 0, 0];
+        let params_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("fixed_point_dot params")),
+            contents: bytemuck::cast_slice(&params_words),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let accum_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("fixed_point_dot accum")),
+            contents: bytemuck::cast_slice(&[0u32; FIXED_POINT_ACCUM_WORDS]),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+        });
+
+        Ok(Self {
+            n,
+            frac_bits,
+            pipeline,
+            params_buffer,
+            accum_buffer,
+        })
+    }
+
+    pub fn n(&self) -> u32 {
+        self.n
+    }
+
+    pub fn frac_bits(&self) -> u32 {
+        self.frac_bits
+    }
+
+    /// The accumulator the last `encode` wrote: [lo, hi, saturated, unused] u32
+    /// (STORAGE | COPY_SRC). Decode with `fixed_point_sum_to_f32`.
+    pub fn accum_buffer(&self) -> &Buffer {
+        &self.accum_buffer
+    }
+
+    /// Encode accum = fixed-point a·b over the first n entries. Does NOT submit.
+    ///
+    /// Panics if `a` or `b` holds fewer than n f32.
+    pub fn encode(&self, ctx: &GpuContext, encoder: &mut CommandEncoder, a: &Buffer, b: &Buffer) {
+        for (name, buffer) in [("a", a), ("b", b)] {
+            assert!(
+                buffer.size() >= self.n as u64 * 4,
+                "FixedPointDotExecutor: {name} holds fewer than n = {} f32",
+                self.n
+            );
+        }
+
+        encoder.clear_buffer(&self.accum_buffer, 0, None);
+
+        // Bind group depends on per-call buffers a/b.
+        let bind_group = create_fixed_point_dot_bind_group(
+            &ctx.device,
+            &self.pipeline.fixed_point_dot_bind_group_layout,
+            &self.params_buffer,
+            a,
+            b,
+            &self.accum_buffer,
+        );
+
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some(&ctx.label("fixed_point_dot pass")),
+            timestamp_writes: None,
+        });
+
+        pass.set_pipeline(&self.pipeline.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(self.n.div_ceil(FIXED_POINT_DOT_WORKGROUP_SIZE), 1, 1);
+    }
+
+    /// Encode, submit and read back a·b.
+    ///
+    /// Fails when a product fell outside the fixed-point range (see the type docs) or
+    /// the readback fails.
+    pub async fn compute(&self, ctx: &GpuContext, a: &Buffer, b: &Buffer) -> Result<f32, String> {
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some(&ctx.label("fixed_point_dot encoder")),
+            });
+        self.encode(ctx, &mut encoder, a, b);
+        ctx.queue.submit(Some(encoder.finish()));
+
+        ctx.count_buffer_created();
+        let words = try_readback_to_vec::<u32>(
+            &ctx.device,
+            &ctx.queue,
+            &self.accum_buffer,
+            FIXED_POINT_ACCUM_WORDS,
+            Some(&ctx.label("fixed_point_dot staging")),
+        )
+        .await?;
+        let sum = ((words[1] as u64) << 32 | words[0] as u64) as i64;
+        fixed_point_sum_to_f32(sum, words[2], self.frac_bits)
+    }
+}
@@ -0,0 +1,446 @@
+use futures::executor;
+use wgpu::{CommandEncoder, CommandEncoderDescriptor};
+
+use crate::{
+    compute::{
+        SolveConfig, block_jacobi_exec::BlockJacobiExecutor, dot_scalar_exec::DotScalarExecutor,
+        observer::SolverObserver, operator::ReleaseBuffersOnDrop, spmv_exec::SpmvExecutor,
+        unsupported_option, vec_ops_exec::VecOpsExecutor,
+    },
+    gpu::{buffer_pool::PooledBuffer, context::GpuContext},
+    reference,
+};
+
+// Restarted GMRES(m) with right block-Jacobi preconditioning, for systems PCG cannot
+// take (nonsymmetric A):
+//
+//   A M^{-1} u = b,   x = M^{-1} u
+//
+// Each cycle starts from the true residual r = b - A x, builds the Arnoldi basis
+// v_0 .. v_m of the Krylov space of A M^{-1} (modified Gram-Schmidt, one dot + readback
+// and one axpy per previous vector), and keeps the (m+1) x m Hessenberg matrix on the
+// host, reduced by Givens rotations so |g_{j+1}| is the residual norm after step j for
+// free. At convergence or after m steps, y = H^{-1} g and x <- x + M^{-1} (V y).
+//
+// Basis storage:
+// - on the GPU (default): m + 1 vectors of length n. Nothing crosses the bus but the
+//   per-step scalars.
+// - offloaded (`GmresOptions::offload_basis`): every new v_{j+1} is read back into host
+//   memory right after it is normalized, and the GPU keeps only the newest basis vector
+//   plus one staging vector, whatever m is. Modified Gram-Schmidt still needs every
+//   older v_i at each step, so they are streamed back up one at a time (into the
+//   staging vector) during the orthogonalization as well as during the reconstruction.
+//
+//   Bandwidth per cycle of k steps, 4 n bytes per vector: k + 1 downloads, and
+//   k (k - 1) / 2 uploads for Gram-Schmidt plus k for the reconstruction, so roughly
+//   2 k^2 n bytes of host -> GPU traffic. The arithmetic is the same in both modes (the
+//   same kernels on the same values), so the iterates are bit-for-bit identical.
+//
+// Like the PCG core loop this does one submit + scalar readback per GPU dot; GMRES is
+// just much chattier (j + 2 readbacks at step j).
+
+/// Knobs for [`gmres_block_jacobi_csr_wgpu`].
+#[derive(Debug, Clone)]
+pub struct GmresOptions {
+    /// Krylov dimension per cycle (the m of GMRES(m)); >= 1.
+    pub restart: usize,
+    /// Keep the basis in host memory instead of m + 1 GPU vectors (see the module
+    /// notes for the transfer cost).
+    pub offload_basis: bool,
+}
+
+impl Default for GmresOptions {
+    fn default() -> Self {
+        Self {
+            restart: 30,
+            offload_basis: false,
+        }
+    }
+}
+
+/// Outcome of a converged GMRES solve.
+#[derive(Debug, Clone)]
+pub struct GmresResult {
+    /// Arnoldi steps over all cycles.
+    pub iterations: usize,
+    /// Cycles started (1 if it converged before the first restart).
+    pub cycles: usize,
+    /// True ||b - A x|| at exit.
+    pub residual_norm: f32,
+    /// Least-squares residual estimate |g_{j+1}| after each Arnoldi step.
+    pub residual_history: Vec<f32>,
+    /// Length-n basis vectors allocated on the GPU (m + 1, or 2 when offloaded).
+    pub gpu_basis_vectors: usize,
+    /// Basis bytes copied between host and GPU (0 unless offloaded).
+    pub host_transfer_bytes: u64,
+}
+
+/// Where the Arnoldi vectors live.
+enum Basis {
+    Gpu(Vec<PooledBuffer<f32>>),
+    Host {
+        vectors: Vec<Vec<f32>>,
+        // the newest vector, still on the GPU (SpMV input of the next step)
+        newest: PooledBuffer<f32>,
+        staging: PooledBuffer<f32>,
+        transfer_bytes: u64,
+    },
+}
+
+impl Basis {
+    fn create(ctx: &GpuContext, n: usize, restart: usize, offload: bool) -> Self {
+        let vector = |label: String| ctx.acquire_storage_buffer_uninit::<f32>(&label, n);
+
+        if offload {
+            Basis::Host {
+                vectors: Vec::with_capacity(restart + 1),
+                newest: vector("gmres newest basis vector".into()),
+                staging: vector("gmres basis staging".into()),
+                transfer_bytes: 0,
+            }
+        } else {
+            Basis::Gpu(
+                (0..=restart)
+                    .map(|i| vector(format!("gmres v {i}")))
+                    .collect(),
+            )
+        }
+    }
+
+    fn gpu_vectors(&self) -> usize {
+        match self {
+            Basis::Gpu(v) => v.len(),
+            Basis::Host { .. } => 2,
+        }
+    }
+
+    fn transfer_bytes(&self) -> u64 {
+        match self {
+            Basis::Gpu(_) => 0,
+            Basis::Host { transfer_bytes, .. } => *transfer_bytes,
+        }
+    }
+
+    /// Buffer v_j is written into after normalization (call [`Basis::store`] once the
+    /// write has been submitted).
+    fn slot(&self, j: usize) -> &wgpu::Buffer {
+        match self {
+            Basis::Gpu(v) => &v[j].buffer,
+            Basis::Host { newest, .. } => &newest.buffer,
+        }
+    }
+
+    /// Offloaded: move the just-written v_j to the host (truncating the older cycle).
+    fn store(&mut self, ctx: &GpuContext, j: usize) -> Result<(), String> {
+        if let Basis::Host {
+            vectors,
+            newest,
+            transfer_bytes,
+            ..
+        } = self
+        {
+            let v = executor::block_on(ctx.try_readback(newest))?;
+            vectors.truncate(j);
+            vectors.push(v);
+            *transfer_bytes += newest.byte_len();
+        }
+        Ok(())
+    }
+
+    /// GPU buffer holding v_i for the next submit. Offloaded, anything older than the
+    /// newest vector is uploaded into the staging vector first, so the caller must
+    /// submit the work reading it before asking for another one.
+    fn get(&mut self, ctx: &GpuContext, i: usize, newest_index: usize) -> &wgpu::Buffer {
+        match self {
+            Basis::Gpu(v) => &v[i].buffer,
+            Basis::Host { newest, .. } if i == newest_index => &newest.buffer,
+            Basis::Host {
+                vectors,
+                staging,
+                transfer_bytes,
+                ..
+            } => {
+                ctx.queue
+                    .write_buffer(&staging.buffer, 0, bytemuck::cast_slice(&vectors[i]));
+                *transfer_bytes += staging.byte_len();
+                &staging.buffer
+            }
+        }
+    }
+}
+
+/// Restarted, right-preconditioned GMRES(m) on the GPU (see the module notes).
+///
+/// Same contract as the PCG core loop: executors are created outside and passed in,
+/// `x` holds x0 on entry and the solution on success (it is left untouched on error).
+/// `config.max_iters` caps the total number of Arnoldi steps. Stops like PCG, once the
+/// residual estimate meets `config.options.stopping_criterion` (`rel_tol * ||b||` by
+/// default, r0 being the residual of x0) or drops to `config.abs_tol`; any other
+/// `PcgOptions` field set away from its default is rejected. The dot executor needs one
+/// scalar slot (slot 0 is used). `observer` receives each step's residual estimate, the values
+/// of [`GmresResult::residual_history`].
+#[allow(clippy::too_many_arguments)]
+pub fn gmres_block_jacobi_csr_wgpu(
+    n: usize,
+    b: &[f32],
+    x: &mut [f32],
+    config: &SolveConfig,
+    ctx: &GpuContext,
+    spmv_exec: &SpmvExecutor,
+    vec_ops_exec: &VecOpsExecutor,
+    dot_scalar_exec: &DotScalarExecutor,
+    block_jacobi_exec: &BlockJacobiExecutor,
+    options: &GmresOptions,
+    mut observer: Option<&mut dyn SolverObserver>,
+) -> Result<GmresResult, String> {
+    if b.len() != n || x.len() != n {
+        return Err(format!(
+            "GMRES(BlockJacobiGpu): dimension mismatch: n={}, b len {}, x len {}",
+            n,
+            b.len(),
+            x.len()
+        ));
+    }
+    if options.restart == 0 {
+        return Err("GMRES(BlockJacobiGpu): restart must be >= 1".into());
+    }
+    if let Some(field) = unsupported_option(&config.options) {
+        return Err(format!(
+            "GMRES(BlockJacobiGpu): PcgOptions::{field} is not supported (only stopping_criterion applies)"
+        ));
+    }
+    let criterion = config.options.stopping_criterion;
+    criterion
+        .validate()
+        .map_err(|e| format!("GMRES(BlockJacobiGpu): {e}"))?;
+    let max_iter = config.max_iters;
+
+    let m = options.restart;
+    let n_u32 = n as u32;
+    let n_bytes = (n * 4) as u64;
+    let err = |e: String| format!("GMRES(BlockJacobiGpu): {e}");
+
+    let b_norm2 = reference::dot(b, b);
+    // ||r0||^2, set by the first cycle's true residual
+    let mut r0_norm2 = 0.0f32;
+    let converged = |r_norm: f32, r0_norm2: f32| {
+        criterion.is_met(
+            r_norm * r_norm,
+            b_norm2,
+            r0_norm2,
+            config.rel_tol,
+            config.abs_tol,
+        )
+    };
+
+    // From the context's buffer pool when one is installed (see `GpuContext::set_buffer_pool`).
+    let b_gpu = ctx.acquire_storage_buffer("gmres b", b);
+    let x_gpu = ctx.acquire_storage_buffer("gmres x", x);
+    let w_gpu = ctx.acquire_storage_buffer_uninit::<f32>("gmres w", n);
+    let z_gpu = ctx.acquire_storage_buffer_uninit::<f32>("gmres z", n);
+    let mut basis = Basis::create(ctx, n, m, options.offload_basis);
+    // The apply bind group cached over z / the basis goes with the vectors.
+    let _release = ReleaseBuffersOnDrop(block_jacobi_exec);
+
+    let new_encoder = || {
+        vec_ops_exec.reset_params_cursor();
+        dot_scalar_exec.reset_params_cursor();
+        ctx.device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some(&ctx.label("gmres encoder")),
+            })
+    };
+    // Finish `encoder` with dot(a, c) into slot 0, submit, and read it back.
+    let submit_dot = |mut encoder: CommandEncoder, a: &wgpu::Buffer, c: &wgpu::Buffer| {
+        dot_scalar_exec.encode_dot_scalar_into(ctx, &mut encoder, a, c, n_u32, 0);
+        dot_scalar_exec.encode_copy_scalar_results_to_readback(&mut encoder);
+        ctx.queue.submit(Some(encoder.finish()));
+        executor::block_on(dot_scalar_exec.try_readback_scalar_results(ctx))
+            .map(|s| s[0])
+            .map_err(err)
+    };
+
+    let mut iterations = 0usize;
+    let mut cycles = 0usize;
+    let mut residual_history: Vec<f32> = Vec::new();
+
+    // Hessenberg (column j holds H[0..=j+1][j]), Givens rotations, rotated rhs
+    let mut h = vec![vec![0.0f64; m + 1]; m];
+    let mut cs = vec![0.0f64; m];
+    let mut sn = vec![0.0f64; m];
+    let mut g = vec![0.0f64; m + 1];
+
+    loop {
+        // w = b - A x, beta = ||w||
+        let mut encoder = new_encoder();
+        spmv_exec.encode_copy_x_from(&mut encoder, &x_gpu.buffer, n_bytes);
+        spmv_exec.encode_spmv(&mut encoder);
+        encoder.copy_buffer_to_buffer(&b_gpu.buffer, 0, &w_gpu.buffer, 0, n_bytes);
+        vec_ops_exec.encode_axpy_inplace(
+            ctx,
+            &mut encoder,
+            spmv_exec.y_buffer(),
+            &w_gpu.buffer,
+            n_u32,
+            -1.0,
+        );
+        let beta = submit_dot(encoder, &w_gpu.buffer, &w_gpu.buffer)?
+            .max(0.0)
+            .sqrt();
+
+        if cycles == 0 {
+            r0_norm2 = beta * beta;
+        }
+        if converged(beta, r0_norm2) {
+            let x_out = executor::block_on(ctx.try_readback(&x_gpu)).map_err(err)?;
+            x.copy_from_slice(&x_out);
+            return Ok(GmresResult {
+                iterations,
+                cycles,
+                residual_norm: beta,
+                residual_history,
+                gpu_basis_vectors: basis.gpu_vectors(),
+                host_transfer_bytes: basis.transfer_bytes(),
+            });
+        }
+        if iterations >= max_iter {
+            return Err(format!(
+                "GMRES(BlockJacobiGpu): did not converge in {max_iter} iterations (||r|| = {beta:e})"
+            ));
+        }
+        cycles += 1;
+
+        // v_0 = w / beta
+        let mut encoder = new_encoder();
+        vec_ops_exec.encode_scaled_copy(
+            ctx,
+            &mut encoder,
+            &w_gpu.buffer,
+            basis.slot(0),
+            n_u32,
+            1.0 / beta,
+        );
+        ctx.queue.submit(Some(encoder.finish()));
+        basis.store(ctx, 0).map_err(err)?;
+
+        g.fill(0.0);
+        g[0] = beta as f64;
+
+        // Arnoldi steps of this cycle
+        let mut k = 0usize;
+        while k < m && iterations < max_iter {
+            let j = k;
+
+            // w = A M^{-1} v_j
+            let mut encoder = new_encoder();
+            block_jacobi_exec.encode_apply(ctx, &mut encoder, basis.get(ctx, j, j), &z_gpu.buffer);
+            spmv_exec.encode_copy_x_from(&mut encoder, &z_gpu.buffer, n_bytes);
+            spmv_exec.encode_spmv(&mut encoder);
+            encoder.copy_buffer_to_buffer(spmv_exec.y_buffer(), 0, &w_gpu.buffer, 0, n_bytes);
+            ctx.queue.submit(Some(encoder.finish()));
+
+            // modified Gram-Schmidt: h_ij = w . v_i, w -= h_ij v_i
+            for (i, h_i) in h[j].iter_mut().enumerate().take(j + 1) {
+                let v_i = basis.get(ctx, i, j);
+                let h_ij = submit_dot(new_encoder(), &w_gpu.buffer, v_i)?;
+
+                let mut encoder = new_encoder();
+                vec_ops_exec.encode_axpy_inplace(
+                    ctx,
+                    &mut encoder,
+                    v_i,
+                    &w_gpu.buffer,
+                    n_u32,
+                    -h_ij,
+                );
+                ctx.queue.submit(Some(encoder.finish()));
+                *h_i = h_ij as f64;
+            }
+
+            let h_next = submit_dot(new_encoder(), &w_gpu.buffer, &w_gpu.buffer)?
+                .max(0.0)
+                .sqrt();
+            h[j][j + 1] = h_next as f64;
+
+            // v_{j+1} = w / h_{j+1,j} (skipped on breakdown: the space is invariant)
+            if h_next > 0.0 {
+                let mut encoder = new_encoder();
+                vec_ops_exec.encode_scaled_copy(
+                    ctx,
+                    &mut encoder,
+                    &w_gpu.buffer,
+                    basis.slot(j + 1),
+                    n_u32,
+                    1.0 / h_next,
+                );
+                ctx.queue.submit(Some(encoder.finish()));
+                basis.store(ctx, j + 1).map_err(err)?;
+            }
+
+            // rotate the new column and extend the rotation set
+            for i in 0..j {
+                let (a, c) = (h[j][i], h[j][i + 1]);
+                h[j][i] = cs[i] * a + sn[i] * c;
+                h[j][i + 1] = -sn[i] * a + cs[i] * c;
+            }
+            let r = h[j][j].hypot(h[j][j + 1]);
+            (cs[j], sn[j]) = if r == 0.0 {
+                (1.0, 0.0)
+            } else {
+                (h[j][j] / r, h[j][j + 1] / r)
+            };
+            h[j][j] = r;
+            h[j][j + 1] = 0.0;
+            g[j + 1] = -sn[j] * g[j];
+            g[j] *= cs[j];
+
+            iterations += 1;
+            k += 1;
+            let estimate = g[j + 1].abs() as f32;
+            residual_history.push(estimate);
+            if let Some(observer) = observer.as_deref_mut() {
+                observer.on_iteration(iterations as u32, estimate);
+            }
+            if converged(estimate, r0_norm2) || h_next == 0.0 {
+                break;
+            }
+        }
+
+        // y = H^{-1} g (upper triangular after the rotations)
+        let mut y = vec![0.0f64; k];
+        for i in (0..k).rev() {
+            let s: f64 = (i + 1..k).map(|l| h[l][i] * y[l]).sum();
+            y[i] = (g[i] - s) / h[i][i];
+        }
+
+        // x = x + M^{-1} (V y), with V y accumulated in w
+        let mut encoder = new_encoder();
+        encoder.clear_buffer(&w_gpu.buffer, 0, None);
+        ctx.queue.submit(Some(encoder.finish()));
+        for (i, y_i) in y.iter().enumerate() {
+            // the newest GPU-resident vector is v_k, not one of v_0 .. v_{k-1}
+            let v_i = basis.get(ctx, i, usize::MAX);
+            let mut encoder = new_encoder();
+            vec_ops_exec.encode_axpy_inplace(
+                ctx,
+                &mut encoder,
+                v_i,
+                &w_gpu.buffer,
+                n_u32,
+                *y_i as f32,
+            );
+            ctx.queue.submit(Some(encoder.finish()));
+        }
+        let mut encoder = new_encoder();
+        block_jacobi_exec.encode_apply(ctx, &mut encoder, &w_gpu.buffer, &z_gpu.buffer);
+        vec_ops_exec.encode_axpy_inplace(
+            ctx,
+            &mut encoder,
+            &z_gpu.buffer,
+            &x_gpu.buffer,
+            n_u32,
+            1.0,
+        );
+        ctx.queue.submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,473 @@
+use wgpu::util::BufferInitDescriptor;
+use wgpu::{BindGroup, Buffer, BufferUsages, CommandEncoder, ComputePassDescriptor};
+
+use crate::compute::triangular_solve::{
+    TriangularSolvePipeline, create_triangular_solve_bind_group, create_triangular_solve_pipeline,
+};
+use crate::gpu::context::GpuContext;
+use crate::matrix::Csr;
+
+// Incomplete-LU preconditioner apply with externally computed factors.
+//
+//   A ~= L U,   z = M^{-1} r = U^{-1} (L^{-1} r)
+//
+// The factorization itself happens elsewhere (e.g. an existing C library); this module
+// only validates the factors and runs the two triangular sweeps on the GPU. Nothing ties
+// the factors to ILU(0) -- any fill pattern works -- but the name follows the usual use.
+//
+// Factor conventions (both n x n CSR, `matrix::Csr` layout, f32 values):
+//
+// - L: lower triangular, unit diagonal. Entries must satisfy col <= row. The diagonal
+//   may be left out (implicit 1, the common "strictly lower" storage) or stored as
+//   exactly 1.0; any other stored diagonal value is rejected.
+// - U: upper triangular. Entries must satisfy col >= row, and every row must store its
+//   diagonal exactly once, finite and nonzero.
+// - Column order inside a row is free; off-diagonal duplicates are summed like SpMV.
+//
+// A combined LU array in one CSR (L strictly below the diagonal, U on and above it)
+// splits into these two by comparing col with row.
+//
+// Each sweep is level-scheduled: rows of one level only depend on rows of earlier
+// levels and are solved by one dispatch. See `TriangularLevels`; factors of banded /
+// structured-grid matrices can have ~n levels, so the apply is correct but can be
+// dispatch-bound there.
+
+/// Level schedule of one triangular factor: rows grouped so that each row only depends
+/// on rows of earlier levels.
+///
+/// Level `k` is `rows[level_ptr[k]..level_ptr[k + 1]]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriangularLevels {
+    pub level_ptr: Vec<u32>,
+    pub rows: Vec<u32>,
+}
+
+impl TriangularLevels {
+    /// Schedule for a forward sweep (row i depends on columns j < i).
+    pub fn lower(l: &Csr) -> Self {
+        Self::build(l, true)
+    }
+
+    /// Schedule for a backward sweep (row i depends on columns j > i).
+    pub fn upper(u: &Csr) -> Self {
+        Self::build(u, false)
+    }
+
+    pub fn num_levels(&self) -> usize {
+        self.level_ptr.len().saturating_sub(1)
+    }
+
+    fn build(csr: &Csr, lower: bool) -> Self {
+        let n = csr.n_rows as usize;
+        let mut level = vec![0u32; n];
+        let order: Box<dyn Iterator<Item = usize>> = if lower {
+            Box::new(0..n)
+        } else {
+            Box::new((0..n).rev())
+        };
+
+        for row in order {
+            let (start, end) = (csr.row_ptr[row] as usize, csr.row_ptr[row + 1] as usize);
+            level[row] = csr.col_idx[start..end]
+                .iter()
+                .map(|&c| c as usize)
+                .filter(|&c| if lower { c < row } else { c > row })
+                .map(|c| level[c] + 1)
+                .max()
+                .unwrap_or(0);
+        }
+
+        let num_levels = level.iter().max().map_or(0, |&m| m as usize + 1);
+        let mut level_ptr = vec![0u32; num_levels + 1];
+        for &l in &level {
+            level_ptr[l as usize + 1] += 1;
+        }
+        for k in 0..num_levels {
+            level_ptr[k + 1] += level_ptr[k];
+        }
+
+        let mut next = level_ptr.clone();
+        let mut rows = vec![0u32; n];
+        for (row, &l) in level.iter().enumerate() {
+            rows[next[l as usize] as usize] = row as u32;
+            next[l as usize] += 1;
+        }
+
+        Self { level_ptr, rows }
+    }
+
+    /// Every row exactly once, and every dependency in an earlier level.
+    fn validate(&self, csr: &Csr, lower: bool, name: &str) -> Result<(), String> {
+        let n = csr.n_rows as usize;
+        let err = |msg: String| Err(format!("Ilu0Executor: {name} levels: {msg}"));
+
+        if self.level_ptr.first() != Some(&0)
+            || self.level_ptr.windows(2).any(|w| w[0] > w[1])
+            || self.level_ptr.last().map(|&e| e as usize) != Some(self.rows.len())
+        {
+            return err(
+                "level_ptr must start at 0, be non-decreasing and end at rows.len()".into(),
+            );
+        }
+        if self.rows.len() != n {
+            return err(format!(
+                "{} rows scheduled, factor has {n}",
+                self.rows.len()
+            ));
+        }
+
+        let mut level_of = vec![u32::MAX; n];
+        for k in 0..self.num_levels() {
+            for &row in &self.rows[self.level_ptr[k] as usize..self.level_ptr[k + 1] as usize] {
+                let Some(slot) = level_of.get_mut(row as usize) else {
+                    return err(format!("row {row} out of range"));
+                };
+                if *slot != u32::MAX {
+                    return err(format!("row {row} scheduled twice"));
+                }
+                *slot = k as u32;
+            }
+        }
+
+        for row in 0..n {
+            let (start, end) = (csr.row_ptr[row] as usize, csr.row_ptr[row + 1] as usize);
+            for &c in &csr.col_idx[start..end] {
+                let c = c as usize;
+                let dependency = if lower { c < row } else { c > row };
+                if dependency && level_of[c] >= level_of[row] {
+                    return err(format!(
+                        "row {row} (level {}) depends on row {c} (level {})",
+                        level_of[row], level_of[c]
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Level schedules of both factors (forward sweep over L, backward sweep over U).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ilu0Levels {
+    pub lower: TriangularLevels,
+    pub upper: TriangularLevels,
+}
+
+impl Ilu0Levels {
+    /// The tightest schedules (fewest levels) for `l` and `u`.
+    pub fn compute(l: &Csr, u: &Csr) -> Self {
+        Self {
+            lower: TriangularLevels::lower(l),
+            upper: TriangularLevels::upper(u),
+        }
+    }
+}
+
+/// Structure checks shared by both factors, plus the triangle / diagonal rules above.
+fn validate_factor(csr: &Csr, n: usize, lower: bool, name: &str) -> Result<(), String> {
+    let err = |msg: String| Err(format!("Ilu0Executor: {name}: {msg}"));
+
+    if csr.n_rows as usize != n || csr.n_cols as usize != n {
+        return err(format!(
+            "must be {n}x{n}, got {}x{}",
+            csr.n_rows, csr.n_cols
+        ));
+    }
+    let nnz = csr.nnz as usize;
+    if csr.row_ptr.len() != n + 1
+        || csr.row_ptr[0] != 0
+        || csr.row_ptr.windows(2).any(|w| w[0] > w[1])
+        || csr.row_ptr[n] as usize != nnz
+        || csr.col_idx.len() != nnz
+        || csr.values.len() != nnz
+    {
+        return err("malformed CSR (row_ptr / col_idx / values lengths)".into());
+    }
+
+    for row in 0..n {
+        let (start, end) = (csr.row_ptr[row] as usize, csr.row_ptr[row + 1] as usize);
+        let mut diagonals = 0;
+        for k in start..end {
+            let (col, value) = (csr.col_idx[k] as usize, csr.values[k]);
+            if col >= n {
+                return err(format!("row {row}: column {col} out of range"));
+            }
+            if !value.is_finite() {
+                return err(format!("row {row}: non-finite value at column {col}"));
+            }
+            if (lower && col > row) || (!lower && col < row) {
+                return err(format!(
+                    "row {row}: entry at column {col} is {} the diagonal",
+                    if lower { "above" } else { "below" }
+                ));
+            }
+            if col == row {
+                diagonals += 1;
+                if lower && value != 1.0 {
+                    return err(format!(
+                        "row {row}: stored diagonal {value} (L must have a unit diagonal)"
+                    ));
+                }
+                if !lower && value == 0.0 {
+                    return err(format!("row {row}: zero diagonal"));
+                }
+            }
+        }
+        if diagonals > 1 {
+            return err(format!("row {row}: diagonal stored {diagonals} times"));
+        }
+        if !lower && diagonals == 0 {
+            return err(format!("row {row}: missing diagonal"));
+        }
+    }
+
+    Ok(())
+}
+
+/// One factor on the GPU (CSR buffers + level rows), kept alive for its bind group.
+struct FactorBuffers {
+    row_ptr: Buffer,
+    col_idx: Buffer,
+    values: Buffer,
+    level_rows: Buffer,
+}
+
+impl FactorBuffers {
+    fn upload(ctx: &GpuContext, csr: &Csr, levels: &TriangularLevels, name: &str) -> Self {
+        let storage = |label: String, contents: &[u8]| {
+            ctx.create_buffer_init(&BufferInitDescriptor {
+                label: Some(&ctx.label(&label)),
+                contents,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            })
+        };
+
+        // wgpu rejects zero-sized storage bindings; an L without off-diagonal entries
+        // (nnz == 0) gets a one-element dummy the shader never reads.
+        let (col_idx, values): (&[u32], &[f32]) = if csr.col_idx.is_empty() {
+            (&[0], &[0.0])
+        } else {
+            (&csr.col_idx, &csr.values)
+        };
+
+        Self {
+            row_ptr: storage(
+                format!("ilu0 {name} row_ptr"),
+                bytemuck::cast_slice(&csr.row_ptr),
+            ),
+            col_idx: storage(
+                format!("ilu0 {name} col_idx"),
+                bytemuck::cast_slice(col_idx),
+            ),
+            values: storage(format!("ilu0 {name} values"), bytemuck::cast_slice(values)),
+            level_rows: storage(
+                format!("ilu0 {name} level_rows"),
+                bytemuck::cast_slice(&levels.rows),
+            ),
+        }
+    }
+}
+
+/// Ilu0Executor
+///
+/// z = U^{-1} L^{-1} r on the GPU with externally supplied factors (conventions at the
+/// top of this file).
+///
+/// Every buffer and both bind groups are built at creation: `encode_apply` copies r into
+/// an internal input buffer, records one dispatch per level of L and of U, and copies the
+/// result into z. Level parameters live in one uniform buffer addressed by dynamic
+/// offsets, so nothing is written through the queue per apply.
+pub struct Ilu0Executor {
+    n: u32,
+
+    pipeline: TriangularSolvePipeline,
+
+    // (dynamic params offset, rows in the level) per dispatch
+    lower_levels: Vec<(u32, u32)>,
+    upper_levels: Vec<(u32, u32)>,
+    lower_bind_group: BindGroup,
+    upper_bind_group: BindGroup,
+
+    // r is copied into `input`; L y = input writes `scratch`; U z = scratch writes `output`
+    input: Buffer,
+    scratch: Buffer,
+    output: Buffer,
+
+    // Kept alive for the bind groups above
+    _params_buffer: Buffer,
+    _lower: FactorBuffers,
+    _upper: FactorBuffers,
+}
+
+impl Ilu0Executor {
+    /// Executor for the factors `l_csr` (unit lower) and `u_csr` (upper) of an n x n
+    /// matrix, with the level schedules `levels` (usually `Ilu0Levels::compute`).
+    ///
+    /// No factorization runs on the device. Factors and schedules are validated first;
+    /// the first violation is returned as an error.
+    pub fn from_factors(
+        ctx: &GpuContext,
+        l_csr: &Csr,
+        u_csr: &Csr,
+        levels: &Ilu0Levels,
+    ) -> Result<Self, String> {
+        let n = u_csr.n_rows as usize;
+        if n == 0 {
+            return Err("Ilu0Executor: empty factors".to_string());
+        }
+        validate_factor(l_csr, n, true, "L")?;
+        validate_factor(u_csr, n, false, "U")?;
+        levels.lower.validate(l_csr, true, "L")?;
+        levels.upper.validate(u_csr, false, "U")?;
+
+        let device = &ctx.device;
+        let pipeline = create_triangular_solve_pipeline(ctx);
+
+        // One params entry per level, L levels first, at the dynamic-offset alignment.
+        let stride = device.limits().min_uniform_buffer_offset_alignment;
+        let mut params_words: Vec<u32> = Vec::new();
+        let mut level_entries = |schedule: &TriangularLevels, unit_diagonal: u32| {
+            (0..schedule.num_levels())
+                .map(|k| {
+                    let offset = (params_words.len() * 4) as u32;
+                    let (start, end) = (schedule.level_ptr[k], schedule.level_ptr[k + 1]);
+                    params_words.extend([start, end - start, unit_diagonal, 0]);
+                    params_words.resize((offset + stride) as usize / 4, 0);
+                    (offset, end - start)
+                })
+                .collect::<Vec<_>>()
+        };
+        let lower_levels = level_entries(&levels.lower, 1);
+        let upper_levels = level_entries(&levels.upper, 0);
+
+        let params_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("ilu0 level params")),
+            contents: bytemuck::cast_slice(&params_words),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let vector = |label: &str| {
+            ctx.create_storage_buffer_uninit::<f32>(label, n, BufferUsages::empty())
+                .buffer
+        };
+        let input = vector("ilu0 input");
+        let scratch = vector("ilu0 scratch");
+        let output = vector("ilu0 output");
+
+        let lower = FactorBuffers::upload(ctx, l_csr, &levels.lower, "L");
+        let upper = FactorBuffers::upload(ctx, u_csr, &levels.upper, "U");
+        let bind_group = |factor: &FactorBuffers, rhs: &Buffer, x: &Buffer| {
+            create_triangular_solve_bind_group(
+                device,
+                &pipeline.triangular_solve_bind_group_layout,
+                &params_buffer,
+                &factor.row_ptr,
+                &factor.col_idx,
+                &factor.values,
+                &factor.level_rows,
+                rhs,
+                x,
+            )
+        };
+        let lower_bind_group = bind_group(&lower, &input, &scratch);
+        let upper_bind_group = bind_group(&upper, &scratch, &output);
+
+        Ok(Self {
+            n: n as u32,
+            pipeline,
+            lower_levels,
+            upper_levels,
+            lower_bind_group,
+            upper_bind_group,
+            input,
+            scratch,
+            output,
+            _params_buffer: params_buffer,
+            _lower: lower,
+            _upper: upper,
+        })
+    }
+
+    /// Encode: z = U^{-1} L^{-1} r
+    pub fn encode_apply(&self, encoder: &mut CommandEncoder, r_gpu: &Buffer, z_gpu: &Buffer) {
+        let bytes = self.n as u64 * 4;
+        encoder.copy_buffer_to_buffer(r_gpu, 0, &self.input, 0, bytes);
+        self.encode_forward_sweep(encoder);
+        self.encode_backward_sweep(encoder);
+        encoder.copy_buffer_to_buffer(&self.output, 0, z_gpu, 0, bytes);
+    }
+
+    /// Encode the forward sweep alone: y = L^{-1} r (`lower_dispatches()` dispatches).
+    pub fn encode_forward(&self, encoder: &mut CommandEncoder, r_gpu: &Buffer, y_gpu: &Buffer) {
+        let bytes = self.n as u64 * 4;
+        encoder.copy_buffer_to_buffer(r_gpu, 0, &self.input, 0, bytes);
+        self.encode_forward_sweep(encoder);
+        encoder.copy_buffer_to_buffer(&self.scratch, 0, y_gpu, 0, bytes);
+    }
+
+    /// Encode the backward sweep alone: z = U^{-1} y (`upper_dispatches()` dispatches).
+    pub fn encode_backward(&self, encoder: &mut CommandEncoder, y_gpu: &Buffer, z_gpu: &Buffer) {
+        let bytes = self.n as u64 * 4;
+        encoder.copy_buffer_to_buffer(y_gpu, 0, &self.scratch, 0, bytes);
+        self.encode_backward_sweep(encoder);
+        encoder.copy_buffer_to_buffer(&self.output, 0, z_gpu, 0, bytes);
+    }
+
+    // input -> scratch
+    fn encode_forward_sweep(&self, encoder: &mut CommandEncoder) {
+        self.encode_sweep(
+            encoder,
+            "ilu0 forward sweep (L)",
+            &self.lower_bind_group,
+            &self.lower_levels,
+        );
+    }
+
+    // scratch -> output
+    fn encode_backward_sweep(&self, encoder: &mut CommandEncoder) {
+        self.encode_sweep(
+            encoder,
+            "ilu0 backward sweep (U)",
+            &self.upper_bind_group,
+            &self.upper_levels,
+        );
+    }
+
+    fn encode_sweep(
+        &self,
+        encoder: &mut CommandEncoder,
+        label: &str,
+        bind_group: &BindGroup,
+        levels: &[(u32, u32)],
+    ) {
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some(label),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&self.pipeline.pipeline);
+        for &(offset, level_len) in levels {
+            pass.set_bind_group(0, bind_group, &[offset]);
+            pass.dispatch_workgroups(level_len.div_ceil(256), 1, 1);
+        }
+    }
+
+    pub fn n(&self) -> u32 {
+        self.n
+    }
+
+    /// Compute dispatches one `encode_apply` records (levels of L + levels of U).
+    pub fn dispatches(&self) -> u64 {
+        self.lower_dispatches() + self.upper_dispatches()
+    }
+
+    /// Compute dispatches of `encode_forward` (levels of L).
+    pub fn lower_dispatches(&self) -> u64 {
+        self.lower_levels.len() as u64
+    }
+
+    /// Compute dispatches of `encode_backward` (levels of U).
+    pub fn upper_dispatches(&self) -> u64 {
+        self.upper_levels.len() as u64
+    }
+}
@@ -0,0 +1,158 @@
+// CG <-> Lanczos connection: the CG step sizes define a symmetric tridiagonal T_k whose
+// eigenvalues (Ritz values) approximate the spectrum of the (preconditioned) operator.
+//
+// With alpha_j = step length and beta_j = rz_{j+1} / rz_j of iteration j:
+//   T[0][0]     = 1 / alpha_0
+//   T[j][j]     = 1 / alpha_j + beta_{j-1} / alpha_{j-1}        (j >= 1)
+//   T[j][j+1]   = sqrt(beta_j) / alpha_j
+//
+// The extreme Ritz values converge from the inside (lambda_min from above, lambda_max
+// from below), so the condition estimate grows towards the true value with iterations
+// and is never an over-estimate in exact arithmetic.
+//
+// The eigenvalues are computed on the host in f64: a Gershgorin interval of T brackets
+// the spectrum, and bisection on Sturm counts (the signs of the LDL^T pivots of T - x I,
+// O(k) each) narrows it to the smallest and largest eigenvalue. Recording the Ritz
+// values after every iteration (`LanczosTridiagonal::new(true)`) repeats that for each
+// T_1, ..., T_k, so the whole history costs O(k^2) host work on top of the solve;
+// harmless at a few hundred iterations, noticeable at many thousands.
+
+/// Accumulates CG coefficients into the Lanczos tridiagonal.
+#[derive(Debug, Clone, Default)]
+pub struct LanczosTridiagonal {
+    diag: Vec<f64>,
+    off_diag: Vec<f64>,
+    prev: Option<(f64, f64)>, // (alpha, beta) of the previous iteration
+    ritz_history: Option<Vec<(f64, f64)>>,
+}
+
+impl LanczosTridiagonal {
+    /// Empty tridiagonal; with `record_ritz_history`, every `push` also records the
+    /// extreme eigenvalues of the grown T (see [`LanczosTridiagonal::ritz_history`]).
+    pub fn new(record_ritz_history: bool) -> Self {
+        Self {
+            ritz_history: record_ritz_history.then(Vec::new),
+            ..Self::default()
+        }
+    }
+
+    /// Record one CG iteration: its step length `alpha` and the `beta` used to build the
+    /// next search direction.
+    pub fn push(&mut self, alpha: f32, beta: f32) {
+        let (alpha, beta) = (alpha as f64, beta as f64);
+
+        let d = match self.prev {
+            None => 1.0 / alpha,
+            Some((prev_alpha, prev_beta)) => {
+                self.off_diag.push(prev_beta.max(0.0).sqrt() / prev_alpha);
+                1.0 / alpha + prev_beta / prev_alpha
+            }
+        };
+        self.diag.push(d);
+        self.prev = Some((alpha, beta));
+
+        if self.ritz_history.is_some() {
+            let extremes = self.extreme_eigenvalues().unwrap_or((f64::NAN, f64::NAN));
+            if let Some(history) = self.ritz_history.as_mut() {
+                history.push(extremes);
+            }
+        }
+    }
+
+    /// (lambda_min, lambda_max) of T_1, T_2, ... after each `push` (NaN where T was not
+    /// finite), when recording was requested in [`LanczosTridiagonal::new`].
+    pub fn ritz_history(&self) -> Option<&[(f64, f64)]> {
+        self.ritz_history.as_deref()
+    }
+
+    /// Size of T (number of recorded iterations).
+    pub fn len(&self) -> usize {
+        self.diag.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diag.is_empty()
+    }
+
+    /// (lambda_min, lambda_max) of T, or None when empty / not finite.
+    pub fn extreme_eigenvalues(&self) -> Option<(f64, f64)> {
+        if self.diag.is_empty()
+            || !self
+                .diag
+                .iter()
+                .chain(&self.off_diag)
+                .all(|v| v.is_finite())
+        {
+            return None;
+        }
+
+        // Gershgorin interval of T brackets every eigenvalue.
+        let n = self.diag.len();
+        let radius = |i: usize| {
+            let left = if i > 0 {
+                self.off_diag[i - 1].abs()
+            } else {
+                0.0
+            };
+            let right = if i + 1 < n {
+                self.off_diag[i].abs()
+            } else {
+                0.0
+            };
+            left + right
+        };
+        let lo = (0..n)
+            .map(|i| self.diag[i] - radius(i))
+            .fold(f64::INFINITY, f64::min);
+        let hi = (0..n)
+            .map(|i| self.diag[i] + radius(i))
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        Some((
+            self.kth_eigenvalue(0, lo, hi),
+            self.kth_eigenvalue(n - 1, lo, hi),
+        ))
+    }
+
+    /// lambda_max / lambda_min of T (None if lambda_min <= 0).
+    pub fn condition_number(&self) -> Option<f64> {
+        let (lo, hi) = self.extreme_eigenvalues()?;
+        (lo > 0.0).then(|| hi / lo)
+    }
+
+    /// Number of eigenvalues of T below `x` (Sturm sequence of the LDL^T pivots).
+    fn count_below(&self, x: f64) -> usize {
+        let mut count = 0;
+        let mut d = 1.0f64;
+        for i in 0..self.diag.len() {
+            d = if i == 0 {
+                self.diag[0] - x
+            } else {
+                self.diag[i] - x - self.off_diag[i - 1].powi(2) / d
+            };
+            if d == 0.0 {
+                d = -f64::EPSILON * (self.diag[i].abs() + x.abs()).max(f64::MIN_POSITIVE);
+            }
+            if d < 0.0 {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// k-th smallest eigenvalue (0-based) by bisection inside [lo, hi].
+    fn kth_eigenvalue(&self, k: usize, mut lo: f64, mut hi: f64) -> f64 {
+        for _ in 0..200 {
+            let mid = 0.5 * (lo + hi);
+            if mid <= lo || mid >= hi {
+                break;
+            }
+            if self.count_below(mid) > k {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+        0.5 * (lo + hi)
+    }
+}
@@ -0,0 +1,263 @@
+use futures::executor;
+use wgpu::util::BufferInitDescriptor;
+use wgpu::{Buffer, BufferUsages, CommandEncoder, CommandEncoderDescriptor, ComputePassDescriptor};
+
+use crate::{
+    compute::{
+        block_jacobi::{create_block_norms_bind_group, create_block_norms_pipeline},
+        dot::{DEFAULT_HOST_REDUCE_THRESHOLD, host_dot},
+        dot_reduce::{DotNarrowPipeline, create_dot_narrow_bind_group, create_dot_sqrt_pipeline},
+        dot_scalar_exec::DotScalarExecutor,
+        spmv_exec::SpmvExecutor,
+    },
+    gpu::{buffer::GpuBuffer, context::GpuContext, readback::readback_to_vec},
+};
+
+// Operator-weighted norms on the GPU, built from the existing SpMV + dot executors.
+//
+// ||r||_M = sqrt(r^T M r) for a mass (or any other) matrix M held by an SpmvExecutor.
+// M must be symmetric positive definite: otherwise r^T M r can be zero or negative for
+// r != 0 and the result is not a norm (negative values are clamped to 0 before sqrt).
+
+/// Encode scalar_results[out_index] = r^T M r.
+///
+/// Clobbers the SpMV executor's internal x/y buffers.
+pub fn encode_weighted_norm2_into(
+    ctx: &GpuContext,
+    encoder: &mut CommandEncoder,
+    mass_spmv: &SpmvExecutor,
+    dot_scalar_exec: &DotScalarExecutor,
+    r: &Buffer,
+    out_index: u32,
+) {
+    let n = mass_spmv.n_rows();
+
+    // M r
+    mass_spmv.encode_copy_x_from(encoder, r, (n as u64) * 4);
+    mass_spmv.encode_spmv(encoder);
+
+    // r^T (M r)
+    dot_scalar_exec.encode_dot_scalar_into(ctx, encoder, r, mass_spmv.y_buffer(), n, out_index);
+}
+
+/// ||r||_M = sqrt(r^T M r), with M the matrix of `mass_spmv` (SPD, n_rows x n_rows).
+///
+/// One submit + one scalar readback; uses scalar slot 0 of `dot_scalar_exec` and resets
+/// its params cursor, so call it between solver iterations, not inside an open encoder.
+///
+/// For n below [`DEFAULT_HOST_REDUCE_THRESHOLD`] only the SpMV runs on the GPU: r and M r
+/// are read back and r^T (M r) is summed on the host.
+pub fn weighted_norm(
+    ctx: &GpuContext,
+    mass_spmv: &SpmvExecutor,
+    dot_scalar_exec: &DotScalarExecutor,
+    r: &Buffer,
+) -> f32 {
+    let n = mass_spmv.n_rows();
+    if n < DEFAULT_HOST_REDUCE_THRESHOLD {
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some(&ctx.label("weighted norm encoder")),
+            });
+        mass_spmv.encode_copy_x_from(&mut encoder, r, (n as u64) * 4);
+        mass_spmv.encode_spmv(&mut encoder);
+        ctx.queue.submit(Some(encoder.finish()));
+
+        ctx.count_buffer_created();
+        let r_host = executor::block_on(readback_to_vec::<f32>(
+            &ctx.device,
+            &ctx.queue,
+            r,
+            n as usize,
+            None,
+        ));
+        ctx.count_buffer_created();
+        let mr_host = executor::block_on(readback_to_vec::<f32>(
+            &ctx.device,
+            &ctx.queue,
+            mass_spmv.y_buffer(),
+            n as usize,
+            None,
+        ));
+        return host_dot(&r_host, &mr_host).max(0.0).sqrt();
+    }
+
+    dot_scalar_exec.reset_params_cursor();
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some(&ctx.label("weighted norm encoder")),
+        });
+
+    encode_weighted_norm2_into(ctx, &mut encoder, mass_spmv, dot_scalar_exec, r, 0);
+    dot_scalar_exec.encode_copy_scalar_results_to_readback(&mut encoder);
+
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let scalar_results = executor::block_on(dot_scalar_exec.readback_scalar_results(ctx));
+    scalar_results[0].max(0.0).sqrt()
+}
+
+/// ||x||_2 of a length-n GPU vector, left on the GPU.
+///
+/// `encode` records the dot x·x (the partials + reduce tree of `DotScalarExecutor`,
+/// summed in f32) and one single-invocation pass that writes sqrt of it into
+/// `result[0]` (dot_narrow.wgsl with TAKE_SQRT), so a stopping test or a later kernel
+/// can use the norm without a readback. A rounding-negative sum gives 0, not NaN.
+///
+/// Like `DotProduct`, the scratch and dot slot are the executor's own: several encodes
+/// in one encoder are fine, as each finishes its sqrt pass before the next dot starts.
+pub struct L2Norm {
+    n: u32,
+    exec: DotScalarExecutor,
+    sqrt_pipeline: DotNarrowPipeline,
+    result: GpuBuffer<f32>,
+}
+
+impl L2Norm {
+    pub fn create(ctx: &GpuContext, n: u32) -> Self {
+        Self {
+            n,
+            exec: DotScalarExecutor::create(ctx, n as usize, 1),
+            sqrt_pipeline: create_dot_sqrt_pipeline(ctx),
+            result: ctx.create_storage_buffer_uninit::<f32>(
+                "l2 norm result",
+                1,
+                BufferUsages::COPY_SRC,
+            ),
+        }
+    }
+
+    pub fn n(&self) -> u32 {
+        self.n
+    }
+
+    /// Compute dispatches one `encode` records: those of the dot plus the sqrt pass.
+    pub fn dispatches(&self) -> u32 {
+        self.exec.dispatches_per_dot(self.n) + 1
+    }
+
+    /// Encode result[0] = ||x||_2 over the first n entries of `x`. `result` needs STORAGE
+    /// usage. Does NOT submit.
+    ///
+    /// Panics if `x` holds fewer than n f32 or `result` is empty.
+    pub fn encode(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        x: &Buffer,
+        result: &Buffer,
+    ) {
+        assert!(
+            x.size() >= self.n as u64 * 4,
+            "L2Norm: x holds fewer than n = {} f32",
+            self.n
+        );
+        assert!(result.size() >= 4, "L2Norm: result buffer is empty");
+
+        self.exec
+            .encode_dot_scalar_into(ctx, encoder, x, x, self.n, 0);
+
+        let bind_group = create_dot_narrow_bind_group(
+            &ctx.device,
+            &self.sqrt_pipeline.dot_narrow_bind_group_layout,
+            self.exec.scalar_results_buffer(),
+            result,
+        );
+        let mut pass = ctx.begin_compute_pass(encoder, "l2 norm sqrt pass");
+        pass.set_pipeline(&self.sqrt_pipeline.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(1, 1, 1);
+    }
+
+    /// Encode into the norm's own 1-element buffer, submit and read ||x||_2 back.
+    pub async fn compute(&self, ctx: &GpuContext, x: &Buffer) -> Result<f32, String> {
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some(&ctx.label("l2 norm encoder")),
+            });
+        self.encode(ctx, &mut encoder, x, &self.result.buffer);
+        ctx.queue.submit(Some(encoder.finish()));
+        Ok(ctx.try_readback(&self.result).await?[0])
+    }
+}
+
+/// ||r_b||_2 for every block b of the partition `block_starts` (length num_blocks + 1,
+/// `[0, ..., n]`): which parts of the domain carry the residual, e.g. to pick where to
+/// refine a mesh.
+///
+/// This is the partition the block-Jacobi preconditioner is built from
+/// (`BlockJacobiExecutor::create`, `build_lu_blocks_from_csr_block_starts_6`), so the
+/// solver's `block_starts` can be passed as is, with the norms then lining up with its
+/// blocks; any other monotone partition works too, and blocks may be longer than 6.
+/// Squares are summed in f32.
+///
+/// Builds the kernel and uploads `block_starts` on every call, then one submit and a
+/// readback of num_blocks floats. Panics if `block_starts` is empty, not monotone, or
+/// ends past the length of `r`.
+pub fn per_block_residual(ctx: &GpuContext, r: &Buffer, block_starts: &[u32]) -> Vec<f32> {
+    let num_blocks = block_starts
+        .len()
+        .checked_sub(1)
+        .expect("per_block_residual: empty block_starts");
+    assert!(
+        block_starts.windows(2).all(|w| w[0] <= w[1]),
+        "per_block_residual: block_starts must be non-decreasing"
+    );
+    let n = block_starts[num_blocks];
+    assert!(
+        n as u64 * 4 <= r.size(),
+        "per_block_residual: block_starts end at {n}, r holds {} floats",
+        r.size() / 4
+    );
+    if num_blocks == 0 {
+        return Vec::new();
+    }
+
+    let device = &ctx.device;
+    let pipeline = create_block_norms_pipeline(ctx);
+
+    let params_words: [u32; 4] = [n, num_blocks as u32, 0, 0];
+    let params_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+        label: Some(&ctx.label("block_norms params")),
+        contents: bytemuck::cast_slice(&params_words),
+        usage: BufferUsages::UNIFORM,
+    });
+    let block_starts_buffer = ctx.create_storage_buffer(
+        "block_norms block_starts",
+        block_starts,
+        BufferUsages::empty(),
+    );
+    let norms = ctx.create_storage_buffer_uninit::<f32>(
+        "block_norms norms",
+        num_blocks,
+        BufferUsages::COPY_SRC,
+    );
+    let bind_group = create_block_norms_bind_group(
+        device,
+        &pipeline.block_norms_bind_group_layout,
+        &params_buffer,
+        &block_starts_buffer.buffer,
+        r,
+        &norms.buffer,
+    );
+
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some(&ctx.label("block_norms encoder")),
+    });
+    {
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some(&ctx.label("block_norms pass")),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&pipeline.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups((num_blocks as u32).div_ceil(64), 1, 1);
+    }
+    ctx.queue.submit(Some(encoder.finish()));
+
+    executor::block_on(ctx.readback(&norms))
+}
@@ -0,0 +1,77 @@
+// Convergence observers: a callback the solver drivers invoke with the residual norm of
+// every iteration, as soon as the host has it (right after the iteration's scalar
+// readback), to watch a solve live, log it, or spot divergence and stalls while it runs.
+//
+// The norm is the one the driver records in its `residual_history`, so a `VecObserver`
+// collects exactly that history:
+// - PCG (`pcg_block_jacobi_csr_wgpu`, `PcgSolver::solve_observed`): ||r_k||, the
+//   recurrence residual (or the recomputed true one, per `ResidualStrategy`). With
+//   `PcgOptions::submit_every` = N the readback happens once per batch, so the observer
+//   is called N times in a row after it.
+// - GMRES (`gmres::gmres_block_jacobi_csr_wgpu`): the least-squares estimate
+//   |g_{j+1}| after each Arnoldi step, iterations counted across restarts.
+//
+// Iterations count from 1 like `PcgResult::iterations`. The observer only watches: it
+// cannot stop the solve or change its arithmetic, and passing `None` costs nothing.
+
+/// Receives the residual norm of every iteration of a solve.
+pub trait SolverObserver {
+    fn on_iteration(&mut self, iter: u32, residual_norm: f32);
+}
+
+/// Collects the residual norms into a `Vec` (index k holds iteration k + 1).
+#[derive(Debug, Clone, Default)]
+pub struct VecObserver {
+    pub residual_norms: Vec<f32>,
+}
+
+impl VecObserver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SolverObserver for VecObserver {
+    fn on_iteration(&mut self, _iter: u32, residual_norm: f32) {
+        self.residual_norms.push(residual_norm);
+    }
+}
+
+/// Prints `<label> iter <k>: residual <norm>` to stderr for every `every`-th iteration.
+#[derive(Debug, Clone)]
+pub struct StderrObserver {
+    label: String,
+    every: u32,
+}
+
+impl StderrObserver {
+    /// Log every iteration, each line prefixed with `label` (e.g. the solve's name).
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            every: 1,
+        }
+    }
+
+    /// Log only iterations divisible by `every` (>= 1), for long solves.
+    pub fn every(self, every: u32) -> Self {
+        Self {
+            every: every.max(1),
+            ..self
+        }
+    }
+
+    /// The line logged for `iter`, or `None` when `every` skips it.
+    pub fn format_line(&self, iter: u32, residual_norm: f32) -> Option<String> {
+        iter.is_multiple_of(self.every)
+            .then(|| format!("{} iter {iter}: residual {residual_norm:e}", self.label))
+    }
+}
+
+impl SolverObserver for StderrObserver {
+    fn on_iteration(&mut self, iter: u32, residual_norm: f32) {
+        if let Some(line) = self.format_line(iter, residual_norm) {
+            eprintln!("{line}");
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use wgpu::DeviceType;
+
+use crate::gpu::context::GpuContext;
+
+// Launch-shape reporting: how many workgroups each kernel of a solve launches, next to
+// the device's compute-unit count, as a rough hint whether a kernel can fill the device.
+//
+// A dispatch of fewer workgroups than the device has compute units leaves some of them
+// idle no matter how fast the kernel is; such a kernel is launch-bound and gains from
+// more, smaller workgroups (fewer elements per invocation), not from a faster body.
+// Conversely, dozens of workgroups per compute unit say there is parallel slack to trade
+// for more work per invocation.
+//
+// Limitations (it is a hint, not a profiler):
+// - wgpu exposes no compute-unit count. `compute_unit_hint` only knows it for CPU
+//   adapters (llvmpipe / WARP / SwiftShader run workgroups on host threads, so it is
+//   the host's available parallelism); for real GPUs pass it in through
+//   `PcgOptions::compute_units` (the vendor's SM / CU / EU count).
+// - One compute unit usually runs several workgroups at once, limited by registers and
+//   workgroup memory, which are not visible here; "workgroups per compute unit" is
+//   therefore a lower bound on the waves a dispatch needs, not achieved occupancy.
+// - Workgroups of size 1 (block Jacobi) occupy one lane of a SIMD unit; their count
+//   overstates how busy the device is.
+
+/// One kernel's dispatch shape: `dispatches` launches of `workgroups` workgroups of
+/// `workgroup_size` invocations each.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KernelLaunch {
+    pub kernel: &'static str,
+    pub workgroup_size: u32,
+    pub workgroups: u32,
+    /// Launches per apply (for an operator) or per iteration (in `PcgTimings`).
+    pub dispatches: u32,
+}
+
+impl KernelLaunch {
+    pub fn new(kernel: &'static str, workgroup_size: u32, workgroups: u32) -> Self {
+        Self {
+            kernel,
+            workgroup_size,
+            workgroups,
+            dispatches: 1,
+        }
+    }
+
+    /// `self` with every dispatch repeated `times` (e.g. three dots per iteration).
+    pub fn times(self, times: u32) -> Self {
+        Self {
+            dispatches: self.dispatches * times,
+            ..self
+        }
+    }
+
+    /// Invocations of one dispatch.
+    pub fn invocations(&self) -> u64 {
+        self.workgroups as u64 * self.workgroup_size as u64
+    }
+
+    /// Workgroups of one dispatch per compute unit; below 1 the dispatch cannot occupy
+    /// every compute unit.
+    pub fn workgroups_per_compute_unit(&self, compute_units: u32) -> f64 {
+        self.workgroups as f64 / compute_units.max(1) as f64
+    }
+
+    /// The dispatch leaves compute units idle (fewer workgroups than units).
+    pub fn launch_bound(&self, compute_units: u32) -> bool {
+        self.workgroups < compute_units
+    }
+}
+
+/// Compute units of the context's adapter, when they can be inferred: the host's
+/// available parallelism for CPU adapters, `None` otherwise (see the limitations at the
+/// top of this file).
+pub fn compute_unit_hint(ctx: &GpuContext) -> Option<u32> {
+    match ctx.adapter_info.device_type {
+        DeviceType::Cpu => std::thread::available_parallelism()
+            .ok()
+            .map(|p| p.get() as u32),
+        _ => None,
+    }
+}
@@ -0,0 +1,428 @@
+use wgpu::{Buffer, BufferUsages, CommandEncoder};
+
+use crate::compute::additive_schwarz::{
+    ADDITIVE_SCHWARZ_COMBINE_WORKGROUP_SIZE, ADDITIVE_SCHWARZ_SOLVE_WORKGROUP_SIZE,
+};
+use crate::compute::additive_schwarz_exec::AdditiveSchwarzExecutor;
+use crate::compute::block_jacobi_exec::{BlockJacobiExecutor, BlockKind};
+use crate::compute::diagonal_jacobi::DIAGONAL_JACOBI_WORKGROUP_SIZE;
+use crate::compute::diagonal_jacobi_exec::DiagonalJacobiExecutor;
+use crate::compute::dot_scalar_exec::DotScalarExecutor;
+use crate::compute::occupancy::KernelLaunch;
+use crate::compute::spmv_exec::SpmvExecutor;
+use crate::compute::ssor::{EisenstatSsorOperator, SsorPreconditioner};
+use crate::compute::ssor_exec::SsorExecutor;
+use crate::compute::vec_ops_exec::VecOpsExecutor;
+use crate::gpu::{buffer::GpuBuffer, context::GpuContext};
+
+/// A linear map y = Op x on GPU vectors, applied by recording passes into an encoder
+/// (nothing is submitted).
+///
+/// `x` and `y` are caller buffers of `n_cols()` and `n_rows()` f32 and must be distinct:
+/// implementations may read x and write y in the same dispatch.
+pub trait LinearOperator {
+    fn n_rows(&self) -> u32;
+    fn n_cols(&self) -> u32;
+
+    /// Encode y = Op x.
+    fn encode_apply(&self, ctx: &GpuContext, encoder: &mut CommandEncoder, x: &Buffer, y: &Buffer);
+
+    /// Compute dispatches one `encode_apply` records (buffer copies not counted).
+    fn dispatches(&self) -> u64 {
+        1
+    }
+
+    /// Launch shape of those dispatches, for `PcgTimings::kernel_launches`; empty when
+    /// the operator does not report them.
+    fn kernel_launches(&self) -> Vec<KernelLaunch> {
+        Vec::new()
+    }
+
+    /// Drop whatever the operator still holds of the x / y buffers of past applies (a
+    /// cached bind group keeps them alive). The solvers call it when a solve's vectors
+    /// are released.
+    fn release_buffers(&self) {}
+}
+
+/// Calls `release_buffers` on the operator when dropped, so a solve lets go of its
+/// vectors on every return path.
+pub(crate) struct ReleaseBuffersOnDrop<'a>(pub(crate) &'a dyn LinearOperator);
+
+impl Drop for ReleaseBuffersOnDrop<'_> {
+    fn drop(&mut self) {
+        self.0.release_buffers();
+    }
+}
+
+/// y = A x, straight on the caller buffers (the executor's internal x/y are not used).
+impl LinearOperator for SpmvExecutor {
+    fn n_rows(&self) -> u32 {
+        SpmvExecutor::n_rows(self)
+    }
+
+    fn n_cols(&self) -> u32 {
+        SpmvExecutor::n_rows(self)
+    }
+
+    fn encode_apply(&self, ctx: &GpuContext, encoder: &mut CommandEncoder, x: &Buffer, y: &Buffer) {
+        SpmvExecutor::encode_apply(self, ctx, encoder, x, y);
+    }
+
+    fn kernel_launches(&self) -> Vec<KernelLaunch> {
+        vec![self.kernel_launch()]
+    }
+}
+
+/// y = M^{-1} x.
+impl LinearOperator for BlockJacobiExecutor {
+    fn n_rows(&self) -> u32 {
+        BlockJacobiExecutor::n_cols(self)
+    }
+
+    fn n_cols(&self) -> u32 {
+        self.n()
+    }
+
+    fn encode_apply(&self, ctx: &GpuContext, encoder: &mut CommandEncoder, x: &Buffer, y: &Buffer) {
+        BlockJacobiExecutor::encode_apply(self, ctx, encoder, x, y);
+    }
+
+    fn release_buffers(&self) {
+        self.clear_bind_group_cache();
+    }
+
+    /// One workgroup of one invocation per block.
+    fn kernel_launches(&self) -> Vec<KernelLaunch> {
+        let kernel = match self.kind() {
+            BlockKind::Square => "block_jacobi",
+            BlockKind::Rectangular => "block_lsq",
+        };
+        vec![KernelLaunch::new(kernel, 1, self.num_blocks())]
+    }
+}
+
+/// y = M^{-1} x, two dispatches (local solves + combine).
+impl LinearOperator for AdditiveSchwarzExecutor {
+    fn n_rows(&self) -> u32 {
+        self.n()
+    }
+
+    fn n_cols(&self) -> u32 {
+        self.n()
+    }
+
+    fn encode_apply(&self, ctx: &GpuContext, encoder: &mut CommandEncoder, x: &Buffer, y: &Buffer) {
+        AdditiveSchwarzExecutor::encode_apply(self, ctx, encoder, x, y);
+    }
+
+    fn dispatches(&self) -> u64 {
+        AdditiveSchwarzExecutor::dispatches(self)
+    }
+
+    fn kernel_launches(&self) -> Vec<KernelLaunch> {
+        vec![
+            KernelLaunch::new(
+                "additive_schwarz_solve",
+                ADDITIVE_SCHWARZ_SOLVE_WORKGROUP_SIZE,
+                self.num_blocks()
+                    .div_ceil(ADDITIVE_SCHWARZ_SOLVE_WORKGROUP_SIZE),
+            ),
+            KernelLaunch::new(
+                "additive_schwarz_combine",
+                ADDITIVE_SCHWARZ_COMBINE_WORKGROUP_SIZE,
+                self.n().div_ceil(ADDITIVE_SCHWARZ_COMBINE_WORKGROUP_SIZE),
+            ),
+        ]
+    }
+}
+
+/// y = D^{-1} x.
+impl LinearOperator for DiagonalJacobiExecutor {
+    fn n_rows(&self) -> u32 {
+        self.n()
+    }
+
+    fn n_cols(&self) -> u32 {
+        self.n()
+    }
+
+    fn encode_apply(&self, ctx: &GpuContext, encoder: &mut CommandEncoder, x: &Buffer, y: &Buffer) {
+        DiagonalJacobiExecutor::encode_apply(self, ctx, encoder, x, y);
+    }
+
+    fn kernel_launches(&self) -> Vec<KernelLaunch> {
+        vec![KernelLaunch::new(
+            "diagonal_jacobi",
+            DIAGONAL_JACOBI_WORKGROUP_SIZE,
+            self.n().div_ceil(DIAGONAL_JACOBI_WORKGROUP_SIZE),
+        )]
+    }
+}
+
+/// y = M^{-1} x for SSOR (two triangular sweeps).
+impl LinearOperator for SsorPreconditioner {
+    fn n_rows(&self) -> u32 {
+        self.n()
+    }
+
+    fn n_cols(&self) -> u32 {
+        self.n()
+    }
+
+    fn encode_apply(
+        &self,
+        _ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        x: &Buffer,
+        y: &Buffer,
+    ) {
+        SsorPreconditioner::encode_apply(self, encoder, x, y);
+    }
+
+    fn dispatches(&self) -> u64 {
+        SsorPreconditioner::dispatches(self)
+    }
+}
+
+/// y = M^{-1} x for multicolor SSOR (one dispatch per color and sweep).
+impl LinearOperator for SsorExecutor {
+    fn n_rows(&self) -> u32 {
+        self.n()
+    }
+
+    fn n_cols(&self) -> u32 {
+        self.n()
+    }
+
+    fn encode_apply(&self, ctx: &GpuContext, encoder: &mut CommandEncoder, x: &Buffer, y: &Buffer) {
+        SsorExecutor::encode_apply(self, ctx, encoder, x, y);
+    }
+
+    fn dispatches(&self) -> u64 {
+        SsorExecutor::dispatches(self)
+    }
+}
+
+/// y = A~ x, the Eisenstat-transformed system of `ssor_cg_csr_wgpu`.
+impl LinearOperator for EisenstatSsorOperator {
+    fn n_rows(&self) -> u32 {
+        self.n()
+    }
+
+    fn n_cols(&self) -> u32 {
+        self.n()
+    }
+
+    fn encode_apply(&self, ctx: &GpuContext, encoder: &mut CommandEncoder, x: &Buffer, y: &Buffer) {
+        EisenstatSsorOperator::encode_apply(self, ctx, encoder, x, y);
+    }
+
+    fn dispatches(&self) -> u64 {
+        EisenstatSsorOperator::dispatches(self)
+    }
+}
+
+/// y = x, the "no preconditioner" M^{-1} = I: PCG with it runs plain CG on A (e.g. for
+/// `PcgOptions::ritz_value_history` to estimate the spectrum of A itself).
+///
+/// The apply is one buffer copy and no compute dispatch.
+pub struct IdentityOperator {
+    n: u32,
+}
+
+impl IdentityOperator {
+    pub fn new(n: u32) -> Self {
+        Self { n }
+    }
+}
+
+impl LinearOperator for IdentityOperator {
+    fn n_rows(&self) -> u32 {
+        self.n
+    }
+
+    fn n_cols(&self) -> u32 {
+        self.n
+    }
+
+    fn encode_apply(
+        &self,
+        _ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        x: &Buffer,
+        y: &Buffer,
+    ) {
+        encoder.copy_buffer_to_buffer(x, 0, y, 0, self.n as u64 * 4);
+    }
+
+    fn dispatches(&self) -> u64 {
+        0
+    }
+}
+
+/// `second(first(x))` as one operator, e.g. the preconditioned operator M^{-1} A as
+/// `CompositeOperator::new(ctx, &spmv_exec, &block_jacobi_exec)` (or A M^{-1} with the
+/// arguments swapped).
+///
+/// The intermediate `first(x)` lives in a scratch buffer of `first.n_rows()` f32 that the
+/// composite allocates once and reuses on every apply. Passes run in recording order, so
+/// several applies in one encoder are fine, but the buffer is rewritten by each of them:
+/// read it (`scratch()`) only right after the apply you care about. Because the two
+/// halves run as separate passes, `x` and `y` may even be the same buffer here.
+pub struct CompositeOperator<'a> {
+    first: &'a dyn LinearOperator,
+    second: &'a dyn LinearOperator,
+    scratch: GpuBuffer<f32>,
+}
+
+impl<'a> CompositeOperator<'a> {
+    /// Fails when `first` produces a vector of a different length than `second` takes.
+    pub fn new(
+        ctx: &GpuContext,
+        first: &'a dyn LinearOperator,
+        second: &'a dyn LinearOperator,
+    ) -> Result<Self, String> {
+        if first.n_rows() != second.n_cols() {
+            return Err(format!(
+                "CompositeOperator: first maps to {} entries but second takes {}",
+                first.n_rows(),
+                second.n_cols()
+            ));
+        }
+        let scratch = ctx.create_storage_buffer_uninit::<f32>(
+            "composite operator scratch",
+            first.n_rows() as usize,
+            BufferUsages::empty(),
+        );
+        Ok(Self {
+            first,
+            second,
+            scratch,
+        })
+    }
+
+    /// The intermediate vector first(x) of the most recent apply.
+    pub fn scratch(&self) -> &GpuBuffer<f32> {
+        &self.scratch
+    }
+}
+
+impl LinearOperator for CompositeOperator<'_> {
+    fn n_rows(&self) -> u32 {
+        self.second.n_rows()
+    }
+
+    fn n_cols(&self) -> u32 {
+        self.first.n_cols()
+    }
+
+    fn encode_apply(&self, ctx: &GpuContext, encoder: &mut CommandEncoder, x: &Buffer, y: &Buffer) {
+        self.first
+            .encode_apply(ctx, encoder, x, &self.scratch.buffer);
+        self.second
+            .encode_apply(ctx, encoder, &self.scratch.buffer, y);
+    }
+
+    fn dispatches(&self) -> u64 {
+        self.first.dispatches() + self.second.dispatches()
+    }
+
+    fn kernel_launches(&self) -> Vec<KernelLaunch> {
+        let mut launches = self.first.kernel_launches();
+        launches.extend(self.second.kernel_launches());
+        launches
+    }
+
+    fn release_buffers(&self) {
+        self.first.release_buffers();
+        self.second.release_buffers();
+    }
+}
+
+/// `base + u v^T` without reassembling: y = base(x) + u (v·x), e.g. a quasi-Newton
+/// rank-1 correction on top of `SpmvExecutor` (`spmv_exec.with_rank1_update(..)`).
+///
+/// The apply records the base apply, the dot v·x (a `DotScalarExecutor` tree, its scalar
+/// kept on the GPU) and one AXPY y += (v·x) u, so nothing is read back. `u` holds
+/// `base.n_rows()` f32 and `v` `base.n_cols()`; x and y must be distinct, as for `base`.
+///
+/// Updates stack by wrapping: `Rank1Update::new(ctx, &first_update, u2, v2)` (or
+/// `first_update.with_rank1_update(..)`) is base + u1 v1^T + u2 v2^T, each level adding
+/// its own dot and AXPY. Past a handful of updates a dense n x k low-rank block applied
+/// with two GEMVs would be cheaper; this is meant for few, short-lived corrections.
+pub struct Rank1Update<'a> {
+    base: &'a dyn LinearOperator,
+    u: &'a Buffer,
+    v: &'a Buffer,
+    dot_exec: DotScalarExecutor,
+    vec_ops_exec: VecOpsExecutor,
+}
+
+impl<'a> Rank1Update<'a> {
+    /// Fails when `u` or `v` holds fewer f32 than `base` has rows or columns.
+    pub fn new(
+        ctx: &GpuContext,
+        base: &'a dyn LinearOperator,
+        u: &'a Buffer,
+        v: &'a Buffer,
+    ) -> Result<Self, String> {
+        for (name, buffer, len) in [("u", u, base.n_rows()), ("v", v, base.n_cols())] {
+            if buffer.size() < len as u64 * 4 {
+                return Err(format!(
+                    "Rank1Update: {name} holds {} bytes, needs {len} f32",
+                    buffer.size()
+                ));
+            }
+        }
+        Ok(Self {
+            base,
+            u,
+            v,
+            dot_exec: DotScalarExecutor::create(ctx, base.n_cols() as usize, 1),
+            vec_ops_exec: VecOpsExecutor::create(ctx),
+        })
+    }
+
+    /// A further rank-1 update on top of this one.
+    pub fn with_rank1_update(
+        &'a self,
+        ctx: &GpuContext,
+        u: &'a Buffer,
+        v: &'a Buffer,
+    ) -> Result<Rank1Update<'a>, String> {
+        Rank1Update::new(ctx, self, u, v)
+    }
+}
+
+impl LinearOperator for Rank1Update<'_> {
+    fn n_rows(&self) -> u32 {
+        self.base.n_rows()
+    }
+
+    fn n_cols(&self) -> u32 {
+        self.base.n_cols()
+    }
+
+    fn encode_apply(&self, ctx: &GpuContext, encoder: &mut CommandEncoder, x: &Buffer, y: &Buffer) {
+        self.base.encode_apply(ctx, encoder, x, y);
+        self.dot_exec
+            .encode_dot_scalar_into(ctx, encoder, self.v, x, self.n_cols(), 0);
+        self.vec_ops_exec.encode_axpy_inplace_from_scalar_results(
+            ctx,
+            encoder,
+            self.u,
+            y,
+            self.n_rows(),
+            self.dot_exec.scalar_results_buffer(),
+            0,
+        );
+    }
+
+    fn dispatches(&self) -> u64 {
+        self.base.dispatches() + self.dot_exec.dispatches_per_dot(self.n_cols()) as u64 + 1
+    }
+
+    fn release_buffers(&self) {
+        self.base.release_buffers();
+    }
+}
@@ -50,13 +50,13 @@ pub fn create_pcg_update_scalars_pipeline(ctx: &GpuContext) -> PcgUpdateScalarsP
     let device = &ctx.device;
 
     let shader = device.create_shader_module(ShaderModuleDescriptor {
-        label: Some("pcg_update_scalars.wgsl"),
+        label: Some(&ctx.label("pcg_update_scalars.wgsl")),
         source: ShaderSource::Wgsl(include_str!("wgsl/pcg_update_scalars.wgsl").into()),
     });
 
     let pcg_update_scalars_bind_group_layout =
         device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: Some("pcg_update_scalars bgl0"),
+            label: Some(&ctx.label("pcg_update_scalars bgl0")),
             entries: &[
                 create_uniform_entry(0),        // Params
                 create_storage_entry(1, false), // scalar_results RW
@@ -64,13 +64,13 @@ pub fn create_pcg_update_scalars_pipeline(ctx: &GpuContext) -> PcgUpdateScalarsP
         });
 
     let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-        label: Some("pcg_update_scalars pipeline layout"),
+        label: Some(&ctx.label("pcg_update_scalars pipeline layout")),
         bind_group_layouts: &[&pcg_update_scalars_bind_group_layout],
         immediate_size: 0,
     });
 
     let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
-        label: Some("pcg_update_scalars pipeline"),
+        label: Some(&ctx.label("pcg_update_scalars pipeline")),
         layout: Some(&pipeline_layout),
         module: &shader,
         entry_point: Some("compute_main"),
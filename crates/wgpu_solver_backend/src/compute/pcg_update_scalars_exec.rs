@@ -32,8 +32,6 @@ pub struct PcgUpdateScalarsExecutor {
 
 impl PcgUpdateScalarsExecutor {
     pub fn create(ctx: &GpuContext) -> Self {
-        let device = &ctx.device;
-
         let pcg_update_scalars_pipeline = create_pcg_update_scalars_pipeline(ctx);
 
         // Same logic as in fea_app: 2 uses per iteration; 4 is safe headroom.
@@ -41,8 +39,8 @@ impl PcgUpdateScalarsExecutor {
 
         let mut params_buffers = Vec::with_capacity(params_buffers_pool_size);
         for i in 0..params_buffers_pool_size {
-            let buf = device.create_buffer(&BufferDescriptor {
-                label: Some(&format!("pcg update scalars params {}", i)),
+            let buf = ctx.create_buffer(&BufferDescriptor {
+                label: Some(&ctx.label(&format!("pcg update scalars params {}", i))),
                 size: 32, // 8 u32
                 usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
                 mapped_at_creation: false,
@@ -69,6 +67,7 @@ impl PcgUpdateScalarsExecutor {
     }
 
     /// Encode one `pcg_update_scalars.wgsl` dispatch.
+    #[allow(clippy::too_many_arguments)]
     pub fn encode_update_scalars(
         &self,
         ctx: &GpuContext,
@@ -109,7 +108,7 @@ impl PcgUpdateScalarsExecutor {
 
         // Dispatch 1 workgroup (WGSL uses @workgroup_size(1)).
         let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
-            label: Some("pcg_update_scalars pass"),
+            label: Some(&ctx.label("pcg_update_scalars pass")),
             timestamp_writes: None,
         });
         pass.set_pipeline(&self.pcg_update_scalars_pipeline.pipeline);
@@ -0,0 +1,536 @@
+use std::mem::size_of;
+use std::time::Instant;
+
+use bytemuck::{Pod, cast_slice};
+use futures::executor;
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, Buffer, BufferDescriptor, BufferUsages,
+    CommandEncoderDescriptor, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor,
+    ErrorFilter, Features, PollType, ShaderModuleDescriptor, ShaderSource,
+};
+
+use crate::{
+    gpu::{buffer::GpuBuffer, context::GpuContext, readback::readback_to_vec},
+    matrix::Csr,
+};
+
+// f32 vs f64 head-to-head on the same matrix.
+//
+// The production kernels are f32-only, so the benchmark carries its own small kernel set
+// (wgsl/precision_bench.wgsl) written against a `scalar` alias and compiled once per
+// precision. Both variants run the same three workloads:
+//
+// - dot:   `reps` dot-partials passes in one submit (the few partials are not reduced)
+// - spmv:  `reps` CSR SpMV passes in one submit
+// - solve: unpreconditioned CG from x0 = 0, scalars on the host (two readbacks per
+//          iteration), until ||r|| <= rel_tol ||b|| or max_iters
+//
+// Accuracy is the true relative residual ||b - A x|| / ||b|| of the returned x, computed on
+// the host in f64 -- not the recurrence residual, which hides the f32 drift.
+
+const WORKGROUP_SIZE: u32 = 256;
+
+/// An element type the benchmark kernels can be instantiated for.
+pub trait BenchScalar: Pod {
+    /// "f32" / "f64", also the WGSL type name.
+    const NAME: &'static str;
+
+    /// Device feature needed besides the defaults.
+    const REQUIRED_FEATURES: Features;
+
+    fn from_f64(v: f64) -> Self;
+    fn to_f64(self) -> f64;
+}
+
+impl BenchScalar for f32 {
+    const NAME: &'static str = "f32";
+    const REQUIRED_FEATURES: Features = Features::empty();
+
+    fn from_f64(v: f64) -> Self {
+        v as f32
+    }
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl BenchScalar for f64 {
+    const NAME: &'static str = "f64";
+    const REQUIRED_FEATURES: Features = Features::SHADER_F64;
+
+    fn from_f64(v: f64) -> Self {
+        v
+    }
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+/// Whether `ctx` can run the f64 variant.
+pub fn f64_supported(ctx: &GpuContext) -> bool {
+    ctx.features.contains(f64::REQUIRED_FEATURES)
+}
+
+/// Benchmark knobs.
+#[derive(Debug, Clone, Copy)]
+pub struct PrecisionBenchOptions {
+    /// Passes timed per dot / spmv measurement.
+    pub reps: usize,
+    pub max_iters: usize,
+    pub rel_tol: f64,
+}
+
+impl Default for PrecisionBenchOptions {
+    fn default() -> Self {
+        Self {
+            reps: 100,
+            max_iters: 2000,
+            rel_tol: 1e-6,
+        }
+    }
+}
+
+/// Results of one precision.
+#[derive(Debug, Clone)]
+pub struct PrecisionBenchRow {
+    /// "f32" or "f64".
+    pub precision: &'static str,
+
+    /// Wall time per pass (ms) and effective bandwidth (GB/s, minimum bytes moved).
+    pub dot_ms: f64,
+    pub dot_gb_per_s: f64,
+    pub spmv_ms: f64,
+    pub spmv_gb_per_s: f64,
+
+    /// Whole CG solve, wall time.
+    pub solve_ms: f64,
+    pub iterations: usize,
+    pub converged: bool,
+    /// True ||b - A x|| / ||b|| of the solution.
+    pub final_rel_residual: f64,
+}
+
+/// f64 row divided by f32 row (> 1: f64 is slower / less accurate).
+#[derive(Debug, Clone, Copy)]
+pub struct PrecisionRatios {
+    pub dot_time: f64,
+    pub spmv_time: f64,
+    pub solve_time: f64,
+    pub final_rel_residual: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PrecisionBenchReport {
+    /// f32 first; the f64 row is present only when the device supports it.
+    pub rows: Vec<PrecisionBenchRow>,
+    /// Why there is no f64 row, if there is none.
+    pub f64_skipped: Option<String>,
+}
+
+impl PrecisionBenchReport {
+    /// f64 / f32 ratios, when both rows exist.
+    pub fn ratios(&self) -> Option<PrecisionRatios> {
+        let f32_row = self.rows.iter().find(|r| r.precision == f32::NAME)?;
+        let f64_row = self.rows.iter().find(|r| r.precision == f64::NAME)?;
+        Some(PrecisionRatios {
+            dot_time: f64_row.dot_ms / f32_row.dot_ms,
+            spmv_time: f64_row.spmv_ms / f32_row.spmv_ms,
+            solve_time: f64_row.solve_ms / f32_row.solve_ms,
+            final_rel_residual: f64_row.final_rel_residual / f32_row.final_rel_residual,
+        })
+    }
+}
+
+/// Run the benchmark for f32 and, where supported, f64 on `csr` (square, SPD for the
+/// solve) with right-hand side `b`.
+pub fn run_precision_bench(
+    ctx: &GpuContext,
+    csr: &Csr,
+    b: &[f32],
+    options: &PrecisionBenchOptions,
+) -> Result<PrecisionBenchReport, String> {
+    let n = csr.n_rows as usize;
+    if csr.n_cols as usize != n || b.len() != n {
+        return Err(format!(
+            "precision bench: matrix is {}x{}, b has {} entries",
+            csr.n_rows,
+            csr.n_cols,
+            b.len()
+        ));
+    }
+    if n == 0 || csr.nnz == 0 {
+        return Err("precision bench: empty matrix".to_string());
+    }
+    if options.reps == 0 {
+        return Err("precision bench: reps must be > 0".to_string());
+    }
+
+    let mut rows = vec![run_one::<f32>(ctx, csr, b, options)?];
+    let f64_skipped = if f64_supported(ctx) {
+        rows.push(run_one::<f64>(ctx, csr, b, options)?);
+        None
+    } else {
+        Some(format!(
+            "{} has no SHADER_F64 support",
+            ctx.adapter_info.name
+        ))
+    };
+
+    Ok(PrecisionBenchReport { rows, f64_skipped })
+}
+
+/// Pipelines + buffers of one precision.
+struct Bench<'a, T: BenchScalar> {
+    ctx: &'a GpuContext,
+    n: u32,
+    groups: u32,
+
+    dot_pipeline: ComputePipeline,
+    spmv_pipeline: ComputePipeline,
+    axpy_pipeline: ComputePipeline,
+    xpay_pipeline: ComputePipeline,
+
+    x: GpuBuffer<T>,
+    r: GpuBuffer<T>,
+    p: GpuBuffer<T>,
+    q: GpuBuffer<T>,
+    partial: GpuBuffer<T>,
+    // [alpha, -alpha, beta]
+    coeffs: GpuBuffer<T>,
+
+    row_ptr: GpuBuffer<u32>,
+    col_idx: GpuBuffer<u32>,
+    values: GpuBuffer<T>,
+
+    // params[k] = [n, k, 0, 0]
+    params: Vec<Buffer>,
+}
+
+/// precision_bench.wgsl with every value in `scalar` ("f32" or "f64").
+pub fn precision_bench_wgsl(scalar: &str) -> String {
+    include_str!("wgsl/precision_bench.wgsl").replacen(
+        "alias scalar = f32;",
+        &format!("alias scalar = {scalar};"),
+        1,
+    )
+}
+
+const ALPHA: u32 = 0;
+const MINUS_ALPHA: u32 = 1;
+const BETA: u32 = 2;
+
+impl<'a, T: BenchScalar> Bench<'a, T> {
+    fn create(ctx: &'a GpuContext, csr: &Csr, b: &[f32]) -> Result<Self, String> {
+        let device = &ctx.device;
+        let n = csr.n_rows;
+        let groups = n.div_ceil(WORKGROUP_SIZE);
+
+        let wgsl = precision_bench_wgsl(T::NAME);
+
+        let scope = device.push_error_scope(ErrorFilter::Validation);
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some(&ctx.label("precision_bench.wgsl")),
+            source: ShaderSource::Wgsl(wgsl.into()),
+        });
+        let pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some(&ctx.label(entry_point)),
+                layout: None,
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let dot_pipeline = pipeline("dot_partials");
+        let spmv_pipeline = pipeline("spmv");
+        let axpy_pipeline = pipeline("axpy");
+        let xpay_pipeline = pipeline("xpay");
+        if let Some(e) = executor::block_on(scope.pop()) {
+            return Err(format!("precision bench ({}): {e}", T::NAME));
+        }
+
+        let convert = |v: &[f32]| v.iter().map(|&a| T::from_f64(a as f64)).collect::<Vec<T>>();
+        let b_t = convert(b);
+        let zeros = vec![T::from_f64(0.0); n as usize];
+
+        let params = (0..3u32)
+            .map(|k| {
+                let buffer = ctx.create_buffer(&BufferDescriptor {
+                    label: Some(&ctx.label("precision bench params")),
+                    size: 16, // [n,k,0,0]
+                    usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                ctx.queue
+                    .write_buffer(&buffer, 0, cast_slice(&[n, k, 0, 0]));
+                buffer
+            })
+            .collect();
+
+        Ok(Self {
+            ctx,
+            n,
+            groups,
+            dot_pipeline,
+            spmv_pipeline,
+            axpy_pipeline,
+            xpay_pipeline,
+            x: ctx.create_storage_buffer("bench x", &zeros, BufferUsages::empty()),
+            r: ctx.create_storage_buffer("bench r", &b_t, BufferUsages::empty()),
+            p: ctx.create_storage_buffer("bench p", &b_t, BufferUsages::empty()),
+            q: ctx.create_storage_buffer("bench q", &zeros, BufferUsages::empty()),
+            partial: ctx.create_storage_buffer_uninit(
+                "bench partial",
+                groups as usize,
+                BufferUsages::empty(),
+            ),
+            coeffs: ctx.create_storage_buffer(
+                "bench coeffs",
+                &[T::from_f64(0.0); 3],
+                BufferUsages::empty(),
+            ),
+            row_ptr: ctx.create_storage_buffer(
+                "bench row_ptr",
+                &csr.row_ptr,
+                BufferUsages::empty(),
+            ),
+            col_idx: ctx.create_storage_buffer(
+                "bench col_idx",
+                &csr.col_idx,
+                BufferUsages::empty(),
+            ),
+            values: ctx.create_storage_buffer(
+                "bench values",
+                &convert(&csr.values),
+                BufferUsages::empty(),
+            ),
+            params,
+        })
+    }
+
+    fn bind_group(&self, pipeline: &ComputePipeline, entries: &[(u32, &Buffer)]) -> BindGroup {
+        let entries: Vec<BindGroupEntry> = entries
+            .iter()
+            .map(|&(binding, buffer)| BindGroupEntry {
+                binding,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+        self.ctx.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("precision bench bind group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        })
+    }
+
+    /// partial = dot partials of (a, b).
+    fn dot_bind_group(&self, a: &GpuBuffer<T>, b: &GpuBuffer<T>) -> BindGroup {
+        self.bind_group(
+            &self.dot_pipeline,
+            &[
+                (0, &self.params[0]),
+                (1, &a.buffer),
+                (3, &self.partial.buffer),
+                (8, &b.buffer),
+            ],
+        )
+    }
+
+    /// out = A v.
+    fn spmv_bind_group(&self, v: &GpuBuffer<T>, out: &GpuBuffer<T>) -> BindGroup {
+        self.bind_group(
+            &self.spmv_pipeline,
+            &[
+                (0, &self.params[0]),
+                (1, &v.buffer),
+                (2, &out.buffer),
+                (5, &self.row_ptr.buffer),
+                (6, &self.col_idx.buffer),
+                (7, &self.values.buffer),
+            ],
+        )
+    }
+
+    /// axpy: y += coeffs[k] x, xpay: y = x + coeffs[k] y.
+    fn vec_op_bind_group(
+        &self,
+        pipeline: &ComputePipeline,
+        k: u32,
+        x: &GpuBuffer<T>,
+        y: &GpuBuffer<T>,
+    ) -> BindGroup {
+        self.bind_group(
+            pipeline,
+            &[
+                (0, &self.params[k as usize]),
+                (1, &x.buffer),
+                (2, &y.buffer),
+                (4, &self.coeffs.buffer),
+            ],
+        )
+    }
+
+    /// Encode + submit `passes`, then wait for the queue to drain.
+    fn submit(&self, passes: &[(&ComputePipeline, &BindGroup)]) {
+        let mut encoder = self
+            .ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("precision bench"),
+            });
+        for (pipeline, bind_group) in passes {
+            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("precision bench pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, *bind_group, &[]);
+            pass.dispatch_workgroups(self.groups, 1, 1);
+        }
+        self.ctx.queue.submit(Some(encoder.finish()));
+        let _ = self.ctx.device.poll(PollType::wait_indefinitely());
+    }
+
+    /// Average wall time of one pass over `reps` passes in one submit (after a warm-up).
+    fn time_passes(&self, pipeline: &ComputePipeline, bind_group: &BindGroup, reps: usize) -> f64 {
+        self.submit(&[(pipeline, bind_group)]);
+        let passes = vec![(pipeline, bind_group); reps];
+        let start = Instant::now();
+        self.submit(&passes);
+        start.elapsed().as_secs_f64() * 1e3 / reps as f64
+    }
+
+    /// Sum of the current partials (host side, f64).
+    fn read_dot(&self) -> f64 {
+        self.ctx.count_buffer_created();
+        executor::block_on(readback_to_vec::<T>(
+            &self.ctx.device,
+            &self.ctx.queue,
+            &self.partial.buffer,
+            self.partial.len,
+            Some("precision bench partials"),
+        ))
+        .into_iter()
+        .map(T::to_f64)
+        .sum()
+    }
+
+    fn write_coeff(&self, k: u32, v: f64) {
+        self.ctx.queue.write_buffer(
+            &self.coeffs.buffer,
+            k as u64 * size_of::<T>() as u64,
+            cast_slice(&[T::from_f64(v)]),
+        );
+    }
+
+    /// Plain CG; returns (iterations, converged).
+    fn solve(&self, b_norm: f64, options: &PrecisionBenchOptions) -> (usize, bool) {
+        let dot_rr = self.dot_bind_group(&self.r, &self.r);
+        let dot_pq = self.dot_bind_group(&self.p, &self.q);
+        let spmv_p = self.spmv_bind_group(&self.p, &self.q);
+        let x_update = self.vec_op_bind_group(&self.axpy_pipeline, ALPHA, &self.p, &self.x);
+        let r_update = self.vec_op_bind_group(&self.axpy_pipeline, MINUS_ALPHA, &self.q, &self.r);
+        let p_update = self.vec_op_bind_group(&self.xpay_pipeline, BETA, &self.r, &self.p);
+
+        let target = options.rel_tol * b_norm;
+        self.submit(&[(&self.dot_pipeline, &dot_rr)]);
+        let mut rr = self.read_dot();
+        if rr.sqrt() <= target {
+            return (0, true);
+        }
+
+        for k in 0..options.max_iters {
+            // p = r + beta p (not on the first iteration), q = A p, p^T q
+            let mut passes = Vec::with_capacity(3);
+            if k > 0 {
+                passes.push((&self.xpay_pipeline, &p_update));
+            }
+            passes.push((&self.spmv_pipeline, &spmv_p));
+            passes.push((&self.dot_pipeline, &dot_pq));
+            self.submit(&passes);
+            let alpha = rr / self.read_dot();
+
+            self.write_coeff(ALPHA, alpha);
+            self.write_coeff(MINUS_ALPHA, -alpha);
+            self.submit(&[
+                (&self.axpy_pipeline, &x_update),
+                (&self.axpy_pipeline, &r_update),
+                (&self.dot_pipeline, &dot_rr),
+            ]);
+            let rr_new = self.read_dot();
+            if rr_new.sqrt() <= target {
+                return (k + 1, true);
+            }
+
+            self.write_coeff(BETA, rr_new / rr);
+            rr = rr_new;
+        }
+
+        (options.max_iters, false)
+    }
+}
+
+fn run_one<T: BenchScalar>(
+    ctx: &GpuContext,
+    csr: &Csr,
+    b: &[f32],
+    options: &PrecisionBenchOptions,
+) -> Result<PrecisionBenchRow, String> {
+    let bench = Bench::<T>::create(ctx, csr, b)?;
+    let n = bench.n as f64;
+    let nnz = csr.nnz as f64;
+    let elem = size_of::<T>() as f64;
+
+    // dot reads two vectors; spmv reads values, col_idx, row_ptr, x and writes y.
+    let dot_bind_group = bench.dot_bind_group(&bench.r, &bench.p);
+    let dot_ms = bench.time_passes(&bench.dot_pipeline, &dot_bind_group, options.reps);
+    let dot_bytes = 2.0 * n * elem;
+
+    let spmv_bind_group = bench.spmv_bind_group(&bench.p, &bench.q);
+    let spmv_ms = bench.time_passes(&bench.spmv_pipeline, &spmv_bind_group, options.reps);
+    let spmv_bytes = nnz * (elem + 4.0) + (n + 1.0) * 4.0 + 2.0 * n * elem;
+
+    let b_norm = b
+        .iter()
+        .map(|&v| (v as f64) * (v as f64))
+        .sum::<f64>()
+        .sqrt();
+    let start = Instant::now();
+    let (iterations, converged) = bench.solve(b_norm, options);
+    let solve_ms = start.elapsed().as_secs_f64() * 1e3;
+
+    let x: Vec<f64> = executor::block_on(ctx.readback(&bench.x))
+        .into_iter()
+        .map(T::to_f64)
+        .collect();
+    let final_rel_residual = true_residual_norm(csr, b, &x) / b_norm.max(f64::MIN_POSITIVE);
+
+    let gb_per_s = |bytes: f64, ms: f64| bytes / (ms * 1e-3) / 1e9;
+    Ok(PrecisionBenchRow {
+        precision: T::NAME,
+        dot_ms,
+        dot_gb_per_s: gb_per_s(dot_bytes, dot_ms),
+        spmv_ms,
+        spmv_gb_per_s: gb_per_s(spmv_bytes, spmv_ms),
+        solve_ms,
+        iterations,
+        converged,
+        final_rel_residual,
+    })
+}
+
+/// ||b - A x|| in f64.
+fn true_residual_norm(csr: &Csr, b: &[f32], x: &[f64]) -> f64 {
+    (0..csr.n_rows as usize)
+        .map(|row| {
+            let (start, end) = (csr.row_ptr[row] as usize, csr.row_ptr[row + 1] as usize);
+            let ax: f64 = (start..end)
+                .map(|k| csr.values[k] as f64 * x[csr.col_idx[k] as usize])
+                .sum();
+            let r = b[row] as f64 - ax;
+            r * r
+        })
+        .sum::<f64>()
+        .sqrt()
+}
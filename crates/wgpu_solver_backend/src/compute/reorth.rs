@@ -0,0 +1,180 @@
+use wgpu::{BufferUsages, CommandEncoder};
+
+use crate::{
+    compute::{dot_scalar_exec::DotScalarExecutor, vec_ops_exec::VecOpsExecutor},
+    gpu::{buffer::GpuBuffer, context::GpuContext},
+};
+
+// Explicit A-reorthogonalization of the PCG search directions.
+//
+// In exact arithmetic every new direction p_{k+1} is A-conjugate to all previous ones;
+// in f32 that conjugacy decays and badly conditioned solves stall, repeating ground
+// the old directions already covered. With a window of the last k directions stored,
+// each new one is projected (modified Gram-Schmidt in the A inner product):
+//
+//   for j in window:  p <- p - (p^T A p_j / p_j^T A p_j) p_j
+//
+// The window keeps p_j and A p_j pre-scaled, so each step is one dot plus one axpy
+// with the coefficient taken straight from a scalar slot:
+//
+//   q_j = p_j / sqrt(p_j^T A p_j),   w_j = -A p_j / sqrt(p_j^T A p_j)
+//   c   = dot(w_j, p)                (= -p^T A q_j)
+//   p  <- p + c q_j
+//
+// The projected p is no longer z + beta p_old, so the step length switches from
+// alpha = r^T z / p^T A p to p^T r / p^T A p (equal in exact arithmetic).
+//
+// Cost: 2 k n floats of GPU memory (q and w per stored direction); per iteration one
+// copy, one clear and three vec-ops to store the newest direction, the extra dot p^T r,
+// plus one dot and one axpy per stored direction (k + 1 dots of length n on top of
+// PCG's three).
+
+/// How [`super::pcg_block_jacobi_csr_wgpu`] keeps the search directions conjugate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReorthPolicy {
+    /// Plain (P)CG recurrences.
+    #[default]
+    None,
+    /// Reorthogonalize against the last k directions (ring buffer). k >= the iteration
+    /// count is full reorthogonalization.
+    Window(u32),
+}
+
+impl ReorthPolicy {
+    /// Directions kept (0 for `None`).
+    pub fn window_len(self) -> usize {
+        match self {
+            ReorthPolicy::None => 0,
+            ReorthPolicy::Window(k) => k as usize,
+        }
+    }
+}
+
+/// Directions a single reorthogonalization command buffer handles, so its dots and
+/// axpys stay inside the executors' uniform pools (16 each).
+pub const REORTH_DIRECTIONS_PER_SUBMIT: usize = 4;
+
+/// Ring buffer of the last `capacity` scaled directions (q_j, w_j) on the GPU.
+pub struct DirectionWindow {
+    q: Vec<GpuBuffer<f32>>,
+    w: Vec<GpuBuffer<f32>>,
+    len: usize,
+    next: usize,
+    n: u32,
+}
+
+impl DirectionWindow {
+    /// Allocate `capacity` (q, w) pairs of length n up front.
+    pub fn create(ctx: &GpuContext, n: usize, capacity: usize) -> Self {
+        let vectors = |label: &str| -> Vec<GpuBuffer<f32>> {
+            (0..capacity)
+                .map(|i| {
+                    ctx.create_storage_buffer_uninit::<f32>(
+                        &format!("{label} {i}"),
+                        n,
+                        BufferUsages::empty(),
+                    )
+                })
+                .collect()
+        };
+
+        Self {
+            q: vectors("reorth q"),
+            w: vectors("reorth w"),
+            len: 0,
+            next: 0,
+            n: n as u32,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.q.len()
+    }
+
+    /// Directions currently stored.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Encode storing (p, A p) as the newest direction, scaled by 1 / sqrt(p^T A p) read
+    /// from `scalar_results[p_ap_index]`; overwrites the oldest one when full.
+    ///
+    /// Uses three vec-ops params from `vec_ops_exec`'s pool.
+    #[allow(clippy::too_many_arguments)]
+    pub fn encode_push(
+        &mut self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        vec_ops_exec: &VecOpsExecutor,
+        p: &wgpu::Buffer,
+        ap: &wgpu::Buffer,
+        scalar_results: &wgpu::Buffer,
+        p_ap_index: u32,
+    ) {
+        let (q, w) = (&self.q[self.next], &self.w[self.next]);
+
+        encoder.copy_buffer_to_buffer(p, 0, &q.buffer, 0, q.byte_len());
+        encoder.clear_buffer(&w.buffer, 0, None);
+        vec_ops_exec.encode_axpy_inplace(ctx, encoder, ap, &w.buffer, self.n, -1.0);
+        for v in [q, w] {
+            vec_ops_exec.encode_normalize_inplace_from_scalar_results(
+                ctx,
+                encoder,
+                &v.buffer,
+                self.n,
+                scalar_results,
+                p_ap_index,
+            );
+        }
+
+        self.next = (self.next + 1) % self.capacity();
+        self.len = (self.len + 1).min(self.capacity());
+    }
+
+    /// Encode the projection of directions `chunk_start..chunk_start + chunk_len` (oldest
+    /// first) out of `p`, using `scalar_results[coefficient_index]` as scratch.
+    ///
+    /// Callers split the window into chunks of at most [`REORTH_DIRECTIONS_PER_SUBMIT`]
+    /// and submit each one separately with freshly reset params cursors.
+    #[allow(clippy::too_many_arguments)]
+    pub fn encode_project_out(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        vec_ops_exec: &VecOpsExecutor,
+        dot_scalar_exec: &DotScalarExecutor,
+        p: &wgpu::Buffer,
+        coefficient_index: u32,
+        chunk_start: usize,
+        chunk_len: usize,
+    ) {
+        let oldest = (self.next + self.capacity() - self.len) % self.capacity();
+
+        for j in chunk_start..(chunk_start + chunk_len).min(self.len) {
+            let slot = (oldest + j) % self.capacity();
+
+            // c = dot(w_j, p); p = p + c q_j
+            dot_scalar_exec.encode_dot_scalar_into(
+                ctx,
+                encoder,
+                &self.w[slot].buffer,
+                p,
+                self.n,
+                coefficient_index,
+            );
+            vec_ops_exec.encode_axpy_inplace_from_scalar_results(
+                ctx,
+                encoder,
+                &self.q[slot].buffer,
+                p,
+                self.n,
+                dot_scalar_exec.scalar_results_buffer(),
+                coefficient_index,
+            );
+        }
+    }
+}
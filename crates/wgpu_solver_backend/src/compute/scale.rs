@@ -0,0 +1,73 @@
+use wgpu::{Buffer, CommandEncoder};
+
+use crate::{compute::vec_ops_exec::VecOpsExecutor, gpu::context::GpuContext};
+
+/// x = alpha * x in place on a length-n GPU vector, fixed n.
+///
+/// - `encode` reads alpha from `alpha_gpu[0]`, so a scalar a previous pass computed
+///   is used without a host roundtrip (scale_from_scalar_results.wgsl).
+/// - `encode_scalar` takes a host alpha through the params uniform (scale.wgsl).
+///
+/// Both record one pass and do NOT submit. The kernels use 256-wide workgroups and are
+/// grid-strided, so the workgroup count is clamped to the per-dimension dispatch limit
+/// and any n is covered. Like `AxpyExecutor`, this is a fixed-length front end of the
+/// SCALE kernels in `VecOpsExecutor`.
+pub struct ScaleExecutor {
+    n: u32,
+    vec_ops: VecOpsExecutor,
+}
+
+impl ScaleExecutor {
+    pub fn create(ctx: &GpuContext, n: u32) -> Self {
+        Self {
+            n,
+            vec_ops: VecOpsExecutor::create(ctx),
+        }
+    }
+
+    pub fn n(&self) -> u32 {
+        self.n
+    }
+
+    /// Encode x = alpha_gpu[0] * x. `alpha_gpu` must have STORAGE usage.
+    ///
+    /// Panics if x holds fewer than n f32 or `alpha_gpu` is empty.
+    pub fn encode(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        alpha_gpu: &Buffer,
+        x_gpu: &Buffer,
+    ) {
+        self.check_buffer(x_gpu);
+        assert!(
+            alpha_gpu.size() >= 4,
+            "ScaleExecutor: alpha buffer is empty"
+        );
+        self.vec_ops
+            .encode_scale_inplace_from_scalar_results(ctx, encoder, x_gpu, self.n, alpha_gpu, 0);
+    }
+
+    /// Encode x = alpha * x with a host alpha.
+    ///
+    /// Panics if x holds fewer than n f32.
+    pub fn encode_scalar(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        alpha: f32,
+        x_gpu: &Buffer,
+    ) {
+        self.check_buffer(x_gpu);
+        self.vec_ops
+            .encode_scale_inplace(ctx, encoder, x_gpu, self.n, alpha);
+    }
+
+    fn check_buffer(&self, x: &Buffer) {
+        assert!(
+            x.size() >= self.n as u64 * 4,
+            "ScaleExecutor: x holds fewer than n = {} f32",
+            self.n
+        );
+    }
+}
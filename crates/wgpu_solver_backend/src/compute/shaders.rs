@@ -0,0 +1,155 @@
+use wgpu::naga::{
+    front::wgsl,
+    valid::{Capabilities, ValidationFlags, Validator},
+};
+
+use crate::compute::{
+    Precision, block_jacobi::LuStorage, block_jacobi::block_jacobi_wgsl,
+    dot_partials::with_accumulator, precision_bench::precision_bench_wgsl,
+};
+
+// Offline WGSL checks: every shader the crate ships, in every variant the host builds
+// from it (accumulator / storage aliases swapped), run through naga's WGSL front end and
+// validator without a device, so a syntax or type error shows up in `wgsl-validate-test`
+// instead of at pipeline creation on the first GPU that runs it.
+//
+// Validation uses every naga capability (f64, f16, subgroups, ...): whether a device
+// supports a variant is checked at pipeline creation, not here. Override values are not
+// substituted; the defaults written in the shaders are what gets validated.
+
+/// One WGSL source as the host passes it to `create_shader_module`.
+#[derive(Debug, Clone)]
+pub struct WgslShader {
+    /// File name under `compute/wgsl/`, with the variant in parentheses, e.g.
+    /// `dot_partials.wgsl (accum = f64)`.
+    pub name: String,
+    pub source: String,
+}
+
+impl WgslShader {
+    fn new(name: impl Into<String>, source: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            source: source.into(),
+        }
+    }
+
+    /// The file name without the variant.
+    pub fn file_name(&self) -> &str {
+        self.name.split(" (").next().unwrap_or(&self.name)
+    }
+}
+
+/// Every shader of `compute/wgsl/` and its host-side variants. A new .wgsl file has to
+/// be listed here; `wgsl-validate-test` fails on files that are not.
+pub fn shipped_shaders() -> Vec<WgslShader> {
+    let mut shaders: Vec<WgslShader> = [
+        (
+            "additive_schwarz_combine.wgsl",
+            include_str!("wgsl/additive_schwarz_combine.wgsl"),
+        ),
+        (
+            "additive_schwarz_solve.wgsl",
+            include_str!("wgsl/additive_schwarz_solve.wgsl"),
+        ),
+        ("axpy.wgsl", include_str!("wgsl/axpy.wgsl")),
+        (
+            "axpy_from_scalar_results.wgsl",
+            include_str!("wgsl/axpy_from_scalar_results.wgsl"),
+        ),
+        ("batched_dot.wgsl", include_str!("wgsl/batched_dot.wgsl")),
+        ("block_lsq.wgsl", include_str!("wgsl/block_lsq.wgsl")),
+        (
+            "block_lu_factor.wgsl",
+            include_str!("wgsl/block_lu_factor.wgsl"),
+        ),
+        ("block_norms.wgsl", include_str!("wgsl/block_norms.wgsl")),
+        (
+            "diagonal_jacobi.wgsl",
+            include_str!("wgsl/diagonal_jacobi.wgsl"),
+        ),
+        (
+            "dot_partials_subgroup.wgsl",
+            include_str!("wgsl/dot_partials_subgroup.wgsl"),
+        ),
+        (
+            "fixed_point_dot.wgsl",
+            include_str!("wgsl/fixed_point_dot.wgsl"),
+        ),
+        (
+            "max_abs_change.wgsl",
+            include_str!("wgsl/max_abs_change.wgsl"),
+        ),
+        (
+            "normalize_from_scalar_results.wgsl",
+            include_str!("wgsl/normalize_from_scalar_results.wgsl"),
+        ),
+        (
+            "pcg_update_scalars.wgsl",
+            include_str!("wgsl/pcg_update_scalars.wgsl"),
+        ),
+        ("scale.wgsl", include_str!("wgsl/scale.wgsl")),
+        (
+            "scale_from_scalar_results.wgsl",
+            include_str!("wgsl/scale_from_scalar_results.wgsl"),
+        ),
+        ("scaled_copy.wgsl", include_str!("wgsl/scaled_copy.wgsl")),
+        ("spmv.wgsl", include_str!("wgsl/spmv.wgsl")),
+        (
+            "spmv_warp_row.wgsl",
+            include_str!("wgsl/spmv_warp_row.wgsl"),
+        ),
+        ("ssor_sweep.wgsl", include_str!("wgsl/ssor_sweep.wgsl")),
+        (
+            "triangular_solve.wgsl",
+            include_str!("wgsl/triangular_solve.wgsl"),
+        ),
+        (
+            "waxpby_from_scalar_results.wgsl",
+            include_str!("wgsl/waxpby_from_scalar_results.wgsl"),
+        ),
+        (
+            "xpby_from_scalar_results.wgsl",
+            include_str!("wgsl/xpby_from_scalar_results.wgsl"),
+        ),
+    ]
+    .into_iter()
+    .map(|(name, source)| WgslShader::new(name, source))
+    .collect();
+
+    for (name, source) in [
+        ("dot_partials.wgsl", include_str!("wgsl/dot_partials.wgsl")),
+        ("dot_reduce.wgsl", include_str!("wgsl/dot_reduce.wgsl")),
+        ("dot_narrow.wgsl", include_str!("wgsl/dot_narrow.wgsl")),
+    ] {
+        for accumulator in [Precision::F32, Precision::F64] {
+            shaders.push(WgslShader::new(
+                format!("{name} (accum = {})", accumulator.wgsl_type()),
+                with_accumulator(source, accumulator),
+            ));
+        }
+    }
+    for (storage, ty) in [(LuStorage::F32, "f32"), (LuStorage::F16, "f16")] {
+        shaders.push(WgslShader::new(
+            format!("block_jacobi.wgsl (lu_t = {ty})"),
+            block_jacobi_wgsl(storage),
+        ));
+    }
+    for scalar in ["f32", "f64"] {
+        shaders.push(WgslShader::new(
+            format!("precision_bench.wgsl (scalar = {scalar})"),
+            precision_bench_wgsl(scalar),
+        ));
+    }
+    shaders
+}
+
+/// Parse and validate `source` with naga; the error is naga's diagnostic, rendered
+/// against the source (line, column and the offending span).
+pub fn validate_wgsl(source: &str) -> Result<(), String> {
+    let module = wgsl::parse_str(source).map_err(|e| e.emit_to_string(source))?;
+    Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|e| e.emit_to_string(source))?;
+    Ok(())
+}
@@ -1,8 +1,8 @@
 use wgpu::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
     BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, ComputePipeline,
-    ComputePipelineDescriptor, Device, PipelineLayoutDescriptor, ShaderModuleDescriptor,
-    ShaderSource, ShaderStages,
+    ComputePipelineDescriptor, Device, DeviceType, PipelineLayoutDescriptor,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages,
 };
 
 use crate::gpu::context::GpuContext;
@@ -12,6 +12,66 @@ pub struct SpmvPipeline {
     pub spmv_bind_group_layout: BindGroupLayout,
 }
 
+/// Average non-zeros per row from which [`SpmvStrategy::Auto`] picks `WarpRow` on GPUs:
+/// one full chunk per row, the point where short rows stop leaving lanes idle.
+///
+/// Measure it on the target with `spmv-strategy-bench`. On CPU adapters (llvmpipe) the
+/// warp-row kernel lost at every row length up to 512 (1M non-zeros: 7x slower at 512,
+/// 300x at 4). Its time there tracks the workgroup count rather than nnz, since a kernel
+/// with barriers pays a fixed cost per workgroup, so `Auto` never picks it there.
+pub const SPMV_WARP_ROW_MIN_AVG_NNZ: u32 = 32;
+
+/// Which CSR kernel an `SpmvExecutor` runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpmvStrategy {
+    /// One invocation per row (spmv.wgsl). Cheapest for short rows; rows of hundreds of
+    /// non-zeros serialize on one invocation while the rest of its SIMD group waits.
+    #[default]
+    ScalarRow,
+    /// One 32-lane chunk per row, partial sums reduced in workgroup memory
+    /// (spmv_warp_row.wgsl). For wide rows; wastes lanes on rows shorter than 32.
+    WarpRow,
+    /// `WarpRow` when nnz / n_rows >= [`SPMV_WARP_ROW_MIN_AVG_NNZ`] on a non-CPU adapter,
+    /// else `ScalarRow`.
+    Auto,
+}
+
+impl SpmvStrategy {
+    /// The kernel this strategy runs for a matrix of this shape on an adapter of this
+    /// type (never `Auto`).
+    pub fn resolve(self, device_type: DeviceType, n_rows: u32, nnz: u32) -> Self {
+        match self {
+            SpmvStrategy::Auto => {
+                if device_type != DeviceType::Cpu
+                    && n_rows > 0
+                    && nnz / n_rows >= SPMV_WARP_ROW_MIN_AVG_NNZ
+                {
+                    SpmvStrategy::WarpRow
+                } else {
+                    SpmvStrategy::ScalarRow
+                }
+            }
+            explicit => explicit,
+        }
+    }
+
+    /// Rows one 256-invocation workgroup covers per grid-stride step (of a resolved strategy).
+    pub fn rows_per_workgroup(self) -> u32 {
+        match self {
+            SpmvStrategy::WarpRow => 8,
+            SpmvStrategy::ScalarRow | SpmvStrategy::Auto => 256,
+        }
+    }
+
+    /// Kernel name in `KernelLaunch` reports.
+    pub fn kernel_name(self) -> &'static str {
+        match self {
+            SpmvStrategy::WarpRow => "spmv_warp_row",
+            SpmvStrategy::ScalarRow | SpmvStrategy::Auto => "spmv",
+        }
+    }
+}
+
 fn create_uniform_entry(binding: u32) -> BindGroupLayoutEntry {
     BindGroupLayoutEntry {
         binding,
@@ -40,15 +100,31 @@ fn create_storage_entry(binding: u32, is_read_only: bool) -> BindGroupLayoutEntr
     }
 }
 
+/// The scalar-row kernel (spmv.wgsl).
 pub fn create_spmv_pipeline(ctx: &GpuContext) -> SpmvPipeline {
+    create_spmv_pipeline_for(ctx, SpmvStrategy::ScalarRow)
+}
+
+/// The kernel of a resolved `strategy` (`Auto` is treated as `ScalarRow`; resolve it
+/// first with [`SpmvStrategy::resolve`]). Both kernels use the same bind group layout.
+pub fn create_spmv_pipeline_for(ctx: &GpuContext, strategy: SpmvStrategy) -> SpmvPipeline {
     let device = &ctx.device;
 
     // ------------------------------------------------------------------------
     // Shader module
     // ------------------------------------------------------------------------
+    let (name, source) = match strategy {
+        SpmvStrategy::WarpRow => (
+            "spmv_warp_row.wgsl",
+            include_str!("wgsl/spmv_warp_row.wgsl"),
+        ),
+        SpmvStrategy::ScalarRow | SpmvStrategy::Auto => {
+            ("spmv.wgsl", include_str!("wgsl/spmv.wgsl"))
+        }
+    };
     let shader = device.create_shader_module(ShaderModuleDescriptor {
-        label: Some("spmv.wgsl"),
-        source: ShaderSource::Wgsl(include_str!("wgsl/spmv.wgsl").into()),
+        label: Some(&ctx.label(name)),
+        source: ShaderSource::Wgsl(source.into()),
     });
 
     // ------------------------------------------------------------------------
@@ -61,7 +137,7 @@ pub fn create_spmv_pipeline(ctx: &GpuContext) -> SpmvPipeline {
     //   5: y       (RW storage)
     // ------------------------------------------------------------------------
     let spmv_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-        label: Some("spmv bgl0"),
+        label: Some(&ctx.label("spmv bgl0")),
         entries: &[
             create_uniform_entry(0),
             create_storage_entry(1, true),
@@ -77,7 +153,7 @@ pub fn create_spmv_pipeline(ctx: &GpuContext) -> SpmvPipeline {
     // (wgpu recent versions use `immediate_size` instead of push_constant_ranges)
     // ------------------------------------------------------------------------
     let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-        label: Some("spmv pipeline layout"),
+        label: Some(&ctx.label("spmv pipeline layout")),
         bind_group_layouts: &[&spmv_bind_group_layout],
         immediate_size: 0,
     });
@@ -86,7 +162,7 @@ pub fn create_spmv_pipeline(ctx: &GpuContext) -> SpmvPipeline {
     // Compute pipeline
     // ------------------------------------------------------------------------
     let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
-        label: Some("spmv pipeline"),
+        label: Some(&ctx.label(&format!("{} pipeline", strategy.kernel_name()))),
         layout: Some(&pipeline_layout),
         module: &shader,
         entry_point: Some("compute_main"),
@@ -100,6 +176,7 @@ pub fn create_spmv_pipeline(ctx: &GpuContext) -> SpmvPipeline {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_spmv_bind_group(
     device: &Device,
     spmv_bind_group_layout: &BindGroupLayout,
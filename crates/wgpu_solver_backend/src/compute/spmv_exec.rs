@@ -1,40 +1,59 @@
-use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::util::BufferInitDescriptor;
 use wgpu::{
     BindGroup, Buffer, BufferDescriptor, BufferUsages, CommandEncoder, ComputePassDescriptor,
 };
 
-use crate::compute::spmv::{SpmvPipeline, create_spmv_bind_group, create_spmv_pipeline};
+use crate::compute::occupancy::KernelLaunch;
+use crate::compute::operator::Rank1Update;
+use crate::compute::spmv::{
+    SpmvPipeline, SpmvStrategy, create_spmv_bind_group, create_spmv_pipeline_for,
+};
 use crate::gpu::context::GpuContext;
 
 /// SpmvExecutor (native wgpu version)
 ///
 /// Owns persistent GPU resources for CSR SpMV:
 ///   - CSR structure buffers: row_ptr, col_idx, values (uploaded once)
-///   - params uniform buffer: n_rows (written once)
+///   - params uniform buffer: n_rows, nnz (written once)
 ///   - internal x/y vectors used by the shader:
 ///       x_buffer: input vector for A*x (copy your current vector into it)
 ///       y_buffer: output vector (SpMV result), reused every call
+///
+/// Empty rows (row_ptr[i] == row_ptr[i + 1], e.g. equations eliminated by boundary
+/// conditions) still get their invocation and write an exact +0.0, so y never keeps
+/// stale values from a previous call. A matrix without any non-zeros is allowed too.
+///
+/// `encode_apply` binds caller vectors instead of the internal x/y (no copies).
+/// Rows past the device's dispatch limit (65535 workgroups of 256 rows by default, 8 rows
+/// for `WarpRow`) are covered by a grid-stride loop in the kernel, so any n_rows works.
+///
+/// The kernel is fixed at creation: one invocation per row (`create`, `try_create`), or
+/// any [`SpmvStrategy`] through [`SpmvExecutor::try_create_with_strategy`].
+#[allow(clippy::doc_overindented_list_items)]
 pub struct SpmvExecutor {
     n_rows: u32,
 
+    // resolved (never Auto)
+    strategy: SpmvStrategy,
+
+    // ceil(n_rows / rows per workgroup), capped at max_compute_workgroups_per_dimension
+    workgroups: u32,
+
     // Pipeline + bind group
     spmv_pipeline: SpmvPipeline,
     spmv_bind_group: BindGroup,
 
     // Persistent buffers (created once)
-    #[allow(dead_code)]
     params_buffer: Buffer,
-    #[allow(dead_code)]
     row_ptr_buffer: Buffer,
-    #[allow(dead_code)]
     col_idx_buffer: Buffer,
-    #[allow(dead_code)]
     values_buffer: Buffer,
     x_buffer: Buffer,
     y_buffer: Buffer,
 }
 
 impl SpmvExecutor {
+    /// [`SpmvExecutor::try_create`] for input known to be consistent; panics otherwise.
     pub fn create(
         ctx: &GpuContext,
         n_rows: u32,
@@ -42,51 +61,119 @@ impl SpmvExecutor {
         col_idx_u32: &[u32],
         values_f32: &[f32],
     ) -> Self {
+        Self::try_create(ctx, n_rows, row_ptr_u32, col_idx_u32, values_f32)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Upload a CSR matrix. Fails unless `row_ptr` has n_rows + 1 entries, starts at 0,
+    /// is non-decreasing and ends at nnz = `col_idx.len()` = `values.len()`.
+    ///
+    /// Column indices are not checked against n_rows (wgpu keeps an out-of-range read of
+    /// x in bounds, but the product is meaningless); the CSR loader validates them.
+    pub fn try_create(
+        ctx: &GpuContext,
+        n_rows: u32,
+        row_ptr_u32: &[u32],
+        col_idx_u32: &[u32],
+        values_f32: &[f32],
+    ) -> Result<Self, String> {
+        Self::try_create_with_strategy(
+            ctx,
+            n_rows,
+            row_ptr_u32,
+            col_idx_u32,
+            values_f32,
+            SpmvStrategy::ScalarRow,
+        )
+    }
+
+    /// [`SpmvExecutor::try_create`] with the kernel chosen by `strategy` (`Auto` resolves
+    /// here, on nnz / n_rows and the adapter type; see [`SpmvExecutor::strategy`]).
+    pub fn try_create_with_strategy(
+        ctx: &GpuContext,
+        n_rows: u32,
+        row_ptr_u32: &[u32],
+        col_idx_u32: &[u32],
+        values_f32: &[f32],
+        strategy: SpmvStrategy,
+    ) -> Result<Self, String> {
+        if row_ptr_u32.len() != n_rows as usize + 1 {
+            return Err(format!(
+                "SpmvExecutor: row_ptr has {} entries, expected n_rows + 1 = {}",
+                row_ptr_u32.len(),
+                n_rows as usize + 1
+            ));
+        }
+        if row_ptr_u32[0] != 0 || !row_ptr_u32.is_sorted() {
+            return Err("SpmvExecutor: row_ptr must start at 0 and be non-decreasing".into());
+        }
+        let nnz = row_ptr_u32[n_rows as usize];
+        if col_idx_u32.len() != nnz as usize || values_f32.len() != nnz as usize {
+            return Err(format!(
+                "SpmvExecutor: row_ptr ends at nnz = {nnz}, but col_idx has {} and values {} entries",
+                col_idx_u32.len(),
+                values_f32.len()
+            ));
+        }
+
         let device = &ctx.device;
 
         // 1) Create pipeline (once).
-        let spmv_pipeline = create_spmv_pipeline(ctx);
+        let strategy = strategy.resolve(ctx.adapter_info.device_type, n_rows, nnz);
+        let spmv_pipeline = create_spmv_pipeline_for(ctx, strategy);
 
-        // 2) Params uniform (once): { n_rows, 0, 0, 0 }
-        let params_words: [u32; 4] = [n_rows, 0, 0, 0];
-        let params_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("spmv params"),
+        // 2) Params uniform (once): { n_rows, nnz, 0, 0 }
+        let params_words: [u32; 4] = [n_rows, nnz, 0, 0];
+        let params_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("spmv params")),
             contents: bytemuck::cast_slice(&params_words),
             usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         });
 
         // 3) CSR buffers (once).
         // row_ptr length must be n_rows + 1; col_idx/values length must be nnz.
-        let row_ptr_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("spmv row_ptr"),
+        // wgpu rejects zero-sized storage bindings, so nnz == 0 (all rows empty) gets a
+        // one-element dummy that the shader never reads (every row loop is empty).
+        let col_idx_u32: &[u32] = if col_idx_u32.is_empty() {
+            &[0]
+        } else {
+            col_idx_u32
+        };
+        let values_f32: &[f32] = if values_f32.is_empty() {
+            &[0.0]
+        } else {
+            values_f32
+        };
+        let row_ptr_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("spmv row_ptr")),
             contents: bytemuck::cast_slice(row_ptr_u32),
             usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
         });
 
-        let col_idx_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("spmv col_idx"),
+        let col_idx_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("spmv col_idx")),
             contents: bytemuck::cast_slice(col_idx_u32),
             usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
         });
 
-        let values_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("spmv values"),
+        let values_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("spmv values")),
             contents: bytemuck::cast_slice(values_f32),
             usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
         });
 
         // 4) Internal x/y buffers (reused every encode_spmv call).
         // x is read-only in WGSL; needs COPY_DST (we fill via GPU->GPU copy).
-        let x_buffer = device.create_buffer(&BufferDescriptor {
-            label: Some("spmv x"),
+        let x_buffer = ctx.create_buffer(&BufferDescriptor {
+            label: Some(&ctx.label("spmv x")),
             size: (n_rows as u64) * 4,
             usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
         // y is read_write and used by other kernels; keep COPY_SRC for optional debug readback.
-        let y_buffer = device.create_buffer(&BufferDescriptor {
-            label: Some("spmv y"),
+        let y_buffer = ctx.create_buffer(&BufferDescriptor {
+            label: Some(&ctx.label("spmv y")),
             size: (n_rows as u64) * 4,
             usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
             mapped_at_creation: false,
@@ -104,8 +191,14 @@ impl SpmvExecutor {
             &y_buffer,
         );
 
-        Self {
+        let workgroups = n_rows
+            .div_ceil(strategy.rows_per_workgroup())
+            .min(device.limits().max_compute_workgroups_per_dimension);
+
+        Ok(Self {
             n_rows,
+            strategy,
+            workgroups,
             spmv_pipeline,
             spmv_bind_group,
             params_buffer,
@@ -114,7 +207,7 @@ impl SpmvExecutor {
             values_buffer,
             x_buffer,
             y_buffer,
-        }
+        })
     }
 
     /// Encode the CSR SpMV compute pass:
@@ -127,10 +220,65 @@ impl SpmvExecutor {
 
         pass.set_pipeline(&self.spmv_pipeline.pipeline);
         pass.set_bind_group(0, &self.spmv_bind_group, &[]);
+        pass.dispatch_workgroups(self.workgroups, 1, 1);
+    }
+
+    /// Encode y_gpu = A * x_gpu on caller buffers (n_rows f32 each, distinct), leaving
+    /// the internal x/y untouched.
+    ///
+    /// Creates its bind group per call because x/y vary; `encode_spmv` on the internal
+    /// vectors reuses one.
+    pub fn encode_apply(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        x_gpu: &Buffer,
+        y_gpu: &Buffer,
+    ) {
+        let bind_group = create_spmv_bind_group(
+            &ctx.device,
+            &self.spmv_pipeline.spmv_bind_group_layout,
+            &self.params_buffer,
+            &self.row_ptr_buffer,
+            &self.col_idx_buffer,
+            &self.values_buffer,
+            x_gpu,
+            y_gpu,
+        );
 
-        let workgroup_size = 256u32;
-        let groups_x = self.n_rows.div_ceil(workgroup_size);
-        pass.dispatch_workgroups(groups_x, 1, 1);
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some(&ctx.label("spmv apply pass")),
+            timestamp_writes: None,
+        });
+
+        pass.set_pipeline(&self.spmv_pipeline.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(self.workgroups, 1, 1);
+    }
+
+    pub fn n_rows(&self) -> u32 {
+        self.n_rows
+    }
+
+    /// The kernel in use (`Auto` already resolved).
+    pub fn strategy(&self) -> SpmvStrategy {
+        self.strategy
+    }
+
+    /// Launch shape of one `encode_spmv` / `encode_apply`.
+    pub fn kernel_launch(&self) -> KernelLaunch {
+        KernelLaunch::new(self.strategy.kernel_name(), 256, self.workgroups)
+    }
+
+    /// The operator y = A x + u (v·x), i.e. A + u v^T without reassembling A; see
+    /// [`Rank1Update`] (also for stacking several updates).
+    pub fn with_rank1_update<'a>(
+        &'a self,
+        ctx: &GpuContext,
+        u: &'a Buffer,
+        v: &'a Buffer,
+    ) -> Result<Rank1Update<'a>, String> {
+        Rank1Update::new(ctx, self, u, v)
     }
 
     /// Output buffer produced by encode_spmv(): y = A*x
@@ -0,0 +1,639 @@
+use std::time::Instant;
+
+use futures::executor;
+use wgpu::{Buffer, CommandEncoder, CommandEncoderDescriptor};
+
+use crate::{
+    compute::{
+        SolveConfig,
+        dot_scalar_exec::DotScalarExecutor,
+        ilu0_exec::{Ilu0Executor, Ilu0Levels},
+        negative_curvature_error,
+        operator::{LinearOperator, ReleaseBuffersOnDrop},
+        spmv_exec::SpmvExecutor,
+        unsupported_option,
+        vec_ops_exec::VecOpsExecutor,
+    },
+    gpu::{buffer::GpuBuffer, buffer_pool::PooledBuffer, context::GpuContext},
+    matrix::Csr,
+    reference,
+};
+
+// SSOR-preconditioned CG, with an optional Eisenstat trick.
+//
+// Split A = L + D + U (strictly lower, diagonal, strictly upper; A SPD, so U = L^T).
+// SSOR with relaxation w in (0, 2) is
+//
+//   M = (D + w L) D^{-1} (D + w U) / (w (2 - w))
+//
+// and runs as two triangular sweeps through `Ilu0Executor`, with the factors
+// (I + w L D^{-1}) (unit lower) and (D + w U) / (w (2 - w)) (upper). Plain SSOR-CG pays
+// one SpMV plus both sweeps per iteration.
+//
+// Eisenstat's trick removes the SpMV. It needs a unit diagonal, so the system is first
+// scaled symmetrically, A^ = S A S with S = D^{-1/2} and A^ x^ = S b, x = S x^. SSOR is
+// invariant under this scaling (M(A^) = S M(A) S), so the iterates are the same. With
+// L^, U^ the strict triangles of A^ and
+//
+//   M1 = I / w + L^,   M2 = I / w + U^ = M1^T,   A^ = M1 + M2 - (2 / w - 1) I,
+//
+// CG runs on the split-preconditioned system
+//
+//   A~ x~ = b~,   A~ = M1^{-1} A^ M2^{-1},   b~ = M1^{-1} S b,   x = S M2^{-1} x~,
+//
+// whose operator needs no product with A at all:
+//
+//   A~ v = t + M1^{-1} (v + (1 - 2 / w) t),   t = M2^{-1} v.
+//
+// That is one backward sweep, one forward sweep and three vector ops (M1^{-1} is applied
+// as w (I + w L^)^{-1}, the w folded into the vector ops). The remaining preconditioner
+// is the scalar I / w, which CG does not see, so the transformed CG runs without one.
+// A~ is SPD and similar to (a multiple of) M^{-1} A, so in exact arithmetic the
+// iterations match plain SSOR-CG one for one.
+//
+// The scaling S must be computed before the solve: it is baked into the factors and
+// the right-hand side on the host (`EisenstatSsorOperator::from_csr`), so A's diagonal
+// has to be known and positive up front, and x0 / b / x are transformed on the way in
+// and out.
+//
+// Both variants stop like PCG, on ||r|| of the original system r = b - A x (the CG
+// recurrence, not a recomputed b - A x) against the config's `StoppingCriterion` and
+// `abs_tol`, so they stop after the same iteration count up to rounding. Plain SSOR-CG
+// has that r at hand. The transformed residual is r~ = M1^{-1} S (b - A x), so the
+// Eisenstat loop maps it back with r = S^{-1} M1 r~, one SpMV over the lower triangle
+// of A per iteration. ||r||^2 rides along in the submit of r^T z. Like GMRES, the loop
+// is host-driven with two scalar readbacks per iteration, the same for both variants;
+// `SsorCgResult::loop_ms` is its wall-clock.
+
+/// Knobs for [`ssor_cg_csr_wgpu`].
+#[derive(Debug, Clone)]
+pub struct SsorOptions {
+    /// SSOR relaxation factor, in (0, 2); 1.0 is symmetric Gauss-Seidel.
+    pub omega: f32,
+    /// Run CG on the Eisenstat-transformed system (no SpMV per iteration, see the
+    /// module notes) instead of plain SSOR-PCG on A.
+    pub eisenstat: bool,
+}
+
+impl Default for SsorOptions {
+    fn default() -> Self {
+        Self {
+            omega: 1.0,
+            eisenstat: false,
+        }
+    }
+}
+
+/// Outcome of a converged SSOR-CG solve.
+#[derive(Debug, Clone)]
+pub struct SsorCgResult {
+    pub iterations: usize,
+    /// ||r|| of the original system after each iteration (the CG recurrence).
+    pub residual_history: Vec<f32>,
+    /// True ||b - A x|| of the returned x (host SpMV).
+    pub residual_norm: f32,
+    /// Compute dispatches of one iteration (operator, preconditioner, dots, vector ops).
+    pub dispatches_per_iteration: u64,
+    /// Wall-clock of the iteration loop; setup and the final transforms are excluded.
+    pub loop_ms: f64,
+}
+
+/// z = M^{-1} r for the SSOR splitting of A (see the module notes).
+pub struct SsorPreconditioner {
+    ilu: Ilu0Executor,
+}
+
+impl SsorPreconditioner {
+    /// Fails when A is not square, `omega` is outside (0, 2) or a diagonal entry is not
+    /// positive.
+    pub fn from_csr(ctx: &GpuContext, a: &Csr, omega: f32) -> Result<Self, String> {
+        let diag = positive_diagonal(a, omega, "SsorPreconditioner")?;
+        let c = omega * (2.0 - omega);
+        let (l, u) = split_factors(
+            a,
+            |i, j, v| {
+                if j < i {
+                    Some(omega * v / diag[j])
+                } else {
+                    None
+                }
+            },
+            |i, j, v| if j > i { Some(omega * v / c) } else { None },
+            |i| diag[i] / c,
+        );
+        let ilu = Ilu0Executor::from_factors(ctx, &l, &u, &Ilu0Levels::compute(&l, &u))?;
+        Ok(Self { ilu })
+    }
+
+    pub fn n(&self) -> u32 {
+        self.ilu.n()
+    }
+
+    pub fn encode_apply(&self, encoder: &mut CommandEncoder, r_gpu: &Buffer, z_gpu: &Buffer) {
+        self.ilu.encode_apply(encoder, r_gpu, z_gpu);
+    }
+
+    /// Compute dispatches of one apply (levels of both sweeps).
+    pub fn dispatches(&self) -> u64 {
+        self.ilu.dispatches()
+    }
+}
+
+/// y = A~ x, the Eisenstat-transformed SSOR operator M1^{-1} A^ M2^{-1} of the
+/// diagonally scaled A (see the module notes), without any product with A.
+pub struct EisenstatSsorOperator {
+    omega: f32,
+    // S = D^{-1/2} of the unscaled A
+    inv_sqrt_diag: Vec<f32>,
+    // forward: (I + w L^)^{-1}, backward: M2^{-1} = (I / w + U^)^{-1}
+    ilu: Ilu0Executor,
+    // strict upper triangle of A^ (zero diagonal), for x~0 = M2 S^{-1} x0 on the host
+    upper: Csr,
+    // r = S^{-1} M1 r~: the residual b - A x belonging to the transformed one
+    residual_map: SpmvExecutor,
+    vec_ops: VecOpsExecutor,
+    // scratch of the apply, from the context's buffer pool when one is installed
+    t: PooledBuffer<f32>,
+    w: PooledBuffer<f32>,
+}
+
+impl EisenstatSsorOperator {
+    /// Scale A to unit diagonal and build the two half-sweeps. Fails like
+    /// [`SsorPreconditioner::from_csr`].
+    pub fn from_csr(ctx: &GpuContext, a: &Csr, omega: f32) -> Result<Self, String> {
+        let diag = positive_diagonal(a, omega, "EisenstatSsorOperator")?;
+        let s: Vec<f32> = diag.iter().map(|d| 1.0 / d.sqrt()).collect();
+        let (l, u) = split_factors(
+            a,
+            |i, j, v| {
+                if j < i {
+                    Some(omega * s[i] * v * s[j])
+                } else {
+                    None
+                }
+            },
+            |i, j, v| if j > i { Some(s[i] * v * s[j]) } else { None },
+            |_| 1.0 / omega,
+        );
+        let ilu = Ilu0Executor::from_factors(ctx, &l, &u, &Ilu0Levels::compute(&l, &u))?;
+        let (_, upper) = split_factors(
+            a,
+            |_, _, _| None,
+            |i, j, v| if j > i { Some(s[i] * v * s[j]) } else { None },
+            |_| 0.0,
+        );
+        // S^{-1} M1 = S^{-1} (I / w + L^): a_ij s_j below the diagonal, 1 / (w s_i) on it.
+        let (_, residual_map) = split_factors(
+            a,
+            |_, _, _| None,
+            |i, j, v| if j < i { Some(v * s[j]) } else { None },
+            |i| 1.0 / (omega * s[i]),
+        );
+        let residual_map = SpmvExecutor::create(
+            ctx,
+            a.n_rows,
+            &residual_map.row_ptr,
+            &residual_map.col_idx,
+            &residual_map.values,
+        );
+
+        let n = a.n_rows as usize;
+        let vector = |label: &str| ctx.acquire_storage_buffer_uninit::<f32>(label, n);
+        Ok(Self {
+            omega,
+            inv_sqrt_diag: s,
+            ilu,
+            upper,
+            residual_map,
+            vec_ops: VecOpsExecutor::create(ctx),
+            t: vector("eisenstat t"),
+            w: vector("eisenstat w"),
+        })
+    }
+
+    pub fn n(&self) -> u32 {
+        self.ilu.n()
+    }
+
+    pub fn omega(&self) -> f32 {
+        self.omega
+    }
+
+    /// Encode y = A~ x.
+    pub fn encode_apply(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        x_gpu: &Buffer,
+        y_gpu: &Buffer,
+    ) {
+        let n = self.n();
+        let w = self.omega;
+
+        // t = M2^{-1} x
+        self.ilu.encode_backward(encoder, x_gpu, &self.t.buffer);
+        // w = (w - 2) t + w x  (= w (x + (1 - 2/w) t))
+        self.vec_ops
+            .encode_scaled_copy(ctx, encoder, &self.t.buffer, &self.w.buffer, n, w - 2.0);
+        self.vec_ops
+            .encode_axpy_inplace(ctx, encoder, x_gpu, &self.w.buffer, n, w);
+        // y = (I + w L^)^{-1} w + t
+        self.ilu.encode_forward(encoder, &self.w.buffer, y_gpu);
+        self.vec_ops
+            .encode_axpy_inplace(ctx, encoder, &self.t.buffer, y_gpu, n, 1.0);
+    }
+
+    /// Compute dispatches of one apply (both sweeps + 3 vector ops).
+    pub fn dispatches(&self) -> u64 {
+        self.ilu.dispatches() + 3
+    }
+
+    /// Host part of b~ = M1^{-1} S b: returns w S b, to be swept by [`Self::encode_rhs`].
+    fn scaled_rhs(&self, b: &[f32]) -> Vec<f32> {
+        b.iter()
+            .zip(&self.inv_sqrt_diag)
+            .map(|(b_i, s_i)| self.omega * s_i * b_i)
+            .collect()
+    }
+
+    /// b~ = (I + w L^)^{-1} (w S b), in place.
+    fn encode_rhs(&self, encoder: &mut CommandEncoder, rhs_gpu: &Buffer) {
+        self.ilu.encode_forward(encoder, rhs_gpu, &self.t.buffer);
+        encoder.copy_buffer_to_buffer(&self.t.buffer, 0, rhs_gpu, 0, self.n() as u64 * 4);
+    }
+
+    /// x~0 = M2 S^{-1} x0 (host).
+    fn transformed_guess(&self, x0: &[f32]) -> Vec<f32> {
+        let x_hat: Vec<f32> = x0
+            .iter()
+            .zip(&self.inv_sqrt_diag)
+            .map(|(x_i, s_i)| x_i / s_i)
+            .collect();
+        let mut ux = vec![0.0f32; x0.len()];
+        reference::spmv_csr(
+            &self.upper.row_ptr,
+            &self.upper.col_idx,
+            &self.upper.values,
+            &x_hat,
+            &mut ux,
+        );
+        x_hat
+            .iter()
+            .zip(&ux)
+            .map(|(x_i, u_i)| x_i / self.omega + u_i)
+            .collect()
+    }
+
+    /// x^ = M2^{-1} x~, in place; x = S x^ follows on the host.
+    fn encode_recover(&self, encoder: &mut CommandEncoder, x_gpu: &Buffer) {
+        self.ilu.encode_backward(encoder, x_gpu, &self.t.buffer);
+        encoder.copy_buffer_to_buffer(&self.t.buffer, 0, x_gpu, 0, self.n() as u64 * 4);
+    }
+}
+
+/// SSOR-preconditioned CG on the GPU: plain, or on the Eisenstat-transformed system when
+/// `options.eisenstat` is set (see the module notes).
+///
+/// Unlike the PCG core loop this builds its own executors from `a`. `x` holds x0 on entry
+/// and the solution on success (it is left untouched on error). Stops like PCG, once
+/// ||r|| meets `config.options.stopping_criterion` (`rel_tol * ||b||` by default) or
+/// drops to `config.abs_tol`; fails after `config.max_iters` iterations or on negative
+/// curvature. The stopping criterion is the only `PcgOptions` field this loop reads: a
+/// config that sets any other one away from its default is rejected.
+pub fn ssor_cg_csr_wgpu(
+    ctx: &GpuContext,
+    a: &Csr,
+    b: &[f32],
+    x: &mut [f32],
+    config: &SolveConfig,
+    options: &SsorOptions,
+) -> Result<SsorCgResult, String> {
+    let n = a.n_rows as usize;
+    if b.len() != n || x.len() != n {
+        return Err(format!(
+            "SSOR-CG: dimension mismatch: n={}, b len {}, x len {}",
+            n,
+            b.len(),
+            x.len()
+        ));
+    }
+    if let Some(field) = unsupported_option(&config.options) {
+        return Err(format!(
+            "SSOR-CG: PcgOptions::{field} is not supported (only stopping_criterion applies)"
+        ));
+    }
+    config
+        .options
+        .stopping_criterion
+        .validate()
+        .map_err(|e| format!("SSOR-CG: {e}"))?;
+    let b_norm2 = reference::dot(b, b);
+
+    let vec_ops_exec = VecOpsExecutor::create(ctx);
+    // slot 0: r^T z (or p^T A p), slot 1: ||r||^2
+    let dot_scalar_exec = DotScalarExecutor::create(ctx, n, 2);
+
+    let (cg, x_out) = if options.eisenstat {
+        let op = EisenstatSsorOperator::from_csr(ctx, a, options.omega)?;
+        let rhs_gpu = ctx.acquire_storage_buffer("ssor-cg rhs", &op.scaled_rhs(b));
+        let x_gpu = ctx.acquire_storage_buffer("ssor-cg x", &op.transformed_guess(x));
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some(&ctx.label("ssor-cg rhs encoder")),
+            });
+        op.encode_rhs(&mut encoder, &rhs_gpu.buffer);
+        ctx.queue.submit(Some(encoder.finish()));
+
+        let cg = cg_loop(
+            ctx,
+            &op,
+            None,
+            Some(&op.residual_map),
+            &vec_ops_exec,
+            &dot_scalar_exec,
+            &rhs_gpu,
+            &x_gpu,
+            config,
+            b_norm2,
+        )?;
+
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some(&ctx.label("ssor-cg recover encoder")),
+            });
+        op.encode_recover(&mut encoder, &x_gpu.buffer);
+        ctx.queue.submit(Some(encoder.finish()));
+        let x_hat = executor::block_on(ctx.try_readback(&x_gpu))?;
+        let x_out = x_hat
+            .iter()
+            .zip(&op.inv_sqrt_diag)
+            .map(|(x_i, s_i)| s_i * x_i)
+            .collect::<Vec<_>>();
+        (cg, x_out)
+    } else {
+        let spmv_exec = SpmvExecutor::create(ctx, a.n_rows, &a.row_ptr, &a.col_idx, &a.values);
+        let ssor = SsorPreconditioner::from_csr(ctx, a, options.omega)?;
+        let rhs_gpu = ctx.acquire_storage_buffer("ssor-cg rhs", b);
+        let x_gpu = ctx.acquire_storage_buffer("ssor-cg x", x);
+
+        let cg = cg_loop(
+            ctx,
+            &spmv_exec,
+            Some(&ssor),
+            None,
+            &vec_ops_exec,
+            &dot_scalar_exec,
+            &rhs_gpu,
+            &x_gpu,
+            config,
+            b_norm2,
+        )?;
+        (cg, executor::block_on(ctx.try_readback(&x_gpu))?)
+    };
+
+    let mut ax = vec![0.0f32; n];
+    reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, &x_out, &mut ax);
+    let residual_norm = b
+        .iter()
+        .zip(&ax)
+        .map(|(b_i, ax_i)| (*b_i as f64 - *ax_i as f64).powi(2))
+        .sum::<f64>()
+        .sqrt() as f32;
+    x.copy_from_slice(&x_out);
+
+    Ok(SsorCgResult {
+        iterations: cg.iterations,
+        residual_history: cg.residual_history,
+        residual_norm,
+        dispatches_per_iteration: cg.dispatches_per_iteration,
+        loop_ms: cg.loop_ms,
+    })
+}
+
+struct CgLoop {
+    iterations: usize,
+    residual_history: Vec<f32>,
+    dispatches_per_iteration: u64,
+    loop_ms: f64,
+}
+
+/// CG on `op x = rhs` with z = M^{-1} r from `preconditioner` (z = r without one), x in
+/// place on the GPU. Stops on the residual of the original system, `residual_map` r
+/// (r itself without one), see the module notes; `b_norm2` is ||b||^2 of that system.
+#[allow(clippy::too_many_arguments)]
+fn cg_loop(
+    ctx: &GpuContext,
+    op: &dyn LinearOperator,
+    preconditioner: Option<&dyn LinearOperator>,
+    residual_map: Option<&SpmvExecutor>,
+    vec_ops_exec: &VecOpsExecutor,
+    dot_scalar_exec: &DotScalarExecutor,
+    rhs_gpu: &GpuBuffer<f32>,
+    x_gpu: &GpuBuffer<f32>,
+    config: &SolveConfig,
+    b_norm2: f32,
+) -> Result<CgLoop, String> {
+    let max_iter = config.max_iters;
+    let n = op.n_rows();
+    let n_bytes = n as u64 * 4;
+    let vector = |label: &str| ctx.acquire_storage_buffer_uninit::<f32>(label, n as usize);
+    let r_gpu = vector("ssor-cg r");
+    let z_gpu = vector("ssor-cg z");
+    let p_gpu = vector("ssor-cg p");
+    let q_gpu = vector("ssor-cg q");
+    let e_gpu = vector("ssor-cg residual");
+    // Whatever the operators cache over these vectors goes with them.
+    let _release_op = ReleaseBuffersOnDrop(op);
+    let _release_preconditioner = preconditioner.map(ReleaseBuffersOnDrop);
+    let (r, p, q) = (&r_gpu.buffer, &p_gpu.buffer, &q_gpu.buffer);
+    let z = if preconditioner.is_some() {
+        &z_gpu.buffer
+    } else {
+        r
+    };
+    let residual = if residual_map.is_some() {
+        &e_gpu.buffer
+    } else {
+        r
+    };
+
+    let new_encoder = || {
+        vec_ops_exec.reset_params_cursor();
+        dot_scalar_exec.reset_params_cursor();
+        ctx.device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some(&ctx.label("ssor-cg encoder")),
+            })
+    };
+    // Finish `encoder` with dot(a, c) into slot 0, submit, and read it back.
+    let submit_dot = |mut encoder: CommandEncoder, a: &Buffer, c: &Buffer| {
+        dot_scalar_exec.encode_dot_scalar_into(ctx, &mut encoder, a, c, n, 0);
+        dot_scalar_exec.encode_copy_scalar_results_to_readback(&mut encoder);
+        ctx.queue.submit(Some(encoder.finish()));
+        executor::block_on(dot_scalar_exec.try_readback_scalar_results(ctx))
+            .map(|s| s[0])
+            .map_err(|e| format!("SSOR-CG: {e}"))
+    };
+    // Finish `encoder` with r^T z into slot 0 and ||r||^2 (original system) into slot 1.
+    let submit_residual_dots = |mut encoder: CommandEncoder| {
+        if let Some(map) = residual_map {
+            map.encode_apply(ctx, &mut encoder, r, residual);
+        }
+        dot_scalar_exec.encode_dot_scalar_into(ctx, &mut encoder, r, z, n, 0);
+        dot_scalar_exec.encode_dot_scalar_into(ctx, &mut encoder, residual, residual, n, 1);
+        dot_scalar_exec.encode_copy_scalar_results_to_readback(&mut encoder);
+        ctx.queue.submit(Some(encoder.finish()));
+        executor::block_on(dot_scalar_exec.try_readback_scalar_results(ctx))
+            .map(|s| (s[0], s[1]))
+            .map_err(|e| format!("SSOR-CG: {e}"))
+    };
+    let encode_preconditioner = |encoder: &mut CommandEncoder| {
+        if let Some(m) = preconditioner {
+            m.encode_apply(ctx, encoder, r, z);
+        }
+    };
+
+    let dispatches_per_iteration = op.dispatches()
+        + preconditioner.map_or(0, |m| m.dispatches())
+        + residual_map.map_or(0, |_| 1)
+        + 3 * dot_scalar_exec.dispatches_per_dot(n) as u64
+        + 4;
+
+    let loop_start = Instant::now();
+
+    // r = rhs - op(x), z = M^{-1} r, p = z
+    let mut encoder = new_encoder();
+    op.encode_apply(ctx, &mut encoder, &x_gpu.buffer, q);
+    encoder.copy_buffer_to_buffer(&rhs_gpu.buffer, 0, r, 0, n_bytes);
+    vec_ops_exec.encode_axpy_inplace(ctx, &mut encoder, q, r, n, -1.0);
+    encode_preconditioner(&mut encoder);
+    let (mut rz, r0_norm2) = submit_residual_dots(encoder)?;
+
+    let criterion = config.options.stopping_criterion;
+    let converged = |r_norm2: f32| {
+        r_norm2 <= 0.0
+            || criterion.is_met(r_norm2, b_norm2, r0_norm2, config.rel_tol, config.abs_tol)
+    };
+    let mut residual_history = Vec::new();
+    let mut r_norm2 = r0_norm2;
+    let mut beta = None;
+    let mut iterations = 0usize;
+    while !converged(r_norm2) {
+        if iterations >= max_iter {
+            return Err(format!(
+                "SSOR-CG: did not converge in {max_iter} iterations (||r|| = {:e}, ||b|| = {:e})",
+                r_norm2.sqrt(),
+                b_norm2.sqrt()
+            ));
+        }
+        iterations += 1;
+
+        // p = z + beta p, q = op(p), pAp = p·q
+        let mut encoder = new_encoder();
+        match beta {
+            None => encoder.copy_buffer_to_buffer(z, 0, p, 0, n_bytes),
+            Some(beta) => {
+                vec_ops_exec.encode_scale_inplace(ctx, &mut encoder, p, n, beta);
+                vec_ops_exec.encode_axpy_inplace(ctx, &mut encoder, z, p, n, 1.0);
+            }
+        }
+        op.encode_apply(ctx, &mut encoder, p, q);
+        let p_ap = submit_dot(encoder, p, q)?;
+        if p_ap.is_nan() || p_ap <= 0.0 {
+            return Err(negative_curvature_error("SSOR-CG", iterations, p_ap));
+        }
+        let alpha = rz / p_ap;
+
+        // x += alpha p, r -= alpha q, z = M^{-1} r, rz = r·z
+        let mut encoder = new_encoder();
+        vec_ops_exec.encode_axpy_inplace(ctx, &mut encoder, p, &x_gpu.buffer, n, alpha);
+        vec_ops_exec.encode_axpy_inplace(ctx, &mut encoder, q, r, n, -alpha);
+        encode_preconditioner(&mut encoder);
+        let (rz_new, r_norm2_new) = submit_residual_dots(encoder)?;
+
+        beta = Some(rz_new / rz);
+        rz = rz_new;
+        r_norm2 = r_norm2_new;
+        residual_history.push(r_norm2.max(0.0).sqrt());
+    }
+
+    Ok(CgLoop {
+        iterations,
+        residual_history,
+        dispatches_per_iteration,
+        loop_ms: loop_start.elapsed().as_secs_f64() * 1e3,
+    })
+}
+
+/// diag(A) (duplicates summed), checked positive; also checks the shape and omega.
+pub(crate) fn positive_diagonal(a: &Csr, omega: f32, who: &str) -> Result<Vec<f32>, String> {
+    if a.n_rows != a.n_cols || a.n_rows == 0 {
+        return Err(format!(
+            "{who}: matrix is {}x{}, expected square and nonempty",
+            a.n_rows, a.n_cols
+        ));
+    }
+    if !(omega > 0.0 && omega < 2.0) {
+        return Err(format!("{who}: omega = {omega} is outside (0, 2)"));
+    }
+
+    let n = a.n_rows as usize;
+    let mut diag = vec![0.0f32; n];
+    for (i, d) in diag.iter_mut().enumerate() {
+        for k in a.row_ptr[i] as usize..a.row_ptr[i + 1] as usize {
+            if a.col_idx[k] as usize == i {
+                *d += a.values[k];
+            }
+        }
+        if !(*d > 0.0 && d.is_finite()) {
+            return Err(format!(
+                "{who}: diagonal entry {i} is {d}, SSOR needs a positive diagonal"
+            ));
+        }
+    }
+    Ok(diag)
+}
+
+/// (L, U) factors in the `Ilu0Executor` conventions: `lower(i, j, a_ij)` keeps an entry
+/// of L (implicit unit diagonal), `upper(i, j, a_ij)` one of U, and U's diagonal is
+/// `diagonal(i)`.
+fn split_factors(
+    a: &Csr,
+    lower: impl Fn(usize, usize, f32) -> Option<f32>,
+    upper: impl Fn(usize, usize, f32) -> Option<f32>,
+    diagonal: impl Fn(usize) -> f32,
+) -> (Csr, Csr) {
+    let n = a.n_rows as usize;
+    let empty = || Csr {
+        n_rows: a.n_rows,
+        n_cols: a.n_cols,
+        nnz: 0,
+        row_ptr: vec![0],
+        col_idx: Vec::new(),
+        values: Vec::new(),
+    };
+    let (mut l, mut u) = (empty(), empty());
+
+    for i in 0..n {
+        u.col_idx.push(i as u32);
+        u.values.push(diagonal(i));
+        for k in a.row_ptr[i] as usize..a.row_ptr[i + 1] as usize {
+            let (j, v) = (a.col_idx[k] as usize, a.values[k]);
+            if let Some(value) = lower(i, j, v) {
+                l.col_idx.push(j as u32);
+                l.values.push(value);
+            }
+            if let Some(value) = upper(i, j, v) {
+                u.col_idx.push(j as u32);
+                u.values.push(value);
+            }
+        }
+        l.row_ptr.push(l.col_idx.len() as u32);
+        u.row_ptr.push(u.col_idx.len() as u32);
+    }
+    l.nnz = l.col_idx.len() as u32;
+    u.nnz = u.col_idx.len() as u32;
+    (l, u)
+}
@@ -0,0 +1,373 @@
+use wgpu::util::BufferInitDescriptor;
+use wgpu::{BindGroup, Buffer, BufferUsages, CommandEncoder, ComputePassDescriptor};
+
+use crate::compute::ssor::positive_diagonal;
+use crate::compute::ssor_sweep::{
+    SSOR_SWEEP_WORKGROUP_SIZE, SsorSweepPipeline, create_ssor_sweep_bind_group,
+    create_ssor_sweep_pipeline,
+};
+use crate::gpu::context::GpuContext;
+use crate::matrix::Csr;
+
+// Multicolor SSOR (symmetric Gauss-Seidel for omega = 1) straight on the CSR arrays of A.
+//
+// With D = diag(A) and L, U the strictly lower / upper parts of A in a *color ordering*
+// (rows of color 0 first, then color 1, ...), the preconditioner is
+//
+//   M = (D + w L) D^{-1} (D + w U) / (w (2 - w)),
+//
+// applied as a forward sweep over the colors in increasing order, a diagonal scaling and
+// a backward sweep in decreasing order:
+//
+//   (D + w L) y = w (2 - w) r,   z = y - w D^{-1} U z.
+//
+// A row only couples to rows of other colors, so all rows of one color update in
+// parallel: one dispatch per color and sweep. The backward sweep skips the last color
+// (it has no upper entries, z = y there), so an apply is 2 k - 1 dispatches for k
+// colors. A 5-point Laplacian needs 2 colors (red-black), a 27-point stencil 8.
+//
+// Unlike `ssor::SsorPreconditioner`, which sweeps in the natural row order (level
+// scheduled through `Ilu0Executor`, ~n levels on a grid), this is a different
+// preconditioner: the ordering changes M. Multicolor orderings parallelize best and
+// usually converge a little slower than the natural one.
+//
+// Coloring input (`SsorColoring`): rows grouped by color, in the `TriangularLevels`
+// layout. Color k is `rows[color_ptr[k]..color_ptr[k + 1]]`; every row appears exactly
+// once, color_ptr starts at 0, is non-decreasing and ends at n. Two rows i != j with a
+// stored entry a_ij (in either row) must have different colors; `SsorExecutor::create`
+// rejects a coloring that breaks this. Build one from a per-row color array with
+// `SsorColoring::from_colors`, or greedily from A with `SsorColoring::greedy`.
+
+/// Rows of A grouped by color (format at the top of this file).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsorColoring {
+    pub color_ptr: Vec<u32>,
+    pub rows: Vec<u32>,
+}
+
+impl SsorColoring {
+    /// Grouping of a per-row color array (`colors[i]` is the color of row i). Colors no
+    /// row has are dropped, the rest keep their relative order.
+    pub fn from_colors(colors: &[u32]) -> Self {
+        let mut used: Vec<u32> = colors.to_vec();
+        used.sort_unstable();
+        used.dedup();
+
+        let mut color_ptr = vec![0u32; used.len() + 1];
+        let dense: Vec<usize> = colors
+            .iter()
+            .map(|c| used.binary_search(c).unwrap_or_default())
+            .collect();
+        for &k in &dense {
+            color_ptr[k + 1] += 1;
+        }
+        for k in 0..used.len() {
+            color_ptr[k + 1] += color_ptr[k];
+        }
+
+        let mut next = color_ptr.clone();
+        let mut rows = vec![0u32; colors.len()];
+        for (row, &k) in dense.iter().enumerate() {
+            rows[next[k] as usize] = row as u32;
+            next[k] += 1;
+        }
+        Self { color_ptr, rows }
+    }
+
+    /// Greedy coloring of the pattern of A + A^T in row order: every row takes the
+    /// smallest color none of its neighbors has. Two colors (red-black) for a 5-point
+    /// Laplacian in natural order; at most max degree + 1 in general.
+    pub fn greedy(a: &Csr) -> Self {
+        let n = a.n_rows as usize;
+        let mut neighbors: Vec<Vec<u32>> = vec![Vec::new(); n];
+        for row in 0..n {
+            for &col in &a.col_idx[a.row_ptr[row] as usize..a.row_ptr[row + 1] as usize] {
+                if col as usize != row && (col as usize) < n {
+                    neighbors[row].push(col);
+                    neighbors[col as usize].push(row as u32);
+                }
+            }
+        }
+
+        let mut colors = vec![u32::MAX; n];
+        let mut taken: Vec<bool> = Vec::new();
+        for row in 0..n {
+            taken.clear();
+            taken.resize(neighbors[row].len() + 1, false);
+            for &j in &neighbors[row] {
+                if let Some(slot) = taken.get_mut(colors[j as usize] as usize) {
+                    *slot = true;
+                }
+            }
+            colors[row] = taken.iter().position(|&t| !t).unwrap_or(0) as u32;
+        }
+        Self::from_colors(&colors)
+    }
+
+    pub fn num_colors(&self) -> usize {
+        self.color_ptr.len().saturating_sub(1)
+    }
+
+    /// Color of every row, checked against the format: every row once, and no stored
+    /// off-diagonal entry of `a` between two rows of the same color.
+    fn row_colors(&self, a: &Csr) -> Result<Vec<u32>, String> {
+        let n = a.n_rows as usize;
+        let err = |msg: String| Err(format!("SsorExecutor: coloring: {msg}"));
+
+        if self.color_ptr.first() != Some(&0)
+            || self.color_ptr.windows(2).any(|w| w[0] > w[1])
+            || self.color_ptr.last().map(|&e| e as usize) != Some(self.rows.len())
+        {
+            return err(
+                "color_ptr must start at 0, be non-decreasing and end at rows.len()".into(),
+            );
+        }
+        if self.rows.len() != n {
+            return err(format!("{} rows colored, matrix has {n}", self.rows.len()));
+        }
+
+        let mut color_of = vec![u32::MAX; n];
+        for k in 0..self.num_colors() {
+            for &row in &self.rows[self.color_ptr[k] as usize..self.color_ptr[k + 1] as usize] {
+                let Some(slot) = color_of.get_mut(row as usize) else {
+                    return err(format!("row {row} out of range"));
+                };
+                if *slot != u32::MAX {
+                    return err(format!("row {row} colored twice"));
+                }
+                *slot = k as u32;
+            }
+        }
+
+        for row in 0..n {
+            for &col in &a.col_idx[a.row_ptr[row] as usize..a.row_ptr[row + 1] as usize] {
+                let col = col as usize;
+                if col != row && color_of[col] == color_of[row] {
+                    return err(format!(
+                        "rows {row} and {col} are coupled but share color {}",
+                        color_of[row]
+                    ));
+                }
+            }
+        }
+
+        Ok(color_of)
+    }
+}
+
+/// SsorExecutor
+///
+/// z = M^{-1} r for multicolor SSOR on the GPU (see the top of this file).
+///
+/// Every buffer and the bind group are built at creation: `encode_apply` copies r into an
+/// internal input buffer, records one dispatch per color of each sweep into one pass,
+/// and copies the result into z. The per-dispatch parameters live in one uniform buffer
+/// addressed by dynamic offsets, so nothing is written through the queue per apply.
+pub struct SsorExecutor {
+    n: u32,
+    omega: f32,
+    num_colors: usize,
+
+    pipeline: SsorSweepPipeline,
+    // (dynamic params offset, rows in the color) per dispatch, forward then backward
+    dispatches: Vec<(u32, u32)>,
+    bind_group: BindGroup,
+
+    // r is copied into `input`; both sweeps write `output`
+    input: Buffer,
+    output: Buffer,
+
+    // Kept alive for the bind group above
+    _buffers: Vec<Buffer>,
+}
+
+impl SsorExecutor {
+    /// Executor for the n x n matrix `a` with relaxation `omega` in (0, 2), the inverse
+    /// of its diagonal `inv_diag` (duplicates summed; every entry finite and positive)
+    /// and the color ordering `coloring`.
+    ///
+    /// The diagonal entries stored in `a` are not read by the sweeps, only `inv_diag`.
+    /// Fails on a malformed coloring (format at the top of this file) or inputs of the
+    /// wrong size.
+    pub fn create(
+        ctx: &GpuContext,
+        a: &Csr,
+        inv_diag: &[f32],
+        omega: f32,
+        coloring: &SsorColoring,
+    ) -> Result<Self, String> {
+        let n = a.n_rows as usize;
+        if a.n_rows != a.n_cols || n == 0 {
+            return Err(format!(
+                "SsorExecutor: matrix is {}x{}, expected square and nonempty",
+                a.n_rows, a.n_cols
+            ));
+        }
+        if !(omega > 0.0 && omega < 2.0) {
+            return Err(format!("SsorExecutor: omega = {omega} is outside (0, 2)"));
+        }
+        if inv_diag.len() != n {
+            return Err(format!(
+                "SsorExecutor: inv_diag len {} != n={n}",
+                inv_diag.len()
+            ));
+        }
+        if let Some(i) = inv_diag.iter().position(|d| !(d.is_finite() && *d > 0.0)) {
+            return Err(format!(
+                "SsorExecutor: inv_diag[{i}] = {}, SSOR needs a positive diagonal",
+                inv_diag[i]
+            ));
+        }
+        let row_color = coloring.row_colors(a)?;
+
+        let device = &ctx.device;
+        let pipeline = create_ssor_sweep_pipeline(ctx);
+
+        // One params entry per dispatch at the dynamic-offset alignment: the colors in
+        // increasing order (forward), then all but the last in decreasing order (backward).
+        let stride = device.limits().min_uniform_buffer_offset_alignment;
+        let num_colors = coloring.num_colors();
+        let scale = omega * (2.0 - omega);
+        let mut params_words: Vec<u32> = Vec::new();
+        let sweeps = (0..num_colors)
+            .map(|k| (k, 0u32))
+            .chain((0..num_colors.saturating_sub(1)).rev().map(|k| (k, 1u32)));
+        let dispatches = sweeps
+            .map(|(k, backward)| {
+                let offset = (params_words.len() * 4) as u32;
+                let (start, end) = (coloring.color_ptr[k], coloring.color_ptr[k + 1]);
+                params_words.extend([
+                    start,
+                    end - start,
+                    k as u32,
+                    backward,
+                    omega.to_bits(),
+                    scale.to_bits(),
+                    0,
+                    0,
+                ]);
+                params_words.resize((offset + stride) as usize / 4, 0);
+                (offset, end - start)
+            })
+            .collect::<Vec<_>>();
+
+        let params_buffer = ctx.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&ctx.label("ssor params")),
+            contents: bytemuck::cast_slice(&params_words),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let storage = |label: &str, contents: &[u8]| {
+            ctx.create_buffer_init(&BufferInitDescriptor {
+                label: Some(&ctx.label(label)),
+                contents,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            })
+        };
+
+        // wgpu rejects zero-sized storage bindings; an A without entries (nnz == 0) gets
+        // a one-element dummy the shader never reads.
+        let (col_idx, values): (&[u32], &[f32]) = if a.col_idx.is_empty() {
+            (&[0], &[0.0])
+        } else {
+            (&a.col_idx, &a.values)
+        };
+        let row_ptr = storage("ssor row_ptr", bytemuck::cast_slice(&a.row_ptr));
+        let col_idx = storage("ssor col_idx", bytemuck::cast_slice(col_idx));
+        let values = storage("ssor values", bytemuck::cast_slice(values));
+        let color_rows = storage("ssor color_rows", bytemuck::cast_slice(&coloring.rows));
+        let row_color = storage("ssor row_color", bytemuck::cast_slice(&row_color));
+        let inv_diag = storage("ssor inv_diag", bytemuck::cast_slice(inv_diag));
+
+        let vector = |label: &str| {
+            ctx.create_storage_buffer_uninit::<f32>(label, n, BufferUsages::empty())
+                .buffer
+        };
+        let input = vector("ssor input");
+        let output = vector("ssor output");
+
+        let bind_group = create_ssor_sweep_bind_group(
+            device,
+            &pipeline.ssor_sweep_bind_group_layout,
+            &params_buffer,
+            [
+                &row_ptr,
+                &col_idx,
+                &values,
+                &color_rows,
+                &row_color,
+                &inv_diag,
+                &input,
+                &output,
+            ],
+        );
+
+        Ok(Self {
+            n: n as u32,
+            omega,
+            num_colors,
+            pipeline,
+            dispatches,
+            bind_group,
+            input,
+            output,
+            _buffers: vec![
+                params_buffer,
+                row_ptr,
+                col_idx,
+                values,
+                color_rows,
+                row_color,
+                inv_diag,
+            ],
+        })
+    }
+
+    /// [`SsorExecutor::create`] with the inverse diagonal taken from `a` and a greedy
+    /// coloring ([`SsorColoring::greedy`]). Fails when a diagonal entry is not positive.
+    pub fn from_csr(ctx: &GpuContext, a: &Csr, omega: f32) -> Result<Self, String> {
+        let diag = positive_diagonal(a, omega, "SsorExecutor")?;
+        let inv_diag: Vec<f32> = diag.iter().map(|d| 1.0 / d).collect();
+        Self::create(ctx, a, &inv_diag, omega, &SsorColoring::greedy(a))
+    }
+
+    /// Encode: z = M^{-1} r
+    pub fn encode_apply(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        r_gpu: &Buffer,
+        z_gpu: &Buffer,
+    ) {
+        let bytes = self.n as u64 * 4;
+        encoder.copy_buffer_to_buffer(r_gpu, 0, &self.input, 0, bytes);
+        {
+            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some(&ctx.label("ssor sweeps")),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipeline.pipeline);
+            for &(offset, color_len) in &self.dispatches {
+                pass.set_bind_group(0, &self.bind_group, &[offset]);
+                pass.dispatch_workgroups(color_len.div_ceil(SSOR_SWEEP_WORKGROUP_SIZE), 1, 1);
+            }
+        }
+        encoder.copy_buffer_to_buffer(&self.output, 0, z_gpu, 0, bytes);
+    }
+
+    pub fn n(&self) -> u32 {
+        self.n
+    }
+
+    pub fn omega(&self) -> f32 {
+        self.omega
+    }
+
+    pub fn num_colors(&self) -> usize {
+        self.num_colors
+    }
+
+    /// Compute dispatches one `encode_apply` records (2 k - 1 for k colors).
+    pub fn dispatches(&self) -> u64 {
+        self.dispatches.len() as u64
+    }
+}
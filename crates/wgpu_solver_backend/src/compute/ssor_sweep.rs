@@ -0,0 +1,140 @@
+use std::num::NonZeroU64;
+
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, Buffer, BufferBinding, BufferBindingType,
+    ComputePipeline, ComputePipelineDescriptor, Device, PipelineLayoutDescriptor,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages,
+};
+
+use crate::gpu::context::GpuContext;
+
+pub struct SsorSweepPipeline {
+    pub pipeline: ComputePipeline,
+    pub ssor_sweep_bind_group_layout: BindGroupLayout,
+}
+
+/// Bytes of one dispatch's `Params` (the window bound at each dynamic offset).
+pub const SSOR_SWEEP_PARAMS_SIZE: u64 = 32;
+
+/// Rows per workgroup: one invocation per row of the color.
+pub const SSOR_SWEEP_WORKGROUP_SIZE: u32 = 256;
+
+// Params are bound with a dynamic offset: one uniform buffer holds every dispatch's
+// [color_start, color_len, color, backward, omega, scale, 0, 0], so an apply needs a
+// single bind group (as in triangular_solve.rs).
+fn create_dynamic_uniform_entry(binding: u32) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: true,
+            min_binding_size: NonZeroU64::new(SSOR_SWEEP_PARAMS_SIZE),
+        },
+        count: None,
+    }
+}
+
+fn create_storage_entry(binding: u32, is_read_only: bool) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Storage {
+                read_only: is_read_only,
+            },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+pub fn create_ssor_sweep_pipeline(ctx: &GpuContext) -> SsorSweepPipeline {
+    let device = &ctx.device;
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(&ctx.label("ssor_sweep.wgsl")),
+        source: ShaderSource::Wgsl(include_str!("wgsl/ssor_sweep.wgsl").into()),
+    });
+
+    // Bind group layout (group 0), matches ssor_sweep.wgsl:
+    //  0: params (uniform, dynamic offset)
+    //  1: row_ptr (RO storage)
+    //  2: col_idx (RO storage)
+    //  3: values (RO storage)
+    //  4: color_rows (RO storage)
+    //  5: row_color (RO storage)
+    //  6: inv_diag (RO storage)
+    //  7: rhs (RO storage)
+    //  8: x (RW storage)
+    let ssor_sweep_bind_group_layout =
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&ctx.label("ssor_sweep bgl0")),
+            entries: &[
+                create_dynamic_uniform_entry(0),
+                create_storage_entry(1, true),
+                create_storage_entry(2, true),
+                create_storage_entry(3, true),
+                create_storage_entry(4, true),
+                create_storage_entry(5, true),
+                create_storage_entry(6, true),
+                create_storage_entry(7, true),
+                create_storage_entry(8, false),
+            ],
+        });
+
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&ctx.label("ssor_sweep pipeline layout")),
+        bind_group_layouts: &[&ssor_sweep_bind_group_layout],
+        immediate_size: 0,
+    });
+
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(&ctx.label("ssor_sweep pipeline")),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("compute_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    SsorSweepPipeline {
+        pipeline,
+        ssor_sweep_bind_group_layout,
+    }
+}
+
+/// `buffers` in binding order 1..=8 (row_ptr, col_idx, values, color_rows, row_color,
+/// inv_diag, rhs, x).
+pub fn create_ssor_sweep_bind_group(
+    device: &Device,
+    ssor_sweep_bind_group_layout: &BindGroupLayout,
+    params_buffer: &Buffer,
+    buffers: [&Buffer; 8],
+) -> BindGroup {
+    let mut entries = vec![BindGroupEntry {
+        binding: 0,
+        resource: BindingResource::Buffer(BufferBinding {
+            buffer: params_buffer,
+            offset: 0,
+            size: NonZeroU64::new(SSOR_SWEEP_PARAMS_SIZE),
+        }),
+    }];
+    entries.extend(
+        buffers
+            .iter()
+            .enumerate()
+            .map(|(i, buffer)| BindGroupEntry {
+                binding: i as u32 + 1,
+                resource: buffer.as_entire_binding(),
+            }),
+    );
+
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("ssor_sweep bind group 0"),
+        layout: ssor_sweep_bind_group_layout,
+        entries: &entries,
+    })
+}
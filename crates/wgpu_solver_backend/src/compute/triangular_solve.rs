@@ -0,0 +1,146 @@
+use std::num::NonZeroU64;
+
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, Buffer, BufferBinding, BufferBindingType,
+    ComputePipeline, ComputePipelineDescriptor, Device, PipelineLayoutDescriptor,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages,
+};
+
+use crate::gpu::context::GpuContext;
+
+pub struct TriangularSolvePipeline {
+    pub pipeline: ComputePipeline,
+    pub triangular_solve_bind_group_layout: BindGroupLayout,
+}
+
+/// Bytes of one level's `Params` (the window bound at each dynamic offset).
+pub const TRIANGULAR_SOLVE_PARAMS_SIZE: u64 = 16;
+
+// Params are bound with a dynamic offset: one uniform buffer holds every level's
+// [level_start, level_len, unit_diagonal, 0], so a sweep needs a single bind group.
+fn create_dynamic_uniform_entry(binding: u32) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: true,
+            min_binding_size: NonZeroU64::new(TRIANGULAR_SOLVE_PARAMS_SIZE),
+        },
+        count: None,
+    }
+}
+
+fn create_storage_entry(binding: u32, is_read_only: bool) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Storage {
+                read_only: is_read_only,
+            },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+pub fn create_triangular_solve_pipeline(ctx: &GpuContext) -> TriangularSolvePipeline {
+    let device = &ctx.device;
+
+    // Shader module
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(&ctx.label("triangular_solve.wgsl")),
+        source: ShaderSource::Wgsl(include_str!("wgsl/triangular_solve.wgsl").into()),
+    });
+
+    // Bind group layout (group 0), matches triangular_solve.wgsl:
+    //  0: params (uniform, dynamic offset)
+    //  1: row_ptr (RO storage)
+    //  2: col_idx (RO storage)
+    //  3: values (RO storage)
+    //  4: level_rows (RO storage)
+    //  5: rhs (RO storage)
+    //  6: x (RW storage)
+    let triangular_solve_bind_group_layout =
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&ctx.label("triangular_solve bgl0")),
+            entries: &[
+                create_dynamic_uniform_entry(0),
+                create_storage_entry(1, true),
+                create_storage_entry(2, true),
+                create_storage_entry(3, true),
+                create_storage_entry(4, true),
+                create_storage_entry(5, true),
+                create_storage_entry(6, false),
+            ],
+        });
+
+    // Pipeline layout (newer wgpu uses immediate_size)
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&ctx.label("triangular_solve pipeline layout")),
+        bind_group_layouts: &[&triangular_solve_bind_group_layout],
+        immediate_size: 0,
+    });
+
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(&ctx.label("triangular_solve pipeline")),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("compute_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    TriangularSolvePipeline {
+        pipeline,
+        triangular_solve_bind_group_layout,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_triangular_solve_bind_group(
+    device: &Device,
+    triangular_solve_bind_group_layout: &BindGroupLayout,
+    params_buffer: &Buffer,
+    row_ptr_buffer: &Buffer,
+    col_idx_buffer: &Buffer,
+    values_buffer: &Buffer,
+    level_rows_buffer: &Buffer,
+    rhs_buffer: &Buffer,
+    x_buffer: &Buffer,
+) -> BindGroup {
+    let buffers = [
+        row_ptr_buffer,
+        col_idx_buffer,
+        values_buffer,
+        level_rows_buffer,
+        rhs_buffer,
+        x_buffer,
+    ];
+    let mut entries = vec![BindGroupEntry {
+        binding: 0,
+        resource: BindingResource::Buffer(BufferBinding {
+            buffer: params_buffer,
+            offset: 0,
+            size: NonZeroU64::new(TRIANGULAR_SOLVE_PARAMS_SIZE),
+        }),
+    }];
+    entries.extend(
+        buffers
+            .iter()
+            .enumerate()
+            .map(|(i, buffer)| BindGroupEntry {
+                binding: i as u32 + 1,
+                resource: buffer.as_entire_binding(),
+            }),
+    );
+
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("triangular_solve bind group 0"),
+        layout: triangular_solve_bind_group_layout,
+        entries: &entries,
+    })
+}
@@ -21,6 +21,32 @@ pub struct AxpyFromScalarResultsPipeline {
     pub axpy_from_scalar_results_bind_group_layout: BindGroupLayout,
 }
 
+/// Pipeline for XPBY where beta is read from `scalar_results_buffer[scalar_index]`:
+///   y[i] = x[i] + beta * y[i]
+///
+/// Shares the bind group layout shape of AXPY_FROM_SCALAR_RESULTS (params, x RO, y RW,
+/// scalar_results RO).
+pub struct XpbyFromScalarResultsPipeline {
+    pub pipeline: ComputePipeline,
+    pub xpby_from_scalar_results_bind_group_layout: BindGroupLayout,
+}
+
+/// Pipeline for WAXPBY where alpha and beta are read from `scalar_results_buffer`:
+///   w[i] = alpha * x[i] + beta * y[i]
+///
+/// w must be distinct from x and y; x and y may alias (both are read-only).
+pub struct WaxpbyFromScalarResultsPipeline {
+    pub pipeline: ComputePipeline,
+    pub waxpby_from_scalar_results_bind_group_layout: BindGroupLayout,
+}
+
+/// Pipeline for SCALE with an immediate alpha (uniform, axpy.wgsl layout):
+///   x[i] = alpha * x[i]
+pub struct ScalePipeline {
+    pub pipeline: ComputePipeline,
+    pub scale_bind_group_layout: BindGroupLayout,
+}
+
 /// Pipeline for SCALE where beta is read from `scalar_results_buffer[scalar_index]`:
 ///   x[i] = x[i] * beta
 pub struct ScaleFromScalarResultsPipeline {
@@ -28,6 +54,26 @@ pub struct ScaleFromScalarResultsPipeline {
     pub scale_from_scalar_results_bind_group_layout: BindGroupLayout,
 }
 
+/// Pipeline for the out-of-place SCALED COPY kernel:
+///   y[i] = alpha * x[i]
+/// where `alpha` is provided in a uniform buffer (same layout as AXPY).
+///
+/// x and y must be distinct buffers; in-place scaling goes through SCALE.
+pub struct ScaledCopyPipeline {
+    pub pipeline: ComputePipeline,
+    pub scaled_copy_bind_group_layout: BindGroupLayout,
+}
+
+/// Pipeline for NORMALIZE where the squared norm is read from
+/// `scalar_results_buffer[scalar_index]`:
+///   x[i] = x[i] / sqrt(norm2)   (zeros when norm2 <= 0)
+///
+/// Shares the bind group layout shape of SCALE (params, x RW, scalar_results RO).
+pub struct NormalizeFromScalarResultsPipeline {
+    pub pipeline: ComputePipeline,
+    pub normalize_from_scalar_results_bind_group_layout: BindGroupLayout,
+}
+
 fn create_uniform_entry(binding: u32) -> BindGroupLayoutEntry {
     BindGroupLayoutEntry {
         binding,
@@ -60,7 +106,7 @@ pub fn create_axpy_pipeline(ctx: &GpuContext) -> AxpyPipeline {
     let device = &ctx.device;
 
     let shader = device.create_shader_module(ShaderModuleDescriptor {
-        label: Some("axpy.wgsl"),
+        label: Some(&ctx.label("axpy.wgsl")),
         source: ShaderSource::Wgsl(include_str!("wgsl/axpy.wgsl").into()),
     });
 
@@ -69,7 +115,7 @@ pub fn create_axpy_pipeline(ctx: &GpuContext) -> AxpyPipeline {
     //   binding(1): x RO storage
     //   binding(2): y RW storage
     let axpy_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-        label: Some("axpy bgl0"),
+        label: Some(&ctx.label("axpy bgl0")),
         entries: &[
             create_uniform_entry(0),
             create_storage_entry(1, true),
@@ -78,13 +124,13 @@ pub fn create_axpy_pipeline(ctx: &GpuContext) -> AxpyPipeline {
     });
 
     let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-        label: Some("axpy pipeline layout"),
+        label: Some(&ctx.label("axpy pipeline layout")),
         bind_group_layouts: &[&axpy_bind_group_layout],
         immediate_size: 0,
     });
 
     let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
-        label: Some("axpy pipeline"),
+        label: Some(&ctx.label("axpy pipeline")),
         layout: Some(&pipeline_layout),
         module: &shader,
         entry_point: Some("compute_main"),
@@ -102,7 +148,7 @@ pub fn create_axpy_from_scalar_results_pipeline(ctx: &GpuContext) -> AxpyFromSca
     let device = &ctx.device;
 
     let shader = device.create_shader_module(ShaderModuleDescriptor {
-        label: Some("axpy_from_scalar_results.wgsl"),
+        label: Some(&ctx.label("axpy_from_scalar_results.wgsl")),
         source: ShaderSource::Wgsl(include_str!("wgsl/axpy_from_scalar_results.wgsl").into()),
     });
 
@@ -113,7 +159,7 @@ pub fn create_axpy_from_scalar_results_pipeline(ctx: &GpuContext) -> AxpyFromSca
     //   binding(3): scalar_results RO storage
     let axpy_from_scalar_results_bind_group_layout =
         device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: Some("axpy_from_scalar_results bgl0"),
+            label: Some(&ctx.label("axpy_from_scalar_results bgl0")),
             entries: &[
                 create_uniform_entry(0),
                 create_storage_entry(1, true),
@@ -123,13 +169,13 @@ pub fn create_axpy_from_scalar_results_pipeline(ctx: &GpuContext) -> AxpyFromSca
         });
 
     let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-        label: Some("axpy_from_scalar_results pipeline layout"),
+        label: Some(&ctx.label("axpy_from_scalar_results pipeline layout")),
         bind_group_layouts: &[&axpy_from_scalar_results_bind_group_layout],
         immediate_size: 0,
     });
 
     let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
-        label: Some("axpy_from_scalar_results pipeline"),
+        label: Some(&ctx.label("axpy_from_scalar_results pipeline")),
         layout: Some(&pipeline_layout),
         module: &shader,
         entry_point: Some("compute_main"),
@@ -143,13 +189,144 @@ pub fn create_axpy_from_scalar_results_pipeline(ctx: &GpuContext) -> AxpyFromSca
     }
 }
 
+pub fn create_xpby_from_scalar_results_pipeline(ctx: &GpuContext) -> XpbyFromScalarResultsPipeline {
+    let device = &ctx.device;
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(&ctx.label("xpby_from_scalar_results.wgsl")),
+        source: ShaderSource::Wgsl(include_str!("wgsl/xpby_from_scalar_results.wgsl").into()),
+    });
+
+    // WGSL group(0) bindings:
+    //   binding(0): uniform Params (n, scalar_index)
+    //   binding(1): x RO storage
+    //   binding(2): y RW storage
+    //   binding(3): scalar_results RO storage
+    let xpby_from_scalar_results_bind_group_layout =
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&ctx.label("xpby_from_scalar_results bgl0")),
+            entries: &[
+                create_uniform_entry(0),
+                create_storage_entry(1, true),
+                create_storage_entry(2, false),
+                create_storage_entry(3, true),
+            ],
+        });
+
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&ctx.label("xpby_from_scalar_results pipeline layout")),
+        bind_group_layouts: &[&xpby_from_scalar_results_bind_group_layout],
+        immediate_size: 0,
+    });
+
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(&ctx.label("xpby_from_scalar_results pipeline")),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("compute_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    XpbyFromScalarResultsPipeline {
+        pipeline,
+        xpby_from_scalar_results_bind_group_layout,
+    }
+}
+
+pub fn create_waxpby_from_scalar_results_pipeline(
+    ctx: &GpuContext,
+) -> WaxpbyFromScalarResultsPipeline {
+    let device = &ctx.device;
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(&ctx.label("waxpby_from_scalar_results.wgsl")),
+        source: ShaderSource::Wgsl(include_str!("wgsl/waxpby_from_scalar_results.wgsl").into()),
+    });
+
+    // WGSL group(0) bindings:
+    //   binding(0): uniform Params (n, alpha_index, beta_index)
+    //   binding(1): x RO storage
+    //   binding(2): y RO storage
+    //   binding(3): w RW storage
+    //   binding(4): scalar_results RO storage
+    let waxpby_from_scalar_results_bind_group_layout =
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&ctx.label("waxpby_from_scalar_results bgl0")),
+            entries: &[
+                create_uniform_entry(0),
+                create_storage_entry(1, true),
+                create_storage_entry(2, true),
+                create_storage_entry(3, false),
+                create_storage_entry(4, true),
+            ],
+        });
+
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&ctx.label("waxpby_from_scalar_results pipeline layout")),
+        bind_group_layouts: &[&waxpby_from_scalar_results_bind_group_layout],
+        immediate_size: 0,
+    });
+
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(&ctx.label("waxpby_from_scalar_results pipeline")),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("compute_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    WaxpbyFromScalarResultsPipeline {
+        pipeline,
+        waxpby_from_scalar_results_bind_group_layout,
+    }
+}
+
+pub fn create_scale_pipeline(ctx: &GpuContext) -> ScalePipeline {
+    let device = &ctx.device;
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(&ctx.label("scale.wgsl")),
+        source: ShaderSource::Wgsl(include_str!("wgsl/scale.wgsl").into()),
+    });
+
+    // WGSL group(0) bindings:
+    //   binding(0): uniform Params (n, alpha)
+    //   binding(1): x RW storage
+    let scale_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some(&ctx.label("scale bgl0")),
+        entries: &[create_uniform_entry(0), create_storage_entry(1, false)],
+    });
+
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&ctx.label("scale pipeline layout")),
+        bind_group_layouts: &[&scale_bind_group_layout],
+        immediate_size: 0,
+    });
+
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(&ctx.label("scale pipeline")),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("compute_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    ScalePipeline {
+        pipeline,
+        scale_bind_group_layout,
+    }
+}
+
 pub fn create_scale_from_scalar_results_pipeline(
     ctx: &GpuContext,
 ) -> ScaleFromScalarResultsPipeline {
     let device = &ctx.device;
 
     let shader = device.create_shader_module(ShaderModuleDescriptor {
-        label: Some("scale_from_scalar_results.wgsl"),
+        label: Some(&ctx.label("scale_from_scalar_results.wgsl")),
         source: ShaderSource::Wgsl(include_str!("wgsl/scale_from_scalar_results.wgsl").into()),
     });
 
@@ -159,7 +336,7 @@ pub fn create_scale_from_scalar_results_pipeline(
     //   binding(2): scalar_results RO storage
     let scale_from_scalar_results_bind_group_layout =
         device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: Some("scale_from_scalar_results bgl0"),
+            label: Some(&ctx.label("scale_from_scalar_results bgl0")),
             entries: &[
                 create_uniform_entry(0),
                 create_storage_entry(1, false),
@@ -168,13 +345,13 @@ pub fn create_scale_from_scalar_results_pipeline(
         });
 
     let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-        label: Some("scale_from_scalar_results pipeline layout"),
+        label: Some(&ctx.label("scale_from_scalar_results pipeline layout")),
         bind_group_layouts: &[&scale_from_scalar_results_bind_group_layout],
         immediate_size: 0,
     });
 
     let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
-        label: Some("scale_from_scalar_results pipeline"),
+        label: Some(&ctx.label("scale_from_scalar_results pipeline")),
         layout: Some(&pipeline_layout),
         module: &shader,
         entry_point: Some("compute_main"),
@@ -188,6 +365,94 @@ pub fn create_scale_from_scalar_results_pipeline(
     }
 }
 
+pub fn create_scaled_copy_pipeline(ctx: &GpuContext) -> ScaledCopyPipeline {
+    let device = &ctx.device;
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(&ctx.label("scaled_copy.wgsl")),
+        source: ShaderSource::Wgsl(include_str!("wgsl/scaled_copy.wgsl").into()),
+    });
+
+    // WGSL group(0) bindings:
+    //   binding(0): uniform Params (n, alpha)
+    //   binding(1): x RO storage (source)
+    //   binding(2): y RW storage (destination)
+    let scaled_copy_bind_group_layout =
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&ctx.label("scaled_copy bgl0")),
+            entries: &[
+                create_uniform_entry(0),
+                create_storage_entry(1, true),
+                create_storage_entry(2, false),
+            ],
+        });
+
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&ctx.label("scaled_copy pipeline layout")),
+        bind_group_layouts: &[&scaled_copy_bind_group_layout],
+        immediate_size: 0,
+    });
+
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(&ctx.label("scaled_copy pipeline")),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("compute_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    ScaledCopyPipeline {
+        pipeline,
+        scaled_copy_bind_group_layout,
+    }
+}
+
+pub fn create_normalize_from_scalar_results_pipeline(
+    ctx: &GpuContext,
+) -> NormalizeFromScalarResultsPipeline {
+    let device = &ctx.device;
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(&ctx.label("normalize_from_scalar_results.wgsl")),
+        source: ShaderSource::Wgsl(include_str!("wgsl/normalize_from_scalar_results.wgsl").into()),
+    });
+
+    // WGSL group(0) bindings:
+    //   binding(0): uniform Params (n, scalar_index)
+    //   binding(1): x RW storage
+    //   binding(2): scalar_results RO storage
+    let normalize_from_scalar_results_bind_group_layout =
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&ctx.label("normalize_from_scalar_results bgl0")),
+            entries: &[
+                create_uniform_entry(0),
+                create_storage_entry(1, false),
+                create_storage_entry(2, true),
+            ],
+        });
+
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&ctx.label("normalize_from_scalar_results pipeline layout")),
+        bind_group_layouts: &[&normalize_from_scalar_results_bind_group_layout],
+        immediate_size: 0,
+    });
+
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(&ctx.label("normalize_from_scalar_results pipeline")),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("compute_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    NormalizeFromScalarResultsPipeline {
+        pipeline,
+        normalize_from_scalar_results_bind_group_layout,
+    }
+}
+
 pub fn create_axpy_bind_group(
     device: &Device,
     axpy_bind_group_layout: &BindGroupLayout,
@@ -247,6 +512,97 @@ pub fn create_axpy_from_scalar_results_bind_group(
     })
 }
 
+pub fn create_xpby_from_scalar_results_bind_group(
+    device: &Device,
+    xpby_from_scalar_results_bind_group_layout: &BindGroupLayout,
+    params_buffer: &Buffer,         // binding(0)
+    x_buffer: &Buffer,              // binding(1)
+    y_buffer: &Buffer,              // binding(2)
+    scalar_results_buffer: &Buffer, // binding(3)
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("xpby_from_scalar_results bind group 0"),
+        layout: xpby_from_scalar_results_bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: x_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: y_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 3,
+                resource: scalar_results_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+pub fn create_waxpby_from_scalar_results_bind_group(
+    device: &Device,
+    waxpby_from_scalar_results_bind_group_layout: &BindGroupLayout,
+    params_buffer: &Buffer,         // binding(0)
+    x_buffer: &Buffer,              // binding(1)
+    y_buffer: &Buffer,              // binding(2)
+    w_buffer: &Buffer,              // binding(3)
+    scalar_results_buffer: &Buffer, // binding(4)
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("waxpby_from_scalar_results bind group 0"),
+        layout: waxpby_from_scalar_results_bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: x_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: y_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 3,
+                resource: w_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 4,
+                resource: scalar_results_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+pub fn create_scale_bind_group(
+    device: &Device,
+    scale_bind_group_layout: &BindGroupLayout,
+    params_buffer: &Buffer, // binding(0)
+    x_buffer: &Buffer,      // binding(1)
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("scale bind group 0"),
+        layout: scale_bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: x_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
 pub fn create_scale_from_scalar_results_bind_group(
     device: &Device,
     scale_from_scalar_results_bind_group_layout: &BindGroupLayout,
@@ -273,3 +629,57 @@ pub fn create_scale_from_scalar_results_bind_group(
         ],
     })
 }
+
+pub fn create_scaled_copy_bind_group(
+    device: &Device,
+    scaled_copy_bind_group_layout: &BindGroupLayout,
+    params_buffer: &Buffer, // binding(0)
+    src_buffer: &Buffer,    // binding(1)
+    dst_buffer: &Buffer,    // binding(2)
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("scaled_copy bind group 0"),
+        layout: scaled_copy_bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: src_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: dst_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+pub fn create_normalize_from_scalar_results_bind_group(
+    device: &Device,
+    normalize_from_scalar_results_bind_group_layout: &BindGroupLayout,
+    params_buffer: &Buffer,         // binding(0)
+    x_buffer: &Buffer,              // binding(1)
+    scalar_results_buffer: &Buffer, // binding(2)
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("normalize_from_scalar_results bind group 0"),
+        layout: normalize_from_scalar_results_bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: x_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: scalar_results_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
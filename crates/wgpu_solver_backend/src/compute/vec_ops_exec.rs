@@ -4,17 +4,34 @@ use bytemuck::cast_slice;
 use wgpu::{Buffer, BufferDescriptor, BufferUsages, CommandEncoder, ComputePassDescriptor};
 
 use crate::compute::vec_ops::{
-    AxpyFromScalarResultsPipeline, AxpyPipeline, ScaleFromScalarResultsPipeline,
-    create_axpy_bind_group, create_axpy_from_scalar_results_bind_group,
-    create_axpy_from_scalar_results_pipeline, create_axpy_pipeline,
+    AxpyFromScalarResultsPipeline, AxpyPipeline, NormalizeFromScalarResultsPipeline,
+    ScaleFromScalarResultsPipeline, ScalePipeline, ScaledCopyPipeline,
+    WaxpbyFromScalarResultsPipeline, XpbyFromScalarResultsPipeline, create_axpy_bind_group,
+    create_axpy_from_scalar_results_bind_group, create_axpy_from_scalar_results_pipeline,
+    create_axpy_pipeline, create_normalize_from_scalar_results_bind_group,
+    create_normalize_from_scalar_results_pipeline, create_scale_bind_group,
     create_scale_from_scalar_results_bind_group, create_scale_from_scalar_results_pipeline,
+    create_scale_pipeline, create_scaled_copy_bind_group, create_scaled_copy_pipeline,
+    create_waxpby_from_scalar_results_bind_group, create_waxpby_from_scalar_results_pipeline,
+    create_xpby_from_scalar_results_bind_group, create_xpby_from_scalar_results_pipeline,
 };
 use crate::gpu::context::GpuContext;
 
+/// Workgroups for the grid-strided SCALE kernels: one element per invocation up to the
+/// per-dimension dispatch limit, more per invocation past it.
+fn strided_scale_workgroups(ctx: &GpuContext, n: u32) -> u32 {
+    n.div_ceil(256)
+        .min(ctx.device.limits().max_compute_workgroups_per_dimension)
+}
+
 /// Vector-ops executor used by PCG:
 ///   - AXPY with an *immediate* scalar alpha (uniform contains alpha bits)
 ///   - AXPY with alpha read from scalar_results_buffer[scalar_index]
-///   - SCALE with beta read from scalar_results_buffer[scalar_index]
+///   - XPBY y = x + beta * y and WAXPBY w = alpha * x + beta * y with the scalars read from
+///     scalar_results_buffer (one dispatch each, e.g. the CG direction update)
+///   - SCALE with an *immediate* alpha, or beta read from scalar_results_buffer[scalar_index]
+///   - SCALED COPY dst = alpha * src with an immediate alpha (src != dst)
+///   - NORMALIZE by the squared norm stored in scalar_results_buffer[scalar_index]
 ///
 /// This executor owns:
 ///   - pipelines + bind group layouts
@@ -27,9 +44,24 @@ pub struct VecOpsExecutor {
     // y = y + scalar_results[scalar_index] * x
     axpy_from_scalar_results_pipeline: AxpyFromScalarResultsPipeline,
 
+    // y = x + scalar_results[scalar_index] * y
+    xpby_from_scalar_results_pipeline: XpbyFromScalarResultsPipeline,
+
+    // w = scalar_results[alpha_index] * x + scalar_results[beta_index] * y
+    waxpby_from_scalar_results_pipeline: WaxpbyFromScalarResultsPipeline,
+
+    // x = alpha * x  (alpha comes from uniform)
+    scale_pipeline: ScalePipeline,
+
     // x = x * scalar_results[scalar_index]
     scale_from_scalar_results_pipeline: ScaleFromScalarResultsPipeline,
 
+    // y = alpha * x  (alpha comes from uniform, x and y distinct)
+    scaled_copy_pipeline: ScaledCopyPipeline,
+
+    // x = x / sqrt(scalar_results[scalar_index])  (zeros for a zero norm)
+    normalize_from_scalar_results_pipeline: NormalizeFromScalarResultsPipeline,
+
     // Uniform params pool shared across all vec-op kernels.
     // Each kernel reads only the subset of fields it needs.
     params_buffers: Vec<Buffer>,
@@ -38,11 +70,15 @@ pub struct VecOpsExecutor {
 
 impl VecOpsExecutor {
     pub fn create(ctx: &GpuContext) -> Self {
-        let device = &ctx.device;
-
         let axpy_pipeline = create_axpy_pipeline(ctx);
         let axpy_from_scalar_results_pipeline = create_axpy_from_scalar_results_pipeline(ctx);
+        let xpby_from_scalar_results_pipeline = create_xpby_from_scalar_results_pipeline(ctx);
+        let waxpby_from_scalar_results_pipeline = create_waxpby_from_scalar_results_pipeline(ctx);
+        let scale_pipeline = create_scale_pipeline(ctx);
         let scale_from_scalar_results_pipeline = create_scale_from_scalar_results_pipeline(ctx);
+        let scaled_copy_pipeline = create_scaled_copy_pipeline(ctx);
+        let normalize_from_scalar_results_pipeline =
+            create_normalize_from_scalar_results_pipeline(ctx);
 
         // Pool size:
         // must cover the maximum number of vec-ops encoded between submits.
@@ -58,10 +94,11 @@ impl VecOpsExecutor {
             //
             // 2) Scalar-results index layout (*_from_scalar_results.wgsl):
             //    [n, scalar_index, 0, 0]            (4 u32 = 16 bytes)
+            //    [n, alpha_index, beta_index, 0]    (waxpby)
             //
             // We just standardize on 32 bytes for everything.
-            let buf = device.create_buffer(&BufferDescriptor {
-                label: Some(&format!("vec_ops params {}", i)),
+            let buf = ctx.create_buffer(&BufferDescriptor {
+                label: Some(&ctx.label(&format!("vec_ops params {}", i))),
                 size: 32,
                 usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
                 mapped_at_creation: false,
@@ -72,7 +109,12 @@ impl VecOpsExecutor {
         Self {
             axpy_pipeline,
             axpy_from_scalar_results_pipeline,
+            xpby_from_scalar_results_pipeline,
+            waxpby_from_scalar_results_pipeline,
+            scale_pipeline,
             scale_from_scalar_results_pipeline,
+            scaled_copy_pipeline,
+            normalize_from_scalar_results_pipeline,
             params_buffers,
             params_cursor: Cell::new(0),
         }
@@ -88,8 +130,8 @@ impl VecOpsExecutor {
     ///
     /// Layout (32 bytes, 8 u32s):
     ///   u32 n            @ offset  0
-    ///   u32 _pad0        @ offset  4
-    ///   u32 _pad1        @ offset  8
+    ///   u32 x_offset     @ offset  4   (axpy.wgsl only, in elements)
+    ///   u32 y_offset     @ offset  8   (axpy.wgsl only, in elements)
     ///   u32 _pad2        @ offset 12
     ///   u32 alpha_bits   @ offset 16   (f32::to_bits)
     ///   u32 _pad3        @ offset 20
@@ -100,10 +142,11 @@ impl VecOpsExecutor {
         ctx: &GpuContext,
         params_buffer: &Buffer,
         n: u32,
+        offsets: (u32, u32),
         alpha: f32,
     ) {
         let alpha_u32 = alpha.to_bits();
-        let words: [u32; 8] = [n, 0, 0, 0, alpha_u32, 0, 0, 0];
+        let words: [u32; 8] = [n, offsets.0, offsets.1, 0, alpha_u32, 0, 0, 0];
         ctx.queue.write_buffer(params_buffer, 0, cast_slice(&words));
     }
 
@@ -126,6 +169,25 @@ impl VecOpsExecutor {
         ctx.queue.write_buffer(params_buffer, 0, cast_slice(&words));
     }
 
+    /// Write uniform params for WAXPBY, which reads two scalars from `scalar_results_buffer`.
+    ///
+    /// Layout (16 bytes, 4 u32s):
+    ///   u32 n           @ offset  0
+    ///   u32 alpha_index @ offset  4
+    ///   u32 beta_index  @ offset  8
+    ///   u32 _pad0       @ offset 12
+    fn write_params_for_two_scalar_results_indices(
+        &self,
+        ctx: &GpuContext,
+        params_buffer: &Buffer,
+        n: u32,
+        alpha_index: u32,
+        beta_index: u32,
+    ) {
+        let words: [u32; 4] = [n, alpha_index, beta_index, 0];
+        ctx.queue.write_buffer(params_buffer, 0, cast_slice(&words));
+    }
+
     /// Encode: y = y + alpha * x, where alpha is provided immediately (uniform).
     pub fn encode_axpy_inplace(
         &self,
@@ -135,9 +197,65 @@ impl VecOpsExecutor {
         y_buffer: &Buffer,
         n: u32,
         alpha: f32,
+    ) {
+        self.encode_axpy_pass(ctx, encoder, x_buffer, 0, y_buffer, 0, n, alpha);
+    }
+
+    /// Encode: y[y_offset..y_offset + len] += alpha * x[x_offset..x_offset + len], leaving
+    /// the rest of y untouched.
+    ///
+    /// Offsets and `len` count f32 elements, independently for x and y (the byte offset is
+    /// 4 * offset, so it is always 4-byte aligned and needs nothing more). The kernel applies
+    /// them itself and binds whole buffers, so the 256-byte
+    /// `min_storage_buffer_offset_alignment` of a bound sub-range does not apply.
+    ///
+    /// Fails when a range runs past the end of its buffer, or when x and y are the same
+    /// buffer (it would be bound read-only and read-write in one dispatch).
+    #[allow(clippy::too_many_arguments)]
+    pub fn encode_axpy_range(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        x_buffer: &Buffer,
+        x_offset: u32,
+        y_buffer: &Buffer,
+        y_offset: u32,
+        len: u32,
+        alpha: f32,
+    ) -> Result<(), String> {
+        for (name, buffer, offset) in [("x", x_buffer, x_offset), ("y", y_buffer, y_offset)] {
+            let capacity = buffer.size() / 4;
+            if offset as u64 + len as u64 > capacity {
+                return Err(format!(
+                    "axpy range: {name}[{offset}..{}] exceeds the buffer ({capacity} f32)",
+                    offset as u64 + len as u64
+                ));
+            }
+        }
+        if x_buffer == y_buffer {
+            return Err("axpy range: x and y must be different buffers".into());
+        }
+
+        self.encode_axpy_pass(
+            ctx, encoder, x_buffer, x_offset, y_buffer, y_offset, len, alpha,
+        );
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn encode_axpy_pass(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        x_buffer: &Buffer,
+        x_offset: u32,
+        y_buffer: &Buffer,
+        y_offset: u32,
+        n: u32,
+        alpha: f32,
     ) {
         let params_buffer = self.next_params_buffer();
-        self.write_params_for_immediate_scalar(ctx, params_buffer, n, alpha);
+        self.write_params_for_immediate_scalar(ctx, params_buffer, n, (x_offset, y_offset), alpha);
 
         let bind_group = create_axpy_bind_group(
             &ctx.device,
@@ -148,7 +266,7 @@ impl VecOpsExecutor {
         );
 
         let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
-            label: Some("axpy pass"),
+            label: Some(&ctx.label("axpy pass")),
             timestamp_writes: None,
         });
 
@@ -160,6 +278,7 @@ impl VecOpsExecutor {
     }
 
     /// Encode: y = y + scalar_results[scalar_index] * x.
+    #[allow(clippy::too_many_arguments)]
     pub fn encode_axpy_inplace_from_scalar_results(
         &self,
         ctx: &GpuContext,
@@ -185,7 +304,7 @@ impl VecOpsExecutor {
         );
 
         let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
-            label: Some("axpy_from_scalar_results pass"),
+            label: Some(&ctx.label("axpy_from_scalar_results pass")),
             timestamp_writes: None,
         });
 
@@ -196,6 +315,99 @@ impl VecOpsExecutor {
         pass.dispatch_workgroups(n.div_ceil(workgroup_size), 1, 1);
     }
 
+    /// Encode: y = x + scalar_results[scalar_index] * y.
+    ///
+    /// One dispatch for what is otherwise a SCALE of y followed by an AXPY with alpha = 1,
+    /// e.g. the CG direction update p = z + beta * p. x and y must be different buffers.
+    #[allow(clippy::too_many_arguments)]
+    pub fn encode_xpby_inplace_from_scalar_results(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        x_buffer: &Buffer,
+        y_buffer: &Buffer,
+        n: u32,
+        scalar_results_buffer: &Buffer,
+        scalar_index: u32,
+    ) {
+        let params_buffer = self.next_params_buffer();
+        self.write_params_for_scalar_results_index(ctx, params_buffer, n, scalar_index);
+
+        let bind_group = create_xpby_from_scalar_results_bind_group(
+            &ctx.device,
+            &self
+                .xpby_from_scalar_results_pipeline
+                .xpby_from_scalar_results_bind_group_layout,
+            params_buffer,
+            x_buffer,
+            y_buffer,
+            scalar_results_buffer,
+        );
+
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some(&ctx.label("xpby_from_scalar_results pass")),
+            timestamp_writes: None,
+        });
+
+        pass.set_pipeline(&self.xpby_from_scalar_results_pipeline.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+
+        let workgroup_size = 256u32;
+        pass.dispatch_workgroups(n.div_ceil(workgroup_size), 1, 1);
+    }
+
+    /// Encode: w = scalar_results[alpha_index] * x + scalar_results[beta_index] * y.
+    ///
+    /// Every element of w is overwritten, so it need not be initialized. Aliasing: w must
+    /// differ from x and y (it is bound read-write, they read-only); x and y may be the
+    /// same buffer. To update y in place with alpha = 1, use
+    /// [`Self::encode_xpby_inplace_from_scalar_results`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn encode_waxpby_from_scalar_results(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        x_buffer: &Buffer,
+        y_buffer: &Buffer,
+        w_buffer: &Buffer,
+        n: u32,
+        scalar_results_buffer: &Buffer,
+        alpha_index: u32,
+        beta_index: u32,
+    ) {
+        let params_buffer = self.next_params_buffer();
+        self.write_params_for_two_scalar_results_indices(
+            ctx,
+            params_buffer,
+            n,
+            alpha_index,
+            beta_index,
+        );
+
+        let bind_group = create_waxpby_from_scalar_results_bind_group(
+            &ctx.device,
+            &self
+                .waxpby_from_scalar_results_pipeline
+                .waxpby_from_scalar_results_bind_group_layout,
+            params_buffer,
+            x_buffer,
+            y_buffer,
+            w_buffer,
+            scalar_results_buffer,
+        );
+
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some(&ctx.label("waxpby_from_scalar_results pass")),
+            timestamp_writes: None,
+        });
+
+        pass.set_pipeline(&self.waxpby_from_scalar_results_pipeline.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+
+        let workgroup_size = 256u32;
+        pass.dispatch_workgroups(n.div_ceil(workgroup_size), 1, 1);
+    }
+
     /// Encode: x = x * scalar_results[scalar_index].
     pub fn encode_scale_inplace_from_scalar_results(
         &self,
@@ -220,13 +432,124 @@ impl VecOpsExecutor {
         );
 
         let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
-            label: Some("scale_from_scalar_results pass"),
+            label: Some(&ctx.label("scale_from_scalar_results pass")),
             timestamp_writes: None,
         });
 
         pass.set_pipeline(&self.scale_from_scalar_results_pipeline.pipeline);
         pass.set_bind_group(0, &bind_group, &[]);
 
+        pass.dispatch_workgroups(strided_scale_workgroups(ctx, n), 1, 1);
+    }
+
+    /// Encode: x = alpha * x, where alpha is provided immediately (uniform).
+    ///
+    /// Like [`Self::encode_scale_inplace_from_scalar_results`], the kernel is grid-strided,
+    /// so n is not limited by the per-dimension dispatch limit.
+    pub fn encode_scale_inplace(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        x_buffer: &Buffer,
+        n: u32,
+        alpha: f32,
+    ) {
+        let params_buffer = self.next_params_buffer();
+        self.write_params_for_immediate_scalar(ctx, params_buffer, n, (0, 0), alpha);
+
+        let bind_group = create_scale_bind_group(
+            &ctx.device,
+            &self.scale_pipeline.scale_bind_group_layout,
+            params_buffer,
+            x_buffer,
+        );
+
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some(&ctx.label("scale pass")),
+            timestamp_writes: None,
+        });
+
+        pass.set_pipeline(&self.scale_pipeline.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+
+        pass.dispatch_workgroups(strided_scale_workgroups(ctx, n), 1, 1);
+    }
+
+    /// Encode: dst = alpha * src, where alpha is provided immediately (uniform).
+    ///
+    /// One dispatch instead of a buffer copy followed by a scale, e.g. to start PCG from
+    /// x0 = scale * x_prev. Every element of dst is overwritten, so it need not be
+    /// initialized.
+    ///
+    /// Aliasing: `src_buffer` and `dst_buffer` must be different buffers (wgpu rejects
+    /// a buffer bound as both read-only and read-write storage in one dispatch). To
+    /// scale a vector in place, use [`Self::encode_scale_inplace_from_scalar_results`].
+    pub fn encode_scaled_copy(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        src_buffer: &Buffer,
+        dst_buffer: &Buffer,
+        n: u32,
+        alpha: f32,
+    ) {
+        let params_buffer = self.next_params_buffer();
+        self.write_params_for_immediate_scalar(ctx, params_buffer, n, (0, 0), alpha);
+
+        let bind_group = create_scaled_copy_bind_group(
+            &ctx.device,
+            &self.scaled_copy_pipeline.scaled_copy_bind_group_layout,
+            params_buffer,
+            src_buffer,
+            dst_buffer,
+        );
+
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some(&ctx.label("scaled_copy pass")),
+            timestamp_writes: None,
+        });
+
+        pass.set_pipeline(&self.scaled_copy_pipeline.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+
+        let workgroup_size = 256u32;
+        pass.dispatch_workgroups(n.div_ceil(workgroup_size), 1, 1);
+    }
+
+    /// Encode: x = x / sqrt(scalar_results[scalar_index]).
+    ///
+    /// The slot must hold the *squared* norm, i.e. what `DotScalarExecutor` writes for
+    /// dot(x, x). A zero norm leaves x as all zeros rather than NaN.
+    pub fn encode_normalize_inplace_from_scalar_results(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        x_buffer: &Buffer,
+        n: u32,
+        scalar_results_buffer: &Buffer,
+        scalar_index: u32,
+    ) {
+        let params_buffer = self.next_params_buffer();
+        self.write_params_for_scalar_results_index(ctx, params_buffer, n, scalar_index);
+
+        let bind_group = create_normalize_from_scalar_results_bind_group(
+            &ctx.device,
+            &self
+                .normalize_from_scalar_results_pipeline
+                .normalize_from_scalar_results_bind_group_layout,
+            params_buffer,
+            x_buffer,
+            scalar_results_buffer,
+        );
+
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some(&ctx.label("normalize_from_scalar_results pass")),
+            timestamp_writes: None,
+        });
+
+        pass.set_pipeline(&self.normalize_from_scalar_results_pipeline.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+
         let workgroup_size = 256u32;
         pass.dispatch_workgroups(n.div_ceil(workgroup_size), 1, 1);
     }
@@ -0,0 +1,57 @@
+// Additive Schwarz preconditioner, pass 2 of 2: combine the local solves into z (GPU)
+//
+// DOF i occurs in the subdomains whose index sets contain it; occ lists, per DOF, the
+// positions of those occurrences in local_z (the transpose of dof_ptr / dofs):
+//
+//     occ[occ_ptr[i] .. occ_ptr[i + 1])
+//
+// and this pass gathers them:
+//
+//     combine == 0 (add):     z[i] = sum_k local_z[occ[k]]
+//     combine == 1 (average): z[i] = sum_k local_z[occ[k]] / (occ_ptr[i + 1] - occ_ptr[i])
+//
+// Every DOF is covered by at least one subdomain (validated on the host), so the
+// average never divides by zero. Being a gather, the sum needs no atomics and its order
+// (increasing subdomain id) is fixed, so repeated applies are bitwise identical.
+//
+// Work mapping:
+//   - workgroup_size = 256, one invocation per DOF, dispatch ceil(n / 256) workgroups
+//
+// Bindings (group 0):
+//   binding(0): uniform Params { n, num_blocks, block_size, combine }
+//   binding(1): occ_ptr (u32)    read-only storage, n + 1
+//   binding(2): occ     (u32)    read-only storage, occ_ptr[n]
+//   binding(3): local_z (f32)    read-only storage
+//   binding(4): z       (f32, n) read-write storage
+
+struct Params {
+    n: u32,
+    num_blocks: u32,
+    block_size: u32,
+    combine: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> occ_ptr: array<u32>;
+@group(0) @binding(2) var<storage, read> occ: array<u32>;
+@group(0) @binding(3) var<storage, read> local_z: array<f32>;
+@group(0) @binding(4) var<storage, read_write> z: array<f32>;
+
+@compute @workgroup_size(256)
+fn compute_main(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let i: u32 = gid.x;
+    if (i >= params.n) {
+        return;
+    }
+
+    let start: u32 = occ_ptr[i];
+    let end: u32 = occ_ptr[i + 1u];
+    var sum: f32 = 0.0;
+    for (var k: u32 = start; k < end; k = k + 1u) {
+        sum = sum + local_z[occ[k]];
+    }
+    if (params.combine == 1u) {
+        sum = sum / f32(end - start);
+    }
+    z[i] = sum;
+}
@@ -0,0 +1,94 @@
+// Additive Schwarz preconditioner, pass 1 of 2: local subdomain solves (GPU)
+//
+// Subdomain b owns the DOF index set
+//
+//     S_b = dofs[dof_ptr[b] .. dof_ptr[b + 1])
+//
+// and the sets may overlap. This pass computes, for every subdomain independently,
+//
+//     local_z[dof_ptr[b] + i] = (A[S_b, S_b]^{-1} r[S_b])_i
+//
+// with the host-computed LU factors of the local matrix A[S_b, S_b]; pass 2
+// (additive_schwarz_combine.wgsl) sums the local results back into z per DOF. Keeping
+// the local results apart avoids float atomics on DOFs shared by several subdomains.
+//
+// Work mapping:
+//   - one invocation per subdomain (workgroup_size = 64), global_invocation_id.x == block_id
+//
+// Data layout contract (CPU <-> GPU):
+//   - lu_blocks packs one dense bs x bs slab per subdomain, row-major, bs * bs entries
+//     apart (bs = params.block_size <= MAX_BLOCK_SIZE, validated on the host)
+//   - a subdomain of m < bs DOFs only uses the leading m x m of its slab
+//   - LU storage as in block_jacobi.wgsl: strict lower triangle L (unit diagonal
+//     implicit), diagonal + upper triangle U, no pivoting
+//
+// Bindings (group 0):
+//   binding(0): uniform Params { n, num_blocks, block_size, combine }
+//   binding(1): lu_blocks (f32)     read-only storage
+//   binding(2): dof_ptr   (u32)     read-only storage, num_blocks + 1
+//   binding(3): dofs      (u32)     read-only storage, dof_ptr[num_blocks]
+//   binding(4): r         (f32, n)  read-only storage
+//   binding(5): local_z   (f32)     read-write storage, dof_ptr[num_blocks]
+
+struct Params {
+    n: u32,
+    num_blocks: u32,
+    block_size: u32,
+    combine: u32, // only read by the combine pass
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> lu_blocks: array<f32>;
+@group(0) @binding(2) var<storage, read> dof_ptr: array<u32>;
+@group(0) @binding(3) var<storage, read> dofs: array<u32>;
+@group(0) @binding(4) var<storage, read> r: array<f32>;
+@group(0) @binding(5) var<storage, read_write> local_z: array<f32>;
+
+// Size of the per-invocation temporaries; must match `additive_schwarz_exec::MAX_BLOCK_SIZE`.
+const MAX_BLOCK_SIZE: u32 = 32u;
+
+@compute @workgroup_size(64)
+fn compute_main(@builtin(global_invocation_id) gi_id: vec3<u32>) {
+    let block_id: u32 = gi_id.x;
+    if (block_id >= params.num_blocks) {
+        return;
+    }
+
+    let start: u32 = dof_ptr[block_id];
+    let bs: u32 = min(params.block_size, MAX_BLOCK_SIZE);
+    let m: u32 = min(bs, dof_ptr[block_id + 1u] - start);
+    let base: u32 = block_id * bs * bs;
+
+    var y: array<f32, MAX_BLOCK_SIZE>;
+    var x: array<f32, MAX_BLOCK_SIZE>;
+
+    // Forward solve: L y = r[S_b] (unit diagonal).
+    for (var i: u32 = 0u; i < m; i = i + 1u) {
+        var sum: f32 = r[dofs[start + i]];
+        for (var j: u32 = 0u; j < i; j = j + 1u) {
+            sum = sum - lu_blocks[base + i * bs + j] * y[j];
+        }
+        y[i] = sum;
+    }
+
+    // Backward solve: U x = y.
+    var ii: i32 = i32(m) - 1;
+    loop {
+        if (ii < 0) {
+            break;
+        }
+
+        let i: u32 = u32(ii);
+        var sum: f32 = y[i];
+        for (var j: u32 = i + 1u; j < m; j = j + 1u) {
+            sum = sum - lu_blocks[base + i * bs + j] * x[j];
+        }
+        x[i] = sum / lu_blocks[base + i * bs + i];
+
+        ii = ii - 1;
+    }
+
+    for (var i: u32 = 0u; i < m; i = i + 1u) {
+        local_z[start + i] = x[i];
+    }
+}
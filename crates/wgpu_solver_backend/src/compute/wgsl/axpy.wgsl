@@ -1,8 +1,12 @@
-// AXPY kernel (immediate scalar):
-//   y[i] = y[i] + alpha * x[i]
+// AXPY kernel (immediate scalar), over a sub-range of x and y:
+//   y[y_offset + i] = y[y_offset + i] + alpha * x[x_offset + i],   i < n
+//
+// The offsets are in f32 elements and come from the uniform, so the bindings always
+// cover the whole buffers (no storage-binding offset alignment applies). Whole-vector
+// AXPY is simply x_offset = y_offset = 0.
 //
 // Bindings (group 0):
-//   binding(0): uniform Params  (n, alpha)
+//   binding(0): uniform Params  (n, x_offset, y_offset, alpha)
 //   binding(1): x  read-only storage buffer
 //   binding(2): y  read-write storage buffer
 //
@@ -12,10 +16,11 @@
 // - Workgroup size is 256; each invocation handles one element.
 
 struct Params {
-    // Number of elements in x/y.
+    // Number of elements updated.
     n: u32,
-    _pad0: u32,
-    _pad1: u32,
+    // First element of x / y that is read / updated.
+    x_offset: u32,
+    y_offset: u32,
     _pad2: u32,
 
     // Scalar multiplier.
@@ -38,5 +43,6 @@ fn compute_main(@builtin(global_invocation_id) gi_id: vec3<u32>) {
         return;
     }
 
-    y[i] = y[i] + params.alpha * x[i];
+    let yi = params.y_offset + i;
+    y[yi] = y[yi] + params.alpha * x[params.x_offset + i];
 }
@@ -0,0 +1,91 @@
+// Purpose:
+//   k independent dot products in one pass tree:
+//
+//     results[j] = sum_i a[j*n + i] * b[j*n + i],   j = 0..k-1
+//
+//   `a` and `b` hold the k vectors of each pair back to back (length k*n each).
+//
+// Entry points (same bindings, explicit layout):
+//   partials_main: dispatch_workgroups(out_len, k), out_len = ceil(n / WG).
+//     Workgroup (x, j) sums the products over [x*WG, x*WG+WG-1] of pair j and writes
+//     output[j*out_len + x]: the partials are laid out [batch][partial].
+//   reduce_main: dispatch_workgroups(out_len, k), out_len = ceil(n / WG), n now the
+//     partials per batch of the previous pass. Folds input[j*n ..] by WG into
+//     output[j*out_len ..]; repeated until out_len = 1, when output is the result
+//     vector itself.
+//
+//   Same tree as dot_partials.wgsl + dot_reduce.wgsl (one level per pass, f32), with
+//   the batch index in workgroup_id.y. The unused bindings of each entry point are
+//   still bound, so a pass binds a, b and both scratch buffers.
+
+struct Params {
+    n: u32,       // entries per batch read by this pass (vector length, or partials)
+    k: u32,       // number of batches (pairs)
+    out_len: u32, // entries per batch written by this pass
+    _pad: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> a: array<f32>;
+@group(0) @binding(2) var<storage, read> b: array<f32>;
+@group(0) @binding(3) var<storage, read> input: array<f32>;
+@group(0) @binding(4) var<storage, read_write> output: array<f32>;
+
+const WORKGROUP_SIZE: u32 = 256u;
+
+var<workgroup> shared_memory: array<f32, WORKGROUP_SIZE>;
+
+// Reduce shared_memory[0..WG-1] into shared_memory[0] and store it at output[index].
+fn reduce_and_store(thread_id: u32, value: f32, index: u32) {
+    shared_memory[thread_id] = value;
+    workgroupBarrier();
+
+    var offset: u32 = WORKGROUP_SIZE / 2u;
+    loop {
+        if (thread_id < offset) {
+            shared_memory[thread_id] =
+                shared_memory[thread_id] + shared_memory[thread_id + offset];
+        }
+        workgroupBarrier();
+
+        if (offset == 1u) {
+            break;
+        }
+        offset = offset / 2u;
+    }
+
+    if (thread_id == 0u) {
+        output[index] = shared_memory[0];
+    }
+}
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn partials_main(
+    @builtin(local_invocation_id) li_id: vec3<u32>,
+    @builtin(workgroup_id) wg_id: vec3<u32>,
+) {
+    let batch: u32 = wg_id.y;
+    let i: u32 = wg_id.x * WORKGROUP_SIZE + li_id.x;
+
+    var v: f32 = 0.0;
+    if (i < params.n && batch < params.k) {
+        let idx: u32 = batch * params.n + i;
+        v = a[idx] * b[idx];
+    }
+    reduce_and_store(li_id.x, v, batch * params.out_len + wg_id.x);
+}
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn reduce_main(
+    @builtin(local_invocation_id) li_id: vec3<u32>,
+    @builtin(workgroup_id) wg_id: vec3<u32>,
+) {
+    let batch: u32 = wg_id.y;
+    let i: u32 = wg_id.x * WORKGROUP_SIZE + li_id.x;
+
+    var v: f32 = 0.0;
+    if (i < params.n && batch < params.k) {
+        v = input[batch * params.n + i];
+    }
+    reduce_and_store(li_id.x, v, batch * params.out_len + wg_id.x);
+}
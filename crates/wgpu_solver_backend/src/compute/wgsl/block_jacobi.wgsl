@@ -23,30 +23,44 @@
 //       next   = block_starts[block_id + 1]
 //
 // Data layout contract (CPU ↔ GPU):
-//   - BLOCK_SIZE is compile-time fixed (here 6)
-//   - lu_blocks packs one dense 6x6 matrix per block, row-major
-//   - For a short final block (m < 6), only the leading m×m portion is used
+//   - the slab size bs = params.block_size is a runtime value, 1 <= bs <= MAX_BLOCK_SIZE
+//     (validated on the host)
+//   - lu_blocks packs one dense bs x bs matrix per block, row-major
+//   - blocks are params.lu_stride entries apart (>= bs * bs); bs * bs is dense packing,
+//     larger strides pad each block to an alignment boundary
+//   - For a short block (m < bs), only the leading m×m portion is used
+//   - lu_blocks holds `lu_t` values: f32, or f16 when the host swaps the alias line for
+//     `enable f16; alias lu_t = f16;` (`LuStorage::F16`); they are widened to f32 on
+//     load, so the substitution itself always runs in f32
 //
 // LU storage contract (must match CPU builder):
 //   - Strict lower triangle stores L(i,j) for i > j
 //   - Diagonal and upper triangle store U(i,j) for i <= j
 //   - L has an implicit unit diagonal (L(i,i) == 1), i.e. it is NOT stored
 //
+// Active-block mask (binding 5):
+//   - block_mask[block_id] == 0: the block's slice of r is known to be all zero, so the
+//     solve is skipped and z_block = 0 is written (exactly what the solve would give)
+//   - the plain apply binds an all-ones mask owned by the executor
+//
 // IMPORTANT:
 //   - No pivoting is performed here.
 //   - U(i,i) must be non-zero; otherwise results become Inf/NaN.
+//   - The mask is trusted: masking a block whose r is not zero silently zeroes z there.
+
+alias lu_t = f32;
 
 struct Params {
     n: u32,          // full vector length
     num_blocks: u32, // == block_starts.len - 1
-    _pad1: u32,
-    _pad2: u32,
+    lu_stride: u32,  // entries between consecutive blocks in lu_blocks (>= block_size^2)
+    block_size: u32, // slab dimension bs (<= MAX_BLOCK_SIZE)
 };
 
 @group(0) @binding(0) var<uniform> params: Params;
 
-// Packed LU factors: num_blocks * (6*6) floats (row-major per block).
-@group(0) @binding(1) var<storage, read> lu_blocks: array<f32>;
+// Packed LU factors: num_blocks * lu_stride entries (bs x bs row-major per block + padding).
+@group(0) @binding(1) var<storage, read> lu_blocks: array<lu_t>;
 
 // Block boundaries: length == num_blocks + 1.
 @group(0) @binding(2) var<storage, read> block_starts: array<u32>;
@@ -57,8 +71,11 @@ struct Params {
 // Output vector z (length n).
 @group(0) @binding(4) var<storage, read_write> z: array<f32>;
 
-const BLOCK_SIZE: u32 = 6u;
-const LU_STRIDE: u32 = 36u; // 6*6
+// Active-block mask: length == num_blocks, 0 = skip (z_block = 0).
+@group(0) @binding(5) var<storage, read> block_mask: array<u32>;
+
+// Size of the per-invocation temporaries; must match `block_jacobi_exec::MAX_BLOCK_SIZE`.
+const MAX_BLOCK_SIZE: u32 = 32u;
 
 @compute @workgroup_size(1)
 fn compute_main(@builtin(workgroup_id) wg_id: vec3<u32>) {
@@ -75,26 +92,29 @@ fn compute_main(@builtin(workgroup_id) wg_id: vec3<u32>) {
         return;
     }
 
-    // Effective block size (<= 6).
-    let m: u32 = min(BLOCK_SIZE, next - offset);
+    // Effective block size (<= bs).
+    let bs: u32 = min(params.block_size, MAX_BLOCK_SIZE);
+    let m: u32 = min(bs, next - offset);
 
-    // Base index into lu_blocks for this block.
-    let base: u32 = block_id * LU_STRIDE;
+    if (block_mask[block_id] == 0u) {
+        for (var i: u32 = 0u; i < m; i = i + 1u) {
+            z[offset + i] = 0.0;
+        }
+        return;
+    }
 
-    // Fixed-size temporaries (only [0..m) are used).
-    var y: array<f32, 6>;
-    var x: array<f32, 6>;
+    // Base index into lu_blocks for this block.
+    let base: u32 = block_id * params.lu_stride;
 
-    for (var i: u32 = 0u; i < 6u; i = i + 1u) {
-        y[i] = 0.0;
-        x[i] = 0.0;
-    }
+    // Fixed-size temporaries (only [0..m) are used; WGSL zero-initializes them).
+    var y: array<f32, MAX_BLOCK_SIZE>;
+    var x: array<f32, MAX_BLOCK_SIZE>;
 
     // Forward solve: L y = r_block (unit diagonal).
     for (var i: u32 = 0u; i < m; i = i + 1u) {
         var sum: f32 = r[offset + i];
         for (var j: u32 = 0u; j < i; j = j + 1u) {
-            let l_ij: f32 = lu_blocks[base + i * 6u + j];
+            let l_ij: f32 = f32(lu_blocks[base + i * bs + j]);
             sum = sum - l_ij * y[j];
         }
         y[i] = sum;
@@ -111,11 +131,11 @@ fn compute_main(@builtin(workgroup_id) wg_id: vec3<u32>) {
         var sum: f32 = y[i];
 
         for (var j: u32 = i + 1u; j < m; j = j + 1u) {
-            let u_ij: f32 = lu_blocks[base + i * 6u + j];
+            let u_ij: f32 = f32(lu_blocks[base + i * bs + j]);
             sum = sum - u_ij * x[j];
         }
 
-        let u_ii: f32 = lu_blocks[base + i * 6u + i];
+        let u_ii: f32 = f32(lu_blocks[base + i * bs + i]);
         x[i] = sum / u_ii;
 
         ii = ii - 1;
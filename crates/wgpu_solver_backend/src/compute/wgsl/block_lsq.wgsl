@@ -0,0 +1,118 @@
+// Rectangular block-Jacobi apply (GPU): a least-squares solve per block.
+//
+// Block b couples the rows [row_starts[b], row_starts[b+1]) of r (m of them) with the
+// columns [col_starts[b], col_starts[b+1]) of z (k of them), m >= k. Its block A_b (m x k)
+// is stored as a thin QR factorization computed on the host:
+//
+//     A_b = Q R,   Q: m x k with orthonormal columns,   R: k x k upper triangular
+//
+// and the apply returns the least-squares solution of A_b z_b ≈ r_b:
+//
+//     z_b = argmin ||A_b z_b - r_b||_2 = R^{-1} Q^T r_b
+//
+// (the normal equations A^T A z = A^T r reduce to R z = Q^T r because Q^T Q = I).
+// For m == k this is the exact solve A_b^{-1} r_b, so square blocks can share the path.
+//
+// Work mapping:
+//   - 1 workgroup per block (workgroup_size = 1), workgroup_id.x == block_id
+//
+// Data layout contract (CPU ↔ GPU, see `build_qr_blocks_from_csr_6`):
+//   - blocks are params.qr_stride floats apart (72)
+//   - floats [0, 36): Q as a 6x6 row-major slab, Q(i,j) at i * 6 + j for i < m, j < k
+//   - floats [36, 72): R as a 6x6 row-major slab, upper triangle of the leading k x k
+//
+// Active-block mask: as in block_jacobi.wgsl, block_mask[block_id] == 0 skips the
+// solve and writes z_b = 0 (the least-squares solution for r_b = 0).
+//
+// IMPORTANT:
+//   - R(j,j) must be non-zero (A_b of full column rank); the host builder rejects
+//     rank-deficient blocks.
+//
+// Bindings (group 0):
+//   binding(0): uniform Params { n_rows, num_blocks, qr_stride, n_cols }
+//   binding(1): qr_blocks  (f32) read-only storage
+//   binding(2): row_starts (u32) read-only storage, num_blocks + 1 entries
+//   binding(3): col_starts (u32) read-only storage, num_blocks + 1 entries
+//   binding(4): r (f32, n_rows) read-only storage
+//   binding(5): z (f32, n_cols) read-write storage
+//   binding(6): block_mask (u32, num_blocks) read-only storage
+
+struct Params {
+    n_rows: u32,
+    num_blocks: u32,
+    qr_stride: u32,
+    n_cols: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> qr_blocks: array<f32>;
+@group(0) @binding(2) var<storage, read> row_starts: array<u32>;
+@group(0) @binding(3) var<storage, read> col_starts: array<u32>;
+@group(0) @binding(4) var<storage, read> r: array<f32>;
+@group(0) @binding(5) var<storage, read_write> z: array<f32>;
+@group(0) @binding(6) var<storage, read> block_mask: array<u32>;
+
+const BLOCK_SIZE: u32 = 6u;
+
+@compute @workgroup_size(1)
+fn compute_main(@builtin(workgroup_id) wg_id: vec3<u32>) {
+    let block_id: u32 = wg_id.x;
+    if (block_id >= params.num_blocks) {
+        return;
+    }
+
+    let row0: u32 = row_starts[block_id];
+    let col0: u32 = col_starts[block_id];
+
+    // Defensive checks against malformed starts.
+    if (row0 >= params.n_rows || col0 >= params.n_cols) {
+        return;
+    }
+
+    let m: u32 = min(BLOCK_SIZE, row_starts[block_id + 1u] - row0);
+    let k: u32 = min(m, col_starts[block_id + 1u] - col0);
+
+    if (block_mask[block_id] == 0u) {
+        for (var j: u32 = 0u; j < k; j = j + 1u) {
+            z[col0 + j] = 0.0;
+        }
+        return;
+    }
+
+    let q_base: u32 = block_id * params.qr_stride;
+    let r_base: u32 = q_base + BLOCK_SIZE * BLOCK_SIZE;
+
+    // y = Q^T r_b
+    var y: array<f32, 6>;
+    for (var j: u32 = 0u; j < 6u; j = j + 1u) {
+        y[j] = 0.0;
+    }
+    for (var i: u32 = 0u; i < m; i = i + 1u) {
+        let r_i: f32 = r[row0 + i];
+        for (var j: u32 = 0u; j < k; j = j + 1u) {
+            y[j] = y[j] + qr_blocks[q_base + i * 6u + j] * r_i;
+        }
+    }
+
+    // Backward solve: R x = y.
+    var x: array<f32, 6>;
+    var jj: i32 = i32(k) - 1;
+    loop {
+        if (jj < 0) {
+            break;
+        }
+
+        let j: u32 = u32(jj);
+        var sum: f32 = y[j];
+        for (var l: u32 = j + 1u; l < k; l = l + 1u) {
+            sum = sum - qr_blocks[r_base + j * 6u + l] * x[l];
+        }
+        x[j] = sum / qr_blocks[r_base + j * 6u + j];
+
+        jj = jj - 1;
+    }
+
+    for (var j: u32 = 0u; j < k; j = j + 1u) {
+        z[col0 + j] = x[j];
+    }
+}
@@ -0,0 +1,94 @@
+// Block LU factorization (GPU), the setup counterpart of block_jacobi.wgsl:
+//
+// Every block's dense bs x bs slab in lu_blocks (bs = params.block_size) holds the
+// (unfactored) diagonal block A_bb on entry and its in-place LU factors on exit, in exactly
+// the storage contract the apply kernel expects (see block_jacobi.wgsl and `lu_factor_inplace` on the host):
+//   - strict lower triangle: L(i,j), unit diagonal implicit
+//   - diagonal + upper triangle: U(i,j)
+//
+// Work mapping:
+//   - one invocation per block (workgroup_size = 64), global_invocation_id.x == block_id
+//   - only the leading m x m of the slab is factored, m = min(bs, block length)
+//
+// No pivoting. A pivot is treated as zero ("singular block") when
+//   |U(k,k)| <= PIVOT_TOLERANCE * max |A_bb(i,j)|
+// i.e. relative to the block's largest entry (so an all-zero block is singular too);
+// PIVOT_TOLERANCE is an override, `BLOCK_LU_PIVOT_TOLERANCE` on the host. Such a pivot
+// stops that block, the slab is reset to the identity (the apply then passes r_b through
+// instead of producing inf/NaN) and it is reported through status:
+//   status[block_id] = 0      factored
+//   status[block_id] = k + 1  singular: tiny pivot at step k (slab reset to identity)
+//
+// Bindings (group 0):
+//   binding(0): uniform Params (same buffer as the apply kernel: n, num_blocks, lu_stride, block_size)
+//   binding(1): lu_blocks    read-write storage
+//   binding(2): block_starts read-only storage
+//   binding(3): status (u32) read-write storage, one entry per block
+
+struct Params {
+    n: u32,
+    num_blocks: u32,
+    lu_stride: u32,
+    block_size: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> lu_blocks: array<f32>;
+@group(0) @binding(2) var<storage, read> block_starts: array<u32>;
+@group(0) @binding(3) var<storage, read_write> status: array<u32>;
+
+override PIVOT_TOLERANCE: f32 = 1e-6;
+
+@compute @workgroup_size(64)
+fn compute_main(@builtin(global_invocation_id) gi_id: vec3<u32>) {
+    let block_id: u32 = gi_id.x;
+    if (block_id >= params.num_blocks) {
+        return;
+    }
+
+    let offset: u32 = block_starts[block_id];
+    let next: u32 = block_starts[block_id + 1u];
+    status[block_id] = 0u;
+    if (offset >= params.n || next <= offset) {
+        return;
+    }
+
+    let bs: u32 = params.block_size;
+    let m: u32 = min(bs, next - offset);
+    let base: u32 = block_id * params.lu_stride;
+
+    var scale: f32 = 0.0;
+    for (var i: u32 = 0u; i < m; i = i + 1u) {
+        for (var j: u32 = 0u; j < m; j = j + 1u) {
+            scale = max(scale, abs(lu_blocks[base + i * bs + j]));
+        }
+    }
+    let tiny: f32 = PIVOT_TOLERANCE * scale;
+
+    for (var k: u32 = 0u; k < m; k = k + 1u) {
+        let a_kk: f32 = lu_blocks[base + k * bs + k];
+        if (abs(a_kk) <= tiny) {
+            for (var i: u32 = 0u; i < m; i = i + 1u) {
+                for (var j: u32 = 0u; j < m; j = j + 1u) {
+                    lu_blocks[base + i * bs + j] = select(0.0, 1.0, i == j);
+                }
+            }
+            status[block_id] = k + 1u;
+            return;
+        }
+
+        // L(i,k) = A(i,k) / pivot
+        for (var i: u32 = k + 1u; i < m; i = i + 1u) {
+            lu_blocks[base + i * bs + k] = lu_blocks[base + i * bs + k] / a_kk;
+        }
+
+        // trailing update: A(i,j) -= L(i,k) * U(k,j)
+        for (var i: u32 = k + 1u; i < m; i = i + 1u) {
+            let l_ik: f32 = lu_blocks[base + i * bs + k];
+            for (var j: u32 = k + 1u; j < m; j = j + 1u) {
+                lu_blocks[base + i * bs + j] =
+                    lu_blocks[base + i * bs + j] - l_ik * lu_blocks[base + k * bs + j];
+            }
+        }
+    }
+}
@@ -0,0 +1,47 @@
+// Per-block 2-norms of a vector (GPU), over the same block partition as block_jacobi.wgsl:
+//
+//     norms[b] = || r[block_starts[b] .. block_starts[b+1]] ||_2
+//
+// Unlike the apply kernels, blocks may be of any length (no 6-row limit): each invocation
+// just walks its rows.
+//
+// Work mapping:
+//   - one invocation per block (workgroup_size = 64), global_invocation_id.x == block_id
+//
+// Sums of squares run in f32, in row order.
+//
+// Bindings (group 0):
+//   binding(0): uniform Params { n, num_blocks }
+//   binding(1): block_starts (u32) read-only storage, num_blocks + 1 entries
+//   binding(2): r (f32, n) read-only storage
+//   binding(3): norms (f32, num_blocks) read-write storage
+
+struct Params {
+    n: u32,
+    num_blocks: u32,
+    _pad0: u32,
+    _pad1: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> block_starts: array<u32>;
+@group(0) @binding(2) var<storage, read> r: array<f32>;
+@group(0) @binding(3) var<storage, read_write> norms: array<f32>;
+
+@compute @workgroup_size(64)
+fn compute_main(@builtin(global_invocation_id) gi_id: vec3<u32>) {
+    let block_id: u32 = gi_id.x;
+    if (block_id >= params.num_blocks) {
+        return;
+    }
+
+    // Defensive clamp against malformed starts.
+    let start: u32 = min(block_starts[block_id], params.n);
+    let end: u32 = min(block_starts[block_id + 1u], params.n);
+
+    var sum: f32 = 0.0;
+    for (var i: u32 = start; i < end; i = i + 1u) {
+        sum = sum + r[i] * r[i];
+    }
+    norms[block_id] = sqrt(sum);
+}
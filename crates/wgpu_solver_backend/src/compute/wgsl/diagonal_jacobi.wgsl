@@ -0,0 +1,35 @@
+// Diagonal (point) Jacobi apply (GPU):
+//   z[i] = inv_diag[i] * r[i]
+//
+// The inverse diagonal 1 / A(i,i) is computed on the host and uploaded once.
+//
+// Work mapping:
+//   - workgroup_size = 256, one invocation per entry,
+//     so dispatch ceil(n / 256) workgroups in X.
+//
+// Bindings (group 0):
+//   binding(0): uniform Params { n, 0, 0, 0 }
+//   binding(1): inv_diag (f32, n) read-only storage
+//   binding(2): r        (f32, n) read-only storage
+//   binding(3): z        (f32, n) read-write storage
+
+struct Params {
+    n: u32,
+    _pad0: u32,
+    _pad1: u32,
+    _pad2: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> inv_diag: array<f32>;
+@group(0) @binding(2) var<storage, read> r: array<f32>;
+@group(0) @binding(3) var<storage, read_write> z: array<f32>;
+
+@compute @workgroup_size(256)
+fn compute_main(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let i: u32 = gid.x;
+    if (i >= params.n) {
+        return;
+    }
+    z[i] = inv_diag[i] * r[i];
+}
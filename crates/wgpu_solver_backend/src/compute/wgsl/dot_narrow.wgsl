@@ -0,0 +1,35 @@
+// Purpose:
+//   Final step of an f64-accumulated dot product: round the reduced f64 sum to the f32
+//   scalar the solver consumes,
+//
+//     output[0] = f32(input[0])
+//
+//   The host then copies output[0] into its scalar slot, exactly like the f32 path
+//   copies the reduce result directly.
+//
+//   With the TAKE_SQRT override the pass writes sqrt(max(sum, 0)) instead, ending an
+//   L2 norm (`norms::L2Norm`) on the GPU; that variant is also compiled with accum = f32.
+//
+// Dispatch convention:
+//   - @workgroup_size(1), dispatch_workgroups(1)
+//
+// Accumulator:
+//   written against `accum` like dot_partials.wgsl / dot_reduce.wgsl; compiled with
+//   `alias accum = f64;` for narrowing (the f32 path needs none), f32 only with TAKE_SQRT.
+
+alias accum = f32;
+
+@group(0) @binding(0) var<storage, read> input: array<accum>;
+@group(0) @binding(1) var<storage, read_write> output: array<f32>;
+
+override TAKE_SQRT: bool = false;
+
+@compute @workgroup_size(1)
+fn compute_main() {
+    let sum: f32 = f32(input[0]);
+    if (TAKE_SQRT) {
+        output[0] = sqrt(max(sum, 0.0));
+    } else {
+        output[0] = sum;
+    }
+}
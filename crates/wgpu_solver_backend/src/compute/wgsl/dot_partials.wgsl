@@ -6,17 +6,31 @@
 //   This kernel does NOT produce the final scalar. Instead it outputs one partial sum
 //   per workgroup into `partial[workgroup_id.x]`.
 //
-// Dispatch convention:
-//   - @workgroup_size(256)
-//   - dispatch_workgroups(groups_x) where groups_x = ceil(n / 256)
+// Dispatch convention (WG = WORKGROUP_SIZE, 256 unless overridden):
+//   - @workgroup_size(WG)
+//   - dispatch_workgroups(groups_x) where groups_x = ceil(n / WG)
 //   - global_invocation_id.x maps 1:1 to input index i
 //
 // Output:
-//   partial[k] holds the sum over indices i in [k*256, k*256+255], with bounds checking.
+//   partial[k] holds the sum over indices i in [k*WG, k*WG+WG-1], with bounds checking.
+//
+// Interleaved input (params.interleaved != 0):
+//   both vectors come from the one buffer `a`, stored pairwise
+//
+//     a = [x0, y0, x1, y1, ..., x_{n-1}, y_{n-1}]   (2n floats)
+//
+//   and the kernel sums x_i * y_i = a[2i] * a[2i+1]. `b` is not read (bind `a` again).
+//   n still counts elements per vector, so the dispatch is unchanged.
+//
+// Accumulator:
+//   products and sums are formed in `accum`. The host swaps the alias line below for
+//   `alias accum = f64;` (SHADER_F64 devices) to accumulate f32 inputs in f64; `partial`
+//   then holds f64 and the reduce must use the same variant (see dot_reduce.wgsl).
+alias accum = f32;
 
 struct Params {
-    n: u32,     // length of vectors a and b
-    _pad0: u32,
+    n: u32,           // length of vectors a and b (pairs, when interleaved)
+    interleaved: u32, // 0: a[i] * b[i],  else: a[2i] * a[2i+1]
     _pad1: u32,
     _pad2: u32,
 };
@@ -29,12 +43,16 @@ struct Params {
 
 // Output partial sums:
 //   partial[wg_id.x] = sum over this workgroup's chunk
-@group(0) @binding(3) var<storage, read_write> partial: array<f32>;
+@group(0) @binding(3) var<storage, read_write> partial: array<accum>;
+
+// Threads per workgroup (= elements per partial). Pipeline-overridable; must be a
+// power of two (tree reduce below) and fit the device's workgroup limits.
+override WORKGROUP_SIZE: u32 = 256u;
 
 // Workgroup shared memory for reduction. One element per thread.
-var<workgroup> shared_memory: array<f32, 256>;
+var<workgroup> shared_memory: array<accum, WORKGROUP_SIZE>;
 
-@compute @workgroup_size(256)
+@compute @workgroup_size(WORKGROUP_SIZE)
 fn compute_main(
     @builtin(local_invocation_id) li_id: vec3<u32>,
     @builtin(global_invocation_id) gi_id: vec3<u32>,
@@ -45,16 +63,20 @@ fn compute_main(
 
     // 1) Load product into shared memory with bounds check.
     //    Threads with i >= n contribute 0.0.
-    var v: f32 = 0.0;
+    var v: accum = 0.0;
     if (i < params.n) {
-        v = a[i] * b[i];
+        if (params.interleaved != 0u) {
+            v = accum(a[2u * i]) * accum(a[2u * i + 1u]);
+        } else {
+            v = accum(a[i]) * accum(b[i]);
+        }
     }
     shared_memory[thread_id] = v;
     workgroupBarrier();
 
-    // 2) Reduce shared_memory[0..255] to shared_memory[0] via tree reduction.
+    // 2) Reduce shared_memory[0..WG-1] to shared_memory[0] via tree reduction.
     //    After each step, barrier ensures writes are visible to other threads.
-    var offset: u32 = 128u;
+    var offset: u32 = WORKGROUP_SIZE / 2u;
     loop {
         if (thread_id < offset) {
             shared_memory[thread_id] =
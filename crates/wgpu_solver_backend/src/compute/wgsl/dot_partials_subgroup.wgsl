@@ -0,0 +1,88 @@
+// Purpose:
+//   Same partial sums as dot_partials.wgsl (one per workgroup, same bindings and
+//   dispatch), reduced inside the workgroup with subgroup adds instead of the
+//   shared-memory tree:
+//
+//     1) every thread forms its product (0 past n)
+//     2) subgroupAdd sums each subgroup; its lane 0 stores that sum in
+//        subgroup_sums[thread_id / SUBGROUP_SIZE]
+//     3) thread 0 adds up the WG / SUBGROUP_SIZE subgroup sums
+//
+// Assumed subgroup size:
+//   SUBGROUP_SIZE is NOT read from the hardware: the host passes the size it assumes
+//   (config, environment or the adapter's report; see `resolve_dot_subgroup_size`).
+//   Only the slot layout depends on it:
+//     - actual size == SUBGROUP_SIZE: one slot per subgroup (the normal case)
+//     - actual size >  SUBGROUP_SIZE: subgroups leave every other slot empty (zeroed
+//       below); the sum is still correct
+//     - actual size <  SUBGROUP_SIZE: several subgroups write the same slot and sums
+//       are lost. This is what a driver over-reporting its subgroup size breaks; a
+//       smaller override (or 0 = tree reduce on the host) works around it.
+//
+// Interleaved input: as in dot_partials.wgsl (params.interleaved != 0 reads a[2i] * a[2i+1]).
+//
+// Requirements (checked on the host):
+//   - SUBGROUP feature
+//   - SUBGROUP_SIZE a power of two <= WORKGROUP_SIZE
+
+struct Params {
+    n: u32,           // length of vectors a and b (pairs, when interleaved)
+    interleaved: u32, // 0: a[i] * b[i],  else: a[2i] * a[2i+1]
+    _pad1: u32,
+    _pad2: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> a: array<f32>;
+@group(0) @binding(2) var<storage, read> b: array<f32>;
+@group(0) @binding(3) var<storage, read_write> partial: array<f32>;
+
+// Threads per workgroup (= elements per partial). Pipeline-overridable.
+override WORKGROUP_SIZE: u32 = 256u;
+
+// Subgroup size the slot layout assumes. Pipeline-overridable (host always sets it).
+override SUBGROUP_SIZE: u32 = 32u;
+
+// One slot per (assumed) subgroup; WORKGROUP_SIZE bounds any SUBGROUP_SIZE >= 1.
+var<workgroup> subgroup_sums: array<f32, WORKGROUP_SIZE>;
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn compute_main(
+    @builtin(local_invocation_id) li_id: vec3<u32>,
+    @builtin(global_invocation_id) gi_id: vec3<u32>,
+    @builtin(workgroup_id) wg_id: vec3<u32>,
+    @builtin(subgroup_invocation_id) lane: u32,
+) {
+    let thread_id: u32 = li_id.x;
+    let i: u32 = gi_id.x;
+    let num_slots: u32 = WORKGROUP_SIZE / SUBGROUP_SIZE;
+
+    // Empty slots must read as 0 (see "actual size > SUBGROUP_SIZE" above).
+    if (thread_id < num_slots) {
+        subgroup_sums[thread_id] = 0.0;
+    }
+    workgroupBarrier();
+
+    var v: f32 = 0.0;
+    if (i < params.n) {
+        if (params.interleaved != 0u) {
+            v = a[2u * i] * a[2u * i + 1u];
+        } else {
+            v = a[i] * b[i];
+        }
+    }
+
+    let subgroup_sum: f32 = subgroupAdd(v);
+    if (lane == 0u) {
+        subgroup_sums[thread_id / SUBGROUP_SIZE] = subgroup_sum;
+    }
+    workgroupBarrier();
+
+    if (thread_id == 0u) {
+        var sum: f32 = 0.0;
+        for (var s: u32 = 0u; s < num_slots; s = s + 1u) {
+            sum = sum + subgroup_sums[s];
+        }
+        partial[wg_id.x] = sum;
+    }
+}
@@ -1,19 +1,34 @@
 // Purpose:
-//   Reduce an input array into a smaller output array by summing chunks of 256.
+//   Reduce an input array into a smaller output array by summing chunks of WG
+//   (WG = WORKGROUP_SIZE, 256 unless overridden), or taking their maximum when the
+//   REDUCE_MAX override is set.
 //
 //   This is used as a generic "reduce-by-sum" step, typically after dot_partials,
-//   and may be invoked repeatedly until only one element remains.
+//   and may be invoked repeatedly until only one element remains. The max variant
+//   folds the per-element values of custom stopping metrics.
 //
 // Dispatch convention:
-//   - @workgroup_size(256)
-//   - dispatch_workgroups(out_len) where out_len = ceil(n / 256)
+//   - @workgroup_size(WG)
+//   - dispatch_workgroups(out_len) where out_len <= ceil(n / WG)
 //
 // Mapping:
-//   - workgroup k handles input indices [k*256, k*256+255]
-//   - output[k] = sum of those inputs (bounds checked)
+//   - workgroup k handles input indices [k*WG, k*WG+WG-1], then strides by
+//     num_workgroups*WG until it runs past n (grid-stride load)
+//   - output[k] = sum (or max) of those inputs (bounds checked; a workgroup with
+//     no inputs writes 0 for sum and -F32_MAX for max)
+//
+//   With out_len = ceil(n / WG) every thread loads at most one element, which is
+//   the classic "one tree level per pass" reduce. Dispatching fewer workgroups lets
+//   a single pass fold an arbitrarily long input into out_len partials.
 //
 // Output:
-//   output length must be >= ceil(n / 256)
+//   output length must be >= out_len
+//
+// Accumulator:
+//   input, output and the sums are `accum`; the host swaps the alias line below for
+//   `alias accum = f64;` to reduce f64 partials (see dot_partials.wgsl). Only the sum is
+//   used that way; dot_narrow.wgsl turns the final f64 back into an f32 scalar.
+alias accum = f32;
 
 struct Params {
     n: u32,     // number of valid elements in `input` for THIS reduction pass
@@ -25,39 +40,68 @@ struct Params {
 @group(0) @binding(0) var<uniform> params: Params;
 
 // Input array (length >= params.n)
-@group(0) @binding(1) var<storage, read> input: array<f32>;
+@group(0) @binding(1) var<storage, read> input: array<accum>;
+
+// Output reduced array (length >= ceil(params.n / WG))
+@group(0) @binding(2) var<storage, read_write> output: array<accum>;
+
+// Threads per workgroup (= inputs folded per output). Pipeline-overridable; must be
+// a power of two (tree reduce below) and fit the device's workgroup limits.
+override WORKGROUP_SIZE: u32 = 256u;
 
-// Output reduced array (length >= ceil(params.n / 256))
-@group(0) @binding(2) var<storage, read_write> output: array<f32>;
+// Fold with max instead of +. Pipeline-overridable (see dot_reduce.rs ReduceOp).
+override REDUCE_MAX: bool = false;
+
+// Largest finite f32: identity of max over finite inputs.
+const F32_MAX: f32 = 3.40282347e38;
+
+fn combine(a: accum, b: accum) -> accum {
+    if (REDUCE_MAX) {
+        return max(a, b);
+    }
+    return a + b;
+}
 
 // Shared memory reduction scratch.
-var<workgroup> shared_memory: array<f32, 256>;
+var<workgroup> shared_memory: array<accum, WORKGROUP_SIZE>;
 
-@compute @workgroup_size(256)
+@compute @workgroup_size(WORKGROUP_SIZE)
 fn compute_main(
     @builtin(local_invocation_id) li_id: vec3<u32>,
     @builtin(workgroup_id) wg_id: vec3<u32>,
+    @builtin(num_workgroups) num_wg: vec3<u32>,
 ) {
     let thread_id: u32 = li_id.x;
 
     // This workgroup's base index into the input.
-    let base: u32 = wg_id.x * 256u;
+    let base: u32 = wg_id.x * WORKGROUP_SIZE;
     let idx: u32 = base + thread_id;
 
-    // 1) Load input[idx] into shared memory, bounds checked.
-    var v: f32 = 0.0;
-    if (idx < params.n) {
-        v = input[idx];
+    // Distance between consecutive loads of the same thread.
+    let stride: u32 = num_wg.x * WORKGROUP_SIZE;
+
+    // 1) Accumulate input[idx], input[idx + stride], ... into shared memory.
+    var v: accum = 0.0;
+    if (REDUCE_MAX) {
+        v = accum(-F32_MAX);
+    }
+    var i: u32 = idx;
+    loop {
+        if (i >= params.n) {
+            break;
+        }
+        v = combine(v, input[i]);
+        i = i + stride;
     }
     shared_memory[thread_id] = v;
     workgroupBarrier();
 
-    // 2) Reduce shared_memory[0..255] to shared_memory[0].
-    var offset: u32 = 128u;
+    // 2) Reduce shared_memory[0..WG-1] to shared_memory[0].
+    var offset: u32 = WORKGROUP_SIZE / 2u;
     loop {
         if (thread_id < offset) {
             shared_memory[thread_id] =
-                shared_memory[thread_id] + shared_memory[thread_id + offset];
+                combine(shared_memory[thread_id], shared_memory[thread_id + offset]);
         }
         workgroupBarrier();
 
@@ -67,7 +111,7 @@ fn compute_main(
         offset = offset / 2u;
     }
 
-    // 3) Thread 0 writes the reduced sum for this workgroup.
+    // 3) Thread 0 writes the reduced value for this workgroup.
     if (thread_id == 0u) {
         output[wg_id.x] = shared_memory[0];
     }
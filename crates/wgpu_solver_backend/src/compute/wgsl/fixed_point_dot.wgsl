@@ -0,0 +1,79 @@
+// Fixed-point dot product (GPU), order-independent by construction:
+//   q[i]   = round(a[i] * b[i] * scale)   (f32 product, then to i32, ties to even)
+//   accum  = sum_i q[i]                   (exact, as a 64-bit two's complement integer)
+//
+// Integer addition is associative, so the accumulator ends up with the same bits no
+// matter how workgroups are scheduled or in which order the atomics land.
+//
+// The 64-bit sum is kept as two u32 words (lo, hi). Adding a sign-extended i32 into
+// them with atomics: atomicAdd on lo, and carry into hi when lo wrapped around. The
+// number of wraps only depends on the total, so this is exact under any interleaving.
+//
+// Products whose scaled value does not fit in i32 are clamped to the i32 range and
+// counted in accum[2] (the host reports them as an error).
+//
+// Work mapping:
+//   - workgroup_size = 256, one invocation per entry,
+//     so dispatch ceil(n / 256) workgroups in X.
+//   - each workgroup first sums into workgroup atomics, then invocation 0 adds the
+//     workgroup total into the global accumulator.
+//
+// Bindings (group 0):
+//   binding(0): uniform Params { n, scale (f32 bits), 0, 0 }
+//   binding(1): a     (f32, n) read-only storage
+//   binding(2): b     (f32, n) read-only storage
+//   binding(3): accum (u32, 4) read-write storage: [lo, hi, saturated, unused],
+//               zeroed by the host before the dispatch
+
+struct Params {
+    n: u32,
+    scale: f32,
+    _pad0: u32,
+    _pad1: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> a: array<f32>;
+@group(0) @binding(2) var<storage, read> b: array<f32>;
+@group(0) @binding(3) var<storage, read_write> accum: array<atomic<u32>>;
+
+var<workgroup> wg_lo: atomic<u32>;
+var<workgroup> wg_hi: atomic<u32>;
+var<workgroup> wg_saturated: atomic<u32>;
+
+// Largest f32 below 2^31; everything in [-I32_LIMIT, I32_LIMIT] converts exactly.
+const I32_LIMIT: f32 = 2147483520.0;
+
+@compute @workgroup_size(256)
+fn compute_main(
+    @builtin(global_invocation_id) gid: vec3<u32>,
+    @builtin(local_invocation_index) lid: u32,
+) {
+    let i: u32 = gid.x;
+
+    if (i < params.n) {
+        let scaled = round(a[i] * b[i] * params.scale);
+        // Written so that NaN is counted too, on implementations that keep NaNs.
+        if (!(abs(scaled) <= I32_LIMIT)) {
+            atomicAdd(&wg_saturated, 1u);
+        }
+        let q = i32(clamp(scaled, -I32_LIMIT, I32_LIMIT));
+
+        let lo = bitcast<u32>(q);
+        let hi = select(0u, 0xffffffffu, q < 0);
+        let old = atomicAdd(&wg_lo, lo);
+        let carry = select(0u, 1u, old + lo < old);
+        atomicAdd(&wg_hi, hi + carry);
+    }
+
+    workgroupBarrier();
+
+    if (lid == 0u) {
+        let lo = atomicLoad(&wg_lo);
+        let hi = atomicLoad(&wg_hi);
+        let old = atomicAdd(&accum[0], lo);
+        let carry = select(0u, 1u, old + lo < old);
+        atomicAdd(&accum[1], hi + carry);
+        atomicAdd(&accum[2], atomicLoad(&wg_saturated));
+    }
+}
@@ -0,0 +1,37 @@
+// Custom stopping metric: per-element change of x since the previous iteration.
+//   value[i] = |x[i] - state[i]|, then state[i] = x[i]
+//
+// Folded with ReduceOp::Max this gives max_i |x_k[i] - x_{k-1}[i]|.
+//
+// Bindings (group 0), the CustomKernel convention (see custom_metric.rs):
+//   binding(0): uniform Params (n)
+//   binding(1): x      read-only storage buffer (current iterate)
+//   binding(2): state  read-write storage buffer (starts as x0; here: previous x)
+//   binding(3): value  read-write storage buffer (one value per element)
+//
+// Workgroup size is 256; each invocation handles one element.
+
+struct Params {
+    n: u32,
+    _pad0: u32,
+    _pad1: u32,
+    _pad2: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> x: array<f32>;
+@group(0) @binding(2) var<storage, read_write> state: array<f32>;
+@group(0) @binding(3) var<storage, read_write> value: array<f32>;
+
+@compute @workgroup_size(256)
+fn compute_main(@builtin(global_invocation_id) gi_id: vec3<u32>) {
+    let i = gi_id.x;
+
+    // Guard against extra threads in the last workgroup.
+    if (i >= params.n) {
+        return;
+    }
+
+    value[i] = abs(x[i] - state[i]);
+    state[i] = x[i];
+}
@@ -0,0 +1,48 @@
+// NORMALIZE kernel (squared norm read from scalar_results buffer):
+//   x[i] = x[i] / sqrt(scalar_results[scalar_index])
+//
+// The slot holds ||x||^2 exactly as the dot reduce leaves it (dot(x, x)), so the
+// square root is taken here and normalization never leaves the GPU.
+//
+// A zero norm (or anything <= 0) produces an all-zero vector instead of NaN/Inf.
+//
+// Bindings (group 0):
+//   binding(0): uniform Params
+//   binding(1): x              read-write storage buffer
+//   binding(2): scalar_results read-only storage buffer
+//
+// Uniform layout (16 bytes):
+//   u32 n            @ offset 0
+//   u32 scalar_index @ offset 4
+//   u32 _pad0        @ offset 8
+//   u32 _pad1        @ offset 12
+
+struct Params {
+    n: u32,
+    scalar_index: u32,
+    _pad0: u32,
+    _pad1: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> x: array<f32>;
+@group(0) @binding(2) var<storage, read> scalar_results: array<f32>;
+
+@compute @workgroup_size(256)
+fn compute_main(@builtin(global_invocation_id) gi_id: vec3<u32>) {
+    let i = gi_id.x;
+
+    // Guard against extra threads in the last workgroup.
+    if (i >= params.n) {
+        return;
+    }
+
+    let norm2: f32 = scalar_results[params.scalar_index];
+
+    var inv_norm: f32 = 0.0;
+    if (norm2 > 0.0) {
+        inv_norm = inverseSqrt(norm2);
+    }
+
+    x[i] = x[i] * inv_norm;
+}
@@ -0,0 +1,102 @@
+// Precision benchmark kernels (see precision_bench.rs), written once for a generic
+// `scalar` type. The host swaps the alias line below for `alias scalar = f64;` when the
+// device has SHADER_F64, so both variants run exactly the same code.
+alias scalar = f32;
+
+// Entry points (WG = 256, one element / row per invocation, ceil(n / WG) workgroups):
+//   dot_partials: partial[wg] = sum over the workgroup's chunk of x[i] * w[i]
+//   spmv:         y = A x (CSR)
+//   axpy:         y = y + coeffs[params.coeff_index] * x
+//   xpay:         y = x + coeffs[params.coeff_index] * y
+//
+// Pipelines use the automatic layout, so every entry point only needs the bindings it
+// actually touches. The dot reads its second operand through the read-only `w` so that
+// dot(r, r) can bind the same buffer twice.
+
+struct Params {
+    n: u32,           // vector length / number of rows
+    coeff_index: u32, // axpy / xpay: which coeffs[] entry to use
+    _pad0: u32,
+    _pad1: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> x: array<scalar>;
+@group(0) @binding(2) var<storage, read_write> y: array<scalar>;
+@group(0) @binding(3) var<storage, read_write> partial: array<scalar>;
+@group(0) @binding(4) var<storage, read> coeffs: array<scalar>;
+@group(0) @binding(5) var<storage, read> row_ptr: array<u32>;
+@group(0) @binding(6) var<storage, read> col_idx: array<u32>;
+@group(0) @binding(7) var<storage, read> values: array<scalar>;
+@group(0) @binding(8) var<storage, read> w: array<scalar>;
+
+const WORKGROUP_SIZE: u32 = 256u;
+
+var<workgroup> shared_memory: array<scalar, WORKGROUP_SIZE>;
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn dot_partials(
+    @builtin(local_invocation_id) li_id: vec3<u32>,
+    @builtin(global_invocation_id) gi_id: vec3<u32>,
+    @builtin(workgroup_id) wg_id: vec3<u32>,
+) {
+    let thread_id = li_id.x;
+    let i = gi_id.x;
+
+    var v = scalar(0.0);
+    if (i < params.n) {
+        v = x[i] * w[i];
+    }
+    shared_memory[thread_id] = v;
+    workgroupBarrier();
+
+    var offset = WORKGROUP_SIZE / 2u;
+    loop {
+        if (thread_id < offset) {
+            shared_memory[thread_id] =
+                shared_memory[thread_id] + shared_memory[thread_id + offset];
+        }
+        workgroupBarrier();
+
+        if (offset == 1u) {
+            break;
+        }
+        offset = offset / 2u;
+    }
+
+    if (thread_id == 0u) {
+        partial[wg_id.x] = shared_memory[0];
+    }
+}
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn spmv(@builtin(global_invocation_id) gi_id: vec3<u32>) {
+    let row = gi_id.x;
+    if (row >= params.n) {
+        return;
+    }
+
+    var sum = scalar(0.0);
+    for (var k = row_ptr[row]; k < row_ptr[row + 1u]; k = k + 1u) {
+        sum = sum + values[k] * x[col_idx[k]];
+    }
+    y[row] = sum;
+}
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn axpy(@builtin(global_invocation_id) gi_id: vec3<u32>) {
+    let i = gi_id.x;
+    if (i >= params.n) {
+        return;
+    }
+    y[i] = y[i] + coeffs[params.coeff_index] * x[i];
+}
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn xpay(@builtin(global_invocation_id) gi_id: vec3<u32>) {
+    let i = gi_id.x;
+    if (i >= params.n) {
+        return;
+    }
+    y[i] = x[i] + coeffs[params.coeff_index] * y[i];
+}
@@ -0,0 +1,38 @@
+// SCALE kernel (immediate scalar), in place:
+//   x[i] = alpha * x[i],   i < n
+//
+// Bindings (group 0):
+//   binding(0): uniform Params  (same 32-byte layout as axpy.wgsl: n, _, _, _, alpha)
+//   binding(1): x  read-write storage buffer
+//
+// Work mapping:
+// - Workgroup size is 256 and coverage is grid-strided: invocation g handles
+//   g, g + stride, g + 2 * stride, ... with stride = 256 * num_workgroups.x, so any n is
+//   covered even when ceil(n / 256) exceeds the per-dimension dispatch limit and the
+//   host clamps the workgroup count.
+
+struct Params {
+    n: u32,
+    _pad0: u32,
+    _pad1: u32,
+    _pad2: u32,
+
+    alpha: f32,
+    _pad3: u32,
+    _pad4: u32,
+    _pad5: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> x: array<f32>;
+
+@compute @workgroup_size(256)
+fn compute_main(
+    @builtin(global_invocation_id) gi_id: vec3<u32>,
+    @builtin(num_workgroups) num_wg: vec3<u32>,
+) {
+    let stride = num_wg.x * 256u;
+    for (var i = gi_id.x; i < params.n; i = i + stride) {
+        x[i] = params.alpha * x[i];
+    }
+}
@@ -11,6 +11,9 @@
 //   u32 scalar_index @ offset 4
 //   u32 _pad0        @ offset 8
 //   u32 _pad1        @ offset 12
+//
+// Coverage is grid-strided like scale.wgsl (stride = 256 * num_workgroups.x), so the
+// host may clamp the workgroup count to the dispatch limit.
 
 struct Params {
     n: u32,
@@ -24,15 +27,14 @@ struct Params {
 @group(0) @binding(2) var<storage, read> scalar_results: array<f32>;
 
 @compute @workgroup_size(256)
-fn compute_main(@builtin(global_invocation_id) gi_id: vec3<u32>) {
-    let i = gi_id.x;
-
-    // Guard against extra threads in the last workgroup.
-    if (i >= params.n) {
-        return;
-    }
-
+fn compute_main(
+    @builtin(global_invocation_id) gi_id: vec3<u32>,
+    @builtin(num_workgroups) num_wg: vec3<u32>,
+) {
     let beta: f32 = scalar_results[params.scalar_index];
 
-    x[i] = x[i] * beta;
+    let stride = num_wg.x * 256u;
+    for (var i = gi_id.x; i < params.n; i = i + stride) {
+        x[i] = x[i] * beta;
+    }
 }
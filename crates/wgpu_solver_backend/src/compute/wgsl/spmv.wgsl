@@ -6,7 +6,8 @@
 //   col_idx: length = nnz
 //   values : length = nnz
 //
-// Each invocation computes one output row i:
+// Each invocation computes output rows i = gid, gid + stride, gid + 2 * stride, ...
+// (stride = total invocations of the dispatch), one at a time:
 //
 //   start = row_ptr[i]
 //   end   = row_ptr[i + 1]
@@ -14,7 +15,7 @@
 //   y[i]  = sum
 //
 // Bindings (group 0):
-//   binding(0): uniform Params { n_rows }
+//   binding(0): uniform Params { n_rows, nnz }
 //   binding(1): row_ptr  (u32) read-only storage
 //   binding(2): col_idx  (u32) read-only storage
 //   binding(3): values   (f32) read-only storage
@@ -24,11 +25,16 @@
 // Notes:
 // - This is the straightforward "one thread per row" CSR SpMV.
 // - Performance depends heavily on row length distribution.
-// - Workgroup size is 256; global_invocation_id.x selects the row.
+// - Workgroup size is 256; global_invocation_id.x selects the first row. The host
+//   dispatches ceil(n_rows / 256) workgroups, capped at the device's per-dimension
+//   limit; beyond it the grid-stride loop covers the remaining rows, so the per-row
+//   sums (and results) are the same either way.
+// - Empty rows (start == end) skip the loop and write y[i] = 0.0: every row below
+//   n_rows is written on every dispatch, so no output survives from a previous call.
 
 struct Params {
     n_rows: u32,
-    _pad0: u32,
+    nnz: u32,
     _pad1: u32,
     _pad2: u32,
 };
@@ -41,24 +47,25 @@ struct Params {
 @group(0) @binding(5) var<storage, read_write> y: array<f32>;
 
 @compute @workgroup_size(256)
-fn compute_main(@builtin(global_invocation_id) gi_id: vec3<u32>) {
-    let i = gi_id.x;
+fn compute_main(
+    @builtin(global_invocation_id) gi_id: vec3<u32>,
+    @builtin(num_workgroups) num_wg: vec3<u32>,
+) {
+    let stride = num_wg.x * 256u;
 
-    // Guard against extra threads in the last workgroup.
-    if (i >= params.n_rows) {
-        return;
-    }
+    // Extra threads in the last workgroup skip the loop.
+    for (var i = gi_id.x; i < params.n_rows; i = i + stride) {
+        let start = row_ptr[i];
+        let end = row_ptr[i + 1u];
 
-    let start = row_ptr[i];
-    let end = row_ptr[i + 1u];
+        var sum: f32 = 0.0;
 
-    var sum: f32 = 0.0;
+        // Iterate over the non-zeros of row i.
+        for (var k = start; k < end; k = k + 1u) {
+            let j = col_idx[k];
+            sum = sum + values[k] * x[j];
+        }
 
-    // Iterate over the non-zeros of row i.
-    for (var k = start; k < end; k = k + 1u) {
-        let j = col_idx[k];
-        sum = sum + values[k] * x[j];
+        y[i] = sum;
     }
-
-    y[i] = sum;
 }
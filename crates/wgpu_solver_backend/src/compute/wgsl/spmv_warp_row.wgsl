@@ -0,0 +1,82 @@
+// CSR SpMV, one 32-lane chunk per row ("warp per row"):
+//   y = A * x
+//
+// Same CSR storage, bindings and Params as spmv.wgsl, so both kernels share one bind
+// group layout.
+//
+// Work mapping:
+// - Workgroup size is 256 = 8 chunks of 32 lanes; chunk c of workgroup g starts at row
+//   g * 8 + c and moves on by 8 * num_workgroups.x rows (grid stride, so the host may cap
+//   the workgroup count at the per-dimension limit).
+// - Lane l of a chunk accumulates the non-zeros start + l, start + l + 32, ... of its
+//   row: consecutive lanes read consecutive col_idx / values (coalesced), and a row of
+//   hundreds of non-zeros is split 32 ways instead of running on one invocation while
+//   its neighbors idle.
+// - The 32 partial sums are reduced in workgroup memory (tree, 5 steps) and lane 0
+//   writes y[row]. The chunk size is fixed rather than the device's subgroup size, so
+//   no SUBGROUP feature is needed and the summation order is the same on every device.
+//
+// Rows shorter than 32 leave lanes idle, which is what makes this slower than
+// spmv.wgsl on short-row matrices (see `SpmvStrategy::Auto`).
+//
+// Barriers sit in loops whose bounds depend only on the workgroup id and uniforms, so
+// every invocation of a workgroup reaches them the same number of times. Chunks past
+// n_rows contribute zeros and write nothing; empty rows write an exact 0.0.
+
+struct Params {
+    n_rows: u32,
+    nnz: u32,
+    _pad1: u32,
+    _pad2: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> row_ptr: array<u32>;
+@group(0) @binding(2) var<storage, read> col_idx: array<u32>;
+@group(0) @binding(3) var<storage, read> values: array<f32>;
+@group(0) @binding(4) var<storage, read> x: array<f32>;
+@group(0) @binding(5) var<storage, read_write> y: array<f32>;
+
+const LANES: u32 = 32u;
+const ROWS_PER_WORKGROUP: u32 = 8u;
+
+var<workgroup> partial: array<f32, 256>;
+
+@compute @workgroup_size(256)
+fn compute_main(
+    @builtin(local_invocation_id) lid: vec3<u32>,
+    @builtin(workgroup_id) wg_id: vec3<u32>,
+    @builtin(num_workgroups) num_wg: vec3<u32>,
+) {
+    let lane = lid.x % LANES;
+    let chunk = lid.x / LANES;
+    let stride = num_wg.x * ROWS_PER_WORKGROUP;
+
+    for (var row_base = wg_id.x * ROWS_PER_WORKGROUP; row_base < params.n_rows; row_base = row_base + stride) {
+        let row = row_base + chunk;
+
+        var sum: f32 = 0.0;
+        if (row < params.n_rows) {
+            let start = row_ptr[row];
+            let end = row_ptr[row + 1u];
+            for (var k = start + lane; k < end; k = k + LANES) {
+                sum = sum + values[k] * x[col_idx[k]];
+            }
+        }
+        partial[lid.x] = sum;
+        workgroupBarrier();
+
+        for (var offset = LANES / 2u; offset > 0u; offset = offset / 2u) {
+            if (lane < offset) {
+                partial[lid.x] = partial[lid.x] + partial[lid.x + offset];
+            }
+            workgroupBarrier();
+        }
+
+        if (lane == 0u && row < params.n_rows) {
+            y[row] = partial[lid.x];
+        }
+        // partial is rewritten by the next row group
+        workgroupBarrier();
+    }
+}
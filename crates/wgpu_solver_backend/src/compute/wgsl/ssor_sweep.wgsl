@@ -0,0 +1,75 @@
+// Multicolor SSOR sweep (CSR), one color per dispatch:
+//
+//   forward  (backward == 0):  x[row] = (scale * rhs[row] - omega * Σ_{color(col) < c} a * x[col]) * inv_diag[row]
+//   backward (backward != 0):  x[row] = x[row] - omega * inv_diag[row] * Σ_{color(col) > c} a * x[col]
+//
+// for every row of the current color c:
+//
+//   row = color_rows[params.color_start + i],   i < params.color_len
+//
+// with scale = omega * (2 - omega). Rows of one color are not coupled, so they update
+// in parallel; the color test on col picks the strictly lower (forward) or upper
+// (backward) triangle of A in the color ordering, and stored diagonal entries are
+// skipped (their sum is 1 / inv_diag). x holds the forward result y when the backward
+// sweep starts, which reads it in place: z_i = y_i - (omega / d_i) Σ_upper a_ij z_j.
+//
+// Bindings (group 0):
+//   binding(0): uniform Params, bound at a dynamic offset (one entry per dispatch in a
+//               shared buffer)
+//   binding(1): row_ptr    (u32) read-only storage
+//   binding(2): col_idx    (u32) read-only storage
+//   binding(3): values     (f32) read-only storage
+//   binding(4): color_rows (u32) read-only storage, rows grouped by color
+//   binding(5): row_color  (u32) read-only storage, color of every row
+//   binding(6): inv_diag   (f32) read-only storage
+//   binding(7): rhs        (f32) read-only storage
+//   binding(8): x          (f32) read-write storage
+//
+// Workgroup size is 256; global_invocation_id.x indexes into the color.
+
+struct Params {
+    color_start: u32,
+    color_len: u32,
+    color: u32,
+    backward: u32,
+    omega: f32,
+    scale: f32,
+    _pad0: u32,
+    _pad1: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> row_ptr: array<u32>;
+@group(0) @binding(2) var<storage, read> col_idx: array<u32>;
+@group(0) @binding(3) var<storage, read> values: array<f32>;
+@group(0) @binding(4) var<storage, read> color_rows: array<u32>;
+@group(0) @binding(5) var<storage, read> row_color: array<u32>;
+@group(0) @binding(6) var<storage, read> inv_diag: array<f32>;
+@group(0) @binding(7) var<storage, read> rhs: array<f32>;
+@group(0) @binding(8) var<storage, read_write> x: array<f32>;
+
+@compute @workgroup_size(256)
+fn compute_main(@builtin(global_invocation_id) gi_id: vec3<u32>) {
+    let i = gi_id.x;
+    if (i >= params.color_len) {
+        return;
+    }
+
+    let row = color_rows[params.color_start + i];
+    let backward = params.backward != 0u;
+
+    var sum: f32 = 0.0;
+    for (var k = row_ptr[row]; k < row_ptr[row + 1u]; k = k + 1u) {
+        let col = col_idx[k];
+        let color = row_color[col];
+        if ((!backward && color < params.color) || (backward && color > params.color)) {
+            sum = sum + values[k] * x[col];
+        }
+    }
+
+    if (backward) {
+        x[row] = x[row] - params.omega * inv_diag[row] * sum;
+    } else {
+        x[row] = (params.scale * rhs[row] - params.omega * sum) * inv_diag[row];
+    }
+}
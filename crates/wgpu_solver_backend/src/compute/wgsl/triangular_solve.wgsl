@@ -0,0 +1,69 @@
+// Level-scheduled sparse triangular solve (CSR), one level per dispatch:
+//
+//   x[row] = (rhs[row] - Σ_{k in row, col != row} values[k] * x[col_idx[k]]) / diag
+//
+// for every row of the current level:
+//
+//   row = level_rows[params.level_start + i],   i < params.level_len
+//
+// Rows in one level only depend on rows of earlier levels, which previous dispatches
+// already wrote into x; so the same kernel does the forward (L) and the backward (U)
+// sweep, only the level order differs.
+//
+// Diagonal:
+// - unit_diagonal != 0 (the L factor): diag = 1, a stored diagonal entry is skipped.
+// - unit_diagonal == 0 (the U factor): diag = the row's stored diagonal entry.
+//
+// Bindings (group 0):
+//   binding(0): uniform Params { level_start, level_len, unit_diagonal }, bound at a
+//               dynamic offset (one entry per level in a shared buffer)
+//   binding(1): row_ptr    (u32) read-only storage
+//   binding(2): col_idx    (u32) read-only storage
+//   binding(3): values     (f32) read-only storage
+//   binding(4): level_rows (u32) read-only storage, rows grouped by level
+//   binding(5): rhs        (f32) read-only storage
+//   binding(6): x          (f32) read-write storage
+//
+// Workgroup size is 256; global_invocation_id.x indexes into the level.
+
+struct Params {
+    level_start: u32,
+    level_len: u32,
+    unit_diagonal: u32,
+    _pad0: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> row_ptr: array<u32>;
+@group(0) @binding(2) var<storage, read> col_idx: array<u32>;
+@group(0) @binding(3) var<storage, read> values: array<f32>;
+@group(0) @binding(4) var<storage, read> level_rows: array<u32>;
+@group(0) @binding(5) var<storage, read> rhs: array<f32>;
+@group(0) @binding(6) var<storage, read_write> x: array<f32>;
+
+@compute @workgroup_size(256)
+fn compute_main(@builtin(global_invocation_id) gi_id: vec3<u32>) {
+    let i = gi_id.x;
+    if (i >= params.level_len) {
+        return;
+    }
+
+    let row = level_rows[params.level_start + i];
+
+    var sum: f32 = rhs[row];
+    var diag: f32 = 1.0;
+    for (var k = row_ptr[row]; k < row_ptr[row + 1u]; k = k + 1u) {
+        let col = col_idx[k];
+        if (col == row) {
+            diag = values[k];
+        } else {
+            sum = sum - values[k] * x[col];
+        }
+    }
+
+    if (params.unit_diagonal != 0u) {
+        x[row] = sum;
+    } else {
+        x[row] = sum / diag;
+    }
+}
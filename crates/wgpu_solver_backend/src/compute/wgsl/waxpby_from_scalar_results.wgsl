@@ -0,0 +1,47 @@
+// WAXPBY kernel (both scalars read from scalar_results buffer):
+//   w[i] = scalar_results[alpha_index] * x[i] + scalar_results[beta_index] * y[i]
+//
+// Out-of-place combination of two vectors in one dispatch. w must be distinct from x
+// and y (binding one buffer as both RO and RW storage is a validation error); x and y
+// may be the same buffer. For w = y use xpby_from_scalar_results.wgsl (alpha = 1).
+//
+// Bindings (group 0):
+//   binding(0): uniform Params
+//   binding(1): x              read-only storage buffer
+//   binding(2): y              read-only storage buffer
+//   binding(3): w              read-write storage buffer (fully overwritten)
+//   binding(4): scalar_results read-only storage buffer (small array of scalars)
+//
+// Uniform layout (16 bytes):
+//   u32 n           @ offset 0
+//   u32 alpha_index @ offset 4
+//   u32 beta_index  @ offset 8
+//   u32 _pad0       @ offset 12
+
+struct Params {
+    n: u32,
+    alpha_index: u32,
+    beta_index: u32,
+    _pad0: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> x: array<f32>;
+@group(0) @binding(2) var<storage, read> y: array<f32>;
+@group(0) @binding(3) var<storage, read_write> w: array<f32>;
+@group(0) @binding(4) var<storage, read> scalar_results: array<f32>;
+
+@compute @workgroup_size(256)
+fn compute_main(@builtin(global_invocation_id) gi_id: vec3<u32>) {
+    let i = gi_id.x;
+
+    // Guard against extra threads in the last workgroup.
+    if (i >= params.n) {
+        return;
+    }
+
+    let alpha: f32 = scalar_results[params.alpha_index];
+    let beta: f32 = scalar_results[params.beta_index];
+
+    w[i] = alpha * x[i] + beta * y[i];
+}
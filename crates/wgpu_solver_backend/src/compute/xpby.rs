@@ -0,0 +1,101 @@
+use wgpu::{Buffer, CommandEncoder};
+
+use crate::{compute::vec_ops_exec::VecOpsExecutor, gpu::context::GpuContext};
+
+/// Fused vector combinations on length-n GPU vectors, fixed n, one dispatch each:
+///
+/// - `encode`: y = x + beta * y, beta from `beta_gpu[0]` (xpby_from_scalar_results.wgsl),
+///   the CG direction update p = z + beta p without a separate SCALE pass.
+/// - `encode_waxpby`: w = alpha * x + beta * y into a third vector, alpha and beta from
+///   `scalars_gpu[0]` and `scalars_gpu[1]` (waxpby_from_scalar_results.wgsl).
+///
+/// The scalars stay on the GPU, so a beta a previous pass computed (e.g. by
+/// `PcgUpdateScalarsExecutor`) is used without a host roundtrip. Both record one pass of
+/// ceil(n / 256) workgroups and do NOT submit. Like `AxpyExecutor`, this is a
+/// fixed-length front end of kernels in `VecOpsExecutor`, which takes any scalar slots.
+pub struct XpbyExecutor {
+    n: u32,
+    vec_ops: VecOpsExecutor,
+}
+
+impl XpbyExecutor {
+    pub fn create(ctx: &GpuContext, n: u32) -> Self {
+        Self {
+            n,
+            vec_ops: VecOpsExecutor::create(ctx),
+        }
+    }
+
+    pub fn n(&self) -> u32 {
+        self.n
+    }
+
+    /// Encode y = x + beta_gpu[0] * y. `beta_gpu` must have STORAGE usage.
+    ///
+    /// Panics if x or y holds fewer than n f32, `beta_gpu` is empty, or x and y are the
+    /// same buffer.
+    pub fn encode(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        beta_gpu: &Buffer,
+        x_gpu: &Buffer,
+        y_gpu: &Buffer,
+    ) {
+        self.check_buffers(&[("x", x_gpu), ("y", y_gpu)]);
+        assert!(beta_gpu.size() >= 4, "XpbyExecutor: beta buffer is empty");
+        assert!(
+            x_gpu != y_gpu,
+            "XpbyExecutor: x and y must be different buffers"
+        );
+        self.vec_ops.encode_xpby_inplace_from_scalar_results(
+            ctx, encoder, x_gpu, y_gpu, self.n, beta_gpu, 0,
+        );
+    }
+
+    /// Encode w = scalars_gpu[0] * x + scalars_gpu[1] * y. `scalars_gpu` must have STORAGE
+    /// usage; x and y may be the same buffer, w is fully overwritten.
+    ///
+    /// Panics if x, y or w holds fewer than n f32, `scalars_gpu` holds fewer than two
+    /// f32, or w is x or y.
+    pub fn encode_waxpby(
+        &self,
+        ctx: &GpuContext,
+        encoder: &mut CommandEncoder,
+        scalars_gpu: &Buffer,
+        x_gpu: &Buffer,
+        y_gpu: &Buffer,
+        w_gpu: &Buffer,
+    ) {
+        self.check_buffers(&[("x", x_gpu), ("y", y_gpu), ("w", w_gpu)]);
+        assert!(
+            scalars_gpu.size() >= 8,
+            "XpbyExecutor: scalars buffer needs alpha and beta"
+        );
+        assert!(
+            w_gpu != x_gpu && w_gpu != y_gpu,
+            "XpbyExecutor: w must differ from x and y"
+        );
+        self.vec_ops.encode_waxpby_from_scalar_results(
+            ctx,
+            encoder,
+            x_gpu,
+            y_gpu,
+            w_gpu,
+            self.n,
+            scalars_gpu,
+            0,
+            1,
+        );
+    }
+
+    fn check_buffers(&self, buffers: &[(&str, &Buffer)]) {
+        for (name, buffer) in buffers {
+            assert!(
+                buffer.size() >= self.n as u64 * 4,
+                "XpbyExecutor: {name} holds fewer than n = {} f32",
+                self.n
+            );
+        }
+    }
+}
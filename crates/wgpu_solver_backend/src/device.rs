@@ -0,0 +1,334 @@
+use futures::executor;
+use wgpu::BufferUsages;
+
+use crate::{
+    compute::{
+        PCG_SCALAR_RESULTS_LEN, PcgResult, SolveConfig, block_jacobi_exec::BlockJacobiExecutor,
+        build_lu_blocks_from_csr_block_starts_6, dot_partials::DEFAULT_WORKGROUP_SIZE,
+        dot_scalar_exec::DotScalarExecutor, fixed_point_dot_exec::FixedPointDotExecutor,
+        observer::SolverObserver, pcg_block_jacobi_csr_wgpu,
+        pcg_update_scalars_exec::PcgUpdateScalarsExecutor, spmv_exec::SpmvExecutor,
+        vec_ops_exec::VecOpsExecutor,
+    },
+    gpu::context::{GpuBackend, GpuContext, GpuError},
+    matrix::Csr,
+    reference,
+};
+
+/// Where solver operations run: a real wgpu device, or the CPU pseudo-backend.
+///
+/// The CPU variant routes every operation to the `reference` kernels. It is meant for
+/// GPU-less CI runners and tiny problems, and is NOT performant (single-threaded loops).
+// One per process and never stored in bulk, so the variant size gap does not matter.
+#[allow(clippy::large_enum_variant)]
+pub enum SolverDevice {
+    Gpu(GpuContext),
+    Cpu,
+}
+
+impl SolverDevice {
+    /// Create a device for `backend`.
+    ///
+    /// - `GpuBackend::Cpu` always gives `SolverDevice::Cpu`.
+    /// - Otherwise a GPU context is created; if that fails and `cpu_fallback` is set,
+    ///   the CPU pseudo-backend is used instead (with a warning on stderr).
+    ///   Without `cpu_fallback` the GPU error is returned unchanged.
+    pub async fn create(
+        backend: GpuBackend,
+        adapter_index: Option<usize>,
+        cpu_fallback: bool,
+    ) -> Result<Self, GpuError> {
+        if backend == GpuBackend::Cpu {
+            return Ok(SolverDevice::Cpu);
+        }
+
+        match GpuContext::create_with_adapter_index(backend, adapter_index).await {
+            Ok(ctx) => Ok(SolverDevice::Gpu(ctx)),
+            Err(GpuError::CpuBackend) => Ok(SolverDevice::Cpu),
+            Err(e) if cpu_fallback => {
+                eprintln!("GPU init failed ({e}); falling back to the CPU reference backend");
+                Ok(SolverDevice::Cpu)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn is_cpu(&self) -> bool {
+        matches!(self, SolverDevice::Cpu)
+    }
+
+    /// The GPU context, if this is a GPU device.
+    pub fn gpu(&self) -> Option<&GpuContext> {
+        match self {
+            SolverDevice::Gpu(ctx) => Some(ctx),
+            SolverDevice::Cpu => None,
+        }
+    }
+
+    /// Whether the GPU device was lost (always false on the CPU).
+    pub fn is_lost(&self) -> bool {
+        self.gpu().is_some_and(GpuContext::is_lost)
+    }
+
+    /// Replace the GPU context with a fresh one on the same backend/adapter.
+    /// No-op on the CPU.
+    pub async fn recreate(&mut self) -> Result<(), GpuError> {
+        if let SolverDevice::Gpu(ctx) = self {
+            *ctx = ctx.recreate().await?;
+        }
+        Ok(())
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            SolverDevice::Gpu(ctx) => ctx.describe(),
+            SolverDevice::Cpu => "CPU reference backend".to_string(),
+        }
+    }
+
+    /// Build everything a block-Jacobi PCG solve needs and run it.
+    ///
+    /// On the GPU this creates the executors, uploads the matrix and runs
+    /// `pcg_block_jacobi_csr_wgpu`; on the CPU it runs the reference loop
+    /// (see `reference::pcg_block_jacobi_csr_cpu` for the `options` that apply there).
+    pub fn pcg_block_jacobi_csr(
+        &self,
+        a: &Csr,
+        block_starts: &[u32],
+        b: &[f32],
+        x: &mut [f32],
+        config: &SolveConfig,
+    ) -> Result<PcgResult, String> {
+        let mut results =
+            self.solve_each(a, block_starts, std::iter::once((b, x)), config, None)?;
+        Ok(results.remove(0))
+    }
+
+    /// [`SolverDevice::pcg_block_jacobi_csr`] for a problem with a known solution: also records
+    /// ||x - x_exact||_A after every iteration in `PcgResult::error_a_norm_history`.
+    ///
+    /// Meant for convergence studies; see `PcgOptions::exact_solution` for the cost.
+    pub fn pcg_block_jacobi_csr_with_exact(
+        &self,
+        a: &Csr,
+        block_starts: &[u32],
+        b: &[f32],
+        x_exact: &[f32],
+        x: &mut [f32],
+        config: &SolveConfig,
+    ) -> Result<PcgResult, String> {
+        let mut config = config.clone();
+        config.options.exact_solution = Some(x_exact.to_vec());
+        self.pcg_block_jacobi_csr(a, block_starts, b, x, &config)
+    }
+
+    /// [`SolverDevice::pcg_block_jacobi_csr`] that survives device loss (driver reset).
+    ///
+    /// When a solve fails and the device reports lost, the context is recreated, the
+    /// matrix, LU blocks and vectors are uploaded again and the solve restarts from the
+    /// last checkpoint, at most `max_device_lost_retries` times. Other errors, or a loss
+    /// once the retries are used up, are returned as-is.
+    ///
+    /// Checkpoints are the `options.snapshot_interval` readbacks of x: without snapshots
+    /// every retry restarts from the caller's x0 and repeats all iterations. Each retry
+    /// costs a device creation, pipeline compilation and full re-upload, and gets the full
+    /// `max_iters` budget again; the returned result describes the final attempt only.
+    ///
+    /// `observer` sees the iterations of every attempt (GPU only), numbered from 1 again
+    /// after each restart.
+    #[allow(clippy::too_many_arguments)]
+    pub fn pcg_block_jacobi_csr_with_retry(
+        &mut self,
+        a: &Csr,
+        block_starts: &[u32],
+        b: &[f32],
+        x: &mut [f32],
+        config: &SolveConfig,
+        max_device_lost_retries: u32,
+        mut observer: Option<&mut dyn SolverObserver>,
+    ) -> Result<PcgResult, String> {
+        let mut config = config.clone();
+        let mut retries = 0;
+
+        loop {
+            let attempt = self
+                .solve_each(
+                    a,
+                    block_starts,
+                    std::iter::once((b, &mut *x)),
+                    &config,
+                    observer
+                        .as_mut()
+                        .map(|o| &mut **o as &mut dyn SolverObserver),
+                )
+                .map(|mut results| results.remove(0));
+            match attempt {
+                Err(e) if self.is_lost() && retries < max_device_lost_retries => {
+                    retries += 1;
+                    eprintln!(
+                        "{e}; recreating the GPU context (retry {retries}/{max_device_lost_retries})"
+                    );
+                    executor::block_on(self.recreate())
+                        .map_err(|e| format!("recreate GPU context after device loss: {e}"))?;
+                    // A restart from a checkpoint has a different r0, so a supplied z0
+                    // no longer fits.
+                    config.options.initial_preconditioned_residual = None;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Solve A x_j = b_j for every column b_j of `rhs` (x0 = 0 for each).
+    ///
+    /// Setup (LU blocks, executors, matrix upload) happens once; the columns are then
+    /// solved one after the other. Returns (result, x_j) per column, in order; the
+    /// first column that fails aborts the batch with its error.
+    ///
+    /// `options.initial_preconditioned_residual` belongs to one right-hand side, so it is
+    /// rejected for more than one column.
+    pub fn pcg_block_jacobi_csr_multi_rhs(
+        &self,
+        a: &Csr,
+        block_starts: &[u32],
+        rhs: &[Vec<f32>],
+        config: &SolveConfig,
+    ) -> Result<Vec<(PcgResult, Vec<f32>)>, String> {
+        if rhs.len() > 1 && config.options.initial_preconditioned_residual.is_some() {
+            return Err(
+                "PCG: initial_preconditioned_residual cannot be shared by several right-hand sides"
+                    .into(),
+            );
+        }
+
+        let n = a.n_rows as usize;
+        let mut xs: Vec<Vec<f32>> = rhs.iter().map(|_| vec![0.0f32; n]).collect();
+
+        let systems = rhs
+            .iter()
+            .map(Vec::as_slice)
+            .zip(xs.iter_mut().map(Vec::as_mut_slice));
+        let results = self.solve_each(a, block_starts, systems, config, None)?;
+
+        Ok(results.into_iter().zip(xs).collect())
+    }
+
+    /// Fixed-point a·b (experimental, see `FixedPointDotExecutor` for the caveats).
+    ///
+    /// The result has the same bits on every GPU and on the CPU backend, which runs
+    /// `reference::fixed_point_dot`.
+    pub fn fixed_point_dot(&self, a: &[f32], b: &[f32], frac_bits: u32) -> Result<f32, String> {
+        if a.len() != b.len() {
+            return Err(format!(
+                "fixed-point dot: a has {} entries but b has {}",
+                a.len(),
+                b.len()
+            ));
+        }
+
+        match self {
+            SolverDevice::Cpu => reference::fixed_point_dot(a, b, frac_bits),
+            // Zero-sized storage bindings are invalid; the empty sum is 0 either way.
+            SolverDevice::Gpu(_) if a.is_empty() => reference::fixed_point_dot(a, b, frac_bits),
+            SolverDevice::Gpu(ctx) => {
+                let exec = FixedPointDotExecutor::create(ctx, a.len() as u32, frac_bits)?;
+                let a_gpu =
+                    ctx.create_storage_buffer("fixed_point_dot a", a, BufferUsages::empty());
+                let b_gpu =
+                    ctx.create_storage_buffer("fixed_point_dot b", b, BufferUsages::empty());
+                executor::block_on(exec.compute(ctx, &a_gpu.buffer, &b_gpu.buffer))
+            }
+        }
+    }
+
+    /// Shared setup + per-system solve loop behind the public entry points.
+    fn solve_each<'a>(
+        &self,
+        a: &Csr,
+        block_starts: &[u32],
+        systems: impl Iterator<Item = (&'a [f32], &'a mut [f32])>,
+        config: &SolveConfig,
+        mut observer: Option<&mut dyn SolverObserver>,
+    ) -> Result<Vec<PcgResult>, String> {
+        let n = a.n_rows as usize;
+        let SolveConfig {
+            max_iters: max_iter,
+            rel_tol,
+            abs_tol,
+            ref options,
+        } = *config;
+
+        let lu_blocks = build_lu_blocks_from_csr_block_starts_6(
+            n,
+            &a.row_ptr,
+            &a.col_idx,
+            &a.values,
+            block_starts,
+        )?;
+
+        match self {
+            SolverDevice::Cpu => systems
+                .map(|(b, x)| {
+                    reference::pcg_block_jacobi_csr_cpu(
+                        n,
+                        &a.row_ptr,
+                        &a.col_idx,
+                        &a.values,
+                        &lu_blocks,
+                        block_starts,
+                        b,
+                        x,
+                        max_iter,
+                        rel_tol,
+                        abs_tol,
+                        options,
+                    )
+                })
+                .collect(),
+            SolverDevice::Gpu(ctx) => {
+                let spmv_exec =
+                    SpmvExecutor::create(ctx, a.n_rows, &a.row_ptr, &a.col_idx, &a.values);
+                let vec_ops_exec = VecOpsExecutor::create(ctx);
+                let (partials_wg, reduce_wg) = options
+                    .dot_workgroup_sizes
+                    .unwrap_or((DEFAULT_WORKGROUP_SIZE, DEFAULT_WORKGROUP_SIZE));
+                let mut dot_scalar_exec = DotScalarExecutor::try_create_with_workgroup_sizes(
+                    ctx,
+                    n,
+                    PCG_SCALAR_RESULTS_LEN,
+                    partials_wg,
+                    reduce_wg,
+                )?;
+                dot_scalar_exec.set_readback_buffering(ctx, options.readback_buffering);
+                dot_scalar_exec.set_dot_precision(ctx, options.dot_precision)?;
+                dot_scalar_exec.set_dot_subgroup_size(ctx, options.dot_subgroup_size)?;
+                let block_jacobi_exec =
+                    BlockJacobiExecutor::create(ctx, a.n_rows, 6, &lu_blocks, block_starts)?;
+                let pcg_update_scalars_exec = PcgUpdateScalarsExecutor::create(ctx);
+
+                systems
+                    .map(|(b, x)| {
+                        pcg_block_jacobi_csr_wgpu(
+                            n,
+                            b,
+                            x,
+                            max_iter,
+                            rel_tol,
+                            abs_tol,
+                            ctx,
+                            &spmv_exec,
+                            &vec_ops_exec,
+                            &dot_scalar_exec,
+                            &block_jacobi_exec,
+                            &pcg_update_scalars_exec,
+                            options,
+                            observer
+                                .as_mut()
+                                .map(|o| &mut **o as &mut dyn SolverObserver),
+                        )
+                    })
+                    .collect()
+            }
+        }
+    }
+}
@@ -1,3 +1,7 @@
 pub mod context;
 pub mod buffer;
+pub mod buffer_pool;
 pub mod readback;
+pub mod timer;
+pub mod submit;
+pub mod checksum;
@@ -0,0 +1,208 @@
+use bytemuck::Pod;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::mem::size_of;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+use wgpu::{Buffer, BufferDescriptor, BufferUsages};
+
+use crate::gpu::{buffer::GpuBuffer, context::GpuContext};
+
+/// Smallest bucket, in bytes.
+pub const MIN_BUCKET_BYTES: u64 = 256;
+
+/// Usages of every pooled buffer.
+pub const POOLED_USAGES: BufferUsages = BufferUsages::STORAGE
+    .union(BufferUsages::COPY_SRC)
+    .union(BufferUsages::COPY_DST);
+
+/// Recycles `STORAGE | COPY_SRC | COPY_DST` buffers between solves.
+///
+/// Requests are rounded up to a power-of-two bucket (at least [`MIN_BUCKET_BYTES`]); a
+/// [`PooledBuffer`] goes back to its bucket's free list when dropped, and the next
+/// request for that bucket takes it instead of creating a buffer. A sweep over many
+/// systems of the same (or similar) size thus allocates its vectors once.
+///
+/// Install a pool with [`GpuContext::set_buffer_pool`] and the solver drivers
+/// (`pcg_block_jacobi_csr_wgpu`, `gmres_block_jacobi_csr_wgpu`, `ssor_cg_csr_wgpu`, and
+/// through them `PcgSolver::solve`) take their per-solve vectors from it;
+/// [`BufferPool::acquire`] uses it directly. Buffers belong to one device, so a pool serves one context.
+///
+/// All state is behind a mutex: the pool is `Send + Sync`, and handles may be dropped
+/// on any thread.
+#[derive(Debug, Default)]
+pub struct BufferPool {
+    state: Mutex<PoolState>,
+}
+
+#[derive(Debug, Default)]
+struct PoolState {
+    free: HashMap<u64, Vec<Buffer>>,
+    max_bytes_held: Option<u64>,
+    stats: BufferPoolStats,
+}
+
+/// Counters of a [`BufferPool`], see [`BufferPool::stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BufferPoolStats {
+    /// Requests served from a free list.
+    pub hits: u64,
+    /// Requests that created a buffer.
+    pub misses: u64,
+    /// Bytes in the free lists, i.e. allocated but not handed out.
+    pub bytes_held: u64,
+    /// Buffers in the free lists.
+    pub buffers_held: usize,
+    /// Bytes of the buffers currently handed out.
+    pub bytes_in_use: u64,
+    /// Released buffers dropped instead of kept, because of `with_max_bytes_held`.
+    pub evictions: u64,
+}
+
+impl BufferPool {
+    /// An empty pool without a limit on the bytes it keeps.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty pool that keeps at most `max_bytes` in its free lists; a buffer released
+    /// beyond that is dropped (counted in [`BufferPoolStats::evictions`]).
+    pub fn with_max_bytes_held(max_bytes: u64) -> Self {
+        let pool = Self::default();
+        pool.state.lock().unwrap().max_bytes_held = Some(max_bytes);
+        pool
+    }
+
+    /// The bucket (buffer size in bytes) a request of `byte_len` bytes is served from.
+    pub fn bucket_size(byte_len: u64) -> u64 {
+        byte_len.max(MIN_BUCKET_BYTES).next_power_of_two()
+    }
+
+    pub fn stats(&self) -> BufferPoolStats {
+        self.state.lock().unwrap().stats
+    }
+
+    /// Drop every free buffer, keeping handed-out ones and the hit / miss counts.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.free.clear();
+        state.stats.bytes_held = 0;
+        state.stats.buffers_held = 0;
+    }
+
+    /// A buffer of at least `len` T, labelled `label` (with the context's prefix) when it
+    /// is created here; a reused one keeps the label of its first request.
+    ///
+    /// A reused buffer holds whatever its last user wrote; only new ones are zeroed.
+    /// `len` counts T, the buffer itself is the bucket size.
+    pub fn acquire<T: Pod>(
+        self: &Arc<Self>,
+        ctx: &GpuContext,
+        label: &str,
+        len: usize,
+    ) -> PooledBuffer<T> {
+        let bucket = Self::bucket_size((len * size_of::<T>()) as u64);
+        let reused = {
+            let mut state = self.state.lock().unwrap();
+            let buffer = state.free.get_mut(&bucket).and_then(Vec::pop);
+            let stats = &mut state.stats;
+            if buffer.is_some() {
+                stats.hits += 1;
+                stats.bytes_held -= bucket;
+                stats.buffers_held -= 1;
+            } else {
+                stats.misses += 1;
+            }
+            stats.bytes_in_use += bucket;
+            buffer
+        };
+        let buffer = reused.unwrap_or_else(|| {
+            ctx.create_buffer(&BufferDescriptor {
+                label: Some(&ctx.label(label)),
+                size: bucket,
+                usage: POOLED_USAGES,
+                mapped_at_creation: false,
+            })
+        });
+
+        PooledBuffer {
+            buffer: GpuBuffer {
+                buffer,
+                len,
+                _marker: PhantomData,
+            },
+            pool: Some(Arc::clone(self)),
+        }
+    }
+
+    /// [`BufferPool::acquire`] for `data.len()` T, then `data` written through the queue
+    /// (ordered before any later submit). Panics when `data` is not a whole number of
+    /// 4-byte words, like `Queue::write_buffer`.
+    pub fn acquire_init<T: Pod>(
+        self: &Arc<Self>,
+        ctx: &GpuContext,
+        label: &str,
+        data: &[T],
+    ) -> PooledBuffer<T> {
+        let pooled = self.acquire::<T>(ctx, label, data.len());
+        if !data.is_empty() {
+            ctx.queue
+                .write_buffer(&pooled.buffer.buffer, 0, bytemuck::cast_slice(data));
+        }
+        pooled
+    }
+
+    fn release(&self, buffer: Buffer) {
+        let bucket = buffer.size();
+        let mut state = self.state.lock().unwrap();
+        state.stats.bytes_in_use -= bucket;
+        if state
+            .max_bytes_held
+            .is_some_and(|max| state.stats.bytes_held + bucket > max)
+        {
+            state.stats.evictions += 1;
+            return;
+        }
+        state.stats.bytes_held += bucket;
+        state.stats.buffers_held += 1;
+        state.free.entry(bucket).or_default().push(buffer);
+    }
+}
+
+/// A storage buffer that returns to its [`BufferPool`] when dropped; derefs to the
+/// [`GpuBuffer`] (whose `len` is the requested length, the buffer may be larger).
+///
+/// [`GpuContext::acquire_storage_buffer`] hands out unpooled ones too, when the context
+/// has no pool; those are plain buffers of the exact size, freed on drop.
+#[derive(Debug)]
+pub struct PooledBuffer<T: Pod> {
+    buffer: GpuBuffer<T>,
+    pool: Option<Arc<BufferPool>>,
+}
+
+impl<T: Pod> PooledBuffer<T> {
+    /// A buffer that is not pooled: dropping it drops `buffer`.
+    pub fn unpooled(buffer: GpuBuffer<T>) -> Self {
+        Self { buffer, pool: None }
+    }
+
+    pub fn is_pooled(&self) -> bool {
+        self.pool.is_some()
+    }
+}
+
+impl<T: Pod> Deref for PooledBuffer<T> {
+    type Target = GpuBuffer<T>;
+
+    fn deref(&self) -> &GpuBuffer<T> {
+        &self.buffer
+    }
+}
+
+impl<T: Pod> Drop for PooledBuffer<T> {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.take() {
+            pool.release(self.buffer.buffer.clone());
+        }
+    }
+}
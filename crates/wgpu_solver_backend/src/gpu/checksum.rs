@@ -0,0 +1,61 @@
+use wgpu::{Buffer, COPY_BUFFER_ALIGNMENT};
+
+use crate::gpu::context::GpuContext;
+use crate::gpu::readback::try_readback_to_vec;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a of `bytes`.
+///
+/// For checking that two runs (or machines) saw the same data, e.g. compare
+/// `fnv1a_64(bytemuck::cast_slice(&values))` on the host with [`buffer_fnv`] of the
+/// uploaded buffer. Not a cryptographic hash: trivial to collide on purpose, so never
+/// use it to authenticate anything.
+pub fn fnv1a_64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |h, &b| {
+        (h ^ b as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// FNV-1a ([`fnv1a_64`]) of the first `len` bytes of a GPU buffer, for reproducibility
+/// debugging (same matrix uploaded on both runs?). Debugging aid only, not security.
+///
+/// Reads the bytes back through a staging buffer and hashes them on the host: one submit
+/// plus a full copy of `len` bytes, so keep it out of hot loops. `buf` needs COPY_SRC
+/// usage and `len` must be a multiple of 4 (wgpu's copy alignment); panics otherwise
+/// or when the readback fails (see [`try_buffer_fnv`]).
+pub async fn buffer_fnv(ctx: &GpuContext, buf: &Buffer, len: u64) -> u64 {
+    try_buffer_fnv(ctx, buf, len)
+        .await
+        .unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Same as [`buffer_fnv`], but errors instead of panicking.
+pub async fn try_buffer_fnv(ctx: &GpuContext, buf: &Buffer, len: u64) -> Result<u64, String> {
+    if !len.is_multiple_of(COPY_BUFFER_ALIGNMENT) {
+        return Err(format!(
+            "buffer_fnv: len must be a multiple of {COPY_BUFFER_ALIGNMENT} bytes, got {len}"
+        ));
+    }
+    if len > buf.size() {
+        return Err(format!(
+            "buffer_fnv: len {len} exceeds the buffer size {}",
+            buf.size()
+        ));
+    }
+    if len == 0 {
+        return Ok(fnv1a_64(&[]));
+    }
+
+    ctx.count_buffer_created();
+    let bytes: Vec<u8> = try_readback_to_vec(
+        &ctx.device,
+        &ctx.queue,
+        buf,
+        len as usize,
+        Some(&ctx.label("buffer_fnv staging")),
+    )
+    .await?;
+    Ok(fnv1a_64(&bytes))
+}
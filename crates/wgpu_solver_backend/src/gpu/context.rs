@@ -1,33 +1,178 @@
 use bytemuck::Pod;
+use std::fmt;
 use std::marker::PhantomData;
 use std::mem::size_of;
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicBool, AtomicU64, Ordering},
+};
 use thiserror::Error;
 use wgpu::{
-    Adapter, Backend, Backends, BufferDescriptor, BufferUsages, Device, DeviceDescriptor,
-    DeviceType, ExperimentalFeatures, Features, Instance, InstanceDescriptor, Limits, MemoryHints,
-    PowerPreference, Queue, RequestAdapterOptions, Trace,
+    Adapter, Backend, Backends, Buffer, BufferDescriptor, BufferUsages, CommandEncoder,
+    ComputePass, ComputePassDescriptor, Device, DeviceDescriptor, DeviceType, ExperimentalFeatures,
+    Features, Instance, InstanceDescriptor, Limits, MemoryHints, Queue, RequestDeviceError, Trace,
     util::{BufferInitDescriptor, DeviceExt},
 };
 
-use crate::gpu::{buffer::GpuBuffer, readback::readback_to_vec};
+use crate::gpu::{
+    buffer::GpuBuffer,
+    buffer_pool::{BufferPool, PooledBuffer},
+    readback::{readback_to_vec, try_readback_to_vec},
+    timer::PassTimer,
+};
 
+/// Why a [`GpuContext`] could not be created.
+///
+/// Roughly three classes, which callers may want to tell apart (the CLI maps them to
+/// distinct exit codes):
+/// - wrong machine: [`GpuError::NoAdapter`], [`GpuError::UnsupportedBackend`], and an
+///   adapter short of what [`GpuContextBuilder::required_limits`] /
+///   [`GpuContextBuilder::required_features`] asked for ([`GpuError::UnsupportedLimit`],
+///   [`GpuError::UnsupportedFeatures`])
+/// - driver problem: [`GpuError::DeviceRequest`] (an adapter exists but will not give a device)
+/// - configuration: everything else (selection, filters, environment variables)
+///
+/// [`GpuError::Readback`] is the odd one out: a later readback from a working context
+/// (see [`crate::gpu::readback::read_f32_buffer`]) that failed.
 #[derive(Debug, Error)]
 pub enum GpuError {
-    #[error("no suitable GPU adapter found")]
-    NoAdapter,
-    #[error("request device failed: {0}")]
-    RequestDevice(String),
+    #[error(
+        "no GPU adapter found for backend {backend:?}; check that a GPU driver is installed \
+         and visible (e.g. `vulkaninfo`), or run with --backend cpu"
+    )]
+    NoAdapter { backend: GpuBackend },
+    #[error(
+        "backend {backend:?} is not available on this platform/build (compiled in: {available}); \
+         pick another --backend"
+    )]
+    UnsupportedBackend {
+        backend: GpuBackend,
+        available: String,
+    },
+    #[error("the adapter was found but the device request failed (driver problem?): {0}")]
+    DeviceRequest(#[from] RequestDeviceError),
+    #[error("adapter index {index} out of range ({available} adapters available)")]
+    AdapterIndexOutOfRange { index: usize, available: usize },
+    #[error("invalid value '{value}' for environment variable {var}")]
+    InvalidEnvVar { var: &'static str, value: String },
+    #[error(
+        "backend 'cpu' is a CPU pseudo-backend and has no GPU context (use device::SolverDevice)"
+    )]
+    CpuBackend,
+    #[error("adapter {index} ({name}) is excluded by the adapter filters")]
+    AdapterDenied { index: usize, name: String },
+    #[error("no adapter passes the adapter filters ({available} adapters available)")]
+    NoPermittedAdapter { available: usize },
+    #[error("no adapter matches {selector:?}; available: {available}")]
+    NoMatchingAdapter {
+        selector: AdapterSelector,
+        available: String,
+    },
+    #[error(
+        "adapter {name} does not support limit {limit}: requested {requested}, the adapter allows {allowed}"
+    )]
+    UnsupportedLimit {
+        name: String,
+        limit: &'static str,
+        requested: u64,
+        allowed: u64,
+    },
+    #[error("adapter {name} does not support the required features {missing:?}")]
+    UnsupportedFeatures { name: String, missing: Features },
+    #[error("GPU readback failed: {0}")]
+    Readback(String),
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Environment variable consulted when no explicit backend is given
+/// (values as accepted by [`GpuBackend::parse`]).
+pub const BACKEND_ENV_VAR: &str = "WGPU_SOLVER_BACKEND";
+
+/// Environment variable consulted when no explicit adapter index is given.
+/// The index refers to the order of `Instance::enumerate_adapters` for the selected backend.
+pub const ADAPTER_INDEX_ENV_VAR: &str = "WGPU_SOLVER_ADAPTER_INDEX";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GpuBackend {
     Auto,
     Vulkan,
     Dx12,
     Metal,
+    /// OpenGL / OpenGL ES (EGL on Linux, WGL on Windows, WebGL2 on wasm): older Linux boxes
+    /// and VMs with no Vulkan driver, and software rasterizers such as llvmpipe.
+    Gl,
+    /// The browser's WebGPU (`navigator.gpu`). Only wasm32 builds with wgpu's `webgpu`
+    /// feature have it; elsewhere context creation fails with
+    /// [`GpuError::UnsupportedBackend`].
+    BrowserWebGpu,
+    /// CPU pseudo-backend: no wgpu device, operations run on the `reference` kernels
+    /// (see `device::SolverDevice`). Not for performance.
+    Cpu,
 }
 
-#[derive(Debug)]
+impl GpuBackend {
+    /// Parse a backend name ("auto", "vulkan", "dx12", "metal", "gl" or "opengl",
+    /// "webgpu", "cpu"), case-insensitive.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "auto" => Some(GpuBackend::Auto),
+            "vulkan" => Some(GpuBackend::Vulkan),
+            "dx12" => Some(GpuBackend::Dx12),
+            "metal" => Some(GpuBackend::Metal),
+            "gl" | "opengl" => Some(GpuBackend::Gl),
+            "webgpu" => Some(GpuBackend::BrowserWebGpu),
+            "cpu" => Some(GpuBackend::Cpu),
+            _ => None,
+        }
+    }
+}
+
+/// Resolve the backend to use.
+///
+/// Precedence: explicit argument > `WGPU_SOLVER_BACKEND` > auto.
+/// `GpuBackend::Auto` passed explicitly counts as "no preference", so the env var still applies.
+pub fn resolve_backend(
+    explicit: GpuBackend,
+    env_value: Option<&str>,
+) -> Result<GpuBackend, GpuError> {
+    if explicit != GpuBackend::Auto {
+        return Ok(explicit);
+    }
+    match env_value {
+        None => Ok(GpuBackend::Auto),
+        Some(v) if v.trim().is_empty() => Ok(GpuBackend::Auto),
+        Some(v) => GpuBackend::parse(v).ok_or_else(|| GpuError::InvalidEnvVar {
+            var: BACKEND_ENV_VAR,
+            value: v.to_string(),
+        }),
+    }
+}
+
+/// Resolve the adapter index to use.
+///
+/// Precedence: explicit argument > `WGPU_SOLVER_ADAPTER_INDEX` > none
+/// (none means: the best-ranked adapter, see [`AdapterSelector::PreferDiscrete`]).
+pub fn resolve_adapter_index(
+    explicit: Option<usize>,
+    env_value: Option<&str>,
+) -> Result<Option<usize>, GpuError> {
+    if explicit.is_some() {
+        return Ok(explicit);
+    }
+    match env_value {
+        None => Ok(None),
+        Some(v) if v.trim().is_empty() => Ok(None),
+        Some(v) => v
+            .trim()
+            .parse::<usize>()
+            .map(Some)
+            .map_err(|_| GpuError::InvalidEnvVar {
+                var: ADAPTER_INDEX_ENV_VAR,
+                value: v.to_string(),
+            }),
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct AdapterInfo {
     pub name: String,
     pub vendor: u32,
@@ -36,6 +181,407 @@ pub struct AdapterInfo {
     pub backend: Backend,
 }
 
+impl AdapterInfo {
+    fn of(adapter: &Adapter) -> Self {
+        let info = adapter.get_info();
+        Self {
+            name: info.name,
+            vendor: info.vendor,
+            device: info.device,
+            device_type: info.device_type,
+            backend: info.backend,
+        }
+    }
+}
+
+/// Which of the enumerated adapters (`Instance::enumerate_adapters` for the backend, in
+/// that order) a context is created on; see [`GpuContext::create_with_selector`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum AdapterSelector {
+    /// The adapter at this enumeration index (as `WGPU_SOLVER_ADAPTER_INDEX`).
+    Index(usize),
+    /// The first adapter whose name contains this, case-insensitive (e.g. "nvidia").
+    NameContains(String),
+    /// The best device type: discrete > integrated > virtual > CPU > other, ties in
+    /// enumeration order. This is "no preference", so `WGPU_SOLVER_ADAPTER_INDEX` still
+    /// overrides it.
+    #[default]
+    PreferDiscrete,
+}
+
+/// "[0] name (DiscreteGpu, Vulkan), [1] ..." for error messages.
+fn describe_adapters(candidates: &[AdapterInfo]) -> String {
+    if candidates.is_empty() {
+        return "none".to_string();
+    }
+    candidates
+        .iter()
+        .enumerate()
+        .map(|(i, a)| format!("[{i}] {} ({:?}, {:?})", a.name, a.device_type, a.backend))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Predicate over an adapter, for [`GpuContextBuilder::deny_adapter`] / `allow_only`.
+pub type AdapterFilter = Arc<dyn Fn(&AdapterInfo) -> bool + Send + Sync>;
+
+/// Context creation with adapter filters, for fleets where some adapters/drivers are
+/// known to be bad:
+///
+/// ```no_run
+/// use wgpu::DeviceType;
+/// use wgpu_solver_backend::gpu::context::{GpuBackend, GpuContextBuilder, GpuError};
+///
+/// # async fn example() -> Result<(), GpuError> {
+/// let ctx = GpuContextBuilder::new(GpuBackend::Auto)
+///     .deny_adapter(|a| a.vendor == 0x1002 && a.name.contains("R9"))
+///     .allow_only(|a| a.device_type != DeviceType::Cpu)
+///     .build()
+///     .await?;
+/// # drop(ctx);
+/// # Ok(())
+/// # }
+/// # futures::executor::block_on(example()).unwrap();
+/// ```
+///
+/// An adapter is permitted when no `deny_adapter` predicate matches it and every
+/// `allow_only` predicate does (each `allow_only` narrows the set further).
+///
+/// Precedence with the other selection settings:
+/// - The backend resolves as in [`GpuContext::create`] (explicit, then
+///   `WGPU_SOLVER_BACKEND`, then auto) and its adapters are enumerated; the filters never
+///   change either.
+/// - The [`AdapterSelector`] then picks among them. `PreferDiscrete` (the default) gives
+///   way to `WGPU_SOLVER_ADAPTER_INDEX` when that is set; `Index` and `NameContains` are
+///   explicit and win over it.
+/// - An index (explicit or from the environment) names one adapter. If the filters
+///   exclude it, `build` fails with [`GpuError::AdapterDenied`] rather than quietly
+///   running somewhere else.
+/// - `NameContains` takes the first permitted adapter with a matching name, and
+///   `PreferDiscrete` the best-ranked permitted one; when none qualifies the error is
+///   [`GpuError::NoMatchingAdapter`] (listing every adapter) or
+///   [`GpuError::NoPermittedAdapter`] respectively.
+///
+/// Device limits and features can be requested up front, e.g. for storage buffers past
+/// the default 128 MiB binding size:
+///
+/// ```no_run
+/// use wgpu::Limits;
+/// use wgpu_solver_backend::gpu::context::{GpuBackend, GpuContextBuilder, GpuError};
+///
+/// # async fn example() -> Result<(), GpuError> {
+/// let ctx = GpuContextBuilder::new(GpuBackend::Auto)
+///     .required_limits(Limits {
+///         max_storage_buffer_binding_size: 1 << 30,
+///         max_buffer_size: 1 << 30,
+///         ..Limits::downlevel_defaults()
+///     })
+///     .build()
+///     .await?;
+/// # drop(ctx);
+/// # Ok(())
+/// # }
+/// # futures::executor::block_on(example()).unwrap();
+/// ```
+///
+/// Requested limits only ever raise what the crate asks for by default (each limit is
+/// the better of the two), so a struct built from `downlevel_defaults()` or
+/// `Limits::default()` with a few fields bumped is fine. They are checked against the
+/// chosen adapter before the device request: the first limit it cannot meet fails
+/// `build` with [`GpuError::UnsupportedLimit`], naming the limit, and missing required
+/// features with [`GpuError::UnsupportedFeatures`]. Adapter selection does not take
+/// them into account; filter or select the adapter as usual.
+///
+/// The chosen adapter is remembered by index, so [`GpuContext::recreate`] comes back on
+/// the same one (with the same requests).
+#[derive(Clone)]
+pub struct GpuContextBuilder {
+    backend: GpuBackend,
+    selector: AdapterSelector,
+    deny: Vec<AdapterFilter>,
+    allow: Vec<AdapterFilter>,
+    limits: Option<Limits>,
+    features: Features,
+}
+
+impl fmt::Debug for GpuContextBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GpuContextBuilder")
+            .field("backend", &self.backend)
+            .field("selector", &self.selector)
+            .field("deny", &self.deny.len())
+            .field("allow", &self.allow.len())
+            .field("limits", &self.limits)
+            .field("features", &self.features)
+            .finish()
+    }
+}
+
+impl GpuContextBuilder {
+    pub fn new(backend: GpuBackend) -> Self {
+        Self {
+            backend,
+            selector: AdapterSelector::default(),
+            deny: Vec::new(),
+            allow: Vec::new(),
+            limits: None,
+            features: Features::empty(),
+        }
+    }
+
+    /// Explicit adapter index (enumeration order); `None` is `PreferDiscrete`, which
+    /// defers to the environment.
+    pub fn adapter_index(mut self, adapter_index: Option<usize>) -> Self {
+        self.selector =
+            adapter_index.map_or(AdapterSelector::PreferDiscrete, AdapterSelector::Index);
+        self
+    }
+
+    pub fn selector(mut self, selector: AdapterSelector) -> Self {
+        self.selector = selector;
+        self
+    }
+
+    /// Never use an adapter this predicate matches.
+    pub fn deny_adapter(
+        mut self,
+        predicate: impl Fn(&AdapterInfo) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.deny.push(Arc::new(predicate));
+        self
+    }
+
+    /// Only use adapters this predicate matches.
+    pub fn allow_only(
+        mut self,
+        predicate: impl Fn(&AdapterInfo) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.allow.push(Arc::new(predicate));
+        self
+    }
+
+    /// Limits the device must be created with (see the type docs for how they combine
+    /// with the defaults). Replaces limits requested before.
+    pub fn required_limits(mut self, limits: Limits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    /// Features the device must have, on top of the optional ones the crate enables when
+    /// available. Accumulates over calls.
+    pub fn required_features(mut self, features: Features) -> Self {
+        self.features |= features;
+        self
+    }
+
+    /// The features and limits `build` would request from an adapter with these
+    /// capabilities, or the error it would fail with. No device is created.
+    pub fn device_request(
+        &self,
+        adapter_name: &str,
+        adapter_features: Features,
+        adapter_limits: &Limits,
+    ) -> Result<(Features, Limits), GpuError> {
+        let missing = self.features - adapter_features;
+        if !missing.is_empty() {
+            return Err(GpuError::UnsupportedFeatures {
+                name: adapter_name.to_string(),
+                missing,
+            });
+        }
+
+        // Defaults, except the compute workgroup limits: whatever the adapter offers, so
+        // WORKGROUP_SIZE overrides above 256 (and their workgroup arrays) can be used.
+        let mut limits = Limits {
+            max_compute_workgroup_size_x: adapter_limits.max_compute_workgroup_size_x,
+            max_compute_invocations_per_workgroup: adapter_limits
+                .max_compute_invocations_per_workgroup,
+            max_compute_workgroup_storage_size: adapter_limits.max_compute_workgroup_storage_size,
+            ..Limits::default()
+        };
+        if let Some(requested) = &self.limits {
+            limits = limits.or_better_values_from(requested);
+        }
+
+        let mut unsupported = None;
+        limits.check_limits_with_fail_fn(adapter_limits, false, |limit, requested, allowed| {
+            unsupported.get_or_insert(GpuError::UnsupportedLimit {
+                name: adapter_name.to_string(),
+                limit,
+                requested,
+                allowed,
+            });
+        });
+        match unsupported {
+            Some(e) => Err(e),
+            None => Ok((
+                (adapter_features & OPTIONAL_FEATURES) | self.features,
+                limits,
+            )),
+        }
+    }
+
+    /// Whether the filters let `info` through.
+    pub fn permits(&self, info: &AdapterInfo) -> bool {
+        !self.deny.iter().any(|f| f(info)) && self.allow.iter().all(|f| f(info))
+    }
+
+    /// Pick from `candidates` (in enumeration order) by the rules above; returns the index.
+    /// `adapter_index` is the resolved index, if any (`None` is `PreferDiscrete`).
+    pub fn select(
+        &self,
+        candidates: &[AdapterInfo],
+        adapter_index: Option<usize>,
+    ) -> Result<usize, GpuError> {
+        let selector =
+            adapter_index.map_or(AdapterSelector::PreferDiscrete, AdapterSelector::Index);
+        self.select_with(candidates, &selector)
+    }
+
+    /// [`GpuContextBuilder::select`] for any selector (the environment is not consulted).
+    pub fn select_with(
+        &self,
+        candidates: &[AdapterInfo],
+        selector: &AdapterSelector,
+    ) -> Result<usize, GpuError> {
+        let available = candidates.len();
+        let needle = match selector {
+            AdapterSelector::Index(index) => {
+                let index = *index;
+                let info = candidates
+                    .get(index)
+                    .ok_or(GpuError::AdapterIndexOutOfRange { index, available })?;
+                if !self.permits(info) {
+                    return Err(GpuError::AdapterDenied {
+                        index,
+                        name: info.name.clone(),
+                    });
+                }
+                return Ok(index);
+            }
+            AdapterSelector::NameContains(needle) => needle.to_lowercase(),
+            AdapterSelector::PreferDiscrete => {
+                return self.select_best(candidates);
+            }
+        };
+
+        candidates
+            .iter()
+            .position(|info| info.name.to_lowercase().contains(&needle) && self.permits(info))
+            .ok_or_else(|| GpuError::NoMatchingAdapter {
+                selector: selector.clone(),
+                available: describe_adapters(candidates),
+            })
+    }
+
+    /// `PreferDiscrete`: the best-ranked permitted adapter.
+    fn select_best(&self, candidates: &[AdapterInfo]) -> Result<usize, GpuError> {
+        let available = candidates.len();
+        let rank = |t: DeviceType| match t {
+            DeviceType::DiscreteGpu => 0,
+            DeviceType::IntegratedGpu => 1,
+            DeviceType::VirtualGpu => 2,
+            DeviceType::Cpu => 3,
+            DeviceType::Other => 4,
+        };
+        candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, info)| self.permits(info))
+            .min_by_key(|(i, info)| (rank(info.device_type), *i))
+            .map(|(i, _)| i)
+            .ok_or(GpuError::NoPermittedAdapter { available })
+    }
+
+    /// The backend after `WGPU_SOLVER_BACKEND` (never `Cpu`, which has no adapters, nor
+    /// one wgpu was built without on this platform).
+    fn resolved_backend(&self) -> Result<GpuBackend, GpuError> {
+        let env_backend = std::env::var(BACKEND_ENV_VAR).ok();
+        let gpu_backend = resolve_backend(self.backend, env_backend.as_deref())?;
+        if gpu_backend == GpuBackend::Cpu {
+            return Err(GpuError::CpuBackend);
+        }
+        let compiled = Instance::enabled_backend_features();
+        if !compiled.intersects(backend_bits(gpu_backend)) {
+            return Err(GpuError::UnsupportedBackend {
+                backend: gpu_backend,
+                available: format!("{compiled:?}"),
+            });
+        }
+        Ok(gpu_backend)
+    }
+
+    /// The selector after `WGPU_SOLVER_ADAPTER_INDEX` (which only overrides `PreferDiscrete`).
+    fn resolved_selector(&self) -> Result<AdapterSelector, GpuError> {
+        let env_adapter_index = std::env::var(ADAPTER_INDEX_ENV_VAR).ok();
+        Ok(match &self.selector {
+            AdapterSelector::PreferDiscrete => {
+                match resolve_adapter_index(None, env_adapter_index.as_deref())? {
+                    Some(index) => AdapterSelector::Index(index),
+                    None => AdapterSelector::PreferDiscrete,
+                }
+            }
+            explicit => explicit.clone(),
+        })
+    }
+
+    fn instance(gpu_backend: GpuBackend) -> Instance {
+        Instance::new(&InstanceDescriptor {
+            backends: backend_bits(gpu_backend),
+            ..Default::default()
+        })
+    }
+
+    /// The adapters `build` chooses from, in enumeration order (no device is created).
+    pub async fn enumerate(&self) -> Result<Vec<AdapterInfo>, GpuError> {
+        let gpu_backend = self.resolved_backend()?;
+        let adapters = Self::instance(gpu_backend)
+            .enumerate_adapters(backend_bits(gpu_backend))
+            .await;
+        Ok(adapters.iter().map(AdapterInfo::of).collect())
+    }
+
+    /// The index into `candidates` (as returned by `enumerate`) that `build` would use,
+    /// environment included.
+    pub fn pick(&self, candidates: &[AdapterInfo]) -> Result<usize, GpuError> {
+        self.select_with(candidates, &self.resolved_selector()?)
+    }
+
+    pub async fn build(self) -> Result<GpuContext, GpuError> {
+        let gpu_backend = self.resolved_backend()?;
+        let instance = Self::instance(gpu_backend);
+
+        let mut adapters = instance.enumerate_adapters(backend_bits(gpu_backend)).await;
+        if adapters.is_empty() {
+            return Err(GpuError::NoAdapter {
+                backend: gpu_backend,
+            });
+        }
+        let infos: Vec<AdapterInfo> = adapters.iter().map(AdapterInfo::of).collect();
+        let index = self.pick(&infos)?;
+        let adapter = adapters.swap_remove(index);
+
+        let (features, limits) =
+            self.device_request(&infos[index].name, adapter.features(), &adapter.limits())?;
+        let request = DeviceRequest {
+            features,
+            limits,
+            requested_limits: self.limits.map(Box::new),
+            requested_features: self.features,
+        };
+        GpuContext::from_adapter(instance, adapter, gpu_backend, Some(index), request).await
+    }
+}
+
+/// What [`GpuContext::from_adapter`] asks the adapter for, and the builder requests it came
+/// from (kept for `recreate`).
+struct DeviceRequest {
+    features: Features,
+    limits: Limits,
+    requested_limits: Option<Box<Limits>>,
+    requested_features: Features,
+}
+
 #[derive(Debug)]
 pub struct GpuContext {
     pub instance: Instance,
@@ -43,46 +589,102 @@ pub struct GpuContext {
     pub device: Device,
     pub queue: Queue,
     pub adapter_info: AdapterInfo,
+    /// Features enabled on `device` (optional ones are only requested when the adapter has them).
+    pub features: Features,
+    /// Set by the device-lost callback; see [`GpuContext::is_lost`].
+    lost: Arc<AtomicBool>,
+    /// See [`GpuContext::buffers_created`].
+    buffers_created: AtomicU64,
+    /// See [`GpuContext::set_label_prefix`].
+    label_prefix: Mutex<String>,
+    /// See [`GpuContext::enable_pass_timing`].
+    pass_timer: Mutex<Option<Arc<PassTimer>>>,
+    /// See [`GpuContext::set_buffer_pool`].
+    buffer_pool: Mutex<Option<Arc<BufferPool>>>,
+    /// Resolved backend/adapter selection and the builder's device requests, reused by
+    /// [`GpuContext::recreate`].
+    backend: GpuBackend,
+    adapter_index: Option<usize>,
+    requested_limits: Option<Box<Limits>>,
+    requested_features: Features,
 }
 
+/// Features we enable opportunistically: requested only if the adapter supports them.
+///
+/// - TIMESTAMP_QUERY: GPU timings, finer with TIMESTAMP_QUERY_INSIDE_ENCODERS (see `gpu::timer`)
+/// - SHADER_F64: the f64 half of `compute::precision_bench`
+/// - SHADER_F16: f16 LU block storage (see `compute::block_jacobi::LuStorage`)
+/// - SUBGROUP: the subgroup-reduce dot (see `compute::dot_partials`)
+const OPTIONAL_FEATURES: Features = Features::TIMESTAMP_QUERY
+    .union(Features::TIMESTAMP_QUERY_INSIDE_ENCODERS)
+    .union(Features::SHADER_F64)
+    .union(Features::SHADER_F16)
+    .union(Features::SUBGROUP);
+
 fn backend_bits(gpu_backend: GpuBackend) -> Backends {
     match gpu_backend {
         GpuBackend::Auto => Backends::all(),
         GpuBackend::Vulkan => Backends::VULKAN,
         GpuBackend::Dx12 => Backends::DX12,
         GpuBackend::Metal => Backends::METAL,
+        GpuBackend::Gl => Backends::GL,
+        GpuBackend::BrowserWebGpu => Backends::BROWSER_WEBGPU,
+        GpuBackend::Cpu => Backends::empty(),
     }
 }
 
 impl GpuContext {
     /// Minimal headless compute context.
     /// Explicit and boring by design.
+    ///
+    /// `GpuBackend::Auto` defers to `WGPU_SOLVER_BACKEND`; the adapter index is taken
+    /// from `WGPU_SOLVER_ADAPTER_INDEX` when set, otherwise the best-ranked adapter is
+    /// used ([`AdapterSelector::PreferDiscrete`]).
     pub async fn create(gpu_backend: GpuBackend) -> Result<Self, GpuError> {
-        let instance = Instance::new(&InstanceDescriptor {
-            backends: backend_bits(gpu_backend),
-            ..Default::default()
-        });
+        Self::create_with_selector(gpu_backend, AdapterSelector::PreferDiscrete).await
+    }
 
-        let adapter = instance
-            .request_adapter(&RequestAdapterOptions {
-                power_preference: PowerPreference::HighPerformance,
-                compatible_surface: None,
-                force_fallback_adapter: false,
-            })
+    /// Same as [`GpuContext::create`], choosing the adapter by `selector` among those the
+    /// backend enumerates (see [`GpuContextBuilder`] for the precedence rules).
+    pub async fn create_with_selector(
+        gpu_backend: GpuBackend,
+        selector: AdapterSelector,
+    ) -> Result<Self, GpuError> {
+        GpuContextBuilder::new(gpu_backend)
+            .selector(selector)
+            .build()
             .await
-            .or(Err(GpuError::NoAdapter))?;
+    }
 
-        let info = adapter.get_info();
-        let adapter_info = AdapterInfo {
-            name: info.name,
-            vendor: info.vendor,
-            device: info.device,
-            device_type: info.device_type,
-            backend: info.backend,
-        };
+    /// Same as [`GpuContext::create`], with an explicit adapter index.
+    ///
+    /// Precedence for both settings: explicit argument > environment variable > auto.
+    pub async fn create_with_adapter_index(
+        gpu_backend: GpuBackend,
+        adapter_index: Option<usize>,
+    ) -> Result<Self, GpuError> {
+        GpuContextBuilder::new(gpu_backend)
+            .adapter_index(adapter_index)
+            .build()
+            .await
+    }
+
+    /// `request` is the builder's `device_request` for this adapter.
+    async fn from_adapter(
+        instance: Instance,
+        adapter: Adapter,
+        gpu_backend: GpuBackend,
+        adapter_index: Option<usize>,
+        request: DeviceRequest,
+    ) -> Result<Self, GpuError> {
+        let adapter_info = AdapterInfo::of(&adapter);
 
-        let required_features = Features::empty();
-        let required_limits = Limits::default();
+        let DeviceRequest {
+            features: required_features,
+            limits: required_limits,
+            requested_limits,
+            requested_features,
+        } = request;
         let experimental_features = ExperimentalFeatures::disabled();
         let memory_hints = MemoryHints::default();
         let trace = Trace::default();
@@ -96,8 +698,14 @@ impl GpuContext {
                 memory_hints,
                 trace,
             })
-            .await
-            .map_err(|e| GpuError::RequestDevice(format!("{e:?}")))?;
+            .await?;
+
+        let lost = Arc::new(AtomicBool::new(false));
+        let lost_flag = Arc::clone(&lost);
+        device.set_device_lost_callback(move |reason, message| {
+            eprintln!("GPU device lost ({reason:?}): {message}");
+            lost_flag.store(true, Ordering::SeqCst);
+        });
 
         Ok(Self {
             instance,
@@ -105,9 +713,44 @@ impl GpuContext {
             device,
             queue,
             adapter_info,
+            features: required_features,
+            lost,
+            buffers_created: AtomicU64::new(0),
+            label_prefix: Mutex::new(String::new()),
+            pass_timer: Mutex::new(None),
+            buffer_pool: Mutex::new(None),
+            backend: gpu_backend,
+            adapter_index,
+            requested_limits,
+            requested_features,
         })
     }
 
+    /// Create a fresh context on the same backend/adapter selection as `self`
+    /// (e.g. after the device was lost), with the same required limits and features.
+    /// Buffers and pipelines of `self` are not carried over.
+    /// The label prefix is kept; pass timing and the buffer pool are not (their queries
+    /// and buffers belong to the old device).
+    pub async fn recreate(&self) -> Result<Self, GpuError> {
+        let mut builder = GpuContextBuilder::new(self.backend)
+            .adapter_index(self.adapter_index)
+            .required_features(self.requested_features);
+        if let Some(limits) = &self.requested_limits {
+            builder = builder.required_limits((**limits).clone());
+        }
+        let ctx = builder.build().await?;
+        ctx.set_label_prefix(self.label_prefix());
+        Ok(ctx)
+    }
+
+    /// Whether the device was lost (driver reset, `Device::destroy`, ...).
+    ///
+    /// A lost device never comes back: every later operation on it is a no-op and
+    /// buffer mappings fail. Recreate the context to continue.
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::SeqCst)
+    }
+
     pub fn describe(&self) -> String {
         format!(
             "{} ({:?}, backend={:?}, vendor=0x{:04x}, device=0x{:04x})",
@@ -119,6 +762,144 @@ impl GpuContext {
         )
     }
 
+    /// Buffers created so far with this context: by [`GpuContext::create_buffer`] and
+    /// [`GpuContext::create_buffer_init`], which every buffer the crate allocates goes
+    /// through (executor setup, per-call staging and uniforms, the pool, the workspace),
+    /// plus the staging buffer of every readback the crate does.
+    ///
+    /// Buffers made on `device` directly, outside the crate, are not counted. Meant for
+    /// checking that a code path allocates nothing, e.g. a solve through
+    /// `solver::pcg::SolverWorkspace`.
+    pub fn buffers_created(&self) -> u64 {
+        self.buffers_created.load(Ordering::Relaxed)
+    }
+
+    /// For the readback helpers, whose staging buffer is made from a bare `Device`.
+    pub(crate) fn count_buffer_created(&self) {
+        self.buffers_created.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// `device.create_buffer`, counted in [`GpuContext::buffers_created`].
+    pub fn create_buffer(&self, desc: &BufferDescriptor) -> Buffer {
+        self.count_buffer_created();
+        self.device.create_buffer(desc)
+    }
+
+    /// `device.create_buffer_init`, counted in [`GpuContext::buffers_created`].
+    pub fn create_buffer_init(&self, desc: &BufferInitDescriptor) -> Buffer {
+        self.count_buffer_created();
+        self.device.create_buffer_init(desc)
+    }
+
+    /// Prefix for the label of every buffer, shader module, pipeline and bind group layout
+    /// the crate creates with this context, and of the encoders and passes it records
+    /// where the context is at hand. The default is empty, i.e. the plain labels such as
+    /// "block_jacobi lu_blocks". It is prepended verbatim, so include a separator:
+    /// "solverA/" gives "solverA/block_jacobi lu_blocks" in a GPU capture.
+    ///
+    /// A label is fixed when its object is created, so with several solvers on one
+    /// context set the prefix before creating each of them; objects created later (per-call
+    /// uniforms, staging buffers) take the prefix current at that time. Bind groups, which
+    /// the pipeline modules build from a bare `Device`, keep their plain labels.
+    pub fn set_label_prefix(&self, prefix: impl Into<String>) {
+        *self.label_prefix.lock().unwrap() = prefix.into();
+    }
+
+    pub fn label_prefix(&self) -> String {
+        self.label_prefix.lock().unwrap().clone()
+    }
+
+    /// `name` with the current label prefix.
+    pub fn label(&self, name: &str) -> String {
+        format!("{}{name}", self.label_prefix.lock().unwrap())
+    }
+
+    /// Whether the device was created with TIMESTAMP_QUERY (the adapter offered it).
+    pub fn supports_timestamps(&self) -> bool {
+        self.features.contains(Features::TIMESTAMP_QUERY)
+    }
+
+    /// Time every compute pass the crate's block-Jacobi and dot executors begin from now
+    /// on (see [`PassTimer`]), with room for `max_passes` per submit. Replaces a timer
+    /// enabled before.
+    ///
+    /// Returns false, and changes nothing, when the device lacks TIMESTAMP_QUERY.
+    pub fn enable_pass_timing(&self, max_passes: u32) -> bool {
+        let Some(timer) = PassTimer::create(self, max_passes) else {
+            return false;
+        };
+        *self.pass_timer.lock().unwrap() = Some(Arc::new(timer));
+        true
+    }
+
+    /// Stop timing passes; returns the timer that was in use.
+    pub fn disable_pass_timing(&self) -> Option<Arc<PassTimer>> {
+        self.pass_timer.lock().unwrap().take()
+    }
+
+    /// The pass timer, while pass timing is enabled.
+    pub fn pass_timer(&self) -> Option<Arc<PassTimer>> {
+        self.pass_timer.lock().unwrap().clone()
+    }
+
+    /// Begin a compute pass labelled `name` (with the label prefix), with timestamp
+    /// writes when pass timing is enabled and the timer still has room.
+    pub fn begin_compute_pass<'e>(
+        &self,
+        encoder: &'e mut CommandEncoder,
+        name: &str,
+    ) -> ComputePass<'e> {
+        let label = self.label(name);
+        let timer = self.pass_timer();
+        encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some(&label),
+            timestamp_writes: timer.as_ref().and_then(|t| t.timestamp_writes(&label)),
+        })
+    }
+
+    /// Serve the per-solve vectors of the solver drivers from `pool` (see
+    /// [`BufferPool`]), or allocate them per solve again with `None`, the default.
+    /// Returns the pool that was installed before.
+    pub fn set_buffer_pool(&self, pool: Option<Arc<BufferPool>>) -> Option<Arc<BufferPool>> {
+        std::mem::replace(&mut *self.buffer_pool.lock().unwrap(), pool)
+    }
+
+    /// The installed buffer pool, if any.
+    pub fn buffer_pool(&self) -> Option<Arc<BufferPool>> {
+        self.buffer_pool.lock().unwrap().clone()
+    }
+
+    /// A `STORAGE | COPY_SRC | COPY_DST` buffer holding `data`: from the installed
+    /// [`BufferPool`] (returned to it on drop), otherwise a new one as from
+    /// `create_storage_buffer`.
+    pub fn acquire_storage_buffer<T: Pod>(&self, label: &str, data: &[T]) -> PooledBuffer<T> {
+        match self.buffer_pool() {
+            Some(pool) => pool.acquire_init(self, label, data),
+            None => PooledBuffer::unpooled(self.create_storage_buffer(
+                label,
+                data,
+                BufferUsages::empty(),
+            )),
+        }
+    }
+
+    /// Same as `acquire_storage_buffer` for `len` T left as they are: zeros in a new
+    /// buffer, the previous user's values in a pooled one.
+    pub fn acquire_storage_buffer_uninit<T: Pod>(
+        &self,
+        label: &str,
+        len: usize,
+    ) -> PooledBuffer<T> {
+        match self.buffer_pool() {
+            Some(pool) => pool.acquire(self, label, len),
+            None => PooledBuffer::unpooled(self.create_storage_buffer_uninit(
+                label,
+                len,
+                BufferUsages::empty(),
+            )),
+        }
+    }
+
     pub fn create_storage_buffer<T: Pod>(
         &self,
         label: &str,
@@ -128,8 +909,8 @@ impl GpuContext {
         let usage =
             BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST | extra_usage;
 
-        let buffer = self.device.create_buffer_init(&BufferInitDescriptor {
-            label: Some(label),
+        let buffer = self.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&self.label(label)),
             contents: bytemuck::cast_slice(data),
             usage,
         });
@@ -151,8 +932,8 @@ impl GpuContext {
         let usage =
             BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST | extra_usage;
 
-        let buffer = self.device.create_buffer(&BufferDescriptor {
-            label: Some(label),
+        let buffer = self.create_buffer(&BufferDescriptor {
+            label: Some(&self.label(label)),
             size: byte_len,
             usage,
             mapped_at_creation: false,
@@ -165,13 +946,28 @@ impl GpuContext {
         }
     }
 
+    /// Like [`GpuContext::readback`], but returns an error instead of panicking when the
+    /// mapping fails (typically because the device was lost).
+    pub async fn try_readback<T: Pod>(&self, buf: &GpuBuffer<T>) -> Result<Vec<T>, String> {
+        self.count_buffer_created();
+        try_readback_to_vec::<T>(
+            &self.device,
+            &self.queue,
+            &buf.buffer,
+            buf.len,
+            Some(&self.label("readback_staging")),
+        )
+        .await
+    }
+
     pub async fn readback<T: Pod>(&self, buf: &GpuBuffer<T>) -> Vec<T> {
+        self.count_buffer_created();
         readback_to_vec::<T>(
             &self.device,
             &self.queue,
             &buf.buffer,
             buf.len,
-            Some("readback_staging"),
+            Some(&self.label("readback_staging")),
         )
         .await
     }
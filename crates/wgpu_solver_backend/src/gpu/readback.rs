@@ -1,11 +1,60 @@
 use bytemuck::{Pod, cast_slice};
 use futures::channel::oneshot;
+use futures::executor;
 use std::mem::size_of;
 use wgpu::PollType;
 use wgpu::{
     Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device, MapMode, Queue,
 };
 
+use crate::gpu::context::{GpuContext, GpuError};
+
+/// Read the first `len` f32 of a GPU buffer back to the host: staging copy, map, cast.
+///
+/// `src` needs COPY_SRC usage and at least `len` f32; both are checked up front (as
+/// [`GpuError::Readback`]) instead of surfacing as a wgpu validation panic. The copy is
+/// submitted on its own, after whatever the caller already submitted.
+pub async fn read_f32_buffer(
+    ctx: &GpuContext,
+    src: &Buffer,
+    len: usize,
+) -> Result<Vec<f32>, GpuError> {
+    if !src.usage().contains(BufferUsages::COPY_SRC) {
+        return Err(GpuError::Readback(
+            "source buffer lacks COPY_SRC usage".to_string(),
+        ));
+    }
+    let byte_len = (len * size_of::<f32>()) as u64;
+    if byte_len > src.size() {
+        return Err(GpuError::Readback(format!(
+            "{len} f32 requested from a buffer of {} bytes",
+            src.size()
+        )));
+    }
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    ctx.count_buffer_created();
+    try_readback_to_vec(
+        &ctx.device,
+        &ctx.queue,
+        src,
+        len,
+        Some(&ctx.label("read_f32_buffer staging")),
+    )
+    .await
+    .map_err(GpuError::Readback)
+}
+
+/// Blocking [`read_f32_buffer`], for drivers without an async runtime.
+pub fn read_f32_buffer_blocking(
+    ctx: &GpuContext,
+    src: &Buffer,
+    len: usize,
+) -> Result<Vec<f32>, GpuError> {
+    executor::block_on(read_f32_buffer(ctx, src, len))
+}
+
 pub async fn readback_to_vec<T: Pod>(
     device: &Device,
     queue: &Queue,
@@ -13,6 +62,20 @@ pub async fn readback_to_vec<T: Pod>(
     len: usize,
     label: Option<&str>,
 ) -> Vec<T> {
+    try_readback_to_vec(device, queue, src, len, label)
+        .await
+        .unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Fallible [`readback_to_vec`]: polling or mapping failures (e.g. a lost device)
+/// are returned as errors.
+pub async fn try_readback_to_vec<T: Pod>(
+    device: &Device,
+    queue: &Queue,
+    src: &Buffer,
+    len: usize,
+    label: Option<&str>,
+) -> Result<Vec<T>, String> {
     let byte_len = (len * size_of::<T>()) as u64;
 
     // Staging buffer must be MAP_READ + COPY_DST (common wgpu pattern). :contentReference[oaicite:1]{index=1}
@@ -39,18 +102,18 @@ pub async fn readback_to_vec<T: Pod>(
     // Ensure mapping completes.
     device
         .poll(PollType::wait_indefinitely())
-        .expect("error at polling");
+        .map_err(|e| format!("error at polling: {e}"))?;
 
     rx.await
-        .expect("map_async callback dropped")
-        .expect("map_async failed");
+        .map_err(|_| "map_async callback dropped".to_string())?
+        .map_err(|e| format!("map_async failed: {e}"))?;
 
     let data = slice.get_mapped_range();
     let out = cast_slice::<u8, T>(&data).to_vec();
     drop(data);
     staging.unmap();
 
-    out
+    Ok(out)
 }
 
 /// Read a buffer that was created with MAP_READ usage.
@@ -58,8 +121,19 @@ pub async fn readback_to_vec<T: Pod>(
 pub async fn read_mapped_buffer_to_vec<T: Pod>(
     device: &Device,
     buffer: &Buffer,
-    _len: usize,
+    len: usize,
 ) -> Vec<T> {
+    try_read_mapped_buffer_to_vec(device, buffer, len)
+        .await
+        .unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Fallible [`read_mapped_buffer_to_vec`].
+pub async fn try_read_mapped_buffer_to_vec<T: Pod>(
+    device: &Device,
+    buffer: &Buffer,
+    _len: usize,
+) -> Result<Vec<T>, String> {
     let slice = buffer.slice(..);
 
     let (tx, rx) = oneshot::channel();
@@ -69,16 +143,49 @@ pub async fn read_mapped_buffer_to_vec<T: Pod>(
 
     device
         .poll(PollType::wait_indefinitely())
-        .expect("error at polling");
+        .map_err(|e| format!("error at polling: {e}"))?;
 
     rx.await
-        .expect("map_async callback dropped")
-        .expect("map_async failed");
+        .map_err(|_| "map_async callback dropped".to_string())?
+        .map_err(|e| format!("map_async failed: {e}"))?;
 
     let data = slice.get_mapped_range();
     let out = cast_slice::<u8, T>(&data).to_vec();
     drop(data);
     buffer.unmap();
 
-    out
+    Ok(out)
+}
+
+/// Map a MAP_READ buffer and copy its first `out.len()` elements into `out` (no host
+/// allocation, no staging buffer).
+pub async fn try_read_mapped_buffer_into<T: Pod>(
+    device: &Device,
+    buffer: &Buffer,
+    out: &mut [T],
+) -> Result<(), String> {
+    if out.is_empty() {
+        return Ok(());
+    }
+    let slice = buffer.slice(..size_of_val(out) as u64);
+
+    let (tx, rx) = oneshot::channel();
+    slice.map_async(MapMode::Read, move |r| {
+        let _ = tx.send(r);
+    });
+
+    device
+        .poll(PollType::wait_indefinitely())
+        .map_err(|e| format!("error at polling: {e}"))?;
+
+    rx.await
+        .map_err(|_| "map_async callback dropped".to_string())?
+        .map_err(|e| format!("map_async failed: {e}"))?;
+
+    let data = slice.get_mapped_range();
+    out.copy_from_slice(cast_slice::<u8, T>(&data));
+    drop(data);
+    buffer.unmap();
+
+    Ok(())
 }
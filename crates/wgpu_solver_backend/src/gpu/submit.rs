@@ -0,0 +1,113 @@
+use std::collections::VecDeque;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use wgpu::{CommandBuffer, PollType, SubmissionIndex};
+
+use crate::gpu::context::GpuContext;
+
+/// Window depth used when nothing else is configured.
+pub const DEFAULT_MAX_IN_FLIGHT_SUBMISSIONS: usize = 2;
+
+/// SubmissionWindow
+///
+/// Bounded queue submission: at most `max_in_flight` submitted command buffers may be
+/// unfinished on the GPU at any time.
+///
+/// Back-pressure: when the window is full, `submit` blocks (device poll on the oldest
+/// submission) until that one has completed, then submits. Nothing is dropped or
+/// reordered; a full window just turns the submit into a wait.
+///
+/// Completion is tracked with `Queue::on_submitted_work_done` callbacks, which wgpu runs
+/// during device polls (including the ones inside readbacks). Submissions made outside
+/// the window (e.g. the staging copy of `GpuContext::readback`) are not counted.
+pub struct SubmissionWindow {
+    max_in_flight: usize,
+    in_flight: VecDeque<(SubmissionIndex, Arc<AtomicBool>)>,
+    max_observed: usize,
+    waits: usize,
+}
+
+impl SubmissionWindow {
+    /// `max_in_flight` must be >= 1.
+    pub fn new(max_in_flight: usize) -> Self {
+        assert!(
+            max_in_flight >= 1,
+            "SubmissionWindow: max_in_flight must be >= 1"
+        );
+        Self {
+            max_in_flight,
+            in_flight: VecDeque::new(),
+            max_observed: 0,
+            waits: 0,
+        }
+    }
+
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    /// Submissions not yet known to be complete.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Highest `in_flight()` seen right after a submit.
+    pub fn max_observed(&self) -> usize {
+        self.max_observed
+    }
+
+    /// Submits that found the window full and had to wait for older work first.
+    pub fn waits(&self) -> usize {
+        self.waits
+    }
+
+    /// Submit `command_buffer`, first waiting for older submissions while the window is full.
+    pub fn submit(
+        &mut self,
+        ctx: &GpuContext,
+        command_buffer: CommandBuffer,
+    ) -> Result<SubmissionIndex, String> {
+        self.retire_completed();
+
+        if self.in_flight.len() >= self.max_in_flight {
+            self.waits += 1;
+        }
+        while self.in_flight.len() >= self.max_in_flight {
+            let (oldest, _) = self
+                .in_flight
+                .front()
+                .expect("window is full, so not empty");
+            ctx.device
+                .poll(PollType::Wait {
+                    submission_index: Some(oldest.clone()),
+                    timeout: None,
+                })
+                .map_err(|e| format!("SubmissionWindow: poll failed: {e}"))?;
+            // Waiting on `oldest` completes it even if its callback lags behind the poll.
+            self.in_flight.pop_front();
+            self.retire_completed();
+        }
+
+        let index = ctx.queue.submit(Some(command_buffer));
+
+        let done = Arc::new(AtomicBool::new(false));
+        let done_flag = Arc::clone(&done);
+        ctx.queue.on_submitted_work_done(move || {
+            done_flag.store(true, Ordering::SeqCst);
+        });
+
+        self.in_flight.push_back((index.clone(), done));
+        self.max_observed = self.max_observed.max(self.in_flight.len());
+
+        Ok(index)
+    }
+
+    /// Drop every submission whose completion callback has fired.
+    pub fn retire_completed(&mut self) {
+        self.in_flight
+            .retain(|(_, done)| !done.load(Ordering::SeqCst));
+    }
+}
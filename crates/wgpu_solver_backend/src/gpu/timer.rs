@@ -0,0 +1,388 @@
+use std::cell::Cell;
+use std::sync::Mutex;
+
+use wgpu::{
+    Buffer, BufferDescriptor, BufferUsages, CommandEncoder, ComputePassDescriptor,
+    ComputePassTimestampWrites, Features, QuerySet, QuerySetDescriptor, QueryType,
+};
+
+use crate::gpu::{
+    context::GpuContext,
+    readback::{read_mapped_buffer_to_vec, try_read_mapped_buffer_to_vec},
+};
+
+/// Features for the preferred [`TimestampMode::Encoder`] of [`GpuTimer`].
+///
+/// - TIMESTAMP_QUERY: timestamp query sets
+/// - TIMESTAMP_QUERY_INSIDE_ENCODERS: `CommandEncoder::write_timestamp` between passes
+///
+/// With TIMESTAMP_QUERY alone the timer falls back to [`TimestampMode::PassBoundary`].
+pub const GPU_TIMER_FEATURES: Features =
+    Features::TIMESTAMP_QUERY.union(Features::TIMESTAMP_QUERY_INSIDE_ENCODERS);
+
+/// How a [`GpuTimer`] records a timestamp between two passes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampMode {
+    /// `CommandEncoder::write_timestamp` (needs [`GPU_TIMER_FEATURES`]).
+    Encoder,
+    /// An empty compute pass whose `timestamp_writes` records its start; needs only
+    /// TIMESTAMP_QUERY, which every timestamp-capable backend has.
+    ///
+    /// Coarser than `Encoder`: the value is taken at a pass boundary, which some backends
+    /// place before the previous pass has fully drained, so short sections can come out
+    /// too small (down to 0) and time shifts into the next one. Section sums over a solve
+    /// stay meaningful; single sub-millisecond sections are not. Each timestamp also
+    /// adds an (empty) pass.
+    PassBoundary,
+}
+
+impl TimestampMode {
+    /// Device features the mode needs.
+    pub fn required_features(self) -> Features {
+        match self {
+            TimestampMode::Encoder => GPU_TIMER_FEATURES,
+            TimestampMode::PassBoundary => Features::TIMESTAMP_QUERY,
+        }
+    }
+
+    /// The finest mode `ctx` supports, if any.
+    pub fn detect(ctx: &GpuContext) -> Option<Self> {
+        [TimestampMode::Encoder, TimestampMode::PassBoundary]
+            .into_iter()
+            .find(|mode| ctx.features.contains(mode.required_features()))
+    }
+}
+
+/// GpuTimer
+///
+/// Small helper around a timestamp query set:
+///   - `write_timestamp(encoder)` records the GPU clock at that point of the encoder
+///     and returns the query index it used
+///   - `encode_resolve(encoder)` resolves all written queries into a mappable buffer
+///   - after submit, `read_ticks(ctx)` maps and returns the raw ticks
+///
+/// Ticks are converted to time with the queue's timestamp period, unless it is
+/// overridden with `set_timestamp_period`. Timestamps go through the encoder when the
+/// device allows it and through pass boundaries otherwise (see [`TimestampMode`]).
+///
+/// Queries are handed out in order from a cursor; call `reset()` before reusing the
+/// timer for a new submit.
+pub struct GpuTimer {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    readback_buffer: Buffer,
+    capacity: u32,
+    cursor: Cell<u32>,
+    mode: TimestampMode,
+
+    // Nanoseconds per tick (Queue::get_timestamp_period, or the override)
+    timestamp_period_ns: f32,
+}
+
+impl GpuTimer {
+    /// Whether `ctx` was created with the features for any [`TimestampMode`].
+    pub fn is_supported(ctx: &GpuContext) -> bool {
+        TimestampMode::detect(ctx).is_some()
+    }
+
+    /// Create a timer able to hold `capacity` timestamps per submit, in the finest mode
+    /// the device supports.
+    ///
+    /// Returns `None` when the device lacks timestamp support (no TIMESTAMP_QUERY).
+    pub fn create(ctx: &GpuContext, capacity: u32) -> Option<Self> {
+        Self::create_with_mode(ctx, capacity, TimestampMode::detect(ctx)?)
+    }
+
+    /// [`GpuTimer::create`] in a given mode; `None` when the device does not support it.
+    pub fn create_with_mode(ctx: &GpuContext, capacity: u32, mode: TimestampMode) -> Option<Self> {
+        if !ctx.features.contains(mode.required_features()) || capacity == 0 {
+            return None;
+        }
+
+        let device = &ctx.device;
+
+        let query_set = device.create_query_set(&QuerySetDescriptor {
+            label: Some(&ctx.label("gpu timer query set")),
+            ty: QueryType::Timestamp,
+            count: capacity,
+        });
+
+        let bytes = (capacity as u64) * 8;
+
+        let resolve_buffer = ctx.create_buffer(&BufferDescriptor {
+            label: Some(&ctx.label("gpu timer resolve")),
+            size: bytes,
+            usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let readback_buffer = ctx.create_buffer(&BufferDescriptor {
+            label: Some(&ctx.label("gpu timer readback")),
+            size: bytes,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            capacity,
+            cursor: Cell::new(0),
+            mode,
+            timestamp_period_ns: ctx.queue.get_timestamp_period(),
+        })
+    }
+
+    pub fn mode(&self) -> TimestampMode {
+        self.mode
+    }
+
+    pub fn reset(&self) {
+        self.cursor.set(0);
+    }
+
+    /// Number of timestamps written since the last `reset()`.
+    pub fn len(&self) -> u32 {
+        self.cursor.get()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cursor.get() == 0
+    }
+
+    /// Record a timestamp between passes. Returns the query index used.
+    pub fn write_timestamp(&self, encoder: &mut CommandEncoder) -> u32 {
+        let i = self.cursor.get();
+        if i >= self.capacity {
+            panic!("GpuTimer: capacity ({}) exceeded", self.capacity);
+        }
+        match self.mode {
+            TimestampMode::Encoder => encoder.write_timestamp(&self.query_set, i),
+            TimestampMode::PassBoundary => {
+                let _pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("gpu timer timestamp"),
+                    timestamp_writes: Some(ComputePassTimestampWrites {
+                        query_set: &self.query_set,
+                        beginning_of_pass_write_index: Some(i),
+                        end_of_pass_write_index: None,
+                    }),
+                });
+            }
+        }
+        self.cursor.set(i + 1);
+        i
+    }
+
+    /// Resolve the written queries and copy them into the mappable readback buffer.
+    /// Call once per submit, after the last `write_timestamp`.
+    pub fn encode_resolve(&self, encoder: &mut CommandEncoder) {
+        let count = self.cursor.get();
+        if count == 0 {
+            return;
+        }
+        encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            (count as u64) * 8,
+        );
+    }
+
+    /// After submit, map and return the raw ticks of the written queries.
+    pub async fn read_ticks(&self, ctx: &GpuContext) -> Vec<u64> {
+        let count = self.cursor.get() as usize;
+        let mut ticks = read_mapped_buffer_to_vec::<u64>(
+            &ctx.device,
+            &self.readback_buffer,
+            self.capacity as usize,
+        )
+        .await;
+        ticks.truncate(count);
+        ticks
+    }
+
+    /// Nanoseconds per tick used by `ticks_to_ms`.
+    pub fn timestamp_period(&self) -> f32 {
+        self.timestamp_period_ns
+    }
+
+    /// Replace the queue's reported period (nanoseconds per tick).
+    ///
+    /// Some drivers report a wrong period, which scales every GPU timing by the same
+    /// wrong factor (a solve whose GPU buckets add up to far more or less than its
+    /// wall-clock total is the usual symptom). Measure the real tick rate, e.g. against
+    /// a known-length workload, and set it here. Fails unless `ns_per_tick` is finite
+    /// and > 0.
+    pub fn set_timestamp_period(&mut self, ns_per_tick: f32) -> Result<(), String> {
+        if !(ns_per_tick.is_finite() && ns_per_tick > 0.0) {
+            return Err(format!(
+                "GpuTimer: timestamp period must be a positive number of ns, got {ns_per_tick}"
+            ));
+        }
+        self.timestamp_period_ns = ns_per_tick;
+        Ok(())
+    }
+
+    /// Elapsed milliseconds between two raw timestamps.
+    pub fn ticks_to_ms(&self, start: u64, end: u64) -> f64 {
+        (end.saturating_sub(start) as f64) * (self.timestamp_period_ns as f64) * 1e-6
+    }
+}
+
+/// GPU time of one timed compute pass, see [`PassTimer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PassTiming {
+    /// The pass label (with the context's label prefix).
+    pub label: String,
+    pub elapsed_ns: u64,
+}
+
+/// PassTimer
+///
+/// Per-pass kernel timings through `ComputePassDescriptor::timestamp_writes`: every pass
+/// begun with [`GpuContext::begin_compute_pass`] while the context's pass timer is
+/// enabled (`GpuContext::enable_pass_timing`) gets a begin/end timestamp pair. Only
+/// TIMESTAMP_QUERY is needed, and the pair brackets the pass itself, so unlike
+/// [`GpuTimer`] there is no pass-boundary approximation.
+///
+/// Per submit:
+///   - encode the passes (each takes two queries; once `max_passes` are taken, further
+///     passes run untimed and are counted in `dropped()`)
+///   - `encode_resolve(encoder)` in the last encoder
+///   - after submit, `read_timings(ctx)` returns the durations in pass order and starts
+///     over for the next submit
+#[derive(Debug)]
+pub struct PassTimer {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    readback_buffer: Buffer,
+    max_passes: u32,
+    // Labels of the timed passes, in query order, and the passes that did not fit.
+    state: Mutex<(Vec<String>, u32)>,
+
+    // Nanoseconds per tick (Queue::get_timestamp_period)
+    timestamp_period_ns: f32,
+}
+
+impl PassTimer {
+    /// Room for `max_passes` timed passes per submit; `None` without TIMESTAMP_QUERY.
+    pub fn create(ctx: &GpuContext, max_passes: u32) -> Option<Self> {
+        if !ctx.supports_timestamps() || max_passes == 0 {
+            return None;
+        }
+
+        let device = &ctx.device;
+        let count = max_passes * 2;
+
+        let query_set = device.create_query_set(&QuerySetDescriptor {
+            label: Some(&ctx.label("pass timer query set")),
+            ty: QueryType::Timestamp,
+            count,
+        });
+
+        let bytes = (count as u64) * 8;
+
+        let resolve_buffer = ctx.create_buffer(&BufferDescriptor {
+            label: Some(&ctx.label("pass timer resolve")),
+            size: bytes,
+            usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let readback_buffer = ctx.create_buffer(&BufferDescriptor {
+            label: Some(&ctx.label("pass timer readback")),
+            size: bytes,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            max_passes,
+            state: Mutex::new((Vec::new(), 0)),
+            timestamp_period_ns: ctx.queue.get_timestamp_period(),
+        })
+    }
+
+    /// Timestamp writes for the next pass, labelled `label`; `None` when the timer is full.
+    pub fn timestamp_writes(&self, label: &str) -> Option<ComputePassTimestampWrites<'_>> {
+        let mut state = self.state.lock().unwrap();
+        let (labels, dropped) = &mut *state;
+        let i = labels.len() as u32;
+        if i >= self.max_passes {
+            *dropped += 1;
+            return None;
+        }
+        labels.push(label.to_string());
+        Some(ComputePassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(2 * i),
+            end_of_pass_write_index: Some(2 * i + 1),
+        })
+    }
+
+    /// Passes timed since the last `read_timings` / `reset`.
+    pub fn len(&self) -> u32 {
+        self.state.lock().unwrap().0.len() as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Passes that ran untimed because all `max_passes` slots were taken.
+    pub fn dropped(&self) -> u32 {
+        self.state.lock().unwrap().1
+    }
+
+    pub fn reset(&self) {
+        *self.state.lock().unwrap() = (Vec::new(), 0);
+    }
+
+    /// Resolve the written queries and copy them into the mappable readback buffer.
+    /// Call once per submit, after the last timed pass.
+    pub fn encode_resolve(&self, encoder: &mut CommandEncoder) {
+        let count = self.len() * 2;
+        if count == 0 {
+            return;
+        }
+        encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            (count as u64) * 8,
+        );
+    }
+
+    /// After submit, the elapsed nanoseconds of every timed pass, in pass order.
+    /// Resets the timer.
+    pub async fn read_timings(&self, ctx: &GpuContext) -> Result<Vec<PassTiming>, String> {
+        let (labels, _) = std::mem::take(&mut *self.state.lock().unwrap());
+        if labels.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ticks = try_read_mapped_buffer_to_vec::<u64>(
+            &ctx.device,
+            &self.readback_buffer,
+            (self.max_passes * 2) as usize,
+        )
+        .await?;
+        Ok(labels
+            .into_iter()
+            .zip(ticks.chunks_exact(2))
+            .map(|(label, pair)| PassTiming {
+                label,
+                elapsed_ns: (pair[1].saturating_sub(pair[0]) as f64
+                    * self.timestamp_period_ns as f64)
+                    .round() as u64,
+            })
+            .collect())
+    }
+}
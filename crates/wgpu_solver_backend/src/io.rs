@@ -1,2 +1,43 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
 pub mod bin_format;
 pub mod loaders;
+pub mod matrix_market;
+pub mod npy;
+pub mod png;
+pub mod vector;
+
+/// Why a vector or matrix file could not be written or read.
+#[derive(Debug, Error)]
+pub enum IoError {
+    #[error("{what} {}: {source}", path.display())]
+    Io {
+        what: &'static str,
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The file was created but not completely written; it holds the first `written`
+    /// of `total` bytes.
+    #[error("write {}: only {written} of {total} bytes written: {source}", path.display())]
+    PartialWrite {
+        path: PathBuf,
+        written: u64,
+        total: u64,
+        source: std::io::Error,
+    },
+    #[error("{}:{line}: {message}", path.display())]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+/// For the `Result<_, String>` call sites.
+impl From<IoError> for String {
+    fn from(e: IoError) -> String {
+        e.to_string()
+    }
+}
@@ -5,8 +5,35 @@ use super::bin_format::{BlockStartsBin, CaseInputBin, CsrMatrixBin, VectorBin};
 
 const MATRIX_MAGIC_CSR1: u32 = 0x4353_5231;
 
+/// How `load_csr_matrix_bin_with_options` post-processes a matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsrLoadOptions {
+    /// Sort column indices within each row (see `Csr::sort_rows`) when the file is not
+    /// sorted already. On by default; turn it off to get the file's entry order as is.
+    pub sort_rows: bool,
+    /// Fail on a row that stores the same column twice. Off by default, in which case
+    /// duplicates are summed into one entry (see `Csr::sum_duplicates`).
+    pub strict: bool,
+}
+
+impl Default for CsrLoadOptions {
+    fn default() -> Self {
+        Self {
+            sort_rows: true,
+            strict: false,
+        }
+    }
+}
+
 pub fn load_case_dir(case_dir: &Path) -> Result<CaseInputBin, String> {
-    let a = load_csr_matrix_bin(&case_dir.join("matrix.csr.bin"))?;
+    load_case_dir_with_options(case_dir, &CsrLoadOptions::default())
+}
+
+pub fn load_case_dir_with_options(
+    case_dir: &Path,
+    options: &CsrLoadOptions,
+) -> Result<CaseInputBin, String> {
+    let a = load_csr_matrix_bin_with_options(&case_dir.join("matrix.csr.bin"), options)?;
     let b = load_vector_bin(&case_dir.join("rhs.bin"))?;
     let x0 = load_vector_bin(&case_dir.join("x0.bin"))?;
     let block_starts = load_block_starts_bin(&case_dir.join("block_starts.bin"))?;
@@ -41,7 +68,15 @@ pub fn load_case_dir(case_dir: &Path) -> Result<CaseInputBin, String> {
     })
 }
 
+/// Load `matrix.csr.bin` with the default [`CsrLoadOptions`] (rows sorted, duplicates summed).
 pub fn load_csr_matrix_bin(path: &Path) -> Result<CsrMatrixBin, String> {
+    load_csr_matrix_bin_with_options(path, &CsrLoadOptions::default())
+}
+
+pub fn load_csr_matrix_bin_with_options(
+    path: &Path,
+    options: &CsrLoadOptions,
+) -> Result<CsrMatrixBin, String> {
     let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
     let mut cur = Cursor::new(&bytes);
 
@@ -113,14 +148,28 @@ pub fn load_csr_matrix_bin(path: &Path) -> Result<CsrMatrixBin, String> {
         ));
     }
 
-    Ok(CsrMatrixBin {
+    let mut csr = CsrMatrixBin {
         n_rows,
         n_cols,
         nnz,
         row_ptr,
         col_idx,
         values,
-    })
+    };
+    if let Some((row, col)) = csr.find_duplicate_col() {
+        if options.strict {
+            return Err(format!(
+                "CSR row {row} of {} stores column {col} more than once",
+                path.display()
+            ));
+        }
+        csr.sum_duplicates();
+    }
+    if options.sort_rows && !csr.is_sorted() {
+        csr.sort_rows();
+    }
+
+    Ok(csr)
 }
 
 pub fn load_vector_bin(path: &Path) -> Result<VectorBin, String> {
@@ -0,0 +1,222 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use super::IoError;
+use crate::matrix::{Csr, CsrBuilder};
+
+// MatrixMarket coordinate format (.mtx), the subset used for real sparse matrices:
+//
+//   %%MatrixMarket matrix coordinate <field> <symmetry>
+//   % comments
+//   M N NNZ
+//   i j [value]        (NNZ lines, 1-based)
+//
+// field:    real | integer (read as f32) | pattern (no value column, every entry is 1)
+// symmetry: general | symmetric (only one triangle stored; mirrored on read)
+//
+// Not supported: array (dense) files, complex / hermitian / skew-symmetric matrices.
+
+/// A sparse matrix as (row, col, value) triplets, 0-based, in file order.
+///
+/// Symmetric files are already expanded: every stored off-diagonal entry appears twice.
+#[derive(Debug, Clone)]
+pub struct CooMatrix {
+    pub n_rows: u32,
+    pub n_cols: u32,
+    pub rows: Vec<u32>,
+    pub cols: Vec<u32>,
+    pub values: Vec<f32>,
+}
+
+impl CooMatrix {
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// The CSR layout `SpmvExecutor` takes: rows sorted by column, duplicate entries
+    /// summed into one (see [`CsrBuilder`]).
+    pub fn to_csr(&self) -> Csr {
+        let mut builder = CsrBuilder::new().with_shape(self.n_rows, self.n_cols);
+        for ((&row, &col), &value) in self.rows.iter().zip(&self.cols).zip(&self.values) {
+            builder.push(row, col, value);
+        }
+        builder.build()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Real,
+    Pattern,
+}
+
+/// Read a MatrixMarket coordinate file (see the module comment for the accepted header).
+///
+/// Errors name the file and line: unsupported headers, a malformed size line, indices
+/// outside 1..=M / 1..=N, and an entry count different from the NNZ of the size line.
+/// Symmetric files may store either triangle; each off-diagonal entry is mirrored.
+pub fn read_coo(path: &Path) -> Result<CooMatrix, IoError> {
+    let io_err = |what, source| IoError::Io {
+        what,
+        path: path.to_path_buf(),
+        source,
+    };
+    let file = File::open(path).map_err(|e| io_err("open", e))?;
+    let mut lines = BufReader::new(file).lines().enumerate();
+    let mut next = || -> Result<Option<(usize, String)>, IoError> {
+        match lines.next() {
+            None => Ok(None),
+            Some((i, line)) => line
+                .map(|l| Some((i + 1, l)))
+                .map_err(|e| io_err("read", e)),
+        }
+    };
+    let parse_err = |line: usize, message: String| IoError::Parse {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+
+    // Header
+    let Some((_, header)) = next()? else {
+        return Err(parse_err(1, "empty file".into()));
+    };
+    let words: Vec<String> = header.split_whitespace().map(str::to_lowercase).collect();
+    let (field, symmetric) = match words.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["%%matrixmarket", "matrix", "coordinate", field, symmetry] => {
+            let field = match field {
+                "real" | "integer" => Field::Real,
+                "pattern" => Field::Pattern,
+                other => {
+                    return Err(parse_err(1, format!("unsupported field '{other}'")));
+                }
+            };
+            let symmetric = match symmetry {
+                "general" => false,
+                "symmetric" => true,
+                other => {
+                    return Err(parse_err(1, format!("unsupported symmetry '{other}'")));
+                }
+            };
+            (field, symmetric)
+        }
+        _ => {
+            return Err(parse_err(
+                1,
+                format!(
+                    "expected '%%MatrixMarket matrix coordinate <real|integer|pattern> <general|symmetric>', got '{}'",
+                    header.trim()
+                ),
+            ));
+        }
+    };
+
+    // Size line (after comments / blank lines)
+    let (size_line_no, size_line) = loop {
+        match next()? {
+            None => return Err(parse_err(1, "missing size line".into())),
+            Some((_, l)) if l.trim().is_empty() || l.trim_start().starts_with('%') => {}
+            Some(entry) => break entry,
+        }
+    };
+    let size: Vec<u64> = size_line
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .map_err(|_| {
+            parse_err(
+                size_line_no,
+                format!("invalid size line '{}'", size_line.trim()),
+            )
+        })?;
+    let [n_rows, n_cols, nnz] = size[..] else {
+        return Err(parse_err(
+            size_line_no,
+            format!("size line needs 'M N NNZ', got '{}'", size_line.trim()),
+        ));
+    };
+    if n_rows >= u32::MAX as u64 || n_cols >= u32::MAX as u64 || nnz >= u32::MAX as u64 {
+        return Err(parse_err(
+            size_line_no,
+            "matrix too large for u32 indices".into(),
+        ));
+    }
+    if symmetric && n_rows != n_cols {
+        return Err(parse_err(
+            size_line_no,
+            format!("symmetric matrix must be square, got {n_rows}x{n_cols}"),
+        ));
+    }
+
+    // Entries
+    let capacity = if symmetric { 2 * nnz } else { nnz } as usize;
+    let mut coo = CooMatrix {
+        n_rows: n_rows as u32,
+        n_cols: n_cols as u32,
+        rows: Vec::with_capacity(capacity),
+        cols: Vec::with_capacity(capacity),
+        values: Vec::with_capacity(capacity),
+    };
+    let mut read = 0u64;
+    while let Some((line_no, line)) = next()? {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('%') {
+            continue;
+        }
+        read += 1;
+        if read > nnz {
+            return Err(parse_err(
+                line_no,
+                format!("more entries than the {nnz} of the size line"),
+            ));
+        }
+
+        let fields: Vec<&str> = trimmed.split_whitespace().collect();
+        let expected = if field == Field::Pattern { 2 } else { 3 };
+        if fields.len() != expected {
+            return Err(parse_err(
+                line_no,
+                format!("expected {expected} fields, got {}", fields.len()),
+            ));
+        }
+        let index = |field: &str, what: &str, dim: u64| -> Result<u32, IoError> {
+            match field.parse::<u64>() {
+                Ok(i) if (1..=dim).contains(&i) => Ok((i - 1) as u32),
+                Ok(i) => Err(parse_err(
+                    line_no,
+                    format!("{what} index {i} outside 1..={dim}"),
+                )),
+                Err(_) => Err(parse_err(
+                    line_no,
+                    format!("invalid {what} index '{field}'"),
+                )),
+            }
+        };
+        let row = index(fields[0], "row", n_rows)?;
+        let col = index(fields[1], "column", n_cols)?;
+        let value = match field {
+            Field::Pattern => 1.0,
+            Field::Real => fields[2]
+                .parse::<f32>()
+                .map_err(|_| parse_err(line_no, format!("invalid value '{}'", fields[2])))?,
+        };
+
+        coo.rows.push(row);
+        coo.cols.push(col);
+        coo.values.push(value);
+        if symmetric && row != col {
+            coo.rows.push(col);
+            coo.cols.push(row);
+            coo.values.push(value);
+        }
+    }
+
+    if read != nnz {
+        return Err(parse_err(
+            size_line_no,
+            format!("size line declares {nnz} entries but the file has {read}"),
+        ));
+    }
+    Ok(coo)
+}
@@ -0,0 +1,216 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// Minimal NumPy .npy support (format version 1.0, little-endian f32, C order).
+//
+// Layout:
+//   "\x93NUMPY" | major=1 | minor=0 | u16 header_len | header | data
+// where header is a Python dict literal padded with spaces and terminated by '\n'
+// so that the data starts at a multiple of 64 bytes.
+
+const NPY_MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// Write a 1D f32 array as `.npy` (dtype `<f4`).
+pub fn write_npy_f32(path: &Path, values: &[f32]) -> Result<(), String> {
+    write_npy_f32_shape(path, &[values.len()], values)
+}
+
+/// Write an f32 array with the given C-order shape as `.npy` (dtype `<f4`).
+pub fn write_npy_f32_shape(path: &Path, shape: &[usize], values: &[f32]) -> Result<(), String> {
+    let expected: usize = shape.iter().product();
+    if expected != values.len() {
+        return Err(format!(
+            "write_npy_f32 {}: shape {:?} needs {} values, got {}",
+            path.display(),
+            shape,
+            expected,
+            values.len()
+        ));
+    }
+
+    let shape_str = match shape {
+        [n] => format!("({n},)"),
+        dims => format!(
+            "({})",
+            dims.iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!("{{'descr': '<f4', 'fortran_order': False, 'shape': {shape_str}, }}");
+
+    // magic(6) + version(2) + header_len(2) + header + '\n' must be a multiple of 64.
+    let unpadded = NPY_MAGIC.len() + 2 + 2 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+
+    let file = File::create(path).map_err(|e| format!("create {}: {e}", path.display()))?;
+    let mut w = BufWriter::new(file);
+
+    let write_err = |e: std::io::Error| format!("write {}: {e}", path.display());
+    w.write_all(NPY_MAGIC).map_err(write_err)?;
+    w.write_all(&[1u8, 0u8]).map_err(write_err)?;
+    w.write_all(&(header.len() as u16).to_le_bytes())
+        .map_err(write_err)?;
+    w.write_all(header.as_bytes()).map_err(write_err)?;
+    // '<f4' is little-endian whatever the host: not the in-memory bytes.
+    for v in values {
+        w.write_all(&v.to_le_bytes()).map_err(write_err)?;
+    }
+    w.flush().map_err(write_err)?;
+
+    Ok(())
+}
+
+/// Read a `.npy` file holding little-endian f32 data in C order.
+///
+/// Returns (shape, values).
+pub fn read_npy_f32(path: &Path) -> Result<(Vec<usize>, Vec<f32>), String> {
+    let bytes = fs::read(path).map_err(|e| format!("read {}: {e}", path.display()))?;
+    let err = |msg: &str| format!("read_npy_f32 {}: {msg}", path.display());
+
+    if bytes.len() < 10 || &bytes[0..6] != NPY_MAGIC {
+        return Err(err("not a .npy file (bad magic)"));
+    }
+
+    let major = bytes[6];
+    let (header_len, header_start) = match major {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10usize),
+        2 | 3 => {
+            if bytes.len() < 12 {
+                return Err(err("truncated header"));
+            }
+            (
+                u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize,
+                12usize,
+            )
+        }
+        v => return Err(err(&format!("unsupported format version {v}"))),
+    };
+
+    let data_start = header_start + header_len;
+    if bytes.len() < data_start {
+        return Err(err("truncated header"));
+    }
+    let header = std::str::from_utf8(&bytes[header_start..data_start])
+        .map_err(|_| err("header is not valid UTF-8"))?;
+
+    let descr = header_value(header, "descr").ok_or_else(|| err("missing 'descr'"))?;
+    if descr.trim_matches(|c| c == '\'' || c == '"') != "<f4" {
+        return Err(err(&format!("unsupported dtype {descr} (expected '<f4')")));
+    }
+
+    let fortran =
+        header_value(header, "fortran_order").ok_or_else(|| err("missing 'fortran_order'"))?;
+    if fortran != "False" {
+        return Err(err("fortran_order arrays are not supported"));
+    }
+
+    let shape_str = header_value(header, "shape").ok_or_else(|| err("missing 'shape'"))?;
+    let shape: Vec<usize> = shape_str
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            s.parse::<usize>()
+                .map_err(|_| err(&format!("bad shape {shape_str}")))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let count: usize = shape.iter().product();
+    let data = &bytes[data_start..];
+    if data.len() != count * 4 {
+        return Err(err(&format!(
+            "shape {:?} needs {} bytes of data, file has {}",
+            shape,
+            count * 4,
+            data.len()
+        )));
+    }
+
+    let values = data
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+
+    Ok((shape, values))
+}
+
+/// Read a 1D (n,) or 2D (n, k) f32 `.npy` as k column vectors of length n.
+///
+/// Used for multi-RHS files: each column is one right-hand side. The row count must
+/// equal `n_rows`.
+pub fn read_npy_f32_columns(path: &Path, n_rows: usize) -> Result<Vec<Vec<f32>>, String> {
+    let (shape, values) = read_npy_f32(path)?;
+
+    let (rows, cols) = match shape.as_slice() {
+        [rows] => (*rows, 1),
+        [rows, cols] => (*rows, *cols),
+        _ => {
+            return Err(format!(
+                "read_npy_f32_columns {}: expected a 1D or 2D array, got shape {:?}",
+                path.display(),
+                shape
+            ));
+        }
+    };
+
+    if rows != n_rows {
+        return Err(format!(
+            "read_npy_f32_columns {}: {} rows, system has n = {}",
+            path.display(),
+            rows,
+            n_rows
+        ));
+    }
+
+    // C order: element (i, j) at i * cols + j
+    Ok((0..cols)
+        .map(|j| (0..rows).map(|i| values[i * cols + j]).collect())
+        .collect())
+}
+
+/// Write k equally long column vectors as one (n, k) f32 `.npy` (C order).
+pub fn write_npy_f32_columns(path: &Path, columns: &[Vec<f32>]) -> Result<(), String> {
+    let rows = columns.first().map_or(0, Vec::len);
+    if let Some(bad) = columns.iter().find(|c| c.len() != rows) {
+        return Err(format!(
+            "write_npy_f32_columns {}: column lengths differ ({} vs {})",
+            path.display(),
+            rows,
+            bad.len()
+        ));
+    }
+
+    let cols = columns.len();
+    let mut values = vec![0.0f32; rows * cols];
+    for (j, column) in columns.iter().enumerate() {
+        for (i, v) in column.iter().enumerate() {
+            values[i * cols + j] = *v;
+        }
+    }
+
+    write_npy_f32_shape(path, &[rows, cols], &values)
+}
+
+/// Extract the raw value text for `key` from the header dict literal.
+fn header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let key_pos = header
+        .find(&format!("'{key}'"))
+        .or_else(|| header.find(&format!("\"{key}\"")))?;
+    let rest = &header[key_pos + key.len() + 2..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+
+    // Tuples contain commas, so they end at the closing parenthesis.
+    let end = if rest.starts_with('(') {
+        rest.find(')')? + 1
+    } else {
+        rest.find([',', '}'])?
+    };
+
+    Some(rest[..end].trim())
+}
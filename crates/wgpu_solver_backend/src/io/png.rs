@@ -0,0 +1,170 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// Minimal grayscale PNG writer (8-bit, one channel, no interlacing).
+//
+// Layout:
+//   signature | IHDR | IDAT | IEND
+// where every chunk is u32 BE length | type | data | CRC-32 of type + data.
+// IDAT holds a zlib stream of *stored* (uncompressed) deflate blocks, one filter byte
+// (0 = none) before each row. Files are therefore about width * height bytes; that is
+// fine for teaching-sized grids and keeps the writer dependency-free.
+
+const PNG_SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+// Largest payload of one stored deflate block.
+const STORED_BLOCK_MAX: usize = 65535;
+
+/// Map `values` linearly onto 0..=255: the smallest finite value becomes 0 (black), the
+/// largest 255 (white), rounding to the nearest level.
+///
+/// Non-finite entries are left out of the range and drawn as 0. When every finite value
+/// is equal (e.g. x = 0) there is no range to stretch and the whole image is 0.
+pub fn normalize_to_gray8(values: &[f32]) -> Vec<u8> {
+    let (lo, hi) = values
+        .iter()
+        .filter(|v| v.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        });
+    let range = hi as f64 - lo as f64;
+
+    values
+        .iter()
+        .map(|&v| {
+            if !v.is_finite() || range <= 0.0 {
+                0
+            } else {
+                ((v as f64 - lo as f64) / range * 255.0).round() as u8
+            }
+        })
+        .collect()
+}
+
+/// Write `values` of a `width` x `height` grid as a normalized grayscale PNG (see
+/// `normalize_to_gray8`).
+///
+/// Entry `x + width * y` becomes pixel (x, y), with y = 0 the top row: the ordering of
+/// `matrix::laplacian_2d(width, height)`.
+pub fn write_grid_png(
+    path: &Path,
+    values: &[f32],
+    width: usize,
+    height: usize,
+) -> Result<(), String> {
+    if width.checked_mul(height) != Some(values.len()) {
+        return Err(format!(
+            "write_grid_png {}: a {width}x{height} grid needs {} values, got {}",
+            path.display(),
+            width.saturating_mul(height),
+            values.len()
+        ));
+    }
+    write_png_gray8(path, width, height, &normalize_to_gray8(values))
+}
+
+/// Write 8-bit grayscale `pixels` (row-major, top row first) as a PNG.
+pub fn write_png_gray8(
+    path: &Path,
+    width: usize,
+    height: usize,
+    pixels: &[u8],
+) -> Result<(), String> {
+    let err = |msg: String| format!("write_png_gray8 {}: {msg}", path.display());
+    if width == 0 || height == 0 || width > i32::MAX as usize || height > i32::MAX as usize {
+        return Err(err(format!("invalid image size {width}x{height}")));
+    }
+    if width * height != pixels.len() {
+        return Err(err(format!(
+            "{width}x{height} needs {} pixels, got {}",
+            width * height,
+            pixels.len()
+        )));
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+    // bit depth 8, color type 0 (grayscale), compression 0, filter 0, no interlace
+    ihdr.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+    let mut raw = Vec::with_capacity((width + 1) * height);
+    for row in pixels.chunks_exact(width) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let file = File::create(path).map_err(|e| format!("create {}: {e}", path.display()))?;
+    let mut w = BufWriter::new(file);
+
+    let write_err = |e: std::io::Error| format!("write {}: {e}", path.display());
+    w.write_all(PNG_SIGNATURE).map_err(write_err)?;
+    write_chunk(&mut w, b"IHDR", &ihdr).map_err(write_err)?;
+    write_chunk(&mut w, b"IDAT", &zlib_stored(&raw)).map_err(write_err)?;
+    write_chunk(&mut w, b"IEND", &[]).map_err(write_err)?;
+    w.flush().map_err(write_err)?;
+
+    Ok(())
+}
+
+fn write_chunk(w: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> std::io::Result<()> {
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+    let crc = crc32_update(crc32_update(0xFFFF_FFFF, kind), data) ^ 0xFFFF_FFFF;
+    w.write_all(&crc.to_be_bytes())
+}
+
+/// zlib stream (RFC 1950) holding `data` in stored deflate blocks (RFC 1951, BTYPE = 00).
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(STORED_BLOCK_MAX).max(1);
+    let mut out = Vec::with_capacity(2 + data.len() + 5 * blocks + 4);
+
+    // CMF = deflate with a 32K window, FLG = no dictionary, fastest level; (CMF, FLG)
+    // is a multiple of 31 as required.
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    let mut chunks = data.chunks(STORED_BLOCK_MAX).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let is_final = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+        out.push(is_final as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// CRC-32 (ISO-HDLC polynomial, reflected) over `data`, continuing from `crc`.
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the largest run that cannot overflow u32 before the modulo.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
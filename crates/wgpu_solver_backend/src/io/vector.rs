@@ -0,0 +1,168 @@
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+use super::IoError;
+
+// Dense f32 vectors as text, for solutions and right-hand sides exchanged with other tools:
+//
+//   PlainText          one value per line
+//   MatrixMarketArray  %%MatrixMarket matrix array real general
+//                      n 1
+//                      one value per line (column-major, i.e. in order)
+//
+// Finite values are written in Rust's shortest round-trip form ("0.1", "-3.5e-12");
+// non-finite ones as "nan", "inf" and "-inf", which Rust's and Python's float parsers
+// (numpy.loadtxt, scipy.io.mmread) read back. NaN payloads and signs are not kept.
+
+/// Text layout of [`write_f32`] / [`read_f32`], see the module comment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorFormat {
+    PlainText,
+    MatrixMarketArray,
+}
+
+impl VectorFormat {
+    /// `MatrixMarketArray` for a `.mtx` extension (any case), `PlainText` otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("mtx") => VectorFormat::MatrixMarketArray,
+            _ => VectorFormat::PlainText,
+        }
+    }
+}
+
+fn format_value(out: &mut String, value: f32) {
+    use std::fmt::Write as _;
+    if value.is_nan() {
+        out.push_str("nan");
+    } else if value.is_infinite() {
+        out.push_str(if value > 0.0 { "inf" } else { "-inf" });
+    } else {
+        let _ = write!(out, "{value:?}");
+    }
+    out.push('\n');
+}
+
+/// Write `values` to `path` as `format`, replacing the file.
+///
+/// The text is formatted in memory and then written; a write that stops early gives
+/// [`IoError::PartialWrite`] with the bytes that made it to the file.
+pub fn write_f32(path: &Path, values: &[f32], format: VectorFormat) -> Result<(), IoError> {
+    let mut text = String::with_capacity(values.len() * 12 + 64);
+    if format == VectorFormat::MatrixMarketArray {
+        text.push_str("%%MatrixMarket matrix array real general\n");
+        text.push_str(&format!("{} 1\n", values.len()));
+    }
+    for &value in values {
+        format_value(&mut text, value);
+    }
+
+    let mut file = File::create(path).map_err(|source| IoError::Io {
+        what: "create",
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let bytes = text.as_bytes();
+    let mut written = 0;
+    let partial = |written: usize, source| IoError::PartialWrite {
+        path: path.to_path_buf(),
+        written: written as u64,
+        total: bytes.len() as u64,
+        source,
+    };
+    while written < bytes.len() {
+        match file.write(&bytes[written..]) {
+            Ok(0) => return Err(partial(written, ErrorKind::WriteZero.into())),
+            Ok(count) => written += count,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(partial(written, e)),
+        }
+    }
+    file.flush().map_err(|e| partial(written, e))
+}
+
+/// Read a vector written by [`write_f32`], or by another tool in the same format.
+///
+/// `PlainText` is lenient: values may also be separated by commas or whitespace on one
+/// line, and blank lines and lines starting with `#` or `%` are skipped.
+/// `MatrixMarketArray` needs the `array real|integer general` header and an `M 1` or
+/// `1 N` size line, followed by exactly M * N values. Errors name the file and line.
+pub fn read_f32(path: &Path, format: VectorFormat) -> Result<Vec<f32>, IoError> {
+    let text = fs::read_to_string(path).map_err(|source| IoError::Io {
+        what: "read",
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let parse_err = |line: usize, message: String| IoError::Parse {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()));
+
+    let mut expected = None;
+    if format == VectorFormat::MatrixMarketArray {
+        let header = lines.next().map_or("", |(_, line)| line);
+        let words: Vec<String> = header.split_whitespace().map(str::to_lowercase).collect();
+        match words.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            [
+                "%%matrixmarket",
+                "matrix",
+                "array",
+                "real" | "integer",
+                "general",
+            ] => {}
+            _ => {
+                return Err(parse_err(
+                    1,
+                    format!(
+                        "expected '%%MatrixMarket matrix array <real|integer> general', got '{header}'"
+                    ),
+                ));
+            }
+        }
+        let (line_no, size_line) = lines
+            .by_ref()
+            .find(|(_, line)| !line.is_empty() && !line.starts_with('%'))
+            .ok_or_else(|| parse_err(1, "missing size line".into()))?;
+        let size: Result<Vec<usize>, _> = size_line.split_whitespace().map(str::parse).collect();
+        match size.as_deref() {
+            Ok(&[m, 1]) | Ok(&[1, m]) => expected = Some((line_no, m)),
+            _ => {
+                return Err(parse_err(
+                    line_no,
+                    format!("size line needs 'M 1' or '1 N' for a vector, got '{size_line}'"),
+                ));
+            }
+        }
+    }
+
+    let mut values = Vec::with_capacity(expected.map_or(0, |(_, m)| m));
+    for (line_no, line) in lines {
+        if line.is_empty() || line.starts_with('#') || line.starts_with('%') {
+            continue;
+        }
+        for field in line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|f| !f.is_empty())
+        {
+            let value: f32 = field
+                .parse()
+                .map_err(|_| parse_err(line_no, format!("invalid value '{field}'")))?;
+            values.push(value);
+        }
+    }
+    if let Some((line_no, m)) = expected
+        && values.len() != m
+    {
+        return Err(parse_err(
+            line_no,
+            format!("size line declares {m} values, file has {}", values.len()),
+        ));
+    }
+    Ok(values)
+}
@@ -1,3 +1,8 @@
 pub mod gpu;
 pub mod compute;
 pub mod io;
+pub mod matrix;
+pub mod reference;
+pub mod device;
+pub mod solver;
+pub mod util;
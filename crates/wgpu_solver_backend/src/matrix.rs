@@ -0,0 +1,487 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::io::bin_format::CsrMatrixBin;
+
+/// CSR matrix as used throughout the crate (same layout as `matrix.csr.bin`).
+///
+/// Column indices are expected to be sorted (non-decreasing) within each row. SpMV and
+/// the diagonal-block gathers scan whole rows and do not care about the order, but
+/// row-wise merges and searches do, so the loaders sort rows by default
+/// (`CsrLoadOptions::sort_rows`); matrices built in memory can use [`Csr::sort_rows`].
+///
+/// A column should appear at most once per row. SpMV sums repeated (row, col) entries,
+/// but nnz counts, sparsity patterns and row-wise merges would see them separately, so
+/// the loaders and [`CsrBuilder`] sum duplicates by default (or reject them in strict
+/// mode); hand-built matrices can be checked with [`Csr::has_duplicate_cols`] and
+/// canonicalized with [`Csr::sum_duplicates`].
+pub type Csr = CsrMatrixBin;
+
+impl Csr {
+    /// Whether col_idx is non-decreasing within every row (duplicates are allowed).
+    pub fn is_sorted(&self) -> bool {
+        self.row_ptr
+            .windows(2)
+            .all(|w| self.col_idx[w[0] as usize..w[1] as usize].is_sorted())
+    }
+
+    /// Sort col_idx within each row, moving values along with their columns.
+    ///
+    /// The sort is stable, so duplicate entries of a row keep their relative order. Rows
+    /// that are already sorted are left untouched.
+    pub fn sort_rows(&mut self) {
+        let mut entries: Vec<(u32, f32)> = Vec::new();
+        for w in self.row_ptr.windows(2) {
+            let (start, end) = (w[0] as usize, w[1] as usize);
+            if self.col_idx[start..end].is_sorted() {
+                continue;
+            }
+
+            entries.clear();
+            entries.extend((start..end).map(|k| (self.col_idx[k], self.values[k])));
+            entries.sort_by_key(|&(col, _)| col);
+            for (k, &(col, value)) in (start..end).zip(&entries) {
+                self.col_idx[k] = col;
+                self.values[k] = value;
+            }
+        }
+    }
+
+    /// Whether some row stores the same column more than once.
+    pub fn has_duplicate_cols(&self) -> bool {
+        self.find_duplicate_col().is_some()
+    }
+
+    /// The first (row, col) stored more than once, scanning rows in order.
+    pub fn find_duplicate_col(&self) -> Option<(u32, u32)> {
+        let mut cols: Vec<u32> = Vec::new();
+        for (row, w) in self.row_ptr.windows(2).enumerate() {
+            let row_cols = &self.col_idx[w[0] as usize..w[1] as usize];
+            let row_cols = if row_cols.is_sorted() {
+                row_cols
+            } else {
+                cols.clear();
+                cols.extend_from_slice(row_cols);
+                cols.sort_unstable();
+                &cols
+            };
+            if let Some(pair) = row_cols.windows(2).find(|p| p[0] == p[1]) {
+                return Some((row as u32, pair[0]));
+            }
+        }
+        None
+    }
+
+    /// Merge repeated columns of every row into one entry holding their sum.
+    ///
+    /// The merged entry takes the place of the first occurrence, so the order of the
+    /// remaining entries (sorted or not) is kept. Returns how many entries were removed.
+    pub fn sum_duplicates(&mut self) -> usize {
+        if !self.has_duplicate_cols() {
+            return 0;
+        }
+
+        let mut order: Vec<usize> = Vec::new();
+        let mut keep = vec![true; self.col_idx.len()];
+        for w in self.row_ptr.windows(2) {
+            order.clear();
+            order.extend(w[0] as usize..w[1] as usize);
+            // Stable: the first occurrence of a column leads its group.
+            order.sort_by_key(|&k| self.col_idx[k]);
+            for pair in order.chunk_by(|&a, &b| self.col_idx[a] == self.col_idx[b]) {
+                let first = pair[0];
+                for &k in &pair[1..] {
+                    self.values[first] += self.values[k];
+                    keep[k] = false;
+                }
+            }
+        }
+
+        let mut next = 0usize;
+        for row in 0..self.row_ptr.len() - 1 {
+            let (start, end) = (self.row_ptr[row] as usize, self.row_ptr[row + 1] as usize);
+            self.row_ptr[row] = next as u32;
+            for k in (start..end).filter(|&k| keep[k]) {
+                self.col_idx[next] = self.col_idx[k];
+                self.values[next] = self.values[k];
+                next += 1;
+            }
+        }
+        let removed = self.col_idx.len() - next;
+        *self.row_ptr.last_mut().unwrap() = next as u32;
+        self.col_idx.truncate(next);
+        self.values.truncate(next);
+        self.nnz = next as u32;
+        removed
+    }
+}
+
+/// Assembles a [`Csr`] from (row, col, value) entries pushed in any order.
+///
+/// The shape is the smallest one that holds every entry, or larger with
+/// [`CsrBuilder::with_shape`]. Columns come out sorted within each row, and entries
+/// pushed more than once for the same (row, col) are summed into one, unless
+/// [`CsrBuilder::strict`] is set, in which case building fails on them instead.
+#[derive(Debug, Clone, Default)]
+pub struct CsrBuilder {
+    n_rows: u32,
+    n_cols: u32,
+    strict: bool,
+    entries: Vec<(u32, u32, f32)>,
+}
+
+impl CsrBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make the matrix at least `n_rows` x `n_cols`, e.g. for trailing empty rows.
+    pub fn with_shape(mut self, n_rows: u32, n_cols: u32) -> Self {
+        self.n_rows = self.n_rows.max(n_rows);
+        self.n_cols = self.n_cols.max(n_cols);
+        self
+    }
+
+    /// Reject duplicate (row, col) entries in [`CsrBuilder::try_build`] instead of
+    /// summing them.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Add a_{row, col} += value (0-based).
+    ///
+    /// Panics when `row` or `col` is `u32::MAX` (the dimension would not fit in u32).
+    pub fn push(&mut self, row: u32, col: u32, value: f32) {
+        assert!(
+            row < u32::MAX && col < u32::MAX,
+            "CsrBuilder: index ({row}, {col}) out of range"
+        );
+        self.n_rows = self.n_rows.max(row + 1);
+        self.n_cols = self.n_cols.max(col + 1);
+        self.entries.push((row, col, value));
+    }
+
+    /// Entries pushed so far (duplicates counted).
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Panics on a duplicate entry in strict mode; see [`CsrBuilder::try_build`].
+    pub fn build(self) -> Csr {
+        self.try_build().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_build(mut self) -> Result<Csr, String> {
+        // Stable: duplicates are summed in push order.
+        self.entries.sort_by_key(|&(row, col, _)| (row, col));
+        let mut merged: Vec<(u32, u32, f32)> = Vec::with_capacity(self.entries.len());
+        for &(row, col, value) in &self.entries {
+            match merged.last_mut() {
+                Some(last) if (last.0, last.1) == (row, col) => {
+                    if self.strict {
+                        return Err(format!(
+                            "CsrBuilder: duplicate entry ({row}, {col}) in strict mode"
+                        ));
+                    }
+                    last.2 += value;
+                }
+                _ => merged.push((row, col, value)),
+            }
+        }
+        self.entries = merged;
+
+        let mut row_ptr = vec![0u32; self.n_rows as usize + 1];
+        for &(row, _, _) in &self.entries {
+            row_ptr[row as usize + 1] += 1;
+        }
+        for i in 0..self.n_rows as usize {
+            row_ptr[i + 1] += row_ptr[i];
+        }
+
+        Ok(Csr {
+            n_rows: self.n_rows,
+            n_cols: self.n_cols,
+            nnz: self.entries.len() as u32,
+            row_ptr,
+            col_idx: self.entries.iter().map(|&(_, col, _)| col).collect(),
+            values: self.entries.iter().map(|&(_, _, value)| value).collect(),
+        })
+    }
+}
+
+/// Read a matrix from a plain triplet text file, one entry per line:
+///
+/// ```text
+/// # comment
+/// row col value
+/// 0, 0, 4.0
+/// 0 1 -1e-1
+/// ```
+///
+/// - fields are separated by whitespace and/or commas; `value` is any f32 literal
+/// - `one_based` selects 1-based (Matrix Market style) or 0-based indices
+/// - blank lines and lines starting with `#` or `%` are skipped (a CSV header must be
+///   commented out)
+/// - entries may come in any order; duplicates are summed into one entry
+/// - the shape is (max row + 1) x (max col + 1), so trailing all-zero rows or columns
+///   cannot be expressed (use [`CsrBuilder::with_shape`] on your own reader for that)
+///
+/// The file is read line by line. Errors name the file and line.
+pub fn load_triplet_csv(path: &Path, one_based: bool) -> Result<Csr, String> {
+    let file = File::open(path).map_err(|e| format!("open {}: {e}", path.display()))?;
+    let mut builder = CsrBuilder::new();
+
+    for (line_no, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("read {}: {e}", path.display()))?;
+        let at = || format!("{}:{}", path.display(), line_no + 1);
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('%') {
+            continue;
+        }
+
+        let fields: Vec<&str> = trimmed
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|f| !f.is_empty())
+            .collect();
+        let [row, col, value] = fields[..] else {
+            return Err(format!(
+                "{}: expected 'row col value', got {} field(s)",
+                at(),
+                fields.len()
+            ));
+        };
+
+        let index = |field: &str, what: &str| -> Result<u32, String> {
+            let i: u64 = field
+                .parse()
+                .map_err(|_| format!("{}: invalid {what} index '{field}'", at()))?;
+            let i = if one_based {
+                i.checked_sub(1)
+                    .ok_or_else(|| format!("{}: {what} index 0 in a 1-based file", at()))?
+            } else {
+                i
+            };
+            if i >= u32::MAX as u64 {
+                return Err(format!("{}: {what} index {field} out of range", at()));
+            }
+            Ok(i as u32)
+        };
+        let value: f32 = value
+            .parse()
+            .map_err(|_| format!("{}: invalid value '{value}'", at()))?;
+
+        builder.push(index(row, "row")?, index(col, "column")?, value);
+    }
+
+    Ok(builder.build())
+}
+
+/// Gershgorin disc of every row: (center = a_ii, radius = sum_{j != i} |a_ij|).
+///
+/// Duplicate entries of a column are summed first, as SpMV would, and only then enter
+/// the radius as |a_ij| (a pair +1, -1 contributes nothing).
+pub fn gershgorin_discs(csr: &Csr) -> Vec<(f32, f32)> {
+    let n = csr.n_rows as usize;
+    let mut discs = Vec::with_capacity(n);
+    let mut off_diag: Vec<(u32, f32)> = Vec::new();
+
+    for i in 0..n {
+        let start = csr.row_ptr[i] as usize;
+        let end = csr.row_ptr[i + 1] as usize;
+
+        let mut diag = 0.0f32;
+        off_diag.clear();
+        for idx in start..end {
+            if csr.col_idx[idx] as usize == i {
+                diag += csr.values[idx];
+            } else {
+                off_diag.push((csr.col_idx[idx], csr.values[idx]));
+            }
+        }
+
+        // Rows need not be sorted: group the columns, then sum each run.
+        off_diag.sort_by_key(|&(col, _)| col);
+        let radius = off_diag
+            .chunk_by(|a, b| a.0 == b.0)
+            .map(|run| run.iter().map(|&(_, v)| v).sum::<f32>().abs())
+            .sum();
+
+        discs.push((diag, radius));
+    }
+
+    discs
+}
+
+/// Conservative eigenvalue bounds (lambda_min_bound, lambda_max_bound) from the Gershgorin
+/// circle theorem: min_i (a_ii - r_i) and max_i (a_ii + r_i).
+///
+/// These are LOOSE bounds: every eigenvalue lies inside, but the interval can be much wider
+/// than the actual spectrum (for the 1D Laplacian the lower bound is 0 for any n).
+/// Good enough for Chebyshev intervals / sanity checks, not for condition numbers.
+///
+/// An empty matrix returns (0.0, 0.0).
+pub fn gershgorin_bounds(csr: &Csr) -> (f32, f32) {
+    let discs = gershgorin_discs(csr);
+    if discs.is_empty() {
+        return (0.0, 0.0);
+    }
+
+    let mut lo = f32::INFINITY;
+    let mut hi = f32::NEG_INFINITY;
+    for (center, radius) in discs {
+        lo = lo.min(center - radius);
+        hi = hi.max(center + radius);
+    }
+
+    (lo, hi)
+}
+
+/// Impose inhomogeneous Dirichlet conditions x[i] = g_i by symmetric row/column
+/// elimination (the standard FEM step).
+///
+/// With g the vector of prescribed values (zero at free DOFs):
+/// - b' = b - A g on free rows (moves the known columns to the right-hand side)
+/// - constrained rows and columns of A are zeroed, with 1.0 on the diagonal
+/// - b'[i] = g_i on constrained rows
+///
+/// Eliminating columns as well as rows keeps a symmetric A symmetric (and SPD), so the
+/// result can go straight into PCG; its solution has x[i] = g_i exactly at constrained
+/// DOFs and solves the original equations at the free ones.
+///
+/// Panics if a constrained index is out of range. If an index appears twice the last
+/// value wins. Explicit zeros elsewhere in A are kept as-is.
+pub fn apply_dirichlet(csr: &Csr, b: &[f32], constrained: &[(u32, f32)]) -> (Csr, Vec<f32>) {
+    let n = csr.n_rows as usize;
+    assert_eq!(b.len(), n, "apply_dirichlet: b len {} != n {}", b.len(), n);
+
+    let mut is_constrained = vec![false; n];
+    let mut g = vec![0.0f32; n];
+    for &(i, value) in constrained {
+        let i = i as usize;
+        assert!(
+            i < n,
+            "apply_dirichlet: constrained DOF {i} out of range (n = {n})"
+        );
+        is_constrained[i] = true;
+        g[i] = value;
+    }
+
+    let mut row_ptr = Vec::with_capacity(n + 1);
+    let mut col_idx = Vec::with_capacity(csr.col_idx.len());
+    let mut values = Vec::with_capacity(csr.values.len());
+    let mut b_out = b.to_vec();
+
+    row_ptr.push(0u32);
+    for i in 0..n {
+        if is_constrained[i] {
+            col_idx.push(i as u32);
+            values.push(1.0);
+            b_out[i] = g[i];
+        } else {
+            let start = csr.row_ptr[i] as usize;
+            let end = csr.row_ptr[i + 1] as usize;
+            for idx in start..end {
+                let j = csr.col_idx[idx] as usize;
+                if is_constrained[j] {
+                    b_out[i] -= csr.values[idx] * g[j];
+                } else {
+                    col_idx.push(j as u32);
+                    values.push(csr.values[idx]);
+                }
+            }
+        }
+        row_ptr.push(col_idx.len() as u32);
+    }
+
+    let out = Csr {
+        n_rows: csr.n_rows,
+        n_cols: csr.n_cols,
+        nnz: values.len() as u32,
+        row_ptr,
+        col_idx,
+        values,
+    };
+
+    (out, b_out)
+}
+
+/// Standard 3-point finite-difference Laplacian (-u'') on n interior nodes.
+///
+/// See [`laplacian_3d`] for scaling and boundary conditions. The result is the SPD
+/// tridiagonal tridiag(-1, 2, -1) with eigenvalues 2 - 2 cos(k pi / (n + 1)), k = 1..=n.
+pub fn laplacian_1d(n: usize) -> Csr {
+    laplacian_grid(&[n])
+}
+
+/// 5-point Laplacian on an nx x ny grid of interior nodes (x fastest: i + nx * j).
+///
+/// See [`laplacian_3d`]; eigenvalues are sums of the 1D ones for nx and ny.
+pub fn laplacian_2d(nx: usize, ny: usize) -> Csr {
+    laplacian_grid(&[nx, ny])
+}
+
+/// 7-point Laplacian on an nx x ny x nz grid of interior nodes (index i + nx * (j + ny * k)).
+///
+/// Unscaled stencil: 2 * dim on the diagonal, -1 per grid neighbour (multiply by 1/h^2
+/// for the physical operator). Homogeneous Dirichlet boundaries: the boundary nodes are
+/// not unknowns, so neighbours outside the grid are simply dropped. The matrix is SPD,
+/// column indices are sorted within each row, and zero-sized grids give an empty matrix.
+pub fn laplacian_3d(nx: usize, ny: usize, nz: usize) -> Csr {
+    laplacian_grid(&[nx, ny, nz])
+}
+
+/// Lexicographic finite-difference Laplacian on a grid with the given extents.
+fn laplacian_grid(dims: &[usize]) -> Csr {
+    let n: usize = dims.iter().product();
+
+    // Index stride of each dimension (first dimension fastest).
+    let strides: Vec<usize> = dims
+        .iter()
+        .scan(1usize, |stride, &d| {
+            let s = *stride;
+            *stride *= d;
+            Some(s)
+        })
+        .collect();
+
+    let mut row_ptr = Vec::with_capacity(n + 1);
+    let mut col_idx = Vec::new();
+    let mut values = Vec::new();
+    row_ptr.push(0u32);
+
+    for row in 0..n {
+        // Lower neighbours (outermost dimension first), diagonal, upper neighbours:
+        // ascending column order.
+        for (d, &stride) in strides.iter().enumerate().rev() {
+            if (row / stride) % dims[d] > 0 {
+                col_idx.push((row - stride) as u32);
+                values.push(-1.0);
+            }
+        }
+        col_idx.push(row as u32);
+        values.push(2.0 * dims.len() as f32);
+        for (d, &stride) in strides.iter().enumerate() {
+            if (row / stride) % dims[d] + 1 < dims[d] {
+                col_idx.push((row + stride) as u32);
+                values.push(-1.0);
+            }
+        }
+
+        row_ptr.push(col_idx.len() as u32);
+    }
+
+    Csr {
+        n_rows: n as u32,
+        n_cols: n as u32,
+        nnz: values.len() as u32,
+        row_ptr,
+        col_idx,
+        values,
+    }
+}
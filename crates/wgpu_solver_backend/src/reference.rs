@@ -0,0 +1,454 @@
+use std::{collections::VecDeque, time::Instant};
+
+use crate::compute::{
+    IterationTrace, PcgOptions, PcgResult, ResidualStrategy,
+    fixed_point_dot::{check_frac_bits, fixed_point_scale, fixed_point_sum_to_f32},
+    lanczos::LanczosTridiagonal,
+    negative_curvature_error, precision_floor_hint,
+    reorth::ReorthPolicy,
+    tolerance_below_precision_floor,
+};
+use crate::matrix::Csr;
+
+// CPU reference kernels.
+//
+// Plain-loop versions of the GPU kernels with the same data layouts (CSR, packed 6x6
+// LU blocks, block_starts). They exist for checking GPU results and as the compute
+// path of the CPU pseudo-backend (see `device::SolverDevice`).
+//
+// NOT performance-oriented: single-threaded, no blocking, no SIMD.
+
+/// dot(a, b), accumulated in f64 and rounded once.
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (*x as f64) * (*y as f64))
+        .sum::<f64>() as f32
+}
+
+/// Fixed-point dot(a, b), bit-identical to `FixedPointDotExecutor` on any GPU: each f32
+/// product is scaled by 2^frac_bits, rounded (ties to even) and clamped to i32, and the
+/// i32s are summed exactly in i64 before one conversion back to f32.
+///
+/// Fails on `frac_bits` above `MAX_FIXED_POINT_FRAC_BITS` or when a product was clamped.
+pub fn fixed_point_dot(a: &[f32], b: &[f32], frac_bits: u32) -> Result<f32, String> {
+    check_frac_bits(frac_bits)?;
+    // Largest f32 below 2^31, as in fixed_point_dot.wgsl.
+    const I32_LIMIT: f32 = 2_147_483_520.0;
+    let scale = fixed_point_scale(frac_bits);
+    let mut sum = 0i64;
+    let mut saturated = 0u32;
+    for (x, y) in a.iter().zip(b) {
+        let scaled = (x * y * scale).round_ties_even();
+        if scaled.is_nan() || scaled.abs() > I32_LIMIT {
+            saturated += 1;
+        }
+        // NaN is counted as saturated here; on the GPU it is unspecified.
+        sum = sum.wrapping_add(scaled.clamp(-I32_LIMIT, I32_LIMIT) as i32 as i64);
+    }
+    fixed_point_sum_to_f32(sum, saturated, frac_bits)
+}
+
+/// y = A * x for a CSR matrix.
+pub fn spmv_csr(row_ptr: &[u32], col_idx: &[u32], values: &[f32], x: &[f32], y: &mut [f32]) {
+    for (i, y_i) in y.iter_mut().enumerate() {
+        let start = row_ptr[i] as usize;
+        let end = row_ptr[i + 1] as usize;
+
+        let mut sum = 0.0f32;
+        for idx in start..end {
+            sum += values[idx] * x[col_idx[idx] as usize];
+        }
+        *y_i = sum;
+    }
+}
+
+/// y = y + alpha * x
+pub fn axpy(alpha: f32, x: &[f32], y: &mut [f32]) {
+    for (y_i, x_i) in y.iter_mut().zip(x) {
+        *y_i += alpha * x_i;
+    }
+}
+
+/// ||r||_M = sqrt(r^T M r) for a CSR matrix M (SPD; negative r^T M r is clamped to 0).
+pub fn weighted_norm(row_ptr: &[u32], col_idx: &[u32], values: &[f32], r: &[f32]) -> f32 {
+    let mut mr = vec![0.0f32; r.len()];
+    spmv_csr(row_ptr, col_idx, values, r, &mut mr);
+    dot(r, &mr).max(0.0).sqrt()
+}
+
+/// ||r_b||_2 per block of `block_starts` (`compute::norms::per_block_residual`), with
+/// the squares accumulated in f64.
+pub fn per_block_norms(r: &[f32], block_starts: &[u32]) -> Vec<f32> {
+    block_starts
+        .windows(2)
+        .map(|w| {
+            let r_b = &r[w[0] as usize..w[1] as usize];
+            dot(r_b, r_b).sqrt()
+        })
+        .collect()
+}
+
+/// ||x - x_exact||_A = sqrt(e^T A e) with e = x - x_exact (A symmetric positive definite).
+pub fn error_a_norm(
+    row_ptr: &[u32],
+    col_idx: &[u32],
+    values: &[f32],
+    x: &[f32],
+    x_exact: &[f32],
+) -> f32 {
+    let e: Vec<f32> = x.iter().zip(x_exact).map(|(a, b)| a - b).collect();
+    weighted_norm(row_ptr, col_idx, values, &e)
+}
+
+/// z = M^{-1} r with the packed LU blocks of `build_lu_blocks_from_csr_block_starts_6`.
+///
+/// Same contract as `block_jacobi.wgsl`: unit-diagonal L below the diagonal, U on and
+/// above it, only the leading m×m of each 6x6 slab used, malformed blocks skipped.
+pub fn block_jacobi_apply_6(lu_blocks: &[f32], block_starts: &[u32], r: &[f32], z: &mut [f32]) {
+    block_jacobi_apply(6, lu_blocks, block_starts, r, z);
+}
+
+/// [`block_jacobi_apply_6`] for `block_size` x `block_size` slabs
+/// (`build_lu_blocks_from_csr_block_starts`).
+pub fn block_jacobi_apply(
+    block_size: usize,
+    lu_blocks: &[f32],
+    block_starts: &[u32],
+    r: &[f32],
+    z: &mut [f32],
+) {
+    let n = r.len();
+    let slab = block_size * block_size;
+    let mut y = vec![0.0f32; block_size];
+    let mut x = vec![0.0f32; block_size];
+
+    for block in 0..block_starts.len().saturating_sub(1) {
+        let offset = block_starts[block] as usize;
+        let next = block_starts[block + 1] as usize;
+        if offset >= n || next <= offset {
+            continue;
+        }
+
+        let m = (next - offset).min(block_size);
+        let lu = &lu_blocks[block * slab..(block + 1) * slab];
+
+        // Forward solve: L y = r_block (unit diagonal).
+        for i in 0..m {
+            let mut sum = r[offset + i];
+            for j in 0..i {
+                sum -= lu[i * block_size + j] * y[j];
+            }
+            y[i] = sum;
+        }
+
+        // Backward solve: U x = y.
+        for i in (0..m).rev() {
+            let mut sum = y[i];
+            for j in (i + 1)..m {
+                sum -= lu[i * block_size + j] * x[j];
+            }
+            x[i] = sum / lu[i * block_size + i];
+        }
+
+        z[offset..offset + m].copy_from_slice(&x[..m]);
+    }
+}
+
+/// z = U^{-1} L^{-1} r for CSR factors (see `compute::ilu0_exec` for the conventions:
+/// unit-diagonal L, stored or not; U with its diagonal).
+pub fn ilu_apply_csr(l: &Csr, u: &Csr, r: &[f32], z: &mut [f32]) {
+    let n = r.len();
+
+    // Forward solve: L y = r.
+    let mut y = vec![0.0f32; n];
+    for i in 0..n {
+        let mut sum = r[i];
+        for k in l.row_ptr[i] as usize..l.row_ptr[i + 1] as usize {
+            let j = l.col_idx[k] as usize;
+            if j != i {
+                sum -= l.values[k] * y[j];
+            }
+        }
+        y[i] = sum;
+    }
+
+    // Backward solve: U z = y.
+    for i in (0..n).rev() {
+        let mut sum = y[i];
+        let mut diag = 1.0f32;
+        for k in u.row_ptr[i] as usize..u.row_ptr[i + 1] as usize {
+            let j = u.col_idx[k] as usize;
+            if j == i {
+                diag = u.values[k];
+            } else {
+                sum -= u.values[k] * z[j];
+            }
+        }
+        z[i] = sum / diag;
+    }
+}
+
+/// CPU version of `compute::pcg_block_jacobi_csr_wgpu`: same recurrences, stopping rule
+/// and breakdown checks (including the p^T A p <= 0 negative-curvature error), so both
+/// paths converge to the same answer (not bit for bit: reductions run in a different order).
+///
+/// Of `options`, only `exact_solution` (||x - x_exact||_A after every iteration, one extra
+/// SpMV and dot each), `initial_preconditioned_residual`, `estimate_condition_number`,
+/// `ritz_value_history`, `reorthogonalize`, `residual_strategy` and `stopping_criterion` apply; a
+/// `custom_stopping_metric` (a WGSL kernel) is an error and `capture_at_iteration` is
+/// ignored. The result carries no timings, no workgroup sizes and zero dispatches.
+#[allow(clippy::too_many_arguments)]
+pub fn pcg_block_jacobi_csr_cpu(
+    n: usize,
+    row_ptr: &[u32],
+    col_idx: &[u32],
+    values: &[f32],
+    lu_blocks: &[f32],
+    block_starts: &[u32],
+    b: &[f32],
+    x: &mut [f32],
+    max_iter: usize,
+    rel_tol: f32,
+    abs_tol: f32,
+    options: &PcgOptions,
+) -> Result<PcgResult, String> {
+    let x_exact = options.exact_solution.as_deref();
+
+    if b.len() != n || x.len() != n || row_ptr.len() != n + 1 {
+        return Err(format!(
+            "PCG(BlockJacobiCpu): dimension mismatch: n={}, b len {}, x len {}, row_ptr len {}",
+            n,
+            b.len(),
+            x.len(),
+            row_ptr.len()
+        ));
+    }
+
+    if let Some(x_exact) = x_exact
+        && x_exact.len() != n
+    {
+        return Err(format!(
+            "PCG(BlockJacobiCpu): exact_solution len {} != n={}",
+            x_exact.len(),
+            n
+        ));
+    }
+
+    if let Some(metric) = &options.custom_stopping_metric {
+        return Err(format!(
+            "PCG(BlockJacobiCpu): custom stopping metric {} needs a GPU device",
+            metric.kernel.label
+        ));
+    }
+
+    if let Some(z0) = &options.initial_preconditioned_residual
+        && z0.len() != n
+    {
+        return Err(format!(
+            "PCG(BlockJacobiCpu): initial_preconditioned_residual len {} != n={}",
+            z0.len(),
+            n
+        ));
+    }
+
+    if options.reorthogonalize == ReorthPolicy::Window(0) {
+        return Err("PCG(BlockJacobiCpu): reorthogonalize window must be > 0".into());
+    }
+    if options.residual_strategy == ResidualStrategy::TrueEvery(0) {
+        return Err("PCG(BlockJacobiCpu): residual_strategy interval must be > 0".into());
+    }
+    if options.submit_every == 0 {
+        return Err("PCG(BlockJacobiCpu): submit_every must be >= 1".into());
+    }
+    options
+        .stopping_criterion
+        .validate()
+        .map_err(|e| format!("PCG(BlockJacobiCpu): {e}"))?;
+
+    let zero = 0.0f32;
+    let mut residual_history: Vec<f32> = Vec::new();
+    let mut error_a_norm_history: Option<Vec<f32>> = x_exact.map(|_| Vec::new());
+    let mut lanczos = (options.estimate_condition_number || options.ritz_value_history)
+        .then(|| LanczosTridiagonal::new(options.ritz_value_history));
+    let mut iteration_trace: Option<Vec<IterationTrace>> = options.trace_iterations.then(Vec::new);
+    let solve_start = Instant::now();
+
+    let b_norm2 = dot(b, b);
+    if b_norm2 == zero {
+        return Ok(PcgResult {
+            iterations: 0,
+            residual_norm: 0.0,
+            dispatches: 0,
+            workgroup_sizes: None,
+            tolerance_below_precision_floor: false,
+            timings: None,
+            residual_history,
+            error_a_norm_history,
+            max_in_flight_submissions: 0,
+            estimated_eigenvalue_range: None,
+            estimated_condition_number: None,
+            ritz_value_history: options.ritz_value_history.then(Vec::new),
+            custom_metric_history: None,
+            capture_marker_iteration: None,
+            iteration_trace,
+        });
+    }
+
+    // r0 = b - A x0, z0 = M^-1 r0, p0 = z0
+    let mut ap = vec![0.0f32; n];
+    spmv_csr(row_ptr, col_idx, values, x, &mut ap);
+
+    let mut r = b.to_vec();
+    axpy(-1.0, &ap, &mut r);
+    let r0_norm2 = dot(&r, &r);
+
+    let below_floor = tolerance_below_precision_floor(
+        n,
+        b_norm2.sqrt(),
+        options
+            .stopping_criterion
+            .equivalent_rel_tol(b_norm2, r0_norm2, rel_tol),
+        abs_tol,
+    );
+
+    let mut z = match &options.initial_preconditioned_residual {
+        Some(z0) => z0.clone(),
+        None => {
+            let mut z = vec![0.0f32; n];
+            block_jacobi_apply_6(lu_blocks, block_starts, &r, &mut z);
+            z
+        }
+    };
+
+    let mut p = z.clone();
+    let mut rz_old = dot(&r, &z);
+
+    // (p_j, -A p_j) / sqrt(p_j^T A p_j) of the last window_len directions
+    let window_len = options.reorthogonalize.window_len();
+    let mut directions: VecDeque<(Vec<f32>, Vec<f32>)> = VecDeque::with_capacity(window_len);
+
+    for k in 0..max_iter {
+        spmv_csr(row_ptr, col_idx, values, &p, &mut ap);
+        let p_ap = dot(&p, &ap);
+
+        if p_ap <= zero {
+            return Err(negative_curvature_error("PCG(BlockJacobiCpu)", k + 1, p_ap));
+        }
+        if rz_old == zero {
+            return Err("PCG(BlockJacobiCpu): rz_old is zero (breakdown)".into());
+        }
+
+        // With reorthogonalization p is no longer z + beta p_old, so p^T r (= rz_old in
+        // exact arithmetic) is used directly.
+        let alpha = if window_len > 0 {
+            dot(&p, &r) / p_ap
+        } else {
+            rz_old / p_ap
+        };
+
+        if window_len > 0 {
+            if directions.len() == window_len {
+                directions.pop_front();
+            }
+            let scale = if p_ap > zero { 1.0 / p_ap.sqrt() } else { zero };
+            directions.push_back((
+                p.iter().map(|v| v * scale).collect(),
+                ap.iter().map(|v| -v * scale).collect(),
+            ));
+        }
+
+        axpy(alpha, &p, x);
+        axpy(-alpha, &ap, &mut r);
+
+        // r = b - A x instead of the recurrence (A p is no longer needed)
+        if options.residual_strategy.recompute_after(k + 1) {
+            spmv_csr(row_ptr, col_idx, values, x, &mut ap);
+            r.copy_from_slice(b);
+            axpy(-1.0, &ap, &mut r);
+        }
+
+        let r_norm2 = dot(&r, &r);
+        residual_history.push(r_norm2.sqrt());
+
+        if let (Some(x_exact), Some(history)) = (x_exact, error_a_norm_history.as_mut()) {
+            history.push(error_a_norm(row_ptr, col_idx, values, x, x_exact));
+        }
+
+        let converged = options
+            .stopping_criterion
+            .is_met(r_norm2, b_norm2, r0_norm2, rel_tol, abs_tol);
+        let mut trace_step = |beta: Option<f32>| {
+            if let Some(trace) = iteration_trace.as_mut() {
+                trace.push(IterationTrace {
+                    iteration: k + 1,
+                    residual_norm: r_norm2.sqrt(),
+                    alpha,
+                    beta,
+                    elapsed_ms: solve_start.elapsed().as_secs_f64() * 1e3,
+                });
+            }
+        };
+
+        if converged {
+            trace_step(None);
+
+            // beta of the last iteration never enters T
+            if let Some(lanczos) = lanczos.as_mut() {
+                lanczos.push(alpha, 0.0);
+            }
+            // T may exist for the Ritz history alone: report the estimate only if asked.
+            let condition_lanczos = lanczos
+                .as_ref()
+                .filter(|_| options.estimate_condition_number);
+            return Ok(PcgResult {
+                iterations: k + 1,
+                residual_norm: r_norm2.sqrt(),
+                dispatches: 0,
+                workgroup_sizes: None,
+                tolerance_below_precision_floor: below_floor,
+                timings: None,
+                residual_history,
+                error_a_norm_history,
+                max_in_flight_submissions: 0,
+                estimated_eigenvalue_range: condition_lanczos
+                    .and_then(LanczosTridiagonal::extreme_eigenvalues),
+                estimated_condition_number: condition_lanczos
+                    .and_then(LanczosTridiagonal::condition_number),
+                ritz_value_history: lanczos
+                    .as_ref()
+                    .and_then(|l| l.ritz_history().map(<[_]>::to_vec)),
+                custom_metric_history: None,
+                capture_marker_iteration: None,
+                iteration_trace,
+            });
+        }
+
+        block_jacobi_apply_6(lu_blocks, block_starts, &r, &mut z);
+        let rz_new = dot(&r, &z);
+
+        // p = z + beta * p
+        let beta = rz_new / rz_old;
+        for (p_i, z_i) in p.iter_mut().zip(&z) {
+            *p_i = z_i + beta * *p_i;
+        }
+        trace_step(Some(beta));
+
+        // p = p - (p^T A q_j) q_j, oldest first (modified Gram-Schmidt)
+        for (q, minus_aq) in &directions {
+            axpy(dot(minus_aq, &p), q, &mut p);
+        }
+
+        if let Some(lanczos) = lanczos.as_mut() {
+            lanczos.push(alpha, beta);
+        }
+
+        rz_old = rz_new;
+    }
+
+    Err(format!(
+        "PCG(BlockJacobiCpu): did not converge in {} iterations{}",
+        max_iter,
+        precision_floor_hint(below_floor)
+    ))
+}
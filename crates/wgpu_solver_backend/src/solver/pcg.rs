@@ -0,0 +1,881 @@
+use std::ops::Deref;
+
+use futures::executor;
+use wgpu::{Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor};
+
+use crate::{
+    compute::{
+        PCG_SCALAR_RESULTS_LEN, PcgOptions, PcgResult, ResidualStrategy, SolveConfig,
+        StoppingCriterion, WorkgroupSizes, block_jacobi_exec::BlockJacobiExecutor,
+        build_lu_blocks_from_csr_block_starts_6, dot_partials::DEFAULT_WORKGROUP_SIZE,
+        dot_scalar_exec::DotScalarExecutor, negative_curvature_error, observer::SolverObserver,
+        operator::LinearOperator, pcg_block_jacobi_csr_wgpu,
+        pcg_update_scalars_exec::PcgUpdateScalarsExecutor, precision_floor_hint,
+        reorth::ReorthPolicy, spmv_exec::SpmvExecutor, tolerance_below_precision_floor,
+        vec_ops_exec::VecOpsExecutor,
+    },
+    gpu::{buffer::GpuBuffer, context::GpuContext, readback::try_read_mapped_buffer_into},
+    matrix::Csr,
+};
+
+// Scalar slots, as in `pcg_block_jacobi_csr_wgpu`.
+const SLOT_P_AP: u32 = 0;
+const SLOT_R_NORM2: u32 = 1;
+const SLOT_RZ_NEW: u32 = 2;
+const SLOT_RZ_OLD: u32 = 3;
+const SLOT_ALPHA: u32 = 4;
+const SLOT_MINUS_ALPHA: u32 = 5;
+const SLOT_BETA: u32 = 6;
+
+/// PCG for one matrix on one device: the executors are built once in [`PcgSolver::new`]
+/// (block Jacobi) or [`PcgSolver::with_preconditioner`] (any M^{-1}, e.g. a
+/// `DiagonalJacobiExecutor`) and shared by every solve.
+///
+/// [`PcgSolver::solve`] runs [`pcg_block_jacobi_csr_wgpu`] to completion;
+/// [`PcgSolver::iter_solve`] runs the same iteration one step at a time;
+/// [`PcgSolver::with_workspace`] runs it in caller-allocated vectors.
+pub struct PcgSolver<'a> {
+    ctx: &'a GpuContext,
+    n: usize,
+    config: SolveConfig,
+    spmv_exec: SpmvExecutor,
+    vec_ops_exec: VecOpsExecutor,
+    dot_scalar_exec: DotScalarExecutor,
+    preconditioner: Box<dyn LinearOperator + 'a>,
+    pcg_update_scalars_exec: PcgUpdateScalarsExecutor,
+}
+
+impl<'a> PcgSolver<'a> {
+    /// Build the preconditioner from `block_starts` and the executors for `a`; every solve
+    /// runs with `config`. The executor-level options (`readback_buffering`,
+    /// `dot_precision`, `dot_subgroup_size`) are applied here; fails when the device
+    /// cannot honor them.
+    pub fn new(
+        ctx: &'a GpuContext,
+        a: &Csr,
+        block_starts: &[u32],
+        config: &SolveConfig,
+    ) -> Result<Self, String> {
+        let lu_blocks = build_lu_blocks_from_csr_block_starts_6(
+            a.n_rows as usize,
+            &a.row_ptr,
+            &a.col_idx,
+            &a.values,
+            block_starts,
+        )?;
+        let block_jacobi_exec =
+            BlockJacobiExecutor::create(ctx, a.n_rows, 6, &lu_blocks, block_starts)?;
+
+        Self::with_preconditioner(ctx, a, Box::new(block_jacobi_exec), config)
+    }
+
+    /// Same as `new`, with a caller-built preconditioner z = M^{-1} r in place of block
+    /// Jacobi. M must be SPD for PCG to converge; fails when it is not n x n.
+    pub fn with_preconditioner(
+        ctx: &'a GpuContext,
+        a: &Csr,
+        preconditioner: Box<dyn LinearOperator + 'a>,
+        config: &SolveConfig,
+    ) -> Result<Self, String> {
+        let n = a.n_rows as usize;
+        if preconditioner.n_rows() != a.n_rows || preconditioner.n_cols() != a.n_rows {
+            return Err(format!(
+                "PcgSolver: preconditioner is {}x{}, matrix has n = {n}",
+                preconditioner.n_rows(),
+                preconditioner.n_cols()
+            ));
+        }
+
+        let spmv_exec = SpmvExecutor::create(ctx, a.n_rows, &a.row_ptr, &a.col_idx, &a.values);
+        let vec_ops_exec = VecOpsExecutor::create(ctx);
+        let options = &config.options;
+        let (partials_wg, reduce_wg) = options
+            .dot_workgroup_sizes
+            .unwrap_or((DEFAULT_WORKGROUP_SIZE, DEFAULT_WORKGROUP_SIZE));
+        let mut dot_scalar_exec = DotScalarExecutor::try_create_with_workgroup_sizes(
+            ctx,
+            n,
+            PCG_SCALAR_RESULTS_LEN,
+            partials_wg,
+            reduce_wg,
+        )?;
+        dot_scalar_exec.set_readback_buffering(ctx, options.readback_buffering);
+        dot_scalar_exec.set_dot_precision(ctx, options.dot_precision)?;
+        dot_scalar_exec.set_dot_subgroup_size(ctx, options.dot_subgroup_size)?;
+        let pcg_update_scalars_exec = PcgUpdateScalarsExecutor::create(ctx);
+
+        Ok(Self {
+            ctx,
+            n,
+            config: config.clone(),
+            spmv_exec,
+            vec_ops_exec,
+            dot_scalar_exec,
+            preconditioner,
+            pcg_update_scalars_exec,
+        })
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    pub fn config(&self) -> &SolveConfig {
+        &self.config
+    }
+
+    pub fn options(&self) -> &PcgOptions {
+        &self.config.options
+    }
+
+    /// Solve A x = b from the initial guess in `x`, which receives the solution.
+    pub fn solve(&self, b: &[f32], x: &mut [f32]) -> Result<PcgResult, String> {
+        self.solve_observed(b, x, None)
+    }
+
+    /// [`PcgSolver::solve`], calling `observer` with ||r_k|| after every iteration's
+    /// readback (see [`crate::compute::observer`]).
+    pub fn solve_observed(
+        &self,
+        b: &[f32],
+        x: &mut [f32],
+        observer: Option<&mut dyn SolverObserver>,
+    ) -> Result<PcgResult, String> {
+        pcg_block_jacobi_csr_wgpu(
+            self.n,
+            b,
+            x,
+            self.config.max_iters,
+            self.config.rel_tol,
+            self.config.abs_tol,
+            self.ctx,
+            &self.spmv_exec,
+            &self.vec_ops_exec,
+            &self.dot_scalar_exec,
+            self.preconditioner.as_ref(),
+            &self.pcg_update_scalars_exec,
+            &self.config.options,
+            observer,
+        )
+    }
+
+    /// Solve A x = b from `x0` one iteration per `next()`, e.g. to show convergence live
+    /// or to stop on a condition of the caller's own.
+    ///
+    /// Every step is the same submit + scalar readback as an iteration of
+    /// [`PcgSolver::solve`], in the same pass order, so running the iterator to the end
+    /// gives bitwise the same residuals and solution. Setup (||b||^2, r0, z0, p0) runs here.
+    /// The stream ends after the converged step; past `max_iters` it yields the same
+    /// "did not converge" error as `solve`, as it does on breakdown or device loss.
+    ///
+    /// Steps carry only the residual norm. [`PcgSteps::with_iterates`] adds x to each
+    /// one, which costs a full readback of n floats per step on top of the scalar one;
+    /// to get the final x only, call [`PcgSteps::solution`] instead.
+    ///
+    /// Of the per-iteration options, only `residual_strategy` and `stopping_criterion` are
+    /// supported; timing, snapshots, exact_solution, the custom metric,
+    /// reorthogonalization, the capture and device-loss hooks, condition estimates, traces
+    /// and `submit_every > 1` are rejected.
+    pub fn iter_solve(&self, b: &[f32], x0: &[f32]) -> Result<PcgSteps<'_>, String> {
+        self.check_step_options("PcgSolver::iter_solve")?;
+        let workspace = Box::new(SolverWorkspace::new(self.ctx, self.n));
+        self.start_steps(
+            StepVectors::Owned(workspace),
+            b,
+            x0,
+            "PcgSolver::iter_solve",
+        )
+    }
+
+    /// Bind a caller-owned workspace for solves that allocate no GPU buffers.
+    ///
+    /// Create the workspace once with [`SolverWorkspace::new`] (`ctx`, `n()`; its size is
+    /// [`SolverWorkspace::size_in_bytes`]) and reuse it for every solve: uploads go
+    /// through `Queue::write_buffer` into its vectors and the solution comes back
+    /// through its own readback buffer, so `ctx.buffers_created()` does not move.
+    ///
+    /// The iteration is the one of [`PcgSolver::iter_solve`], with the same option
+    /// restrictions; fails when they are not met or the workspace has a different n.
+    ///
+    /// This is also the way to keep the solution on the device: `solve` allocates its
+    /// vectors per call and keeps none, while the workspace's x, r and p stay bindable
+    /// ([`SolverWorkspace::solution_buffer`]).
+    pub fn with_workspace<'s>(
+        &'s self,
+        workspace: &'s mut SolverWorkspace,
+    ) -> Result<WorkspaceSolve<'s>, String> {
+        self.check_step_options("PcgSolver::with_workspace")?;
+        if workspace.n != self.n {
+            return Err(format!(
+                "PcgSolver::with_workspace: workspace is for n={}, solver has n={}",
+                workspace.n, self.n
+            ));
+        }
+        Ok(WorkspaceSolve {
+            solver: self,
+            workspace,
+        })
+    }
+
+    /// Upload b, x0 (and z0) into `vectors`, then run the setup: ||b||^2, r0, z0, p0.
+    fn start_steps<'s>(
+        &'s self,
+        vectors: StepVectors<'s>,
+        b: &[f32],
+        x0: &[f32],
+        what: &str,
+    ) -> Result<PcgSteps<'s>, String> {
+        if b.len() != self.n || x0.len() != self.n {
+            return Err(format!(
+                "{what}: dimension mismatch: n={}, b len {}, x0 len {}",
+                self.n,
+                b.len(),
+                x0.len()
+            ));
+        }
+        let z0 = self
+            .config
+            .options
+            .initial_preconditioned_residual
+            .as_deref();
+        if let Some(z0) = z0
+            && z0.len() != self.n
+        {
+            return Err(format!(
+                "{what}: initial_preconditioned_residual len {} != n={}",
+                z0.len(),
+                self.n
+            ));
+        }
+
+        let ctx = self.ctx;
+        let n_u32 = self.n as u32;
+        let n_bytes = (self.n * 4) as u64;
+        let dot = &self.dot_scalar_exec;
+        let dot_dispatches = dot.dispatches_per_dot(n_u32) as u64;
+
+        ctx.queue
+            .write_buffer(&vectors.b.buffer, 0, bytemuck::cast_slice(b));
+        ctx.queue
+            .write_buffer(&vectors.x.buffer, 0, bytemuck::cast_slice(x0));
+        if let Some(z0) = z0 {
+            ctx.queue
+                .write_buffer(&vectors.z.buffer, 0, bytemuck::cast_slice(z0));
+        }
+
+        // ||b||^2
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some(&ctx.label("pcg steps b_norm2 encoder")),
+            });
+        dot.encode_dot_scalar_into(
+            ctx,
+            &mut encoder,
+            &vectors.b.buffer,
+            &vectors.b.buffer,
+            n_u32,
+            0,
+        );
+        dot.encode_copy_scalar_results_to_readback(&mut encoder);
+        ctx.queue.submit(Some(encoder.finish()));
+        let b_norm2 = executor::block_on(dot.try_readback_scalar_results(ctx))
+            .map_err(|e| self.readback_err(e))?[0];
+
+        let mut steps = PcgSteps {
+            solver: self,
+            vectors,
+            b_norm2,
+            r0_norm2: 0.0,
+            rz_old: 0.0,
+            below_floor: false,
+            iterations: 0,
+            dispatches: dot_dispatches,
+            done: b_norm2 == 0.0,
+            with_iterates: false,
+        };
+        if steps.done {
+            return Ok(steps);
+        }
+
+        // r0 = b - A x0, z0 = M^-1 r0 (or the supplied one), p0 = z0, rz_old = r0^T z0
+        let v = &steps.vectors;
+        self.vec_ops_exec.reset_params_cursor();
+        self.pcg_update_scalars_exec.reset_params_cursor();
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some(&ctx.label("pcg steps init encoder")),
+            });
+        encoder.copy_buffer_to_buffer(&v.b.buffer, 0, &v.r.buffer, 0, n_bytes);
+        self.spmv_exec
+            .encode_copy_x_from(&mut encoder, &v.x.buffer, n_bytes);
+        self.spmv_exec.encode_spmv(&mut encoder);
+        self.vec_ops_exec.encode_axpy_inplace(
+            ctx,
+            &mut encoder,
+            self.spmv_exec.y_buffer(),
+            &v.r.buffer,
+            n_u32,
+            -1.0,
+        );
+        if z0.is_none() {
+            self.preconditioner
+                .encode_apply(ctx, &mut encoder, &v.r.buffer, &v.z.buffer);
+        }
+        encoder.copy_buffer_to_buffer(&v.z.buffer, 0, &v.p.buffer, 0, n_bytes);
+        dot.encode_dot_scalar_into(
+            ctx,
+            &mut encoder,
+            &v.r.buffer,
+            &v.z.buffer,
+            n_u32,
+            SLOT_RZ_OLD,
+        );
+        let reduction_target = matches!(
+            self.config.options.stopping_criterion,
+            StoppingCriterion::ReductionFactor(_)
+        );
+        if reduction_target {
+            dot.encode_dot_scalar_into(
+                ctx,
+                &mut encoder,
+                &v.r.buffer,
+                &v.r.buffer,
+                n_u32,
+                SLOT_R_NORM2,
+            );
+        }
+        dot.encode_copy_scalar_results_to_readback(&mut encoder);
+        ctx.queue.submit(Some(encoder.finish()));
+        let scalar_results = executor::block_on(dot.try_readback_scalar_results(ctx))
+            .map_err(|e| self.readback_err(e))?;
+        steps.rz_old = scalar_results[SLOT_RZ_OLD as usize];
+        if reduction_target {
+            steps.r0_norm2 = scalar_results[SLOT_R_NORM2 as usize];
+        }
+
+        // spmv + axpy + preconditioner + dot(r,z) (+ dot(r,r)), as in the batch loop
+        steps.dispatches += 2 + dot_dispatches;
+        if z0.is_none() {
+            steps.dispatches += self.preconditioner.dispatches();
+        }
+        if reduction_target {
+            steps.dispatches += dot_dispatches;
+        }
+
+        steps.below_floor = tolerance_below_precision_floor(
+            self.n,
+            b_norm2.sqrt(),
+            self.config.options.stopping_criterion.equivalent_rel_tol(
+                b_norm2,
+                steps.r0_norm2,
+                self.config.rel_tol,
+            ),
+            self.config.abs_tol,
+        );
+
+        Ok(steps)
+    }
+
+    fn check_step_options(&self, what: &str) -> Result<(), String> {
+        let o = &self.config.options;
+        if o.timing
+            || o.snapshot_interval.is_some()
+            || o.exact_solution.is_some()
+            || o.custom_stopping_metric.is_some()
+            || o.reorthogonalize != ReorthPolicy::None
+            || o.capture_at_iteration.is_some()
+            || o.estimate_condition_number
+            || o.ritz_value_history
+            || o.trace_iterations
+            || o.submit_every != 1
+        {
+            return Err(format!(
+                "{what}: only residual_strategy, stopping_criterion and the executor options \
+                 are supported per step"
+            ));
+        }
+        if o.residual_strategy == ResidualStrategy::TrueEvery(0) {
+            return Err(format!("{what}: residual_strategy interval must be > 0"));
+        }
+        o.stopping_criterion
+            .validate()
+            .map_err(|e| format!("{what}: {e}"))?;
+        Ok(())
+    }
+
+    fn readback_err(&self, e: String) -> String {
+        if self.ctx.is_lost() {
+            format!("PcgSolver: device lost: {e}")
+        } else {
+            format!("PcgSolver: readback failed: {e}")
+        }
+    }
+}
+
+/// One PCG iteration as yielded by [`PcgSteps`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolveStep {
+    /// 1-based, like [`PcgResult::iterations`].
+    pub iteration: usize,
+    /// ||r|| after the iteration's update.
+    pub residual_norm: f32,
+    /// The stopping test passed; this is the last step.
+    pub converged: bool,
+    /// x after the iteration; only with [`PcgSteps::with_iterates`].
+    pub x: Option<Vec<f32>>,
+}
+
+/// The GPU vectors of one PCG solve for n unknowns, see [`PcgSolver::with_workspace`].
+///
+/// b, x, r, p and z (n f32 each), a MAP_READ copy of x for reading the solution back,
+/// and a 4-byte stash that carries rz_new into the next iteration's rz_old on the GPU
+/// (instead of a staging buffer per iteration). Everything else a solve touches -- the
+/// matrix, the preconditioner, the scalar slots and uniform pools -- belongs to the
+/// solver's executors and was allocated in [`PcgSolver::new`].
+///
+/// x, r and p can be bound directly by the caller's own pipelines (e.g. to draw the
+/// solution every frame without a copy): see [`SolverWorkspace::solution_buffer`].
+pub struct SolverWorkspace {
+    n: usize,
+    b: GpuBuffer<f32>,
+    x: GpuBuffer<f32>,
+    r: GpuBuffer<f32>,
+    p: GpuBuffer<f32>,
+    z: GpuBuffer<f32>,
+    x_readback: Buffer,
+    rz_stash: Buffer,
+}
+
+impl SolverWorkspace {
+    /// Storage vectors of n f32 in a workspace (b, x, r, p, z).
+    pub const VECTORS: usize = 5;
+
+    /// Device memory of a workspace for `n` unknowns: the storage vectors and the x
+    /// readback (n f32 each), plus the 4-byte stash.
+    pub fn size_in_bytes(n: usize) -> u64 {
+        ((Self::VECTORS + 1) * n * 4 + 4) as u64
+    }
+
+    /// Allocate every buffer a solve of `n` unknowns needs (see `size_in_bytes`).
+    pub fn new(ctx: &GpuContext, n: usize) -> Self {
+        Self::with_extra_usages(ctx, n, BufferUsages::empty())
+    }
+
+    /// Same as `new`, with `extra_usages` added to the storage vectors (b, x, r, p, z),
+    /// e.g. `VERTEX` to bind the solution as a vertex buffer. Usages are fixed at
+    /// creation, so a renderer that binds [`SolverWorkspace::solution_buffer`] needs
+    /// them requested here. `MAP_READ` / `MAP_WRITE` cannot be combined with `STORAGE`
+    /// and fail buffer creation.
+    pub fn with_extra_usages(ctx: &GpuContext, n: usize, extra_usages: BufferUsages) -> Self {
+        let vector = |label: &str| {
+            ctx.create_storage_buffer_uninit::<f32>(label, n, BufferUsages::COPY_SRC | extra_usages)
+        };
+        Self {
+            n,
+            b: vector("pcg workspace b"),
+            x: vector("pcg workspace x"),
+            r: vector("pcg workspace r"),
+            p: vector("pcg workspace p"),
+            z: vector("pcg workspace z"),
+            x_readback: ctx.create_buffer(&BufferDescriptor {
+                label: Some(&ctx.label("pcg workspace x readback")),
+                size: (n * 4) as u64,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            rz_stash: ctx.create_buffer(&BufferDescriptor {
+                label: Some(&ctx.label("pcg workspace rz stash")),
+                size: 4,
+                usage: BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        }
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// x: n f32, with `STORAGE | COPY_SRC | COPY_DST` and the extra usages of
+    /// [`SolverWorkspace::with_extra_usages`].
+    ///
+    /// After a [`WorkspaceSolve::solve`] it holds the solution (the same values the solve
+    /// wrote to the host slice), and during an `iter_solve` the current iterate; it stays
+    /// valid until the next solve in this workspace overwrites it. The buffer lives as
+    /// long as the workspace, and while a solve holds the workspace the borrow checker
+    /// keeps it unreachable here: between steps, use [`PcgSteps::solution_buffer`].
+    /// Reads in later submissions see the solve's writes; writing to it changes the
+    /// next step's x.
+    pub fn solution_buffer(&self) -> &Buffer {
+        &self.x.buffer
+    }
+
+    /// r: the recurrence residual of the last step (b - A x when the
+    /// `residual_strategy` recomputed it). Same usages and lifetime as `solution_buffer`.
+    pub fn residual_buffer(&self) -> &Buffer {
+        &self.r.buffer
+    }
+
+    /// p: the next search direction. Same usages and lifetime as `solution_buffer`.
+    pub fn direction_buffer(&self) -> &Buffer {
+        &self.p.buffer
+    }
+}
+
+/// The vectors of a [`PcgSteps`]: its own (`iter_solve`) or the caller's (`with_workspace`).
+enum StepVectors<'s> {
+    Owned(Box<SolverWorkspace>),
+    Borrowed(&'s mut SolverWorkspace),
+}
+
+impl Deref for StepVectors<'_> {
+    type Target = SolverWorkspace;
+
+    fn deref(&self) -> &SolverWorkspace {
+        match self {
+            StepVectors::Owned(workspace) => workspace,
+            StepVectors::Borrowed(workspace) => workspace,
+        }
+    }
+}
+
+/// A [`PcgSolver`] bound to a [`SolverWorkspace`], see [`PcgSolver::with_workspace`].
+pub struct WorkspaceSolve<'s> {
+    solver: &'s PcgSolver<'s>,
+    workspace: &'s mut SolverWorkspace,
+}
+
+impl WorkspaceSolve<'_> {
+    /// Solve A x = b from the initial guess in `x`, which receives the solution.
+    ///
+    /// Same residuals and solution as [`PcgSolver::solve`], bitwise. The result carries
+    /// the residual history; the per-option fields (timings, traces, ...) stay empty.
+    pub fn solve(&mut self, b: &[f32], x: &mut [f32]) -> Result<PcgResult, String> {
+        let mut steps = self.iter_solve(b, x)?;
+        let mut residual_history = Vec::new();
+        let mut residual_norm = 0.0;
+        for step in &mut steps {
+            residual_norm = step?.residual_norm;
+            residual_history.push(residual_norm);
+        }
+        if steps.iterations > 0 {
+            steps.read_solution_into(x)?;
+        }
+
+        Ok(PcgResult {
+            iterations: steps.iterations,
+            residual_norm,
+            dispatches: steps.dispatches,
+            workgroup_sizes: Some(WorkgroupSizes::for_pcg(&steps.solver.dot_scalar_exec)),
+            tolerance_below_precision_floor: steps.below_floor,
+            timings: None,
+            residual_history,
+            error_a_norm_history: None,
+            max_in_flight_submissions: 1,
+            estimated_eigenvalue_range: None,
+            estimated_condition_number: None,
+            ritz_value_history: None,
+            custom_metric_history: None,
+            capture_marker_iteration: None,
+            iteration_trace: None,
+        })
+    }
+
+    /// [`PcgSolver::iter_solve`] in the workspace's vectors.
+    pub fn iter_solve(&mut self, b: &[f32], x0: &[f32]) -> Result<PcgSteps<'_>, String> {
+        self.solver.start_steps(
+            StepVectors::Borrowed(self.workspace),
+            b,
+            x0,
+            "PcgSolver::with_workspace",
+        )
+    }
+}
+
+/// A PCG solve in progress, see [`PcgSolver::iter_solve`]. Its vectors are its own or
+/// a [`SolverWorkspace`]; the executors are the solver's, so one solver runs one
+/// `PcgSteps` at a time (the borrow allows several, but their scalar slots would clash).
+pub struct PcgSteps<'s> {
+    solver: &'s PcgSolver<'s>,
+    vectors: StepVectors<'s>,
+    b_norm2: f32,
+    r0_norm2: f32,
+    rz_old: f32,
+    below_floor: bool,
+    iterations: usize,
+    dispatches: u64,
+    done: bool,
+    with_iterates: bool,
+}
+
+impl PcgSteps<'_> {
+    /// Read x back after every step into [`SolveStep::x`] (one extra n-float readback
+    /// per step).
+    pub fn with_iterates(mut self) -> Self {
+        self.with_iterates = true;
+        self
+    }
+
+    /// Iterations run so far.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// The current x on the device, see [`SolverWorkspace::solution_buffer`] (an
+    /// `iter_solve` workspace has no extra usages).
+    pub fn solution_buffer(&self) -> &Buffer {
+        self.vectors.solution_buffer()
+    }
+
+    /// The current r, see [`SolverWorkspace::residual_buffer`].
+    pub fn residual_buffer(&self) -> &Buffer {
+        self.vectors.residual_buffer()
+    }
+
+    /// The current p, see [`SolverWorkspace::direction_buffer`].
+    pub fn direction_buffer(&self) -> &Buffer {
+        self.vectors.direction_buffer()
+    }
+
+    /// Current x (x0 before the first step).
+    pub fn solution(&self) -> Result<Vec<f32>, String> {
+        let mut x = vec![0.0f32; self.solver.n];
+        self.read_solution_into(&mut x)?;
+        Ok(x)
+    }
+
+    /// Current x into `x` (n entries), through the workspace's readback buffer.
+    pub fn read_solution_into(&self, x: &mut [f32]) -> Result<(), String> {
+        let s = self.solver;
+        if x.len() != s.n {
+            return Err(format!(
+                "PcgSteps::read_solution_into: x len {} != n={}",
+                x.len(),
+                s.n
+            ));
+        }
+
+        let v = &self.vectors;
+        let mut encoder = s
+            .ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("pcg steps x readback encoder"),
+            });
+        encoder.copy_buffer_to_buffer(&v.x.buffer, 0, &v.x_readback, 0, (s.n * 4) as u64);
+        s.ctx.queue.submit(Some(encoder.finish()));
+        executor::block_on(try_read_mapped_buffer_into(&s.ctx.device, &v.x_readback, x))
+            .map_err(|e| s.readback_err(e))
+    }
+
+    /// Record, submit and read back one iteration (passes A-K of the batch loop).
+    fn step(&mut self) -> Result<SolveStep, String> {
+        let s = self.solver;
+        let ctx = s.ctx;
+        let n_u32 = s.n as u32;
+        let n_bytes = (s.n * 4) as u64;
+        let dot = &s.dot_scalar_exec;
+        let scalars = dot.scalar_results_buffer();
+        let iteration = self.iterations + 1;
+        let v = &self.vectors;
+
+        s.vec_ops_exec.reset_params_cursor();
+        s.pcg_update_scalars_exec.reset_params_cursor();
+
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some(&ctx.label("pcg steps iteration encoder")),
+            });
+
+        // Ap = A p; pAp = dot(p, Ap)
+        s.spmv_exec
+            .encode_copy_x_from(&mut encoder, &v.p.buffer, n_bytes);
+        s.spmv_exec.encode_spmv(&mut encoder);
+        dot.encode_dot_scalar_into(
+            ctx,
+            &mut encoder,
+            &v.p.buffer,
+            s.spmv_exec.y_buffer(),
+            n_u32,
+            SLOT_P_AP,
+        );
+
+        // alpha from rz_old: the setup left it in its slot; later, it is the previous
+        // step's rz_new, still in that slot (through the stash: a copy cannot have the
+        // same buffer on both ends).
+        if self.iterations > 0 {
+            encoder.copy_buffer_to_buffer(scalars, SLOT_RZ_NEW as u64 * 4, &v.rz_stash, 0, 4);
+            encoder.copy_buffer_to_buffer(&v.rz_stash, 0, scalars, SLOT_RZ_OLD as u64 * 4, 4);
+        }
+        s.pcg_update_scalars_exec.encode_update_scalars(
+            ctx,
+            &mut encoder,
+            scalars,
+            SLOT_P_AP,
+            SLOT_RZ_NEW,
+            SLOT_RZ_OLD,
+            SLOT_ALPHA,
+            SLOT_MINUS_ALPHA,
+            SLOT_BETA,
+        );
+
+        // x += alpha p; r -= alpha Ap
+        s.vec_ops_exec.encode_axpy_inplace_from_scalar_results(
+            ctx,
+            &mut encoder,
+            &v.p.buffer,
+            &v.x.buffer,
+            n_u32,
+            scalars,
+            SLOT_ALPHA,
+        );
+        s.vec_ops_exec.encode_axpy_inplace_from_scalar_results(
+            ctx,
+            &mut encoder,
+            s.spmv_exec.y_buffer(),
+            &v.r.buffer,
+            n_u32,
+            scalars,
+            SLOT_MINUS_ALPHA,
+        );
+
+        // r = b - A x
+        if s.config
+            .options
+            .residual_strategy
+            .recompute_after(iteration)
+        {
+            s.spmv_exec
+                .encode_copy_x_from(&mut encoder, &v.x.buffer, n_bytes);
+            s.spmv_exec.encode_spmv(&mut encoder);
+            encoder.copy_buffer_to_buffer(&v.b.buffer, 0, &v.r.buffer, 0, n_bytes);
+            s.vec_ops_exec.encode_axpy_inplace(
+                ctx,
+                &mut encoder,
+                s.spmv_exec.y_buffer(),
+                &v.r.buffer,
+                n_u32,
+                -1.0,
+            );
+        }
+
+        // ||r||^2; z = M^-1 r; rz_new; beta; p = z + beta p
+        dot.encode_dot_scalar_into(
+            ctx,
+            &mut encoder,
+            &v.r.buffer,
+            &v.r.buffer,
+            n_u32,
+            SLOT_R_NORM2,
+        );
+        s.preconditioner
+            .encode_apply(ctx, &mut encoder, &v.r.buffer, &v.z.buffer);
+        dot.encode_dot_scalar_into(
+            ctx,
+            &mut encoder,
+            &v.r.buffer,
+            &v.z.buffer,
+            n_u32,
+            SLOT_RZ_NEW,
+        );
+        s.pcg_update_scalars_exec.encode_update_scalars(
+            ctx,
+            &mut encoder,
+            scalars,
+            SLOT_P_AP,
+            SLOT_RZ_NEW,
+            SLOT_RZ_OLD,
+            SLOT_ALPHA,
+            SLOT_MINUS_ALPHA,
+            SLOT_BETA,
+        );
+        s.vec_ops_exec.encode_xpby_inplace_from_scalar_results(
+            ctx,
+            &mut encoder,
+            &v.z.buffer,
+            &v.p.buffer,
+            n_u32,
+            scalars,
+            SLOT_BETA,
+        );
+
+        dot.encode_copy_scalar_results_to_readback(&mut encoder);
+        ctx.queue.submit(Some(encoder.finish()));
+        let scalar_results = executor::block_on(dot.try_readback_scalar_results(ctx))
+            .map_err(|e| s.readback_err(e))?;
+        self.iterations = iteration;
+        self.dispatches +=
+            6 + s.preconditioner.dispatches() + 3 * dot.dispatches_per_dot(n_u32) as u64;
+        if s.config
+            .options
+            .residual_strategy
+            .recompute_after(iteration)
+        {
+            self.dispatches += 2;
+        }
+
+        let p_ap = scalar_results[SLOT_P_AP as usize];
+        let r_norm2 = scalar_results[SLOT_R_NORM2 as usize];
+        let rz_new = scalar_results[SLOT_RZ_NEW as usize];
+        if p_ap <= 0.0 {
+            return Err(negative_curvature_error("PcgSolver", iteration, p_ap));
+        }
+        if self.rz_old == 0.0 {
+            return Err("PcgSolver: rz_old is zero (breakdown)".into());
+        }
+        self.rz_old = rz_new;
+
+        let converged = s.config.options.stopping_criterion.is_met(
+            r_norm2,
+            self.b_norm2,
+            self.r0_norm2,
+            s.config.rel_tol,
+            s.config.abs_tol,
+        );
+        let x = if self.with_iterates {
+            Some(self.solution()?)
+        } else {
+            None
+        };
+
+        Ok(SolveStep {
+            iteration,
+            residual_norm: r_norm2.sqrt(),
+            converged,
+            x,
+        })
+    }
+}
+
+/// The preconditioner's cached bind group over these vectors goes with them, whether
+/// they are the steps' own or the caller's workspace.
+impl Drop for PcgSteps<'_> {
+    fn drop(&mut self) {
+        self.solver.preconditioner.release_buffers();
+    }
+}
+
+impl Iterator for PcgSteps<'_> {
+    type Item = Result<SolveStep, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if self.iterations >= self.solver.config.max_iters {
+            self.done = true;
+            return Some(Err(format!(
+                "PcgSolver: did not converge in {} iterations{}",
+                self.solver.config.max_iters,
+                precision_floor_hint(self.below_floor)
+            )));
+        }
+
+        let step = self.step();
+        self.done = step.as_ref().map_or(true, |s| s.converged);
+        Some(step)
+    }
+}
@@ -0,0 +1,54 @@
+/// How far two vectors are apart (see [`compare`]). Sums run in f64.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Comparison {
+    pub n: usize,
+    /// max_i |a_i - b_i|
+    pub max_abs_diff: f64,
+    /// Index of `max_abs_diff`; None when the vectors are identical (or empty).
+    pub max_abs_diff_index: Option<usize>,
+    /// ||a - b||_2
+    pub l2_diff: f64,
+    /// ||a - b||_2 / ||a||_2, i.e. `a` is the reference; 0 for identical vectors and
+    /// infinite when a = 0 but b is not.
+    pub rel_l2_diff: f64,
+}
+
+/// Compare `b` against the reference `a` element by element.
+///
+/// A NaN in either vector counts as the largest disagreement: the first one found is
+/// reported and every norm comes out NaN.
+///
+/// Panics if the lengths differ.
+pub fn compare(a: &[f32], b: &[f32]) -> Comparison {
+    assert_eq!(a.len(), b.len(), "compare: length mismatch");
+
+    let mut max_abs_diff = 0.0f64;
+    let mut max_abs_diff_index = None;
+    let mut sum_sq_diff = 0.0f64;
+    let mut sum_sq_a = 0.0f64;
+
+    for (i, (&a_i, &b_i)) in a.iter().zip(b).enumerate() {
+        let d = (a_i as f64 - b_i as f64).abs();
+        if d > max_abs_diff || (d.is_nan() && !max_abs_diff.is_nan()) {
+            max_abs_diff = d;
+            max_abs_diff_index = Some(i);
+        }
+        sum_sq_diff += d * d;
+        sum_sq_a += (a_i as f64) * (a_i as f64);
+    }
+
+    let l2_diff = sum_sq_diff.sqrt();
+    let rel_l2_diff = if l2_diff == 0.0 {
+        0.0
+    } else {
+        l2_diff / sum_sq_a.sqrt()
+    };
+
+    Comparison {
+        n: a.len(),
+        max_abs_diff,
+        max_abs_diff_index,
+        l2_diff,
+        rel_l2_diff,
+    }
+}
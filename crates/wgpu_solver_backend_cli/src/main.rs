@@ -1,24 +1,102 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use futures::executor;
 use serde::Serialize;
 use serde_json::to_string_pretty;
 use std::fs::{self, File};
-use std::io::Read;
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
 use std::process;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use time::{OffsetDateTime, format_description::well_known::Rfc3339};
-use wgpu::{BufferUsages, CommandEncoderDescriptor};
-use wgpu_solver_backend::compute::block_jacobi_exec::BlockJacobiExecutor;
-use wgpu_solver_backend::compute::dot_scalar_exec::DotScalarExecutor;
+use wgpu::{
+    Backend, Backends, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, DeviceType,
+    Features, Instance, Limits,
+};
+use wgpu_solver_backend::compute::additive_schwarz_exec::{
+    AdditiveSchwarzExecutor, SchwarzCombine, SchwarzSubdomains,
+};
+use wgpu_solver_backend::compute::axpy::AxpyExecutor;
+use wgpu_solver_backend::compute::batched_dot::BatchedDotProduct;
+use wgpu_solver_backend::compute::block_jacobi::LuStorage;
+use wgpu_solver_backend::compute::block_jacobi_exec::{
+    BlockJacobiExecutor, BlockKind, MAX_BLOCK_SIZE, active_block_mask, f16_bits_to_f32,
+    f32_to_f16_bits,
+};
+use wgpu_solver_backend::compute::buffers::encode_write_f32_into_storage_buffer_at_index;
+use wgpu_solver_backend::compute::custom_metric::{CustomKernel, CustomStoppingMetric};
+use wgpu_solver_backend::compute::diagonal_jacobi_exec::DiagonalJacobiExecutor;
+use wgpu_solver_backend::compute::dot::{DEFAULT_HOST_REDUCE_THRESHOLD, DotProduct};
+use wgpu_solver_backend::compute::dot_partials::{
+    create_dot_partials_pipeline_with_workgroup_size, select_dot_subgroup_size,
+    try_create_dot_partials_pipeline_with_accumulator,
+};
+use wgpu_solver_backend::compute::dot_reduce::{
+    ReduceOp, create_dot_reduce_pipeline_with_workgroup_size,
+    try_create_dot_reduce_pipeline_with_accumulator,
+};
+use wgpu_solver_backend::compute::dot_scalar_exec::{DotScalarExecutor, ReadbackBuffering};
+use wgpu_solver_backend::compute::fixed_point_dot::{
+    DEFAULT_FIXED_POINT_FRAC_BITS, MAX_FIXED_POINT_FRAC_BITS,
+};
+use wgpu_solver_backend::compute::gmres::{GmresOptions, gmres_block_jacobi_csr_wgpu};
+use wgpu_solver_backend::compute::ilu0_exec::{Ilu0Executor, Ilu0Levels};
+use wgpu_solver_backend::compute::norms::{L2Norm, per_block_residual, weighted_norm};
+use wgpu_solver_backend::compute::observer::{SolverObserver, StderrObserver, VecObserver};
+use wgpu_solver_backend::compute::occupancy::compute_unit_hint;
+use wgpu_solver_backend::compute::operator::{CompositeOperator, IdentityOperator, LinearOperator};
 use wgpu_solver_backend::compute::pcg_update_scalars_exec::PcgUpdateScalarsExecutor;
+use wgpu_solver_backend::compute::precision_bench::{
+    PrecisionBenchOptions, PrecisionBenchReport, f64_supported, run_precision_bench,
+};
+use wgpu_solver_backend::compute::reorth::ReorthPolicy;
+use wgpu_solver_backend::compute::scale::ScaleExecutor;
+use wgpu_solver_backend::compute::shaders::{shipped_shaders, validate_wgsl};
+use wgpu_solver_backend::compute::spmv::{SPMV_WARP_ROW_MIN_AVG_NNZ, SpmvStrategy};
 use wgpu_solver_backend::compute::spmv_exec::SpmvExecutor;
+use wgpu_solver_backend::compute::ssor::{
+    SsorCgResult, SsorOptions, SsorPreconditioner, ssor_cg_csr_wgpu,
+};
+use wgpu_solver_backend::compute::ssor_exec::{SsorColoring, SsorExecutor};
 use wgpu_solver_backend::compute::vec_ops_exec::VecOpsExecutor;
+use wgpu_solver_backend::compute::xpby::XpbyExecutor;
 use wgpu_solver_backend::compute::{
-    build_lu_blocks_from_csr_block_starts_6, pcg_block_jacobi_csr_wgpu,
+    IterationTrace, PCG_SCALAR_RESULTS_LEN, PcgOptions, PcgResult, PcgTimings, Precision,
+    ResidualStrategy, SolveConfig, StoppingCriterion, WorkgroupSizes,
+    build_lu_blocks_from_csr_block_starts, build_lu_blocks_from_csr_block_starts_6,
+    capture_marker_label, f32_residual_floor, gather_diagonal_blocks_6, pcg_block_jacobi_csr_wgpu,
+    snapshot_file_name, tolerance_below_precision_floor,
 };
-use wgpu_solver_backend::gpu::context::{GpuBackend, GpuContext};
-use wgpu_solver_backend::gpu::readback::readback_to_vec;
-use wgpu_solver_backend::io::loaders::load_case_dir;
+use wgpu_solver_backend::device::SolverDevice;
+use wgpu_solver_backend::gpu::buffer_pool::BufferPool;
+use wgpu_solver_backend::gpu::checksum::{buffer_fnv, fnv1a_64, try_buffer_fnv};
+use wgpu_solver_backend::gpu::context::{
+    ADAPTER_INDEX_ENV_VAR, AdapterInfo, AdapterSelector, BACKEND_ENV_VAR, GpuBackend, GpuContext,
+    GpuContextBuilder, GpuError, resolve_adapter_index, resolve_backend,
+};
+use wgpu_solver_backend::gpu::readback::{
+    read_f32_buffer, read_f32_buffer_blocking, readback_to_vec,
+};
+use wgpu_solver_backend::gpu::submit::{DEFAULT_MAX_IN_FLIGHT_SUBMISSIONS, SubmissionWindow};
+use wgpu_solver_backend::gpu::timer::{GpuTimer, PassTiming, TimestampMode};
+use wgpu_solver_backend::io::IoError;
+use wgpu_solver_backend::io::loaders::{
+    CsrLoadOptions, load_block_starts_bin, load_case_dir, load_csr_matrix_bin,
+    load_csr_matrix_bin_with_options,
+};
+use wgpu_solver_backend::io::matrix_market::read_coo;
+use wgpu_solver_backend::io::npy::{
+    read_npy_f32, read_npy_f32_columns, write_npy_f32, write_npy_f32_columns,
+};
+use wgpu_solver_backend::io::png::write_grid_png;
+use wgpu_solver_backend::io::vector::{VectorFormat, read_f32, write_f32};
+use wgpu_solver_backend::matrix::{
+    Csr, CsrBuilder, apply_dirichlet, gershgorin_bounds, gershgorin_discs, laplacian_1d,
+    laplacian_2d, laplacian_3d, load_triplet_csv,
+};
+use wgpu_solver_backend::reference;
+use wgpu_solver_backend::solver::pcg::{PcgSolver, SolverWorkspace};
+use wgpu_solver_backend::util::{Comparison, compare};
 
 #[derive(Parser, Debug)]
 #[command(
@@ -27,8 +105,17 @@ use wgpu_solver_backend::io::loaders::load_case_dir;
     about = "Compute-first wgpu backend for iterative solvers"
 )]
 struct Cli {
-    #[arg(long, default_value = "auto")]
-    backend: String,
+    /// Backend (auto, vulkan, dx12, metal, gl/opengl, webgpu, cpu). Defaults to WGPU_SOLVER_BACKEND, else auto; an explicit "auto" ignores the variable.
+    #[arg(long, value_parser = parse_backend)]
+    backend: Option<GpuBackend>,
+
+    /// Adapter index (enumeration order). Defaults to WGPU_SOLVER_ADAPTER_INDEX, else auto.
+    #[arg(long)]
+    adapter_index: Option<usize>,
+
+    /// run-pcg-case: use the (slow) CPU reference backend if GPU init fails
+    #[arg(long, default_value_t = false)]
+    cpu_fallback: bool,
 
     #[command(subcommand)]
     cmd: Cmd,
@@ -38,12 +125,207 @@ struct Cli {
 enum Cmd {
     /// Print GPU adapter info and emit a metrics JSON blob (stdout).
     Info,
+    /// List every adapter the backend enumerates (all backends for "auto"), marking the
+    /// one the other commands would use.
+    ListAdapters {
+        /// Print a JSON array instead of one line per adapter
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// list-adapters marks exactly the adapter GpuContext creation picks
+    ListAdaptersTest,
+    /// GpuError classes map to distinct exit codes; a backend missing from this build exits with 3
+    GpuErrorTest,
     /// Sanity test for vec ops (AXPY): y = y + alpha * x
     VecTest,
+    /// AXPY over a sub-range of larger buffers; the rest of y must stay untouched.
+    AxpyRangeTest,
+    /// AxpyExecutor with a host alpha and with a GPU-computed alpha.
+    AxpyExecutorTest,
+    /// ScaleExecutor with a GPU-computed and a host alpha; elements past n stay untouched.
+    ScaleExecutorTest,
+    /// XpbyExecutor: y = x + beta y and w = alpha x + beta y with GPU scalars; elements past n stay untouched.
+    XpbyExecutorTest,
     DotTest,
+    /// DotProduct: one-call dot over several reduce levels, into its own and a caller buffer.
+    DotProductTest,
+    /// BatchedDotProduct: k packed pairs vs a CPU reference and DotProduct, dispatches independent of k
+    BatchedDotTest,
+    /// Small-n dots and weighted norms finish on the host and match the GPU reduction.
+    HostReduceTest,
+    /// Tree vs two-level dot reduce: identical sums for sizes around the switch-over
+    DotReduceTest,
+    /// Dot over one interleaved [a0, b0, a1, b1, ...] buffer vs the standard two-buffer dot
+    DotInterleavedTest,
+    /// On-device normalization: norm of the result is ~1, a zero vector stays zero
+    NormalizeTest,
+    /// L2Norm: ||x||_2 computed and square-rooted on the GPU vs the f64 host norm
+    L2NormTest,
+    /// util::compare statistics on known vectors, and the diff subcommand on .npy files
+    DiffTest,
+    /// Out-of-place scaled copy matches a buffer copy followed by an in-place scale
+    ScaledCopyTest,
+    /// Dot pipelines report the WORKGROUP_SIZE override they were created with
+    WorkgroupSizeTest,
     SpmvTest,
+    /// SpMV on seeded random CSR matrices (empty/full rows) vs a dense CPU reference
+    SpmvFuzzTest,
+    /// SpMV writes exact zeros for empty CSR rows (including nnz = 0)
+    SpmvEmptyRowsTest,
+    /// SpmvExecutor::try_create validation, encode_apply on caller buffers, rows past the dispatch limit
+    SpmvApplyTest,
+    /// Scalar-row and warp-row SpMV kernels vs the CPU on short, wide and empty rows; Auto picks by nnz per row
+    SpmvStrategyTest,
+    /// Csr::sort_rows on a scrambled matrix: SpMV and diagonal blocks unchanged, loader sorts by default
+    CsrSortTest,
+    /// Csr::has_duplicate_cols / sum_duplicates vs SpMV, CsrBuilder and the loader sum by default, strict mode rejects
+    CsrDuplicatesTest,
+    /// matrix::load_triplet_csv on 0- and 1-based dumps vs a dense reference, malformed lines rejected
+    TripletCsvTest,
+    /// io::matrix_market::read_coo: symmetric expansion, pattern files, CSR into SpMV, malformed headers/sizes rejected
+    MatrixMarketTest,
+    /// ILU apply with externally supplied L/U factors vs the CPU reference
+    Ilu0Test,
+    /// SSOR-CG with and without the Eisenstat trick: same iterations and solution, for several omega
+    SsorEisenstatTest,
+    /// SsorExecutor (multicolor SSOR): greedy and given colorings vs a CPU reference, PCG with it beats diagonal Jacobi
+    SsorExecutorTest,
+    /// AdditiveSchwarzExecutor (overlapping blocks) vs a CPU reference; overlap cuts PCG iterations on a 1D Laplacian
+    AdditiveSchwarzTest,
+    /// DiagonalJacobiExecutor apply (z = D^-1 r) vs the CPU elementwise product
+    DiagonalJacobiTest,
     BlockJacobiTest,
+    /// BlockJacobiExecutor::from_csr (diagonal blocks gathered from CSR, factored on the GPU) vs CPU block Jacobi
+    BlockJacobiFromCsrTest,
+    /// BlockJacobiExecutor apply bind-group cache: reused for a repeated r/z pair, rebuilt for new buffers
+    BlockJacobiBindGroupCacheTest,
+    /// BlockJacobiExecutor::from_csr_with_setup reports singular blocks (zero, rank-deficient, tiny pivot)
+    BlockJacobiSingularTest,
+    /// Rectangular block-Jacobi (per-block QR least squares) vs a CPU least-squares solve
+    BlockLsqTest,
+    /// Block-Jacobi apply with an active-block mask (zero-r blocks skipped) vs the full apply
+    BlockJacobiMaskTest,
+    /// CompositeOperator (M^-1 A, A M^-1) vs applying the two operators in sequence
+    CompositeOperatorTest,
+    /// SpmvExecutor::with_rank1_update (A + u v^T, one and two stacked updates) vs the dense product
+    Rank1UpdateTest,
+    /// gpu::checksum::buffer_fnv: identical uploads match, a single flipped bit differs
+    ChecksumTest,
+    /// Padded vs dense LU block layout: identical apply results (prints apply timings)
+    BlockJacobiAlignTest,
+    /// f16 LU block storage: conversion, apply vs the CPU on rounded blocks, f32 fallback without SHADER_F16
+    BlockJacobiF16Test,
+    /// Runtime LU block sizes (3, 9) vs the CPU apply, PCG with 9-row blocks, bad sizes rejected
+    BlockSizeTest,
+    /// BlockJacobiExecutor::create rejects malformed block_starts (start, order, end, blocks longer than bs)
+    BlockStartsTest,
     PcgUpdateScalarsTest,
+    /// PCG timing breakdown: buckets add up to the total solve time (wall-clock only without timestamps)
+    PcgTimingTest,
+    /// PcgTimings::kernel_launches match the dispatch math; compute-unit hint and override
+    PcgOccupancyTest,
+    /// PcgOptions::timing without timestamp queries: wall-clock iteration times, flagged approximate
+    PcgWallClockTimingTest,
+    /// Tolerance below the f32 precision floor is flagged in the result
+    PrecisionFloorTest,
+    /// Gershgorin bounds on matrices with hand-computed discs (no GPU needed)
+    GershgorinTest,
+    /// Finite-difference Laplacian structure and eigenpairs (no GPU needed)
+    LaplacianTest,
+    /// Every shipped WGSL shader and host variant parses and validates with naga (no GPU needed)
+    WgslValidateTest,
+    /// Inhomogeneous Dirichlet elimination: solved x equals the prescribed values
+    DirichletTest,
+    /// Backend / adapter-index precedence: explicit arg > env var > auto (no GPU needed)
+    EnvBackendTest,
+    /// GpuBackend::Gl / BrowserWebGpu: names parse, Gl opens a GL adapter where GL is built in, WebGPU off wasm is UnsupportedBackend
+    BackendVariantsTest,
+    /// Prometheus metrics output: HELP/TYPE + sample lines parse for converged and failed runs (no GPU needed)
+    PrometheusFormatTest,
+    /// Periodic .npy snapshots: expected file count, last one matches the final x
+    SnapshotTest,
+    /// PcgOptions::capture_at_iteration marks exactly one iteration
+    CaptureMarkerTest,
+    /// Custom GPU stopping metric (max |dx|) drives termination
+    CustomMetricTest,
+    /// CPU pseudo-backend: same PCG answer as the GPU path
+    CpuFallbackTest,
+    /// 2D .npy RHS block: every column solved, matching single-RHS solves
+    MultiRhsTest,
+    /// Simulate a GPU device loss mid-solve and check the recreate + retry path
+    DeviceLostTest,
+    /// Check the A-norm error history against a manufactured solution
+    ExactErrorTest,
+    /// Condition-number estimate from the CG coefficients on a known spectrum
+    ConditionEstimateTest,
+    /// Ritz values after every iteration stay inside the spectrum and end on its extremes
+    RitzHistoryTest,
+    /// PCG on an indefinite / negative definite matrix fails with a negative-curvature error
+    NegativeCurvatureTest,
+    /// Fixed-point dot: same bits on the device, the CPU backend and in reversed element order
+    FixedPointDotTest,
+    /// Windowed reorthogonalization cuts PCG iterations on an ill-conditioned problem
+    ReorthTest,
+    /// GMRES(m) with the Krylov basis offloaded to host memory matches the in-GPU basis bit for bit
+    GmresOffloadTest,
+    /// ResidualStrategy::AlwaysTrue never reports a tolerance the true residual misses (GPU or --backend cpu)
+    ResidualStrategyTest,
+    /// run-pcg-case --image/--grid: a 2D Poisson solution written as a grayscale PNG of the grid's size
+    GridImageTest,
+    /// PcgOptions::dot_precision F64: f64-accumulated dots vs all-f32 on an ill-conditioned solve (GPU or --backend cpu)
+    DotPrecisionTest,
+    /// PcgOptions::submit_every: batching iterations per submit leaves the solve unchanged
+    SubmitEveryTest,
+    /// Subgroup-reduce dot with explicit / env / adapter subgroup sizes (0 = tree) computes correct sums
+    SubgroupDotTest,
+    /// PcgSolver::iter_solve run to completion matches PcgSolver::solve bitwise
+    IterSolveTest,
+    /// PcgSolver::with_workspace: solves in a pre-allocated SolverWorkspace create no buffers and match PcgSolver::solve
+    WorkspaceTest,
+    /// SolveConfig: the default solves a 2D Laplacian, builder overrides (tol, max_iters, criterion, precision) take effect
+    SolveConfigTest,
+    /// SolverObserver: PCG and GMRES report every iteration's residual norm, equal to the residual history
+    ObserverTest,
+    /// BufferPool: buckets, hits / misses / bytes held, eviction; pooled PCG and GMRES solves match unpooled ones and stop allocating
+    BufferPoolTest,
+    /// SolverWorkspace / PcgSteps solution, residual and direction buffers hold the solve's vectors; extra usages are applied
+    SolverBuffersTest,
+    /// PcgSolver::with_preconditioner: diagonal Jacobi vs block Jacobi, batch and stepped solves agree
+    PreconditionerTest,
+    /// A supplied z0 = M^-1 r0 gives the same trajectory as the solve's own preconditioner apply
+    PrecomputedZ0Test,
+    /// GpuTimer timestamp period: read, override, and the scaled timings
+    TimestampPeriodTest,
+    /// StoppingCriterion::ReductionFactor stops at ||r|| / ||r0|| < factor, independent of ||b||
+    ReductionFactorTest,
+    /// Per-block residual norms (block_norms.wgsl) vs the CPU, block-Jacobi and irregular partitions
+    PerBlockResidualTest,
+    /// GpuTimer in encoder and pass-boundary mode gives positive, ordered durations
+    TimerFallbackTest,
+    /// GpuContext pass timing: labelled per-pass GPU times for the block-Jacobi and dot passes
+    PassTimingTest,
+    /// run-pcg-case / solve --profile: each profile resolves to its settings, individual flags override
+    ProfileTest,
+    /// GpuContextBuilder::deny_adapter / allow_only: denied adapters are skipped in selection
+    AdapterFilterTest,
+    /// AdapterSelector: by index, by name substring and by device-type preference
+    AdapterSelectorTest,
+    /// GpuContextBuilder::required_limits / required_features: granted, or rejected naming the limit
+    ContextLimitsTest,
+    /// GpuContext::set_label_prefix: crate-created buffers and pipelines carry the prefix
+    LabelPrefixTest,
+    /// --trace-file JSON Lines: one parseable line per iteration plus a summary line (GPU or --backend cpu)
+    TraceFileTest,
+    /// Check scalar readbacks with single and double staging buffers
+    ReadbackBufferingTest,
+    /// read_f32_buffer / read_f32_buffer_blocking: values, prefixes and usage/length errors
+    ReadF32BufferTest,
+    /// Check the mass-weighted norm ||r||_M against the CPU reference
+    WeightedNormTest,
+    /// Check that submissions in flight never exceed the configured window
+    SubmissionWindowTest,
+    /// f32 vs f64 precision bench on a small 2D Laplacian (f64 rows only where supported)
+    PrecisionBenchTest,
     /// Run PCG(Block-Jacobi) on a case directory (matrix.csr.bin, rhs.bin, x0.bin, block_starts.bin)
     RunPcgCase {
         /// Case directory containing matrix.csr.bin, rhs.bin, x0.bin, block_starts.bin
@@ -69,6 +351,207 @@ enum Cmd {
         /// Where to write metrics.json
         #[arg(long)]
         out_metrics: String,
+
+        /// Also write x as a grayscale PNG, reshaped to --grid: min(x) is black, max(x)
+        /// white, linear in between (see `io::png::normalize_to_gray8`)
+        #[arg(long, requires = "grid")]
+        image: Option<String>,
+
+        /// Grid of the unknowns for --image as WxH; entry x + W * y is pixel (x, y), top
+        /// row first (the ordering of `matrix::laplacian_2d(W, H)`)
+        #[arg(long, value_parser = parse_grid_size, requires = "image")]
+        grid: Option<GridSize>,
+
+        /// Collect a per-operation GPU timing breakdown (needs timestamp query support)
+        #[arg(long, default_value_t = false)]
+        timing: bool,
+
+        /// With --timing: nanoseconds per timestamp tick, overriding the driver's report
+        #[arg(long)]
+        timestamp_period_ns: Option<f32>,
+
+        /// With --timing: the device's compute-unit count (SMs / CUs / EUs) for the
+        /// per-kernel occupancy hints; wgpu cannot report it for GPUs
+        #[arg(long)]
+        compute_units: Option<u32>,
+
+        /// Write x as .npy every N iterations (and at convergence)
+        #[arg(long)]
+        snapshot_interval: Option<u32>,
+
+        /// Directory for the x_iter_<iteration>.npy snapshots
+        #[arg(long, default_value = "snapshots")]
+        snapshot_dir: String,
+
+        /// Format of the metrics written to --out-metrics (and stdout)
+        #[arg(long, value_enum, default_value_t = MetricsFormat::Json)]
+        metrics_format: MetricsFormat,
+
+        /// On GPU device loss, recreate the context and restart from the last snapshot
+        /// (or x0) up to N times
+        #[arg(long, default_value_t = 0)]
+        device_lost_retries: u32,
+
+        /// Insert a labeled empty pass ("pcg capture marker: iteration N") at iteration N,
+        /// for locating that iteration in a GPU debugger capture
+        #[arg(long)]
+        capture_at_iteration: Option<u32>,
+
+        /// Also write the run as JSON Lines: one object per iteration, then a summary
+        /// line (schema: `TraceLine`)
+        #[arg(long)]
+        trace_file: Option<String>,
+
+        /// Preset bundle of the solver knobs below (see `SolverSettings::for_profile`);
+        /// without it: block Jacobi, recurrence residual, no reorthogonalization
+        #[arg(long, value_enum)]
+        profile: Option<SolverProfile>,
+
+        /// Preconditioner (overrides --profile)
+        #[arg(long, value_enum)]
+        preconditioner: Option<PreconditionerChoice>,
+
+        /// Residual strategy: "recurrence", "every:<m>" (true residual every m
+        /// iterations) or "always" (overrides --profile)
+        #[arg(long, value_parser = parse_residual_strategy)]
+        residual_strategy: Option<ResidualStrategy>,
+
+        /// Reorthogonalize against the last K search directions, 0 = off (overrides
+        /// --profile)
+        #[arg(long)]
+        reorth_window: Option<u32>,
+
+        /// Record N iterations per GPU submit: 1 keeps the desktop most responsive, larger
+        /// values trade that for fewer round trips (see `PcgOptions::submit_every`)
+        #[arg(long, default_value_t = 1)]
+        submit_every: u32,
+
+        /// WORKGROUP_SIZE of the dot pipelines as PARTIALS[,REDUCE] (powers of two;
+        /// default 256,256; see `PcgOptions::dot_workgroup_sizes`)
+        #[arg(long, value_parser = parse_dot_workgroup_sizes)]
+        dot_workgroup_sizes: Option<(u32, u32)>,
+    },
+    /// Stay resident: build the solver for one matrix once, then solve one RHS frame from
+    /// stdin per request and write a solution frame to stdout (wire format: `serve_frames`)
+    Serve {
+        /// Case directory (matrix.csr.bin and block_starts.bin are used)
+        #[arg(
+            long,
+            required_unless_present = "matrix_market",
+            conflicts_with = "matrix_market"
+        )]
+        case_dir: Option<String>,
+
+        /// MatrixMarket coordinate file instead of a case directory, split into uniform
+        /// blocks of --block-size rows
+        #[arg(long)]
+        matrix_market: Option<String>,
+
+        /// Block size for --matrix-market
+        #[arg(long, default_value_t = 6)]
+        block_size: usize,
+
+        /// Max PCG iterations (per request)
+        #[arg(long, default_value_t = 2000)]
+        max_iters: usize,
+
+        /// Relative tolerance
+        #[arg(long, default_value_t = 1e-8)]
+        rel_tol: f32,
+
+        /// Absolute tolerance
+        #[arg(long, default_value_t = 0.0)]
+        abs_tol: f32,
+
+        /// Start each solve from the previous request's solution instead of x0 = 0
+        #[arg(long, default_value_t = false)]
+        warm_start: bool,
+    },
+    /// `serve` answers two piped RHS frames with the solutions of a direct solve
+    ServeTest,
+    /// Solve A x = b for a MatrixMarket matrix and a vector file with PCG; the solution
+    /// and convergence metrics go to stdout as JSON (`SolveCommandMetrics`)
+    Solve {
+        /// MatrixMarket coordinate file (square)
+        #[arg(long)]
+        matrix: String,
+
+        /// Right-hand side: `.npy` (f32), a MatrixMarket array (`.mtx`), or text with one
+        /// value per line or separated by whitespace / commas (`read_vector_text`)
+        #[arg(long)]
+        rhs: String,
+
+        /// Also write the solution here when the solve converges: a MatrixMarket array
+        /// for a `.mtx` extension, otherwise plain text with one value per line
+        #[arg(long)]
+        out: Option<String>,
+
+        /// Relative tolerance
+        #[arg(long, default_value_t = 1e-8)]
+        tol: f32,
+
+        /// Absolute tolerance
+        #[arg(long, default_value_t = 0.0)]
+        abs_tol: f32,
+
+        /// Max PCG iterations
+        #[arg(long, default_value_t = 2000)]
+        max_iter: usize,
+
+        /// Preset bundle of the solver knobs below, as for run-pcg-case; without it:
+        /// block Jacobi, recurrence residual, no reorthogonalization
+        #[arg(long, value_enum)]
+        profile: Option<SolverProfile>,
+
+        /// Preconditioner; block Jacobi uses uniform blocks of --block-size rows
+        /// (overrides --profile)
+        #[arg(long, value_enum)]
+        preconditioner: Option<PreconditionerChoice>,
+
+        /// Residual strategy: "recurrence", "every:<m>" (true residual every m
+        /// iterations) or "always" (overrides --profile)
+        #[arg(long, value_parser = parse_residual_strategy)]
+        residual_strategy: Option<ResidualStrategy>,
+
+        /// Reorthogonalize against the last K search directions, 0 = off (overrides
+        /// --profile)
+        #[arg(long)]
+        reorth_window: Option<u32>,
+
+        /// Rows per block for --preconditioner block-jacobi (the last block takes the
+        /// remainder)
+        #[arg(long, default_value_t = 6)]
+        block_size: usize,
+    },
+    /// `solve` on a MatrixMarket Laplacian + text RHS: JSON parses, x solves the system, failures reported
+    SolveTest,
+    /// io::vector write_f32 / read_f32 round-trip (plain text and MatrixMarket array, NaN / Inf), malformed files rejected
+    VectorIoTest,
+    /// Solve every column of an n×k .npy RHS block against a case's matrix (x0 = 0)
+    RunPcgMultiRhs {
+        /// Case directory (matrix.csr.bin and block_starts.bin are used)
+        #[arg(long)]
+        case_dir: String,
+
+        /// RHS block: .npy f32 array of shape (n,) or (n, k), one RHS per column
+        #[arg(long)]
+        rhs_npy: String,
+
+        /// Max PCG iterations (per column)
+        #[arg(long, default_value_t = 2000)]
+        max_iters: usize,
+
+        /// Relative tolerance
+        #[arg(long, default_value_t = 1e-8)]
+        rel_tol: f32,
+
+        /// Absolute tolerance
+        #[arg(long, default_value_t = 0.0)]
+        abs_tol: f32,
+
+        /// Where to write the (n, k) solution block as .npy
+        #[arg(long)]
+        out_x_npy: String,
     },
     /// Compare two solution vectors stored in .bin format (u32 len + f32[len])
     CompareX {
@@ -92,6 +575,223 @@ enum Cmd {
         #[arg(long, default_value_t = 10)]
         top_k: usize,
     },
+    /// Compare two .npy vectors: max abs diff (and where), L2 diff, relative L2 (a = reference)
+    Diff {
+        /// Reference vector (.npy f32, any shape, compared flattened)
+        a: String,
+        /// Vector to compare against it
+        b: String,
+    },
+    /// Per-iteration cost of SSOR-CG with and without the Eisenstat trick on a 2D Laplacian; JSON on stdout
+    SsorBench {
+        /// Grid side of the 2D Laplacian (n = grid^2, b = 1)
+        #[arg(long, default_value_t = 64)]
+        grid: usize,
+
+        /// SSOR relaxation factor, in (0, 2)
+        #[arg(long, default_value_t = 1.0)]
+        omega: f32,
+
+        /// Max CG iterations
+        #[arg(long, default_value_t = 2000)]
+        max_iters: usize,
+
+        /// Relative tolerance: ||r|| <= rel_tol * ||b||
+        #[arg(long, default_value_t = 1e-5)]
+        rel_tol: f32,
+    },
+    /// Time the scalar-row and warp-row SpMV kernels over average row lengths at fixed nnz; JSON on stdout
+    SpmvStrategyBench {
+        /// Non-zeros of every matrix (n = nnz / row length)
+        #[arg(long, default_value_t = 1 << 20)]
+        nnz: usize,
+
+        /// SpMV passes per timing (one submit, after a warm-up pass)
+        #[arg(long, default_value_t = 20)]
+        reps: usize,
+    },
+    /// Time dot / spmv / CG in f32 and f64 (where supported) on one matrix; JSON on stdout
+    PrecisionBench {
+        /// Case directory (matrix.csr.bin and rhs.bin are used); default: a 2D Laplacian
+        #[arg(long)]
+        case_dir: Option<String>,
+
+        /// Without --case-dir: grid side of the 2D Laplacian (n = grid^2, b = 1)
+        #[arg(long, default_value_t = 256)]
+        grid: usize,
+
+        /// Passes per dot / spmv timing
+        #[arg(long, default_value_t = 100)]
+        reps: usize,
+
+        /// Max CG iterations
+        #[arg(long, default_value_t = 2000)]
+        max_iters: usize,
+
+        /// Relative tolerance of the CG solve
+        #[arg(long, default_value_t = 1e-6)]
+        rel_tol: f64,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum MetricsFormat {
+    /// Pretty-printed JSON (SolveMetrics)
+    Json,
+    /// Prometheus text exposition format (node exporter textfile collector)
+    Prometheus,
+}
+
+/// `run-pcg-case --profile` / `solve --profile`: ready-made choices for users who don't
+/// want to pick every knob. Vectors and kernels are f32 PCG throughout, so the profiles
+/// differ in preconditioner, residual handling, reorthogonalization and the dot
+/// accumulator only.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum SolverProfile {
+    /// Point Jacobi, recurrence residual: cheapest iterations, fine for well-conditioned
+    /// problems
+    Fast,
+    /// The case's block Jacobi, true residual every 50 iterations: guards the stopping
+    /// test against f32 drift for ~2% extra SpMV work
+    Robust,
+    /// Block Jacobi, true residual every iteration, 8-direction reorthogonalization and
+    /// f64 dot accumulation (f32 on adapters without SHADER_F64): never reports a
+    /// tolerance it did not reach, at roughly twice the cost
+    Accurate,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum PreconditionerChoice {
+    /// Diagonal (point) Jacobi: block Jacobi with one-row blocks; block_starts.bin unused
+    Jacobi,
+    /// Block Jacobi over the case's block_starts.bin
+    BlockJacobi,
+}
+
+/// The solver knobs a profile bundles; resolved once per run-pcg-case / solve.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct SolverSettings {
+    preconditioner: PreconditionerChoice,
+    residual_strategy: ResidualStrategy,
+    reorthogonalize: ReorthPolicy,
+    dot_precision: Precision,
+}
+
+impl Default for SolverSettings {
+    /// No profile: the plain loop.
+    fn default() -> Self {
+        Self {
+            preconditioner: PreconditionerChoice::BlockJacobi,
+            residual_strategy: ResidualStrategy::Recurrence,
+            reorthogonalize: ReorthPolicy::None,
+            dot_precision: Precision::F32,
+        }
+    }
+}
+
+impl SolverSettings {
+    fn for_profile(profile: SolverProfile) -> Self {
+        match profile {
+            SolverProfile::Fast => Self {
+                preconditioner: PreconditionerChoice::Jacobi,
+                residual_strategy: ResidualStrategy::Recurrence,
+                reorthogonalize: ReorthPolicy::None,
+                dot_precision: Precision::F32,
+            },
+            SolverProfile::Robust => Self {
+                preconditioner: PreconditionerChoice::BlockJacobi,
+                residual_strategy: ResidualStrategy::TrueEvery(50),
+                reorthogonalize: ReorthPolicy::None,
+                dot_precision: Precision::F32,
+            },
+            SolverProfile::Accurate => Self {
+                preconditioner: PreconditionerChoice::BlockJacobi,
+                residual_strategy: ResidualStrategy::AlwaysTrue,
+                reorthogonalize: ReorthPolicy::Window(8),
+                dot_precision: Precision::F64,
+            },
+        }
+    }
+
+    /// Profile (or the defaults), then every flag that was given on top of it.
+    fn resolve(
+        profile: Option<SolverProfile>,
+        preconditioner: Option<PreconditionerChoice>,
+        residual_strategy: Option<ResidualStrategy>,
+        reorth_window: Option<u32>,
+    ) -> Self {
+        let mut settings = profile.map(Self::for_profile).unwrap_or_default();
+        if let Some(p) = preconditioner {
+            settings.preconditioner = p;
+        }
+        if let Some(r) = residual_strategy {
+            settings.residual_strategy = r;
+        }
+        if let Some(k) = reorth_window {
+            settings.reorthogonalize = match k {
+                0 => ReorthPolicy::None,
+                k => ReorthPolicy::Window(k),
+            };
+        }
+        settings
+    }
+
+    /// Fall back to f32 dots when `device` is a GPU without SHADER_F64 (the CPU reference
+    /// accumulates in f64 anyway); returns whether it did.
+    fn fit_to_device(&mut self, device: &SolverDevice) -> bool {
+        let unsupported = self.dot_precision != Precision::F32
+            && device
+                .gpu()
+                .is_some_and(|ctx| !self.dot_precision.supported_by(ctx));
+        if unsupported {
+            self.dot_precision = Precision::F32;
+        }
+        unsupported
+    }
+
+    /// The `PcgOptions` knobs of these settings (the preconditioner is not one of them).
+    fn apply_to(self, options: PcgOptions) -> PcgOptions {
+        PcgOptions {
+            residual_strategy: self.residual_strategy,
+            reorthogonalize: self.reorthogonalize,
+            dot_precision: self.dot_precision,
+            ..options
+        }
+    }
+}
+
+/// `--grid` extents of a 2D problem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GridSize {
+    width: usize,
+    height: usize,
+}
+
+/// `--grid` values: "<W>x<H>" with W, H >= 1.
+fn parse_grid_size(s: &str) -> Result<GridSize, String> {
+    match s
+        .split_once(['x', 'X'])
+        .map(|(w, h)| (w.parse::<usize>(), h.parse::<usize>()))
+    {
+        Some((Ok(width), Ok(height))) if width >= 1 && height >= 1 => {
+            Ok(GridSize { width, height })
+        }
+        _ => Err(format!("expected <W>x<H> with W, H >= 1, got {s:?}")),
+    }
+}
+
+/// `--residual-strategy` values: "recurrence", "every:<m>" (m >= 1), "always".
+fn parse_residual_strategy(s: &str) -> Result<ResidualStrategy, String> {
+    match s {
+        "recurrence" => Ok(ResidualStrategy::Recurrence),
+        "always" => Ok(ResidualStrategy::AlwaysTrue),
+        _ => match s.strip_prefix("every:").map(str::parse::<u32>) {
+            Some(Ok(m)) if m >= 1 => Ok(ResidualStrategy::TrueEvery(m)),
+            _ => Err(format!(
+                "expected recurrence, every:<m> (m >= 1) or always, got {s:?}"
+            )),
+        },
+    }
 }
 
 #[derive(Serialize)]
@@ -99,9 +799,17 @@ struct Metrics {
     run_id: String,
     command: String,
     gpu: GpuMetrics,
+    /// Per-pass GPU times of a small sample workload; null without TIMESTAMP_QUERY.
+    kernel_timings: Option<Vec<KernelTimingMetrics>>,
     build: BuildMetrics,
 }
 
+#[derive(Serialize)]
+struct KernelTimingMetrics {
+    pass: String,
+    elapsed_ns: u64,
+}
+
 #[derive(Serialize)]
 struct GpuMetrics {
     adapter_name: String,
@@ -130,15 +838,178 @@ struct SolveMetrics {
     abs_tol: f32,
 
     iterations: Option<usize>,
+    final_residual_norm: Option<f32>,
+    tolerance_below_precision_floor: Option<bool>,
+    dispatches: Option<u64>,
     converged: bool,
     error: Option<String>,
 
     timings_ms: TimingsMs,
+    pcg_timings_ms: Option<PcgTimingsMs>,
+    /// Tick length the GPU timings were converted with (reported or overridden).
+    timestamp_period_ns: Option<f32>,
+    workgroup_sizes: Option<WorkgroupSizesMetrics>,
 
     gpu: GpuMetrics,
     build: BuildMetrics,
 }
 
+/// What `solve` prints: the header of [`Metrics`] (run id, command, device, build) plus
+/// the `solve` section and the solution.
+#[derive(Serialize)]
+struct SolveCommandMetrics {
+    run_id: String,
+    command: String,
+    gpu: GpuMetrics,
+    build: BuildMetrics,
+    solve: SolveSectionMetrics,
+    /// The solution; null when the solve failed.
+    x: Option<Vec<f32>>,
+}
+
+#[derive(Serialize)]
+struct SolveSectionMetrics {
+    matrix: String,
+    rhs: String,
+    n: u32,
+    nnz: u32,
+    preconditioner: String,
+    max_iter: usize,
+    rel_tol: f32,
+    abs_tol: f32,
+
+    converged: bool,
+    iterations: Option<usize>,
+    final_residual_norm: Option<f32>,
+    /// Setup + solve, without loading the inputs or creating the device.
+    wall_time_ms: f64,
+    error: Option<String>,
+}
+
+/// One line of the `run-pcg-case --trace-file` JSON Lines file, tagged by `"type"`:
+///
+/// ```text
+/// {"type":"iteration","iter":1,"residual":0.73,"alpha":1.2,"beta":0.41,"elapsed_ms":3.1}
+/// ...
+/// {"type":"summary","run_id":"...","case_dir":"...","converged":true,"iterations":370,...}
+/// ```
+///
+/// Iteration lines come first, in order (from `PcgResult::iteration_trace`); `beta` is
+/// null on the last one and `elapsed_ms` counts from the start of the solve. The summary
+/// is always the last line. A solve that fails returns no trace and writes the summary
+/// only (with `converged: false` and `error`).
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TraceLine {
+    Iteration {
+        iter: usize,
+        residual: f32,
+        alpha: f32,
+        beta: Option<f32>,
+        elapsed_ms: f64,
+    },
+    Summary {
+        run_id: String,
+        case_dir: String,
+        n: u32,
+        converged: bool,
+        iterations: Option<usize>,
+        final_residual_norm: Option<f32>,
+        error: Option<String>,
+        solve_ms: f64,
+    },
+}
+
+/// Per-operation breakdown from `PcgResult::timings` (see `PcgTimings` docs).
+#[derive(Serialize)]
+struct PcgTimingsMs {
+    spmv: f64,
+    preconditioner: f64,
+    dots: f64,
+    vec_ops: f64,
+    readback: f64,
+    host_encode: f64,
+    setup: f64,
+    total: f64,
+    /// Wall-clock only: the device has no timestamp queries, so the GPU buckets are 0
+    /// and `readback` includes the GPU time.
+    approximate: bool,
+    /// Compute units the launches are compared with (null when unknown, see
+    /// `--compute-units`).
+    compute_units: Option<u32>,
+    /// Launch shape of each kernel of one iteration (`PcgTimings::kernel_launches`).
+    kernels: Vec<KernelLaunchMetrics>,
+}
+
+/// One entry of `PcgTimings::kernel_launches` plus the occupancy hint.
+#[derive(Serialize)]
+struct KernelLaunchMetrics {
+    kernel: &'static str,
+    workgroup_size: u32,
+    workgroups: u32,
+    dispatches_per_iteration: u32,
+    invocations: u64,
+    /// workgroups / compute_units; below 1 the kernel cannot fill the device.
+    workgroups_per_compute_unit: Option<f64>,
+    launch_bound: Option<bool>,
+}
+
+impl From<&PcgTimings> for PcgTimingsMs {
+    fn from(t: &PcgTimings) -> Self {
+        Self {
+            spmv: t.spmv_ms,
+            preconditioner: t.preconditioner_ms,
+            dots: t.dots_ms,
+            vec_ops: t.vec_ops_ms,
+            readback: t.readback_ms,
+            host_encode: t.host_encode_ms,
+            setup: t.setup_ms,
+            total: t.total_ms,
+            approximate: t.approximate,
+            compute_units: t.compute_units,
+            kernels: t
+                .kernel_launches
+                .iter()
+                .map(|k| KernelLaunchMetrics {
+                    kernel: k.kernel,
+                    workgroup_size: k.workgroup_size,
+                    workgroups: k.workgroups,
+                    dispatches_per_iteration: k.dispatches,
+                    invocations: k.invocations(),
+                    workgroups_per_compute_unit: t
+                        .compute_units
+                        .map(|cu| k.workgroups_per_compute_unit(cu)),
+                    launch_bound: t.compute_units.map(|cu| k.launch_bound(cu)),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Effective workgroup sizes from `PcgResult::workgroup_sizes` (absent on the CPU backend).
+#[derive(Serialize)]
+struct WorkgroupSizesMetrics {
+    dot_partials: u32,
+    dot_reduce: u32,
+    spmv: u32,
+    vec_ops: u32,
+    block_jacobi: u32,
+    pcg_update_scalars: u32,
+}
+
+impl From<&WorkgroupSizes> for WorkgroupSizesMetrics {
+    fn from(w: &WorkgroupSizes) -> Self {
+        Self {
+            dot_partials: w.dot_partials,
+            dot_reduce: w.dot_reduce,
+            spmv: w.spmv,
+            vec_ops: w.vec_ops,
+            block_jacobi: w.block_jacobi,
+            pcg_update_scalars: w.pcg_update_scalars,
+        }
+    }
+}
+
 #[derive(Serialize)]
 struct TimingsMs {
     total: u128,
@@ -149,412 +1020,10586 @@ struct TimingsMs {
     write_out: u128,
 }
 
-fn parse_backend(s: &str) -> GpuBackend {
-    match s.to_lowercase().as_str() {
-        "auto" => GpuBackend::Auto,
-        "vulkan" => GpuBackend::Vulkan,
-        "dx12" => GpuBackend::Dx12,
-        "metal" => GpuBackend::Metal,
-        other => {
-            eprintln!("Unknown backend '{other}', using auto");
-            GpuBackend::Auto
-        }
+/// Render the run-pcg-case metrics in Prometheus text exposition format.
+///
+/// Metric names (all gauges, prefix `wgpu_solver_`, base units per Prometheus naming):
+/// - `wgpu_solver_converged`: 1 if the solve converged, else 0
+/// - `wgpu_solver_iterations`: PCG iterations
+/// - `wgpu_solver_final_residual_norm`: ||r|| at exit
+/// - `wgpu_solver_wall_time_seconds`: end-to-end wall time of the command
+/// - `wgpu_solver_solve_time_seconds`: wall time of the solve alone
+/// - `wgpu_solver_dispatches`: compute dispatches recorded by the solve
+/// - `wgpu_solver_problem_size` / `wgpu_solver_nnz`: system size
+/// - `wgpu_solver_last_run_timestamp_seconds`: unix time the metrics were produced
+///
+/// Every sample carries `case`, `adapter` and `backend` labels. Values that don't
+/// exist for a failed run (iterations, residual, dispatches) are omitted.
+///
+/// The timestamp is a metric rather than a per-sample timestamp because the
+/// textfile collector rejects explicit sample timestamps.
+fn render_prometheus(m: &SolveMetrics, unix_time: i64) -> String {
+    fn escape(v: &str) -> String {
+        v.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
     }
-}
-
-fn now_utc_rfc3339() -> String {
-    OffsetDateTime::now_utc()
-        .format(&Rfc3339)
-        .unwrap_or_else(|_| "unknown-time".to_string())
-}
 
-fn run_vec_test(ctx: &GpuContext) {
-    let n: usize = 1024;
-    let alpha: f32 = 3.0;
+    let labels = format!(
+        "case=\"{}\",adapter=\"{}\",backend=\"{}\"",
+        escape(&m.case_dir),
+        escape(&m.gpu.adapter_name),
+        escape(&m.gpu.backend)
+    );
 
-    let x_host = vec![2.0f32; n];
-    let y_host = vec![1.0f32; n];
+    let mut out = String::new();
+    let mut gauge = |name: &str, help: &str, value: Option<f64>| {
+        if let Some(v) = value {
+            out.push_str(&format!("# HELP {name} {help}\n"));
+            out.push_str(&format!("# TYPE {name} gauge\n"));
+            out.push_str(&format!("{name}{{{labels}}} {v}\n"));
+        }
+    };
 
-    // Create GPU buffers.
-    // We want STORAGE for compute + COPY_SRC for readback + COPY_DST for init/updates.
-    let x = ctx.create_storage_buffer("x", &x_host, BufferUsages::empty());
-    let y = ctx.create_storage_buffer("y", &y_host, BufferUsages::empty());
+    gauge(
+        "wgpu_solver_converged",
+        "Whether the last solve converged (1) or failed (0).",
+        Some(if m.converged { 1.0 } else { 0.0 }),
+    );
+    gauge(
+        "wgpu_solver_iterations",
+        "PCG iterations of the last solve.",
+        m.iterations.map(|v| v as f64),
+    );
+    gauge(
+        "wgpu_solver_final_residual_norm",
+        "Residual 2-norm ||b - Ax|| at exit of the last solve.",
+        m.final_residual_norm.map(|v| v as f64),
+    );
+    gauge(
+        "wgpu_solver_wall_time_seconds",
+        "End-to-end wall time of the last run (load, init, solve, write).",
+        Some(m.timings_ms.total as f64 / 1e3),
+    );
+    gauge(
+        "wgpu_solver_solve_time_seconds",
+        "Wall time of the last solve.",
+        Some(m.timings_ms.solve as f64 / 1e3),
+    );
+    gauge(
+        "wgpu_solver_dispatches",
+        "Compute dispatches recorded by the last solve.",
+        m.dispatches.map(|v| v as f64),
+    );
+    gauge(
+        "wgpu_solver_problem_size",
+        "Number of unknowns of the last solve.",
+        Some(m.n as f64),
+    );
+    gauge(
+        "wgpu_solver_nnz",
+        "Nonzeros of the system matrix of the last solve.",
+        Some(m.nnz as f64),
+    );
+    gauge(
+        "wgpu_solver_last_run_timestamp_seconds",
+        "Unix time at which the last run finished.",
+        Some(unix_time as f64),
+    );
 
-    // Create executor (pipelines + uniform pool).
-    let vec_exec = VecOpsExecutor::create(ctx);
-    vec_exec.reset_params_cursor();
+    out
+}
 
-    // Encode.
-    let mut encoder = ctx
-        .device
-        .create_command_encoder(&CommandEncoderDescriptor {
-            label: Some("vec-test encoder"),
-        });
+/// Minimal check of Prometheus text format: every sample is `name{labels} value`,
+/// preceded by HELP and TYPE lines for that name. Returns the number of samples.
+fn validate_prometheus_text(text: &str) -> Result<usize, String> {
+    fn valid_name(name: &str) -> bool {
+        let mut chars = name.chars();
+        matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_' || c == ':')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+    }
 
-    vec_exec.encode_axpy_inplace(ctx, &mut encoder, &x.buffer, &y.buffer, n as u32, alpha);
+    let mut help: Option<String> = None;
+    let mut typed: Option<String> = None;
+    let mut samples = 0usize;
 
-    // Submit exactly once (matches your model).
-    ctx.queue.submit(Some(encoder.finish()));
+    for (line_no, line) in text.lines().enumerate() {
+        let err = |msg: &str| format!("line {}: {msg}: {line:?}", line_no + 1);
 
-    // Read back and assert.
-    let y_out = executor::block_on(ctx.readback(&y));
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("# HELP ") {
+            let name = rest.split(' ').next().unwrap_or("");
+            if !valid_name(name) {
+                return Err(err("bad metric name in HELP"));
+            }
+            help = Some(name.to_string());
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("# TYPE ") {
+            let mut parts = rest.split(' ');
+            let name = parts.next().unwrap_or("");
+            let ty = parts.next().unwrap_or("");
+            if help.as_deref() != Some(name) {
+                return Err(err("TYPE without matching HELP"));
+            }
+            if !matches!(
+                ty,
+                "gauge" | "counter" | "histogram" | "summary" | "untyped"
+            ) {
+                return Err(err("unknown metric type"));
+            }
+            typed = Some(name.to_string());
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
 
-    let expected = 1.0f32 + alpha * 2.0f32; // 7.0
-    for (i, v) in y_out.iter().enumerate() {
-        assert!(
-            (*v - expected).abs() < 1e-6,
-            "vec-test failed at i={i}: got {v}, expected {expected}"
-        );
+        let (series, value) = line.rsplit_once(' ').ok_or_else(|| err("missing value"))?;
+        let name = series.split('{').next().unwrap_or("");
+        if !valid_name(name) {
+            return Err(err("bad metric name"));
+        }
+        if typed.as_deref() != Some(name) {
+            return Err(err("sample without HELP/TYPE"));
+        }
+        if series.contains('{') && !series.ends_with('}') {
+            return Err(err("unterminated label set"));
+        }
+        value
+            .parse::<f64>()
+            .map_err(|_| err("value is not a number"))?;
+        samples += 1;
     }
 
-    println!("VecTest OK: all y[i] == {expected}");
+    Ok(samples)
 }
 
-fn run_dot_test(ctx: &GpuContext) {
-    // dot([1,2,3], [4,5,6]) = 1*4 + 2*5 + 3*6 = 32
-    let a = vec![1.0f32, 2.0, 3.0];
-    let b = vec![4.0f32, 5.0, 6.0];
-    let n = a.len() as u32;
+#[derive(Serialize)]
+struct PrecisionBenchMetrics {
+    run_id: String,
+    command: String,
+    gpu: GpuMetrics,
+    n: u32,
+    nnz: u32,
+    reps: usize,
+    rel_tol: f64,
+    rows: Vec<PrecisionBenchRowMetrics>,
+    /// Why there is no f64 row (device without SHADER_F64)
+    f64_skipped: Option<String>,
+    /// f64 / f32, only with both rows
+    f64_over_f32: Option<PrecisionRatiosMetrics>,
+}
 
-    let a_buf = ctx.create_storage_buffer("dot a", &a, BufferUsages::empty());
-    let b_buf = ctx.create_storage_buffer("dot b", &b, BufferUsages::empty());
+#[derive(Serialize)]
+struct PrecisionBenchRowMetrics {
+    precision: String,
+    dot_ms: f64,
+    dot_gb_per_s: f64,
+    spmv_ms: f64,
+    spmv_gb_per_s: f64,
+    solve_ms: f64,
+    iterations: usize,
+    converged: bool,
+    final_rel_residual: f64,
+}
 
-    // Allocate executor for up to n elements, and 4 scalar slots.
-    let exec = DotScalarExecutor::create(ctx, a.len(), 4);
-    exec.reset_params_cursor();
+#[derive(Serialize)]
+struct PrecisionRatiosMetrics {
+    dot_time: f64,
+    spmv_time: f64,
+    solve_time: f64,
+    final_rel_residual: f64,
+}
 
-    let mut encoder = ctx
-        .device
-        .create_command_encoder(&CommandEncoderDescriptor {
-            label: Some("dot-test encoder"),
-        });
+fn precision_bench_metrics(
+    ctx: &GpuContext,
+    csr: &Csr,
+    options: &PrecisionBenchOptions,
+    report: &PrecisionBenchReport,
+) -> PrecisionBenchMetrics {
+    PrecisionBenchMetrics {
+        run_id: now_utc_rfc3339(),
+        command: "precision-bench".to_string(),
+        gpu: GpuMetrics {
+            adapter_name: ctx.adapter_info.name.clone(),
+            backend: format!("{:?}", ctx.adapter_info.backend),
+            device_type: format!("{:?}", ctx.adapter_info.device_type),
+            vendor: ctx.adapter_info.vendor,
+            device: ctx.adapter_info.device,
+        },
+        n: csr.n_rows,
+        nnz: csr.nnz,
+        reps: options.reps,
+        rel_tol: options.rel_tol,
+        rows: report
+            .rows
+            .iter()
+            .map(|r| PrecisionBenchRowMetrics {
+                precision: r.precision.to_string(),
+                dot_ms: r.dot_ms,
+                dot_gb_per_s: r.dot_gb_per_s,
+                spmv_ms: r.spmv_ms,
+                spmv_gb_per_s: r.spmv_gb_per_s,
+                solve_ms: r.solve_ms,
+                iterations: r.iterations,
+                converged: r.converged,
+                final_rel_residual: r.final_rel_residual,
+            })
+            .collect(),
+        f64_skipped: report.f64_skipped.clone(),
+        f64_over_f32: report.ratios().map(|q| PrecisionRatiosMetrics {
+            dot_time: q.dot_time,
+            spmv_time: q.spmv_time,
+            solve_time: q.solve_time,
+            final_rel_residual: q.final_rel_residual,
+        }),
+    }
+}
 
-    exec.encode_dot_scalar_into(ctx, &mut encoder, &a_buf.buffer, &b_buf.buffer, n, 0);
-    exec.encode_copy_scalar_results_to_readback(&mut encoder);
+/// Exit status for a failed GPU context creation, by failure class, so that scripts can
+/// tell a machine without a usable GPU from a driver problem:
+///   2: configuration (backend / adapter selection, filters, environment variables)
+///   3: wrong machine: no adapter, the backend does not exist on this platform, or the
+///      adapter lacks a required limit / feature
+///   4: driver problem: an adapter was found but refused to create a device
+fn gpu_error_exit_code(e: &GpuError) -> i32 {
+    match e {
+        GpuError::NoAdapter { .. }
+        | GpuError::UnsupportedBackend { .. }
+        | GpuError::UnsupportedLimit { .. }
+        | GpuError::UnsupportedFeatures { .. } => 3,
+        GpuError::DeviceRequest(_) => 4,
+        GpuError::AdapterIndexOutOfRange { .. }
+        | GpuError::InvalidEnvVar { .. }
+        | GpuError::CpuBackend
+        | GpuError::AdapterDenied { .. }
+        | GpuError::NoPermittedAdapter { .. }
+        | GpuError::NoMatchingAdapter { .. } => 2,
+        GpuError::Readback(_) => 1,
+    }
+}
 
-    ctx.queue.submit(Some(encoder.finish()));
+fn exit_gpu_init_failed(e: &GpuError) -> ! {
+    eprintln!("Failed to init GPU context: {e}");
+    process::exit(gpu_error_exit_code(e));
+}
 
-    let scalars = futures::executor::block_on(exec.readback_scalar_results(ctx));
+fn adapter_metrics(info: &AdapterInfo) -> GpuMetrics {
+    GpuMetrics {
+        adapter_name: info.name.clone(),
+        backend: format!("{:?}", info.backend),
+        device_type: format!("{:?}", info.device_type),
+        vendor: info.vendor,
+        device: info.device,
+    }
+}
 
-    let got = scalars[0];
-    let expected = 32.0f32;
+/// Block-Jacobi factor + apply and one dot product on a 1D Laplacian of `n` rows, each
+/// pass timed through the context's pass timer. `None` without TIMESTAMP_QUERY.
+///
+/// Pass timing is switched off again afterwards.
+fn sample_kernel_timings(ctx: &GpuContext, n: usize) -> Option<Vec<PassTiming>> {
+    if !ctx.enable_pass_timing(16) {
+        return None;
+    }
+    let a = laplacian_1d(n);
+    let r: Vec<f32> = (0..n).map(|i| 1.0 + (i % 7) as f32).collect();
+    let r_gpu = ctx.create_storage_buffer("sample timings r", &r, BufferUsages::empty());
+    let z_gpu =
+        ctx.create_storage_buffer_uninit::<f32>("sample timings z", n, BufferUsages::empty());
 
-    assert!(
-        (got - expected).abs() < 1e-5,
-        "dot-test failed: got {got}, expected {expected}"
-    );
+    let bj = BlockJacobiExecutor::from_csr(ctx, &a, 6)
+        .unwrap_or_else(|e| panic!("sample kernel timings: {e}"));
+    let dot = DotScalarExecutor::create(ctx, n, 1);
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("sample timings encoder"),
+        });
+    bj.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+    dot.encode_dot_scalar_into(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer, n as u32, 0);
+    let timer = ctx.disable_pass_timing()?;
+    timer.encode_resolve(&mut encoder);
+    ctx.queue.submit(Some(encoder.finish()));
 
-    println!("DotTest OK: got {got}");
+    Some(
+        executor::block_on(timer.read_timings(ctx))
+            .unwrap_or_else(|e| panic!("sample kernel timings: {e}")),
+    )
 }
 
-fn run_spmv_test(ctx: &GpuContext) {
-    // 3x3 matrix:
-    // [ 10 0  2 ]
-    // [ 3  9  0 ]
-    // [ 0  7  8 ]
-    //
-    // CSR:
-    // row_ptr = [0, 2, 4, 6]
-    // col_idx = [0,2, 0,1, 1,2]
-    // vals    = [10,2, 3,9, 7,8]
-    //
-    // x = [1,2,3]
-    // y = [16, 21, 38]
-
-    let n_rows: u32 = 3;
-
-    let row_ptr: Vec<u32> = vec![0, 2, 4, 6];
-    let col_idx: Vec<u32> = vec![0, 2, 0, 1, 1, 2];
-    let values: Vec<f32> = vec![10.0, 2.0, 3.0, 9.0, 7.0, 8.0];
+/// One line of `list-adapters`.
+#[derive(Serialize)]
+struct AdapterListEntry {
+    index: usize,
+    #[serde(flatten)]
+    gpu: GpuMetrics,
+    /// The adapter GpuContext creation picks with the same --backend / --adapter-index.
+    selected: bool,
+}
 
-    let x_host: Vec<f32> = vec![1.0, 2.0, 3.0];
-    let expected: Vec<f32> = vec![16.0, 21.0, 38.0];
+/// Every adapter of `gpu_backend`, with the one context creation would use marked (none
+/// when the selection fails, e.g. an out-of-range index).
+fn adapter_list(
+    gpu_backend: GpuBackend,
+    adapter_index: Option<usize>,
+) -> Result<Vec<AdapterListEntry>, GpuError> {
+    let builder = GpuContextBuilder::new(gpu_backend).adapter_index(adapter_index);
+    let infos = executor::block_on(builder.enumerate())?;
+    let selected = builder.pick(&infos).ok();
+    Ok(infos
+        .iter()
+        .enumerate()
+        .map(|(index, info)| AdapterListEntry {
+            index,
+            gpu: adapter_metrics(info),
+            selected: selected == Some(index),
+        })
+        .collect())
+}
 
-    // Upload x as a standalone GPU buffer (like your iteration vectors).
-    let x_gpu = ctx.create_storage_buffer("spmv-test x_src", &x_host, BufferUsages::empty());
+fn run_list_adapters(gpu_backend: GpuBackend, adapter_index: Option<usize>, json: bool) {
+    let entries = adapter_list(gpu_backend, adapter_index).unwrap_or_else(|e| {
+        eprintln!("Failed to enumerate adapters: {e}");
+        process::exit(gpu_error_exit_code(&e));
+    });
 
-    // Create executor (owns CSR + internal x/y buffers).
-    let spmv = SpmvExecutor::create(ctx, n_rows, &row_ptr, &col_idx, &values);
+    if json {
+        println!("{}", to_string_pretty(&entries).unwrap());
+        return;
+    }
+    if entries.is_empty() {
+        println!("no adapters");
+    }
+    for e in &entries {
+        println!(
+            "[{}] {} (backend={}, type={}, vendor=0x{:04x}, device=0x{:04x}){}",
+            e.index,
+            e.gpu.adapter_name,
+            e.gpu.backend,
+            e.gpu.device_type,
+            e.gpu.vendor,
+            e.gpu.device,
+            if e.selected { "  <- selected" } else { "" }
+        );
+    }
+}
 
-    // Encode: copy x into internal x_buffer, then SpMV into internal y_buffer.
-    let mut encoder = ctx
-        .device
-        .create_command_encoder(&CommandEncoderDescriptor {
-            label: Some("spmv-test encoder"),
-        });
+fn run_gpu_error_test() {
+    // Failure classes and their exit codes.
+    let no_adapter = GpuError::NoAdapter {
+        backend: GpuBackend::Vulkan,
+    };
+    assert_eq!(gpu_error_exit_code(&no_adapter), 3);
+    assert!(
+        no_adapter.to_string().contains("--backend cpu"),
+        "gpu-error-test: {no_adapter}"
+    );
+    assert_eq!(gpu_error_exit_code(&GpuError::CpuBackend), 2);
+    assert_eq!(
+        gpu_error_exit_code(&GpuError::AdapterIndexOutOfRange {
+            index: 7,
+            available: 1
+        }),
+        2
+    );
 
-    // n_bytes = n_rows * sizeof(f32)
-    spmv.encode_copy_x_from(&mut encoder, &x_gpu.buffer, (n_rows as u64) * 4);
-    spmv.encode_spmv(&mut encoder);
+    // The CPU pseudo-backend has no GPU context: a configuration error.
+    let err = executor::block_on(GpuContext::create(GpuBackend::Cpu))
+        .expect_err("gpu-error-test: GPU context on the cpu backend");
+    assert!(matches!(err, GpuError::CpuBackend), "gpu-error-test: {err}");
+
+    // Backends wgpu was built without here fail up front, in process and as exit code 3.
+    let compiled = Instance::enabled_backend_features();
+    let missing: Vec<(&str, GpuBackend)> = [
+        ("vulkan", GpuBackend::Vulkan, Backends::VULKAN),
+        ("dx12", GpuBackend::Dx12, Backends::DX12),
+        ("metal", GpuBackend::Metal, Backends::METAL),
+    ]
+    .into_iter()
+    .filter(|(_, _, bits)| !compiled.intersects(*bits))
+    .map(|(name, backend, _)| (name, backend))
+    .collect();
+    let exe = std::env::current_exe().unwrap_or_else(|e| panic!("gpu-error-test: {e}"));
+    for &(name, backend) in &missing {
+        let err = executor::block_on(GpuContext::create(backend))
+            .expect_err("gpu-error-test: context on a backend that is not compiled in");
+        assert!(
+            matches!(err, GpuError::UnsupportedBackend { backend: b, .. } if b == backend),
+            "gpu-error-test: {name}: {err}"
+        );
+        let status = process::Command::new(&exe)
+            .args(["--backend", name, "info"])
+            .stdout(process::Stdio::null())
+            .stderr(process::Stdio::null())
+            .status()
+            .unwrap_or_else(|e| panic!("gpu-error-test: spawn: {e}"));
+        assert_eq!(
+            status.code(),
+            Some(3),
+            "gpu-error-test: info --backend {name}"
+        );
+    }
 
-    // Single submit (matches your model).
-    ctx.queue.submit(Some(encoder.finish()));
+    println!(
+        "GpuErrorTest OK: exit codes by failure class; backends not in this build ({}) exit with 3",
+        missing
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+}
 
-    // Read back y.
-    // y_buffer length is n_rows f32.
-    let y_out: Vec<f32> = executor::block_on(readback_to_vec::<f32>(
-        &ctx.device,
-        &ctx.queue,
-        spmv.y_buffer(),
-        n_rows as usize,
-        Some("spmv-test y readback"),
-    ));
+fn run_list_adapters_test(gpu_backend: GpuBackend, adapter_index: Option<usize>) {
+    let entries = adapter_list(gpu_backend, adapter_index)
+        .unwrap_or_else(|e| panic!("list-adapters-test failed: {e}"));
+    let selected: Vec<&AdapterListEntry> = entries.iter().filter(|e| e.selected).collect();
+    assert_eq!(
+        selected.len(),
+        1,
+        "list-adapters-test failed: {} adapters marked selected",
+        selected.len()
+    );
 
-    assert_eq!(y_out.len(), expected.len());
+    let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+        gpu_backend,
+        adapter_index,
+    ))
+    .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+    let used = adapter_metrics(&ctx.adapter_info);
+    assert_eq!(
+        (&selected[0].gpu.adapter_name, &selected[0].gpu.backend),
+        (&used.adapter_name, &used.backend),
+        "list-adapters-test failed: marked adapter differs from the one created"
+    );
 
-    for i in 0..expected.len() {
-        let got = y_out[i];
-        let exp = expected[i];
+    let json: serde_json::Value = serde_json::to_value(&entries).unwrap();
+    let first = &json
+        .as_array()
+        .expect("list-adapters-test: not a JSON array")[0];
+    for key in [
+        "index",
+        "adapter_name",
+        "backend",
+        "device_type",
+        "vendor",
+        "device",
+        "selected",
+    ] {
         assert!(
-            (got - exp).abs() < 1e-6,
-            "spmv-test failed at i={i}: got {got}, expected {exp}"
+            first.get(key).is_some(),
+            "list-adapters-test failed: JSON lacks {key}"
         );
     }
 
-    println!("SpmvTest OK: y == {:?}", y_out);
+    println!(
+        "ListAdaptersTest OK: {} adapter(s), selected [{}] {}",
+        entries.len(),
+        selected[0].index,
+        used.adapter_name
+    );
 }
 
-fn run_block_jacobi_test(ctx: &GpuContext) {
-    // Two 6x6 blocks => n = 12
-    let n: u32 = 12;
+fn solver_device_metrics(device: &SolverDevice) -> GpuMetrics {
+    match device {
+        SolverDevice::Gpu(ctx) => adapter_metrics(&ctx.adapter_info),
+        SolverDevice::Cpu => GpuMetrics {
+            adapter_name: device.describe(),
+            backend: "Cpu".to_string(),
+            device_type: "Cpu".to_string(),
+            vendor: 0,
+            device: 0,
+        },
+    }
+}
 
-    // block_starts: [0, 6, 12]
-    let block_starts: Vec<u32> = vec![0, 6, 12];
+fn parse_backend(s: &str) -> Result<GpuBackend, String> {
+    GpuBackend::parse(s).ok_or_else(|| {
+        format!("unknown backend '{s}' (auto, vulkan, dx12, metal, gl, opengl, webgpu, cpu)")
+    })
+}
 
-    // Identity LU blocks: 2 blocks * 36 floats each
-    let mut lu_blocks = vec![0.0f32; 2 * 36];
-    for b in 0..2 {
-        for i in 0..6 {
-            lu_blocks[b * 36 + i * 6 + i] = 1.0;
-        }
+/// The `--backend` value that parses back to `backend`.
+fn backend_arg(backend: GpuBackend) -> &'static str {
+    match backend {
+        GpuBackend::Auto => "auto",
+        GpuBackend::Vulkan => "vulkan",
+        GpuBackend::Dx12 => "dx12",
+        GpuBackend::Metal => "metal",
+        GpuBackend::Gl => "gl",
+        GpuBackend::BrowserWebGpu => "webgpu",
+        GpuBackend::Cpu => "cpu",
     }
+}
 
-    // r = [1..12]
-    let r_host: Vec<f32> = (1..=12).map(|v| v as f32).collect();
+fn now_utc_rfc3339() -> String {
+    OffsetDateTime::now_utc()
+        .format(&Rfc3339)
+        .unwrap_or_else(|_| "unknown-time".to_string())
+}
 
-    // Upload r and allocate z
-    let r_gpu = ctx.create_storage_buffer("bj-test r", &r_host, BufferUsages::empty());
-    let z_gpu =
-        ctx.create_storage_buffer_uninit::<f32>("bj-test z", n as usize, BufferUsages::COPY_SRC);
+fn run_vec_test(ctx: &GpuContext) {
+    let n: usize = 1024;
+    let alpha: f32 = 3.0;
 
-    // Create executor
-    let bj = BlockJacobiExecutor::create(ctx, n, &lu_blocks, &block_starts);
+    let x_host = vec![2.0f32; n];
+    let y_host = vec![1.0f32; n];
 
-    // Encode apply and submit once
+    // Create GPU buffers.
+    // We want STORAGE for compute + COPY_SRC for readback + COPY_DST for init/updates.
+    let x = ctx.create_storage_buffer("x", &x_host, BufferUsages::empty());
+    let y = ctx.create_storage_buffer("y", &y_host, BufferUsages::empty());
+
+    // Create executor (pipelines + uniform pool).
+    let vec_exec = VecOpsExecutor::create(ctx);
+    vec_exec.reset_params_cursor();
+
+    // Encode.
     let mut encoder = ctx
         .device
         .create_command_encoder(&CommandEncoderDescriptor {
-            label: Some("block-jacobi-test encoder"),
+            label: Some("vec-test encoder"),
         });
 
-    bj.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+    vec_exec.encode_axpy_inplace(ctx, &mut encoder, &x.buffer, &y.buffer, n as u32, alpha);
 
+    // Submit exactly once (matches your model).
     ctx.queue.submit(Some(encoder.finish()));
 
-    // Read back z
-    let z_out = executor::block_on(ctx.readback(&z_gpu));
+    // Read back and assert.
+    let y_out = executor::block_on(ctx.readback(&y));
 
-    // Assert z == r
-    for i in 0..(n as usize) {
-        let got = z_out[i];
-        let exp = r_host[i];
+    let expected = 1.0f32 + alpha * 2.0f32; // 7.0
+    for (i, v) in y_out.iter().enumerate() {
         assert!(
-            (got - exp).abs() < 1e-6,
-            "block-jacobi-test failed at i={i}: got {got}, expected {exp}"
+            (*v - expected).abs() < 1e-6,
+            "vec-test failed at i={i}: got {v}, expected {expected}"
         );
     }
 
-    println!("BlockJacobiTest OK: z == r (identity blocks)");
+    println!("VecTest OK: all y[i] == {expected}");
 }
 
-const PAP: u32 = 0;
-const RZ_NEW: u32 = 1;
-const RZ_OLD: u32 = 2;
-const ALPHA: u32 = 3;
-const MINUS_ALPHA: u32 = 4;
-const BETA: u32 = 5;
+fn run_axpy_range_test(ctx: &GpuContext) {
+    // x and y have different lengths and the slices sit at different, unaligned offsets.
+    let x_host: Vec<f32> = (0..700).map(|i| i as f32 * 0.5).collect();
+    let y_host: Vec<f32> = (0..1000).map(|i| 1000.0 - i as f32).collect();
+    let (x_offset, y_offset, len, alpha) = (37u32, 301u32, 413u32, -1.5f32);
 
-fn run_pcg_update_scalars_test(ctx: &GpuContext) {
-    // Scalar buffer with a few slots.
-    let scalar_len = 8usize;
+    let x = ctx.create_storage_buffer("axpy range x", &x_host, BufferUsages::empty());
+    let y = ctx.create_storage_buffer("axpy range y", &y_host, BufferUsages::empty());
 
-    // Host init: we set only the inputs; outputs can start at 0.
-    let mut host = vec![0.0f32; scalar_len];
+    let vec_exec = VecOpsExecutor::create(ctx);
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("axpy-range-test encoder"),
+        });
+    vec_exec
+        .encode_axpy_range(
+            ctx,
+            &mut encoder,
+            &x.buffer,
+            x_offset,
+            &y.buffer,
+            y_offset,
+            len,
+            alpha,
+        )
+        .unwrap_or_else(|e| panic!("axpy-range-test: {e}"));
+    ctx.queue.submit(Some(encoder.finish()));
+    let y_out = executor::block_on(ctx.readback(&y));
 
-    // pAp = 2, rz_old = 10, rz_new = 5
-    host[PAP as usize] = 2.0;
-    host[RZ_OLD as usize] = 10.0;
-    host[RZ_NEW as usize] = 5.0;
+    let slice = y_offset as usize..(y_offset + len) as usize;
+    for (i, (&got, &y0)) in y_out.iter().zip(&y_host).enumerate() {
+        if slice.contains(&i) {
+            let expected = y0 + alpha * x_host[i - y_offset as usize + x_offset as usize];
+            assert!(
+                (got - expected).abs() <= 1e-4 * expected.abs().max(1.0),
+                "axpy-range-test: y[{i}] = {got}, expected {expected}"
+            );
+        } else {
+            assert_eq!(
+                got.to_bits(),
+                y0.to_bits(),
+                "axpy-range-test: y[{i}] outside the range changed"
+            );
+        }
+    }
 
-    let scalar_gpu = ctx.create_storage_buffer(
-        "pcg_update_scalars_test scalar_results",
-        &host,
-        BufferUsages::COPY_SRC, // for readback
+    // Ranges past the end of either buffer, and x == y, are rejected before encoding.
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor { label: None });
+    for (xo, yo, n) in [(288, 0, 413), (0, 588, 413), (0, 0, 1001)] {
+        let err = vec_exec
+            .encode_axpy_range(ctx, &mut encoder, &x.buffer, xo, &y.buffer, yo, n, alpha)
+            .expect_err("axpy-range-test: out-of-range AXPY accepted");
+        assert!(
+            err.contains("exceeds"),
+            "axpy-range-test: unexpected error {err}"
+        );
+    }
+    vec_exec
+        .encode_axpy_range(ctx, &mut encoder, &y.buffer, 0, &y.buffer, 500, 10, alpha)
+        .expect_err("axpy-range-test: aliased x/y accepted");
+
+    println!(
+        "AxpyRangeTest OK: y[{y_offset}..{}] updated, other {} entries bit-identical",
+        y_offset + len,
+        y_host.len() - len as usize
     );
+}
 
-    let exec = PcgUpdateScalarsExecutor::create(ctx);
+fn run_dot_test(ctx: &GpuContext) {
+    // dot([1,2,3], [4,5,6]) = 1*4 + 2*5 + 3*6 = 32
+    let a = vec![1.0f32, 2.0, 3.0];
+    let b = vec![4.0f32, 5.0, 6.0];
+    let n = a.len() as u32;
+
+    let a_buf = ctx.create_storage_buffer("dot a", &a, BufferUsages::empty());
+    let b_buf = ctx.create_storage_buffer("dot b", &b, BufferUsages::empty());
+
+    // Allocate executor for up to n elements, and 4 scalar slots.
+    let exec = DotScalarExecutor::create(ctx, a.len(), 4);
     exec.reset_params_cursor();
 
     let mut encoder = ctx
         .device
         .create_command_encoder(&CommandEncoderDescriptor {
-            label: Some("pcg_update_scalars_test encoder"),
+            label: Some("dot-test encoder"),
         });
 
-    exec.encode_update_scalars(
-        ctx,
-        &mut encoder,
-        &scalar_gpu.buffer,
-        PAP,
-        RZ_NEW,
-        RZ_OLD,
-        ALPHA,
-        MINUS_ALPHA,
-        BETA,
-    );
+    exec.encode_dot_scalar_into(ctx, &mut encoder, &a_buf.buffer, &b_buf.buffer, n, 0);
+    exec.encode_copy_scalar_results_to_readback(&mut encoder);
 
     ctx.queue.submit(Some(encoder.finish()));
 
-    let out = executor::block_on(ctx.readback(&scalar_gpu));
+    let scalars = futures::executor::block_on(exec.readback_scalar_results(ctx));
 
-    let alpha = out[ALPHA as usize];
-    let minus_alpha = out[MINUS_ALPHA as usize];
+    let got = scalars[0];
+    let expected = 32.0f32;
+
+    assert!(
+        (got - expected).abs() < 1e-5,
+        "dot-test failed: got {got}, expected {expected}"
+    );
+
+    println!("DotTest OK: got {got}");
+}
+
+fn run_axpy_executor_test(ctx: &GpuContext) {
+    let n = 1000u32;
+    let x: Vec<f32> = (0..n).map(|i| (i % 17) as f32 - 8.0).collect();
+    let y: Vec<f32> = (0..n).map(|i| 0.25 * i as f32).collect();
+    let x_gpu = ctx.create_storage_buffer("axpy executor x", &x, BufferUsages::empty());
+    let y_gpu = ctx.create_storage_buffer("axpy executor y", &y, BufferUsages::empty());
+
+    // alpha = x·w with w = 1/n (the mean of x) computed on the GPU, then y += alpha * x
+    // and y += -0.5 * x in the same encoder: the scalar never leaves the device.
+    let w = vec![1.0 / n as f32; n as usize];
+    let w_gpu = ctx.create_storage_buffer("axpy executor w", &w, BufferUsages::empty());
+    let dot = DotProduct::create(ctx, n);
+    let axpy = AxpyExecutor::create(ctx, n);
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("axpy-executor-test encoder"),
+        });
+    dot.encode(ctx, &mut encoder, &x_gpu.buffer, &w_gpu.buffer);
+    let alpha_gpu = dot.result_buffer();
+    axpy.encode(ctx, &mut encoder, alpha_gpu, &x_gpu.buffer, &y_gpu.buffer);
+    axpy.encode_scalar(ctx, &mut encoder, -0.5, &x_gpu.buffer, &y_gpu.buffer);
+    ctx.queue.submit(Some(encoder.finish()));
+    let y_out = executor::block_on(ctx.readback(&y_gpu));
+
+    let alpha: f32 = x.iter().zip(&w).map(|(a, b)| a * b).sum();
+    for (i, ((&got, &y0), &x0)) in y_out.iter().zip(&y).zip(&x).enumerate() {
+        let expected = y0 + alpha * x0 - 0.5 * x0;
+        assert!(
+            (got - expected).abs() <= 1e-4 * expected.abs().max(1.0),
+            "axpy-executor-test: y[{i}] = {got}, expected {expected}"
+        );
+    }
+
+    println!("AxpyExecutorTest OK: n={n}, GPU alpha {alpha} then host alpha -0.5");
+}
+
+fn run_scale_executor_test(ctx: &GpuContext) {
+    // x is longer than n: the tail must come back unchanged.
+    let n = 1000u32;
+    let len = n as usize + 37;
+    let x: Vec<f32> = (0..len).map(|i| (i % 13) as f32 - 4.0).collect();
+    let x_gpu = ctx.create_storage_buffer("scale executor x", &x, BufferUsages::empty());
+
+    // alpha = x[..n]·w with w = 1/n computed on the GPU, then x *= alpha and x *= -0.5 in
+    // the same encoder.
+    let w = vec![1.0 / n as f32; n as usize];
+    let w_gpu = ctx.create_storage_buffer("scale executor w", &w, BufferUsages::empty());
+    let dot = DotProduct::create(ctx, n);
+    let scale = ScaleExecutor::create(ctx, n);
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("scale-executor-test encoder"),
+        });
+    dot.encode(ctx, &mut encoder, &x_gpu.buffer, &w_gpu.buffer);
+    let alpha_gpu = dot.result_buffer();
+    scale.encode(ctx, &mut encoder, alpha_gpu, &x_gpu.buffer);
+    scale.encode_scalar(ctx, &mut encoder, -0.5, &x_gpu.buffer);
+    ctx.queue.submit(Some(encoder.finish()));
+    let x_out = executor::block_on(ctx.readback(&x_gpu));
+
+    let alpha: f32 = x[..n as usize].iter().zip(&w).map(|(a, b)| a * b).sum();
+    assert!(
+        alpha.abs() > 1e-3,
+        "scale-executor-test: alpha {alpha} too small to test"
+    );
+    for (i, (&got, &x0)) in x_out.iter().zip(&x).enumerate() {
+        let expected = if i < n as usize {
+            -0.5 * alpha * x0
+        } else {
+            x0
+        };
+        assert!(
+            (got - expected).abs() <= 1e-4 * expected.abs().max(1.0),
+            "scale-executor-test: x[{i}] = {got}, expected {expected}"
+        );
+    }
+
+    println!(
+        "ScaleExecutorTest OK: n={n}, GPU alpha {alpha} then host alpha -0.5, {} tail elements untouched",
+        len - n as usize
+    );
+}
+
+fn run_xpby_executor_test(ctx: &GpuContext) {
+    // The vectors are longer than n: their tails must come back unchanged.
+    let n = 1000u32;
+    let len = n as usize + 37;
+    let x: Vec<f32> = (0..len).map(|i| (i % 13) as f32 - 4.0).collect();
+    let y: Vec<f32> = (0..len).map(|i| 0.5 - (i % 7) as f32 * 0.25).collect();
+    let w0 = vec![7.0f32; len];
+    let (alpha, beta) = (-1.5f32, 0.75f32);
+
+    let x_gpu = ctx.create_storage_buffer("xpby executor x", &x, BufferUsages::empty());
+    let y_gpu = ctx.create_storage_buffer("xpby executor y", &y, BufferUsages::empty());
+    let w_gpu = ctx.create_storage_buffer("xpby executor w", &w0, BufferUsages::empty());
+    let w2_gpu = ctx.create_storage_buffer("xpby executor w2", &w0, BufferUsages::empty());
+    let beta_gpu = ctx.create_storage_buffer("xpby executor beta", &[beta], BufferUsages::empty());
+    let scalars_gpu = ctx.create_storage_buffer(
+        "xpby executor alpha beta",
+        &[alpha, beta],
+        BufferUsages::empty(),
+    );
+
+    // y = x + beta y, then w = alpha x + beta y (the updated y) and w2 = alpha x + beta x
+    // (x and y aliased), all in one encoder.
+    let xpby = XpbyExecutor::create(ctx, n);
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("xpby-executor-test encoder"),
+        });
+    xpby.encode(
+        ctx,
+        &mut encoder,
+        &beta_gpu.buffer,
+        &x_gpu.buffer,
+        &y_gpu.buffer,
+    );
+    xpby.encode_waxpby(
+        ctx,
+        &mut encoder,
+        &scalars_gpu.buffer,
+        &x_gpu.buffer,
+        &y_gpu.buffer,
+        &w_gpu.buffer,
+    );
+    xpby.encode_waxpby(
+        ctx,
+        &mut encoder,
+        &scalars_gpu.buffer,
+        &x_gpu.buffer,
+        &x_gpu.buffer,
+        &w2_gpu.buffer,
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+    let y_out = executor::block_on(ctx.readback(&y_gpu));
+    let w_out = executor::block_on(ctx.readback(&w_gpu));
+    let w2_out = executor::block_on(ctx.readback(&w2_gpu));
+
+    let check = |name: &str, got: &[f32], expected: &dyn Fn(usize) -> f32, tail: &[f32]| {
+        for (i, &got) in got.iter().enumerate() {
+            let expected = if i < n as usize { expected(i) } else { tail[i] };
+            assert!(
+                (got - expected).abs() <= 1e-5 * expected.abs().max(1.0),
+                "xpby-executor-test: {name}[{i}] = {got}, expected {expected}"
+            );
+        }
+    };
+    let y_new = |i: usize| x[i] + beta * y[i];
+    check("y", &y_out, &y_new, &y);
+    check("w", &w_out, &|i| alpha * x[i] + beta * y_new(i), &w0);
+    check("w2", &w2_out, &|i| (alpha + beta) * x[i], &w0);
+
+    println!(
+        "XpbyExecutorTest OK: n={n}, xpby beta {beta}, waxpby alpha {alpha} beta {beta} (also with x = y), {} tail elements untouched",
+        len - n as usize
+    );
+}
+
+fn run_batched_dot_test(ctx: &GpuContext) {
+    let mut rng = SplitMix64(0xba7c);
+    // (n, k, dispatches): 1 pass up to n = 256, one more per reduce level, none for n = 0.
+    let cases = [
+        (0u32, 2u32, 0u32),
+        (5, 1, 1),
+        (256, 3, 1),
+        (1000, 4, 2),
+        (70_000, 3, 3),
+    ];
+    for (n, k, expected_dispatches) in cases {
+        let batched = BatchedDotProduct::create(ctx, n, k)
+            .unwrap_or_else(|e| panic!("batched-dot-test: n={n} k={k}: {e}"));
+        assert_eq!((batched.n(), batched.k()), (n, k));
+        assert_eq!(
+            batched.dispatches(),
+            expected_dispatches,
+            "batched-dot-test failed: n={n} k={k}: dispatches"
+        );
+
+        let len = (n * k) as usize;
+        let a: Vec<f32> = (0..len).map(|_| rng.next_f32() - 0.5).collect();
+        let b: Vec<f32> = (0..len).map(|_| rng.next_f32() - 0.5).collect();
+        let a_gpu = ctx.create_storage_buffer("batched-dot-test a", &a, BufferUsages::empty());
+        let b_gpu = ctx.create_storage_buffer("batched-dot-test b", &b, BufferUsages::empty());
+        let results_gpu = ctx.create_storage_buffer(
+            "batched-dot-test results",
+            &vec![f32::NAN; k as usize],
+            BufferUsages::empty(),
+        );
+        let run = |x_gpu: &wgpu::Buffer, y_gpu: &wgpu::Buffer| {
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&CommandEncoderDescriptor {
+                    label: Some("batched-dot-test encoder"),
+                });
+            batched.encode(ctx, &mut encoder, x_gpu, y_gpu, &results_gpu.buffer);
+            ctx.queue.submit(Some(encoder.finish()));
+            executor::block_on(ctx.readback(&results_gpu))
+        };
+
+        // a_j · b_j against the host (f64) and against one DotProduct per pair.
+        let got = run(&a_gpu.buffer, &b_gpu.buffer);
+        let n_usize = n as usize;
+        let single = (n > 0).then(|| DotProduct::create(ctx, n));
+        for (j, &got_j) in got.iter().enumerate() {
+            let range = j * n_usize..(j + 1) * n_usize;
+            let (aj, bj) = (&a[range.clone()], &b[range.clone()]);
+            let expected: f64 = aj.iter().zip(bj).map(|(x, y)| *x as f64 * *y as f64).sum();
+            let scale: f64 = aj
+                .iter()
+                .zip(bj)
+                .map(|(x, y)| (*x as f64 * *y as f64).abs())
+                .sum();
+            assert!(
+                (got_j as f64 - expected).abs() <= 1e-5 * scale.max(1e-30),
+                "batched-dot-test failed: n={n} k={k}: results[{j}] = {}, expected {expected}",
+                got_j
+            );
+            if let Some(single) = &single {
+                let pair = |v: &[f32], name: &str| {
+                    ctx.create_storage_buffer(name, v, BufferUsages::empty())
+                };
+                let one = executor::block_on(single.compute(
+                    ctx,
+                    &pair(aj, "batched-dot-test aj").buffer,
+                    &pair(bj, "batched-dot-test bj").buffer,
+                ))
+                .unwrap_or_else(|e| panic!("batched-dot-test: DotProduct: {e}"));
+                assert!(
+                    (got_j - one).abs() <= 1e-5 * scale.max(1e-30) as f32,
+                    "batched-dot-test failed: n={n} k={k}: results[{j}] = {}, DotProduct {one}",
+                    got_j
+                );
+            }
+        }
+
+        // The same buffer on both sides: k squared norms.
+        let norms = run(&a_gpu.buffer, &a_gpu.buffer);
+        for (j, &norm) in norms.iter().enumerate() {
+            let expected: f64 = a[j * n_usize..(j + 1) * n_usize]
+                .iter()
+                .map(|x| *x as f64 * *x as f64)
+                .sum();
+            assert!(
+                (norm as f64 - expected).abs() <= 1e-5 * expected.max(1e-30),
+                "batched-dot-test failed: n={n} k={k}: ||a_{j}||^2 = {}, expected {expected}",
+                norm
+            );
+        }
+    }
+
+    match BatchedDotProduct::create(ctx, 100, 0) {
+        Err(e) => assert!(e.contains("k must be >= 1"), "batched-dot-test: {e}"),
+        Ok(_) => panic!("batched-dot-test failed: k = 0 accepted"),
+    }
+
+    println!(
+        "BatchedDotTest OK: {} (n, k) shapes up to n=70000 match the CPU and DotProduct, 0-3 dispatches regardless of k",
+        cases.len()
+    );
+}
+
+fn run_dot_product_test(ctx: &GpuContext) {
+    // Long enough for several reduce levels (n / 256 partials > 256).
+    let n = 200_003usize;
+    let a: Vec<f32> = (0..n)
+        .map(|i| ((i * 37) % 101) as f32 * 0.01 - 0.5)
+        .collect();
+    let b: Vec<f32> = (0..n)
+        .map(|i| ((i * 13) % 89) as f32 * 0.02 - 0.9)
+        .collect();
+    let expected: f64 = a.iter().zip(&b).map(|(&x, &y)| x as f64 * y as f64).sum();
+
+    let a_gpu = ctx.create_storage_buffer("dot product a", &a, BufferUsages::empty());
+    let b_gpu = ctx.create_storage_buffer("dot product b", &b, BufferUsages::empty());
+    let out_ab =
+        ctx.create_storage_buffer_uninit::<f32>("dot product a·b", 1, BufferUsages::empty());
+    let out_aa =
+        ctx.create_storage_buffer_uninit::<f32>("dot product a·a", 1, BufferUsages::empty());
+
+    let dot = DotProduct::create(ctx, n as u32);
+    let got = executor::block_on(dot.compute(ctx, &a_gpu.buffer, &b_gpu.buffer))
+        .unwrap_or_else(|e| panic!("dot-product-test: {e}"));
+    let tol = 1e-4
+        * a.iter()
+            .zip(&b)
+            .map(|(&x, &y)| (x * y).abs() as f64)
+            .sum::<f64>();
+    assert!(
+        (got as f64 - expected).abs() <= tol,
+        "dot-product-test: got {got}, expected {expected}"
+    );
+
+    // Two dots in one encoder, each copied out to its own caller buffer.
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("dot-product-test encoder"),
+        });
+    dot.encode_into(
+        ctx,
+        &mut encoder,
+        &a_gpu.buffer,
+        &b_gpu.buffer,
+        &out_ab.buffer,
+    );
+    dot.encode_into(
+        ctx,
+        &mut encoder,
+        &a_gpu.buffer,
+        &a_gpu.buffer,
+        &out_aa.buffer,
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+    let ab = executor::block_on(ctx.readback(&out_ab))[0];
+    let aa = executor::block_on(ctx.readback(&out_aa))[0];
+    let expected_aa: f64 = a.iter().map(|&x| x as f64 * x as f64).sum();
+
+    assert_eq!(
+        ab.to_bits(),
+        got.to_bits(),
+        "dot-product-test: encode_into differs from compute"
+    );
+    assert!(
+        (aa as f64 - expected_aa).abs() <= 1e-4 * expected_aa,
+        "dot-product-test: a·a = {aa}, expected {expected_aa}"
+    );
+
+    println!("DotProductTest OK: n={n} a·b={got} (f64 {expected:.6}), a·a={aa}");
+}
+
+fn run_host_reduce_test(ctx: &GpuContext) {
+    for n in [
+        1u32,
+        7,
+        DEFAULT_HOST_REDUCE_THRESHOLD - 1,
+        DEFAULT_HOST_REDUCE_THRESHOLD,
+    ] {
+        let a: Vec<f32> = (0..n)
+            .map(|i| ((i * 37) % 101) as f32 * 0.01 - 0.5)
+            .collect();
+        let b: Vec<f32> = (0..n)
+            .map(|i| ((i * 13) % 89) as f32 * 0.02 - 0.9)
+            .collect();
+        let a_gpu = ctx.create_storage_buffer("host reduce a", &a, BufferUsages::empty());
+        let b_gpu = ctx.create_storage_buffer("host reduce b", &b, BufferUsages::empty());
+
+        let dot = DotProduct::create(ctx, n);
+        let mut gpu_dot = DotProduct::create(ctx, n);
+        gpu_dot.set_host_reduce_threshold(0);
+        let host_path = n < DEFAULT_HOST_REDUCE_THRESHOLD;
+        assert_eq!(
+            dot.compute_dispatches() == 0,
+            host_path,
+            "host-reduce-test: n={n} dispatches {}",
+            dot.compute_dispatches()
+        );
+        assert!(gpu_dot.compute_dispatches() > 0);
+
+        let got = executor::block_on(dot.compute(ctx, &a_gpu.buffer, &b_gpu.buffer))
+            .unwrap_or_else(|e| panic!("host-reduce-test: {e}"));
+        let on_gpu = executor::block_on(gpu_dot.compute(ctx, &a_gpu.buffer, &b_gpu.buffer))
+            .unwrap_or_else(|e| panic!("host-reduce-test: {e}"));
+        let scale: f32 = a.iter().zip(&b).map(|(x, y)| (x * y).abs()).sum();
+        assert!(
+            (got - on_gpu).abs() <= 1e-5 * scale.max(1.0),
+            "host-reduce-test: n={n} host {got} vs GPU {on_gpu}"
+        );
+
+        // The host path never touches the result slot (zero-initialized), the GPU one does.
+        let slot = executor::block_on(readback_to_vec::<f32>(
+            &ctx.device,
+            &ctx.queue,
+            dot.result_buffer(),
+            1,
+            None,
+        ))[0];
+        if host_path {
+            assert_eq!(
+                slot, 0.0,
+                "host-reduce-test: n={n} wrote the GPU result slot"
+            );
+        } else {
+            assert_eq!(slot.to_bits(), got.to_bits());
+        }
+    }
+
+    // weighted_norm below the threshold: SpMV on the GPU, dot on the host.
+    let csr = laplacian_1d(DEFAULT_HOST_REDUCE_THRESHOLD as usize / 2);
+    let n = csr.n_rows as usize;
+    let r: Vec<f32> = (0..n).map(|i| ((i * 7) % 11) as f32 - 5.0).collect();
+    let spmv = SpmvExecutor::create(ctx, csr.n_rows, &csr.row_ptr, &csr.col_idx, &csr.values);
+    let dot_exec = DotScalarExecutor::create(ctx, n, 1);
+    let r_gpu = ctx.create_storage_buffer("host reduce r", &r, BufferUsages::empty());
+    let got = weighted_norm(ctx, &spmv, &dot_exec, &r_gpu.buffer);
+    let expected = reference::weighted_norm(&csr.row_ptr, &csr.col_idx, &csr.values, &r);
+    assert!(
+        (got - expected).abs() <= 1e-5 * expected,
+        "host-reduce-test: weighted norm {got} vs CPU {expected}"
+    );
+
+    println!(
+        "HostReduceTest OK: n < {DEFAULT_HOST_REDUCE_THRESHOLD} reduces on the host with no dispatch; ||r||_A = {got}"
+    );
+}
+
+fn run_dot_interleaved_test(ctx: &GpuContext) {
+    // Interleaved and deinterleaved inputs go through the same partial/reduce tree, so
+    // the two dots must agree bit for bit.
+    let n_max = 100_003usize;
+    let exec = DotScalarExecutor::create(ctx, n_max, 2);
+
+    for n in [1usize, 3, 256, 257, n_max] {
+        let a: Vec<f32> = (0..n)
+            .map(|i| ((i * 37) % 101) as f32 * 0.013 - 0.6)
+            .collect();
+        let b: Vec<f32> = (0..n)
+            .map(|i| ((i * 53) % 97) as f32 * 0.021 - 1.0)
+            .collect();
+        let ab: Vec<f32> = a.iter().zip(&b).flat_map(|(&x, &y)| [x, y]).collect();
+
+        let a_buf = ctx.create_storage_buffer("dot-interleaved a", &a, BufferUsages::empty());
+        let b_buf = ctx.create_storage_buffer("dot-interleaved b", &b, BufferUsages::empty());
+        let ab_buf = ctx.create_storage_buffer("dot-interleaved ab", &ab, BufferUsages::empty());
+
+        exec.reset_params_cursor();
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("dot-interleaved-test encoder"),
+            });
+        exec.encode_dot_scalar_into(ctx, &mut encoder, &a_buf.buffer, &b_buf.buffer, n as u32, 0);
+        exec.encode_dot_scalar_interleaved_into(ctx, &mut encoder, &ab_buf.buffer, n as u32, 1);
+        exec.encode_copy_scalar_results_to_readback(&mut encoder);
+        ctx.queue.submit(Some(encoder.finish()));
+
+        let scalars = futures::executor::block_on(exec.readback_scalar_results(ctx));
+        assert_eq!(
+            scalars[0].to_bits(),
+            scalars[1].to_bits(),
+            "dot-interleaved-test failed (n={n}): standard {} vs interleaved {}",
+            scalars[0],
+            scalars[1]
+        );
+        let cpu: f64 = a.iter().zip(&b).map(|(&x, &y)| x as f64 * y as f64).sum();
+        assert!(
+            (scalars[1] as f64 - cpu).abs() <= 1e-4 * cpu.abs().max(1.0),
+            "dot-interleaved-test failed (n={n}): GPU {} vs CPU {cpu}",
+            scalars[1]
+        );
+    }
+
+    println!(
+        "DotInterleavedTest OK: interleaved dot == standard dot (bitwise) for n up to {n_max}"
+    );
+}
+
+fn run_dot_reduce_test(ctx: &GpuContext) {
+    // Small integer-valued inputs keep every partial sum exactly representable in f32,
+    // so the tree and the two-level reduce must agree bit for bit regardless of the
+    // order in which they add things up.
+    //
+    // The threshold is lowered to 300 partials so that sizes on both sides of the
+    // switch-over stay cheap to run.
+    let threshold: u32 = 300;
+    let sizes: [usize; 6] = [
+        256 * 299,
+        256 * 300,
+        256 * 300 + 1,
+        256 * 301,
+        256 * 1000 + 17,
+        256 * 2048,
+    ];
+    let n_max = *sizes.iter().max().unwrap();
+
+    let mut tree = DotScalarExecutor::create(ctx, n_max, 1);
+    tree.set_two_level_reduce_threshold(u32::MAX);
+    let mut two_level = DotScalarExecutor::create(ctx, n_max, 1);
+    two_level.set_two_level_reduce_threshold(threshold);
+
+    for n in sizes {
+        let a: Vec<f32> = (0..n).map(|i| (i % 7) as f32 - 3.0).collect();
+        let b: Vec<f32> = (0..n).map(|i| (i % 5) as f32).collect();
+        let expected: f64 = a
+            .iter()
+            .zip(&b)
+            .map(|(x, y)| (*x as f64) * (*y as f64))
+            .sum();
+
+        let a_buf = ctx.create_storage_buffer("dot-reduce a", &a, BufferUsages::empty());
+        let b_buf = ctx.create_storage_buffer("dot-reduce b", &b, BufferUsages::empty());
+
+        let mut got = [0.0f32; 2];
+        for (slot, exec) in [&tree, &two_level].into_iter().enumerate() {
+            exec.reset_params_cursor();
+
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&CommandEncoderDescriptor {
+                    label: Some("dot-reduce-test encoder"),
+                });
+            exec.encode_dot_scalar_into(
+                ctx,
+                &mut encoder,
+                &a_buf.buffer,
+                &b_buf.buffer,
+                n as u32,
+                0,
+            );
+            exec.encode_copy_scalar_results_to_readback(&mut encoder);
+            ctx.queue.submit(Some(encoder.finish()));
+
+            got[slot] = executor::block_on(exec.readback_scalar_results(ctx))[0];
+        }
+
+        assert!(
+            got[0].to_bits() == got[1].to_bits(),
+            "dot-reduce-test failed for n={n}: tree {} vs two-level {}",
+            got[0],
+            got[1]
+        );
+        assert!(
+            got[0] as f64 == expected,
+            "dot-reduce-test failed for n={n}: got {}, expected {expected}",
+            got[0]
+        );
+    }
+
+    println!(
+        "DotReduceTest OK: tree and two-level sums match for {} sizes",
+        sizes.len()
+    );
+}
+
+fn run_l2_norm_test(ctx: &GpuContext) {
+    let mut worst = 0.0f64;
+    for n in [1usize, 63, 1000, 100_000] {
+        let x: Vec<f32> = (0..n).map(|i| ((i % 13) as f32 - 6.0) * 0.37).collect();
+        let expected = x
+            .iter()
+            .map(|&v| (v as f64) * (v as f64))
+            .sum::<f64>()
+            .sqrt();
+        let x_gpu = ctx.create_storage_buffer("l2-norm x", &x, BufferUsages::empty());
+        let norm = L2Norm::create(ctx, n as u32);
+
+        let got = executor::block_on(norm.compute(ctx, &x_gpu.buffer))
+            .unwrap_or_else(|e| panic!("l2-norm-test: {e}"));
+        let err = (got as f64 - expected).abs() / expected.max(1.0);
+        worst = worst.max(err);
+        assert!(
+            err <= 1e-5,
+            "l2-norm-test failed (n={n}): GPU {got} vs {expected}"
+        );
+    }
+
+    // Into caller buffers, twice in one encoder: x, then 2 x (and a zero vector).
+    let n = 5000;
+    let x: Vec<f32> = (0..n).map(|i| (i % 7) as f32 - 3.0).collect();
+    let x2: Vec<f32> = x.iter().map(|v| 2.0 * v).collect();
+    let x_gpu = ctx.create_storage_buffer("l2-norm x", &x, BufferUsages::empty());
+    let x2_gpu = ctx.create_storage_buffer("l2-norm 2x", &x2, BufferUsages::empty());
+    let zero_gpu =
+        ctx.create_storage_buffer("l2-norm zero", &vec![0.0f32; n], BufferUsages::empty());
+    let norm = L2Norm::create(ctx, n as u32);
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("l2-norm-test encoder"),
+        });
+    let outs: Vec<_> = (0..3)
+        .map(|i| {
+            ctx.create_storage_buffer_uninit::<f32>(
+                &format!("l2-norm out {i}"),
+                1,
+                BufferUsages::empty(),
+            )
+        })
+        .collect();
+    norm.encode(ctx, &mut encoder, &x_gpu.buffer, &outs[0].buffer);
+    norm.encode(ctx, &mut encoder, &x2_gpu.buffer, &outs[1].buffer);
+    norm.encode(ctx, &mut encoder, &zero_gpu.buffer, &outs[2].buffer);
+    ctx.queue.submit(Some(encoder.finish()));
+    let got: Vec<f32> = outs
+        .iter()
+        .map(|o| executor::block_on(ctx.readback(o))[0])
+        .collect();
+    let expected = x.iter().map(|v| v * v).sum::<f32>().sqrt();
+    assert_eq!(
+        got[0], expected,
+        "l2-norm-test failed: ||x|| {} vs {expected}",
+        got[0]
+    );
+    assert_eq!(
+        got[1],
+        2.0 * got[0],
+        "l2-norm-test failed: ||2x|| != 2 ||x||"
+    );
+    assert_eq!(got[2], 0.0, "l2-norm-test failed: ||0|| = {}", got[2]);
+
+    println!(
+        "L2NormTest OK: GPU-side sqrt(x.x) matches the host norm (max rel err {worst:.2e}), three encodes per submit"
+    );
+}
+
+fn run_normalize_test(ctx: &GpuContext) {
+    // Slots: 0 = ||x||^2 before, 1 = ||x||^2 after normalization.
+    // Everything between upload and the final scalar readback stays on the GPU.
+    let n: usize = 5000;
+    let x_host: Vec<f32> = (0..n).map(|i| ((i % 13) as f32) - 6.0).collect();
+
+    let x = ctx.create_storage_buffer("normalize x", &x_host, BufferUsages::empty());
+    let zero = ctx.create_storage_buffer("normalize zero", &vec![0.0f32; n], BufferUsages::empty());
+
+    let vec_exec = VecOpsExecutor::create(ctx);
+    let dot_exec = DotScalarExecutor::create(ctx, n, 4);
+    vec_exec.reset_params_cursor();
+    dot_exec.reset_params_cursor();
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("normalize-test encoder"),
+        });
+
+    let scalars_buf = dot_exec.scalar_results_buffer();
+    let n_u32 = n as u32;
+
+    dot_exec.encode_dot_scalar_into(ctx, &mut encoder, &x.buffer, &x.buffer, n_u32, 0);
+    vec_exec.encode_normalize_inplace_from_scalar_results(
+        ctx,
+        &mut encoder,
+        &x.buffer,
+        n_u32,
+        scalars_buf,
+        0,
+    );
+    dot_exec.encode_dot_scalar_into(ctx, &mut encoder, &x.buffer, &x.buffer, n_u32, 1);
+
+    // Zero vector: norm 0 must give zeros, not NaN.
+    dot_exec.encode_dot_scalar_into(ctx, &mut encoder, &zero.buffer, &zero.buffer, n_u32, 2);
+    vec_exec.encode_normalize_inplace_from_scalar_results(
+        ctx,
+        &mut encoder,
+        &zero.buffer,
+        n_u32,
+        scalars_buf,
+        2,
+    );
+
+    dot_exec.encode_copy_scalar_results_to_readback(&mut encoder);
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let scalars = executor::block_on(dot_exec.readback_scalar_results(ctx));
+    let norm_after = scalars[1].sqrt();
+    assert!(
+        (norm_after - 1.0).abs() < 1e-4,
+        "normalize-test failed: ||x|| after normalization = {norm_after}"
+    );
+
+    let zero_out = executor::block_on(ctx.readback(&zero));
+    assert!(
+        zero_out.iter().all(|v| *v == 0.0),
+        "normalize-test failed: zero vector did not stay zero"
+    );
+
+    println!(
+        "NormalizeTest OK: ||x|| {} -> {norm_after}",
+        scalars[0].sqrt()
+    );
+}
+
+fn run_scaled_copy_test(ctx: &GpuContext) {
+    // dst = scale * src in one dispatch must equal copy(src -> dst) followed by
+    // dst = dst * scale (the scale read from a scalar slot), bit for bit: both are a
+    // single f32 multiply per element. n is not a multiple of the workgroup size so the
+    // tail guard is exercised too.
+    let n: usize = 3001;
+    let scale: f32 = -0.37;
+    let src_host: Vec<f32> = (0..n).map(|i| ((i % 17) as f32) * 0.25 - 2.0).collect();
+
+    let src = ctx.create_storage_buffer("scaled-copy src", &src_host, BufferUsages::empty());
+    // Garbage in the destinations: the scaled copy must overwrite every element.
+    let fused = ctx.create_storage_buffer(
+        "scaled-copy fused",
+        &vec![f32::NAN; n],
+        BufferUsages::empty(),
+    );
+    let two_step = ctx.create_storage_buffer(
+        "scaled-copy two-step",
+        &vec![f32::NAN; n],
+        BufferUsages::empty(),
+    );
+    let scalar = ctx.create_storage_buffer("scaled-copy scalar", &[scale], BufferUsages::empty());
+
+    let vec_exec = VecOpsExecutor::create(ctx);
+    vec_exec.reset_params_cursor();
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("scaled-copy-test encoder"),
+        });
+
+    vec_exec.encode_scaled_copy(
+        ctx,
+        &mut encoder,
+        &src.buffer,
+        &fused.buffer,
+        n as u32,
+        scale,
+    );
+
+    encoder.copy_buffer_to_buffer(&src.buffer, 0, &two_step.buffer, 0, src.byte_len());
+    vec_exec.encode_scale_inplace_from_scalar_results(
+        ctx,
+        &mut encoder,
+        &two_step.buffer,
+        n as u32,
+        &scalar.buffer,
+        0,
+    );
+
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let fused_out = executor::block_on(ctx.readback(&fused));
+    let two_step_out = executor::block_on(ctx.readback(&two_step));
+    let src_out = executor::block_on(ctx.readback(&src));
+
+    for i in 0..n {
+        assert!(
+            fused_out[i].to_bits() == two_step_out[i].to_bits(),
+            "scaled-copy-test failed at i={i}: scaled copy {} vs copy + scale {}",
+            fused_out[i],
+            two_step_out[i]
+        );
+    }
+    assert!(
+        src_out == src_host,
+        "scaled-copy-test failed: source vector was modified"
+    );
+
+    println!("ScaledCopyTest OK: {n} elements match copy + scale (scale = {scale})");
+}
+
+fn run_read_f32_buffer_test(ctx: &GpuContext) {
+    let values: Vec<f32> = (0..1000).map(|i| i as f32 * 0.5 - 7.0).collect();
+    let buf = ctx.create_storage_buffer("read-f32 src", &values, BufferUsages::empty());
+
+    let all = executor::block_on(read_f32_buffer(ctx, &buf.buffer, values.len()))
+        .unwrap_or_else(|e| panic!("read-f32-buffer-test failed: {e}"));
+    assert_eq!(all, values, "read-f32-buffer-test failed: full read");
+    let before = ctx.buffers_created();
+    let prefix = read_f32_buffer_blocking(ctx, &buf.buffer, 10)
+        .unwrap_or_else(|e| panic!("read-f32-buffer-test failed: {e}"));
+    assert_eq!(prefix, values[..10], "read-f32-buffer-test failed: prefix");
+    assert_eq!(
+        ctx.buffers_created(),
+        before + 1,
+        "read-f32-buffer-test failed: staging buffer not counted"
+    );
+    assert!(
+        read_f32_buffer_blocking(ctx, &buf.buffer, 0)
+            .unwrap()
+            .is_empty()
+    );
+
+    // Too long, and a buffer that cannot be copied from, are errors (not wgpu panics).
+    match read_f32_buffer_blocking(ctx, &buf.buffer, values.len() + 1) {
+        Err(GpuError::Readback(msg)) => assert!(
+            msg.contains("1001"),
+            "read-f32-buffer-test failed: unexpected error {msg}"
+        ),
+        other => panic!("read-f32-buffer-test failed: overlong read gave {other:?}"),
+    }
+    let no_copy = ctx.device.create_buffer(&BufferDescriptor {
+        label: Some("read-f32 no COPY_SRC"),
+        size: 64,
+        usage: BufferUsages::STORAGE,
+        mapped_at_creation: false,
+    });
+    assert!(
+        matches!(
+            read_f32_buffer_blocking(ctx, &no_copy, 4),
+            Err(GpuError::Readback(_))
+        ),
+        "read-f32-buffer-test failed: buffer without COPY_SRC accepted"
+    );
+
+    println!("ReadF32BufferTest OK: async and blocking reads match, bad length/usage rejected");
+}
+
+fn run_readback_buffering_test(ctx: &GpuContext) {
+    let n = 10_000usize;
+    let a: Vec<f32> = (0..n).map(|i| (i % 3) as f32).collect();
+    let ones = vec![1.0f32; n];
+    let expected: f32 = a.iter().sum();
+
+    let a_buf = ctx.create_storage_buffer("readback-buffering a", &a, BufferUsages::empty());
+    let ones_buf = ctx.create_storage_buffer("readback-buffering 1", &ones, BufferUsages::empty());
+
+    for buffering in [ReadbackBuffering::Single, ReadbackBuffering::Double] {
+        let mut exec = DotScalarExecutor::create(ctx, n, 2);
+        exec.set_readback_buffering(ctx, buffering);
+        assert_eq!(exec.readback_buffering(), buffering);
+
+        // Slot 1 changes every iteration: reading a stale staging buffer would show k - 1.
+        for k in 0..64u32 {
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&CommandEncoderDescriptor {
+                    label: Some("readback-buffering-test encoder"),
+                });
+            exec.encode_dot_scalar_into(
+                ctx,
+                &mut encoder,
+                &a_buf.buffer,
+                &ones_buf.buffer,
+                n as u32,
+                0,
+            );
+            encode_write_f32_into_storage_buffer_at_index(
+                ctx,
+                &mut encoder,
+                exec.scalar_results_buffer(),
+                1,
+                k as f32,
+                "readback-buffering-test k",
+            );
+            exec.encode_copy_scalar_results_to_readback(&mut encoder);
+            ctx.queue.submit(Some(encoder.finish()));
+
+            let got = executor::block_on(exec.readback_scalar_results(ctx));
+            assert_eq!(
+                got,
+                vec![expected, k as f32],
+                "readback-buffering-test failed: {buffering:?}, iteration {k}"
+            );
+        }
+    }
+
+    // A full solve reads the same scalars either way, so it follows the same path.
+    let n = 1500;
+    let (row_ptr, col_idx, values) = tridiagonal_test_matrix(n);
+    let block_starts = uniform_block_starts(n, 6);
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 5) as f32 * 0.5).collect();
+    let solve = |buffering| {
+        let options = PcgOptions {
+            readback_buffering: buffering,
+            ..Default::default()
+        };
+        solve_test_system(
+            ctx,
+            &row_ptr,
+            &col_idx,
+            &values,
+            &block_starts,
+            &b,
+            2000,
+            1e-5,
+            &options,
+        )
+        .unwrap_or_else(|e| panic!("readback-buffering-test: {buffering:?} solve failed: {e}"))
+    };
+    let (single, x_single) = solve(ReadbackBuffering::Single);
+    let (double, x_double) = solve(ReadbackBuffering::Double);
+    assert_eq!(single.iterations, double.iterations);
+    assert!(
+        x_single
+            .iter()
+            .zip(&x_double)
+            .all(|(p, q)| p.to_bits() == q.to_bits()),
+        "readback-buffering-test failed: single vs double solutions differ"
+    );
+
+    println!(
+        "ReadbackBufferingTest OK: 64 scalar readbacks per mode, solve identical ({} iterations)",
+        single.iterations
+    );
+}
+
+fn run_weighted_norm_test(ctx: &GpuContext) {
+    // Consistent P1 mass matrix on a uniform 1D mesh: h/6 * tridiag(1, 4, 1) (SPD).
+    let n = 2000usize;
+    let h = 1.0 / n as f32;
+    let mut row_ptr = vec![0u32];
+    let mut col_idx = Vec::new();
+    let mut values = Vec::new();
+    for i in 0..n {
+        if i > 0 {
+            col_idx.push((i - 1) as u32);
+            values.push(h / 6.0);
+        }
+        col_idx.push(i as u32);
+        values.push(4.0 * h / 6.0);
+        if i + 1 < n {
+            col_idx.push((i + 1) as u32);
+            values.push(h / 6.0);
+        }
+        row_ptr.push(col_idx.len() as u32);
+    }
+
+    let mass_spmv = SpmvExecutor::create(ctx, n as u32, &row_ptr, &col_idx, &values);
+    let dot_exec = DotScalarExecutor::create(ctx, n, 1);
+
+    for (label, r) in [
+        ("ones", vec![1.0f32; n]),
+        (
+            "oscillating",
+            (0..n)
+                .map(|i| ((i * 7) % 11) as f32 - 5.0)
+                .collect::<Vec<f32>>(),
+        ),
+    ] {
+        let r_gpu = ctx.create_storage_buffer("weighted-norm r", &r, BufferUsages::empty());
+
+        let got = weighted_norm(ctx, &mass_spmv, &dot_exec, &r_gpu.buffer);
+        let expected = reference::weighted_norm(&row_ptr, &col_idx, &values, &r);
+        assert!(
+            (got - expected).abs() <= 1e-4 * expected,
+            "weighted-norm-test failed: {label}: gpu {got} vs cpu {expected}"
+        );
+    }
+
+    // ||1||_M^2 = sum of all mass entries = 1 - h/3 (the two boundary rows miss an h/6)
+    let ones_gpu =
+        ctx.create_storage_buffer("weighted-norm 1", &vec![1.0f32; n], BufferUsages::empty());
+    let ones_norm = weighted_norm(ctx, &mass_spmv, &dot_exec, &ones_gpu.buffer);
+    assert!(
+        (ones_norm - 1.0).abs() < 1e-3,
+        "weighted-norm-test failed: ||1||_M = {ones_norm}, expected ~1"
+    );
+
+    println!("WeightedNormTest OK: GPU ||r||_M matches the CPU reference, ||1||_M = {ones_norm}");
+}
+
+fn run_precision_bench_test(ctx: &GpuContext) {
+    let csr = laplacian_2d(32, 32);
+    let b = vec![1.0f32; csr.n_rows as usize];
+    let options = PrecisionBenchOptions {
+        reps: 10,
+        max_iters: 500,
+        rel_tol: 1e-5,
+    };
+
+    let report = run_precision_bench(ctx, &csr, &b, &options)
+        .unwrap_or_else(|e| panic!("precision-bench-test: bench failed: {e}"));
+
+    // Check what the command prints, not just the library struct.
+    let json = to_string_pretty(&precision_bench_metrics(ctx, &csr, &options, &report)).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let rows = value["rows"].as_array().expect("rows array");
+    let precisions: Vec<&str> = rows
+        .iter()
+        .map(|r| r["precision"].as_str().unwrap())
+        .collect();
+
+    let expected: &[&str] = if f64_supported(ctx) {
+        &["f32", "f64"]
+    } else {
+        &["f32"]
+    };
+    assert_eq!(
+        precisions, expected,
+        "precision-bench-test failed: rows {precisions:?}"
+    );
+    assert_eq!(
+        value["f64_over_f32"].is_null(),
+        !f64_supported(ctx),
+        "precision-bench-test failed: ratios present iff both rows are"
+    );
+    assert_eq!(value["f64_skipped"].is_null(), f64_supported(ctx));
+
+    for row in &report.rows {
+        assert!(
+            row.converged,
+            "precision-bench-test failed: {} solve did not converge",
+            row.precision
+        );
+        assert!(row.dot_ms > 0.0 && row.spmv_ms > 0.0 && row.solve_ms > 0.0);
+        // The true residual can sit above rel_tol in f32, but not by orders of magnitude.
+        assert!(
+            row.final_rel_residual < 1e-3,
+            "precision-bench-test failed: {} true residual {:e}",
+            row.precision,
+            row.final_rel_residual
+        );
+    }
+    if let Some(q) = report.ratios() {
+        assert!(
+            q.final_rel_residual < 1.0,
+            "precision-bench-test failed: f64 not more accurate than f32 ({:e})",
+            q.final_rel_residual
+        );
+    }
+
+    let summary: Vec<String> = report
+        .rows
+        .iter()
+        .map(|r| {
+            format!(
+                "{} {} its, residual {:.2e}",
+                r.precision, r.iterations, r.final_rel_residual
+            )
+        })
+        .collect();
+    match &report.f64_skipped {
+        None => println!("PrecisionBenchTest OK: {}", summary.join("; ")),
+        Some(reason) => println!(
+            "PrecisionBenchTest OK: {} (f64 skipped: {reason})",
+            summary.join("; ")
+        ),
+    }
+}
+
+fn run_submission_window_test(ctx: &GpuContext) {
+    let n = 200_000usize;
+    let a = ctx.create_storage_buffer("window a", &vec![1.0f32; n], BufferUsages::empty());
+    let dot_exec = DotScalarExecutor::create(ctx, n, 8);
+
+    // Back-to-back submits with no readback in between: the window must throttle them.
+    // Each submit gets its own completion flag, independent of the window's bookkeeping:
+    // once submit k returns, submits 0..=k-depth must have finished on the GPU.
+    let mut waits = Vec::new();
+    for depth in [1usize, 2, 3] {
+        let mut window = SubmissionWindow::new(depth);
+        let mut done: Vec<Arc<AtomicBool>> = Vec::new();
+        for k in 0..32 {
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&CommandEncoderDescriptor {
+                    label: Some("submission-window-test encoder"),
+                });
+            dot_exec.reset_params_cursor();
+            for slot in 0..8 {
+                dot_exec.encode_dot_scalar_into(
+                    ctx,
+                    &mut encoder,
+                    &a.buffer,
+                    &a.buffer,
+                    n as u32,
+                    slot,
+                );
+            }
+            window
+                .submit(ctx, encoder.finish())
+                .unwrap_or_else(|e| panic!("submission-window-test: {e}"));
+            let flag = Arc::new(AtomicBool::new(false));
+            let flag_set = Arc::clone(&flag);
+            ctx.queue.on_submitted_work_done(move || {
+                flag_set.store(true, Ordering::SeqCst);
+            });
+            done.push(flag);
+
+            assert!(
+                window.in_flight() <= depth,
+                "submission-window-test failed: {} in flight, window {depth}",
+                window.in_flight()
+            );
+            if k >= depth {
+                let last_done = k - depth;
+                let unfinished = done[..=last_done]
+                    .iter()
+                    .filter(|d| !d.load(Ordering::SeqCst))
+                    .count();
+                assert_eq!(
+                    unfinished, 0,
+                    "submission-window-test failed: submit {k} went out with {unfinished} older submits unfinished, window {depth}"
+                );
+            }
+        }
+        assert!(window.max_observed() <= depth);
+        // A window of 1 is full at every submit after the first (its one submit is only
+        // retired by a poll), so it must have waited. Deeper windows fill only when the
+        // GPU falls behind, which a backend that finishes work at submit speed (GL) never
+        // does.
+        assert!(
+            depth > 1 || window.waits() > 0,
+            "submission-window-test failed: window {depth} never waited"
+        );
+        waits.push(window.waits());
+
+        ctx.device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .expect("submission-window-test: poll");
+        window.retire_completed();
+        assert_eq!(
+            window.in_flight(),
+            0,
+            "submission-window-test failed: not drained"
+        );
+    }
+
+    // The solve reports its peak, which must respect the configured window.
+    let n = 1500;
+    let (row_ptr, col_idx, values) = tridiagonal_test_matrix(n);
+    let block_starts = uniform_block_starts(n, 6);
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 5) as f32 * 0.5).collect();
+    let options = PcgOptions::default();
+    assert_eq!(
+        options.max_in_flight_submissions,
+        DEFAULT_MAX_IN_FLIGHT_SUBMISSIONS
+    );
+    let (result, _) = solve_test_system(
+        ctx,
+        &row_ptr,
+        &col_idx,
+        &values,
+        &block_starts,
+        &b,
+        2000,
+        1e-5,
+        &options,
+    )
+    .unwrap_or_else(|e| panic!("submission-window-test: solve failed: {e}"));
+    assert!(
+        (1..=options.max_in_flight_submissions).contains(&result.max_in_flight_submissions),
+        "submission-window-test failed: solve peaked at {} in flight",
+        result.max_in_flight_submissions
+    );
+
+    let zero_window = PcgOptions {
+        max_in_flight_submissions: 0,
+        ..Default::default()
+    };
+    let err = solve_test_system(
+        ctx,
+        &row_ptr,
+        &col_idx,
+        &values,
+        &block_starts,
+        &b,
+        2000,
+        1e-5,
+        &zero_window,
+    )
+    .expect_err("submission-window-test: window 0 must be rejected");
+    assert!(
+        err.contains("max_in_flight_submissions"),
+        "submission-window-test failed: {err}"
+    );
+
+    println!(
+        "SubmissionWindowTest OK: windows 1/2/3 held over 32 submits (waits {waits:?}), solve peak {}",
+        result.max_in_flight_submissions
+    );
+}
+
+/// `--dot-workgroup-sizes`: "P" (both pipelines) or "P,R", each a power of two >= 2.
+fn parse_dot_workgroup_sizes(s: &str) -> Result<(u32, u32), String> {
+    let parse = |part: &str| -> Result<u32, String> {
+        let size: u32 = part
+            .trim()
+            .parse()
+            .map_err(|e| format!("invalid workgroup size {part:?}: {e}"))?;
+        if size < 2 || !size.is_power_of_two() {
+            return Err(format!(
+                "workgroup size must be a power of two >= 2, got {size}"
+            ));
+        }
+        Ok(size)
+    };
+    match s.split_once(',') {
+        Some((partials, reduce)) => Ok((parse(partials)?, parse(reduce)?)),
+        None => parse(s).map(|size| (size, size)),
+    }
+}
+
+fn run_workgroup_size_test(ctx: &GpuContext) {
+    let partials = create_dot_partials_pipeline_with_workgroup_size(ctx, 128);
+    assert_eq!(
+        partials.workgroup_size(),
+        128,
+        "workgroup-size-test failed: partials"
+    );
+    let reduce = create_dot_reduce_pipeline_with_workgroup_size(ctx, 64);
+    assert_eq!(
+        reduce.workgroup_size(),
+        64,
+        "workgroup-size-test failed: reduce"
+    );
+
+    // Overridden sizes must still give the right dot product, and be reported as such.
+    let n = 100_000usize;
+    let a: Vec<f32> = (0..n).map(|i| (i % 7) as f32 - 3.0).collect();
+    let b: Vec<f32> = (0..n).map(|i| (i % 5) as f32).collect();
+    let expected: f64 = a
+        .iter()
+        .zip(&b)
+        .map(|(x, y)| (*x as f64) * (*y as f64))
+        .sum();
+
+    let a_buf = ctx.create_storage_buffer("wg-size a", &a, BufferUsages::empty());
+    let b_buf = ctx.create_storage_buffer("wg-size b", &b, BufferUsages::empty());
+
+    // The largest power of two the device limits allow (workgroup array included), next
+    // to the defaults: on most adapters this is above 256.
+    let limits = ctx.device.limits();
+    let max_wg = limits
+        .max_compute_workgroup_size_x
+        .min(limits.max_compute_invocations_per_workgroup)
+        .min(limits.max_compute_workgroup_storage_size / 4);
+    let large_wg = 1u32 << (31 - max_wg.leading_zeros());
+
+    for (partials_wg, reduce_wg) in [
+        (256, 256),
+        (128, 128),
+        (128, 64),
+        (64, 256),
+        (large_wg, large_wg),
+    ] {
+        let exec =
+            DotScalarExecutor::create_with_workgroup_sizes(ctx, n, 1, partials_wg, reduce_wg);
+        assert_eq!(exec.dot_partials_workgroup_size(), partials_wg);
+        assert_eq!(exec.dot_reduce_workgroup_size(), reduce_wg);
+
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("workgroup-size-test encoder"),
+            });
+        exec.encode_dot_scalar_into(ctx, &mut encoder, &a_buf.buffer, &b_buf.buffer, n as u32, 0);
+        exec.encode_copy_scalar_results_to_readback(&mut encoder);
+        ctx.queue.submit(Some(encoder.finish()));
+
+        let got = executor::block_on(exec.readback_scalar_results(ctx))[0];
+        assert_eq!(
+            got as f64, expected,
+            "workgroup-size-test failed: sizes ({partials_wg}, {reduce_wg}): got {got}, expected {expected}"
+        );
+    }
+
+    // Through the solver: PcgOptions::dot_workgroup_sizes reaches the dot pipelines.
+    let a = laplacian_2d(20, 20);
+    let block_starts = uniform_block_starts(a.n_rows as usize, 4);
+    let rhs = vec![1.0f32; a.n_rows as usize];
+    let solve = |sizes: Option<(u32, u32)>| {
+        let options = PcgOptions {
+            dot_workgroup_sizes: sizes,
+            ..Default::default()
+        };
+        PcgSolver::new(
+            ctx,
+            &a,
+            &block_starts,
+            &SolveConfig::default()
+                .max_iters(500)
+                .tol(1e-5)
+                .options(options),
+        )
+        .and_then(|solver| {
+            let mut x = vec![0.0f32; a.n_rows as usize];
+            solver.solve(&rhs, &mut x)
+        })
+    };
+    let default = solve(None).unwrap_or_else(|e| panic!("workgroup-size-test: {e}"));
+    let tuned = solve(Some((64, 128))).unwrap_or_else(|e| panic!("workgroup-size-test: {e}"));
+    let reported = tuned.workgroup_sizes.unwrap();
+    assert_eq!(
+        (reported.dot_partials, reported.dot_reduce),
+        (64, 128),
+        "workgroup-size-test failed: solver sizes"
+    );
+    assert!(
+        tuned.iterations.abs_diff(default.iterations) <= 1,
+        "workgroup-size-test failed: {} iterations with 64/128, {} with the defaults",
+        tuned.iterations,
+        default.iterations
+    );
+    for sizes in [(96, 256), (256, 1), (1, 256)] {
+        assert!(
+            solve(Some(sizes)).is_err(),
+            "workgroup-size-test failed: solver accepted workgroup sizes {sizes:?}"
+        );
+    }
+    assert_eq!(parse_dot_workgroup_sizes("128"), Ok((128, 128)));
+    assert_eq!(parse_dot_workgroup_sizes("64,512"), Ok((64, 512)));
+    assert!(parse_dot_workgroup_sizes("100").is_err());
+    assert!(parse_dot_workgroup_sizes("256,1").is_err());
+    assert!(parse_dot_workgroup_sizes("1").is_err());
+
+    // Past the limit (or not a power of two) the fallible constructors refuse.
+    let too_large = large_wg * 2;
+    for err in [
+        try_create_dot_partials_pipeline_with_accumulator(ctx, too_large, Precision::F32).err(),
+        try_create_dot_reduce_pipeline_with_accumulator(ctx, too_large, Precision::F32).err(),
+    ] {
+        let err = err.expect("workgroup-size-test failed: oversized workgroup accepted");
+        assert!(
+            err.contains(&too_large.to_string()),
+            "workgroup-size-test failed: unexpected error {err}"
+        );
+    }
+    assert!(
+        try_create_dot_partials_pipeline_with_accumulator(ctx, 96, Precision::F32).is_err(),
+        "workgroup-size-test failed: non-power-of-two workgroup accepted"
+    );
+    for err in [
+        try_create_dot_partials_pipeline_with_accumulator(ctx, 1, Precision::F32).err(),
+        try_create_dot_reduce_pipeline_with_accumulator(ctx, 1, Precision::F32).err(),
+    ] {
+        assert!(
+            err.is_some_and(|e| e.contains(">= 2")),
+            "workgroup-size-test failed: workgroup size 1 accepted"
+        );
+    }
+
+    println!(
+        "WorkgroupSizeTest OK: overrides 128/64 reported, dot exact for all size pairs up to {large_wg}, 1 and {too_large} rejected, solver honors dot_workgroup_sizes"
+    );
+}
+
+fn run_spmv_test(ctx: &GpuContext) {
+    // 3x3 matrix:
+    // [ 10 0  2 ]
+    // [ 3  9  0 ]
+    // [ 0  7  8 ]
+    //
+    // CSR:
+    // row_ptr = [0, 2, 4, 6]
+    // col_idx = [0,2, 0,1, 1,2]
+    // vals    = [10,2, 3,9, 7,8]
+    //
+    // x = [1,2,3]
+    // y = [16, 21, 38]
+
+    let n_rows: u32 = 3;
+
+    let row_ptr: Vec<u32> = vec![0, 2, 4, 6];
+    let col_idx: Vec<u32> = vec![0, 2, 0, 1, 1, 2];
+    let values: Vec<f32> = vec![10.0, 2.0, 3.0, 9.0, 7.0, 8.0];
+
+    let x_host: Vec<f32> = vec![1.0, 2.0, 3.0];
+    let expected: Vec<f32> = vec![16.0, 21.0, 38.0];
+
+    // Upload x as a standalone GPU buffer (like your iteration vectors).
+    let x_gpu = ctx.create_storage_buffer("spmv-test x_src", &x_host, BufferUsages::empty());
+
+    // Create executor (owns CSR + internal x/y buffers).
+    let spmv = SpmvExecutor::create(ctx, n_rows, &row_ptr, &col_idx, &values);
+
+    // Encode: copy x into internal x_buffer, then SpMV into internal y_buffer.
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("spmv-test encoder"),
+        });
+
+    // n_bytes = n_rows * sizeof(f32)
+    spmv.encode_copy_x_from(&mut encoder, &x_gpu.buffer, (n_rows as u64) * 4);
+    spmv.encode_spmv(&mut encoder);
+
+    // Single submit (matches your model).
+    ctx.queue.submit(Some(encoder.finish()));
+
+    // Read back y.
+    // y_buffer length is n_rows f32.
+    let y_out: Vec<f32> = executor::block_on(readback_to_vec::<f32>(
+        &ctx.device,
+        &ctx.queue,
+        spmv.y_buffer(),
+        n_rows as usize,
+        Some("spmv-test y readback"),
+    ));
+
+    assert_eq!(y_out.len(), expected.len());
+
+    for i in 0..expected.len() {
+        let got = y_out[i];
+        let exp = expected[i];
+        assert!(
+            (got - exp).abs() < 1e-6,
+            "spmv-test failed at i={i}: got {got}, expected {exp}"
+        );
+    }
+
+    println!("SpmvTest OK: y == {:?}", y_out);
+}
+
+/// Small deterministic PRNG (SplitMix64) for the fuzz-style tests.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1).
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Uniform in [0, bound).
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+/// ILU(0) of `a` on the host, split into (L strictly lower, implicit unit diagonal; U
+/// with its diagonal). Stands in for the external library of `ilu0-test`.
+fn host_ilu0_factors(a: &Csr) -> (Csr, Csr) {
+    let n = a.n_rows as usize;
+    let rows: Vec<Vec<(usize, f32)>> = (0..n)
+        .map(|i| {
+            let mut row: Vec<(usize, f32)> = (a.row_ptr[i] as usize..a.row_ptr[i + 1] as usize)
+                .map(|k| (a.col_idx[k] as usize, a.values[k]))
+                .collect();
+            row.sort_by_key(|&(j, _)| j);
+            row
+        })
+        .collect();
+
+    // IKJ variant restricted to the pattern of A; `factored[i]` holds row i of L\U.
+    let mut factored: Vec<Vec<(usize, f32)>> = Vec::with_capacity(n);
+    for (i, source) in rows.iter().enumerate() {
+        let mut row = source.clone();
+        for p in 0..row.len() {
+            let k = row[p].0;
+            if k >= i {
+                break;
+            }
+            let u_kk = factored[k].iter().find(|&&(j, _)| j == k).unwrap().1;
+            row[p].1 /= u_kk;
+            let l_ik = row[p].1;
+            for &(j, u_kj) in factored[k].iter().filter(|&&(j, _)| j > k) {
+                if let Some(entry) = row.iter_mut().find(|e| e.0 == j) {
+                    entry.1 -= l_ik * u_kj;
+                }
+            }
+        }
+        factored.push(row);
+    }
+
+    let split = |keep: fn(usize, usize) -> bool| {
+        let mut csr = Csr {
+            n_rows: n as u32,
+            n_cols: n as u32,
+            nnz: 0,
+            row_ptr: vec![0],
+            col_idx: Vec::new(),
+            values: Vec::new(),
+        };
+        for (i, row) in factored.iter().enumerate() {
+            for &(j, v) in row.iter().filter(|&&(j, _)| keep(i, j)) {
+                csr.col_idx.push(j as u32);
+                csr.values.push(v);
+            }
+            csr.row_ptr.push(csr.col_idx.len() as u32);
+        }
+        csr.nnz = csr.col_idx.len() as u32;
+        csr
+    };
+
+    (split(|i, j| j < i), split(|i, j| j >= i))
+}
+
+/// z = M^{-1} r through `Ilu0Executor` (one submit, read back).
+fn ilu0_apply_gpu(ctx: &GpuContext, ilu: &Ilu0Executor, r: &[f32]) -> Vec<f32> {
+    let r_gpu = ctx.create_storage_buffer("ilu0-test r", r, BufferUsages::empty());
+    let z_gpu =
+        ctx.create_storage_buffer_uninit::<f32>("ilu0-test z", r.len(), BufferUsages::empty());
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("ilu0-test encoder"),
+        });
+    ilu.encode_apply(&mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+    ctx.queue.submit(Some(encoder.finish()));
+
+    executor::block_on(ctx.readback(&z_gpu))
+}
+
+fn run_ilu0_test(ctx: &GpuContext) {
+    let max_rel_diff = |a: &[f32], b: &[f32]| {
+        let scale = b.iter().fold(0.0f32, |m, v| m.max(v.abs())).max(1e-30);
+        a.iter()
+            .zip(b)
+            .fold(0.0f32, |m, (x, y)| m.max((x - y).abs()))
+            / scale
+    };
+
+    // 2D Laplacian: ILU(0) drops fill, so check the apply against the CPU reference.
+    let a = laplacian_2d(24, 24);
+    let n = a.n_rows as usize;
+    let r: Vec<f32> = (0..n)
+        .map(|i| 1.0 + ((i * 37) % 11) as f32 * 0.25)
+        .collect();
+    let (l, u) = host_ilu0_factors(&a);
+    let levels = Ilu0Levels::compute(&l, &u);
+    let ilu = Ilu0Executor::from_factors(ctx, &l, &u, &levels)
+        .unwrap_or_else(|e| panic!("ilu0-test: from_factors failed: {e}"));
+    assert_eq!(
+        ilu.dispatches() as usize,
+        levels.lower.num_levels() + levels.upper.num_levels()
+    );
+
+    let z_gpu = ilu0_apply_gpu(ctx, &ilu, &r);
+    let mut z_ref = vec![0.0f32; n];
+    reference::ilu_apply_csr(&l, &u, &r, &mut z_ref);
+    let diff = max_rel_diff(&z_gpu, &z_ref);
+    assert!(
+        diff <= 1e-5,
+        "ilu0-test failed: GPU vs CPU apply differ by {diff:e}"
+    );
+
+    // The same L with its unit diagonal stored explicitly gives the same apply.
+    let mut l_stored = Csr {
+        nnz: 0,
+        row_ptr: vec![0],
+        col_idx: Vec::new(),
+        values: Vec::new(),
+        ..l.clone()
+    };
+    for i in 0..n {
+        let range = l.row_ptr[i] as usize..l.row_ptr[i + 1] as usize;
+        l_stored
+            .col_idx
+            .extend_from_slice(&l.col_idx[range.clone()]);
+        l_stored.values.extend_from_slice(&l.values[range]);
+        l_stored.col_idx.push(i as u32);
+        l_stored.values.push(1.0);
+        l_stored.row_ptr.push(l_stored.col_idx.len() as u32);
+    }
+    l_stored.nnz = l_stored.col_idx.len() as u32;
+    let ilu_stored = Ilu0Executor::from_factors(ctx, &l_stored, &u, &levels)
+        .unwrap_or_else(|e| panic!("ilu0-test: stored unit diagonal rejected: {e}"));
+    let diff_stored = max_rel_diff(&ilu0_apply_gpu(ctx, &ilu_stored, &r), &z_gpu);
+    assert!(
+        diff_stored <= 1e-6,
+        "ilu0-test failed: stored vs implicit unit diagonal differ by {diff_stored:e}"
+    );
+
+    // Tridiagonal: ILU(0) is the exact LU, so M^{-1} r solves A z = r.
+    let t = laplacian_1d(100);
+    let (tl, tu) = host_ilu0_factors(&t);
+    let t_ilu = Ilu0Executor::from_factors(ctx, &tl, &tu, &Ilu0Levels::compute(&tl, &tu))
+        .unwrap_or_else(|e| panic!("ilu0-test: tridiagonal from_factors failed: {e}"));
+    let tr = vec![1.0f32; 100];
+    let tz = ilu0_apply_gpu(ctx, &t_ilu, &tr);
+    let mut t_az = vec![0.0f32; 100];
+    reference::spmv_csr(&t.row_ptr, &t.col_idx, &t.values, &tz, &mut t_az);
+    let exact_diff = max_rel_diff(&t_az, &tr);
+    assert!(
+        exact_diff <= 1e-3,
+        "ilu0-test failed: exact LU apply leaves residual {exact_diff:e}"
+    );
+
+    // Structure checks.
+    let mut l_bad_diag = l_stored.clone();
+    let last = l_bad_diag.values.len() - 1;
+    l_bad_diag.values[last] = 2.0;
+    let mut u_lower_entry = u.clone();
+    u_lower_entry.col_idx[u.row_ptr[5] as usize + 1] = 0;
+    let mut u_no_diag = u.clone();
+    u_no_diag.col_idx[u.row_ptr[7] as usize] = 8;
+    let mut l_upper_entry = l.clone();
+    l_upper_entry.col_idx[l.row_ptr[30] as usize] = 31;
+    let mut one_level = levels.clone();
+    one_level.lower.level_ptr = vec![0, n as u32];
+    one_level.lower.rows = (0..n as u32).collect();
+
+    let rejected = [
+        (
+            "non-unit L diagonal",
+            l_bad_diag,
+            u.clone(),
+            levels.clone(),
+            "unit diagonal",
+        ),
+        (
+            "U entry below the diagonal",
+            l.clone(),
+            u_lower_entry,
+            levels.clone(),
+            "below",
+        ),
+        (
+            "U without diagonal",
+            l.clone(),
+            u_no_diag,
+            levels.clone(),
+            "missing diagonal",
+        ),
+        (
+            "L rows in one level",
+            l.clone(),
+            u.clone(),
+            one_level,
+            "depends on",
+        ),
+        (
+            "L entry above the diagonal",
+            l_upper_entry,
+            u.clone(),
+            levels.clone(),
+            "above",
+        ),
+    ];
+    for (case, bad_l, bad_u, bad_levels, expected) in &rejected {
+        match Ilu0Executor::from_factors(ctx, bad_l, bad_u, bad_levels) {
+            Ok(_) => panic!("ilu0-test failed: {case} accepted"),
+            Err(e) => assert!(
+                e.contains(expected),
+                "ilu0-test failed: {case}: unexpected error '{e}'"
+            ),
+        }
+    }
+
+    println!(
+        "Ilu0Test OK: n={n}, {} + {} levels, GPU vs CPU {diff:.2e}, exact LU residual {exact_diff:.2e}, {} bad inputs rejected",
+        levels.lower.num_levels(),
+        levels.upper.num_levels(),
+        rejected.len()
+    );
+}
+
+/// 2D Laplacian with a varying diagonal (so the Eisenstat scaling is not a multiple of I).
+fn ssor_test_matrix(nx: usize, ny: usize) -> Csr {
+    let mut a = laplacian_2d(nx, ny);
+    for i in 0..a.n_rows as usize {
+        for k in a.row_ptr[i] as usize..a.row_ptr[i + 1] as usize {
+            if a.col_idx[k] as usize == i {
+                a.values[k] += (i % 7) as f32 * 0.05;
+            }
+        }
+    }
+    a
+}
+
+/// Multicolor SSOR apply z = M^{-1} r on the host: forward sweep over the colors, then
+/// backward (the `ssor_exec` formulas, in f64).
+fn host_multicolor_ssor(a: &Csr, coloring: &SsorColoring, omega: f64, r: &[f32]) -> Vec<f32> {
+    let n = a.n_rows as usize;
+    let mut color = vec![0usize; n];
+    let mut diag = vec![0.0f64; n];
+    for k in 0..coloring.num_colors() {
+        for &row in
+            &coloring.rows[coloring.color_ptr[k] as usize..coloring.color_ptr[k + 1] as usize]
+        {
+            color[row as usize] = k;
+        }
+    }
+    for (i, d) in diag.iter_mut().enumerate() {
+        for k in a.row_ptr[i] as usize..a.row_ptr[i + 1] as usize {
+            if a.col_idx[k] as usize == i {
+                *d += a.values[k] as f64;
+            }
+        }
+    }
+    let sum = |z: &[f64], row: usize, keep: &dyn Fn(usize) -> bool| -> f64 {
+        (a.row_ptr[row] as usize..a.row_ptr[row + 1] as usize)
+            .filter(|&k| keep(color[a.col_idx[k] as usize]))
+            .map(|k| a.values[k] as f64 * z[a.col_idx[k] as usize])
+            .sum()
+    };
+
+    let mut z = vec![0.0f64; n];
+    let rows_of = |k: usize| {
+        &coloring.rows[coloring.color_ptr[k] as usize..coloring.color_ptr[k + 1] as usize]
+    };
+    for k in 0..coloring.num_colors() {
+        for &row in rows_of(k) {
+            let row = row as usize;
+            z[row] = (omega * (2.0 - omega) * r[row] as f64 - omega * sum(&z, row, &|c| c < k))
+                / diag[row];
+        }
+    }
+    for k in (0..coloring.num_colors()).rev() {
+        for &row in rows_of(k) {
+            let row = row as usize;
+            z[row] -= omega / diag[row] * sum(&z, row, &|c| c > k);
+        }
+    }
+    z.iter().map(|&v| v as f32).collect()
+}
+
+fn run_ssor_executor_test(ctx: &GpuContext) {
+    let (nx, ny) = (20, 20);
+    let a = laplacian_2d(nx, ny);
+    let n = a.n_rows as usize;
+    let mut rng = SplitMix64(0x55_0e);
+    let r: Vec<f32> = (0..n).map(|_| rng.next_f32() - 0.5).collect();
+    let r_gpu = ctx.create_storage_buffer("ssor-executor-test r", &r, BufferUsages::empty());
+    let z_gpu =
+        ctx.create_storage_buffer_uninit::<f32>("ssor-executor-test z", n, BufferUsages::COPY_SRC);
+    let apply = |ssor: &SsorExecutor| {
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("ssor-executor-test encoder"),
+            });
+        ssor.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+        ctx.queue.submit(Some(encoder.finish()));
+        executor::block_on(ctx.readback(&z_gpu))
+    };
+    let check = |name: &str, got: &[f32], expected: &[f32]| {
+        let scale = expected.iter().fold(0.0f32, |m, v| m.max(v.abs()));
+        for (i, (g, e)) in got.iter().zip(expected).enumerate() {
+            assert!(
+                (g - e).abs() <= 1e-5 * scale,
+                "ssor-executor-test failed: {name}: z[{i}] = {g}, expected {e}"
+            );
+        }
+    };
+
+    // Greedy coloring of the 5-point Laplacian is red-black: 2 colors, 3 dispatches.
+    let omega = 1.2f32;
+    let greedy = SsorColoring::greedy(&a);
+    assert_eq!(
+        greedy.num_colors(),
+        2,
+        "ssor-executor-test: greedy coloring"
+    );
+    let ssor = SsorExecutor::from_csr(ctx, &a, omega)
+        .unwrap_or_else(|e| panic!("ssor-executor-test: {e}"));
+    assert_eq!((ssor.num_colors(), ssor.dispatches()), (2, 3));
+    check(
+        "red-black",
+        &apply(&ssor),
+        &host_multicolor_ssor(&a, &greedy, omega as f64, &r),
+    );
+
+    // A caller coloring (4 colors by x / y parity, listed out of order) and omega = 1
+    // (symmetric Gauss-Seidel).
+    let colors: Vec<u32> = (0..n)
+        .map(|i| [3, 1, 2, 0][(i % nx) % 2 + 2 * ((i / nx) % 2)])
+        .collect();
+    let quad = SsorColoring::from_colors(&colors);
+    let inv_diag: Vec<f32> = vec![0.25; n];
+    let sgs = SsorExecutor::create(ctx, &a, &inv_diag, 1.0, &quad)
+        .unwrap_or_else(|e| panic!("ssor-executor-test: {e}"));
+    assert_eq!((sgs.num_colors(), sgs.dispatches()), (4, 7));
+    check(
+        "4 colors",
+        &apply(&sgs),
+        &host_multicolor_ssor(&a, &quad, 1.0, &r),
+    );
+
+    // Bad inputs are rejected.
+    let one_color = SsorColoring::from_colors(&vec![0; n]);
+    let err = SsorExecutor::create(ctx, &a, &inv_diag, 1.0, &one_color)
+        .err()
+        .unwrap_or_else(|| panic!("ssor-executor-test failed: coupled rows of one color accepted"));
+    assert!(err.contains("share color"), "ssor-executor-test: {err}");
+    assert!(SsorExecutor::create(ctx, &a, &inv_diag, 2.0, &quad).is_err());
+    assert!(SsorExecutor::create(ctx, &a, &inv_diag[1..], 1.0, &quad).is_err());
+    let mut missing = quad.clone();
+    missing.rows[0] = missing.rows[1];
+    assert!(SsorExecutor::create(ctx, &a, &inv_diag, 1.0, &missing).is_err());
+
+    // As a PCG preconditioner: fewer iterations than diagonal Jacobi.
+    let a = laplacian_2d(32, 32);
+    let n = a.n_rows as usize;
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 5) as f32 * 0.25).collect();
+    let config = SolveConfig::default();
+    let solve = |preconditioner: Box<dyn LinearOperator>| {
+        let solver = PcgSolver::with_preconditioner(ctx, &a, preconditioner, &config)
+            .unwrap_or_else(|e| panic!("ssor-executor-test: {e}"));
+        let mut x = vec![0.0f32; n];
+        let result = solver
+            .solve(&b, &mut x)
+            .unwrap_or_else(|e| panic!("ssor-executor-test: PCG: {e}"));
+        let mut ax = vec![0.0f32; n];
+        reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, &x, &mut ax);
+        let r: Vec<f32> = b.iter().zip(&ax).map(|(b, ax)| b - ax).collect();
+        let rel = (reference::dot(&r, &r) / reference::dot(&b, &b)).sqrt();
+        assert!(
+            rel < 1e-4,
+            "ssor-executor-test failed: relative residual {rel:e}"
+        );
+        result.iterations
+    };
+    let ssor_iterations = solve(Box::new(
+        SsorExecutor::from_csr(ctx, &a, omega)
+            .unwrap_or_else(|e| panic!("ssor-executor-test: {e}")),
+    ));
+    let jacobi_iterations = solve(Box::new(
+        DiagonalJacobiExecutor::from_csr(ctx, &a)
+            .unwrap_or_else(|e| panic!("ssor-executor-test: {e}")),
+    ));
+    assert!(
+        ssor_iterations < jacobi_iterations,
+        "ssor-executor-test failed: SSOR took {ssor_iterations} iterations, diagonal Jacobi {jacobi_iterations}"
+    );
+
+    println!(
+        "SsorExecutorTest OK: red-black and 4-color applies match the CPU reference, PCG {ssor_iterations} iterations with multicolor SSOR (omega {omega}) vs {jacobi_iterations} with diagonal Jacobi"
+    );
+}
+
+fn run_ssor_eisenstat_test(ctx: &GpuContext) {
+    let a = ssor_test_matrix(24, 24);
+    let n = a.n_rows as usize;
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + ((i * 13) % 5) as f32 * 0.5).collect();
+    // Nonzero x0 exercises the transformed initial guess.
+    let x0: Vec<f32> = (0..n).map(|i| ((i % 3) as f32 - 1.0) * 0.1).collect();
+    let b_norm = reference::dot(&b, &b).sqrt();
+    let (max_iter, rel_tol) = (500, 1e-5);
+
+    let solve = |omega: f32, eisenstat: bool| -> (Vec<f32>, SsorCgResult) {
+        let mut x = x0.clone();
+        let options = SsorOptions { omega, eisenstat };
+        let result = ssor_cg_csr_wgpu(
+            ctx,
+            &a,
+            &b,
+            &mut x,
+            &SolveConfig::default().max_iters(max_iter).tol(rel_tol),
+            &options,
+        )
+        .unwrap_or_else(|e| {
+            panic!("ssor-eisenstat-test: omega {omega}, eisenstat {eisenstat}: {e}")
+        });
+        (x, result)
+    };
+
+    let mut summary = Vec::new();
+    for omega in [1.0f32, 1.5] {
+        let (x_plain, plain) = solve(omega, false);
+        let (x_eis, eis) = solve(omega, true);
+
+        // Same iteration in exact arithmetic; f32 may shift the crossing by one.
+        assert!(
+            plain.iterations.abs_diff(eis.iterations) <= 1,
+            "ssor-eisenstat-test: omega {omega}: {} plain vs {} Eisenstat iterations",
+            plain.iterations,
+            eis.iterations
+        );
+        for (name, result) in [("plain", &plain), ("Eisenstat", &eis)] {
+            assert!(
+                result.residual_norm <= 1e-3 * b_norm,
+                "ssor-eisenstat-test: omega {omega}: {name} true residual {:e} (||b|| = {b_norm})",
+                result.residual_norm
+            );
+        }
+        let x_norm = reference::dot(&x_plain, &x_plain).sqrt();
+        let diff = x_plain
+            .iter()
+            .zip(&x_eis)
+            .map(|(p, e)| (p - e) * (p - e))
+            .sum::<f32>()
+            .sqrt();
+        assert!(
+            diff <= 1e-4 * x_norm,
+            "ssor-eisenstat-test: omega {omega}: solutions differ by {diff:e} (||x|| = {x_norm})"
+        );
+
+        // The SpMV is gone from the transformed iteration; three vector ops and the
+        // residual map (lower triangle, for the stopping test) took its place.
+        assert_eq!(
+            eis.dispatches_per_iteration,
+            plain.dispatches_per_iteration - 1 + 3 + 1,
+            "ssor-eisenstat-test: omega {omega}: dispatches per iteration"
+        );
+        summary.push(format!(
+            "omega {omega}: {} / {} iterations",
+            plain.iterations, eis.iterations
+        ));
+    }
+
+    // SsorPreconditioner also plugs into the PCG core.
+    let ssor = SsorPreconditioner::from_csr(ctx, &a, 1.0)
+        .unwrap_or_else(|e| panic!("ssor-eisenstat-test: SsorPreconditioner: {e}"));
+    let solver = PcgSolver::with_preconditioner(
+        ctx,
+        &a,
+        Box::new(ssor),
+        &SolveConfig::default().max_iters(max_iter).tol(1e-5),
+    )
+    .unwrap_or_else(|e| panic!("ssor-eisenstat-test: PcgSolver: {e}"));
+    let mut x_core = x0.clone();
+    let core = solver
+        .solve(&b, &mut x_core)
+        .unwrap_or_else(|e| panic!("ssor-eisenstat-test: PCG core solve failed: {e}"));
+    let (x_plain, _) = solve(1.0, false);
+    let x_norm = reference::dot(&x_plain, &x_plain).sqrt();
+    let diff = x_plain
+        .iter()
+        .zip(&x_core)
+        .map(|(p, c)| (p - c) * (p - c))
+        .sum::<f32>()
+        .sqrt();
+    assert!(
+        diff <= 1e-3 * x_norm,
+        "ssor-eisenstat-test: PCG core with SsorPreconditioner differs by {diff:e}"
+    );
+
+    // Rejected inputs.
+    for (omega, what) in [(0.0f32, "outside (0, 2)"), (2.0, "outside (0, 2)")] {
+        let mut x = x0.clone();
+        let options = SsorOptions {
+            omega,
+            eisenstat: true,
+        };
+        let err = ssor_cg_csr_wgpu(
+            ctx,
+            &a,
+            &b,
+            &mut x,
+            &SolveConfig::default().max_iters(max_iter).tol(rel_tol),
+            &options,
+        )
+        .err()
+        .unwrap_or_else(|| panic!("ssor-eisenstat-test: omega {omega} accepted"));
+        assert!(
+            err.contains(what),
+            "ssor-eisenstat-test: unexpected error: {err}"
+        );
+        assert_eq!(x, x0, "ssor-eisenstat-test: x changed on error");
+    }
+    let mut negative = a.clone();
+    let k = negative.row_ptr[3] as usize..negative.row_ptr[4] as usize;
+    for k in k {
+        if negative.col_idx[k] == 3 {
+            negative.values[k] = -1.0;
+        }
+    }
+    let err = SsorPreconditioner::from_csr(ctx, &negative, 1.0)
+        .err()
+        .expect("ssor-eisenstat-test: negative diagonal accepted");
+    assert!(
+        err.contains("diagonal entry 3"),
+        "ssor-eisenstat-test: unexpected error: {err}"
+    );
+
+    println!(
+        "SsorEisenstatTest OK: n={n}, {} (plain / Eisenstat), PCG core took {} with the same SSOR",
+        summary.join(", "),
+        core.iterations
+    );
+}
+
+#[derive(Serialize)]
+struct SsorBenchMetrics {
+    run_id: String,
+    command: String,
+    gpu: GpuMetrics,
+    n: u32,
+    nnz: u32,
+    omega: f32,
+    rel_tol: f32,
+    rows: Vec<SsorBenchRowMetrics>,
+    /// Eisenstat / plain wall-clock per iteration
+    eisenstat_over_plain_per_iteration: f64,
+}
+
+#[derive(Serialize)]
+struct SsorBenchRowMetrics {
+    variant: String,
+    iterations: usize,
+    loop_ms: f64,
+    ms_per_iteration: f64,
+    dispatches_per_iteration: u64,
+    final_rel_residual: f64,
+}
+
+/// One preconditioner apply z = M^{-1} r through the `LinearOperator` interface.
+fn additive_schwarz_apply_gpu(ctx: &GpuContext, op: &dyn LinearOperator, r: &[f32]) -> Vec<f32> {
+    let r_gpu = ctx.create_storage_buffer("additive-schwarz-test r", r, BufferUsages::empty());
+    let z_gpu = ctx.create_storage_buffer_uninit::<f32>(
+        "additive-schwarz-test z",
+        r.len(),
+        BufferUsages::empty(),
+    );
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("additive-schwarz-test encoder"),
+        });
+    op.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+    ctx.queue.submit(Some(encoder.finish()));
+
+    executor::block_on(ctx.readback(&z_gpu))
+}
+
+/// CPU additive Schwarz in f64: local solves by dense elimination, then add / average.
+fn additive_schwarz_apply_host(
+    a: &Csr,
+    subdomains: &SchwarzSubdomains,
+    combine: SchwarzCombine,
+    r: &[f32],
+) -> Vec<f32> {
+    let n = a.n_rows as usize;
+    let mut z = vec![0.0f64; n];
+    let mut count = vec![0usize; n];
+    for b in 0..subdomains.num_blocks() {
+        let set = subdomains.block(b);
+        // A[S, S] is symmetric here, so its rows double as the columns the helper takes.
+        let local: Vec<Vec<f64>> = set
+            .iter()
+            .map(|&row| {
+                set.iter()
+                    .map(|&col| {
+                        (a.row_ptr[row as usize] as usize..a.row_ptr[row as usize + 1] as usize)
+                            .filter(|&k| a.col_idx[k] == col)
+                            .map(|k| a.values[k] as f64)
+                            .sum()
+                    })
+                    .collect()
+            })
+            .collect();
+        let r_local: Vec<f64> = set.iter().map(|&i| r[i as usize] as f64).collect();
+        for (&dof, v) in set.iter().zip(dense_least_squares_f64(&local, &r_local)) {
+            z[dof as usize] += v;
+            count[dof as usize] += 1;
+        }
+    }
+    z.iter()
+        .zip(&count)
+        .map(|(&v, &c)| match combine {
+            SchwarzCombine::Add => v as f32,
+            SchwarzCombine::Average => (v / c as f64) as f32,
+        })
+        .collect()
+}
+
+fn run_additive_schwarz_test(ctx: &GpuContext) {
+    let max_rel_diff = |a: &[f32], b: &[f32]| {
+        let scale = b.iter().fold(0.0f32, |m, v| m.max(v.abs())).max(1e-30);
+        a.iter()
+            .zip(b)
+            .fold(0.0f32, |m, (x, y)| m.max((x - y).abs()))
+            / scale
+    };
+
+    let n = 128;
+    let a = laplacian_1d(n);
+    let (core, overlap) = (8, 2);
+    let r: Vec<f32> = (0..n)
+        .map(|i| 1.0 + ((i * 37) % 11) as f32 * 0.25)
+        .collect();
+
+    // Apply vs the CPU reference, both combine modes, plus a scattered (non-contiguous)
+    // decomposition: even and odd DOFs of each window in separate subdomains.
+    let contiguous = SchwarzSubdomains::contiguous(n, core, overlap);
+    assert_eq!(contiguous.num_blocks(), n / core);
+    assert_eq!(contiguous.block(0), &(0..10).collect::<Vec<u32>>()[..]);
+    assert_eq!(contiguous.block(1), &(6..18).collect::<Vec<u32>>()[..]);
+    let mut scattered = SchwarzSubdomains {
+        dof_ptr: vec![0],
+        dofs: Vec::new(),
+    };
+    for start in (0..n as u32).step_by(16) {
+        for parity in [1, 0] {
+            let end = (start + 20).min(n as u32);
+            scattered
+                .dofs
+                .extend((start..end).filter(|i| i % 2 == parity).rev());
+            scattered.dof_ptr.push(scattered.dofs.len() as u32);
+        }
+    }
+    let mut apply_diff = 0.0f32;
+    for (name, subdomains) in [("contiguous", &contiguous), ("scattered", &scattered)] {
+        for combine in [SchwarzCombine::Add, SchwarzCombine::Average] {
+            let schwarz = AdditiveSchwarzExecutor::from_csr(ctx, &a, subdomains, combine)
+                .unwrap_or_else(|e| panic!("additive-schwarz-test: {name} {combine:?}: {e}"));
+            assert_eq!(schwarz.dispatches(), 2);
+            let z_gpu = additive_schwarz_apply_gpu(ctx, &schwarz, &r);
+            let z_ref = additive_schwarz_apply_host(&a, subdomains, combine, &r);
+            let diff = max_rel_diff(&z_gpu, &z_ref);
+            assert!(
+                diff <= 1e-5,
+                "additive-schwarz-test failed: {name} {combine:?}: GPU vs CPU apply differ by {diff:e}"
+            );
+            apply_diff = apply_diff.max(diff);
+        }
+    }
+
+    // Without overlap the operator is block Jacobi with the same blocks.
+    let no_overlap_6 = AdditiveSchwarzExecutor::from_csr(
+        ctx,
+        &a,
+        &SchwarzSubdomains::contiguous(n, 6, 0),
+        SchwarzCombine::Add,
+    )
+    .unwrap_or_else(|e| panic!("additive-schwarz-test: {e}"));
+    let block_jacobi = BlockJacobiExecutor::from_csr(ctx, &a, 6)
+        .unwrap_or_else(|e| panic!("additive-schwarz-test: {e}"));
+    let bj_diff = max_rel_diff(
+        &additive_schwarz_apply_gpu(ctx, &no_overlap_6, &r),
+        &additive_schwarz_apply_gpu(ctx, &block_jacobi, &r),
+    );
+    assert!(
+        bj_diff <= 1e-5,
+        "additive-schwarz-test failed: overlap 0 vs block Jacobi differ by {bj_diff:e}"
+    );
+
+    // PCG on the 1D Laplacian: overlap lets each apply reach past the block boundaries,
+    // so it needs fewer iterations than the non-overlapping blocks of the same core size.
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 7) as f32 * 0.25).collect();
+    let b_norm = b.iter().map(|v| v * v).sum::<f32>().sqrt();
+    let (max_iter, rel_tol) = (2000, 1e-5);
+    let mut iterations = Vec::new();
+    for o in [0, overlap] {
+        let schwarz = AdditiveSchwarzExecutor::from_csr(
+            ctx,
+            &a,
+            &SchwarzSubdomains::contiguous(n, core, o),
+            SchwarzCombine::Add,
+        )
+        .unwrap_or_else(|e| panic!("additive-schwarz-test: overlap {o}: {e}"));
+        let solver = PcgSolver::with_preconditioner(
+            ctx,
+            &a,
+            Box::new(schwarz),
+            &SolveConfig::default().max_iters(max_iter).tol(rel_tol),
+        )
+        .unwrap_or_else(|e| panic!("additive-schwarz-test: overlap {o}: {e}"));
+        let mut x = vec![0.0f32; n];
+        let result = solver
+            .solve(&b, &mut x)
+            .unwrap_or_else(|e| panic!("additive-schwarz-test: overlap {o}: solve failed: {e}"));
+
+        // cond(A) ~ 6.6e3 and ||x|| >> ||b||: the f32 true residual floors near 1e-3.
+        let mut ax = vec![0.0f32; n];
+        reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, &x, &mut ax);
+        let true_residual = b
+            .iter()
+            .zip(&ax)
+            .map(|(b, ax)| (b - ax) * (b - ax))
+            .sum::<f32>()
+            .sqrt();
+        assert!(
+            true_residual <= 5e-3 * b_norm,
+            "additive-schwarz-test failed: overlap {o}: ||b - A x|| = {true_residual} after {} iterations",
+            result.iterations
+        );
+        iterations.push(result.iterations);
+    }
+    assert!(
+        iterations[1] < iterations[0],
+        "additive-schwarz-test failed: overlap {overlap} took {} iterations, no overlap {}",
+        iterations[1],
+        iterations[0]
+    );
+
+    // Malformed decompositions are rejected.
+    let mut repeated = SchwarzSubdomains::contiguous(n, core, 0);
+    repeated.dofs[1] = repeated.dofs[0];
+    let mut out_of_range = SchwarzSubdomains::contiguous(n, core, 0);
+    *out_of_range.dofs.last_mut().unwrap() = n as u32;
+    let rejected = [
+        (
+            "uncovered DOF",
+            SchwarzSubdomains {
+                dof_ptr: vec![0, 2],
+                dofs: vec![0, 1],
+            },
+            "not in any subdomain",
+        ),
+        ("repeated DOF", repeated, "listed twice"),
+        (
+            "oversized subdomain",
+            SchwarzSubdomains::contiguous(n, 40, 0),
+            "expected 1..=32",
+        ),
+        ("DOF out of range", out_of_range, "out of range"),
+    ];
+    for (case, subdomains, expected) in &rejected {
+        match AdditiveSchwarzExecutor::from_csr(ctx, &a, subdomains, SchwarzCombine::Add) {
+            Ok(_) => panic!("additive-schwarz-test failed: {case} accepted"),
+            Err(e) => assert!(
+                e.contains(expected),
+                "additive-schwarz-test failed: {case}: unexpected error '{e}'"
+            ),
+        }
+    }
+
+    println!(
+        "AdditiveSchwarzTest OK: n={n}, core {core}, GPU vs CPU {apply_diff:.2e}, PCG iterations {} (overlap 0) vs {} (overlap {overlap}), {} bad inputs rejected",
+        iterations[0],
+        iterations[1],
+        rejected.len()
+    );
+}
+
+fn run_diagonal_jacobi_test(ctx: &GpuContext) {
+    // 23 x 23 grid: n = 529 is not a multiple of the workgroup size, so the tail
+    // workgroup is exercised too.
+    let a = laplacian_2d(23, 23);
+    let n = a.n_rows as usize;
+    let inv_diag: Vec<f32> = (0..n)
+        .map(|i| {
+            let range = a.row_ptr[i] as usize..a.row_ptr[i + 1] as usize;
+            let d = range
+                .filter(|&k| a.col_idx[k] as usize == i)
+                .map(|k| a.values[k])
+                .sum::<f32>();
+            1.0 / (d + (i % 5) as f32 * 0.5)
+        })
+        .collect();
+    let r: Vec<f32> = (0..n)
+        .map(|i| 1.0 + ((i * 37) % 11) as f32 * 0.25 - (i % 3) as f32)
+        .collect();
+
+    let jacobi = DiagonalJacobiExecutor::create(ctx, n as u32, &inv_diag);
+    assert_eq!(jacobi.n() as usize, n);
+
+    let r_gpu = ctx.create_storage_buffer("diagonal-jacobi-test r", &r, BufferUsages::empty());
+    let z_gpu =
+        ctx.create_storage_buffer_uninit::<f32>("diagonal-jacobi-test z", n, BufferUsages::empty());
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("diagonal-jacobi-test encoder"),
+        });
+    jacobi.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+    ctx.queue.submit(Some(encoder.finish()));
+    let z = executor::block_on(ctx.readback(&z_gpu));
+
+    // One correctly rounded f32 multiply per entry: GPU and CPU agree exactly.
+    let z_ref: Vec<f32> = r.iter().zip(&inv_diag).map(|(r, d)| r * d).collect();
+    let mismatches = z.iter().zip(&z_ref).filter(|(g, c)| g != c).count();
+    assert_eq!(
+        mismatches, 0,
+        "diagonal-jacobi-test failed: {mismatches} of {n} entries differ from r[i] * inv_diag[i]"
+    );
+
+    println!("DiagonalJacobiTest OK: n={n}, z = D^-1 r matches the CPU bitwise");
+}
+
+fn run_csr_sort_test(ctx: &GpuContext) {
+    let sorted = laplacian_2d(17, 13);
+    let n = sorted.n_rows as usize;
+    assert!(
+        sorted.is_sorted(),
+        "csr-sort-test failed: laplacian_2d is not sorted"
+    );
+
+    // Same matrix with every row's entries reversed and rotated.
+    let mut unsorted = sorted.clone();
+    for i in 0..n {
+        let (start, end) = (
+            unsorted.row_ptr[i] as usize,
+            unsorted.row_ptr[i + 1] as usize,
+        );
+        let shift = i % (end - start);
+        unsorted.col_idx[start..end].reverse();
+        unsorted.values[start..end].reverse();
+        unsorted.col_idx[start..end].rotate_left(shift);
+        unsorted.values[start..end].rotate_left(shift);
+    }
+    assert!(
+        !unsorted.is_sorted(),
+        "csr-sort-test failed: scrambled matrix reports sorted"
+    );
+
+    // SpMV is order-independent (integer data, so the sums are exact in any order).
+    let x: Vec<f32> = (0..n).map(|i| (i % 7) as f32 - 3.0).collect();
+    let x_gpu = ctx.create_storage_buffer("csr-sort-test x", &x, BufferUsages::empty());
+    let spmv_gpu = |a: &Csr| {
+        let spmv = SpmvExecutor::create(ctx, a.n_rows, &a.row_ptr, &a.col_idx, &a.values);
+        let y_gpu =
+            ctx.create_storage_buffer_uninit::<f32>("csr-sort-test y", n, BufferUsages::empty());
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("csr-sort-test encoder"),
+            });
+        LinearOperator::encode_apply(&spmv, ctx, &mut encoder, &x_gpu.buffer, &y_gpu.buffer);
+        ctx.queue.submit(Some(encoder.finish()));
+        executor::block_on(ctx.readback(&y_gpu))
+    };
+    let y_sorted = spmv_gpu(&sorted);
+    assert_eq!(
+        spmv_gpu(&unsorted),
+        y_sorted,
+        "csr-sort-test failed: SpMV depends on the entry order"
+    );
+
+    // sort_rows restores the original matrix exactly.
+    let mut resorted = unsorted.clone();
+    resorted.sort_rows();
+    assert!(
+        resorted.is_sorted(),
+        "csr-sort-test failed: sort_rows left unsorted rows"
+    );
+    assert_eq!(
+        resorted.col_idx, sorted.col_idx,
+        "csr-sort-test failed: col_idx"
+    );
+    assert_eq!(
+        resorted.values, sorted.values,
+        "csr-sort-test failed: values"
+    );
+    assert_eq!(
+        spmv_gpu(&resorted),
+        y_sorted,
+        "csr-sort-test failed: sorted SpMV"
+    );
+
+    // Diagonal-block extraction sees the same blocks either way.
+    let block_starts = uniform_block_starts(n, 6);
+    let gather = |a: &Csr| {
+        gather_diagonal_blocks_6(n, &a.row_ptr, &a.col_idx, &a.values, &block_starts)
+            .unwrap_or_else(|e| panic!("csr-sort-test: {e}"))
+    };
+    let blocks = gather(&sorted);
+    assert_eq!(
+        gather(&resorted),
+        blocks,
+        "csr-sort-test failed: diagonal blocks"
+    );
+    assert_eq!(
+        gather(&unsorted),
+        blocks,
+        "csr-sort-test failed: unsorted diagonal blocks"
+    );
+
+    // The loader sorts by default and keeps the file order when asked to.
+    let path = std::env::temp_dir().join(format!("wgpu_solver_csr_sort_{}.bin", process::id()));
+    let mut bytes = Vec::new();
+    let header = [
+        0x4353_5231u32,
+        1,
+        unsorted.n_rows,
+        unsorted.n_cols,
+        unsorted.nnz,
+        0,
+        0,
+        0,
+    ];
+    for word in header
+        .iter()
+        .chain(&unsorted.row_ptr)
+        .chain(&unsorted.col_idx)
+    {
+        bytes.extend_from_slice(&word.to_le_bytes());
+    }
+    for value in &unsorted.values {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    fs::write(&path, &bytes).unwrap_or_else(|e| panic!("csr-sort-test: write: {e}"));
+    let loaded = load_csr_matrix_bin(&path).unwrap_or_else(|e| panic!("csr-sort-test: load: {e}"));
+    let raw = load_csr_matrix_bin_with_options(
+        &path,
+        &CsrLoadOptions {
+            sort_rows: false,
+            ..CsrLoadOptions::default()
+        },
+    )
+    .unwrap_or_else(|e| panic!("csr-sort-test: load: {e}"));
+    let _ = fs::remove_file(&path);
+    assert_eq!(
+        loaded.col_idx, sorted.col_idx,
+        "csr-sort-test failed: loader did not sort"
+    );
+    assert_eq!(
+        loaded.values, sorted.values,
+        "csr-sort-test failed: loader values"
+    );
+    assert_eq!(
+        raw.col_idx, unsorted.col_idx,
+        "csr-sort-test failed: sort_rows = false reordered the file"
+    );
+
+    println!(
+        "CsrSortTest OK: n={n}, nnz={}, unsorted rows sorted, SpMV and diagonal blocks unchanged",
+        sorted.nnz
+    );
+}
+
+fn run_csr_duplicates_test(ctx: &GpuContext) {
+    // 4x4 by hand: row 0 repeats column 0 (sorted), row 2 repeats column 3 around
+    // column 1 (unsorted), row 3 stores column 2 three times, row 1 is clean.
+    let raw = Csr {
+        n_rows: 4,
+        n_cols: 4,
+        nnz: 11,
+        row_ptr: vec![0, 3, 5, 8, 11],
+        col_idx: vec![0, 0, 1, 1, 2, 3, 1, 3, 2, 2, 2],
+        values: vec![3.0, 1.0, -1.0, 4.0, -2.0, 2.0, -1.0, 3.0, 1.0, 0.5, 0.5],
+    };
+    let dense: [[f32; 4]; 4] = [
+        [4.0, -1.0, 0.0, 0.0],
+        [0.0, 4.0, -2.0, 0.0],
+        [0.0, -1.0, 0.0, 5.0],
+        [0.0, 0.0, 2.0, 0.0],
+    ];
+    assert!(
+        raw.has_duplicate_cols(),
+        "csr-duplicates-test failed: duplicates not detected"
+    );
+    assert_eq!(
+        raw.find_duplicate_col(),
+        Some((0, 0)),
+        "csr-duplicates-test failed: first duplicate"
+    );
+    let mut later = raw.clone();
+    later.col_idx[1] = 2;
+    assert_eq!(
+        later.find_duplicate_col(),
+        Some((2, 3)),
+        "csr-duplicates-test failed: duplicate in an unsorted row"
+    );
+    assert!(
+        !laplacian_2d(9, 7).has_duplicate_cols(),
+        "csr-duplicates-test failed: laplacian_2d reports duplicates"
+    );
+
+    let mut summed = raw.clone();
+    assert_eq!(
+        summed.sum_duplicates(),
+        4,
+        "csr-duplicates-test failed: removed entries"
+    );
+    assert_eq!(
+        (summed.nnz, summed.row_ptr.clone()),
+        (7, vec![0, 2, 4, 6, 7]),
+        "csr-duplicates-test failed: summed layout"
+    );
+    // The merged entry keeps the first occurrence's slot, so row 2 stays unsorted.
+    assert_eq!(
+        summed.col_idx,
+        vec![0, 1, 1, 2, 3, 1, 2],
+        "csr-duplicates-test failed: summed col_idx"
+    );
+    assert!(
+        !summed.has_duplicate_cols(),
+        "csr-duplicates-test failed: duplicates left after summing"
+    );
+    let mut got = [[0.0f32; 4]; 4];
+    for (row, w) in got.iter_mut().zip(summed.row_ptr.windows(2)) {
+        for k in w[0] as usize..w[1] as usize {
+            assert_eq!(
+                row[summed.col_idx[k] as usize], 0.0,
+                "csr-duplicates-test failed: column stored twice"
+            );
+            row[summed.col_idx[k] as usize] = summed.values[k];
+        }
+    }
+    assert_eq!(got, dense, "csr-duplicates-test failed: summed entries");
+    assert_eq!(
+        summed.clone().sum_duplicates(),
+        0,
+        "csr-duplicates-test failed: second pass removed entries"
+    );
+
+    // SpMV gives the same result with and without the duplicates.
+    let x = [1.0f32, -2.0, 3.0, 0.5];
+    let x_gpu = ctx.create_storage_buffer("csr-duplicates-test x", &x, BufferUsages::empty());
+    let spmv_gpu = |a: &Csr| {
+        let spmv = SpmvExecutor::create(ctx, a.n_rows, &a.row_ptr, &a.col_idx, &a.values);
+        let y_gpu = ctx.create_storage_buffer_uninit::<f32>(
+            "csr-duplicates-test y",
+            x.len(),
+            BufferUsages::empty(),
+        );
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("csr-duplicates-test encoder"),
+            });
+        LinearOperator::encode_apply(&spmv, ctx, &mut encoder, &x_gpu.buffer, &y_gpu.buffer);
+        ctx.queue.submit(Some(encoder.finish()));
+        executor::block_on(ctx.readback(&y_gpu))
+    };
+    let expected: Vec<f32> = dense
+        .iter()
+        .map(|row| row.iter().zip(&x).map(|(a, x)| a * x).sum())
+        .collect();
+    assert_eq!(
+        spmv_gpu(&summed),
+        expected,
+        "csr-duplicates-test failed: SpMV of the summed matrix"
+    );
+    assert_eq!(
+        spmv_gpu(&raw),
+        expected,
+        "csr-duplicates-test failed: SpMV of the raw matrix"
+    );
+
+    // CsrBuilder sums by default and rejects duplicates in strict mode.
+    let builder = |strict: bool| {
+        let mut builder = CsrBuilder::new().strict(strict);
+        for (row, w) in raw.row_ptr.windows(2).enumerate() {
+            for k in w[0] as usize..w[1] as usize {
+                builder.push(row as u32, raw.col_idx[k], raw.values[k]);
+            }
+        }
+        builder
+    };
+    let built = builder(false).build();
+    let mut sorted = summed.clone();
+    sorted.sort_rows();
+    assert_eq!(
+        (built.nnz, &built.row_ptr, &built.col_idx, &built.values),
+        (sorted.nnz, &sorted.row_ptr, &sorted.col_idx, &sorted.values),
+        "csr-duplicates-test failed: CsrBuilder did not sum duplicates"
+    );
+    let err = builder(true)
+        .try_build()
+        .expect_err("csr-duplicates-test failed: strict CsrBuilder accepted duplicates");
+    assert!(err.contains("(0, 0)"), "csr-duplicates-test: {err}");
+
+    // The matrix.csr.bin loader does the same.
+    let path =
+        std::env::temp_dir().join(format!("wgpu_solver_csr_duplicates_{}.bin", process::id()));
+    let mut bytes = Vec::new();
+    let header = [0x4353_5231u32, 1, raw.n_rows, raw.n_cols, raw.nnz, 0, 0, 0];
+    for word in header.iter().chain(&raw.row_ptr).chain(&raw.col_idx) {
+        bytes.extend_from_slice(&word.to_le_bytes());
+    }
+    for value in &raw.values {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    fs::write(&path, &bytes).unwrap_or_else(|e| panic!("csr-duplicates-test: write: {e}"));
+    let loaded =
+        load_csr_matrix_bin(&path).unwrap_or_else(|e| panic!("csr-duplicates-test: load: {e}"));
+    let strict = load_csr_matrix_bin_with_options(
+        &path,
+        &CsrLoadOptions {
+            strict: true,
+            ..CsrLoadOptions::default()
+        },
+    );
+    let _ = fs::remove_file(&path);
+    assert_eq!(
+        (loaded.nnz, &loaded.col_idx, &loaded.values),
+        (sorted.nnz, &sorted.col_idx, &sorted.values),
+        "csr-duplicates-test failed: loader did not sum duplicates"
+    );
+    let err = strict.expect_err("csr-duplicates-test failed: strict loader accepted duplicates");
+    assert!(
+        err.contains("row 0") && err.contains("column 0"),
+        "csr-duplicates-test: {err}"
+    );
+
+    println!(
+        "CsrDuplicatesTest OK: {} duplicate entries detected and summed, SpMV unchanged, strict builder/loader reject them",
+        raw.nnz - summed.nnz
+    );
+}
+
+fn run_triplet_csv_test() {
+    // 4x5 with an empty row, unordered lines, a duplicate and a mix of separators.
+    let dense: [[f32; 5]; 4] = [
+        [4.0, -1.0, 0.0, 0.0, 0.5],
+        [0.0, 0.0, 0.0, 0.0, 0.0],
+        [-1.0, 0.0, 3.0, 0.0, 0.0],
+        [0.0, 2.5, 0.0, -1.0e-3, 7.0],
+    ];
+    let body = |base: u32| {
+        let line = |r: u32, c: u32, v: &str| format!("{} {} {v}", r + base, c + base);
+        [
+            "% homegrown dump".to_string(),
+            "# row col value".to_string(),
+            line(3, 4, "7"),
+            String::new(),
+            line(0, 0, "4.0"),
+            format!("{},{},-1", base, 1 + base),
+            line(2, 2, "1.5"),
+            format!("  {}\t{}  0.5", base, 4 + base),
+            line(2, 0, "-1.0"),
+            line(3, 1, "2.5e0"),
+            line(2, 2, "1.5"),
+            line(3, 3, "-1e-3"),
+        ]
+        .join("\n")
+    };
+
+    let path = std::env::temp_dir().join(format!("wgpu_solver_triplets_{}.txt", process::id()));
+    let load = |text: &str, one_based: bool| {
+        fs::write(&path, text).unwrap_or_else(|e| panic!("triplet-csv-test: write: {e}"));
+        load_triplet_csv(&path, one_based)
+    };
+
+    for one_based in [false, true] {
+        let csr = load(&body(one_based as u32), one_based)
+            .unwrap_or_else(|e| panic!("triplet-csv-test: load: {e}"));
+        assert_eq!(
+            (csr.n_rows, csr.n_cols, csr.nnz),
+            (4, 5, 8),
+            "triplet-csv-test: shape"
+        );
+        assert!(
+            !csr.has_duplicate_cols(),
+            "triplet-csv-test: duplicate not summed"
+        );
+        assert!(csr.is_sorted(), "triplet-csv-test: rows not sorted");
+        let mut got = [[0.0f32; 5]; 4];
+        for (row, w) in got.iter_mut().zip(csr.row_ptr.windows(2)) {
+            for k in w[0] as usize..w[1] as usize {
+                row[csr.col_idx[k] as usize] += csr.values[k];
+            }
+        }
+        assert_eq!(
+            got, dense,
+            "triplet-csv-test (one_based = {one_based}): entries"
+        );
+    }
+
+    let bad = [
+        ("1 1 2\n0 1 1", true, ":2: row index 0 in a 1-based file"),
+        (
+            "0 0 1\n\n1 1",
+            false,
+            ":3: expected 'row col value', got 2 field(s)",
+        ),
+        ("0 0 x", false, ":1: invalid value 'x'"),
+        ("-1 0 1", false, ":1: invalid row index '-1'"),
+    ];
+    for (text, one_based, expected) in bad {
+        let err = load(text, one_based).expect_err("triplet-csv-test: malformed file accepted");
+        assert!(err.ends_with(expected), "triplet-csv-test: {err}");
+    }
+    let _ = fs::remove_file(&path);
+
+    println!(
+        "TripletCsvTest OK: 0- and 1-based dumps match the 4x5 reference, {} malformed files rejected",
+        bad.len()
+    );
+}
+
+fn run_matrix_market_test(ctx: &GpuContext) {
+    let path =
+        std::env::temp_dir().join(format!("wgpu_solver_matrix_market_{}.mtx", process::id()));
+    let read = |text: &str| {
+        fs::write(&path, text).unwrap_or_else(|e| panic!("matrix-market-test: write: {e}"));
+        read_coo(&path)
+    };
+
+    // Symmetric real: the lower triangle of a 2D Laplacian expands to the full matrix.
+    let a = laplacian_2d(4, 3);
+    let n = a.n_rows as usize;
+    let mut lower = Vec::new();
+    for row in 0..n {
+        for k in a.row_ptr[row] as usize..a.row_ptr[row + 1] as usize {
+            let col = a.col_idx[k] as usize;
+            if col <= row {
+                lower.push(format!("{} {} {:e}", row + 1, col + 1, a.values[k]));
+            }
+        }
+    }
+    let text = format!(
+        "%%MatrixMarket matrix coordinate real symmetric\n% 2D Laplacian, 4 x 3 grid\n%\n{n} {n} {}\n{}\n",
+        lower.len(),
+        lower.join("\n")
+    );
+    let coo = read(&text).unwrap_or_else(|e| panic!("matrix-market-test: {e}"));
+    assert_eq!(
+        coo.nnz(),
+        a.nnz as usize,
+        "matrix-market-test: symmetric expansion"
+    );
+    let csr = coo.to_csr();
+    assert_eq!(
+        (&csr.row_ptr, &csr.col_idx, &csr.values),
+        (&a.row_ptr, &a.col_idx, &a.values),
+        "matrix-market-test: symmetric CSR"
+    );
+
+    // ... and goes straight into the GPU SpMV.
+    let x: Vec<f32> = (0..n).map(|i| 1.0 + (i % 4) as f32 * 0.5).collect();
+    let mut y_ref = vec![0.0f32; n];
+    reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, &x, &mut y_ref);
+    let spmv = SpmvExecutor::create(ctx, csr.n_rows, &csr.row_ptr, &csr.col_idx, &csr.values);
+    let x_gpu = ctx.create_storage_buffer("matrix-market-test x", &x, BufferUsages::empty());
+    let y_gpu =
+        ctx.create_storage_buffer_uninit::<f32>("matrix-market-test y", n, BufferUsages::empty());
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("matrix-market-test encoder"),
+        });
+    LinearOperator::encode_apply(&spmv, ctx, &mut encoder, &x_gpu.buffer, &y_gpu.buffer);
+    ctx.queue.submit(Some(encoder.finish()));
+    let y = executor::block_on(ctx.readback(&y_gpu));
+    assert_eq!(y, y_ref, "matrix-market-test: SpMV");
+
+    // General pattern, rectangular, unordered: every entry is 1.
+    let coo = read("%%MatrixMarket matrix coordinate pattern general\n3 4 4\n3 4\n1 1\n2 3\n1 2\n")
+        .unwrap_or_else(|e| panic!("matrix-market-test: {e}"));
+    let csr = coo.to_csr();
+    assert_eq!((csr.n_rows, csr.n_cols), (3, 4));
+    assert_eq!(csr.row_ptr, [0, 2, 3, 4]);
+    assert_eq!(csr.col_idx, [0, 1, 2, 3]);
+    assert_eq!(csr.values, [1.0; 4]);
+
+    let header = "%%MatrixMarket matrix coordinate real general\n";
+    let bad = [
+        (
+            format!("{header}2 2 3\n1 1 1\n2 2 1\n"),
+            "declares 3 entries but the file has 2",
+        ),
+        (
+            format!("{header}2 2 1\n1 1 1\n2 2 1\n"),
+            ":4: more entries than the 1 of the size line",
+        ),
+        (
+            format!("{header}2 2 1\n3 1 1\n"),
+            ":3: row index 3 outside 1..=2",
+        ),
+        (
+            format!("{header}2 2\n"),
+            ":2: size line needs 'M N NNZ', got '2 2'",
+        ),
+        (
+            "%%MatrixMarket matrix coordinate real skew-symmetric\n2 2 0\n".to_string(),
+            ":1: unsupported symmetry 'skew-symmetric'",
+        ),
+        (
+            "%%MatrixMarket matrix coordinate real symmetric\n2 3 0\n".to_string(),
+            "symmetric matrix must be square, got 2x3",
+        ),
+    ];
+    for (text, expected) in &bad {
+        let err = read(text).expect_err("matrix-market-test: malformed file accepted");
+        assert!(
+            matches!(err, IoError::Parse { .. }) && err.to_string().ends_with(expected),
+            "matrix-market-test: {err}"
+        );
+    }
+    let _ = fs::remove_file(&path);
+    assert!(
+        matches!(read_coo(&path), Err(IoError::Io { what: "open", .. })),
+        "matrix-market-test: missing file not reported as an open error"
+    );
+
+    println!(
+        "MatrixMarketTest OK: symmetric {n}x{n} Laplacian expanded to nnz={} and matches SpMV, pattern 3x4 read, {} malformed files rejected",
+        a.nnz,
+        bad.len()
+    );
+}
+
+fn run_spmv_empty_rows_test(ctx: &GpuContext) {
+    // (n, is_empty(row)): scattered empty rows, empty rows at both ends, a whole
+    // workgroup (rows 256..512) of empty rows, and a matrix without any non-zeros.
+    type IsEmptyRow = fn(usize, usize) -> bool;
+    let cases: [(usize, IsEmptyRow); 2] = [
+        (1000, |i, n| {
+            i % 3 == 0 || i < 5 || i >= n - 5 || (256..512).contains(&i)
+        }),
+        (300, |_, _| true),
+    ];
+
+    for (n, is_empty) in cases {
+        let mut row_ptr = vec![0u32];
+        let mut col_idx: Vec<u32> = Vec::new();
+        let mut values: Vec<f32> = Vec::new();
+        for i in 0..n {
+            if !is_empty(i, n) {
+                for j in i.saturating_sub(1)..(i + 2).min(n) {
+                    col_idx.push(j as u32);
+                    values.push(if i == j {
+                        2.0
+                    } else {
+                        -1.0 - (j % 4) as f32 * 0.25
+                    });
+                }
+            }
+            row_ptr.push(col_idx.len() as u32);
+        }
+
+        let x_host: Vec<f32> = (0..n).map(|i| 1.0 + (i % 7) as f32 * 0.5).collect();
+        let mut y_ref = vec![0.0f32; n];
+        reference::spmv_csr(&row_ptr, &col_idx, &values, &x_host, &mut y_ref);
+
+        let spmv = SpmvExecutor::create(ctx, n as u32, &row_ptr, &col_idx, &values);
+        let x_gpu = ctx.create_storage_buffer("spmv-empty-rows x", &x_host, BufferUsages::empty());
+
+        // Stale output from an earlier call must not survive in empty rows.
+        let stale = ctx.create_storage_buffer(
+            "spmv-empty-rows stale y",
+            &vec![123.0f32; n],
+            BufferUsages::empty(),
+        );
+
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("spmv-empty-rows-test encoder"),
+            });
+        encoder.copy_buffer_to_buffer(&stale.buffer, 0, spmv.y_buffer(), 0, (n as u64) * 4);
+        spmv.encode_copy_x_from(&mut encoder, &x_gpu.buffer, (n as u64) * 4);
+        spmv.encode_spmv(&mut encoder);
+        ctx.queue.submit(Some(encoder.finish()));
+
+        let y_out: Vec<f32> = executor::block_on(readback_to_vec::<f32>(
+            &ctx.device,
+            &ctx.queue,
+            spmv.y_buffer(),
+            n,
+            Some("spmv-empty-rows-test y readback"),
+        ));
+
+        for i in 0..n {
+            if is_empty(i, n) {
+                assert!(
+                    y_out[i].to_bits() == 0.0f32.to_bits(),
+                    "spmv-empty-rows-test failed: n={n}, empty row {i} gave {}",
+                    y_out[i]
+                );
+            } else {
+                assert!(
+                    (y_out[i] - y_ref[i]).abs() <= 1e-5 * y_ref[i].abs().max(1.0),
+                    "spmv-empty-rows-test failed: n={n}, row {i}: gpu {} vs ref {}",
+                    y_out[i],
+                    y_ref[i]
+                );
+            }
+        }
+    }
+
+    println!(
+        "SpmvEmptyRowsTest OK: empty rows give exact +0.0 (scattered, edges, whole workgroup, nnz = 0)"
+    );
+}
+
+fn run_spmv_apply_test(ctx: &GpuContext) {
+    // Malformed CSR input is an error, not a panic or a bad dispatch.
+    let a = laplacian_2d(20, 15);
+    let n = a.n_rows as usize;
+    let bad_inputs: [(&str, Vec<u32>, usize); 3] = [
+        ("short row_ptr", a.row_ptr[..n].to_vec(), a.col_idx.len()),
+        (
+            "decreasing row_ptr",
+            {
+                let mut row_ptr = a.row_ptr.clone();
+                row_ptr.swap(1, 2);
+                row_ptr
+            },
+            a.col_idx.len(),
+        ),
+        ("nnz mismatch", a.row_ptr.clone(), a.col_idx.len() - 1),
+    ];
+    for (what, row_ptr, nnz) in bad_inputs {
+        let result =
+            SpmvExecutor::try_create(ctx, a.n_rows, &row_ptr, &a.col_idx[..nnz], &a.values[..nnz]);
+        assert!(result.is_err(), "spmv-apply-test failed: {what} accepted");
+    }
+
+    // encode_apply on caller buffers matches the internal-buffer path bitwise.
+    let spmv = SpmvExecutor::try_create(ctx, a.n_rows, &a.row_ptr, &a.col_idx, &a.values)
+        .unwrap_or_else(|e| panic!("spmv-apply-test: {e}"));
+    let x: Vec<f32> = (0..n)
+        .map(|i| ((i * 13) % 17) as f32 * 0.125 - 1.0)
+        .collect();
+    let x_gpu = ctx.create_storage_buffer("spmv-apply-test x", &x, BufferUsages::empty());
+    let y_gpu =
+        ctx.create_storage_buffer_uninit::<f32>("spmv-apply-test y", n, BufferUsages::empty());
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("spmv-apply-test encoder"),
+        });
+    spmv.encode_copy_x_from(&mut encoder, &x_gpu.buffer, (n * 4) as u64);
+    spmv.encode_spmv(&mut encoder);
+    spmv.encode_apply(ctx, &mut encoder, &x_gpu.buffer, &y_gpu.buffer);
+    ctx.queue.submit(Some(encoder.finish()));
+    let y_internal: Vec<f32> = executor::block_on(readback_to_vec::<f32>(
+        &ctx.device,
+        &ctx.queue,
+        spmv.y_buffer(),
+        n,
+        Some("spmv-apply-test y readback"),
+    ));
+    let y_apply = executor::block_on(ctx.readback(&y_gpu));
+    assert_eq!(
+        y_apply, y_internal,
+        "spmv-apply-test failed: encode_apply differs from encode_spmv"
+    );
+    let mut y_ref = vec![0.0f32; n];
+    reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, &x, &mut y_ref);
+    assert_eq!(
+        y_apply, y_ref,
+        "spmv-apply-test failed: differs from the CPU"
+    );
+
+    // More rows than one dispatch can cover: the grid-stride loop picks up the tail.
+    let max_groups = ctx.device.limits().max_compute_workgroups_per_dimension as usize;
+    let big_n = max_groups * 256 + 1000;
+    let row_ptr: Vec<u32> = (0..=big_n as u32).collect();
+    let col_idx: Vec<u32> = (0..big_n as u32).collect();
+    let values: Vec<f32> = (0..big_n).map(|i| (i % 7 + 1) as f32).collect();
+    let big = SpmvExecutor::try_create(ctx, big_n as u32, &row_ptr, &col_idx, &values)
+        .unwrap_or_else(|e| panic!("spmv-apply-test: {e}"));
+    let ones = ctx.create_storage_buffer(
+        "spmv-apply-test ones",
+        &vec![1.0f32; big_n],
+        BufferUsages::empty(),
+    );
+    let y_big = ctx.create_storage_buffer_uninit::<f32>(
+        "spmv-apply-test y big",
+        big_n,
+        BufferUsages::empty(),
+    );
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("spmv-apply-test big encoder"),
+        });
+    big.encode_apply(ctx, &mut encoder, &ones.buffer, &y_big.buffer);
+    ctx.queue.submit(Some(encoder.finish()));
+    let y = executor::block_on(ctx.readback(&y_big));
+    let wrong = y.iter().zip(&values).filter(|(y, v)| y != v).count();
+    assert_eq!(
+        wrong, 0,
+        "spmv-apply-test failed: {wrong} of {big_n} rows wrong past the dispatch limit"
+    );
+
+    println!(
+        "SpmvApplyTest OK: malformed CSR rejected, caller-buffer apply == encode_spmv == CPU (n={n}), {big_n} rows past {max_groups} workgroups"
+    );
+}
+
+fn run_spmv_fuzz_test(ctx: &GpuContext) {
+    const SEED: u64 = 0x5eed_5a3e;
+    let mut rng = SplitMix64(SEED);
+
+    // Sizes around the 256-wide workgroup boundary, plus tiny and larger ones.
+    let sizes = [1usize, 2, 3, 31, 255, 256, 257, 600, 2049];
+    let mut total_nnz = 0usize;
+
+    for (case, &n) in sizes.iter().enumerate() {
+        // Rows: ~15% empty, ~5% full, the rest 1..=12 random distinct columns.
+        let mut row_ptr = vec![0u32];
+        let mut col_idx: Vec<u32> = Vec::new();
+        let mut values: Vec<f32> = Vec::new();
+        for _ in 0..n {
+            let roll = rng.next_f32();
+            let cols: Vec<usize> = if roll < 0.15 {
+                Vec::new()
+            } else if roll < 0.20 {
+                (0..n).collect()
+            } else {
+                let k = 1 + rng.below(n.min(12));
+                let mut cols: Vec<usize> = (0..k).map(|_| rng.below(n)).collect();
+                cols.sort_unstable();
+                cols.dedup();
+                cols
+            };
+            for j in cols {
+                col_idx.push(j as u32);
+                values.push(rng.next_f32() * 2.0 - 1.0);
+            }
+            row_ptr.push(col_idx.len() as u32);
+        }
+        // Buffers cannot be empty: make sure there is at least one entry.
+        if col_idx.is_empty() {
+            col_idx.push(0);
+            values.push(1.0);
+            for p in row_ptr.iter_mut().skip(1) {
+                *p = 1;
+            }
+        }
+        total_nnz += values.len();
+
+        let x_host: Vec<f32> = (0..n).map(|_| rng.next_f32() * 2.0 - 1.0).collect();
+
+        // Dense reference (f64).
+        let mut dense = vec![0.0f64; n * n];
+        for i in 0..n {
+            for idx in row_ptr[i] as usize..row_ptr[i + 1] as usize {
+                dense[i * n + col_idx[idx] as usize] = values[idx] as f64;
+            }
+        }
+
+        let spmv = SpmvExecutor::create(ctx, n as u32, &row_ptr, &col_idx, &values);
+        let x_gpu = ctx.create_storage_buffer("spmv-fuzz x", &x_host, BufferUsages::empty());
+
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("spmv-fuzz-test encoder"),
+            });
+        spmv.encode_copy_x_from(&mut encoder, &x_gpu.buffer, (n as u64) * 4);
+        spmv.encode_spmv(&mut encoder);
+        ctx.queue.submit(Some(encoder.finish()));
+
+        let y_out: Vec<f32> = executor::block_on(readback_to_vec::<f32>(
+            &ctx.device,
+            &ctx.queue,
+            spmv.y_buffer(),
+            n,
+            Some("spmv-fuzz-test y readback"),
+        ));
+
+        for i in 0..n {
+            let row = &dense[i * n..(i + 1) * n];
+            let expected: f64 = row.iter().zip(&x_host).map(|(a, x)| a * *x as f64).sum();
+            let scale: f64 = row
+                .iter()
+                .zip(&x_host)
+                .map(|(a, x)| (a * *x as f64).abs())
+                .sum();
+            let got = y_out[i] as f64;
+            assert!(
+                (got - expected).abs() <= 1e-5 * scale + 1e-6,
+                "spmv-fuzz-test failed (seed {SEED:#x}, case {case}, n={n}, nnz={}): \
+                 row {i} ({} entries): got {got}, expected {expected}",
+                values.len(),
+                row_ptr[i + 1] - row_ptr[i]
+            );
+        }
+    }
+
+    println!(
+        "SpmvFuzzTest OK: {} random CSR matrices (n up to {}, {total_nnz} nnz total) match the dense reference",
+        sizes.len(),
+        sizes[sizes.len() - 1]
+    );
+}
+
+/// CSR matrix with `row_len` non-zeros in every row at evenly spaced columns
+/// (i, i + n / row_len, ...), wrapped mod n; values in [-1, 1).
+fn spmv_strategy_matrix(n: usize, row_len: usize, rng: &mut SplitMix64) -> Csr {
+    let step = (n / row_len.max(1)).max(1);
+    let mut row_ptr = vec![0u32];
+    let mut col_idx = Vec::with_capacity(n * row_len);
+    let mut values = Vec::with_capacity(n * row_len);
+    for i in 0..n {
+        let mut cols: Vec<u32> = (0..row_len).map(|k| ((i + k * step) % n) as u32).collect();
+        cols.sort_unstable();
+        cols.dedup();
+        for j in cols {
+            col_idx.push(j);
+            values.push(rng.next_f32() * 2.0 - 1.0);
+        }
+        row_ptr.push(col_idx.len() as u32);
+    }
+    Csr {
+        n_rows: n as u32,
+        n_cols: n as u32,
+        nnz: col_idx.len() as u32,
+        row_ptr,
+        col_idx,
+        values,
+    }
+}
+
+/// y = A x through `encode_apply` on fresh caller buffers.
+fn spmv_strategy_apply(ctx: &GpuContext, spmv: &SpmvExecutor, x: &[f32]) -> Vec<f32> {
+    let x_gpu = ctx.create_storage_buffer("spmv-strategy x", x, BufferUsages::empty());
+    let y_gpu = ctx.create_storage_buffer_uninit::<f32>(
+        "spmv-strategy y",
+        spmv.n_rows() as usize,
+        BufferUsages::empty(),
+    );
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("spmv-strategy encoder"),
+        });
+    spmv.encode_apply(ctx, &mut encoder, &x_gpu.buffer, &y_gpu.buffer);
+    ctx.queue.submit(Some(encoder.finish()));
+    executor::block_on(ctx.readback(&y_gpu))
+}
+
+fn run_spmv_strategy_test(ctx: &GpuContext) {
+    const SEED: u64 = 0x5eed_3a71;
+    let mut rng = SplitMix64(SEED);
+
+    // Row lengths around the 32-lane chunk — empty, 1, 31, 32, 33, 64, 300 — and random
+    // ones, so both kernels see rows shorter and longer than a chunk.
+    let n = 600usize;
+    let fixed = [0usize, 1, 31, 32, 33, 64, 300];
+    let mut row_ptr = vec![0u32];
+    let mut col_idx: Vec<u32> = Vec::new();
+    let mut values: Vec<f32> = Vec::new();
+    for i in 0..n {
+        let len = if i < fixed.len() {
+            fixed[i]
+        } else if rng.next_f32() < 0.1 {
+            0
+        } else {
+            1 + rng.below(120)
+        };
+        let mut cols: Vec<u32> = (0..len).map(|_| rng.below(n) as u32).collect();
+        cols.sort_unstable();
+        cols.dedup();
+        for j in cols {
+            col_idx.push(j);
+            values.push(rng.next_f32() * 2.0 - 1.0);
+        }
+        row_ptr.push(col_idx.len() as u32);
+    }
+    let x: Vec<f32> = (0..n).map(|_| rng.next_f32() * 2.0 - 1.0).collect();
+    let mut y_ref = vec![0.0f32; n];
+    reference::spmv_csr(&row_ptr, &col_idx, &values, &x, &mut y_ref);
+
+    for strategy in [SpmvStrategy::ScalarRow, SpmvStrategy::WarpRow] {
+        let spmv = SpmvExecutor::try_create_with_strategy(
+            ctx, n as u32, &row_ptr, &col_idx, &values, strategy,
+        )
+        .unwrap_or_else(|e| panic!("spmv-strategy-test: {e}"));
+        assert_eq!(spmv.strategy(), strategy);
+        let y = spmv_strategy_apply(ctx, &spmv, &x);
+        for i in 0..n {
+            let scale: f32 = (row_ptr[i] as usize..row_ptr[i + 1] as usize)
+                .map(|k| (values[k] * x[col_idx[k] as usize]).abs())
+                .sum();
+            assert!(
+                (y[i] - y_ref[i]).abs() <= 1e-5 * scale + 1e-6,
+                "spmv-strategy-test failed ({strategy:?}, seed {SEED:#x}): row {i} ({} entries): got {}, expected {}",
+                row_ptr[i + 1] - row_ptr[i],
+                y[i],
+                y_ref[i]
+            );
+            if row_ptr[i] == row_ptr[i + 1] {
+                assert_eq!(
+                    y[i].to_bits(),
+                    0,
+                    "spmv-strategy-test failed ({strategy:?}): empty row {i} gave {}",
+                    y[i]
+                );
+            }
+        }
+    }
+
+    // Auto: a 5-point Laplacian stays on the scalar kernel, 64 entries per row go warp-row
+    // (except on CPU adapters, where warp-row never pays off).
+    let device_type = ctx.adapter_info.device_type;
+    let short = laplacian_2d(16, 16);
+    let wide = spmv_strategy_matrix(512, 64, &mut rng);
+    let wide_kernel = if device_type == DeviceType::Cpu {
+        SpmvStrategy::ScalarRow
+    } else {
+        SpmvStrategy::WarpRow
+    };
+    for (a, expected) in [(&short, SpmvStrategy::ScalarRow), (&wide, wide_kernel)] {
+        let spmv = SpmvExecutor::try_create_with_strategy(
+            ctx,
+            a.n_rows,
+            &a.row_ptr,
+            &a.col_idx,
+            &a.values,
+            SpmvStrategy::Auto,
+        )
+        .unwrap_or_else(|e| panic!("spmv-strategy-test: {e}"));
+        assert_eq!(
+            spmv.strategy(),
+            expected,
+            "spmv-strategy-test failed: Auto on {} nnz / {} rows",
+            a.nnz,
+            a.n_rows
+        );
+        assert_eq!(spmv.kernel_launch().kernel, expected.kernel_name());
+        let x: Vec<f32> = (0..a.n_rows).map(|i| (i % 5) as f32 - 2.0).collect();
+        let mut y_ref = vec![0.0f32; a.n_rows as usize];
+        reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, &x, &mut y_ref);
+        let y = spmv_strategy_apply(ctx, &spmv, &x);
+        let max_err = y
+            .iter()
+            .zip(&y_ref)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0f32, f32::max);
+        assert!(
+            max_err < 1e-4,
+            "spmv-strategy-test failed: Auto ({expected:?}) max error {max_err}"
+        );
+    }
+    let gpu = DeviceType::DiscreteGpu;
+    let wide_nnz = 10 * SPMV_WARP_ROW_MIN_AVG_NNZ;
+    assert_eq!(
+        SpmvStrategy::Auto.resolve(gpu, 0, 0),
+        SpmvStrategy::ScalarRow
+    );
+    assert_eq!(
+        SpmvStrategy::Auto.resolve(gpu, 10, wide_nnz - 1),
+        SpmvStrategy::ScalarRow
+    );
+    assert_eq!(
+        SpmvStrategy::Auto.resolve(gpu, 10, wide_nnz),
+        SpmvStrategy::WarpRow
+    );
+    assert_eq!(
+        SpmvStrategy::Auto.resolve(DeviceType::Cpu, 10, wide_nnz),
+        SpmvStrategy::ScalarRow
+    );
+
+    // Warp rows past the dispatch limit (8 rows per workgroup): the grid-stride tail.
+    let max_groups = ctx.device.limits().max_compute_workgroups_per_dimension as usize;
+    let big_n = max_groups * 8 + 100;
+    let row_ptr: Vec<u32> = (0..=big_n as u32).collect();
+    let col_idx: Vec<u32> = (0..big_n as u32).collect();
+    let values: Vec<f32> = (0..big_n).map(|i| (i % 7 + 1) as f32).collect();
+    let big = SpmvExecutor::try_create_with_strategy(
+        ctx,
+        big_n as u32,
+        &row_ptr,
+        &col_idx,
+        &values,
+        SpmvStrategy::WarpRow,
+    )
+    .unwrap_or_else(|e| panic!("spmv-strategy-test: {e}"));
+    assert_eq!(big.kernel_launch().workgroups as usize, max_groups);
+    let y = spmv_strategy_apply(ctx, &big, &vec![1.0f32; big_n]);
+    let wrong = y.iter().zip(&values).filter(|(y, v)| y != v).count();
+    assert_eq!(
+        wrong, 0,
+        "spmv-strategy-test failed: {wrong} of {big_n} warp rows wrong past the dispatch limit"
+    );
+
+    println!(
+        "SpmvStrategyTest OK: scalar-row == warp-row == CPU on n={n} (row lengths 0..300), Auto threshold {SPMV_WARP_ROW_MIN_AVG_NNZ} nnz/row, {big_n} warp rows past {max_groups} workgroups"
+    );
+}
+
+#[derive(Serialize)]
+struct SpmvStrategyBenchMetrics {
+    run_id: String,
+    command: String,
+    gpu: GpuMetrics,
+    nnz: usize,
+    reps: usize,
+    auto_min_avg_nnz: u32,
+    rows: Vec<SpmvStrategyBenchRowMetrics>,
+    /// Shortest row length at which warp-row beat scalar-row (None: never)
+    measured_crossover_row_len: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct SpmvStrategyBenchRowMetrics {
+    row_len: usize,
+    n: u32,
+    scalar_row_ms: f64,
+    warp_row_ms: f64,
+    /// scalar-row / warp-row time (> 1: warp-row faster)
+    warp_row_speedup: f64,
+    auto: String,
+}
+
+/// Average wall time of one `encode_spmv` over `reps` passes in one submit (after a warm-up).
+fn time_spmv(ctx: &GpuContext, spmv: &SpmvExecutor, reps: usize) -> f64 {
+    let submit = |passes: usize| {
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("spmv-strategy-bench encoder"),
+            });
+        for _ in 0..passes {
+            spmv.encode_spmv(&mut encoder);
+        }
+        ctx.queue.submit(Some(encoder.finish()));
+        let _ = ctx.device.poll(wgpu::PollType::wait_indefinitely());
+    };
+    submit(1);
+    let start = std::time::Instant::now();
+    submit(reps);
+    start.elapsed().as_secs_f64() * 1e3 / reps.max(1) as f64
+}
+
+fn run_block_jacobi_test(ctx: &GpuContext) {
+    // Two 6x6 blocks => n = 12
+    let n: u32 = 12;
+
+    // block_starts: [0, 6, 12]
+    let block_starts: Vec<u32> = vec![0, 6, 12];
+
+    // Identity LU blocks: 2 blocks * 36 floats each
+    let mut lu_blocks = vec![0.0f32; 2 * 36];
+    for b in 0..2 {
+        for i in 0..6 {
+            lu_blocks[b * 36 + i * 6 + i] = 1.0;
+        }
+    }
+
+    // r = [1..12]
+    let r_host: Vec<f32> = (1..=12).map(|v| v as f32).collect();
+
+    // Upload r and allocate z
+    let r_gpu = ctx.create_storage_buffer("bj-test r", &r_host, BufferUsages::empty());
+    let z_gpu =
+        ctx.create_storage_buffer_uninit::<f32>("bj-test z", n as usize, BufferUsages::COPY_SRC);
+
+    // Create executor
+    let bj = BlockJacobiExecutor::create(ctx, n, 6, &lu_blocks, &block_starts)
+        .unwrap_or_else(|e| panic!("block-jacobi-test: {e}"));
+
+    // Encode apply and submit once
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("block-jacobi-test encoder"),
+        });
+
+    bj.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+
+    ctx.queue.submit(Some(encoder.finish()));
+
+    // Read back z
+    let z_out = executor::block_on(ctx.readback(&z_gpu));
+
+    // Assert z == r
+    for i in 0..(n as usize) {
+        let got = z_out[i];
+        let exp = r_host[i];
+        assert!(
+            (got - exp).abs() < 1e-6,
+            "block-jacobi-test failed at i={i}: got {got}, expected {exp}"
+        );
+    }
+
+    println!("BlockJacobiTest OK: z == r (identity blocks)");
+}
+
+fn run_block_jacobi_align_test(ctx: &GpuContext) {
+    // Real LU factors (tridiagonal blocks) with a short final block.
+    let n = 6 * 20_000 + 3;
+    let (row_ptr, col_idx, values) = tridiagonal_test_matrix(n);
+    let block_starts = uniform_block_starts(n, 6);
+    let lu_blocks =
+        build_lu_blocks_from_csr_block_starts_6(n, &row_ptr, &col_idx, &values, &block_starts)
+            .unwrap_or_else(|e| panic!("block-jacobi-align-test: {e}"));
+
+    let r_host: Vec<f32> = (0..n).map(|i| ((i % 11) as f32) - 5.0).collect();
+    let r_gpu = ctx.create_storage_buffer("bj-align r", &r_host, BufferUsages::empty());
+    let z_gpu = ctx.create_storage_buffer_uninit::<f32>("bj-align z", n, BufferUsages::COPY_SRC);
+
+    // Repeated applies per layout, so the printed timings mean something.
+    let reps = 20;
+    let apply = |bj: &BlockJacobiExecutor| -> (Vec<f32>, f64) {
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("block-jacobi-align-test encoder"),
+            });
+        for _ in 0..reps {
+            bj.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+        }
+        let t0 = std::time::Instant::now();
+        ctx.queue.submit(Some(encoder.finish()));
+        let z = executor::block_on(ctx.readback(&z_gpu));
+        (z, t0.elapsed().as_secs_f64() * 1e3 / reps as f64)
+    };
+
+    let dense = BlockJacobiExecutor::create(ctx, n as u32, 6, &lu_blocks, &block_starts)
+        .unwrap_or_else(|e| panic!("block-jacobi-align-test: {e}"));
+    assert_eq!(dense.lu_stride(), 36);
+    let (z_dense, ms_dense) = apply(&dense);
+
+    let mut report = format!("dense {ms_dense:.2} ms");
+    for (alignment, stride) in [(16u32, 36u32), (32, 40), (64, 48), (256, 64)] {
+        let bj = BlockJacobiExecutor::create_with_alignment(
+            ctx,
+            n as u32,
+            6,
+            &lu_blocks,
+            &block_starts,
+            alignment,
+        )
+        .unwrap_or_else(|e| panic!("block-jacobi-align-test: {e}"));
+        assert_eq!(
+            bj.lu_stride(),
+            stride,
+            "block-jacobi-align-test failed: alignment {alignment} stride"
+        );
+
+        let (z, ms) = apply(&bj);
+        for (i, (a, b)) in z.iter().zip(&z_dense).enumerate() {
+            assert!(
+                a.to_bits() == b.to_bits(),
+                "block-jacobi-align-test failed: alignment {alignment}, i={i}: {a} vs dense {b}"
+            );
+        }
+        report.push_str(&format!(", {alignment}B {ms:.2} ms"));
+    }
+
+    println!("BlockJacobiAlignTest OK: padded == dense (per apply: {report})");
+}
+
+fn run_block_jacobi_f16_test(ctx: &GpuContext) {
+    // Conversion: exact values, rounding to nearest even, overflow, subnormals, NaN.
+    let cases: [(f32, u16); 10] = [
+        (0.0, 0x0000),
+        (-0.0, 0x8000),
+        (1.0, 0x3c00),
+        (-2.0, 0xc000),
+        (0.1, 0x2e66),
+        (1.0 + 3.0 * 2f32.powi(-11), 0x3c02), // tie between 0x3c01 and 0x3c02: even
+        (65504.0, 0x7bff),
+        (65520.0, 0x7c00), // half-way to the next binade: overflows to inf
+        (2f32.powi(-24), 0x0001),
+        (1e-8, 0x0000),
+    ];
+    for (v, bits) in cases {
+        assert_eq!(
+            f32_to_f16_bits(v),
+            bits,
+            "block-jacobi-f16-test failed: f16({v:e})"
+        );
+    }
+    assert!(f16_bits_to_f32(f32_to_f16_bits(f32::NAN)).is_nan());
+    for bits in 0..=u16::MAX {
+        let v = f16_bits_to_f32(bits);
+        assert!(
+            v.is_nan() || f32_to_f16_bits(v) == bits,
+            "block-jacobi-f16-test failed: round trip of 0x{bits:04x} ({v:e})"
+        );
+    }
+
+    let n = 6 * 500 + 4;
+    let (row_ptr, col_idx, values) = tridiagonal_test_matrix(n);
+    let block_starts = uniform_block_starts(n, 6);
+    let lu_blocks =
+        build_lu_blocks_from_csr_block_starts_6(n, &row_ptr, &col_idx, &values, &block_starts)
+            .unwrap_or_else(|e| panic!("block-jacobi-f16-test: {e}"));
+    let r: Vec<f32> = (0..n).map(|i| ((i % 13) as f32) - 6.0).collect();
+    let r_gpu = ctx.create_storage_buffer("bj-f16 r", &r, BufferUsages::empty());
+    let z_gpu = ctx.create_storage_buffer_uninit::<f32>("bj-f16 z", n, BufferUsages::COPY_SRC);
+    let apply = |bj: &BlockJacobiExecutor| {
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("block-jacobi-f16-test encoder"),
+            });
+        bj.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+        ctx.queue.submit(Some(encoder.finish()));
+        executor::block_on(ctx.readback(&z_gpu))
+    };
+
+    let f32_exec = BlockJacobiExecutor::create(ctx, n as u32, 6, &lu_blocks, &block_starts)
+        .unwrap_or_else(|e| panic!("block-jacobi-f16-test: {e}"));
+    let z_f32 = apply(&f32_exec);
+    let half = BlockJacobiExecutor::create_with_storage(
+        ctx,
+        n as u32,
+        6,
+        &lu_blocks,
+        &block_starts,
+        LuStorage::F16,
+    )
+    .unwrap_or_else(|e| panic!("block-jacobi-f16-test: {e}"));
+    assert_eq!(half.lu_stride(), 36);
+    let z_half = apply(&half);
+
+    let mode = if LuStorage::F16.supported_by(ctx) {
+        assert_eq!(half.lu_storage(), LuStorage::F16);
+        // Same substitution on the host with the factors rounded to f16.
+        let rounded: Vec<f32> = lu_blocks
+            .iter()
+            .map(|&v| f16_bits_to_f32(f32_to_f16_bits(v)))
+            .collect();
+        let mut z_ref = vec![0.0f32; n];
+        reference::block_jacobi_apply_6(&rounded, &block_starts, &r, &mut z_ref);
+        let vs_rounded = compare(&z_ref, &z_half);
+        assert!(
+            vs_rounded.rel_l2_diff <= 1e-6,
+            "block-jacobi-f16-test failed: f16 apply vs host on rounded blocks: {vs_rounded:?}"
+        );
+        let vs_f32 = compare(&z_f32, &z_half);
+        assert!(
+            vs_f32.rel_l2_diff <= 1e-2,
+            "block-jacobi-f16-test failed: f16 apply vs f32 apply: {vs_f32:?}"
+        );
+        format!(
+            "f16 blocks, rel err {:.2e} vs f32 blocks",
+            vs_f32.rel_l2_diff
+        )
+    } else {
+        assert_eq!(
+            half.lu_storage(),
+            LuStorage::F32,
+            "block-jacobi-f16-test failed: no SHADER_F16 but f16 storage kept"
+        );
+        assert!(
+            z_half
+                .iter()
+                .zip(&z_f32)
+                .all(|(a, b)| a.to_bits() == b.to_bits()),
+            "block-jacobi-f16-test failed: f32 fallback differs from create"
+        );
+        "no SHADER_F16, fell back to f32 blocks".to_string()
+    };
+
+    println!("BlockJacobiF16Test OK: f16 conversion round-trips all 65536 values, {mode}");
+}
+
+const PAP: u32 = 0;
+const RZ_NEW: u32 = 1;
+const RZ_OLD: u32 = 2;
+const ALPHA: u32 = 3;
+const MINUS_ALPHA: u32 = 4;
+const BETA: u32 = 5;
+
+fn run_block_starts_test(ctx: &GpuContext) {
+    let n = 12u32;
+    let identity_blocks = |num_blocks: usize| {
+        let mut lu = vec![0.0f32; num_blocks * 36];
+        for block in lu.chunks_exact_mut(36) {
+            for i in 0..6 {
+                block[i * 6 + i] = 1.0;
+            }
+        }
+        lu
+    };
+
+    // Accepted, including blocks shorter than bs; the identity apply copies r to z.
+    for starts in [vec![0u32, 6, 12], vec![0, 4, 10, 12], vec![0, 1, 2, 8, 12]] {
+        let exec =
+            BlockJacobiExecutor::create(ctx, n, 6, &identity_blocks(starts.len() - 1), &starts)
+                .unwrap_or_else(|e| panic!("block-starts-test failed: {starts:?} rejected: {e}"));
+        let r: Vec<f32> = (0..n).map(|i| 1.0 + i as f32).collect();
+        let r_gpu = ctx.create_storage_buffer("block-starts-test r", &r, BufferUsages::empty());
+        let z_gpu = ctx.create_storage_buffer_uninit::<f32>(
+            "block-starts-test z",
+            n as usize,
+            BufferUsages::empty(),
+        );
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("block-starts-test encoder"),
+            });
+        exec.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+        ctx.queue.submit(Some(encoder.finish()));
+        assert_eq!(
+            executor::block_on(ctx.readback(&z_gpu)),
+            r,
+            "block-starts-test failed: identity apply with {starts:?}"
+        );
+    }
+
+    // Rejected with the first offending index, through every square constructor.
+    let cases: [(&[u32], &str); 6] = [
+        (&[], "block_starts is empty"),
+        (&[1, 6, 12], "block_starts[0] = 1, must be 0"),
+        (
+            &[0, 6, 6, 12],
+            "not strictly increasing at index 2: 6 after 6",
+        ),
+        (
+            &[0, 5, 3, 12],
+            "not strictly increasing at index 2: 3 after 5",
+        ),
+        (
+            &[0, 4, 11, 12],
+            "block 1 ([4, 11)) has 7 rows, more than block_size = 6",
+        ),
+        (&[0, 6, 11], "block_starts ends at 11, must equal n = 12"),
+    ];
+    for (starts, expected) in cases {
+        let lu = identity_blocks(starts.len().saturating_sub(1));
+        let results = [
+            BlockJacobiExecutor::create(ctx, n, 6, &lu, starts),
+            BlockJacobiExecutor::create_with_alignment(ctx, n, 6, &lu, starts, 64),
+            BlockJacobiExecutor::create_with_storage(ctx, n, 6, &lu, starts, LuStorage::F32),
+        ];
+        for result in results {
+            match result {
+                Err(e) => assert!(
+                    e.contains(expected),
+                    "block-starts-test failed: {starts:?}: unexpected error {e}"
+                ),
+                Ok(_) => panic!("block-starts-test failed: {starts:?} was accepted"),
+            }
+        }
+    }
+
+    println!(
+        "BlockStartsTest OK: 3 partitions (short blocks included) accepted, {} malformed block_starts rejected by create / create_with_alignment / create_with_storage",
+        cases.len()
+    );
+}
+
+fn run_block_size_test(ctx: &GpuContext) {
+    // 23 x 23 grid (n = 529): bs = 3 leaves a 1-row final block, bs = 9 a 7-row one.
+    let a = laplacian_2d(23, 23);
+    let n = a.n_rows as usize;
+    let r: Vec<f32> = (0..n)
+        .map(|i| 1.0 + ((i * 37) % 11) as f32 * 0.25 - (i % 3) as f32)
+        .collect();
+    let r_gpu = ctx.create_storage_buffer("block-size-test r", &r, BufferUsages::empty());
+    let z_gpu =
+        ctx.create_storage_buffer_uninit::<f32>("block-size-test z", n, BufferUsages::empty());
+
+    let mut iterations = Vec::new();
+    for block_size in [3usize, 9] {
+        let block_starts = uniform_block_starts(n, block_size);
+        let lu_blocks = build_lu_blocks_from_csr_block_starts(
+            n,
+            block_size,
+            &a.row_ptr,
+            &a.col_idx,
+            &a.values,
+            &block_starts,
+        )
+        .unwrap_or_else(|e| panic!("block-size-test: bs={block_size}: {e}"));
+        let bj = BlockJacobiExecutor::create(
+            ctx,
+            n as u32,
+            block_size as u32,
+            &lu_blocks,
+            &block_starts,
+        )
+        .unwrap_or_else(|e| panic!("block-size-test: bs={block_size}: {e}"));
+        assert_eq!(bj.block_size() as usize, block_size);
+        assert_eq!(bj.lu_stride() as usize, block_size * block_size);
+
+        // Apply against the CPU reference.
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("block-size-test encoder"),
+            });
+        bj.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+        ctx.queue.submit(Some(encoder.finish()));
+        let z = executor::block_on(ctx.readback(&z_gpu));
+
+        let mut z_ref = vec![0.0f32; n];
+        reference::block_jacobi_apply(block_size, &lu_blocks, &block_starts, &r, &mut z_ref);
+        for (i, (got, exp)) in z.iter().zip(&z_ref).enumerate() {
+            assert!(
+                (got - exp).abs() <= 1e-5 * exp.abs().max(1.0),
+                "block-size-test failed: bs={block_size} z[{i}] = {got}, expected {exp}"
+            );
+        }
+
+        // Full PCG solve with this preconditioner.
+        let spmv_exec = SpmvExecutor::create(ctx, a.n_rows, &a.row_ptr, &a.col_idx, &a.values);
+        let vec_ops_exec = VecOpsExecutor::create(ctx);
+        let dot_scalar_exec = DotScalarExecutor::create(ctx, n, PCG_SCALAR_RESULTS_LEN);
+        let pcg_update_scalars_exec = PcgUpdateScalarsExecutor::create(ctx);
+        let mut x = vec![0.0f32; n];
+        let result = pcg_block_jacobi_csr_wgpu(
+            n,
+            &r,
+            &mut x,
+            1000,
+            1e-5,
+            0.0,
+            ctx,
+            &spmv_exec,
+            &vec_ops_exec,
+            &dot_scalar_exec,
+            &bj,
+            &pcg_update_scalars_exec,
+            &PcgOptions::default(),
+            None,
+        )
+        .unwrap_or_else(|e| panic!("block-size-test: bs={block_size} solve failed: {e}"));
+
+        let b_norm = r.iter().map(|v| v * v).sum::<f32>().sqrt();
+        assert!(
+            result.residual_norm <= 1e-5 * b_norm,
+            "block-size-test failed: bs={block_size} stopped at ||r|| = {} after {} iterations",
+            result.residual_norm,
+            result.iterations
+        );
+        iterations.push(result.iterations);
+    }
+    assert!(
+        iterations[1] <= iterations[0],
+        "block-size-test failed: 9-row blocks took {} iterations, 3-row blocks {}",
+        iterations[1],
+        iterations[0]
+    );
+
+    // Rejected: sizes outside 1..=MAX_BLOCK_SIZE and a buffer of the wrong length.
+    let block_starts = uniform_block_starts(n, 9);
+    let num_blocks = block_starts.len() - 1;
+    for block_size in [0u32, MAX_BLOCK_SIZE + 1] {
+        let lu_blocks = vec![0.0f32; num_blocks * (block_size * block_size) as usize];
+        match BlockJacobiExecutor::create(ctx, n as u32, block_size, &lu_blocks, &block_starts) {
+            Err(e) => assert!(
+                e.contains("block_size must be in"),
+                "block-size-test failed: bs={block_size}: unexpected error {e}"
+            ),
+            Ok(_) => panic!("block-size-test failed: bs={block_size} was accepted"),
+        }
+    }
+    let short = vec![0.0f32; num_blocks * 81 - 1];
+    match BlockJacobiExecutor::create(ctx, n as u32, 9, &short, &block_starts) {
+        Err(e) => assert!(
+            e.contains("lu_blocks has"),
+            "block-size-test failed: unexpected length error {e}"
+        ),
+        Ok(_) => panic!("block-size-test failed: short lu_blocks was accepted"),
+    }
+
+    println!(
+        "BlockSizeTest OK: n={n}, bs=3/9 match the CPU apply, PCG iterations {} / {}, bs=0/{} and a short buffer rejected",
+        iterations[0],
+        iterations[1],
+        MAX_BLOCK_SIZE + 1
+    );
+}
+
+fn run_pcg_update_scalars_test(ctx: &GpuContext) {
+    // Scalar buffer with a few slots.
+    let scalar_len = 8usize;
+
+    // Host init: we set only the inputs; outputs can start at 0.
+    let mut host = vec![0.0f32; scalar_len];
+
+    // pAp = 2, rz_old = 10, rz_new = 5
+    host[PAP as usize] = 2.0;
+    host[RZ_OLD as usize] = 10.0;
+    host[RZ_NEW as usize] = 5.0;
+
+    let scalar_gpu = ctx.create_storage_buffer(
+        "pcg_update_scalars_test scalar_results",
+        &host,
+        BufferUsages::COPY_SRC, // for readback
+    );
+
+    let exec = PcgUpdateScalarsExecutor::create(ctx);
+    exec.reset_params_cursor();
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("pcg_update_scalars_test encoder"),
+        });
+
+    exec.encode_update_scalars(
+        ctx,
+        &mut encoder,
+        &scalar_gpu.buffer,
+        PAP,
+        RZ_NEW,
+        RZ_OLD,
+        ALPHA,
+        MINUS_ALPHA,
+        BETA,
+    );
+
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let out = executor::block_on(ctx.readback(&scalar_gpu));
+
+    let alpha = out[ALPHA as usize];
+    let minus_alpha = out[MINUS_ALPHA as usize];
     let beta = out[BETA as usize];
 
-    let exp_alpha = 10.0 / 2.0; // 5
-    let exp_minus_alpha = -exp_alpha; // -5
-    let exp_beta = 5.0 / 10.0; // 0.5
+    let exp_alpha = 10.0 / 2.0; // 5
+    let exp_minus_alpha = -exp_alpha; // -5
+    let exp_beta = 5.0 / 10.0; // 0.5
+
+    let eps = 1e-6;
+    assert!(
+        (alpha - exp_alpha).abs() < eps,
+        "alpha got {alpha}, expected {exp_alpha}"
+    );
+    assert!(
+        (minus_alpha - exp_minus_alpha).abs() < eps,
+        "minus_alpha got {minus_alpha}, expected {exp_minus_alpha}"
+    );
+    assert!(
+        (beta - exp_beta).abs() < eps,
+        "beta got {beta}, expected {exp_beta}"
+    );
+
+    println!("PcgUpdateScalarsTest OK: alpha={alpha}, minus_alpha={minus_alpha}, beta={beta}");
+}
+
+fn write_x_bin(path: &str, x: &[f32]) -> Result<(), String> {
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::Path;
+
+    let p = Path::new(path);
+    if let Some(parent) = p.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("create_dir_all {}: {e}", parent.display()))?;
+    }
+
+    let mut f = File::create(p).map_err(|e| format!("create {}: {e}", p.display()))?;
+    let n = x.len() as u32;
+    f.write_all(&n.to_le_bytes()).map_err(|e| e.to_string())?;
+    for &v in x {
+        f.write_all(&v.to_le_bytes()).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn write_json(path: &str, json: &str) -> Result<(), String> {
+    let p = Path::new(path);
+    if let Some(parent) = p.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("create_dir_all {}: {e}", parent.display()))?;
+    }
+
+    fs::write(p, json).map_err(|e| format!("write {}: {e}", p.display()))
+}
+
+/// Write `trace` followed by `summary` as JSON Lines (see [`TraceLine`]).
+fn write_trace_jsonl(
+    path: &str,
+    trace: &[IterationTrace],
+    summary: TraceLine,
+) -> Result<(), String> {
+    let lines = trace
+        .iter()
+        .map(|t| TraceLine::Iteration {
+            iter: t.iteration,
+            residual: t.residual_norm,
+            alpha: t.alpha,
+            beta: t.beta,
+            elapsed_ms: t.elapsed_ms,
+        })
+        .chain(std::iter::once(summary));
+
+    let mut text = String::new();
+    for line in lines {
+        text.push_str(&serde_json::to_string(&line).map_err(|e| format!("trace line: {e}"))?);
+        text.push('\n');
+    }
+    write_json(path, &text)
+}
+
+/// Small SPD test system: 1D Laplacian-like tridiagonal [-1, 2.5, -1] in CSR.
+fn tridiagonal_test_matrix(n: usize) -> (Vec<u32>, Vec<u32>, Vec<f32>) {
+    let mut row_ptr = vec![0u32];
+    let mut col_idx = Vec::new();
+    let mut values = Vec::new();
+
+    for i in 0..n {
+        if i > 0 {
+            col_idx.push((i - 1) as u32);
+            values.push(-1.0);
+        }
+        col_idx.push(i as u32);
+        values.push(2.5);
+        if i + 1 < n {
+            col_idx.push((i + 1) as u32);
+            values.push(-1.0);
+        }
+        row_ptr.push(col_idx.len() as u32);
+    }
+
+    (row_ptr, col_idx, values)
+}
+
+/// Uniform block partition [0, bs, 2*bs, ..., n].
+fn uniform_block_starts(n: usize, block_size: usize) -> Vec<u32> {
+    let mut starts: Vec<u32> = (0..n).step_by(block_size).map(|s| s as u32).collect();
+    starts.push(n as u32);
+    starts
+}
+
+/// Build all executors for a CSR system and run the block-Jacobi PCG core loop from x0 = 0.
+#[allow(clippy::too_many_arguments)]
+fn solve_test_system(
+    ctx: &GpuContext,
+    row_ptr: &[u32],
+    col_idx: &[u32],
+    values: &[f32],
+    block_starts: &[u32],
+    b: &[f32],
+    max_iters: usize,
+    rel_tol: f32,
+    options: &PcgOptions,
+) -> Result<(PcgResult, Vec<f32>), String> {
+    let n = b.len();
+
+    let spmv_exec = SpmvExecutor::create(ctx, n as u32, row_ptr, col_idx, values);
+    let vec_ops_exec = VecOpsExecutor::create(ctx);
+    let mut dot_scalar_exec = DotScalarExecutor::create(ctx, n, PCG_SCALAR_RESULTS_LEN);
+    dot_scalar_exec.set_readback_buffering(ctx, options.readback_buffering);
+    dot_scalar_exec.set_dot_precision(ctx, options.dot_precision)?;
+    dot_scalar_exec.set_dot_subgroup_size(ctx, options.dot_subgroup_size)?;
+    let lu_blocks =
+        build_lu_blocks_from_csr_block_starts_6(n, row_ptr, col_idx, values, block_starts)?;
+    let block_jacobi_exec =
+        BlockJacobiExecutor::create(ctx, n as u32, 6, &lu_blocks, block_starts)?;
+    let pcg_update_scalars_exec = PcgUpdateScalarsExecutor::create(ctx);
+
+    let mut x = vec![0.0f32; n];
+    let result = pcg_block_jacobi_csr_wgpu(
+        n,
+        b,
+        &mut x,
+        max_iters,
+        rel_tol,
+        0.0,
+        ctx,
+        &spmv_exec,
+        &vec_ops_exec,
+        &dot_scalar_exec,
+        &block_jacobi_exec,
+        &pcg_update_scalars_exec,
+        options,
+        None,
+    )?;
+
+    Ok((result, x))
+}
+
+fn run_pcg_timing_test(ctx: &GpuContext) {
+    let n = 4096;
+    let (row_ptr, col_idx, values) = tridiagonal_test_matrix(n);
+    let block_starts = uniform_block_starts(n, 6);
+    let b = vec![1.0f32; n];
+
+    let options = PcgOptions {
+        timing: true,
+        ..Default::default()
+    };
+    let (result, _x) = solve_test_system(
+        ctx,
+        &row_ptr,
+        &col_idx,
+        &values,
+        &block_starts,
+        &b,
+        2000,
+        1e-5,
+        &options,
+    )
+    .unwrap_or_else(|e| panic!("pcg-timing-test: solve failed: {e}"));
+
+    let t = result
+        .timings
+        .unwrap_or_else(|| panic!("pcg-timing-test failed: timing requested, no timings"));
+    assert_eq!(
+        t.approximate,
+        !ctx.supports_timestamps(),
+        "pcg-timing-test failed: approximate flag"
+    );
+
+    let sum = t.sum_ms();
+    let total = t.total_ms;
+    assert!(
+        (sum - total).abs() <= 0.1 * total + 1.0,
+        "pcg-timing-test failed: buckets sum to {sum:.3} ms, total {total:.3} ms"
+    );
+
+    println!(
+        "PcgTimingTest OK: {} iterations, buckets {sum:.3} ms vs total {total:.3} ms{}",
+        result.iterations,
+        if t.approximate {
+            " (wall-clock only)"
+        } else {
+            ""
+        }
+    );
+}
+
+fn run_pcg_occupancy_test(ctx: &GpuContext) {
+    let n = 4096;
+    let (row_ptr, col_idx, values) = tridiagonal_test_matrix(n);
+    let block_starts = uniform_block_starts(n, 6);
+    let b = vec![1.0f32; n];
+    let solve = |compute_units| {
+        let options = PcgOptions {
+            timing: true,
+            compute_units,
+            ..Default::default()
+        };
+        let (result, _x) = solve_test_system(
+            ctx,
+            &row_ptr,
+            &col_idx,
+            &values,
+            &block_starts,
+            &b,
+            2000,
+            1e-5,
+            &options,
+        )
+        .unwrap_or_else(|e| panic!("pcg-occupancy-test: solve failed: {e}"));
+        result
+            .timings
+            .unwrap_or_else(|| panic!("pcg-occupancy-test failed: timing requested, no timings"))
+    };
+
+    // The launches follow the dispatch math for n = 4096: 256-wide SpMV / vector
+    // kernels, one workgroup per 6-row block, a dot tree of 16 partials -> 1.
+    let t = solve(Some(24));
+    let launches: Vec<(&str, u32, u32, u32)> = t
+        .kernel_launches
+        .iter()
+        .map(|k| (k.kernel, k.workgroup_size, k.workgroups, k.dispatches))
+        .collect();
+    assert_eq!(
+        launches,
+        vec![
+            ("spmv", 256, 16, 1),
+            ("block_jacobi", 1, 683, 1),
+            ("dot_partials", 256, 16, 3),
+            ("dot_reduce", 256, 1, 3),
+            ("vec_ops", 256, 16, 3),
+            ("pcg_update_scalars", 1, 1, 2),
+        ],
+        "pcg-occupancy-test failed: kernel launches"
+    );
+    assert_eq!(t.compute_units, Some(24));
+    let spmv = &t.kernel_launches[0];
+    assert_eq!(spmv.invocations(), n as u64);
+    assert!(spmv.launch_bound(24) && !t.kernel_launches[1].launch_bound(24));
+    assert!((spmv.workgroups_per_compute_unit(24) - 16.0 / 24.0).abs() < 1e-12);
+
+    // Without the override only CPU adapters get a count (their host threads).
+    let hint = solve(None).compute_units;
+    assert_eq!(hint, compute_unit_hint(ctx));
+    if ctx.adapter_info.device_type == DeviceType::Cpu {
+        assert!(hint.is_some_and(|cu| cu >= 1));
+    } else {
+        assert_eq!(hint, None);
+    }
+
+    println!(
+        "PcgOccupancyTest OK: {} kernels per iteration, compute-unit hint {hint:?}, spmv {} workgroups ({:.2} per unit of 24)",
+        t.kernel_launches.len(),
+        spmv.workgroups,
+        spmv.workgroups_per_compute_unit(24)
+    );
+}
+
+fn run_pcg_wall_clock_timing_test(ctx: &mut GpuContext) {
+    // Same solve with GPU timestamps and with the device's timestamp features masked
+    // off, as on hardware without TIMESTAMP_QUERY.
+    let a = laplacian_2d(48, 48);
+    let n = a.n_rows as usize;
+    let block_starts = uniform_block_starts(n, 6);
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 7) as f32 * 0.25).collect();
+    let options = PcgOptions {
+        timing: true,
+        ..Default::default()
+    };
+    let solve = |ctx: &GpuContext| {
+        solve_test_system(
+            ctx,
+            &a.row_ptr,
+            &a.col_idx,
+            &a.values,
+            &block_starts,
+            &b,
+            2000,
+            1e-5,
+            &options,
+        )
+        .unwrap_or_else(|e| panic!("pcg-wall-clock-timing-test: solve failed: {e}"))
+    };
+
+    let had_timestamps = ctx.supports_timestamps();
+    let (exact, x_exact) = solve(ctx);
+    ctx.features
+        .remove(Features::TIMESTAMP_QUERY | Features::TIMESTAMP_QUERY_INSIDE_ENCODERS);
+    assert!(!ctx.supports_timestamps());
+    let (approx, x_approx) = solve(ctx);
+
+    assert_eq!(approx.iterations, exact.iterations);
+    assert_eq!(x_approx, x_exact, "pcg-wall-clock-timing-test failed: x");
+    let t = approx
+        .timings
+        .expect("pcg-wall-clock-timing-test failed: no timings without timestamps");
+    assert!(t.approximate && t.timestamp_period_ns == 0.0);
+    assert_eq!(
+        (t.spmv_ms, t.preconditioner_ms, t.dots_ms, t.vec_ops_ms),
+        (0.0, 0.0, 0.0, 0.0),
+        "pcg-wall-clock-timing-test failed: GPU buckets without timestamps"
+    );
+    assert_eq!(
+        t.iteration_ms.len(),
+        approx.iterations,
+        "pcg-wall-clock-timing-test failed: one duration per iteration"
+    );
+    assert!(
+        t.iteration_ms.iter().all(|&ms| ms > 0.0),
+        "pcg-wall-clock-timing-test failed: non-positive iteration time"
+    );
+
+    // The iterations cover the solve apart from setup and the final x readback; the
+    // buckets still partition it.
+    let per_iteration: f64 = t.iteration_ms.iter().sum();
+    let total = t.total_ms;
+    assert!(
+        per_iteration <= total && per_iteration + t.setup_ms >= 0.8 * total,
+        "pcg-wall-clock-timing-test failed: iterations {per_iteration:.3} ms, setup {:.3} ms, total {total:.3} ms",
+        t.setup_ms
+    );
+    let sum = t.sum_ms();
+    assert!(
+        (sum - total).abs() <= 0.1 * total + 1.0,
+        "pcg-wall-clock-timing-test failed: buckets sum to {sum:.3} ms, total {total:.3} ms"
+    );
+    let t_exact = exact
+        .timings
+        .expect("pcg-wall-clock-timing-test failed: no timings with timestamps");
+    assert_eq!(t_exact.approximate, !had_timestamps);
+    assert_eq!(t_exact.iteration_ms.len(), exact.iterations);
+
+    println!(
+        "PcgWallClockTimingTest OK: {} iterations, {per_iteration:.3} ms of {total:.3} ms in iterations (mean {:.3} ms), approximate",
+        approx.iterations,
+        per_iteration / approx.iterations as f64
+    );
+}
+
+fn run_precision_floor_test(ctx: &GpuContext) {
+    // Floor for n = 10^6, ||b|| = 1: 1000 * eps ~ 1.2e-4.
+    let floor = f32_residual_floor(1_000_000, 1.0);
+    assert!((floor - 1000.0 * f32::EPSILON).abs() <= 1e-9);
+    assert!(tolerance_below_precision_floor(1_000_000, 1.0, 1e-10, 0.0));
+    assert!(!tolerance_below_precision_floor(1_000_000, 1.0, 1e-3, 0.0));
+    // A loose abs_tol rescues a too-tight rel_tol (the looser target stops the solve).
+    assert!(!tolerance_below_precision_floor(
+        1_000_000, 1.0, 1e-10, 1e-3
+    ));
+
+    // Identity system: block-Jacobi is exact, so r becomes exactly 0 in one step and
+    // even tol = 1e-10 "converges" -- but the request is still flagged.
+    let n = 4096;
+    let row_ptr: Vec<u32> = (0..=n as u32).collect();
+    let col_idx: Vec<u32> = (0..n as u32).collect();
+    let values = vec![1.0f32; n];
+    let block_starts = uniform_block_starts(n, 6);
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 3) as f32).collect();
+
+    let (tight, _x) = solve_test_system(
+        ctx,
+        &row_ptr,
+        &col_idx,
+        &values,
+        &block_starts,
+        &b,
+        10,
+        1e-10,
+        &PcgOptions::default(),
+    )
+    .unwrap_or_else(|e| panic!("precision-floor-test: identity solve failed: {e}"));
+    assert!(
+        tight.tolerance_below_precision_floor,
+        "precision-floor-test failed: tol 1e-10 not flagged"
+    );
+
+    let (loose, _x) = solve_test_system(
+        ctx,
+        &row_ptr,
+        &col_idx,
+        &values,
+        &block_starts,
+        &b,
+        10,
+        1e-4,
+        &PcgOptions::default(),
+    )
+    .unwrap_or_else(|e| panic!("precision-floor-test: identity solve failed: {e}"));
+    assert!(
+        !loose.tolerance_below_precision_floor,
+        "precision-floor-test failed: tol 1e-4 flagged"
+    );
+
+    println!("PrecisionFloorTest OK: floor {floor:e} for n=1e6, tight tol flagged");
+}
+
+// User-side custom metric: value[i] = x[i], summed -> sum(x). Same interface as
+// wgsl/max_abs_change.wgsl; `state` is unused.
+const SUM_X_METRIC_WGSL: &str = "
+struct Params { n: u32, _pad0: u32, _pad1: u32, _pad2: u32 };
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> x: array<f32>;
+@group(0) @binding(2) var<storage, read_write> state: array<f32>;
+@group(0) @binding(3) var<storage, read_write> value: array<f32>;
+
+@compute @workgroup_size(256)
+fn compute_main(@builtin(global_invocation_id) gi_id: vec3<u32>) {
+    let i = gi_id.x;
+    if (i >= params.n) {
+        return;
+    }
+    value[i] = x[i];
+}
+";
+
+fn run_custom_metric_test(ctx: &GpuContext) {
+    let Csr {
+        n_rows,
+        row_ptr,
+        col_idx,
+        values,
+        ..
+    } = laplacian_2d(48, 48);
+    let n = n_rows as usize;
+    let block_starts = uniform_block_starts(n, 6);
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 5) as f32).collect();
+    let b_norm = reference::dot(&b, &b).sqrt();
+    let rel_tol = 1e-5;
+    let solve = |options: &PcgOptions| {
+        solve_test_system(
+            ctx,
+            &row_ptr,
+            &col_idx,
+            &values,
+            &block_starts,
+            &b,
+            2000,
+            rel_tol,
+            options,
+        )
+    };
+
+    let (plain, _) = solve(&PcgOptions::default())
+        .unwrap_or_else(|e| panic!("custom-metric-test: solve failed: {e}"));
+
+    // 1) max |x_k - x_{k-1}| <= threshold stops the solve, at the first iteration it holds.
+    let threshold = 1e-2;
+    let dir = std::env::temp_dir().join(format!("wgpu_solver_custom_metric_{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let options = PcgOptions {
+        custom_stopping_metric: Some(CustomStoppingMetric::max_abs_change(threshold)),
+        snapshot_interval: Some(1),
+        snapshot_dir: dir.clone(),
+        ..Default::default()
+    };
+    let (result, _) =
+        solve(&options).unwrap_or_else(|e| panic!("custom-metric-test: solve failed: {e}"));
+    let history = result.custom_metric_history.as_ref().unwrap();
+    let k = result.iterations;
+
+    assert_eq!(
+        history.len(),
+        k,
+        "custom-metric-test failed: history length"
+    );
+    assert!(
+        history[k - 1] <= threshold && history[..k - 1].iter().all(|m| *m > threshold),
+        "custom-metric-test failed: stopped at {k} with history {history:?}"
+    );
+    assert!(
+        k < plain.iterations && result.residual_norm > rel_tol * b_norm,
+        "custom-metric-test failed: metric did not drive termination ({k} vs {} iterations)",
+        plain.iterations
+    );
+
+    // The GPU value matches max |x_k - x_{k-1}| from the snapshots.
+    let read = |iteration: usize| {
+        read_npy_f32(&dir.join(snapshot_file_name(iteration)))
+            .unwrap_or_else(|e| panic!("custom-metric-test: {e}"))
+            .1
+    };
+    let (x_prev, x_last) = (read(k - 1), read(k));
+    let max_change = x_prev
+        .iter()
+        .zip(&x_last)
+        .map(|(a, b)| (a - b).abs())
+        .fold(0.0f32, f32::max);
+    assert!(
+        (max_change - history[k - 1]).abs() <= 1e-6 * max_change.max(1.0),
+        "custom-metric-test failed: GPU metric {} vs host {max_change}",
+        history[k - 1]
+    );
+    let _ = fs::remove_dir_all(&dir);
+
+    // 2) A metric that never fires leaves the residual test in charge; sum reduction.
+    let options = PcgOptions {
+        custom_stopping_metric: Some(CustomStoppingMetric {
+            kernel: CustomKernel::new("sum_x", SUM_X_METRIC_WGSL),
+            reduction: ReduceOp::Sum,
+            threshold: f32::NEG_INFINITY,
+        }),
+        ..Default::default()
+    };
+    let (result, x) =
+        solve(&options).unwrap_or_else(|e| panic!("custom-metric-test: solve failed: {e}"));
+    let sum_x = result.custom_metric_history.as_ref().unwrap()[result.iterations - 1];
+    let host_sum: f32 = x.iter().sum();
+    assert_eq!(result.iterations, plain.iterations);
+    assert!(
+        (sum_x - host_sum).abs() <= 1e-4 * host_sum.abs(),
+        "custom-metric-test failed: sum(x) {sum_x} vs host {host_sum}"
+    );
+
+    // 2b) The same kernel under another entry point name.
+    let my_main_wgsl = SUM_X_METRIC_WGSL.replace("fn compute_main(", "fn my_main(");
+    let my_main = |kernel: CustomKernel| PcgOptions {
+        custom_stopping_metric: Some(CustomStoppingMetric {
+            kernel,
+            reduction: ReduceOp::Sum,
+            threshold: f32::NEG_INFINITY,
+        }),
+        ..Default::default()
+    };
+    let (result, _) = solve(&my_main(
+        CustomKernel::new("sum_x_my_main", my_main_wgsl.as_str()).with_entry_point("my_main"),
+    ))
+    .unwrap_or_else(|e| panic!("custom-metric-test: my_main solve failed: {e}"));
+    assert_eq!(
+        result.custom_metric_history.as_ref().unwrap()[result.iterations - 1],
+        sum_x,
+        "custom-metric-test failed: my_main entry point"
+    );
+    let err = solve(&my_main(CustomKernel::new(
+        "no_compute_main",
+        my_main_wgsl.as_str(),
+    )))
+    .expect_err("custom-metric-test failed: missing compute_main accepted");
+    assert!(
+        err.contains("custom kernel no_compute_main"),
+        "custom-metric-test failed: unexpected error {err}"
+    );
+
+    // 3) A broken kernel is an error, not a device panic.
+    let options = PcgOptions {
+        custom_stopping_metric: Some(CustomStoppingMetric {
+            kernel: CustomKernel::new("broken", "fn compute_main( {"),
+            reduction: ReduceOp::Max,
+            threshold: 0.0,
+        }),
+        ..Default::default()
+    };
+    let err = solve(&options).expect_err("custom-metric-test failed: broken kernel accepted");
+    assert!(
+        err.contains("custom kernel broken"),
+        "custom-metric-test failed: unexpected error {err}"
+    );
+
+    println!(
+        "CustomMetricTest OK: max|dx| <= {threshold:e} stopped at {k} iterations \
+         (residual test alone: {}), GPU sum(x) matches host, my_main entry point runs",
+        plain.iterations
+    );
+}
+
+fn run_capture_marker_test(ctx: &GpuContext) {
+    let n = 2048;
+    let (row_ptr, col_idx, values) = tridiagonal_test_matrix(n);
+    let block_starts = uniform_block_starts(n, 6);
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 5) as f32).collect();
+    // With the pass timer on, every pass begun through the context (the marker's
+    // included) is recorded: the timed labels plus the overflow count.
+    let timed = ctx.enable_pass_timing(2048);
+    let solve = |capture_at_iteration: Option<u32>| {
+        let options = PcgOptions {
+            capture_at_iteration,
+            ..Default::default()
+        };
+        if let Some(timer) = ctx.pass_timer() {
+            timer.reset();
+        }
+        let solved = solve_test_system(
+            ctx,
+            &row_ptr,
+            &col_idx,
+            &values,
+            &block_starts,
+            &b,
+            2000,
+            1e-5,
+            &options,
+        )?;
+        let passes = ctx.pass_timer().map(|timer| {
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("capture-marker-test resolve"),
+                });
+            timer.encode_resolve(&mut encoder);
+            ctx.queue.submit(Some(encoder.finish()));
+            let dropped = timer.dropped();
+            let labels: Vec<String> = executor::block_on(timer.read_timings(ctx))
+                .unwrap_or_else(|e| panic!("capture-marker-test: read_timings failed: {e}"))
+                .into_iter()
+                .map(|t| t.label)
+                .collect();
+            (labels, dropped)
+        });
+        Ok::<_, String>((solved, passes))
+    };
+
+    let ((plain, x_plain), plain_passes) =
+        solve(None).unwrap_or_else(|e| panic!("capture-marker-test: solve failed: {e}"));
+    assert_eq!(plain.capture_marker_iteration, None);
+    let k = plain.iterations;
+    assert!(
+        k >= 3,
+        "capture-marker-test: needs a few iterations, got {k}"
+    );
+
+    // The marker lands in exactly the requested iteration and changes nothing else.
+    for target in [1, k / 2, k] {
+        let ((result, x), passes) = solve(Some(target as u32))
+            .unwrap_or_else(|e| panic!("capture-marker-test: solve failed: {e}"));
+        assert_eq!(
+            result.capture_marker_iteration,
+            Some(target),
+            "capture-marker-test failed: marker for iteration {target}"
+        );
+        if let (Some((labels, dropped)), Some((plain_labels, plain_dropped))) =
+            (&passes, &plain_passes)
+        {
+            // Exactly one extra pass, and (while it fits in the timer) it is the marker.
+            assert_eq!(
+                labels.len() as u32 + dropped,
+                plain_labels.len() as u32 + plain_dropped + 1,
+                "capture-marker-test failed: marker at {target} is not one extra pass"
+            );
+            let marker = ctx.label(&capture_marker_label(target));
+            let found = labels.iter().filter(|l| **l == marker).count();
+            assert!(
+                found == 1 || (found == 0 && *dropped > 0),
+                "capture-marker-test failed: {found} passes labeled \"{marker}\""
+            );
+            if target == 1 {
+                assert_eq!(
+                    found, 1,
+                    "capture-marker-test failed: marker pass at iteration 1 not recorded"
+                );
+            }
+        }
+        assert!(
+            result.iterations == k
+                && result.dispatches == plain.dispatches
+                && x.iter()
+                    .zip(&x_plain)
+                    .all(|(a, b)| a.to_bits() == b.to_bits()),
+            "capture-marker-test failed: marker at {target} changed the solve"
+        );
+    }
+
+    // Past the last iteration: never emitted. Iteration 0 does not exist.
+    let ((result, _), _) =
+        solve(Some(k as u32 + 5)).unwrap_or_else(|e| panic!("capture-marker-test: {e}"));
+    assert_eq!(result.capture_marker_iteration, None);
+    assert!(
+        solve(Some(0)).is_err(),
+        "capture-marker-test failed: iteration 0 accepted"
+    );
+    ctx.disable_pass_timing();
+
+    println!(
+        "CaptureMarkerTest OK: \"{}\" emitted once, solve unchanged ({k} iterations){}",
+        capture_marker_label(k / 2),
+        if timed {
+            ", marker pass seen by the pass timer"
+        } else {
+            " (no TIMESTAMP_QUERY: marker pass not checked)"
+        }
+    );
+}
+
+fn run_snapshot_test(ctx: &GpuContext) {
+    let n = 2048;
+    let (row_ptr, col_idx, values) = tridiagonal_test_matrix(n);
+    let block_starts = uniform_block_starts(n, 6);
+    let b = vec![1.0f32; n];
+
+    let dir = std::env::temp_dir().join(format!("wgpu_solver_snapshot_test_{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    let interval = 2u32;
+    let options = PcgOptions {
+        snapshot_interval: Some(interval),
+        snapshot_dir: dir.clone(),
+        ..Default::default()
+    };
+    let (result, x) = solve_test_system(
+        ctx,
+        &row_ptr,
+        &col_idx,
+        &values,
+        &block_starts,
+        &b,
+        2000,
+        1e-5,
+        &options,
+    )
+    .unwrap_or_else(|e| panic!("snapshot-test: solve failed: {e}"));
+
+    let iterations = result.iterations;
+    let mut expected = iterations / interval as usize;
+    if iterations % interval as usize != 0 {
+        expected += 1;
+    }
+
+    let mut files: Vec<_> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("snapshot-test: read_dir {}: {e}", dir.display()))
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    files.sort();
+    assert_eq!(
+        files.len(),
+        expected,
+        "snapshot-test failed: {} iterations, interval {interval}: expected {expected} files, got {}",
+        iterations,
+        files.len()
+    );
+
+    let last = dir.join(snapshot_file_name(iterations));
+    assert_eq!(
+        files.last(),
+        Some(&last),
+        "snapshot-test failed: no final snapshot"
+    );
+
+    let (shape, x_snapshot) = read_npy_f32(&last).unwrap_or_else(|e| panic!("snapshot-test: {e}"));
+    assert_eq!(shape, vec![n], "snapshot-test failed: shape {shape:?}");
+    assert!(
+        x_snapshot
+            .iter()
+            .zip(&x)
+            .all(|(a, b)| a.to_bits() == b.to_bits()),
+        "snapshot-test failed: final snapshot differs from returned x"
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+
+    println!(
+        "SnapshotTest OK: {iterations} iterations, {} snapshots",
+        files.len()
+    );
+}
+
+fn run_cpu_fallback_test(ctx: &GpuContext) {
+    let n = 3000;
+    let (row_ptr, col_idx, values) = tridiagonal_test_matrix(n);
+    let block_starts = uniform_block_starts(n, 6);
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 7) as f32 * 0.25).collect();
+    let a = Csr {
+        n_rows: n as u32,
+        n_cols: n as u32,
+        nnz: values.len() as u32,
+        row_ptr: row_ptr.clone(),
+        col_idx: col_idx.clone(),
+        values: values.clone(),
+    };
+
+    let (gpu_result, x_gpu) = solve_test_system(
+        ctx,
+        &row_ptr,
+        &col_idx,
+        &values,
+        &block_starts,
+        &b,
+        2000,
+        1e-6,
+        &PcgOptions::default(),
+    )
+    .unwrap_or_else(|e| panic!("cpu-fallback-test: GPU solve failed: {e}"));
+
+    let cpu = executor::block_on(SolverDevice::create(GpuBackend::Cpu, None, false))
+        .unwrap_or_else(|e| panic!("cpu-fallback-test: {e}"));
+    assert!(
+        cpu.is_cpu(),
+        "cpu-fallback-test failed: backend cpu gave a GPU device"
+    );
+
+    let mut x_cpu = vec![0.0f32; n];
+    let cpu_result = cpu
+        .pcg_block_jacobi_csr(
+            &a,
+            &block_starts,
+            &b,
+            &mut x_cpu,
+            &SolveConfig::default().max_iters(2000).tol(1e-6),
+        )
+        .unwrap_or_else(|e| panic!("cpu-fallback-test: CPU solve failed: {e}"));
+
+    let stats = compare_x_vectors(&x_gpu, &x_cpu, 1);
+    assert!(
+        stats.rel_l2 <= 1e-4,
+        "cpu-fallback-test failed: rel L2 diff {:.3e} (GPU {} iters, CPU {} iters)",
+        stats.rel_l2,
+        gpu_result.iterations,
+        cpu_result.iterations
+    );
+    assert!(
+        gpu_result.iterations.abs_diff(cpu_result.iterations) <= 1,
+        "cpu-fallback-test failed: GPU {} iters vs CPU {} iters",
+        gpu_result.iterations,
+        cpu_result.iterations
+    );
+
+    println!(
+        "CpuFallbackTest OK: GPU {} iters, CPU {} iters, rel L2 diff {:.3e}",
+        gpu_result.iterations, cpu_result.iterations, stats.rel_l2
+    );
+}
+
+fn run_multi_rhs_test(device: &SolverDevice) {
+    let n = 1500;
+    let k = 3;
+    let (row_ptr, col_idx, values) = tridiagonal_test_matrix(n);
+    let block_starts = uniform_block_starts(n, 6);
+    let a = Csr {
+        n_rows: n as u32,
+        n_cols: n as u32,
+        nnz: values.len() as u32,
+        row_ptr,
+        col_idx,
+        values,
+    };
+
+    // Column j of the (n, k) block: varies with j so the solves differ.
+    let columns: Vec<Vec<f32>> = (0..k)
+        .map(|j| {
+            (0..n)
+                .map(|i| 1.0 + ((i * (j + 1)) % 5) as f32 * 0.5)
+                .collect()
+        })
+        .collect();
+
+    let path = std::env::temp_dir().join(format!("wgpu_solver_multi_rhs_{}.npy", process::id()));
+    write_npy_f32_columns(&path, &columns).unwrap_or_else(|e| panic!("multi-rhs-test: {e}"));
+
+    let (shape, _) = read_npy_f32(&path).unwrap_or_else(|e| panic!("multi-rhs-test: {e}"));
+    assert_eq!(shape, vec![n, k], "multi-rhs-test failed: shape {shape:?}");
+
+    // The data is '<f4' in C order on any host.
+    let bytes = fs::read(&path).unwrap_or_else(|e| panic!("multi-rhs-test: {e}"));
+    let le: Vec<u8> = (0..n)
+        .flat_map(|i| columns.iter().map(move |c| c[i]))
+        .flat_map(f32::to_le_bytes)
+        .collect();
+    assert!(
+        bytes.ends_with(&le),
+        "multi-rhs-test failed: data is not little-endian C order"
+    );
+
+    let rhs = read_npy_f32_columns(&path, n).unwrap_or_else(|e| panic!("multi-rhs-test: {e}"));
+    assert_eq!(
+        rhs, columns,
+        "multi-rhs-test failed: columns differ after round trip"
+    );
+
+    let err = read_npy_f32_columns(&path, n + 1).expect_err("multi-rhs-test: row count");
+    assert!(err.contains("rows"), "multi-rhs-test failed: {err}");
+
+    let _ = fs::remove_file(&path);
+
+    let solved = device
+        .pcg_block_jacobi_csr_multi_rhs(
+            &a,
+            &block_starts,
+            &rhs,
+            &SolveConfig::default().max_iters(2000).tol(1e-4),
+        )
+        .unwrap_or_else(|e| panic!("multi-rhs-test: solve failed: {e}"));
+    assert_eq!(solved.len(), k);
+
+    for (j, ((result, x), b)) in solved.iter().zip(&rhs).enumerate() {
+        // Same answer as a standalone solve of that column.
+        let mut x_single = vec![0.0f32; n];
+        device
+            .pcg_block_jacobi_csr(
+                &a,
+                &block_starts,
+                b,
+                &mut x_single,
+                &SolveConfig::default().max_iters(2000).tol(1e-4),
+            )
+            .unwrap_or_else(|e| panic!("multi-rhs-test: single solve {j} failed: {e}"));
+        assert!(
+            x.iter()
+                .zip(&x_single)
+                .all(|(p, q)| p.to_bits() == q.to_bits()),
+            "multi-rhs-test failed: column {j} differs from the single-RHS solve"
+        );
+
+        // And it actually solves A x = b.
+        let mut ax = vec![0.0f32; n];
+        reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, x, &mut ax);
+        let r: Vec<f32> = b.iter().zip(&ax).map(|(b_i, ax_i)| b_i - ax_i).collect();
+        let rel = (reference::dot(&r, &r) / reference::dot(b, b)).sqrt();
+        assert!(
+            rel <= 1e-3,
+            "multi-rhs-test failed: column {j}: relative residual {rel:e} ({} iters)",
+            result.iterations
+        );
+    }
+
+    println!(
+        "MultiRhsTest OK: {k} columns from a ({n}, {k}) .npy, iterations {:?}",
+        solved.iter().map(|(r, _)| r.iterations).collect::<Vec<_>>()
+    );
+}
+
+fn run_device_lost_test(device: &mut SolverDevice) {
+    if device.is_cpu() {
+        println!("DeviceLostTest SKIPPED: the CPU backend has no device to lose");
+        return;
+    }
+
+    // Simulates a driver reset: destroys the device once, after iteration `at` was read
+    // back, and polls so the device-lost callback has run before the next iteration.
+    struct LoseDeviceAt {
+        device: wgpu::Device,
+        at: u32,
+        fired: bool,
+    }
+    impl SolverObserver for LoseDeviceAt {
+        fn on_iteration(&mut self, iter: u32, _residual_norm: f32) {
+            if iter == self.at && !self.fired {
+                self.fired = true;
+                self.device.destroy();
+                let _ = self.device.poll(wgpu::PollType::Poll);
+            }
+        }
+    }
+    let lose_device = |device: &SolverDevice| LoseDeviceAt {
+        device: device.gpu().expect("GPU device").device.clone(),
+        at: 3,
+        fired: false,
+    };
+
+    let n = 1500;
+    let (row_ptr, col_idx, values) = tridiagonal_test_matrix(n);
+    let block_starts = uniform_block_starts(n, 6);
+    let a = Csr {
+        n_rows: n as u32,
+        n_cols: n as u32,
+        nnz: values.len() as u32,
+        row_ptr,
+        col_idx,
+        values,
+    };
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 5) as f32 * 0.5).collect();
+
+    let snapshot_dir =
+        std::env::temp_dir().join(format!("wgpu_solver_device_lost_{}", process::id()));
+    // Checkpoint every 2 iterations, lose the device while running iteration 4.
+    let options = PcgOptions {
+        snapshot_interval: Some(2),
+        snapshot_dir: snapshot_dir.clone(),
+        ..Default::default()
+    };
+
+    // 1) No retries: the loss is reported and x holds the iteration-2 checkpoint.
+    let mut x = vec![0.0f32; n];
+    let err = device
+        .pcg_block_jacobi_csr_with_retry(
+            &a,
+            &block_starts,
+            &b,
+            &mut x,
+            &SolveConfig::default()
+                .max_iters(2000)
+                .tol(1e-4)
+                .options(options.clone()),
+            0,
+            Some(&mut lose_device(device)),
+        )
+        .expect_err("device-lost-test: solve should fail without retries");
+    assert!(
+        err.contains("device lost"),
+        "device-lost-test failed: unexpected error {err}"
+    );
+    assert!(
+        device.is_lost(),
+        "device-lost-test failed: device not marked lost"
+    );
+
+    let (_, checkpoint) = read_npy_f32(&snapshot_dir.join(snapshot_file_name(2)))
+        .unwrap_or_else(|e| panic!("device-lost-test: {e}"));
+    assert!(
+        x.iter()
+            .zip(&checkpoint)
+            .all(|(p, q)| p.to_bits() == q.to_bits()),
+        "device-lost-test failed: x is not the last checkpoint"
+    );
+
+    // 2) One retry: the context is recreated and the solve completes.
+    executor::block_on(device.recreate())
+        .unwrap_or_else(|e| panic!("device-lost-test: recreate failed: {e}"));
+    assert!(
+        !device.is_lost(),
+        "device-lost-test failed: fresh context is lost"
+    );
+
+    let mut x = vec![0.0f32; n];
+    let result = device
+        .pcg_block_jacobi_csr_with_retry(
+            &a,
+            &block_starts,
+            &b,
+            &mut x,
+            &SolveConfig::default()
+                .max_iters(2000)
+                .tol(1e-4)
+                .options(options.clone()),
+            1,
+            Some(&mut lose_device(device)),
+        )
+        .unwrap_or_else(|e| panic!("device-lost-test: retried solve failed: {e}"));
+    assert!(
+        !device.is_lost(),
+        "device-lost-test failed: device lost after retry"
+    );
+
+    let mut ax = vec![0.0f32; n];
+    reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, &x, &mut ax);
+    let r: Vec<f32> = b.iter().zip(&ax).map(|(b_i, ax_i)| b_i - ax_i).collect();
+    let rel = (reference::dot(&r, &r) / reference::dot(&b, &b)).sqrt();
+    assert!(
+        rel <= 1e-3,
+        "device-lost-test failed: relative residual {rel:e} after retry"
+    );
+
+    let _ = fs::remove_dir_all(&snapshot_dir);
+
+    println!(
+        "DeviceLostTest OK: lost at iteration 4, restarted from the iteration-2 checkpoint, \
+         converged in {} more iterations (rel residual {rel:e})",
+        result.iterations
+    );
+}
+
+fn run_exact_error_test(device: &SolverDevice) {
+    // 2D Poisson: enough iterations for the A-norm history to say something.
+    let a = laplacian_2d(40, 40);
+    let n = a.n_rows as usize;
+    let block_starts = uniform_block_starts(n, 6);
+
+    // Manufactured solution: pick x_exact, set b = A x_exact.
+    let x_exact: Vec<f32> = (0..n).map(|i| (i as f32 * 0.01).sin() + 1.0).collect();
+    let mut b = vec![0.0f32; n];
+    reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, &x_exact, &mut b);
+
+    let mut x = vec![0.0f32; n];
+    let result = device
+        .pcg_block_jacobi_csr_with_exact(
+            &a,
+            &block_starts,
+            &b,
+            &x_exact,
+            &mut x,
+            &SolveConfig::default().max_iters(2000).tol(1e-5),
+        )
+        .unwrap_or_else(|e| panic!("exact-error-test: solve failed: {e}"));
+
+    let history = result
+        .error_a_norm_history
+        .as_ref()
+        .expect("exact-error-test: no error history");
+    assert_eq!(history.len(), result.iterations);
+    assert_eq!(result.residual_history.len(), result.iterations);
+
+    // CG minimizes the A-norm error over a growing Krylov space: non-increasing
+    // (small slack for f32 rounding once the error is tiny).
+    let initial =
+        reference::error_a_norm(&a.row_ptr, &a.col_idx, &a.values, &vec![0.0; n], &x_exact);
+    let mut prev = initial;
+    for (k, e) in history.iter().enumerate() {
+        assert!(
+            *e <= prev * (1.0 + 1e-3) + 1e-6 * initial,
+            "exact-error-test failed: error grew at iteration {}: {prev:e} -> {e:e}",
+            k + 1
+        );
+        prev = *e;
+    }
+
+    let final_error = reference::error_a_norm(&a.row_ptr, &a.col_idx, &a.values, &x, &x_exact);
+    let last = *history.last().unwrap();
+    assert!(
+        (last - final_error).abs() <= 1e-2 * final_error.max(1e-6 * initial),
+        "exact-error-test failed: last recorded error {last:e} vs recomputed {final_error:e}"
+    );
+    assert!(
+        last < 1e-2 * initial,
+        "exact-error-test failed: error only went from {initial:e} to {last:e}"
+    );
+
+    println!(
+        "ExactErrorTest OK: {} iterations, ||e||_A {initial:e} -> {last:e}, non-increasing",
+        result.iterations
+    );
+}
+
+/// Dense SPD matrix H D H with Strakos' spectrum
+/// lambda_i = l1 + (i / (n-1)) (ln - l1) rho^(n-1-i) (clustered at the low end, isolated
+/// large eigenvalues), rotated by the Householder reflector H so Jacobi cannot undo it.
+/// The classic example of CG losing conjugacy in finite precision.
+fn strakos_test_matrix(n: usize, rho: f64) -> Csr {
+    let (l1, ln) = (0.1f64, 100.0f64);
+    let lambda: Vec<f64> = (0..n)
+        .map(|i| l1 + (i as f64) / ((n - 1) as f64) * (ln - l1) * rho.powi((n - 1 - i) as i32))
+        .collect();
+
+    // H = I - 2 v v^T / v^T v
+    let v: Vec<f64> = (0..n).map(|i| 1.0 + ((i * 37) % 11) as f64).collect();
+    let vv: f64 = v.iter().map(|a| a * a).sum();
+    let h = |i: usize, j: usize| f64::from(u8::from(i == j)) - 2.0 * v[i] * v[j] / vv;
+
+    let mut row_ptr = vec![0u32];
+    let mut col_idx = Vec::with_capacity(n * n);
+    let mut values = Vec::with_capacity(n * n);
+    for i in 0..n {
+        for j in 0..n {
+            let a_ij: f64 = (0..n).map(|k| h(i, k) * lambda[k] * h(k, j)).sum();
+            col_idx.push(j as u32);
+            values.push(a_ij as f32);
+        }
+        row_ptr.push(col_idx.len() as u32);
+    }
+
+    Csr {
+        n_rows: n as u32,
+        n_cols: n as u32,
+        nnz: values.len() as u32,
+        row_ptr,
+        col_idx,
+        values,
+    }
+}
+
+fn run_trace_file_test(device: &SolverDevice) {
+    let a = laplacian_2d(16, 16);
+    let n = a.n_rows as usize;
+    let b = vec![1.0f32; n];
+    let mut x = vec![0.0f32; n];
+    let options = PcgOptions {
+        trace_iterations: true,
+        ..Default::default()
+    };
+
+    let result = device
+        .pcg_block_jacobi_csr(
+            &a,
+            &uniform_block_starts(n, 1),
+            &b,
+            &mut x,
+            &SolveConfig::default()
+                .max_iters(500)
+                .tol(1e-5)
+                .options(options.clone()),
+        )
+        .unwrap_or_else(|e| panic!("trace-file-test: solve failed: {e}"));
+    let trace = result
+        .iteration_trace
+        .as_deref()
+        .expect("trace-file-test: no iteration trace despite trace_iterations");
+    assert_eq!(trace.len(), result.iterations);
+
+    let path = std::env::temp_dir().join(format!("wgpu_solver_trace_{}.jsonl", process::id()));
+    let summary = TraceLine::Summary {
+        run_id: now_utc_rfc3339(),
+        case_dir: "laplacian_2d(16, 16)".to_string(),
+        n: n as u32,
+        converged: true,
+        iterations: Some(result.iterations),
+        final_residual_norm: Some(result.residual_norm),
+        error: None,
+        solve_ms: 0.0,
+    };
+    write_trace_jsonl(path.to_str().unwrap(), trace, summary)
+        .unwrap_or_else(|e| panic!("trace-file-test: {e}"));
+
+    let text = fs::read_to_string(&path).unwrap();
+    let _ = fs::remove_file(&path);
+    let lines: Vec<serde_json::Value> = text
+        .lines()
+        .map(|line| {
+            serde_json::from_str(line)
+                .unwrap_or_else(|e| panic!("trace-file-test: line does not parse ({e}): {line}"))
+        })
+        .collect();
+
+    assert_eq!(
+        lines.len(),
+        result.iterations + 1,
+        "trace-file-test failed: expected iterations + 1 lines"
+    );
+    for (i, line) in lines[..result.iterations].iter().enumerate() {
+        assert_eq!(line["type"], "iteration");
+        assert_eq!(line["iter"], i + 1);
+        assert_eq!(
+            line["residual"].as_f64().unwrap() as f32,
+            trace[i].residual_norm
+        );
+        assert!(line["alpha"].is_number() && line["elapsed_ms"].is_number());
+        // beta drives the next direction: present on every line but the last
+        assert_eq!(line["beta"].is_null(), i + 1 == result.iterations);
+    }
+    let summary = &lines[result.iterations];
+    assert_eq!(summary["type"], "summary");
+    assert_eq!(summary["iterations"], result.iterations);
+    assert_eq!(summary["converged"], true);
+
+    println!(
+        "TraceFileTest OK ({}): {} iteration lines + summary",
+        device.describe(),
+        result.iterations
+    );
+}
+
+fn run_gmres_offload_test(ctx: &GpuContext) {
+    // Nonsymmetric tridiagonal [-1.3, 2.05, -0.7] (convection-diffusion-like, weakly
+    // diagonally dominant), so GMRES(10) needs several restarts.
+    let n: usize = 2000;
+    let mut row_ptr = vec![0u32];
+    let mut col_idx = Vec::new();
+    let mut values = Vec::new();
+    for i in 0..n {
+        for (j, v) in [(i.wrapping_sub(1), -1.3f32), (i, 2.05), (i + 1, -0.7)] {
+            if j < n {
+                col_idx.push(j as u32);
+                values.push(v);
+            }
+        }
+        row_ptr.push(col_idx.len() as u32);
+    }
+    let block_starts = uniform_block_starts(n, 6);
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 7) as f32 * 0.5).collect();
+    let rel_tol = 1e-5;
+
+    let spmv_exec = SpmvExecutor::create(ctx, n as u32, &row_ptr, &col_idx, &values);
+    let vec_ops_exec = VecOpsExecutor::create(ctx);
+    let dot_scalar_exec = DotScalarExecutor::create(ctx, n, 1);
+    let lu_blocks =
+        build_lu_blocks_from_csr_block_starts_6(n, &row_ptr, &col_idx, &values, &block_starts)
+            .unwrap();
+    let block_jacobi_exec =
+        BlockJacobiExecutor::create(ctx, n as u32, 6, &lu_blocks, &block_starts)
+            .unwrap_or_else(|e| panic!("gmres-offload-test: {e}"));
+
+    let solve = |offload_basis: bool| {
+        let options = GmresOptions {
+            restart: 10,
+            offload_basis,
+        };
+        let mut x = vec![0.0f32; n];
+        let result = gmres_block_jacobi_csr_wgpu(
+            n,
+            &b,
+            &mut x,
+            &SolveConfig::default().max_iters(2000).tol(rel_tol),
+            ctx,
+            &spmv_exec,
+            &vec_ops_exec,
+            &dot_scalar_exec,
+            &block_jacobi_exec,
+            &options,
+            None,
+        )
+        .unwrap_or_else(|e| panic!("gmres-offload-test: offload_basis={offload_basis}: {e}"));
+        (result, x)
+    };
+
+    let (on_gpu, x_gpu) = solve(false);
+    let (offloaded, x_offloaded) = solve(true);
+
+    assert!(
+        on_gpu.cycles > 1,
+        "gmres-offload-test: problem too easy, converged in {} cycle(s)",
+        on_gpu.cycles
+    );
+    assert_eq!(on_gpu.iterations, offloaded.iterations);
+    assert!(
+        x_gpu
+            .iter()
+            .zip(&x_offloaded)
+            .all(|(a, b)| a.to_bits() == b.to_bits()),
+        "gmres-offload-test failed: offloaded solution differs from the in-GPU one"
+    );
+    assert_eq!(
+        (on_gpu.gpu_basis_vectors, on_gpu.host_transfer_bytes),
+        (11, 0)
+    );
+    assert!(offloaded.gpu_basis_vectors == 2 && offloaded.host_transfer_bytes > 0);
+
+    // an actual solution of A x = b
+    let mut ax = vec![0.0f32; n];
+    reference::spmv_csr(&row_ptr, &col_idx, &values, &x_gpu, &mut ax);
+    let r: Vec<f32> = b.iter().zip(&ax).map(|(b, ax)| b - ax).collect();
+    let true_rel = (reference::dot(&r, &r) / reference::dot(&b, &b)).sqrt();
+    assert!(
+        true_rel <= 2.0 * rel_tol,
+        "gmres-offload-test failed: true relative residual {true_rel:e}"
+    );
+
+    println!(
+        "GmresOffloadTest OK: {} iterations in {} cycles, identical x; GPU basis vectors {} -> {}, {:.1} MiB streamed",
+        on_gpu.iterations,
+        on_gpu.cycles,
+        on_gpu.gpu_basis_vectors,
+        offloaded.gpu_basis_vectors,
+        offloaded.host_transfer_bytes as f64 / (1024.0 * 1024.0)
+    );
+}
+
+fn run_diff_test() {
+    // d = a - b = [0, -0.5, 0, 3]: max at i=3, ||d|| = sqrt(9.25), ||a|| = sqrt(30)
+    let a = [1.0f32, 2.0, 3.0, 4.0];
+    let b = [1.0f32, 2.5, 3.0, 1.0];
+    let c = compare(&a, &b);
+    let close = |got: f64, expected: f64| (got - expected).abs() <= 1e-12 * expected.abs();
+    assert_eq!(c.n, 4);
+    assert_eq!(c.max_abs_diff, 3.0);
+    assert_eq!(c.max_abs_diff_index, Some(3));
+    assert!(close(c.l2_diff, 9.25f64.sqrt()), "diff-test failed: {c:?}");
+    assert!(
+        close(c.rel_l2_diff, (9.25f64 / 30.0).sqrt()),
+        "diff-test failed: {c:?}"
+    );
+
+    // identical vectors: all zero, no index
+    let same = compare(&a, &a);
+    assert_eq!(
+        (same.max_abs_diff, same.l2_diff, same.rel_l2_diff),
+        (0.0, 0.0, 0.0)
+    );
+    assert_eq!(same.max_abs_diff_index, None);
+
+    // a NaN is the largest disagreement
+    let nan = compare(&a, &[1.0, f32::NAN, 3.0, 100.0]);
+    assert_eq!(nan.max_abs_diff_index, Some(1));
+    assert!(nan.l2_diff.is_nan());
+
+    // the subcommand reads .npy files and reports the same statistics
+    let dir = std::env::temp_dir().join(format!("wgpu_solver_diff_{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let (a_path, b_path) = (dir.join("a.npy"), dir.join("b.npy"));
+    write_npy_f32(&a_path, &a).unwrap();
+    write_npy_f32(&b_path, &b).unwrap();
+    let from_files = run_diff(a_path.to_str().unwrap(), b_path.to_str().unwrap());
+    write_npy_f32(&b_path, &b[..3]).unwrap();
+    let mismatch = run_diff(a_path.to_str().unwrap(), b_path.to_str().unwrap());
+    let _ = fs::remove_dir_all(&dir);
+
+    assert_eq!(from_files, Ok(c));
+    assert!(
+        mismatch.is_err(),
+        "diff-test failed: length mismatch accepted"
+    );
+
+    println!(
+        "DiffTest OK: max |a-b| {} at i=3, ||a-b|| {:.6}, rel {:.6}",
+        c.max_abs_diff, c.l2_diff, c.rel_l2_diff
+    );
+}
+
+/// Nonsymmetric banded test matrix: A[i][j] for |i - j| <= bandwidth, diagonally dominant.
+fn banded_test_matrix(n: usize, bandwidth: usize) -> Csr {
+    let mut row_ptr = vec![0u32];
+    let mut col_idx = Vec::new();
+    let mut values = Vec::new();
+    for i in 0..n {
+        for j in i.saturating_sub(bandwidth)..(i + bandwidth + 1).min(n) {
+            col_idx.push(j as u32);
+            values.push(if i == j {
+                4.0 + (i % 3) as f32
+            } else {
+                -0.3 - 0.1 * ((i + 2 * j) % 5) as f32
+            });
+        }
+        row_ptr.push(col_idx.len() as u32);
+    }
+    Csr {
+        n_rows: n as u32,
+        n_cols: n as u32,
+        nnz: col_idx.len() as u32,
+        row_ptr,
+        col_idx,
+        values,
+    }
+}
+
+fn run_block_jacobi_bind_group_cache_test(ctx: &GpuContext) {
+    let a = laplacian_2d(20, 20);
+    let n = a.n_rows as usize;
+    let exec = BlockJacobiExecutor::from_csr(ctx, &a, 6)
+        .unwrap_or_else(|e| panic!("block-jacobi-bind-group-cache-test: {e}"));
+    let r: Vec<f32> = (0..n).map(|i| 1.0 + (i % 9) as f32 * 0.5).collect();
+    let r_gpu = ctx.create_storage_buffer("bind-group-cache-test r", &r, BufferUsages::empty());
+    let z_gpu = ctx.create_storage_buffer_uninit::<f32>(
+        "bind-group-cache-test z",
+        n,
+        BufferUsages::empty(),
+    );
+    let z2_gpu = ctx.create_storage_buffer_uninit::<f32>(
+        "bind-group-cache-test z2",
+        n,
+        BufferUsages::empty(),
+    );
+
+    // Many applies to the same r/z, as in a CG loop: one bind group.
+    for _ in 0..3 {
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("bind-group-cache-test encoder"),
+            });
+        for _ in 0..10 {
+            exec.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+        }
+        ctx.queue.submit(Some(encoder.finish()));
+    }
+    assert_eq!(exec.bind_groups_created(), 1);
+    let z = executor::block_on(ctx.readback(&z_gpu));
+
+    // Another output buffer misses and really writes there; the cached result is the
+    // same as an uncached apply.
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("bind-group-cache-test encoder"),
+        });
+    exec.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z2_gpu.buffer);
+    ctx.queue.submit(Some(encoder.finish()));
+    assert_eq!(exec.bind_groups_created(), 2);
+    assert_eq!(
+        executor::block_on(ctx.readback(&z2_gpu)),
+        z,
+        "block-jacobi-bind-group-cache-test failed: applies to z and z2 differ"
+    );
+
+    // The masked apply is keyed on its mask too, and clearing forces a rebuild.
+    let mask = ctx.create_storage_buffer(
+        "bind-group-cache-test mask",
+        &vec![1u32; exec.num_blocks() as usize],
+        BufferUsages::empty(),
+    );
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("bind-group-cache-test encoder"),
+        });
+    exec.encode_apply_masked(
+        ctx,
+        &mut encoder,
+        &r_gpu.buffer,
+        &z2_gpu.buffer,
+        &mask.buffer,
+    );
+    exec.encode_apply_masked(
+        ctx,
+        &mut encoder,
+        &r_gpu.buffer,
+        &z2_gpu.buffer,
+        &mask.buffer,
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+    assert_eq!(exec.bind_groups_created(), 3);
+    exec.clear_bind_group_cache();
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("bind-group-cache-test encoder"),
+        });
+    exec.encode_apply_masked(
+        ctx,
+        &mut encoder,
+        &r_gpu.buffer,
+        &z2_gpu.buffer,
+        &mask.buffer,
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+    assert_eq!(exec.bind_groups_created(), 4);
+    assert_eq!(executor::block_on(ctx.readback(&z2_gpu)), z);
+
+    // A solve builds one bind group over its own r/z and drops it with them, so the
+    // executor does not keep the solve's vectors alive.
+    let spmv_exec = SpmvExecutor::create(ctx, n as u32, &a.row_ptr, &a.col_idx, &a.values);
+    let vec_ops_exec = VecOpsExecutor::create(ctx);
+    let dot_scalar_exec = DotScalarExecutor::create(ctx, n, PCG_SCALAR_RESULTS_LEN);
+    let pcg_update_scalars_exec = PcgUpdateScalarsExecutor::create(ctx);
+    let mut x = vec![0.0f32; n];
+    let result = pcg_block_jacobi_csr_wgpu(
+        n,
+        &r,
+        &mut x,
+        1000,
+        1e-5,
+        0.0,
+        ctx,
+        &spmv_exec,
+        &vec_ops_exec,
+        &dot_scalar_exec,
+        &exec,
+        &pcg_update_scalars_exec,
+        &PcgOptions::default(),
+        None,
+    )
+    .unwrap_or_else(|e| panic!("block-jacobi-bind-group-cache-test: solve failed: {e}"));
+    assert_eq!(
+        exec.bind_groups_created(),
+        5,
+        "block-jacobi-bind-group-cache-test failed: {} applies of the solve rebuilt the bind group",
+        result.iterations + 1
+    );
+    assert!(
+        !exec.has_cached_bind_group(),
+        "block-jacobi-bind-group-cache-test failed: the solve's r/z are still cached"
+    );
+
+    println!(
+        "BlockJacobiBindGroupCacheTest OK: n={n}, 34 applies + a {}-iteration solve, {} bind groups",
+        result.iterations,
+        exec.bind_groups_created()
+    );
+}
+
+fn run_block_jacobi_from_csr_test(ctx: &GpuContext) {
+    // Bandwidth 3 reaches across every block boundary, so some entries are outside the
+    // diagonal blocks (and must be ignored). n = 1003 leaves a short last block.
+    let n = 1003;
+    let a = banded_test_matrix(n, 3);
+    let r: Vec<f32> = (0..n).map(|i| ((i % 11) as f32) - 5.0).collect();
+    let r_gpu = ctx.create_storage_buffer("bj-from-csr r", &r, BufferUsages::empty());
+    let z_gpu = ctx.create_storage_buffer_uninit::<f32>("bj-from-csr z", n, BufferUsages::empty());
+
+    let mut worst = 0.0f32;
+    for block_size in [1, 4, 6] {
+        let bj = BlockJacobiExecutor::from_csr(ctx, &a, block_size)
+            .unwrap_or_else(|e| panic!("block-jacobi-from-csr-test: block_size {block_size}: {e}"));
+
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("block-jacobi-from-csr-test encoder"),
+            });
+        bj.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+        ctx.queue.submit(Some(encoder.finish()));
+        let z = executor::block_on(ctx.readback(&z_gpu));
+
+        // CPU block Jacobi: host extraction + factorization + apply
+        let block_starts = uniform_block_starts(n, block_size);
+        let lu_blocks = build_lu_blocks_from_csr_block_starts_6(
+            n,
+            &a.row_ptr,
+            &a.col_idx,
+            &a.values,
+            &block_starts,
+        )
+        .unwrap();
+        let mut z_cpu = vec![0.0f32; n];
+        reference::block_jacobi_apply_6(&lu_blocks, &block_starts, &r, &mut z_cpu);
+
+        for (i, (got, expected)) in z.iter().zip(&z_cpu).enumerate() {
+            let err = (got - expected).abs() / expected.abs().max(1.0);
+            worst = worst.max(err);
+            assert!(
+                err <= 1e-5,
+                "block-jacobi-from-csr-test failed (block_size {block_size}) at i={i}: GPU {got} vs CPU {expected}"
+            );
+        }
+    }
+
+    // A zero pivot is reported with its block; sizes outside 1..=6 are rejected.
+    let mut singular = a.clone();
+    for k in singular.row_ptr[8] as usize..singular.row_ptr[9] as usize {
+        if singular.col_idx[k] == 8 {
+            singular.values[k] = 0.0;
+        }
+    }
+    let err = BlockJacobiExecutor::from_csr(ctx, &singular, 4)
+        .err()
+        .expect("block-jacobi-from-csr-test failed: zero pivot accepted");
+    assert!(
+        err.contains("block 2"),
+        "block-jacobi-from-csr-test failed: unexpected error {err}"
+    );
+    for block_size in [0, 7] {
+        assert!(
+            BlockJacobiExecutor::from_csr(ctx, &a, block_size).is_err(),
+            "block-jacobi-from-csr-test failed: block_size {block_size} accepted"
+        );
+    }
+
+    println!(
+        "BlockJacobiFromCsrTest OK: GPU-factored apply matches CPU block Jacobi for block sizes 1/4/6 (max rel err {worst:.2e})"
+    );
+}
+
+/// Least squares of one dense m x k block (column-major `a[j][i]`) by the normal equations
+fn run_block_jacobi_singular_test(ctx: &GpuContext) {
+    let n = 1003;
+    let a = banded_test_matrix(n, 3);
+    let set = |m: &mut Csr, i: usize, j: usize, v: f32| {
+        let k = (m.row_ptr[i]..m.row_ptr[i + 1])
+            .find(|&k| m.col_idx[k as usize] == j as u32)
+            .expect("entry in the band");
+        m.values[k as usize] = v;
+    };
+
+    // block_size 4: block 2 (rows 8..12) gets a zero pivot at k = 0, block 5 (rows 20..24)
+    // two equal rows (zero pivot at k = 1 after elimination), block 7 (rows 28..32) a
+    // pivot 1e-9 of its block's scale, which an exact zero test would let through.
+    let mut singular = a.clone();
+    set(&mut singular, 8, 8, 0.0);
+    for j in 20..24 {
+        let k = (a.row_ptr[20]..a.row_ptr[21])
+            .find(|&k| a.col_idx[k as usize] == j)
+            .unwrap();
+        set(&mut singular, 21, j as usize, a.values[k as usize]);
+    }
+    set(&mut singular, 28, 28, 1e-9);
+
+    let (bj, setup) = BlockJacobiExecutor::from_csr_with_setup(ctx, &singular, 4)
+        .unwrap_or_else(|e| panic!("block-jacobi-singular-test failed: {e}"));
+    assert!(
+        !setup.preconditioner_setup_ok,
+        "block-jacobi-singular-test failed: setup reported ok"
+    );
+    assert_eq!(
+        setup.singular_blocks,
+        vec![2, 5, 7],
+        "block-jacobi-singular-test failed: singular blocks"
+    );
+    assert_eq!(
+        setup.singular_pivots,
+        vec![0, 1, 0],
+        "block-jacobi-singular-test failed: pivot steps"
+    );
+
+    // The apply stays finite: singular blocks pass r through, the others are untouched.
+    let r: Vec<f32> = (0..n).map(|i| ((i % 11) as f32) - 5.0).collect();
+    let r_gpu = ctx.create_storage_buffer("bj-singular r", &r, BufferUsages::empty());
+    let z_gpu = ctx.create_storage_buffer_uninit::<f32>("bj-singular z", n, BufferUsages::empty());
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("block-jacobi-singular-test encoder"),
+        });
+    bj.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+    ctx.queue.submit(Some(encoder.finish()));
+    let z = executor::block_on(ctx.readback(&z_gpu));
+    assert!(
+        z.iter().all(|v| v.is_finite()),
+        "block-jacobi-singular-test failed: non-finite z"
+    );
+    for &block in &setup.singular_blocks {
+        let rows = block as usize * 4..block as usize * 4 + 4;
+        assert_eq!(
+            z[rows.clone()],
+            r[rows],
+            "block-jacobi-singular-test failed: block {block} is not the identity"
+        );
+    }
+
+    let (_, clean) = BlockJacobiExecutor::from_csr_with_setup(ctx, &a, 4).unwrap();
+    assert!(
+        clean.preconditioner_setup_ok && clean.singular_blocks.is_empty(),
+        "block-jacobi-singular-test failed: regular matrix reported {clean:?}"
+    );
+
+    println!(
+        "BlockJacobiSingularTest OK: singular blocks {:?} reported, apply finite",
+        setup.singular_blocks
+    );
+}
+
+fn run_checksum_test(ctx: &GpuContext) {
+    // Published FNV-1a 64 test vectors.
+    assert_eq!(fnv1a_64(b""), 0xcbf29ce484222325);
+    assert_eq!(fnv1a_64(b"a"), 0xaf63dc4c8601ec8c);
+    assert_eq!(fnv1a_64(b"foobar"), 0x85944171f73967e8);
+
+    let a = banded_test_matrix(2001, 3);
+    let upload = |label: &str, values: &[f32]| {
+        ctx.create_storage_buffer(label, values, BufferUsages::empty())
+    };
+    let first = upload("checksum values 1", &a.values);
+    let second = upload("checksum values 2", &a.values);
+    let len = (a.values.len() * 4) as u64;
+
+    let h1 = executor::block_on(buffer_fnv(ctx, &first.buffer, len));
+    let h2 = executor::block_on(buffer_fnv(ctx, &second.buffer, len));
+    assert_eq!(
+        h1, h2,
+        "checksum-test failed: identical uploads hash differently"
+    );
+    let host_bytes: Vec<u8> = a.values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    assert_eq!(
+        h1,
+        fnv1a_64(&host_bytes),
+        "checksum-test failed: GPU checksum != host checksum"
+    );
+
+    // One flipped bit (lowest mantissa bit of one value) changes the checksum.
+    let mut flipped = a.values.clone();
+    flipped[1234] = f32::from_bits(flipped[1234].to_bits() ^ 1);
+    let third = upload("checksum values flipped", &flipped);
+    let h3 = executor::block_on(buffer_fnv(ctx, &third.buffer, len));
+    assert_ne!(
+        h1, h3,
+        "checksum-test failed: single-bit change not detected"
+    );
+
+    assert!(
+        executor::block_on(try_buffer_fnv(ctx, &first.buffer, len - 2)).is_err(),
+        "checksum-test failed: unaligned length accepted"
+    );
+    assert!(
+        executor::block_on(try_buffer_fnv(ctx, &first.buffer, len + 4)).is_err(),
+        "checksum-test failed: length past the buffer accepted"
+    );
+
+    println!("ChecksumTest OK: identical uploads -> {h1:016x}, one flipped bit -> {h3:016x}");
+}
+
+fn run_composite_operator_test(ctx: &GpuContext) {
+    let n = 1003;
+    let a = banded_test_matrix(n, 3);
+    let spmv = SpmvExecutor::create(ctx, n as u32, &a.row_ptr, &a.col_idx, &a.values);
+    let bj = BlockJacobiExecutor::from_csr(ctx, &a, 4)
+        .unwrap_or_else(|e| panic!("composite-operator-test: {e}"));
+
+    let x: Vec<f32> = (0..n)
+        .map(|i| ((i * 29) % 17) as f32 * 0.25 - 2.0)
+        .collect();
+    let x_gpu = ctx.create_storage_buffer("composite x", &x, BufferUsages::empty());
+    let tmp = ctx.create_storage_buffer_uninit::<f32>("composite tmp", n, BufferUsages::empty());
+    let sequential =
+        ctx.create_storage_buffer_uninit::<f32>("composite seq", n, BufferUsages::empty());
+    let y_mina =
+        ctx.create_storage_buffer_uninit::<f32>("composite M^-1 A x", n, BufferUsages::empty());
+    let y_amin =
+        ctx.create_storage_buffer_uninit::<f32>("composite A M^-1 x", n, BufferUsages::empty());
+
+    // M^{-1} A and A M^{-1} as composites, against the two applies in sequence by hand.
+    let m_inv_a = CompositeOperator::new(ctx, &spmv, &bj).unwrap();
+    let a_m_inv = CompositeOperator::new(ctx, &bj, &spmv).unwrap();
+    assert_eq!((m_inv_a.n_rows(), m_inv_a.n_cols()), (n as u32, n as u32));
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("composite-operator-test encoder"),
+        });
+    spmv.encode_copy_x_from(&mut encoder, &x_gpu.buffer, (n * 4) as u64);
+    spmv.encode_spmv(&mut encoder);
+    bj.encode_apply(ctx, &mut encoder, spmv.y_buffer(), &sequential.buffer);
+    m_inv_a.encode_apply(ctx, &mut encoder, &x_gpu.buffer, &y_mina.buffer);
+    a_m_inv.encode_apply(ctx, &mut encoder, &x_gpu.buffer, &y_amin.buffer);
+    // Reuses the first composite's scratch after the other applies have run.
+    m_inv_a.encode_apply(ctx, &mut encoder, &x_gpu.buffer, &tmp.buffer);
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let seq = executor::block_on(ctx.readback(&sequential));
+    let got = executor::block_on(ctx.readback(&y_mina));
+    let again = executor::block_on(ctx.readback(&tmp));
+    assert!(
+        seq.iter()
+            .zip(&got)
+            .all(|(s, g)| s.to_bits() == g.to_bits())
+            && got
+                .iter()
+                .zip(&again)
+                .all(|(g, a)| g.to_bits() == a.to_bits()),
+        "composite-operator-test failed: M^-1 A composite != SpMV then block Jacobi"
+    );
+
+    // A M^{-1} on the host from the GPU's M^{-1} x.
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("composite-operator-test encoder 2"),
+        });
+    bj.encode_apply(ctx, &mut encoder, &x_gpu.buffer, &tmp.buffer);
+    ctx.queue.submit(Some(encoder.finish()));
+    let m_inv_x = executor::block_on(ctx.readback(&tmp));
+    let amin = executor::block_on(ctx.readback(&y_amin));
+    let mut worst = 0.0f32;
+    for (i, &got) in amin.iter().enumerate() {
+        let (lo, hi) = (a.row_ptr[i] as usize, a.row_ptr[i + 1] as usize);
+        let expected: f32 = (lo..hi)
+            .map(|k| a.values[k] * m_inv_x[a.col_idx[k] as usize])
+            .sum();
+        let err = (got - expected).abs() / expected.abs().max(1.0);
+        worst = worst.max(err);
+        assert!(
+            err <= 1e-5,
+            "composite-operator-test failed at i={i}: A M^-1 x {got} vs {expected}"
+        );
+    }
+
+    // Mismatched dimensions are rejected.
+    let small = banded_test_matrix(10, 1);
+    let spmv_small = SpmvExecutor::create(ctx, 10, &small.row_ptr, &small.col_idx, &small.values);
+    assert!(
+        CompositeOperator::new(ctx, &spmv_small, &bj).is_err(),
+        "composite-operator-test failed: 10 -> 1003 composite accepted"
+    );
+
+    println!(
+        "CompositeOperatorTest OK: M^-1 A == SpMV then M^-1 (bitwise), A M^-1 max rel err {worst:.2e}"
+    );
+}
+
+fn run_rank1_update_test(ctx: &GpuContext) {
+    let n = 300;
+    let a = banded_test_matrix(n, 2);
+    let spmv = SpmvExecutor::create(ctx, n as u32, &a.row_ptr, &a.col_idx, &a.values);
+
+    let x: Vec<f32> = (0..n).map(|i| ((i * 13) % 9) as f32 * 0.25 - 1.0).collect();
+    let u1: Vec<f32> = (0..n).map(|i| ((i % 5) as f32 - 2.0) * 0.1).collect();
+    let v1: Vec<f32> = (0..n).map(|i| ((i * 7) % 11) as f32 * 0.05).collect();
+    let u2: Vec<f32> = (0..n)
+        .map(|i| if i % 3 == 0 { 0.5 } else { -0.25 })
+        .collect();
+    let v2: Vec<f32> = (0..n).map(|i| ((i % 4) as f32) * 0.125).collect();
+    let x_gpu = ctx.create_storage_buffer("rank1 x", &x, BufferUsages::empty());
+    let u1_gpu = ctx.create_storage_buffer("rank1 u1", &u1, BufferUsages::empty());
+    let v1_gpu = ctx.create_storage_buffer("rank1 v1", &v1, BufferUsages::empty());
+    let u2_gpu = ctx.create_storage_buffer("rank1 u2", &u2, BufferUsages::empty());
+    let v2_gpu = ctx.create_storage_buffer("rank1 v2", &v2, BufferUsages::empty());
+    let y1 = ctx.create_storage_buffer_uninit::<f32>("rank1 y1", n, BufferUsages::empty());
+    let y2 = ctx.create_storage_buffer_uninit::<f32>("rank1 y2", n, BufferUsages::empty());
+
+    let once = spmv
+        .with_rank1_update(ctx, &u1_gpu.buffer, &v1_gpu.buffer)
+        .unwrap_or_else(|e| panic!("rank1-update-test: {e}"));
+    let twice = once
+        .with_rank1_update(ctx, &u2_gpu.buffer, &v2_gpu.buffer)
+        .unwrap_or_else(|e| panic!("rank1-update-test: {e}"));
+    assert_eq!((twice.n_rows(), twice.n_cols()), (n as u32, n as u32));
+    assert_eq!(
+        twice.dispatches(),
+        once.dispatches() * 2 - 1,
+        "rank1-update-test failed: dispatch count"
+    );
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("rank1-update-test encoder"),
+        });
+    once.encode_apply(ctx, &mut encoder, &x_gpu.buffer, &y1.buffer);
+    twice.encode_apply(ctx, &mut encoder, &x_gpu.buffer, &y2.buffer);
+    ctx.queue.submit(Some(encoder.finish()));
+    let got1 = executor::block_on(ctx.readback(&y1));
+    let got2 = executor::block_on(ctx.readback(&y2));
+
+    // Dense (A + u1 v1^T + u2 v2^T) x in f64.
+    let mut dense = vec![vec![0.0f64; n]; n];
+    for (i, row) in dense.iter_mut().enumerate() {
+        for k in a.row_ptr[i] as usize..a.row_ptr[i + 1] as usize {
+            row[a.col_idx[k] as usize] += a.values[k] as f64;
+        }
+    }
+    let mut worst = 0.0f64;
+    for (updates, got) in [(1, &got1), (2, &got2)] {
+        for i in 0..n {
+            let expected: f64 = (0..n)
+                .map(|j| {
+                    let mut aij = dense[i][j] + u1[i] as f64 * v1[j] as f64;
+                    if updates == 2 {
+                        aij += u2[i] as f64 * v2[j] as f64;
+                    }
+                    aij * x[j] as f64
+                })
+                .sum();
+            let err = (got[i] as f64 - expected).abs() / expected.abs().max(1.0);
+            worst = worst.max(err);
+            assert!(
+                err <= 1e-5,
+                "rank1-update-test failed ({updates} updates) at i={i}: {} vs {expected}",
+                got[i]
+            );
+        }
+    }
+
+    // Short u/v are rejected.
+    let short = ctx.create_storage_buffer("rank1 short", &u1[..10], BufferUsages::empty());
+    assert!(
+        spmv.with_rank1_update(ctx, &short.buffer, &v1_gpu.buffer)
+            .is_err(),
+        "rank1-update-test failed: short u accepted"
+    );
+
+    println!(
+        "Rank1UpdateTest OK: A + u v^T and a stacked second update match the dense product (max rel err {worst:.2e})"
+    );
+}
+
+fn run_block_jacobi_mask_test(ctx: &GpuContext) {
+    // Localized load: r is non-zero on two small row ranges only.
+    let n = 1003;
+    let block_size = 4;
+    let a = banded_test_matrix(n, 3);
+    let bj = BlockJacobiExecutor::from_csr(ctx, &a, block_size)
+        .unwrap_or_else(|e| panic!("block-jacobi-mask-test: {e}"));
+
+    let r: Vec<f32> = (0..n)
+        .map(|i| match i {
+            101..=130 => ((i % 7) as f32) - 3.0,
+            998..=1002 => 1.5,
+            _ => 0.0,
+        })
+        .collect();
+    let mask = active_block_mask(&uniform_block_starts(n, block_size), &r);
+    let active = mask.iter().filter(|&&m| m != 0).count();
+    assert_eq!(mask.len(), bj.num_blocks() as usize);
+    assert!(
+        active < mask.len() / 20,
+        "block-jacobi-mask-test: mask not sparse"
+    );
+
+    // Garbage in z: skipped blocks must still be written.
+    let garbage = vec![f32::NAN; n];
+    let r_gpu = ctx.create_storage_buffer("bj-mask r", &r, BufferUsages::empty());
+    let z_full = ctx.create_storage_buffer("bj-mask z full", &garbage, BufferUsages::empty());
+    let z_masked = ctx.create_storage_buffer("bj-mask z masked", &garbage, BufferUsages::empty());
+    let mask_gpu = ctx.create_storage_buffer("bj-mask mask", &mask, BufferUsages::empty());
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("block-jacobi-mask-test encoder"),
+        });
+    bj.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_full.buffer);
+    bj.encode_apply_masked(
+        ctx,
+        &mut encoder,
+        &r_gpu.buffer,
+        &z_masked.buffer,
+        &mask_gpu.buffer,
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+    let full = executor::block_on(ctx.readback(&z_full));
+    let masked = executor::block_on(ctx.readback(&z_masked));
+
+    for (i, (f, m)) in full.iter().zip(&masked).enumerate() {
+        assert!(
+            f.to_bits() == m.to_bits() || (*f == 0.0 && *m == 0.0),
+            "block-jacobi-mask-test failed at i={i}: full {f} vs masked {m}"
+        );
+    }
+    assert!(
+        full[101..=130].iter().any(|&v| v != 0.0),
+        "block-jacobi-mask-test failed: active blocks came out zero"
+    );
+
+    println!(
+        "BlockJacobiMaskTest OK: masked apply == full apply with {active}/{} blocks active",
+        mask.len()
+    );
+}
+
+/// in f64: (A^T A) z = A^T r, Gaussian elimination without pivoting (A^T A is SPD).
+fn dense_least_squares_f64(a: &[Vec<f64>], r: &[f64]) -> Vec<f64> {
+    let k = a.len();
+    let mut g: Vec<Vec<f64>> = (0..k)
+        .map(|p| {
+            (0..k)
+                .map(|q| a[p].iter().zip(&a[q]).map(|(x, y)| x * y).sum())
+                .collect()
+        })
+        .collect();
+    let mut rhs: Vec<f64> = a
+        .iter()
+        .map(|col| col.iter().zip(r).map(|(x, y)| x * y).sum())
+        .collect();
+    for p in 0..k {
+        let (done, rest) = g.split_at_mut(p + 1);
+        let pivot_row = &done[p];
+        for (q, row) in rest.iter_mut().enumerate() {
+            let f = row[p] / pivot_row[p];
+            for (v, &pv) in row[p..].iter_mut().zip(&pivot_row[p..]) {
+                *v -= f * pv;
+            }
+            rhs[p + 1 + q] -= f * rhs[p];
+        }
+    }
+    let mut z = vec![0.0f64; k];
+    for p in (0..k).rev() {
+        let tail: f64 = (p + 1..k).map(|c| g[p][c] * z[c]).sum();
+        z[p] = (rhs[p] - tail) / g[p][p];
+    }
+    z
+}
+
+fn run_block_lsq_test(ctx: &GpuContext) {
+    // Block shapes (rows x cols) cycle through tall and square ones.
+    let shapes = [(3usize, 2usize), (2, 2), (6, 4), (5, 1)];
+    let reps = 25;
+    let mut row_starts = vec![0u32];
+    let mut col_starts = vec![0u32];
+    for b in 0..reps * shapes.len() {
+        let (m, k) = shapes[b % shapes.len()];
+        row_starts.push(row_starts[b] + m as u32);
+        col_starts.push(col_starts[b] + k as u32);
+    }
+    let num_blocks = row_starts.len() - 1;
+    let n_rows = *row_starts.last().unwrap() as usize;
+    let n_cols = *col_starts.last().unwrap() as usize;
+
+    // Block entries plus one coupling entry per row into the next block's columns, which
+    // the preconditioner must ignore.
+    let entry = |i: usize, j: usize| -> f32 {
+        ((i * 7 + j * 13) % 11) as f32 / 11.0 - 0.5 + if i % 6 == j % 6 { 2.0 } else { 0.0 }
+    };
+    let mut dense_blocks: Vec<Vec<Vec<f64>>> = Vec::with_capacity(num_blocks);
+    let mut row_ptr = vec![0u32];
+    let mut col_idx = Vec::new();
+    let mut values = Vec::new();
+    for b in 0..num_blocks {
+        let (r0, r1) = (row_starts[b] as usize, row_starts[b + 1] as usize);
+        let (c0, c1) = (col_starts[b] as usize, col_starts[b + 1] as usize);
+        let mut block = vec![vec![0.0f64; r1 - r0]; c1 - c0];
+        for i in r0..r1 {
+            for j in c0..c1 {
+                let v = entry(i - r0, j - c0) + 0.01 * (b % 5) as f32;
+                col_idx.push(j as u32);
+                values.push(v);
+                block[j - c0][i - r0] = v as f64;
+            }
+            if c1 < n_cols {
+                col_idx.push(c1 as u32);
+                values.push(0.75);
+            }
+            row_ptr.push(col_idx.len() as u32);
+        }
+        dense_blocks.push(block);
+    }
+    let a = Csr {
+        n_rows: n_rows as u32,
+        n_cols: n_cols as u32,
+        nnz: col_idx.len() as u32,
+        row_ptr,
+        col_idx,
+        values,
+    };
+
+    let bj = BlockJacobiExecutor::from_csr_rectangular(ctx, &a, &row_starts, &col_starts)
+        .unwrap_or_else(|e| panic!("block-lsq-test: {e}"));
+    assert_eq!(bj.kind(), BlockKind::Rectangular);
+    assert_eq!((bj.n() as usize, bj.n_cols() as usize), (n_rows, n_cols));
+
+    let r: Vec<f32> = (0..n_rows).map(|i| ((i % 9) as f32) - 4.0).collect();
+    let r_gpu = ctx.create_storage_buffer("block-lsq r", &r, BufferUsages::empty());
+    let z_gpu =
+        ctx.create_storage_buffer_uninit::<f32>("block-lsq z", n_cols, BufferUsages::empty());
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("block-lsq-test encoder"),
+        });
+    bj.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+    ctx.queue.submit(Some(encoder.finish()));
+    let z = executor::block_on(ctx.readback(&z_gpu));
+
+    let mut worst = 0.0f64;
+    for b in 0..num_blocks {
+        let (r0, r1) = (row_starts[b] as usize, row_starts[b + 1] as usize);
+        let c0 = col_starts[b] as usize;
+        let r_b: Vec<f64> = r[r0..r1].iter().map(|&v| v as f64).collect();
+        let z_cpu = dense_least_squares_f64(&dense_blocks[b], &r_b);
+        for (j, expected) in z_cpu.iter().enumerate() {
+            let got = z[c0 + j] as f64;
+            let err = (got - expected).abs() / expected.abs().max(1.0);
+            worst = worst.max(err);
+            assert!(
+                err <= 1e-4,
+                "block-lsq-test failed: block {b} col {j}: GPU {got} vs CPU least squares {expected}"
+            );
+        }
+    }
+
+    // Wide blocks and rank-deficient blocks are rejected.
+    let wide = BlockJacobiExecutor::from_csr_rectangular(
+        ctx,
+        &a,
+        &[0, 2, n_rows as u32],
+        &[0, 3, n_cols as u32],
+    )
+    .err()
+    .expect("block-lsq-test failed: wide block accepted");
+    assert!(
+        wide.contains("block 0"),
+        "block-lsq-test failed: unexpected error {wide}"
+    );
+    let mut rank_deficient = a.clone();
+    let (k0, k1) = (
+        rank_deficient.row_ptr[0] as usize,
+        rank_deficient.row_ptr[3] as usize,
+    );
+    for k in k0..k1 {
+        rank_deficient.values[k] = 1.0;
+    }
+    let err =
+        BlockJacobiExecutor::from_csr_rectangular(ctx, &rank_deficient, &row_starts, &col_starts)
+            .err()
+            .expect("block-lsq-test failed: rank-deficient block accepted");
+    assert!(
+        err.contains("rank-deficient"),
+        "block-lsq-test failed: unexpected error {err}"
+    );
+
+    println!(
+        "BlockLsqTest OK: {num_blocks} rectangular/square blocks ({n_rows}x{n_cols}) match CPU least squares (max rel err {worst:.2e})"
+    );
+}
+
+fn run_adapter_filter_test(gpu_backend: GpuBackend) {
+    let adapter = |name: &str, vendor: u32, device_type: DeviceType| AdapterInfo {
+        name: name.to_string(),
+        vendor,
+        device: 0,
+        device_type,
+        backend: Backend::Vulkan,
+    };
+    let fleet = [
+        adapter("Radeon R9 (buggy driver)", 0x1002, DeviceType::DiscreteGpu),
+        adapter("Intel iGPU", 0x8086, DeviceType::IntegratedGpu),
+        adapter("llvmpipe", 0x10005, DeviceType::Cpu),
+    ];
+    let builder = || GpuContextBuilder::new(gpu_backend);
+
+    // The denied adapter is skipped in favor of the next candidate.
+    let deny_amd = builder().deny_adapter(|a| a.vendor == 0x1002);
+    assert_eq!(builder().select(&fleet, None).unwrap(), 0);
+    assert_eq!(deny_amd.select(&fleet, None).unwrap(), 1);
+    let deny_by_name = builder().deny_adapter(|a| a.name.contains("buggy"));
+    assert_eq!(deny_by_name.select(&fleet, None).unwrap(), 1);
+
+    // allow_only narrows; several of them intersect.
+    let cpu_only = builder().allow_only(|a| a.device_type == DeviceType::Cpu);
+    assert_eq!(cpu_only.select(&fleet, None).unwrap(), 2);
+    let nothing = cpu_only.clone().allow_only(|a| a.vendor == 0x8086);
+    assert!(
+        matches!(
+            nothing.select(&fleet, None),
+            Err(GpuError::NoPermittedAdapter { available: 3 })
+        ),
+        "adapter-filter-test failed: disjoint allowlists selected an adapter"
+    );
+
+    // An index names one adapter: honored when permitted, an error when denied.
+    assert_eq!(deny_amd.select(&fleet, Some(2)).unwrap(), 2);
+    assert!(
+        matches!(
+            deny_amd.select(&fleet, Some(0)),
+            Err(GpuError::AdapterDenied { index: 0, .. })
+        ),
+        "adapter-filter-test failed: denied adapter selected by index"
+    );
+
+    // Real enumeration: deny whatever adapter comes first, build picks another one (or
+    // reports that none is left).
+    let first = executor::block_on(builder().build()).unwrap_or_else(|e| exit_gpu_init_failed(&e));
+    let denied_name = first.adapter_info.name.clone();
+    let name = denied_name.clone();
+    match executor::block_on(builder().deny_adapter(move |a| a.name == name).build()) {
+        Ok(ctx) => assert_ne!(
+            ctx.adapter_info.name, denied_name,
+            "adapter-filter-test failed: denied adapter was used"
+        ),
+        Err(GpuError::NoPermittedAdapter { .. }) => {}
+        Err(e) => panic!("adapter-filter-test failed: {e}"),
+    }
+
+    println!(
+        "AdapterFilterTest OK: denied adapters skipped, allowlists narrow (denied {denied_name})"
+    );
+}
+
+fn run_context_limits_test(gpu_backend: GpuBackend, adapter_index: Option<usize>) {
+    let builder = || GpuContextBuilder::new(gpu_backend).adapter_index(adapter_index);
+    let plain = executor::block_on(builder().build()).unwrap_or_else(|e| exit_gpu_init_failed(&e));
+    let adapter_limits = plain.adapter.limits();
+    let adapter_features = plain.adapter.features();
+    let name = plain.adapter_info.name.clone();
+    let allowed = adapter_limits.max_storage_buffer_binding_size;
+
+    // Zero-config creation keeps the default binding size (128 MiB).
+    let default_binding = Limits::default().max_storage_buffer_binding_size;
+    assert_eq!(
+        plain.device.limits().max_storage_buffer_binding_size,
+        default_binding.min(allowed)
+    );
+
+    // Everything the adapter offers is granted, and survives recreate().
+    let bumped = Limits {
+        max_storage_buffer_binding_size: allowed,
+        max_buffer_size: adapter_limits.max_buffer_size,
+        ..Limits::downlevel_defaults()
+    };
+    let ctx = executor::block_on(builder().required_limits(bumped.clone()).build())
+        .unwrap_or_else(|e| panic!("context-limits-test: adapter limits rejected: {e}"));
+    assert_eq!(ctx.device.limits().max_storage_buffer_binding_size, allowed);
+    // Requests only raise the defaults: the workgroup limits are still the adapter's.
+    assert_eq!(
+        ctx.device.limits().max_compute_workgroup_size_x,
+        adapter_limits.max_compute_workgroup_size_x
+    );
+    let again = executor::block_on(ctx.recreate())
+        .unwrap_or_else(|e| panic!("context-limits-test: recreate failed: {e}"));
+    assert_eq!(
+        again.device.limits().max_storage_buffer_binding_size,
+        allowed
+    );
+
+    // One past the adapter: a clear error naming the limit, before any device request.
+    let too_big = Limits {
+        max_storage_buffer_binding_size: allowed + 1,
+        ..Limits::default()
+    };
+    let err = executor::block_on(builder().required_limits(too_big.clone()).build())
+        .expect_err("context-limits-test: over-limit request accepted");
+    match &err {
+        GpuError::UnsupportedLimit {
+            limit,
+            requested,
+            allowed: reported,
+            ..
+        } => {
+            assert_eq!(*limit, "max_storage_buffer_binding_size");
+            assert_eq!(
+                (*requested, *reported),
+                (allowed as u64 + 1, allowed as u64)
+            );
+        }
+        e => panic!("context-limits-test: unexpected error: {e}"),
+    }
+    assert!(err.to_string().contains("max_storage_buffer_binding_size"));
+    assert_eq!(gpu_error_exit_code(&err), 3);
+
+    // A minimum-style limit: asking for a finer alignment than the adapter has.
+    let alignment = adapter_limits.min_storage_buffer_offset_alignment;
+    if alignment > 1 {
+        let finer = Limits {
+            min_storage_buffer_offset_alignment: alignment / 2,
+            ..Limits::default()
+        };
+        let err = builder()
+            .required_limits(finer)
+            .device_request(&name, adapter_features, &adapter_limits)
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                GpuError::UnsupportedLimit {
+                    limit: "min_storage_buffer_offset_alignment",
+                    ..
+                }
+            ),
+            "context-limits-test: unexpected error: {err}"
+        );
+    }
+
+    // Required features: missing ones are listed; optional ones still ride along.
+    let err = builder()
+        .required_features(Features::SHADER_F64)
+        .device_request(&name, Features::TIMESTAMP_QUERY, &adapter_limits)
+        .unwrap_err();
+    assert!(
+        matches!(err, GpuError::UnsupportedFeatures { missing, .. } if missing == Features::SHADER_F64),
+        "context-limits-test: unexpected error: {err}"
+    );
+    let (features, _) = builder()
+        .required_features(Features::SHADER_F64)
+        .device_request(
+            &name,
+            Features::SHADER_F64 | Features::TIMESTAMP_QUERY,
+            &adapter_limits,
+        )
+        .unwrap_or_else(|e| panic!("context-limits-test: {e}"));
+    assert_eq!(features, Features::SHADER_F64 | Features::TIMESTAMP_QUERY);
+
+    println!(
+        "ContextLimitsTest OK: {name}: max_storage_buffer_binding_size {} MiB granted (default {} MiB), {} rejected by name",
+        allowed >> 20,
+        default_binding >> 20,
+        too_big.max_storage_buffer_binding_size
+    );
+}
+
+fn run_adapter_selector_test(gpu_backend: GpuBackend) {
+    let adapter = |name: &str, device_type: DeviceType| AdapterInfo {
+        name: name.to_string(),
+        vendor: 0,
+        device: 0,
+        device_type,
+        backend: Backend::Vulkan,
+    };
+    // Enumeration order puts the integrated GPU first, as on many dual-GPU laptops.
+    let fleet = [
+        adapter("Intel(R) UHD Graphics", DeviceType::IntegratedGpu),
+        adapter("NVIDIA GeForce RTX 4070", DeviceType::DiscreteGpu),
+        adapter("llvmpipe", DeviceType::Cpu),
+    ];
+    let builder = GpuContextBuilder::new(gpu_backend);
+    let select = |selector: AdapterSelector| builder.select_with(&fleet, &selector);
+
+    assert_eq!(select(AdapterSelector::PreferDiscrete).unwrap(), 1);
+    assert_eq!(select(AdapterSelector::Index(2)).unwrap(), 2);
+    assert_eq!(
+        select(AdapterSelector::NameContains("nvidia".into())).unwrap(),
+        1
+    );
+    assert_eq!(
+        select(AdapterSelector::NameContains("UHD".into())).unwrap(),
+        0
+    );
+    assert!(matches!(
+        select(AdapterSelector::Index(3)),
+        Err(GpuError::AdapterIndexOutOfRange {
+            index: 3,
+            available: 3
+        })
+    ));
+    let err = select(AdapterSelector::NameContains("radeon".into())).unwrap_err();
+    let message = err.to_string();
+    assert!(
+        matches!(err, GpuError::NoMatchingAdapter { .. })
+            && fleet.iter().all(|a| message.contains(&a.name)),
+        "adapter-selector-test failed: no-match error does not list the adapters: {message}"
+    );
+
+    // Name matching respects the filters: a denied match does not count.
+    let no_nvidia = GpuContextBuilder::new(gpu_backend).deny_adapter(|a| a.name.contains("NVIDIA"));
+    assert!(matches!(
+        no_nvidia.select_with(&fleet, &AdapterSelector::NameContains("geforce".into())),
+        Err(GpuError::NoMatchingAdapter { .. })
+    ));
+    assert_eq!(
+        no_nvidia
+            .select_with(&fleet, &AdapterSelector::PreferDiscrete)
+            .unwrap(),
+        0
+    );
+
+    // Real adapters: the default context, then the same adapter found again by name.
+    let default_ctx = executor::block_on(GpuContext::create(gpu_backend))
+        .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+    let name = default_ctx.adapter_info.name.clone();
+    let needle: String = name.chars().take(6).collect::<String>().to_uppercase();
+    let by_name = executor::block_on(GpuContext::create_with_selector(
+        gpu_backend,
+        AdapterSelector::NameContains(needle.clone()),
+    ))
+    .unwrap_or_else(|e| panic!("adapter-selector-test failed: {needle:?}: {e}"));
+    assert!(
+        by_name
+            .adapter_info
+            .name
+            .to_lowercase()
+            .contains(&needle.to_lowercase())
+    );
+
+    match executor::block_on(GpuContext::create_with_selector(
+        gpu_backend,
+        AdapterSelector::NameContains("no such adapter".into()),
+    )) {
+        Err(e @ GpuError::NoMatchingAdapter { .. }) => {
+            assert!(
+                e.to_string().contains(&name),
+                "adapter-selector-test failed: {e}"
+            )
+        }
+        Err(e) => panic!("adapter-selector-test failed: unexpected error {e}"),
+        Ok(_) => panic!("adapter-selector-test failed: unknown name matched an adapter"),
+    }
+
+    println!("AdapterSelectorTest OK: index/name/preference selection; default adapter {name}");
+}
+
+fn run_label_prefix_test(ctx: &GpuContext) {
+    // wgpu has no label getter, but validation errors name the resource by its label: use
+    // a destroyed buffer and read the label back from the error.
+    let label_in_error = |buffer: &wgpu::Buffer| -> String {
+        buffer.destroy();
+        let scope = ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let sink = ctx.create_storage_buffer_uninit::<f32>("label sink", 1, BufferUsages::empty());
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor { label: None });
+        encoder.copy_buffer_to_buffer(buffer, 0, &sink.buffer, 0, 4);
+        ctx.queue.submit(Some(encoder.finish()));
+        executor::block_on(scope.pop())
+            .map(|e| e.to_string())
+            .expect("label-prefix-test: using a destroyed buffer raised no error")
+    };
+
+    assert_eq!(
+        ctx.label_prefix(),
+        "",
+        "label-prefix-test: default prefix is not empty"
+    );
+    let plain = ctx.create_storage_buffer("plain vector", &[1.0f32], BufferUsages::empty());
+    let plain_error = label_in_error(&plain.buffer);
+    assert!(
+        plain_error.contains("'plain vector'"),
+        "label-prefix-test failed: {plain_error}"
+    );
+
+    // Two "solvers" on one context, each created under its own prefix.
+    let a = laplacian_1d(8);
+    let mut errors = Vec::new();
+    for prefix in ["solverA/", "solverB/"] {
+        ctx.set_label_prefix(prefix);
+        let vector = ctx.create_storage_buffer("vector", &[1.0f32], BufferUsages::empty());
+        let spmv = SpmvExecutor::create(ctx, a.n_rows, &a.row_ptr, &a.col_idx, &a.values);
+        for (what, buffer) in [("upload", &vector.buffer), ("executor", spmv.y_buffer())] {
+            let error = label_in_error(buffer);
+            assert!(
+                error.contains(&format!("'{prefix}")),
+                "label-prefix-test failed: {what} buffer lacks {prefix:?}: {error}"
+            );
+            errors.push(error);
+        }
+    }
+    ctx.set_label_prefix("");
+    assert!(errors[0].contains("'solverA/vector'") && errors[2].contains("'solverB/vector'"));
+
+    let example = errors[3].split('\'').nth(1).unwrap_or_default();
+    println!("LabelPrefixTest OK: buffers carry their creator's prefix, e.g. '{example}'");
+}
+
+fn run_profile_test() {
+    // Parse a run-pcg-case and a solve command line and resolve their solver settings
+    // the way the commands do; both must agree.
+    let parse = |command: &[&str], extra: &[&str]| -> SolverSettings {
+        let mut args = vec!["wgpu_solver_backend_cli"];
+        args.extend_from_slice(command);
+        args.extend_from_slice(extra);
+        let cli = Cli::try_parse_from(&args)
+            .unwrap_or_else(|e| panic!("profile-test: {args:?} rejected: {e}"));
+        match cli.cmd {
+            Cmd::RunPcgCase {
+                profile,
+                preconditioner,
+                residual_strategy,
+                reorth_window,
+                ..
+            }
+            | Cmd::Solve {
+                profile,
+                preconditioner,
+                residual_strategy,
+                reorth_window,
+                ..
+            } => SolverSettings::resolve(profile, preconditioner, residual_strategy, reorth_window),
+            other => panic!("profile-test: parsed as {other:?}"),
+        }
+    };
+    let settings_for = |extra: &[&str]| -> SolverSettings {
+        let run_pcg_case = parse(
+            &[
+                "run-pcg-case",
+                "--case-dir",
+                "c",
+                "--out-x",
+                "x.bin",
+                "--out-metrics",
+                "m.json",
+            ],
+            extra,
+        );
+        let solve = parse(&["solve", "--matrix", "a.mtx", "--rhs", "b.txt"], extra);
+        assert_eq!(
+            run_pcg_case, solve,
+            "profile-test failed: run-pcg-case and solve differ for {extra:?}"
+        );
+        run_pcg_case
+    };
+
+    let expect = |extra: &[&str], expected: SolverSettings| {
+        let got = settings_for(extra);
+        assert_eq!(got, expected, "profile-test failed for {extra:?}");
+    };
+
+    expect(&[], SolverSettings::default());
+    expect(
+        &["--profile", "fast"],
+        SolverSettings {
+            preconditioner: PreconditionerChoice::Jacobi,
+            residual_strategy: ResidualStrategy::Recurrence,
+            reorthogonalize: ReorthPolicy::None,
+            dot_precision: Precision::F32,
+        },
+    );
+    expect(
+        &["--profile", "robust"],
+        SolverSettings {
+            preconditioner: PreconditionerChoice::BlockJacobi,
+            residual_strategy: ResidualStrategy::TrueEvery(50),
+            reorthogonalize: ReorthPolicy::None,
+            dot_precision: Precision::F32,
+        },
+    );
+    expect(
+        &["--profile", "accurate"],
+        SolverSettings {
+            preconditioner: PreconditionerChoice::BlockJacobi,
+            residual_strategy: ResidualStrategy::AlwaysTrue,
+            reorthogonalize: ReorthPolicy::Window(8),
+            dot_precision: Precision::F64,
+        },
+    );
+
+    // Individual flags override the profile, and only the knob they name.
+    expect(
+        &[
+            "--profile",
+            "accurate",
+            "--residual-strategy",
+            "every:10",
+            "--reorth-window",
+            "0",
+        ],
+        SolverSettings {
+            preconditioner: PreconditionerChoice::BlockJacobi,
+            residual_strategy: ResidualStrategy::TrueEvery(10),
+            reorthogonalize: ReorthPolicy::None,
+            dot_precision: Precision::F64,
+        },
+    );
+    expect(
+        &["--preconditioner", "jacobi", "--profile", "robust"],
+        SolverSettings {
+            preconditioner: PreconditionerChoice::Jacobi,
+            ..SolverSettings::for_profile(SolverProfile::Robust)
+        },
+    );
+    expect(
+        &["--residual-strategy", "always"],
+        SolverSettings {
+            residual_strategy: ResidualStrategy::AlwaysTrue,
+            ..SolverSettings::default()
+        },
+    );
+
+    for bad in ["every:0", "every:", "sometimes"] {
+        assert!(
+            parse_residual_strategy(bad).is_err(),
+            "profile-test failed: residual strategy {bad:?} accepted"
+        );
+    }
+
+    // The profile's knobs reach PcgOptions; the rest of the options are kept.
+    let accurate = SolverSettings::for_profile(SolverProfile::Accurate).apply_to(PcgOptions {
+        submit_every: 3,
+        ..Default::default()
+    });
+    assert_eq!(
+        (
+            accurate.dot_precision,
+            accurate.residual_strategy,
+            accurate.reorthogonalize,
+            accurate.submit_every
+        ),
+        (
+            Precision::F64,
+            ResidualStrategy::AlwaysTrue,
+            ReorthPolicy::Window(8),
+            3
+        ),
+        "profile-test failed: apply_to"
+    );
+
+    println!(
+        "ProfileTest OK: fast/robust/accurate resolve to their settings for run-pcg-case and solve; flags override"
+    );
+}
+
+/// (width, height, pixels) of a grayscale PNG written by `io::png` (stored deflate
+/// blocks only; chunk CRCs are not checked).
+fn decode_stored_gray_png(bytes: &[u8]) -> Result<(usize, usize, Vec<u8>), String> {
+    if bytes.len() < 8 || &bytes[..8] != b"\x89PNG\r\n\x1a\n" {
+        return Err("bad PNG signature".into());
+    }
+    let be32 = |b: &[u8]| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize;
+
+    let (mut width, mut height, mut idat) = (0, 0, Vec::new());
+    let mut pos = 8;
+    while pos + 12 <= bytes.len() {
+        let len = be32(&bytes[pos..]);
+        let kind = &bytes[pos + 4..pos + 8];
+        let data = bytes.get(pos + 8..pos + 8 + len).ok_or("truncated chunk")?;
+        match kind {
+            b"IHDR" => {
+                if data[8..13] != [8, 0, 0, 0, 0] {
+                    return Err(format!("not 8-bit grayscale: {:?}", &data[8..13]));
+                }
+                (width, height) = (be32(data), be32(&data[4..]));
+            }
+            b"IDAT" => idat.extend_from_slice(data),
+            _ => {}
+        }
+        pos += 12 + len;
+    }
+
+    // zlib header, then stored blocks: [BFINAL, LEN (LE), NLEN, payload]
+    let mut raw = Vec::new();
+    let mut p = 2;
+    loop {
+        let header = *idat.get(p).ok_or("truncated deflate stream")?;
+        if header & 0b110 != 0 {
+            return Err("compressed deflate block".into());
+        }
+        let len = u16::from_le_bytes([idat[p + 1], idat[p + 2]]) as usize;
+        raw.extend_from_slice(&idat[p + 5..p + 5 + len]);
+        p += 5 + len;
+        if header & 1 == 1 {
+            break;
+        }
+    }
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in raw.chunks(width + 1) {
+        if row[0] != 0 {
+            return Err(format!("unexpected filter type {}", row[0]));
+        }
+        pixels.extend_from_slice(&row[1..]);
+    }
+    Ok((width, height, pixels))
+}
+
+fn run_grid_image_test(device: &SolverDevice) {
+    // -Δu = 1 on a non-square grid with zero Dirichlet boundaries.
+    let (w, h) = (24usize, 15usize);
+    let a = laplacian_2d(w, h);
+    let n = a.n_rows as usize;
+    let b = vec![1.0f32; n];
+    let mut x = vec![0.0f32; n];
+    let result = device
+        .pcg_block_jacobi_csr(
+            &a,
+            &uniform_block_starts(n, 6),
+            &b,
+            &mut x,
+            &SolveConfig::default().max_iters(2000).tol(1e-5),
+        )
+        .unwrap_or_else(|e| panic!("grid-image-test: solve failed: {e}"));
+
+    let path = std::env::temp_dir().join(format!("wgpu_solver_grid_image_{}.png", process::id()));
+    write_grid_png(&path, &x, w, h).unwrap_or_else(|e| panic!("grid-image-test: {e}"));
+    let bytes = fs::read(&path).unwrap_or_else(|e| panic!("grid-image-test: read: {e}"));
+    let _ = fs::remove_file(&path);
+
+    let (width, height, pixels) =
+        decode_stored_gray_png(&bytes).unwrap_or_else(|e| panic!("grid-image-test: {e}"));
+    assert_eq!(
+        (width, height),
+        (w, h),
+        "grid-image-test failed: image is {width}x{height}, grid is {w}x{h}"
+    );
+    assert_eq!(pixels.len(), n);
+
+    // Normalized: the full 0..=255 range is used, the peak sits in the middle of the
+    // grid and the solution's symmetry survives quantization.
+    let min = *pixels.iter().min().unwrap();
+    let max = *pixels.iter().max().unwrap();
+    assert_eq!(
+        (min, max),
+        (0, 255),
+        "grid-image-test failed: range {min}..={max}"
+    );
+    let px = |x: usize, y: usize| pixels[x + w * y] as i32;
+    assert!(
+        px(w / 2, h / 2) >= 250 && px(0, 0) < 40,
+        "grid-image-test failed: centre {} / corner {}",
+        px(w / 2, h / 2),
+        px(0, 0)
+    );
+    for y in 0..h {
+        for x in 0..w {
+            assert!(
+                (px(x, y) - px(w - 1 - x, h - 1 - y)).abs() <= 1,
+                "grid-image-test failed: ({x}, {y}) not symmetric"
+            );
+        }
+    }
+
+    // A mismatched grid is an error, not a garbled image.
+    assert!(write_grid_png(&path, &x, w + 1, h).is_err());
+
+    // --image and --grid go together on run-pcg-case.
+    let base = [
+        "wgpu_solver_backend_cli",
+        "run-pcg-case",
+        "--case-dir",
+        "c",
+        "--out-x",
+        "x.bin",
+        "--out-metrics",
+        "m.json",
+    ];
+    let parse = |extra: &[&str]| Cli::try_parse_from(base.iter().chain(extra));
+    assert!(parse(&["--image", "x.png", "--grid", "24x15"]).is_ok());
+    assert!(parse(&["--image", "x.png"]).is_err());
+    assert!(parse(&["--grid", "24x15"]).is_err());
+    assert!(parse(&["--image", "x.png", "--grid", "24"]).is_err());
+    assert!(parse(&["--image", "x.png", "--grid", "0x15"]).is_err());
+
+    println!(
+        "GridImageTest OK: {w}x{h} PNG ({} bytes) after {} iterations",
+        bytes.len(),
+        result.iterations
+    );
+}
+
+fn run_residual_strategy_test(device: &SolverDevice) {
+    let solve = |a: &Csr, b: &[f32], rel_tol: f32, max_iters: usize, strategy| {
+        let n = a.n_rows as usize;
+        let mut x = vec![0.0f32; n];
+        let options = PcgOptions {
+            residual_strategy: strategy,
+            ..Default::default()
+        };
+        let result = device.pcg_block_jacobi_csr(
+            a,
+            &uniform_block_starts(n, 1),
+            b,
+            &mut x,
+            &SolveConfig::default()
+                .max_iters(max_iters)
+                .tol(rel_tol)
+                .options(options.clone()),
+        );
+
+        // ||b - A x|| / ||b||, independent of what the solver tracked
+        let mut ax = vec![0.0f32; n];
+        reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, &x, &mut ax);
+        let r: Vec<f32> = b.iter().zip(&ax).map(|(b, ax)| b - ax).collect();
+        let true_rel = (reference::dot(&r, &r) / reference::dot(b, b)).sqrt();
+        (result, true_rel)
+    };
+
+    // Hard: 1D Laplacian with point Jacobi (kappa ~ 1.6e4) and a tolerance below what
+    // f32 can attain. The recurrence residual still drops below it.
+    let hard = laplacian_1d(200);
+    let b: Vec<f32> = (0..200).map(|i| 1.0 + (i % 5) as f32 * 0.3).collect();
+    let rel_tol = 1e-6;
+    let (recurrence, recurrence_true) =
+        solve(&hard, &b, rel_tol, 600, ResidualStrategy::Recurrence);
+    assert!(
+        recurrence.is_ok() && recurrence_true > 10.0 * rel_tol,
+        "residual-strategy-test: problem too easy, Recurrence gave {recurrence:?} with true residual {recurrence_true:e}"
+    );
+
+    // AlwaysTrue never claims a tolerance the true residual misses.
+    let (always_true, always_true_rel) =
+        solve(&hard, &b, rel_tol, 600, ResidualStrategy::AlwaysTrue);
+    if let Ok(result) = &always_true {
+        assert!(
+            always_true_rel <= 2.0 * rel_tol,
+            "residual-strategy-test failed: AlwaysTrue converged in {} iterations with true residual {always_true_rel:e}",
+            result.iterations
+        );
+    }
+
+    // Easy problem: both recomputing strategies still converge, to a real solution.
+    let easy = laplacian_2d(24, 24);
+    let easy_b = vec![1.0f32; 576];
+    for strategy in [ResidualStrategy::TrueEvery(5), ResidualStrategy::AlwaysTrue] {
+        let (result, true_rel) = solve(&easy, &easy_b, 1e-4, 500, strategy);
+        let result =
+            result.unwrap_or_else(|e| panic!("residual-strategy-test: {strategy:?} failed: {e}"));
+        assert!(
+            true_rel <= 2e-4,
+            "residual-strategy-test failed: {strategy:?} true residual {true_rel:e}"
+        );
+        assert!(result.iterations > 0);
+    }
+
+    assert!(
+        solve(&easy, &easy_b, 1e-4, 500, ResidualStrategy::TrueEvery(0))
+            .0
+            .is_err(),
+        "residual-strategy-test failed: interval 0 accepted"
+    );
+
+    println!(
+        "ResidualStrategyTest OK ({}): Recurrence stopped at {} iterations with true residual {recurrence_true:.2e} (tol {rel_tol:e}); AlwaysTrue {}",
+        device.describe(),
+        recurrence.map_or(0, |r| r.iterations),
+        match &always_true {
+            Ok(r) => format!(
+                "converged in {} iterations (true residual {always_true_rel:.2e})",
+                r.iterations
+            ),
+            Err(_) => "did not converge".to_string(),
+        }
+    );
+}
+
+fn run_dot_precision_test(device: &SolverDevice) {
+    // The residual-strategy-test problem: 1D Laplacian with point Jacobi and a tolerance
+    // below what f32 can attain, where the recurrence "converges" to a worse x.
+    let a = laplacian_1d(200);
+    let n = a.n_rows as usize;
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 5) as f32 * 0.3).collect();
+    let rel_tol = 1e-6;
+    let solve = |dot_precision| {
+        let mut x = vec![0.0f32; n];
+        let options = PcgOptions {
+            dot_precision,
+            ..Default::default()
+        };
+        let result = device.pcg_block_jacobi_csr(
+            &a,
+            &uniform_block_starts(n, 1),
+            &b,
+            &mut x,
+            &SolveConfig::default()
+                .max_iters(600)
+                .tol(rel_tol)
+                .options(options.clone()),
+        )?;
+
+        let mut ax = vec![0.0f32; n];
+        reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, &x, &mut ax);
+        let r: Vec<f32> = b.iter().zip(&ax).map(|(b, ax)| b - ax).collect();
+        let true_rel = (reference::dot(&r, &r) / reference::dot(&b, &b)).sqrt();
+        Ok::<_, String>((result, true_rel))
+    };
+
+    let ctx = match device {
+        SolverDevice::Gpu(ctx) => ctx,
+        SolverDevice::Cpu => {
+            // The reference dots accumulate in f64 already: the option changes nothing.
+            let (f32_dots, _) = solve(Precision::F32).expect("dot-precision-test: F32 solve");
+            let (f64_dots, _) = solve(Precision::F64).expect("dot-precision-test: F64 solve");
+            assert_eq!(
+                f32_dots.iterations, f64_dots.iterations,
+                "dot-precision-test failed: CPU result depends on dot_precision"
+            );
+            println!(
+                "DotPrecisionTest OK ({}): dots always f64 on the CPU, {} iterations either way",
+                device.describe(),
+                f32_dots.iterations
+            );
+            return;
+        }
+    };
+
+    if !Precision::F64.supported_by(ctx) {
+        let err =
+            solve(Precision::F64).expect_err("dot-precision-test: F64 accepted without SHADER_F64");
+        assert!(
+            err.contains("SHADER_F64"),
+            "dot-precision-test failed: unhelpful error {err:?}"
+        );
+        let exec = DotScalarExecutor::create(ctx, n, 1);
+        assert_eq!(exec.dot_precision(), Precision::F32);
+        println!(
+            "DotPrecisionTest OK ({}): no SHADER_F64, F64 dots rejected ({err}); f32/f64 comparison skipped",
+            ctx.describe()
+        );
+        return;
+    }
+
+    // One long dot: the f64 accumulator is at least as close to the exact (f64 host) sum.
+    let len = 1 << 18;
+    let u: Vec<f32> = (0..len).map(|i| 1.0 + (i % 97) as f32 * 1e-3).collect();
+    let v: Vec<f32> = (0..len).map(|i| 1.0 - (i % 89) as f32 * 1e-3).collect();
+    let exact: f64 = u.iter().zip(&v).map(|(u, v)| *u as f64 * *v as f64).sum();
+    let u_gpu = ctx.create_storage_buffer("dot-precision u", &u, BufferUsages::empty());
+    let v_gpu = ctx.create_storage_buffer("dot-precision v", &v, BufferUsages::empty());
+    let mut exec = DotScalarExecutor::create(ctx, len, 2);
+    let f32_dispatches = exec.dispatches_per_dot(len as u32);
+    exec.set_dot_precision(ctx, Precision::F64)
+        .expect("dot-precision-test: set_dot_precision(F64)");
+    assert_eq!(exec.dispatches_per_dot(len as u32), f32_dispatches + 1);
+    let mut dot_errors = [0.0f64; 2];
+    for (slot, precision) in [Precision::F32, Precision::F64].into_iter().enumerate() {
+        exec.set_dot_precision(ctx, precision).unwrap();
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor { label: None });
+        exec.encode_dot_scalar_into(
+            ctx,
+            &mut encoder,
+            &u_gpu.buffer,
+            &v_gpu.buffer,
+            len as u32,
+            0,
+        );
+        exec.encode_copy_scalar_results_to_readback(&mut encoder);
+        ctx.queue.submit([encoder.finish()]);
+        let got = executor::block_on(exec.readback_scalar_results(ctx))[0] as f64;
+        dot_errors[slot] = (got - exact).abs() / exact;
+    }
+    // The f64 sum is only rounded once: within half an f32 ulp.
+    assert!(
+        dot_errors[1] <= f32::EPSILON as f64 && dot_errors[1] <= dot_errors[0],
+        "dot-precision-test failed: relative dot errors f32 {:e}, f64 {:e}",
+        dot_errors[0],
+        dot_errors[1]
+    );
+
+    // The solve: with f64 dots a reported convergence is not further from the truth.
+    let (f32_result, f32_true) = solve(Precision::F32).expect("dot-precision-test: F32 solve");
+    let (f64_result, f64_true) = solve(Precision::F64).expect("dot-precision-test: F64 solve");
+    assert!(
+        f64_true <= 1.05 * f32_true,
+        "dot-precision-test failed: true residual f32 dots {f32_true:e}, f64 dots {f64_true:e}"
+    );
+
+    println!(
+        "DotPrecisionTest OK ({}): dot rel. error f32 {:.2e} / f64 {:.2e}; solve to {rel_tol:e}: f32 dots {} iterations, true residual {f32_true:.2e}; f64 dots {} iterations, true residual {f64_true:.2e}",
+        ctx.describe(),
+        dot_errors[0],
+        dot_errors[1],
+        f32_result.iterations,
+        f64_result.iterations
+    );
+}
+
+fn run_submit_every_test(ctx: &GpuContext) {
+    let a = laplacian_2d(16, 16);
+    let n = a.n_rows as usize;
+    let block_starts = uniform_block_starts(n, 1);
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 7) as f32 * 0.25).collect();
+    let rel_tol = 1e-5;
+    let solve = |submit_every: u32, residual_strategy: ResidualStrategy| {
+        let options = PcgOptions {
+            submit_every,
+            residual_strategy,
+            ..Default::default()
+        };
+        solve_test_system(
+            ctx,
+            &a.row_ptr,
+            &a.col_idx,
+            &a.values,
+            &block_starts,
+            &b,
+            500,
+            rel_tol,
+            &options,
+        )
+    };
+
+    for strategy in [ResidualStrategy::Recurrence, ResidualStrategy::AlwaysTrue] {
+        let (reference, x_reference) = solve(1, strategy)
+            .unwrap_or_else(|e| panic!("submit-every-test: {strategy:?} solve failed: {e}"));
+        let converged_at = reference.iterations;
+
+        // One batch that ends exactly at convergence: the very same solve.
+        let (whole, x_whole) = solve(converged_at as u32, strategy)
+            .unwrap_or_else(|e| panic!("submit-every-test: single-batch solve failed: {e}"));
+        assert_eq!(whole.iterations, converged_at);
+        assert_eq!(whole.residual_history, reference.residual_history);
+        assert!(
+            x_whole == x_reference,
+            "submit-every-test failed: {strategy:?} x differs for submit_every = {converged_at}"
+        );
+
+        // Other batch sizes run on to the end of the batch that converged; the iterations
+        // they share with the unbatched solve are bit-identical.
+        for submit_every in [4usize, 7] {
+            let (batched, x_batched) = solve(submit_every as u32, strategy)
+                .unwrap_or_else(|e| panic!("submit-every-test: submit_every {submit_every}: {e}"));
+            assert_eq!(
+                batched.iterations,
+                converged_at.next_multiple_of(submit_every).min(500),
+                "submit-every-test failed: {strategy:?} submit_every {submit_every}"
+            );
+            assert_eq!(
+                batched.residual_history[..converged_at],
+                reference.residual_history[..],
+                "submit-every-test failed: {strategy:?} submit_every {submit_every} history"
+            );
+            let diff = compare(&x_reference, &x_batched);
+            assert!(
+                diff.rel_l2_diff <= 1e-3,
+                "submit-every-test failed: {strategy:?} submit_every {submit_every}: {diff:?}"
+            );
+        }
+    }
+
+    assert!(solve(0, ResidualStrategy::Recurrence).is_err());
+    let err = solve(4, ResidualStrategy::TrueEvery(5)).expect_err("TrueEvery accepted");
+    assert!(
+        err.contains("submit_every"),
+        "submit-every-test failed: {err}"
+    );
+
+    println!(
+        "SubmitEveryTest OK: submit_every 1 / 4 / 7 / converged-at agree (bitwise over the shared iterations) for Recurrence and AlwaysTrue"
+    );
+}
+
+fn run_subgroup_dot_test(ctx: &GpuContext) {
+    // Precedence: config > environment > adapter report; 0 = tree everywhere.
+    let select = select_dot_subgroup_size;
+    assert_eq!(select(Some(0), Some("16"), true, (32, 32)), Ok(0));
+    assert_eq!(select(Some(8), Some("16"), true, (32, 32)), Ok(8));
+    assert_eq!(select(None, Some(" 16 "), true, (32, 32)), Ok(16));
+    assert_eq!(select(None, Some("0"), true, (32, 32)), Ok(0));
+    assert_eq!(select(None, None, true, (32, 32)), Ok(32));
+    assert_eq!(select(None, None, true, (16, 32)), Ok(0));
+    assert_eq!(select(None, None, false, (32, 32)), Ok(0));
+    assert!(select(Some(8), None, false, (0, 0)).is_err());
+    assert!(select(Some(12), None, true, (32, 32)).is_err());
+    assert!(select(None, Some("eight"), true, (8, 8)).is_err());
+
+    let info = ctx.adapter.get_info();
+    let has_subgroups = ctx.features.contains(Features::SUBGROUP);
+
+    // Assumed sizes to check: the tree, and every power of two up to the smallest size
+    // the adapter can run (never larger than the actual one, so always correct).
+    let mut overrides = vec![0u32];
+    if has_subgroups {
+        overrides.extend((0..=info.subgroup_min_size.max(1).ilog2()).map(|k| 1u32 << k));
+    }
+
+    let n_max = 100_003usize;
+    let a: Vec<f32> = (0..n_max).map(|i| 1.0 + (i % 13) as f32 * 0.125).collect();
+    let b: Vec<f32> = (0..n_max).map(|i| 0.5 + (i % 7) as f32 * 0.25).collect();
+    let ab: Vec<f32> = a.iter().zip(&b).flat_map(|(a, b)| [*a, *b]).collect();
+    let a_gpu = ctx.create_storage_buffer("subgroup dot a", &a, BufferUsages::empty());
+    let b_gpu = ctx.create_storage_buffer("subgroup dot b", &b, BufferUsages::empty());
+    let ab_gpu = ctx.create_storage_buffer("subgroup dot ab", &ab, BufferUsages::empty());
+
+    let mut exec = DotScalarExecutor::create(ctx, n_max, 2);
+    for &subgroup_size in &overrides {
+        exec.set_dot_subgroup_size(ctx, Some(subgroup_size))
+            .unwrap_or_else(|e| panic!("subgroup-dot-test: size {subgroup_size}: {e}"));
+        assert_eq!(exec.dot_subgroup_size(), subgroup_size);
+
+        for n in [1usize, 31, 256, 257, 4099, n_max] {
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&CommandEncoderDescriptor { label: None });
+            exec.encode_dot_scalar_into(
+                ctx,
+                &mut encoder,
+                &a_gpu.buffer,
+                &b_gpu.buffer,
+                n as u32,
+                0,
+            );
+            exec.encode_dot_scalar_interleaved_into(ctx, &mut encoder, &ab_gpu.buffer, n as u32, 1);
+            exec.encode_copy_scalar_results_to_readback(&mut encoder);
+            ctx.queue.submit([encoder.finish()]);
+            let got = executor::block_on(exec.readback_scalar_results(ctx));
+
+            let exact = reference::dot(&a[..n], &b[..n]) as f64;
+            for value in got {
+                let rel = (value as f64 - exact).abs() / exact;
+                assert!(
+                    rel <= 1e-5,
+                    "subgroup-dot-test failed: subgroup size {subgroup_size}, n={n}: {value} vs {exact}"
+                );
+            }
+        }
+    }
+
+    // An explicit subgroup size the device cannot honor is refused, not ignored.
+    if !has_subgroups {
+        let err = exec
+            .set_dot_subgroup_size(ctx, Some(32))
+            .expect_err("accepted without SUBGROUP");
+        assert!(err.contains("SUBGROUP"), "subgroup-dot-test failed: {err}");
+        assert_eq!(exec.dot_subgroup_size(), 0);
+    }
+
+    println!(
+        "SubgroupDotTest OK: selection rules hold; sums correct for assumed subgroup sizes {overrides:?}{}",
+        if has_subgroups {
+            format!(
+                " (adapter reports {}..={})",
+                info.subgroup_min_size, info.subgroup_max_size
+            )
+        } else {
+            " (no SUBGROUP on this device: tree reduce only)".to_string()
+        }
+    );
+}
+
+fn run_iter_solve_test(ctx: &GpuContext) {
+    let a = laplacian_2d(40, 40);
+    let n = a.n_rows as usize;
+    let block_starts = uniform_block_starts(n, 6);
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 5) as f32 * 0.25).collect();
+    let x0 = vec![0.0f32; n];
+
+    for strategy in [ResidualStrategy::Recurrence, ResidualStrategy::AlwaysTrue] {
+        let options = PcgOptions {
+            residual_strategy: strategy,
+            ..Default::default()
+        };
+        let solver = PcgSolver::new(
+            ctx,
+            &a,
+            &block_starts,
+            &SolveConfig::default()
+                .max_iters(1000)
+                .tol(1e-4)
+                .options(options),
+        )
+        .unwrap_or_else(|e| panic!("iter-solve-test: {e}"));
+
+        let mut x_batch = x0.clone();
+        let batch = solver
+            .solve(&b, &mut x_batch)
+            .unwrap_or_else(|e| panic!("iter-solve-test: batch solve failed: {e}"));
+
+        let mut steps = solver
+            .iter_solve(&b, &x0)
+            .unwrap_or_else(|e| panic!("iter-solve-test: {e}"));
+        let mut history = Vec::new();
+        for step in steps.by_ref() {
+            let step = step.unwrap_or_else(|e| panic!("iter-solve-test: step failed: {e}"));
+            assert_eq!(step.iteration, history.len() + 1);
+            assert!(step.x.is_none());
+            assert_eq!(step.converged, step.iteration == batch.iterations);
+            history.push(step.residual_norm);
+        }
+        let x_steps = steps
+            .solution()
+            .expect("iter-solve-test: solution readback");
+
+        assert_eq!(
+            history, batch.residual_history,
+            "iter-solve-test failed ({strategy:?}): residual histories differ"
+        );
+        assert_eq!(steps.iterations(), batch.iterations);
+        assert_eq!(
+            x_steps, x_batch,
+            "iter-solve-test failed ({strategy:?}): solutions differ"
+        );
+
+        // With iterates, every step carries x and the last one is the solution.
+        let last = solver
+            .iter_solve(&b, &x0)
+            .unwrap_or_else(|e| panic!("iter-solve-test: {e}"))
+            .with_iterates()
+            .last()
+            .expect("iter-solve-test: no steps")
+            .unwrap_or_else(|e| panic!("iter-solve-test: step failed: {e}"));
+        assert!(last.converged);
+        assert_eq!(last.x.as_deref(), Some(&x_batch[..]));
+    }
+
+    // Out of iterations: after max_iter steps the stream ends in the batch solve's error.
+    let solver = PcgSolver::new(
+        ctx,
+        &a,
+        &block_starts,
+        &SolveConfig::default().max_iters(3).tol(1e-4),
+    )
+    .unwrap_or_else(|e| panic!("iter-solve-test: {e}"));
+    let items: Vec<_> = solver
+        .iter_solve(&b, &x0)
+        .unwrap_or_else(|e| panic!("iter-solve-test: {e}"))
+        .collect();
+    assert_eq!(items.len(), 4);
+    assert!(
+        items[..3]
+            .iter()
+            .all(|s| s.as_ref().is_ok_and(|s| !s.converged))
+    );
+    let err = items[3]
+        .as_ref()
+        .expect_err("iter-solve-test: stream did not fail");
+    assert!(
+        err.contains("did not converge in 3"),
+        "iter-solve-test failed: {err}"
+    );
+
+    // Options the stepper does not implement are refused up front.
+    let options = PcgOptions {
+        trace_iterations: true,
+        ..Default::default()
+    };
+    let solver = PcgSolver::new(
+        ctx,
+        &a,
+        &block_starts,
+        &SolveConfig::default()
+            .max_iters(1000)
+            .tol(1e-4)
+            .options(options),
+    )
+    .unwrap_or_else(|e| panic!("iter-solve-test: {e}"));
+    assert!(solver.iter_solve(&b, &x0).is_err());
+
+    println!(
+        "IterSolveTest OK: n={n}, stepping matches the batch solve bitwise (Recurrence and AlwaysTrue)"
+    );
+}
+
+fn run_workspace_test(ctx: &GpuContext) {
+    let a = laplacian_2d(30, 30);
+    let n = a.n_rows as usize;
+    let block_starts = uniform_block_starts(n, 6);
+    let rhs: Vec<Vec<f32>> = (0..3)
+        .map(|k| (0..n).map(|i| 1.0 + ((i + k) % 5) as f32 * 0.25).collect())
+        .collect();
+
+    let solver = PcgSolver::new(
+        ctx,
+        &a,
+        &block_starts,
+        &SolveConfig::default().max_iters(1000).tol(1e-4),
+    )
+    .unwrap_or_else(|e| panic!("workspace-test: {e}"));
+
+    // The regular solve allocates its vectors (and the counter sees them).
+    let before = ctx.buffers_created();
+    let expected: Vec<(PcgResult, Vec<f32>)> = rhs
+        .iter()
+        .map(|b| {
+            let mut x = vec![0.0f32; n];
+            let result = solver
+                .solve(b, &mut x)
+                .unwrap_or_else(|e| panic!("workspace-test: solve failed: {e}"));
+            (result, x)
+        })
+        .collect();
+    let allocating = ctx.buffers_created() - before;
+    assert!(
+        allocating >= 5 * rhs.len() as u64,
+        "workspace-test failed: solve created only {allocating} buffers"
+    );
+
+    // Through the workspace: nothing is allocated once it exists, and every solve
+    // matches the regular one bitwise.
+    let mut workspace = SolverWorkspace::new(ctx, n);
+    assert_eq!(workspace.n(), n);
+    assert_eq!(SolverWorkspace::size_in_bytes(n), (6 * n * 4 + 4) as u64);
+    let mut bound = solver
+        .with_workspace(&mut workspace)
+        .unwrap_or_else(|e| panic!("workspace-test: {e}"));
+
+    let before = ctx.buffers_created();
+    for (b, (expected, x_expected)) in rhs.iter().zip(&expected) {
+        let mut x = vec![0.0f32; n];
+        let result = bound
+            .solve(b, &mut x)
+            .unwrap_or_else(|e| panic!("workspace-test: workspace solve failed: {e}"));
+        assert_eq!(result.iterations, expected.iterations);
+        assert_eq!(result.residual_history, expected.residual_history);
+        assert_eq!(
+            result.dispatches, expected.dispatches,
+            "workspace-test failed: dispatch counts differ"
+        );
+        assert_eq!(
+            &x, x_expected,
+            "workspace-test failed: workspace solution differs from the regular solve"
+        );
+    }
+    let created = ctx.buffers_created() - before;
+    assert_eq!(
+        created, 0,
+        "workspace-test failed: {created} buffers created by workspace solves"
+    );
+
+    // A workspace of the wrong size is rejected.
+    let mut small = SolverWorkspace::new(ctx, n - 1);
+    assert!(solver.with_workspace(&mut small).is_err());
+
+    println!(
+        "WorkspaceTest OK: n={n}, {} solves through a {}-byte workspace, 0 buffers created (regular solves: {allocating})",
+        rhs.len(),
+        SolverWorkspace::size_in_bytes(n)
+    );
+}
+
+fn run_solve_config_test(ctx: &GpuContext) {
+    let a = laplacian_2d(32, 32);
+    let n = a.n_rows as usize;
+    let block_starts = uniform_block_starts(n, 6);
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 5) as f32 * 0.25).collect();
+    let b_norm = reference::dot(&b, &b).sqrt();
+    let solve = |config: &SolveConfig| -> Result<(PcgResult, Vec<f32>), String> {
+        let solver = PcgSolver::new(ctx, &a, &block_starts, config)?;
+        let mut x = vec![0.0f32; n];
+        let result = solver.solve(&b, &mut x)?;
+        Ok((result, x))
+    };
+    let true_rel_residual = |x: &[f32]| {
+        let mut ax = vec![0.0f32; n];
+        reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, x, &mut ax);
+        let r: Vec<f32> = b.iter().zip(&ax).map(|(b, ax)| b - ax).collect();
+        reference::dot(&r, &r).sqrt() / b_norm
+    };
+
+    // The documented defaults, and they solve the problem.
+    let config = SolveConfig::default();
+    assert_eq!(config.max_iters, 1000);
+    assert_eq!(config.rel_tol, 1e-5);
+    assert_eq!(config.abs_tol, 0.0);
+    assert_eq!(
+        config.options.stopping_criterion,
+        StoppingCriterion::Relative
+    );
+    let (default_result, x) =
+        solve(&config).unwrap_or_else(|e| panic!("solve-config-test: default config: {e}"));
+    let rel = true_rel_residual(&x);
+    assert!(
+        rel < 1e-4,
+        "solve-config-test failed: default config left a relative residual of {rel:e}"
+    );
+
+    // Overrides take effect.
+    let (loose, _) = solve(&SolveConfig::default().tol(1e-2))
+        .unwrap_or_else(|e| panic!("solve-config-test: tol(1e-2): {e}"));
+    assert!(
+        loose.iterations < default_result.iterations,
+        "solve-config-test failed: tol(1e-2) took {} iterations, default {}",
+        loose.iterations,
+        default_result.iterations
+    );
+    let err = solve(&SolveConfig::default().max_iters(3))
+        .err()
+        .unwrap_or_else(|| panic!("solve-config-test failed: max_iters(3) converged"));
+    assert!(
+        err.contains("did not converge in 3 iterations"),
+        "solve-config-test: unexpected error: {err}"
+    );
+    let (absolute, _) = solve(&SolveConfig::default().abs_tol(0.5 * b_norm as f32))
+        .unwrap_or_else(|e| panic!("solve-config-test: abs_tol: {e}"));
+    assert!(absolute.residual_norm <= 0.5 * b_norm as f32);
+    assert!(absolute.iterations < loose.iterations);
+
+    let reduction = SolveConfig::default().reduction_factor(1e-2);
+    assert_eq!(
+        reduction.options.stopping_criterion,
+        StoppingCriterion::ReductionFactor(1e-2)
+    );
+    // x0 = 0, so ||r0|| = ||b|| and a factor of 1e-2 is tol(1e-2).
+    let (reduced, _) =
+        solve(&reduction).unwrap_or_else(|e| panic!("solve-config-test: reduction_factor: {e}"));
+    assert_eq!(reduced.iterations, loose.iterations);
+    assert_eq!(
+        reduction.relative().options.stopping_criterion,
+        StoppingCriterion::Relative
+    );
+
+    let strategy = SolveConfig::default().residual_strategy(ResidualStrategy::TrueEvery(5));
+    assert_eq!(
+        strategy.options.residual_strategy,
+        ResidualStrategy::TrueEvery(5)
+    );
+    let f64_dots = SolveConfig::default().precision(Precision::F64);
+    match solve(&f64_dots) {
+        Ok((result, x)) => {
+            assert!(Precision::F64.supported_by(ctx));
+            assert!(result.iterations > 0 && true_rel_residual(&x) < 1e-4);
+        }
+        Err(e) => assert!(
+            !Precision::F64.supported_by(ctx),
+            "solve-config-test failed: f64 dots on a SHADER_F64 device: {e}"
+        ),
+    }
+
+    // The same config drives SSOR-CG.
+    let mut x = vec![0.0f32; n];
+    let ssor = SsorOptions::default();
+    assert!(
+        ssor_cg_csr_wgpu(
+            ctx,
+            &a,
+            &b,
+            &mut x,
+            &SolveConfig::default().max_iters(2),
+            &ssor
+        )
+        .is_err()
+    );
+    let result = ssor_cg_csr_wgpu(ctx, &a, &b, &mut x, &SolveConfig::default(), &ssor)
+        .unwrap_or_else(|e| panic!("solve-config-test: SSOR-CG: {e}"));
+    // Same stopping rule as PCG: ||r|| <= rel_tol * ||b||, or abs_tol, on both variants.
+    let b_norm = reference::dot(&b, &b).sqrt();
+    for eisenstat in [false, true] {
+        let ssor = SsorOptions {
+            eisenstat,
+            ..Default::default()
+        };
+        let ssor_solve = |config: &SolveConfig| {
+            let mut x = vec![0.0f32; n];
+            ssor_cg_csr_wgpu(ctx, &a, &b, &mut x, config, &ssor)
+                .unwrap_or_else(|e| panic!("solve-config-test: SSOR-CG ({eisenstat}): {e}"))
+        };
+        let relative = ssor_solve(&SolveConfig::default().tol(1e-4));
+        let last = *relative.residual_history.last().unwrap();
+        assert!(
+            last <= 1e-4 * b_norm && relative.residual_norm <= 2e-4 * b_norm,
+            "solve-config-test failed: SSOR-CG ({eisenstat}) stopped at ||r|| = {last:e}, true {:e}, ||b|| = {b_norm}",
+            relative.residual_norm
+        );
+        let abs_tol = 1e-2 * b_norm;
+        let absolute = ssor_solve(&SolveConfig::default().tol(0.0).abs_tol(abs_tol));
+        assert!(
+            absolute.iterations < relative.iterations
+                && *absolute.residual_history.last().unwrap() <= abs_tol,
+            "solve-config-test failed: SSOR-CG ({eisenstat}) abs_tol: {} iterations",
+            absolute.iterations
+        );
+        let reduced = ssor_solve(&SolveConfig::default().reduction_factor(1e-2));
+        assert!(*reduced.residual_history.last().unwrap() < 1e-2 * b_norm);
+    }
+    let unsupported = SolveConfig::default().residual_strategy(ResidualStrategy::TrueEvery(5));
+    match ssor_cg_csr_wgpu(ctx, &a, &b, &mut x, &unsupported, &ssor) {
+        Err(e) => assert!(
+            e.contains("residual_strategy"),
+            "solve-config-test failed: {e}"
+        ),
+        Ok(_) => panic!("solve-config-test failed: SSOR-CG accepted residual_strategy"),
+    }
+
+    // ... and GMRES, on its least-squares residual estimate.
+    let lu_blocks = build_lu_blocks_from_csr_block_starts_6(
+        n,
+        &a.row_ptr,
+        &a.col_idx,
+        &a.values,
+        &block_starts,
+    )
+    .unwrap();
+    let spmv_exec = SpmvExecutor::create(ctx, a.n_rows, &a.row_ptr, &a.col_idx, &a.values);
+    let vec_ops_exec = VecOpsExecutor::create(ctx);
+    let dot_scalar_exec = DotScalarExecutor::create(ctx, n, 1);
+    let block_jacobi_exec =
+        BlockJacobiExecutor::create(ctx, a.n_rows, 6, &lu_blocks, &block_starts)
+            .unwrap_or_else(|e| panic!("solve-config-test: {e}"));
+    let gmres_solve = |config: &SolveConfig| {
+        let mut x = vec![0.0f32; n];
+        gmres_block_jacobi_csr_wgpu(
+            n,
+            &b,
+            &mut x,
+            config,
+            ctx,
+            &spmv_exec,
+            &vec_ops_exec,
+            &dot_scalar_exec,
+            &block_jacobi_exec,
+            &GmresOptions::default(),
+            None,
+        )
+    };
+    let gmres_relative = gmres_solve(&SolveConfig::default().tol(1e-4))
+        .unwrap_or_else(|e| panic!("solve-config-test: GMRES: {e}"));
+    let last = *gmres_relative.residual_history.last().unwrap();
+    assert!(
+        last <= 1e-4 * b_norm && gmres_relative.residual_norm <= 2e-4 * b_norm,
+        "solve-config-test failed: GMRES stopped at estimate {last:e}, true {:e}, ||b|| = {b_norm}",
+        gmres_relative.residual_norm
+    );
+    let gmres_abs_tol = 1e-2 * b_norm;
+    let gmres_absolute = gmres_solve(&SolveConfig::default().tol(0.0).abs_tol(gmres_abs_tol))
+        .unwrap_or_else(|e| panic!("solve-config-test: GMRES abs_tol: {e}"));
+    assert!(
+        gmres_absolute.iterations < gmres_relative.iterations
+            && *gmres_absolute.residual_history.last().unwrap() <= gmres_abs_tol,
+        "solve-config-test failed: GMRES abs_tol: {} iterations",
+        gmres_absolute.iterations
+    );
+    let gmres_reduced = gmres_solve(&SolveConfig::default().reduction_factor(1e-2))
+        .unwrap_or_else(|e| panic!("solve-config-test: GMRES reduction_factor: {e}"));
+    assert!(*gmres_reduced.residual_history.last().unwrap() < 1e-2 * b_norm);
+    match gmres_solve(&SolveConfig::default().max_iters(2)) {
+        Err(e) => assert!(
+            e.contains("did not converge in 2 iterations"),
+            "solve-config-test: unexpected GMRES error: {e}"
+        ),
+        Ok(_) => panic!("solve-config-test failed: GMRES converged in max_iters(2)"),
+    }
+    match gmres_solve(&unsupported) {
+        Err(e) => assert!(
+            e.contains("residual_strategy"),
+            "solve-config-test failed: {e}"
+        ),
+        Ok(_) => panic!("solve-config-test failed: GMRES accepted residual_strategy"),
+    }
+
+    println!(
+        "SolveConfigTest OK: n={n}, default config converged in {} iterations (true rel residual {rel:.2e}), tol(1e-2): {}, abs_tol: {}, SSOR-CG: {}, GMRES tol(1e-4): {}",
+        default_result.iterations,
+        loose.iterations,
+        absolute.iterations,
+        result.iterations,
+        gmres_relative.iterations
+    );
+}
+
+fn run_buffer_pool_test(ctx: &GpuContext) {
+    // Buckets: powers of two from 256 bytes; a smaller request of the same bucket is a hit.
+    assert_eq!(BufferPool::bucket_size(0), 256);
+    assert_eq!(BufferPool::bucket_size(256), 256);
+    assert_eq!(BufferPool::bucket_size(4000), 4096);
+    let pool = Arc::new(BufferPool::new());
+    let first = pool.acquire::<f32>(ctx, "buffer-pool-test a", 1000);
+    assert_eq!((first.len, first.buffer.size()), (1000, 4096));
+    drop(first);
+    let stats = pool.stats();
+    assert_eq!(
+        (
+            stats.misses,
+            stats.hits,
+            stats.bytes_held,
+            stats.buffers_held
+        ),
+        (1, 0, 4096, 1)
+    );
+    let data: Vec<f32> = (0..900).map(|i| i as f32 * 0.5).collect();
+    let reused = pool.acquire_init(ctx, "buffer-pool-test b", &data);
+    let stats = pool.stats();
+    assert_eq!(
+        (
+            stats.misses,
+            stats.hits,
+            stats.bytes_held,
+            stats.bytes_in_use
+        ),
+        (1, 1, 0, 4096)
+    );
+    assert_eq!(
+        executor::block_on(ctx.readback(&reused)),
+        data,
+        "buffer-pool-test failed: acquire_init contents"
+    );
+    drop(reused);
+
+    // A limit on the bytes kept evicts what does not fit.
+    let small = Arc::new(BufferPool::with_max_bytes_held(4096));
+    let held: Vec<_> = (0..3)
+        .map(|_| small.acquire::<f32>(ctx, "buffer-pool-test c", 512))
+        .collect();
+    drop(held);
+    let stats = small.stats();
+    assert_eq!(
+        (stats.buffers_held, stats.bytes_held, stats.evictions),
+        (2, 4096, 1)
+    );
+    small.clear();
+    assert_eq!(
+        (small.stats().buffers_held, small.stats().bytes_held),
+        (0, 0)
+    );
+
+    // Handles acquired and dropped from several threads.
+    let shared = Arc::new(BufferPool::new());
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for len in [64, 300, 64, 1000] {
+                    drop(shared.acquire::<f32>(ctx, "buffer-pool-test threads", len));
+                }
+            });
+        }
+    });
+    let stats = shared.stats();
+    assert_eq!(stats.hits + stats.misses, 16);
+    assert_eq!(stats.bytes_in_use, 0);
+    assert!(stats.buffers_held as u64 == stats.misses && stats.misses <= 12);
+
+    // PCG: with the pool installed, repeated solves reuse b, x, r, p and z and give the
+    // same iterates as without it.
+    let a = laplacian_2d(24, 24);
+    let n = a.n_rows as usize;
+    let block_starts = uniform_block_starts(n, 6);
+    let solver = PcgSolver::new(ctx, &a, &block_starts, &SolveConfig::default())
+        .unwrap_or_else(|e| panic!("buffer-pool-test: PcgSolver::new: {e}"));
+    let solve = |seed: usize| {
+        let b: Vec<f32> = (0..n)
+            .map(|i| 1.0 + ((i + seed) % 5) as f32 * 0.25)
+            .collect();
+        let mut x = vec![0.0f32; n];
+        let before = ctx.buffers_created();
+        let result = solver
+            .solve(&b, &mut x)
+            .unwrap_or_else(|e| panic!("buffer-pool-test: PCG: {e}"));
+        (result.residual_history, x, ctx.buffers_created() - before)
+    };
+    let unpooled: Vec<_> = (0..3).map(solve).collect();
+
+    let pcg_pool = Arc::new(BufferPool::new());
+    assert!(ctx.set_buffer_pool(Some(Arc::clone(&pcg_pool))).is_none());
+    let pooled: Vec<_> = (0..3).map(solve).collect();
+    for (seed, (plain, pooled)) in unpooled.iter().zip(&pooled).enumerate() {
+        assert_eq!(
+            (&plain.0, &plain.1),
+            (&pooled.0, &pooled.1),
+            "buffer-pool-test failed: pooled PCG solve {seed} differs"
+        );
+    }
+    let pcg_stats = pcg_pool.stats();
+    assert_eq!(
+        (pcg_stats.misses, pcg_stats.hits, pcg_stats.bytes_in_use),
+        (5, 10, 0)
+    );
+    assert_eq!(pooled[0].2, unpooled[0].2);
+    assert_eq!(
+        pooled[1].2 + 5,
+        unpooled[1].2,
+        "buffer-pool-test failed: a pooled PCG solve still allocates its vectors"
+    );
+
+    // GMRES (restarted, nonsymmetric tridiagonal): same residuals and x, basis reused.
+    let n_gmres: usize = 300;
+    let mut row_ptr = vec![0u32];
+    let mut col_idx = Vec::new();
+    let mut values = Vec::new();
+    for i in 0..n_gmres {
+        for (j, v) in [(i.wrapping_sub(1), -1.3f32), (i, 2.05), (i + 1, -0.7)] {
+            if j < n_gmres {
+                col_idx.push(j as u32);
+                values.push(v);
+            }
+        }
+        row_ptr.push(col_idx.len() as u32);
+    }
+    let gmres_block_starts = uniform_block_starts(n_gmres, 6);
+    let b_gmres: Vec<f32> = (0..n_gmres).map(|i| 1.0 + (i % 7) as f32 * 0.5).collect();
+    let spmv_exec = SpmvExecutor::create(ctx, n_gmres as u32, &row_ptr, &col_idx, &values);
+    let vec_ops_exec = VecOpsExecutor::create(ctx);
+    let dot_scalar_exec = DotScalarExecutor::create(ctx, n_gmres, 1);
+    let lu_blocks = build_lu_blocks_from_csr_block_starts_6(
+        n_gmres,
+        &row_ptr,
+        &col_idx,
+        &values,
+        &gmres_block_starts,
+    )
+    .unwrap();
+    let block_jacobi_exec =
+        BlockJacobiExecutor::create(ctx, n_gmres as u32, 6, &lu_blocks, &gmres_block_starts)
+            .unwrap_or_else(|e| panic!("buffer-pool-test: {e}"));
+    let gmres = || {
+        let mut x = vec![0.0f32; n_gmres];
+        let result = gmres_block_jacobi_csr_wgpu(
+            n_gmres,
+            &b_gmres,
+            &mut x,
+            &SolveConfig::default().max_iters(2000),
+            ctx,
+            &spmv_exec,
+            &vec_ops_exec,
+            &dot_scalar_exec,
+            &block_jacobi_exec,
+            &GmresOptions {
+                restart: 5,
+                offload_basis: false,
+            },
+            None,
+        )
+        .unwrap_or_else(|e| panic!("buffer-pool-test: GMRES: {e}"));
+        (result.residual_history, x)
+    };
+    let gmres_pool = Arc::new(BufferPool::new());
+    ctx.set_buffer_pool(Some(Arc::clone(&gmres_pool)));
+    let gmres_pooled = [gmres(), gmres()];
+    let gmres_stats = gmres_pool.stats();
+    assert!(
+        Arc::ptr_eq(&ctx.set_buffer_pool(None).unwrap(), &gmres_pool),
+        "buffer-pool-test failed: set_buffer_pool did not return the installed pool"
+    );
+    let gmres_plain = gmres();
+    for pooled in &gmres_pooled {
+        assert_eq!(
+            *pooled, gmres_plain,
+            "buffer-pool-test failed: pooled GMRES solve differs"
+        );
+    }
+    // b, x, w, z and the m + 1 = 6 basis vectors.
+    assert_eq!((gmres_stats.misses, gmres_stats.hits), (10, 10));
+    assert!(ctx.buffer_pool().is_none());
+
+    // SSOR-CG, both variants: the second solve takes every vector from the pool.
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 5) as f32 * 0.25).collect();
+    let mut ssor_counts = Vec::new();
+    for eisenstat in [false, true] {
+        let ssor = SsorOptions {
+            eisenstat,
+            ..Default::default()
+        };
+        let ssor_cg = || {
+            let mut x = vec![0.0f32; n];
+            let result = ssor_cg_csr_wgpu(ctx, &a, &b, &mut x, &SolveConfig::default(), &ssor)
+                .unwrap_or_else(|e| panic!("buffer-pool-test: SSOR-CG ({eisenstat}): {e}"));
+            (result.residual_history, x)
+        };
+        let ssor_plain = ssor_cg();
+        let ssor_pool = Arc::new(BufferPool::new());
+        ctx.set_buffer_pool(Some(Arc::clone(&ssor_pool)));
+        let ssor_pooled = [ssor_cg(), ssor_cg()];
+        ctx.set_buffer_pool(None);
+        for pooled in &ssor_pooled {
+            assert_eq!(
+                *pooled, ssor_plain,
+                "buffer-pool-test failed: pooled SSOR-CG ({eisenstat}) solve differs"
+            );
+        }
+        // rhs, x, r, z, p, q and the residual; Eisenstat adds its scratch t and w.
+        let vectors = if eisenstat { 9 } else { 7 };
+        let stats = ssor_pool.stats();
+        assert_eq!(
+            (stats.misses, stats.hits, stats.bytes_in_use),
+            (vectors, vectors, 0),
+            "buffer-pool-test failed: SSOR-CG ({eisenstat}) pool stats"
+        );
+        ssor_counts.push(stats.hits);
+    }
+
+    println!(
+        "BufferPoolTest OK: PCG {} misses / {} hits over 3 solves ({} bytes held), GMRES {} misses / {} hits over 2 solves, SSOR-CG {:?} hits on the second solve, same iterates as unpooled",
+        pcg_stats.misses,
+        pcg_stats.hits,
+        pcg_stats.bytes_held,
+        gmres_stats.misses,
+        gmres_stats.hits,
+        ssor_counts
+    );
+}
+
+fn run_observer_test(ctx: &GpuContext) {
+    // Records the iteration numbers the solver reports, next to the norms.
+    #[derive(Default)]
+    struct IterationLog {
+        iters: Vec<u32>,
+        norms: VecObserver,
+    }
+    impl SolverObserver for IterationLog {
+        fn on_iteration(&mut self, iter: u32, residual_norm: f32) {
+            self.iters.push(iter);
+            self.norms.on_iteration(iter, residual_norm);
+        }
+    }
+    let counts_from_one = |iters: &[u32]| iters.iter().copied().eq(1..=iters.len() as u32);
+
+    // PCG: one call per iteration with the values of the residual history, also when
+    // iterations are batched into one readback.
+    let a = laplacian_2d(24, 24);
+    let n = a.n_rows as usize;
+    let block_starts = uniform_block_starts(n, 6);
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 5) as f32 * 0.25).collect();
+    let mut pcg_iterations = Vec::new();
+    for submit_every in [1, 4] {
+        let config = SolveConfig::default().options(PcgOptions {
+            submit_every,
+            ..PcgOptions::default()
+        });
+        let solver = PcgSolver::new(ctx, &a, &block_starts, &config)
+            .unwrap_or_else(|e| panic!("observer-test: PcgSolver::new: {e}"));
+        let mut log = IterationLog::default();
+        let mut x = vec![0.0f32; n];
+        let observed = solver
+            .solve_observed(&b, &mut x, Some(&mut log))
+            .unwrap_or_else(|e| panic!("observer-test: submit_every={submit_every}: {e}"));
+        assert_eq!(log.iters.len(), observed.iterations);
+        assert!(
+            counts_from_one(&log.iters),
+            "observer-test failed: submit_every={submit_every} reported iterations {:?}",
+            log.iters
+        );
+        assert_eq!(
+            log.norms.residual_norms, observed.residual_history,
+            "observer-test failed: submit_every={submit_every}: observed norms differ from the history"
+        );
+
+        let mut x_plain = vec![0.0f32; n];
+        let plain = solver
+            .solve(&b, &mut x_plain)
+            .unwrap_or_else(|e| panic!("observer-test: solve: {e}"));
+        assert_eq!(plain.residual_history, observed.residual_history);
+        assert_eq!(x_plain, x);
+        pcg_iterations.push(observed.iterations);
+    }
+
+    // GMRES: one call per Arnoldi step, counted across restarts.
+    let n_gmres: usize = 400;
+    let mut row_ptr = vec![0u32];
+    let mut col_idx = Vec::new();
+    let mut values = Vec::new();
+    for i in 0..n_gmres {
+        for (j, v) in [(i.wrapping_sub(1), -1.3f32), (i, 2.05), (i + 1, -0.7)] {
+            if j < n_gmres {
+                col_idx.push(j as u32);
+                values.push(v);
+            }
+        }
+        row_ptr.push(col_idx.len() as u32);
+    }
+    let gmres_block_starts = uniform_block_starts(n_gmres, 6);
+    let b_gmres: Vec<f32> = (0..n_gmres).map(|i| 1.0 + (i % 7) as f32 * 0.5).collect();
+    let spmv_exec = SpmvExecutor::create(ctx, n_gmres as u32, &row_ptr, &col_idx, &values);
+    let vec_ops_exec = VecOpsExecutor::create(ctx);
+    let dot_scalar_exec = DotScalarExecutor::create(ctx, n_gmres, 1);
+    let lu_blocks = build_lu_blocks_from_csr_block_starts_6(
+        n_gmres,
+        &row_ptr,
+        &col_idx,
+        &values,
+        &gmres_block_starts,
+    )
+    .unwrap();
+    let block_jacobi_exec =
+        BlockJacobiExecutor::create(ctx, n_gmres as u32, 6, &lu_blocks, &gmres_block_starts)
+            .unwrap_or_else(|e| panic!("observer-test: {e}"));
+    let mut log = IterationLog::default();
+    let mut x = vec![0.0f32; n_gmres];
+    let gmres = gmres_block_jacobi_csr_wgpu(
+        n_gmres,
+        &b_gmres,
+        &mut x,
+        &SolveConfig::default().max_iters(2000),
+        ctx,
+        &spmv_exec,
+        &vec_ops_exec,
+        &dot_scalar_exec,
+        &block_jacobi_exec,
+        &GmresOptions {
+            restart: 5,
+            offload_basis: false,
+        },
+        Some(&mut log),
+    )
+    .unwrap_or_else(|e| panic!("observer-test: GMRES: {e}"));
+    assert!(gmres.cycles > 1, "observer-test: GMRES(5) did not restart");
+    assert_eq!(log.iters.len(), gmres.iterations);
+    assert!(
+        counts_from_one(&log.iters),
+        "observer-test failed: GMRES reported iterations {:?}",
+        log.iters
+    );
+    assert_eq!(log.norms.residual_norms, gmres.residual_history);
+
+    let stderr = StderrObserver::new("pcg").every(5);
+    assert_eq!(
+        stderr.format_line(10, 0.5).as_deref(),
+        Some("pcg iter 10: residual 5e-1")
+    );
+    assert_eq!(stderr.format_line(3, 0.5), None);
+
+    println!(
+        "ObserverTest OK: PCG reported {} iterations (submit_every=4: {}), GMRES(5) {} steps over {} cycles",
+        pcg_iterations[0], pcg_iterations[1], gmres.iterations, gmres.cycles
+    );
+}
+
+fn run_solver_buffers_test(ctx: &GpuContext) {
+    let a = laplacian_2d(24, 24);
+    let n = a.n_rows as usize;
+    let block_starts = uniform_block_starts(n, 6);
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 7) as f32 * 0.125).collect();
+    let solver = PcgSolver::new(
+        ctx,
+        &a,
+        &block_starts,
+        &SolveConfig::default().max_iters(1000).tol(1e-5),
+    )
+    .unwrap_or_else(|e| panic!("solver-buffers-test: {e}"));
+    let read = |buffer: &wgpu::Buffer| {
+        executor::block_on(readback_to_vec::<f32>(
+            &ctx.device,
+            &ctx.queue,
+            buffer,
+            n,
+            Some("solver-buffers-test readback"),
+        ))
+    };
+
+    // Extra usages (here VERTEX, for drawing the solution) are on the vectors.
+    let mut workspace = SolverWorkspace::with_extra_usages(ctx, n, BufferUsages::VERTEX);
+    for buffer in [
+        workspace.solution_buffer(),
+        workspace.residual_buffer(),
+        workspace.direction_buffer(),
+    ] {
+        assert!(
+            buffer
+                .usage()
+                .contains(BufferUsages::VERTEX | BufferUsages::STORAGE),
+            "solver-buffers-test failed: usages {:?}",
+            buffer.usage()
+        );
+        assert_eq!(buffer.size(), (n * 4) as u64);
+    }
+    assert!(
+        !SolverWorkspace::new(ctx, n)
+            .solution_buffer()
+            .usage()
+            .contains(BufferUsages::VERTEX)
+    );
+
+    // After a solve: x on the device is bitwise the host solution, r the final residual.
+    let mut x = vec![0.0f32; n];
+    let result = solver
+        .with_workspace(&mut workspace)
+        .and_then(|mut bound| bound.solve(&b, &mut x))
+        .unwrap_or_else(|e| panic!("solver-buffers-test: solve failed: {e}"));
+    assert_eq!(
+        read(workspace.solution_buffer()),
+        x,
+        "solver-buffers-test failed: solution_buffer differs from the returned x"
+    );
+    let r = read(workspace.residual_buffer());
+    let r_norm = reference::dot(&r, &r).sqrt();
+    assert!(
+        (r_norm - result.residual_norm).abs() <= 1e-3 * result.residual_norm,
+        "solver-buffers-test failed: ||residual_buffer|| = {r_norm}, solve reported {}",
+        result.residual_norm
+    );
+
+    // Between steps: the buffer is the current iterate.
+    let mut steps = solver
+        .iter_solve(&b, &vec![0.0f32; n])
+        .unwrap_or_else(|e| panic!("solver-buffers-test: {e}"));
+    for _ in 0..3 {
+        steps
+            .next()
+            .expect("solver-buffers-test: iterator ended early")
+            .unwrap_or_else(|e| panic!("solver-buffers-test: {e}"));
+        let current = steps
+            .solution()
+            .unwrap_or_else(|e| panic!("solver-buffers-test: {e}"));
+        assert_eq!(
+            read(steps.solution_buffer()),
+            current,
+            "solver-buffers-test failed: solution_buffer differs from the iterate after step {}",
+            steps.iterations()
+        );
+    }
+    let p = read(steps.direction_buffer());
+    assert!(
+        p.iter().any(|v| *v != 0.0),
+        "solver-buffers-test failed: direction_buffer is zero mid-solve"
+    );
+
+    println!(
+        "SolverBuffersTest OK: n={n}, solution_buffer == x after {} iterations and between steps, VERTEX usage applied",
+        result.iterations
+    );
+}
+
+fn run_preconditioner_test(ctx: &GpuContext) {
+    // Laplacian with a varying diagonal, so D^-1 is not just a scaling.
+    let mut a = laplacian_2d(30, 30);
+    let n = a.n_rows as usize;
+    for i in 0..n {
+        for k in a.row_ptr[i] as usize..a.row_ptr[i + 1] as usize {
+            if a.col_idx[k] as usize == i {
+                a.values[k] += (i % 5) as f32 * 0.5;
+            }
+        }
+    }
+    let block_starts = uniform_block_starts(n, 6);
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 7) as f32 * 0.25).collect();
+    let (max_iter, rel_tol) = (1000, 1e-5);
+
+    let block = PcgSolver::new(
+        ctx,
+        &a,
+        &block_starts,
+        &SolveConfig::default().max_iters(max_iter).tol(rel_tol),
+    )
+    .unwrap_or_else(|e| panic!("preconditioner-test: {e}"));
+    let jacobi = DiagonalJacobiExecutor::from_csr(ctx, &a)
+        .unwrap_or_else(|e| panic!("preconditioner-test: {e}"));
+    let diagonal = PcgSolver::with_preconditioner(
+        ctx,
+        &a,
+        Box::new(jacobi),
+        &SolveConfig::default().max_iters(max_iter).tol(rel_tol),
+    )
+    .unwrap_or_else(|e| panic!("preconditioner-test: {e}"));
+
+    let b_norm = b.iter().map(|v| v * v).sum::<f32>().sqrt();
+    let mut iterations = Vec::new();
+    for (name, solver) in [("block", &block), ("diagonal", &diagonal)] {
+        let mut x = vec![0.0f32; n];
+        let result = solver
+            .solve(&b, &mut x)
+            .unwrap_or_else(|e| panic!("preconditioner-test: {name} solve failed: {e}"));
+
+        // The true residual confirms the solution, not just the recurrence.
+        let mut ax = vec![0.0f32; n];
+        reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, &x, &mut ax);
+        let true_residual = b
+            .iter()
+            .zip(&ax)
+            .map(|(b, ax)| (b - ax) * (b - ax))
+            .sum::<f32>()
+            .sqrt();
+        assert!(
+            true_residual <= 1e-4 * b_norm,
+            "preconditioner-test failed: {name}: ||b - A x|| = {true_residual} after {} iterations",
+            result.iterations
+        );
+
+        // The stepped path applies the same preconditioner.
+        let mut workspace = SolverWorkspace::new(ctx, n);
+        let mut x_ws = vec![0.0f32; n];
+        let stepped = solver
+            .with_workspace(&mut workspace)
+            .and_then(|mut bound| bound.solve(&b, &mut x_ws))
+            .unwrap_or_else(|e| panic!("preconditioner-test: {name} workspace solve failed: {e}"));
+        assert_eq!(stepped.residual_history, result.residual_history);
+        assert_eq!(stepped.dispatches, result.dispatches);
+        assert_eq!(
+            x_ws, x,
+            "preconditioner-test failed: {name}: stepped solution differs"
+        );
+
+        iterations.push(result.iterations);
+    }
+    assert!(
+        iterations[0] <= iterations[1],
+        "preconditioner-test failed: block Jacobi took {} iterations, diagonal {}",
+        iterations[0],
+        iterations[1]
+    );
+
+    // A preconditioner of the wrong size is rejected.
+    let small = DiagonalJacobiExecutor::create(ctx, n as u32 - 1, &vec![1.0; n - 1]);
+    assert!(
+        PcgSolver::with_preconditioner(
+            ctx,
+            &a,
+            Box::new(small),
+            &SolveConfig::default().max_iters(max_iter).tol(rel_tol)
+        )
+        .is_err()
+    );
+
+    println!(
+        "PreconditionerTest OK: n={n}, block Jacobi {} iterations, diagonal Jacobi {} iterations",
+        iterations[0], iterations[1]
+    );
+}
+
+fn run_precomputed_z0_test(ctx: &GpuContext) {
+    let a = laplacian_2d(30, 30);
+    let n = a.n_rows as usize;
+    let block_starts = uniform_block_starts(n, 6);
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 7) as f32 * 0.2).collect();
+    let lu_blocks = build_lu_blocks_from_csr_block_starts_6(
+        n,
+        &a.row_ptr,
+        &a.col_idx,
+        &a.values,
+        &block_starts,
+    )
+    .expect("precomputed-z0-test: LU blocks");
+
+    // x0 = 0, so r0 = b and z0 = M^-1 b, computed here with the kernel the solve uses.
+    let block_jacobi_exec =
+        BlockJacobiExecutor::create(ctx, a.n_rows, 6, &lu_blocks, &block_starts)
+            .unwrap_or_else(|e| panic!("precomputed-z0-test: {e}"));
+    let b_gpu = ctx.create_storage_buffer("precomputed z0 b", &b, BufferUsages::empty());
+    let z_gpu =
+        ctx.create_storage_buffer_uninit::<f32>("precomputed z0 z", n, BufferUsages::COPY_SRC);
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor { label: None });
+    block_jacobi_exec.encode_apply(ctx, &mut encoder, &b_gpu.buffer, &z_gpu.buffer);
+    ctx.queue.submit([encoder.finish()]);
+    let z0_gpu = executor::block_on(ctx.readback(&z_gpu));
+
+    let solve = |z0: Option<Vec<f32>>| {
+        let options = PcgOptions {
+            initial_preconditioned_residual: z0,
+            ..Default::default()
+        };
+        solve_test_system(
+            ctx,
+            &a.row_ptr,
+            &a.col_idx,
+            &a.values,
+            &block_starts,
+            &b,
+            1000,
+            1e-5,
+            &options,
+        )
+    };
+    let (internal, x_internal) =
+        solve(None).unwrap_or_else(|e| panic!("precomputed-z0-test: solve failed: {e}"));
+    let (supplied, x_supplied) = solve(Some(z0_gpu))
+        .unwrap_or_else(|e| panic!("precomputed-z0-test: solve with z0 failed: {e}"));
+
+    assert_eq!(
+        supplied.residual_history, internal.residual_history,
+        "precomputed-z0-test failed: GPU trajectories differ"
+    );
+    assert_eq!(
+        x_supplied, x_internal,
+        "precomputed-z0-test failed: GPU solutions differ"
+    );
+    assert_eq!(supplied.dispatches + 1, internal.dispatches);
+
+    // Same on the CPU reference path, with its own apply.
+    let cpu_solve = |z0: Option<Vec<f32>>| {
+        let options = PcgOptions {
+            initial_preconditioned_residual: z0,
+            ..Default::default()
+        };
+        let mut x = vec![0.0f32; n];
+        let result = reference::pcg_block_jacobi_csr_cpu(
+            n,
+            &a.row_ptr,
+            &a.col_idx,
+            &a.values,
+            &lu_blocks,
+            &block_starts,
+            &b,
+            &mut x,
+            1000,
+            1e-5,
+            0.0,
+            &options,
+        )
+        .unwrap_or_else(|e| panic!("precomputed-z0-test: CPU solve failed: {e}"));
+        (result, x)
+    };
+    let mut z0_cpu = vec![0.0f32; n];
+    reference::block_jacobi_apply_6(&lu_blocks, &block_starts, &b, &mut z0_cpu);
+    let (cpu_internal, x_cpu_internal) = cpu_solve(None);
+    let (cpu_supplied, x_cpu_supplied) = cpu_solve(Some(z0_cpu));
+    assert_eq!(cpu_supplied.residual_history, cpu_internal.residual_history);
+    assert_eq!(x_cpu_supplied, x_cpu_internal);
+
+    // A z0 of the wrong length is refused.
+    let err = solve(Some(vec![0.0; n - 1])).expect_err("precomputed-z0-test: short z0 accepted");
+    assert!(
+        err.contains("initial_preconditioned_residual len"),
+        "precomputed-z0-test failed: {err}"
+    );
+
+    println!(
+        "PrecomputedZ0Test OK: n={n}, supplied z0 reproduces the trajectory ({} GPU / {} CPU iterations), one dispatch fewer",
+        internal.iterations, cpu_internal.iterations
+    );
+}
+
+fn run_timestamp_period_test(ctx: &GpuContext) {
+    let Some(mut timer) = GpuTimer::create(ctx, 2) else {
+        println!("TimestampPeriodTest SKIPPED: device has no timestamp query support");
+        return;
+    };
+    let reported = ctx.queue.get_timestamp_period();
+    assert_eq!(timer.timestamp_period(), reported);
+
+    // Time one dot so the two ticks are apart.
+    let n = 1 << 18;
+    let v = vec![1.0f32; n];
+    let v_gpu = ctx.create_storage_buffer("timestamp period v", &v, BufferUsages::empty());
+    let dot_exec = DotScalarExecutor::create(ctx, n, 1);
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor { label: None });
+    timer.write_timestamp(&mut encoder);
+    dot_exec.encode_dot_scalar_into(ctx, &mut encoder, &v_gpu.buffer, &v_gpu.buffer, n as u32, 0);
+    timer.write_timestamp(&mut encoder);
+    timer.encode_resolve(&mut encoder);
+    ctx.queue.submit([encoder.finish()]);
+    let ticks = executor::block_on(timer.read_ticks(ctx));
+    let reported_ms = timer.ticks_to_ms(ticks[0], ticks[1]);
+
+    // Same ticks, three times the period: three times the time.
+    let overridden = reported * 3.0;
+    timer
+        .set_timestamp_period(overridden)
+        .unwrap_or_else(|e| panic!("timestamp-period-test: {e}"));
+    assert_eq!(timer.timestamp_period(), overridden);
+    let overridden_ms = timer.ticks_to_ms(ticks[0], ticks[1]);
+    assert!(
+        (overridden_ms - 3.0 * reported_ms).abs() <= 1e-9 * overridden_ms.max(1.0),
+        "timestamp-period-test failed: {overridden_ms} ms vs 3 x {reported_ms} ms"
+    );
+    for bad in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+        assert!(timer.set_timestamp_period(bad).is_err());
+    }
+    assert_eq!(timer.timestamp_period(), overridden);
+
+    // Through the solve: the override is used and reported with the timings.
+    let n = 2048;
+    let (row_ptr, col_idx, values) = tridiagonal_test_matrix(n);
+    let block_starts = uniform_block_starts(n, 6);
+    let b = vec![1.0f32; n];
+    let solve = |timestamp_period_ns| {
+        let options = PcgOptions {
+            timing: true,
+            timestamp_period_ns,
+            ..Default::default()
+        };
+        solve_test_system(
+            ctx,
+            &row_ptr,
+            &col_idx,
+            &values,
+            &block_starts,
+            &b,
+            2000,
+            1e-5,
+            &options,
+        )
+    };
+    let (result, _) = solve(None).unwrap_or_else(|e| panic!("timestamp-period-test: {e}"));
+    let timings = result.timings.expect("timestamp-period-test: no timings");
+    assert_eq!(timings.timestamp_period_ns, reported);
+    let (result, _) =
+        solve(Some(overridden)).unwrap_or_else(|e| panic!("timestamp-period-test: {e}"));
+    let timings = result.timings.expect("timestamp-period-test: no timings");
+    assert_eq!(timings.timestamp_period_ns, overridden);
+    assert!(solve(Some(0.0)).is_err());
+
+    println!(
+        "TimestampPeriodTest OK: reported {reported} ns/tick, {reported_ms:.4} ms -> {overridden_ms:.4} ms at {overridden} ns/tick"
+    );
+}
+
+fn run_reduction_factor_test(device: &SolverDevice) {
+    let a = laplacian_2d(30, 30);
+    let n = a.n_rows as usize;
+    let block_starts = uniform_block_starts(n, 6);
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 7) as f32 * 0.2).collect();
+    let solve = |x0: &[f32], rel_tol: f32, criterion| {
+        let mut x = x0.to_vec();
+        let options = PcgOptions {
+            stopping_criterion: criterion,
+            ..Default::default()
+        };
+        device
+            .pcg_block_jacobi_csr(
+                &a,
+                &block_starts,
+                &b,
+                &mut x,
+                &SolveConfig::default()
+                    .max_iters(1000)
+                    .tol(rel_tol)
+                    .options(options.clone()),
+            )
+            .map(|result| (result, x))
+    };
+    let residual_norm = |x: &[f32]| {
+        let mut ax = vec![0.0f32; n];
+        reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, x, &mut ax);
+        let r: Vec<f32> = b.iter().zip(&ax).map(|(b, ax)| b - ax).collect();
+        reference::dot(&r, &r).sqrt()
+    };
+
+    // A good starting guess, as an outer Newton step would hand in: ||r0|| ~ 1e-2 ||b||.
+    let (_, x0) = solve(&vec![0.0; n], 1e-2, StoppingCriterion::Relative)
+        .unwrap_or_else(|e| panic!("reduction-factor-test: warm-up solve failed: {e}"));
+    let r0 = residual_norm(&x0);
+
+    // rel_tol is ignored by the reduction target; pass one that Relative would never reach.
+    let factor = 0.1;
+    let (reduced, x) = solve(&x0, 1e-12, StoppingCriterion::ReductionFactor(factor))
+        .unwrap_or_else(|e| panic!("reduction-factor-test: solve failed: {e}"));
+    let history = &reduced.residual_history;
+    let k = reduced.iterations;
+    assert!(
+        history[k - 1] < factor * r0,
+        "reduction-factor-test failed: stopped at ||r|| = {} >= {factor} * {r0}",
+        history[k - 1]
+    );
+    assert!(
+        k == 1 || history[k - 2] >= factor * r0 * (1.0 - 1e-4),
+        "reduction-factor-test failed: iteration {} already met the target",
+        k - 1
+    );
+    let true_reduction = residual_norm(&x) / r0;
+    assert!(
+        true_reduction < factor * 1.01,
+        "reduction-factor-test failed: true reduction {true_reduction:.3e}"
+    );
+
+    // Relative with the same number means 0.1 ||b||, which this x0 already satisfies.
+    let (relative, _) = solve(&x0, factor, StoppingCriterion::Relative)
+        .unwrap_or_else(|e| panic!("reduction-factor-test: relative solve failed: {e}"));
+    assert_eq!(relative.iterations, 1);
+    assert!(k > 1);
+
+    for bad in [0.0, -0.5, f32::NAN] {
+        assert!(solve(&x0, 1e-6, StoppingCriterion::ReductionFactor(bad)).is_err());
+    }
+
+    println!(
+        "ReductionFactorTest OK ({}): ||r0|| = {r0:.3e}, {k} iterations to ||r||/||r0|| = {:.3e} < {factor}; Relative({factor}) stops after {}",
+        device.describe(),
+        history[k - 1] / r0,
+        relative.iterations
+    );
+}
+
+fn run_per_block_residual_test(ctx: &GpuContext) {
+    let n = 10_007;
+    // Mostly small, with one hot region that the norms have to single out.
+    let r: Vec<f32> = (0..n)
+        .map(|i| {
+            let base = ((i * 37) % 101) as f32 * 1e-3 - 0.05;
+            if (6000..6012).contains(&i) {
+                base + 2.0
+            } else {
+                base
+            }
+        })
+        .collect();
+    let r_gpu = ctx.create_storage_buffer("per block residual r", &r, BufferUsages::empty());
+
+    // The block-Jacobi partition, and an irregular one with an empty and a long block.
+    let mut irregular = vec![0u32, 5, 5, 400, 401];
+    irregular.extend((1000..n as u32).step_by(997));
+    irregular.push(n as u32);
+    for block_starts in [uniform_block_starts(n, 6), irregular] {
+        let got = per_block_residual(ctx, &r_gpu.buffer, &block_starts);
+        let want = reference::per_block_norms(&r, &block_starts);
+        assert_eq!(got.len(), block_starts.len() - 1);
+        for (b, (g, w)) in got.iter().zip(&want).enumerate() {
+            assert!(
+                (g - w).abs() <= 1e-5 * w.max(1e-3),
+                "per-block-residual-test failed: block {b} ({}..{}): {g} vs {w}",
+                block_starts[b],
+                block_starts[b + 1]
+            );
+        }
+
+        let hottest = |norms: &[f32]| {
+            (0..norms.len())
+                .max_by(|&i, &j| norms[i].total_cmp(&norms[j]))
+                .unwrap()
+        };
+        let b = hottest(&got);
+        assert_eq!(b, hottest(&want));
+        assert!(
+            block_starts[b] < 6012 && block_starts[b + 1] > 6000,
+            "per-block-residual-test failed: hottest block {b} misses the hot rows"
+        );
+    }
+    assert!(per_block_residual(ctx, &r_gpu.buffer, &[0]).is_empty());
+
+    println!("PerBlockResidualTest OK: n={n}, per-block norms match the CPU for both partitions");
+}
+
+fn run_timer_fallback_test(ctx: &GpuContext) {
+    let n = 1 << 16;
+    let v = vec![1.0f32; n];
+    let v_gpu = ctx.create_storage_buffer("timer fallback v", &v, BufferUsages::empty());
+    let dot_exec = DotScalarExecutor::create(ctx, n, 1);
+
+    let mut report = Vec::new();
+    for mode in [TimestampMode::Encoder, TimestampMode::PassBoundary] {
+        let Some(timer) = GpuTimer::create_with_mode(ctx, 3, mode) else {
+            report.push(format!("{mode:?} unsupported"));
+            continue;
+        };
+        assert_eq!(timer.mode(), mode);
+
+        // t0 | one dot | t1 | four dots | t2
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor { label: None });
+        timer.write_timestamp(&mut encoder);
+        dot_exec.encode_dot_scalar_into(
+            ctx,
+            &mut encoder,
+            &v_gpu.buffer,
+            &v_gpu.buffer,
+            n as u32,
+            0,
+        );
+        timer.write_timestamp(&mut encoder);
+        for _ in 0..4 {
+            dot_exec.encode_dot_scalar_into(
+                ctx,
+                &mut encoder,
+                &v_gpu.buffer,
+                &v_gpu.buffer,
+                n as u32,
+                0,
+            );
+        }
+        timer.write_timestamp(&mut encoder);
+        timer.encode_resolve(&mut encoder);
+
+        let wall_start = std::time::Instant::now();
+        ctx.queue.submit([encoder.finish()]);
+        let ticks = executor::block_on(timer.read_ticks(ctx));
+        let wall_ms = wall_start.elapsed().as_secs_f64() * 1e3;
+
+        assert_eq!(ticks.len(), 3);
+        assert!(
+            ticks[0] <= ticks[1] && ticks[1] <= ticks[2],
+            "timer-fallback-test failed ({mode:?}): ticks out of order {ticks:?}"
+        );
+        let one = timer.ticks_to_ms(ticks[0], ticks[1]);
+        let four = timer.ticks_to_ms(ticks[1], ticks[2]);
+        assert!(
+            one > 0.0 && four > 0.0,
+            "timer-fallback-test failed ({mode:?}): non-positive durations {one} / {four} ms"
+        );
+        assert!(
+            one + four <= wall_ms * 1.5 + 1.0,
+            "timer-fallback-test failed ({mode:?}): {one} + {four} ms on the GPU but {wall_ms} ms wall clock"
+        );
+        report.push(format!("{mode:?} {one:.3} / {four:.3} ms"));
+    }
+    println!(
+        "TimerFallbackTest OK: one dot / four dots: {}",
+        report.join(", ")
+    );
+}
+
+fn run_pass_timing_test(ctx: &GpuContext) {
+    if !ctx.supports_timestamps() {
+        assert!(!ctx.enable_pass_timing(8));
+        assert!(ctx.pass_timer().is_none());
+        println!("PassTimingTest SKIPPED: device has no timestamp query support");
+        return;
+    }
+
+    // Factor + apply + partials + reduce are at least four passes; room for three.
+    let n = 1 << 16;
+    ctx.set_label_prefix("timed/");
+    assert!(ctx.enable_pass_timing(3));
+    let timings = {
+        let a = laplacian_1d(n);
+        let r: Vec<f32> = (0..n).map(|i| 1.0 + (i % 5) as f32).collect();
+        let r_gpu = ctx.create_storage_buffer("pass timing r", &r, BufferUsages::empty());
+        let z_gpu =
+            ctx.create_storage_buffer_uninit::<f32>("pass timing z", n, BufferUsages::empty());
+        let bj = BlockJacobiExecutor::from_csr(ctx, &a, 6)
+            .unwrap_or_else(|e| panic!("pass-timing-test: {e}"));
+        let dot = DotScalarExecutor::create(ctx, n, 1);
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("pass timing encoder"),
+            });
+        bj.encode_apply(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer);
+        dot.encode_dot_scalar_into(ctx, &mut encoder, &r_gpu.buffer, &z_gpu.buffer, n as u32, 0);
+        let timer = ctx
+            .pass_timer()
+            .expect("pass-timing-test: timer not installed");
+        assert_eq!(timer.len(), 3);
+        assert!(
+            timer.dropped() >= 1,
+            "pass-timing-test: no pass was dropped"
+        );
+        timer.encode_resolve(&mut encoder);
+        ctx.queue.submit(Some(encoder.finish()));
+        executor::block_on(timer.read_timings(ctx))
+            .unwrap_or_else(|e| panic!("pass-timing-test: {e}"))
+    };
+    ctx.set_label_prefix("");
+
+    let labels: Vec<&str> = timings.iter().map(|t| t.label.as_str()).collect();
+    assert_eq!(
+        labels,
+        [
+            "timed/block_lu_factor pass",
+            "timed/block_jacobi apply pass",
+            "timed/dot_partials pass"
+        ],
+        "pass-timing-test: unexpected passes"
+    );
+    let total_ns: u64 = timings.iter().map(|t| t.elapsed_ns).sum();
+    assert!(total_ns > 0, "pass-timing-test: all passes took 0 ns");
+    let timer = ctx
+        .disable_pass_timing()
+        .expect("pass-timing-test: timer gone");
+    assert!(
+        timer.is_empty() && timer.dropped() == 0,
+        "pass-timing-test: read did not reset"
+    );
+    assert!(ctx.pass_timer().is_none());
+
+    println!(
+        "PassTimingTest OK: {}",
+        timings
+            .iter()
+            .map(|t| format!("{} {:.3} ms", t.label, t.elapsed_ns as f64 * 1e-6))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+}
+
+fn run_reorth_test(device: &SolverDevice) {
+    let n = 48;
+    let a = strakos_test_matrix(n, 0.9);
+    let block_starts = uniform_block_starts(n, 1);
+    let b = vec![1.0f32; n];
+
+    let solve = |reorthogonalize: ReorthPolicy| {
+        let mut x = vec![0.0f32; n];
+        let options = PcgOptions {
+            reorthogonalize,
+            ..Default::default()
+        };
+        device
+            .pcg_block_jacobi_csr(
+                &a,
+                &block_starts,
+                &b,
+                &mut x,
+                &SolveConfig::default()
+                    .max_iters(50 * n)
+                    .tol(1e-5)
+                    .options(options.clone()),
+            )
+            .map(|result| (result, x))
+    };
+    let iterations = |policy: ReorthPolicy| {
+        let (result, x) =
+            solve(policy).unwrap_or_else(|e| panic!("reorth-test: solve ({policy:?}) failed: {e}"));
+
+        // Still a real solution: true relative residual, not just the recursive one.
+        let mut ax = vec![0.0f32; n];
+        reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, &x, &mut ax);
+        let r: Vec<f32> = b.iter().zip(&ax).map(|(b, ax)| b - ax).collect();
+        let rel = (reference::dot(&r, &r) / reference::dot(&b, &b)).sqrt();
+        assert!(
+            rel < 1e-4,
+            "reorth-test failed: {policy:?} true relative residual {rel:e}"
+        );
+        result.iterations
+    };
+
+    let plain = iterations(ReorthPolicy::None);
+    let window_4 = iterations(ReorthPolicy::Window(4));
+    let window_16 = iterations(ReorthPolicy::Window(16));
+    assert!(
+        window_4 < plain && window_16 + 3 <= plain,
+        "reorth-test failed: iterations None {plain}, Window(4) {window_4}, Window(16) {window_16}"
+    );
+    assert!(
+        solve(ReorthPolicy::Window(0)).is_err(),
+        "reorth-test failed: empty window accepted"
+    );
+
+    println!(
+        "ReorthTest OK ({}): Strakos n={n}, iterations None {plain}, Window(4) {window_4}, Window(16) {window_16}",
+        device.describe()
+    );
+}
+
+fn run_condition_estimate_test(device: &SolverDevice) {
+    // 1D Laplacian with point Jacobi (1x1 blocks): M^{-1} A = A / 2 has eigenvalues
+    // 1 - cos(k pi / (n + 1)), so kappa = (1 + cos(pi / (n+1))) / (1 - cos(pi / (n+1))).
+    let n = 100;
+    let a = laplacian_1d(n);
+    let block_starts = uniform_block_starts(n, 1);
+    let theta = std::f64::consts::PI / (n + 1) as f64;
+    let kappa = (1.0 + theta.cos()) / (1.0 - theta.cos());
+
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 3) as f32).collect();
+    let mut x = vec![0.0f32; n];
+    let options = PcgOptions {
+        estimate_condition_number: true,
+        ..Default::default()
+    };
+    let result = device
+        .pcg_block_jacobi_csr(
+            &a,
+            &block_starts,
+            &b,
+            &mut x,
+            &SolveConfig::default()
+                .max_iters(2000)
+                .tol(1e-5)
+                .options(options.clone()),
+        )
+        .unwrap_or_else(|e| panic!("condition-estimate-test: solve failed: {e}"));
+
+    let estimate = result
+        .estimated_condition_number
+        .expect("condition-estimate-test: no estimate");
+    let (lambda_min, lambda_max) = result.estimated_eigenvalue_range.unwrap();
+    assert!(
+        (0.5 * kappa..=1.5 * kappa).contains(&estimate),
+        "condition-estimate-test failed: estimate {estimate:.1} vs kappa {kappa:.1}"
+    );
+    assert!(
+        (lambda_max - (1.0 + theta.cos())).abs() < 1e-2,
+        "condition-estimate-test failed: lambda_max {lambda_max}"
+    );
+
+    // Off by default.
+    let mut x = vec![0.0f32; n];
+    let plain = device
+        .pcg_block_jacobi_csr(
+            &a,
+            &block_starts,
+            &b,
+            &mut x,
+            &SolveConfig::default().max_iters(2000).tol(1e-5),
+        )
+        .unwrap_or_else(|e| panic!("condition-estimate-test: solve failed: {e}"));
+    assert!(plain.estimated_condition_number.is_none());
+
+    println!(
+        "ConditionEstimateTest OK: {} iterations, kappa ~ {estimate:.1} (exact {kappa:.1}), \
+         lambda in [{lambda_min:.3e}, {lambda_max:.4}]",
+        result.iterations
+    );
+}
+
+fn run_negative_curvature_test(device: &SolverDevice) {
+    // -L (negative definite: p^T A p < 0 for the very first direction) and L - 1.5 I
+    // (spectrum about (-1.5, 2.5): indefinite, with a positive diagonal, so the point Jacobi
+    // preconditioner stays SPD).
+    let n = 60;
+    let shifted = |shift: f32, sign: f32| {
+        let mut a = laplacian_1d(n);
+        for row in 0..n {
+            for k in a.row_ptr[row] as usize..a.row_ptr[row + 1] as usize {
+                if a.col_idx[k] as usize == row {
+                    a.values[k] -= shift;
+                }
+                a.values[k] *= sign;
+            }
+        }
+        a
+    };
+    // Mostly high-frequency, where L - 1.5 I is positive: the first direction curves up.
+    let b: Vec<f32> = (0..n)
+        .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 } * (1.0 + 0.1 * (i % 4) as f32))
+        .collect();
+    let block_starts = uniform_block_starts(n, 1);
+    let options = PcgOptions::default();
+
+    for (what, a, first_iteration) in [
+        ("negative definite", shifted(0.0, -1.0), true),
+        ("indefinite", shifted(1.5, 1.0), false),
+    ] {
+        let mut x = vec![0.0f32; n];
+        let err = device
+            .pcg_block_jacobi_csr(
+                &a,
+                &block_starts,
+                &b,
+                &mut x,
+                &SolveConfig::default()
+                    .max_iters(500)
+                    .tol(1e-6)
+                    .options(options.clone()),
+            )
+            .expect_err("negative-curvature-test: solve on a non-SPD matrix succeeded");
+        assert!(
+            err.contains("negative curvature at iteration"),
+            "negative-curvature-test: {what}: unexpected error: {err}"
+        );
+        assert_eq!(
+            err.contains("iteration 1:"),
+            first_iteration,
+            "negative-curvature-test: {what}: detected at the wrong iteration: {err}"
+        );
+
+        // The stepped solver stops on the same check.
+        if let Some(ctx) = device.gpu() {
+            let solver = PcgSolver::new(
+                ctx,
+                &a,
+                &block_starts,
+                &SolveConfig::default()
+                    .max_iters(500)
+                    .tol(1e-6)
+                    .options(options.clone()),
+            )
+            .unwrap_or_else(|e| panic!("negative-curvature-test: {e}"));
+            let step_err = solver
+                .iter_solve(&b, &vec![0.0f32; n])
+                .unwrap_or_else(|e| panic!("negative-curvature-test: {e}"))
+                .find_map(Result::err)
+                .expect("negative-curvature-test: stepped solve on a non-SPD matrix succeeded");
+            assert!(
+                step_err.contains("negative curvature at iteration"),
+                "negative-curvature-test: {what}: stepped: {step_err}"
+            );
+        }
+        println!("  {what}: {err}");
+    }
 
-    let eps = 1e-6;
+    // The SPD control solves as before.
+    let mut x = vec![0.0f32; n];
+    device
+        .pcg_block_jacobi_csr(
+            &laplacian_1d(n),
+            &block_starts,
+            &b,
+            &mut x,
+            &SolveConfig::default()
+                .max_iters(500)
+                .tol(1e-5)
+                .options(options.clone()),
+        )
+        .unwrap_or_else(|e| panic!("negative-curvature-test: SPD solve failed: {e}"));
+
+    println!("NegativeCurvatureTest OK: non-SPD matrices reported, SPD control converged");
+}
+
+fn run_fixed_point_dot_test(device: &SolverDevice) {
+    // Products over five decades and both signs: an f32 sum of these depends on the order.
+    let n = 10_000;
+    let mut state = 0x2545_f491u32;
+    let mut next = move || {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (state >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0
+    };
+    let mut a = Vec::with_capacity(n);
+    let mut b = Vec::with_capacity(n);
+    for i in 0..n {
+        let decade = 10f32.powi((i % 5) as i32 - 3);
+        a.push(next() * decade * 10.0);
+        b.push(next());
+    }
+    let a_rev: Vec<f32> = a.iter().rev().copied().collect();
+    let b_rev: Vec<f32> = b.iter().rev().copied().collect();
+
+    let frac_bits = DEFAULT_FIXED_POINT_FRAC_BITS;
+    let cpu = SolverDevice::Cpu;
+    let run = |device: &SolverDevice, a: &[f32], b: &[f32]| {
+        device
+            .fixed_point_dot(a, b, frac_bits)
+            .unwrap_or_else(|e| panic!("fixed-point-dot-test ({}): {e}", device.describe()))
+    };
+
+    // Same bits on the device under test and the CPU backend, in either element order.
+    let on_cpu = run(&cpu, &a, &b);
+    for (what, value) in [
+        ("device", run(device, &a, &b)),
+        ("device, reversed", run(device, &a_rev, &b_rev)),
+        ("CPU, reversed", run(&cpu, &a_rev, &b_rev)),
+    ] {
+        assert_eq!(
+            value.to_bits(),
+            on_cpu.to_bits(),
+            "fixed-point-dot-test: {what}: {value:e} vs CPU {on_cpu:e}"
+        );
+    }
+
+    // Approximate: each product rounds to a multiple of 2^-frac_bits, plus the final f32 rounding.
+    let exact = reference::dot(&a, &b);
+    let bound = n as f32 * 0.5f32.powi(frac_bits as i32 + 1) + exact.abs() * 1e-6;
     assert!(
-        (alpha - exp_alpha).abs() < eps,
-        "alpha got {alpha}, expected {exp_alpha}"
+        (on_cpu - exact).abs() <= bound,
+        "fixed-point-dot-test: {on_cpu:e} vs float {exact:e} (bound {bound:e})"
+    );
+    let forward: f32 = a.iter().zip(&b).map(|(x, y)| x * y).sum();
+    let backward: f32 = a_rev.iter().zip(&b_rev).map(|(x, y)| x * y).sum();
+
+    // A product beyond ±2^(31 - frac_bits) is an error on both, as is too many fractional bits.
+    let mut a_big = a.clone();
+    let mut b_big = b.clone();
+    (a_big[17], b_big[17]) = (3000.0, 1.0);
+    (a_big[18], b_big[18]) = (-5000.0, 1.0);
+    for device in [device, &cpu] {
+        let err = device
+            .fixed_point_dot(&a_big, &b_big, frac_bits)
+            .expect_err("fixed-point-dot-test: out-of-range products accepted");
+        assert!(
+            err.contains("2 product(s)") && err.contains("clamped"),
+            "fixed-point-dot-test: {err}"
+        );
+    }
+    for device in [device, &cpu] {
+        let err = device
+            .fixed_point_dot(&a, &b, MAX_FIXED_POINT_FRAC_BITS + 1)
+            .expect_err("fixed-point-dot-test: frac_bits above the maximum accepted");
+        assert!(err.contains("exceeds"), "fixed-point-dot-test: {err}");
+    }
+
+    println!(
+        "FixedPointDotTest OK ({} vs CPU reference): n={n}, frac_bits={frac_bits}, bits {:#010x} \
+         in both orders on both ({on_cpu:e}; f64-accumulated {exact:e}; plain f32 forward {forward:e}, reversed {backward:e})",
+        device.describe(),
+        on_cpu.to_bits()
+    );
+}
+
+fn run_ritz_history_test(device: &SolverDevice) {
+    // 1D Laplacian: eigenvalues 2 - 2 cos(k pi / (n + 1)), k = 1..=n.
+    let n = 40;
+    let a = laplacian_1d(n);
+    let theta = std::f64::consts::PI / (n + 1) as f64;
+    let (lambda_min, lambda_max) = (2.0 - 2.0 * theta.cos(), 2.0 + 2.0 * theta.cos());
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 3) as f32).collect();
+
+    // Every T_k's extreme Ritz values lie inside [lambda_min, lambda_max] (up to f32
+    // rounding of the CG coefficients) and the final ones sit on the true extremes.
+    let check = |what: &str, scale: f64, result: &PcgResult| -> (f64, f64) {
+        let history = result
+            .ritz_value_history
+            .as_ref()
+            .unwrap_or_else(|| panic!("ritz-history-test: {what}: no Ritz history"));
+        assert_eq!(history.len(), result.iterations);
+        let (lo, hi) = (scale * lambda_min, scale * lambda_max);
+        for (k, &(ritz_min, ritz_max)) in history.iter().enumerate() {
+            assert!(
+                ritz_min >= lo * (1.0 - 1e-3) && ritz_max <= hi * (1.0 + 1e-3),
+                "ritz-history-test failed: {what}: T_{} Ritz values [{ritz_min}, {ritz_max}] outside [{lo}, {hi}]",
+                k + 1
+            );
+        }
+        let (ritz_min, ritz_max) = *history.last().unwrap();
+        assert!(
+            (ritz_min - lo).abs() <= 1e-2 * lo && (ritz_max - hi).abs() <= 1e-3 * hi,
+            "ritz-history-test failed: {what}: final Ritz values [{ritz_min}, {ritz_max}] vs [{lo}, {hi}]"
+        );
+        (ritz_min, ritz_max)
+    };
+
+    // Point Jacobi through the device: M^{-1} A = A / 2. With both options on, the last
+    // history entry is the condition estimate's eigenvalue range.
+    let block_starts = uniform_block_starts(n, 1);
+    let options = PcgOptions {
+        ritz_value_history: true,
+        estimate_condition_number: true,
+        ..Default::default()
+    };
+    let mut x = vec![0.0f32; n];
+    let result = device
+        .pcg_block_jacobi_csr(
+            &a,
+            &block_starts,
+            &b,
+            &mut x,
+            &SolveConfig::default()
+                .max_iters(1000)
+                .tol(1e-6)
+                .options(options.clone()),
+        )
+        .unwrap_or_else(|e| panic!("ritz-history-test: solve failed: {e}"));
+    check("point Jacobi", 0.5, &result);
+    assert_eq!(
+        result.ritz_value_history.as_ref().unwrap().last().copied(),
+        result.estimated_eigenvalue_range
+    );
+
+    // The history alone does not turn on the condition estimate.
+    let options = PcgOptions {
+        ritz_value_history: true,
+        ..Default::default()
+    };
+    let mut x = vec![0.0f32; n];
+    let result = device
+        .pcg_block_jacobi_csr(
+            &a,
+            &block_starts,
+            &b,
+            &mut x,
+            &SolveConfig::default()
+                .max_iters(1000)
+                .tol(1e-6)
+                .options(options.clone()),
+        )
+        .unwrap_or_else(|e| panic!("ritz-history-test: solve failed: {e}"));
+    assert!(result.ritz_value_history.is_some());
+    assert!(result.estimated_condition_number.is_none());
+    assert!(result.estimated_eigenvalue_range.is_none());
+
+    // Preconditioner-free on the GPU: the Ritz values of A itself.
+    let mut report = String::new();
+    if let Some(ctx) = device.gpu() {
+        let solver = PcgSolver::with_preconditioner(
+            ctx,
+            &a,
+            Box::new(IdentityOperator::new(n as u32)),
+            &SolveConfig::default()
+                .max_iters(1000)
+                .tol(1e-6)
+                .options(options),
+        )
+        .unwrap_or_else(|e| panic!("ritz-history-test: {e}"));
+        let mut x = vec![0.0f32; n];
+        let result = solver
+            .solve(&b, &mut x)
+            .unwrap_or_else(|e| panic!("ritz-history-test: unpreconditioned solve failed: {e}"));
+        let (ritz_min, ritz_max) = check("no preconditioner", 1.0, &result);
+        report = format!(
+            ", A alone: [{ritz_min:.4e}, {ritz_max:.4}] after {} iterations",
+            result.iterations
+        );
+    }
+
+    println!(
+        "RitzHistoryTest OK ({}): n={n}, Ritz values inside the spectrum every iteration, final ones on [{lambda_min:.4e}, {lambda_max:.4}]{report}",
+        device.describe()
+    );
+}
+
+fn run_gershgorin_test() {
+    // [  4 -1  0 ]   discs: center 4 radius 1 -> [3, 5]
+    // [ -1  5 -2 ]          center 5 radius 3 -> [2, 8]
+    // [  0 -2  6 ]          center 6 radius 2 -> [4, 8]
+    // => bounds (2, 8)
+    let a = Csr {
+        n_rows: 3,
+        n_cols: 3,
+        nnz: 7,
+        row_ptr: vec![0, 2, 5, 7],
+        col_idx: vec![0, 1, 0, 1, 2, 1, 2],
+        values: vec![4.0, -1.0, -1.0, 5.0, -2.0, -2.0, 6.0],
+    };
+
+    assert_eq!(
+        gershgorin_discs(&a),
+        vec![(4.0, 1.0), (5.0, 3.0), (6.0, 2.0)]
     );
+    assert_eq!(gershgorin_bounds(&a), (2.0, 8.0));
+
+    // tridiag(-1, 2.5, -1): interior discs [0.5, 4.5], end rows [1.5, 3.5] -> (0.5, 4.5)
+    let (row_ptr, col_idx, values) = tridiagonal_test_matrix(10);
+    let t = Csr {
+        n_rows: 10,
+        n_cols: 10,
+        nnz: values.len() as u32,
+        row_ptr,
+        col_idx,
+        values,
+    };
+    assert_eq!(gershgorin_bounds(&t), (0.5, 4.5));
+
+    // Unsorted row with duplicates: column 2 holds -3 + 1 = -2 (radius 2, not 4), the
+    // diagonal 1 + 3 = 4, and column 1's +1, -1 pair cancels.
+    let d = Csr {
+        n_rows: 1,
+        n_cols: 3,
+        nnz: 6,
+        row_ptr: vec![0, 6],
+        col_idx: vec![2, 0, 1, 2, 0, 1],
+        values: vec![-3.0, 1.0, 1.0, 1.0, 3.0, -1.0],
+    };
+    assert_eq!(gershgorin_discs(&d), vec![(4.0, 2.0)]);
+
+    println!("GershgorinTest OK: bounds (2, 8) and (0.5, 4.5), duplicates summed per column");
+}
+
+fn run_wgsl_validate_test() {
+    let shaders = shipped_shaders();
+    let failures: Vec<String> = shaders
+        .iter()
+        .filter_map(|shader| {
+            validate_wgsl(&shader.source)
+                .err()
+                .map(|e| format!("{}:\n{e}", shader.name))
+        })
+        .collect();
     assert!(
-        (minus_alpha - exp_minus_alpha).abs() < eps,
-        "minus_alpha got {minus_alpha}, expected {exp_minus_alpha}"
+        failures.is_empty(),
+        "wgsl-validate-test failed: {} of {} shaders rejected by naga:\n\n{}",
+        failures.len(),
+        shaders.len(),
+        failures.join("\n")
     );
+
+    // Every file of the shader directory is in the list (a new one must be added there).
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../wgpu_solver_backend/src/compute/wgsl");
+    let mut files: Vec<String> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("wgsl-validate-test: {}: {e}", dir.display()))
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".wgsl"))
+        .collect();
+    files.sort();
+    let unlisted: Vec<&String> = files
+        .iter()
+        .filter(|file| {
+            !shaders
+                .iter()
+                .any(|shader| shader.file_name() == file.as_str())
+        })
+        .collect();
     assert!(
-        (beta - exp_beta).abs() < eps,
-        "beta got {beta}, expected {exp_beta}"
+        unlisted.is_empty(),
+        "wgsl-validate-test failed: not in compute::shaders::shipped_shaders: {unlisted:?}"
     );
 
-    println!("PcgUpdateScalarsTest OK: alpha={alpha}, minus_alpha={minus_alpha}, beta={beta}");
+    // Broken shaders come back with naga's diagnostic.
+    for (what, source) in [
+        ("syntax", "fn main( {"),
+        (
+            "types",
+            "@compute @workgroup_size(1) fn main() { let a: u32 = 1.5; }",
+        ),
+    ] {
+        let err = validate_wgsl(source)
+            .err()
+            .unwrap_or_else(|| panic!("wgsl-validate-test failed: {what} error accepted"));
+        assert!(
+            err.contains("error"),
+            "wgsl-validate-test failed: {what} diagnostic without an error line: {err}"
+        );
+    }
+
+    println!(
+        "WgslValidateTest OK: {} shaders ({} files) validate with naga, malformed sources rejected",
+        shaders.len(),
+        files.len()
+    );
 }
 
-fn write_x_bin(path: &str, x: &[f32]) -> Result<(), String> {
-    use std::fs::{self, File};
-    use std::io::Write;
-    use std::path::Path;
+fn run_laplacian_test() {
+    // 1D, n = 4: tridiag(-1, 2, -1)
+    let a = laplacian_1d(4);
+    assert_eq!(a.n_rows, 4);
+    assert_eq!(a.row_ptr, vec![0, 2, 5, 8, 10]);
+    assert_eq!(a.col_idx, vec![0, 1, 0, 1, 2, 1, 2, 3, 2, 3]);
+    assert_eq!(
+        a.values,
+        vec![2.0, -1.0, -1.0, 2.0, -1.0, -1.0, 2.0, -1.0, -1.0, 2.0]
+    );
+    assert_eq!(a.nnz, 10);
+
+    // Eigenpairs: v_k[i] = sin(k pi (i+1) / (n+1)), lambda_k = 2 - 2 cos(k pi / (n+1)).
+    let eigen_residual = |a: &Csr, v: &[f32], lambda: f32| {
+        let mut av = vec![0.0f32; v.len()];
+        reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, v, &mut av);
+        av.iter()
+            .zip(v)
+            .map(|(av_i, v_i)| (av_i - lambda * v_i).abs())
+            .fold(0.0f32, f32::max)
+    };
+    let lambda_1d =
+        |n: usize, k: usize| 2.0 - 2.0 * (k as f64 * std::f64::consts::PI / (n + 1) as f64).cos();
+    let mode_1d = |n: usize, k: usize, i: usize| {
+        (k as f64 * std::f64::consts::PI * (i + 1) as f64 / (n + 1) as f64).sin()
+    };
+
+    let n = 50;
+    let a = laplacian_1d(n);
+    for k in [1, 2, n / 2, n] {
+        let v: Vec<f32> = (0..n).map(|i| mode_1d(n, k, i) as f32).collect();
+        let res = eigen_residual(&a, &v, lambda_1d(n, k) as f32);
+        assert!(
+            res < 1e-5,
+            "laplacian-test failed: 1D mode {k}: residual {res}"
+        );
+    }
 
-    let p = Path::new(path);
-    if let Some(parent) = p.parent()
-        && !parent.as_os_str().is_empty() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("create_dir_all {}: {e}", parent.display()))?;
+    // 2D: 5-point stencil, separable modes with lambda = lambda_i(nx) + lambda_j(ny).
+    let (nx, ny) = (7, 5);
+    let a = laplacian_2d(nx, ny);
+    assert_eq!(a.n_rows as usize, nx * ny);
+    assert_eq!(
+        a.nnz as usize,
+        nx * ny + 2 * ((nx - 1) * ny + nx * (ny - 1))
+    );
+    for (ki, kj) in [(1, 1), (3, 2), (nx, ny)] {
+        let v: Vec<f32> = (0..nx * ny)
+            .map(|idx| (mode_1d(nx, ki, idx % nx) * mode_1d(ny, kj, idx / nx)) as f32)
+            .collect();
+        let lambda = (lambda_1d(nx, ki) + lambda_1d(ny, kj)) as f32;
+        let res = eigen_residual(&a, &v, lambda);
+        assert!(
+            res < 1e-5,
+            "laplacian-test failed: 2D mode ({ki}, {kj}): residual {res}"
+        );
+    }
+
+    // 3D: 7-point stencil, symmetric, diagonal 6, at most 7 entries per row.
+    let (nx, ny, nz) = (4, 3, 5);
+    let a = laplacian_3d(nx, ny, nz);
+    let n = nx * ny * nz;
+    assert_eq!(
+        a.nnz as usize,
+        n + 2 * ((nx - 1) * ny * nz + nx * (ny - 1) * nz + nx * ny * (nz - 1))
+    );
+    let mut dense = vec![0.0f32; n * n];
+    for i in 0..n {
+        let row = a.row_ptr[i] as usize..a.row_ptr[i + 1] as usize;
+        assert!(row.len() <= 7);
+        assert!(
+            a.col_idx[row.clone()].windows(2).all(|w| w[0] < w[1]),
+            "laplacian-test failed: 3D row {i} columns not sorted"
+        );
+        for idx in row {
+            dense[i * n + a.col_idx[idx] as usize] = a.values[idx];
+        }
+        assert_eq!(dense[i * n + i], 6.0);
+    }
+    for i in 0..n {
+        for j in 0..n {
+            assert_eq!(
+                dense[i * n + j],
+                dense[j * n + i],
+                "laplacian-test failed: 3D not symmetric"
+            );
         }
+    }
 
-    let mut f = File::create(p).map_err(|e| format!("create {}: {e}", p.display()))?;
-    let n = x.len() as u32;
-    f.write_all(&n.to_le_bytes()).map_err(|e| e.to_string())?;
-    for &v in x {
-        f.write_all(&v.to_le_bytes()).map_err(|e| e.to_string())?;
+    assert_eq!(laplacian_2d(0, 3).n_rows, 0);
+
+    println!("LaplacianTest OK: 1D structure + eigenpairs, 2D eigenpairs, 3D stencil");
+}
+
+fn run_dirichlet_test(ctx: &GpuContext) {
+    let n = 600;
+    let (row_ptr, col_idx, values) = tridiagonal_test_matrix(n);
+    let a = Csr {
+        n_rows: n as u32,
+        n_cols: n as u32,
+        nnz: values.len() as u32,
+        row_ptr,
+        col_idx,
+        values,
+    };
+    let b = vec![1.0f32; n];
+    let constrained: [(u32, f32); 3] = [(0, 2.0), (299, -1.5), (599, 0.75)];
+
+    let (a_bc, b_bc) = apply_dirichlet(&a, &b, &constrained);
+
+    // Symmetric elimination: the modified matrix is still symmetric.
+    let entry = |m: &Csr, i: usize, j: usize| -> f32 {
+        (m.row_ptr[i] as usize..m.row_ptr[i + 1] as usize)
+            .find(|&idx| m.col_idx[idx] as usize == j)
+            .map_or(0.0, |idx| m.values[idx])
+    };
+    for i in 0..n {
+        for idx in a_bc.row_ptr[i] as usize..a_bc.row_ptr[i + 1] as usize {
+            let j = a_bc.col_idx[idx] as usize;
+            assert_eq!(
+                a_bc.values[idx],
+                entry(&a_bc, j, i),
+                "dirichlet-test failed: A not symmetric at ({i}, {j})"
+            );
+        }
     }
-    Ok(())
+
+    let block_starts = uniform_block_starts(n, 6);
+    let (_result, x) = solve_test_system(
+        ctx,
+        &a_bc.row_ptr,
+        &a_bc.col_idx,
+        &a_bc.values,
+        &block_starts,
+        &b_bc,
+        2000,
+        1e-6,
+        &PcgOptions::default(),
+    )
+    .unwrap_or_else(|e| panic!("dirichlet-test: solve failed: {e}"));
+
+    for &(i, g) in &constrained {
+        let got = x[i as usize];
+        assert!(
+            (got - g).abs() <= 1e-5 * g.abs().max(1.0),
+            "dirichlet-test failed: x[{i}] = {got}, prescribed {g}"
+        );
+    }
+
+    // Free DOFs satisfy the ORIGINAL equations.
+    let mut ax = vec![0.0f32; n];
+    reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, &x, &mut ax);
+    let max_free_residual = (0..n)
+        .filter(|i| !constrained.iter().any(|&(c, _)| c as usize == *i))
+        .map(|i| (b[i] - ax[i]).abs())
+        .fold(0.0f32, f32::max);
+    assert!(
+        max_free_residual <= 1e-4,
+        "dirichlet-test failed: free-row residual {max_free_residual}"
+    );
+
+    println!(
+        "DirichletTest OK: constrained x match prescribed values, free residual {max_free_residual:.2e}"
+    );
 }
 
-fn write_json(path: &str, json: &str) -> Result<(), String> {
-    let p = Path::new(path);
-    if let Some(parent) = p.parent()
-        && !parent.as_os_str().is_empty() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("create_dir_all {}: {e}", parent.display()))?;
+fn run_backend_variants_test() {
+    for (name, expected) in [
+        ("gl", GpuBackend::Gl),
+        ("OpenGL", GpuBackend::Gl),
+        (" webgpu ", GpuBackend::BrowserWebGpu),
+        ("WebGPU", GpuBackend::BrowserWebGpu),
+    ] {
+        assert_eq!(
+            GpuBackend::parse(name),
+            Some(expected),
+            "backend-variants-test failed: {name:?}"
+        );
+    }
+    assert_eq!(
+        resolve_backend(GpuBackend::Auto, Some("opengl")).unwrap(),
+        GpuBackend::Gl
+    );
+    assert!(GpuBackend::parse("gles3").is_none());
+
+    // Gl: a context on a GL adapter, or NoAdapter on a machine without a GL driver.
+    let compiled = Instance::enabled_backend_features();
+    let gl = executor::block_on(GpuContext::create(GpuBackend::Gl));
+    let gl_outcome = match gl {
+        Ok(ctx) => {
+            assert_eq!(
+                ctx.adapter_info.backend,
+                Backend::Gl,
+                "backend-variants-test failed: Gl gave {:?}",
+                ctx.adapter_info.backend
+            );
+            format!("opened {}", ctx.adapter_info.name)
         }
+        Err(GpuError::NoAdapter { backend, .. }) => {
+            assert_eq!(backend, GpuBackend::Gl);
+            "no GL adapter here".to_string()
+        }
+        Err(GpuError::UnsupportedBackend { backend, .. }) => {
+            assert_eq!(backend, GpuBackend::Gl);
+            assert!(!compiled.contains(Backends::GL));
+            "GL not built in".to_string()
+        }
+        Err(e) => panic!("backend-variants-test failed: Gl: {e}"),
+    };
 
-    fs::write(p, json).map_err(|e| format!("write {}: {e}", p.display()))
+    // BrowserWebGpu only exists in wasm builds with wgpu's webgpu feature.
+    let webgpu = executor::block_on(GpuContext::create(GpuBackend::BrowserWebGpu));
+    if !compiled.contains(Backends::BROWSER_WEBGPU) {
+        assert!(
+            matches!(
+                webgpu,
+                Err(GpuError::UnsupportedBackend {
+                    backend: GpuBackend::BrowserWebGpu,
+                    ..
+                })
+            ),
+            "backend-variants-test failed: BrowserWebGpu without the backend: {:?}",
+            webgpu.err()
+        );
+    }
+
+    println!(
+        "BackendVariantsTest OK: gl/opengl/webgpu parse; Gl: {gl_outcome}; BrowserWebGpu {}",
+        if compiled.contains(Backends::BROWSER_WEBGPU) {
+            "built in"
+        } else {
+            "rejected as UnsupportedBackend (not built in)"
+        }
+    );
+}
+
+fn run_env_backend_test() {
+    // No env var, no explicit choice -> auto.
+    assert_eq!(
+        resolve_backend(GpuBackend::Auto, None).unwrap(),
+        GpuBackend::Auto
+    );
+
+    // Env var set, no explicit choice -> env wins.
+    assert_eq!(
+        resolve_backend(GpuBackend::Auto, Some("vulkan")).unwrap(),
+        GpuBackend::Vulkan
+    );
+    assert_eq!(
+        resolve_backend(GpuBackend::Auto, Some(" Metal ")).unwrap(),
+        GpuBackend::Metal
+    );
+
+    // Explicit choice -> env ignored.
+    assert_eq!(
+        resolve_backend(GpuBackend::Dx12, Some("vulkan")).unwrap(),
+        GpuBackend::Dx12
+    );
+
+    // Garbage in the env var is an error, not a silent fallback.
+    assert!(resolve_backend(GpuBackend::Auto, Some("cuda")).is_err());
+
+    // Same precedence for the adapter index.
+    assert_eq!(resolve_adapter_index(None, None).unwrap(), None);
+    assert_eq!(resolve_adapter_index(None, Some("1")).unwrap(), Some(1));
+    assert_eq!(resolve_adapter_index(Some(0), Some("1")).unwrap(), Some(0));
+    assert!(resolve_adapter_index(None, Some("first")).is_err());
+
+    // The CLI itself: a child `solve` with the variable set, with and without `--backend`.
+    let dir = std::env::temp_dir().join(format!("wgpu_solver_env_backend_{}", process::id()));
+    fs::create_dir_all(&dir).unwrap_or_else(|e| panic!("env-backend-test: {e}"));
+    let (matrix, rhs) = (dir.join("a.mtx"), dir.join("b.txt"));
+    fs::write(
+        &matrix,
+        "%%MatrixMarket matrix coordinate real general\n2 2 2\n1 1 2\n2 2 4\n",
+    )
+    .unwrap_or_else(|e| panic!("env-backend-test: {e}"));
+    fs::write(&rhs, "1 1").unwrap_or_else(|e| panic!("env-backend-test: {e}"));
+    let exe = std::env::current_exe().unwrap_or_else(|e| panic!("env-backend-test: {e}"));
+    let solve = |env_value: &str, backend: Option<&str>| {
+        let mut cmd = process::Command::new(&exe);
+        cmd.env(BACKEND_ENV_VAR, env_value);
+        if let Some(backend) = backend {
+            cmd.args(["--backend", backend]);
+        }
+        let out = cmd
+            .args(["solve", "--matrix"])
+            .arg(&matrix)
+            .arg("--rhs")
+            .arg(&rhs)
+            .stderr(process::Stdio::null())
+            .output()
+            .unwrap_or_else(|e| panic!("env-backend-test: spawn: {e}"));
+        let json = serde_json::from_slice::<serde_json::Value>(&out.stdout).ok();
+        (
+            out.status.code(),
+            json.map(|j| j["gpu"]["backend"].as_str().unwrap_or_default().to_string()),
+        )
+    };
+
+    // Honored without `--backend`: cpu runs on the CPU pseudo-backend, garbage fails.
+    assert_eq!(
+        solve("cpu", None),
+        (Some(0), Some("Cpu".to_string())),
+        "env-backend-test failed: {BACKEND_ENV_VAR}=cpu not honored"
+    );
+    let (code, _) = solve("cuda", None);
+    assert_ne!(
+        code,
+        Some(0),
+        "env-backend-test failed: {BACKEND_ENV_VAR}=cuda accepted"
+    );
+    // Overridden by an explicit `--backend auto`: a GPU adapter, or the GPU init error
+    // of a machine without one, but never the CPU backend the variable asks for.
+    let (code, backend) = solve("cpu", Some("auto"));
+    assert!(
+        code != Some(0) || backend.as_deref().is_some_and(|b| b != "Cpu"),
+        "env-backend-test failed: --backend auto gave {backend:?} under {BACKEND_ENV_VAR}=cpu"
+    );
+    let _ = fs::remove_dir_all(&dir);
+    let overridden = match (code, backend) {
+        (Some(0), Some(backend)) => backend,
+        _ => "no GPU here".to_string(),
+    };
+
+    println!(
+        "EnvBackendTest OK: explicit arg > {BACKEND_ENV_VAR}/{ADAPTER_INDEX_ENV_VAR} > auto; CLI: {BACKEND_ENV_VAR}=cpu honored, --backend auto overrides it ({overridden})"
+    );
+}
+
+fn run_prometheus_format_test() {
+    let metrics = |converged: bool| SolveMetrics {
+        run_id: now_utc_rfc3339(),
+        command: "run_pcg_case".to_string(),
+        case_dir: "cases/\"quoted\" dir".to_string(),
+        n: 1234,
+        nnz: 5678,
+        max_iters: 2000,
+        rel_tol: 1e-8,
+        abs_tol: 0.0,
+        iterations: converged.then_some(370),
+        final_residual_norm: converged.then_some(1.5e-7),
+        tolerance_below_precision_floor: converged.then_some(false),
+        dispatches: converged.then_some(4242),
+        converged,
+        error: (!converged).then(|| "did not converge".to_string()),
+        timings_ms: TimingsMs {
+            total: 1500,
+            load_io: 100,
+            gpu_init: 200,
+            gpu_setup: 0,
+            solve: 1100,
+            write_out: 5,
+        },
+        pcg_timings_ms: None,
+        timestamp_period_ns: None,
+        workgroup_sizes: None,
+        gpu: GpuMetrics {
+            adapter_name: "llvmpipe (LLVM 15.0.7, 256 bits)".to_string(),
+            backend: "Gl".to_string(),
+            device_type: "Cpu".to_string(),
+            vendor: 0,
+            device: 0,
+        },
+        build: BuildMetrics {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            git_rev: None,
+        },
+    };
+
+    let unix_time = 1_700_000_000;
+
+    let text = render_prometheus(&metrics(true), unix_time);
+    let samples = validate_prometheus_text(&text)
+        .unwrap_or_else(|e| panic!("prometheus-format-test failed: {e}\n{text}"));
+    assert_eq!(
+        samples, 9,
+        "prometheus-format-test failed: converged run\n{text}"
+    );
+    for name in [
+        "wgpu_solver_iterations",
+        "wgpu_solver_final_residual_norm",
+        "wgpu_solver_wall_time_seconds",
+        "wgpu_solver_dispatches",
+        "wgpu_solver_last_run_timestamp_seconds",
+    ] {
+        assert!(
+            text.contains(&format!("# TYPE {name} gauge")),
+            "prometheus-format-test failed: missing {name}\n{text}"
+        );
+    }
+    assert!(
+        text.contains("wgpu_solver_last_run_timestamp_seconds{") && text.contains("} 1700000000\n"),
+        "prometheus-format-test failed: timestamp\n{text}"
+    );
+
+    // Failed run: no iterations / residual / dispatches, still valid.
+    let text = render_prometheus(&metrics(false), unix_time);
+    let samples = validate_prometheus_text(&text)
+        .unwrap_or_else(|e| panic!("prometheus-format-test failed: {e}\n{text}"));
+    assert_eq!(
+        samples, 6,
+        "prometheus-format-test failed: failed run\n{text}"
+    );
+
+    // The validator itself must reject malformed input.
+    assert!(validate_prometheus_text("wgpu_solver_x 1\n").is_err());
+    assert!(validate_prometheus_text("# HELP a b\n# TYPE a gauge\na{x=\"1\"} one\n").is_err());
+
+    println!("PrometheusFormatTest OK: {samples} samples for a failed run, 9 for a converged one");
 }
 
+/// Report a converged solve whose tolerance was below the f32 precision floor (the
+/// library only sets `PcgResult::tolerance_below_precision_floor`; a solve that did not
+/// converge already says so in its error).
+fn warn_if_below_precision_floor(result: &PcgResult, rel_tol: f32, abs_tol: f32) {
+    if result.tolerance_below_precision_floor {
+        eprintln!(
+            "warning: requested tolerance (rel {rel_tol:e}, abs {abs_tol:e}) is below the f32 \
+             precision floor; the true residual b - A x is unlikely to be that small"
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn run_pcg_case(
-    ctx: &GpuContext,
+    device: &mut SolverDevice,
     case_dir: &str,
     max_iters: usize,
     rel_tol: f32,
     abs_tol: f32,
-) -> Result<(usize, Vec<f32>, u32, u32), String> {
+    preconditioner: PreconditionerChoice,
+    options: &PcgOptions,
+    device_lost_retries: u32,
+) -> Result<(PcgResult, Vec<f32>, u32, u32), String> {
     // Load bin inputs (using your backend io module)
     let case = load_case_dir(Path::new(case_dir))?;
-
-    let n = case.a.n_rows as usize;
     let nnz = case.a.nnz;
 
-    // Create executors (once)
-    // SpMV
-    let spmv_exec = SpmvExecutor::create(
-        ctx,
-        case.a.n_rows,
-        &case.a.row_ptr,
-        &case.a.col_idx,
-        &case.a.values,
+    // Point Jacobi is block Jacobi with one-row blocks.
+    let block_starts = match preconditioner {
+        PreconditionerChoice::BlockJacobi => case.block_starts.starts.clone(),
+        PreconditionerChoice::Jacobi => uniform_block_starts(case.a.n_rows as usize, 1),
+    };
+
+    // Executors + LU blocks are built inside (GPU), or the reference loop runs (CPU).
+    let mut x = case.x0.values.clone();
+    let result = device.pcg_block_jacobi_csr_with_retry(
+        &case.a,
+        &block_starts,
+        &case.b.values,
+        &mut x,
+        &SolveConfig::default()
+            .max_iters(max_iters)
+            .tol(rel_tol)
+            .abs_tol(abs_tol)
+            .options(options.clone()),
+        device_lost_retries,
+        None,
+    )?;
+
+    Ok((result, x, case.a.n_rows, nnz))
+}
+
+/// Read a vector for `solve --rhs`: `.npy` (f32, flattened) by extension, otherwise
+/// `io::vector::read_f32` in the format of the extension (`VectorFormat::from_path`):
+/// a MatrixMarket array for `.mtx`, else text with values separated by whitespace,
+/// commas or newlines. Errors name the file and line.
+fn read_vector_text(path: &Path) -> Result<Vec<f32>, String> {
+    if path.extension().is_some_and(|ext| ext == "npy") {
+        return read_npy_f32(path).map(|(_, values)| values);
+    }
+    Ok(read_f32(path, VectorFormat::from_path(path))?)
+}
+
+/// Load `matrix` + `rhs` and solve from x0 = 0 for `solve`. Input errors are returned
+/// (exit code 2); a failed solve is reported in the metrics instead.
+#[allow(clippy::too_many_arguments)]
+fn run_solve_command(
+    device: &SolverDevice,
+    matrix: &str,
+    rhs: &str,
+    rel_tol: f32,
+    abs_tol: f32,
+    max_iter: usize,
+    settings: SolverSettings,
+    block_size: usize,
+) -> Result<SolveCommandMetrics, String> {
+    let preconditioner = settings.preconditioner;
+    let a = read_coo(Path::new(matrix))?.to_csr();
+    if a.n_rows != a.n_cols {
+        return Err(format!(
+            "{matrix}: matrix must be square for PCG: got {}x{}",
+            a.n_rows, a.n_cols
+        ));
+    }
+    let b = read_vector_text(Path::new(rhs))?;
+    if b.len() != a.n_rows as usize {
+        return Err(format!(
+            "{rhs}: {} values, but the matrix has n = {}",
+            b.len(),
+            a.n_rows
+        ));
+    }
+    if block_size == 0 {
+        return Err("--block-size must be >= 1".into());
+    }
+    let block_starts = match preconditioner {
+        PreconditionerChoice::BlockJacobi => uniform_block_starts(a.n_rows as usize, block_size),
+        PreconditionerChoice::Jacobi => uniform_block_starts(a.n_rows as usize, 1),
+    };
+
+    let mut x = vec![0.0f32; b.len()];
+    let t0 = std::time::Instant::now();
+    let result = device.pcg_block_jacobi_csr(
+        &a,
+        &block_starts,
+        &b,
+        &mut x,
+        &SolveConfig::default()
+            .max_iters(max_iter)
+            .tol(rel_tol)
+            .abs_tol(abs_tol)
+            .options(settings.apply_to(PcgOptions::default())),
     );
+    let wall_time_ms = t0.elapsed().as_secs_f64() * 1e3;
 
-    // Vec ops
-    let vec_ops_exec = VecOpsExecutor::create(ctx);
+    let (res, error) = match result {
+        Ok(res) => {
+            warn_if_below_precision_floor(&res, rel_tol, abs_tol);
+            (Some(res), None)
+        }
+        Err(e) => (None, Some(e)),
+    };
+    Ok(SolveCommandMetrics {
+        run_id: now_utc_rfc3339(),
+        command: "solve".to_string(),
+        gpu: solver_device_metrics(device),
+        build: BuildMetrics {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            git_rev: option_env!("GIT_REV").map(|s| s.to_string()),
+        },
+        solve: SolveSectionMetrics {
+            matrix: matrix.to_string(),
+            rhs: rhs.to_string(),
+            n: a.n_rows,
+            nnz: a.nnz,
+            preconditioner: format!("{preconditioner:?}"),
+            max_iter,
+            rel_tol,
+            abs_tol,
+            converged: res.is_some(),
+            iterations: res.as_ref().map(|r| r.iterations),
+            final_residual_norm: res.as_ref().map(|r| r.residual_norm),
+            wall_time_ms,
+            error,
+        },
+        x: res.is_some().then_some(x),
+    })
+}
+
+fn run_solve_test(backend: Option<GpuBackend>, adapter_index: Option<usize>) {
+    // 2D Laplacian, 9 x 8 grid, as a general MatrixMarket file; b as text, in two layouts.
+    let a = laplacian_2d(9, 8);
+    let n = a.n_rows as usize;
+    let mut mtx = format!(
+        "%%MatrixMarket matrix coordinate real general\n{n} {n} {}\n",
+        a.nnz
+    );
+    for i in 0..n {
+        for k in a.row_ptr[i] as usize..a.row_ptr[i + 1] as usize {
+            mtx.push_str(&format!("{} {} {}\n", i + 1, a.col_idx[k] + 1, a.values[k]));
+        }
+    }
+    let b: Vec<f32> = (0..n).map(|i| 1.0 + (i % 5) as f32 * 0.5).collect();
+    let dir = std::env::temp_dir().join(format!("wgpu_solver_solve_{}", process::id()));
+    fs::create_dir_all(&dir).unwrap_or_else(|e| panic!("solve-test: {e}"));
+    let matrix = dir.join("a.mtx");
+    let rhs_lines = dir.join("b.txt");
+    let rhs_csv = dir.join("b.csv");
+    let rhs_short = dir.join("short.txt");
+    fs::write(&matrix, mtx).unwrap_or_else(|e| panic!("solve-test: {e}"));
+    let lines: Vec<String> = b.iter().map(|v| v.to_string()).collect();
+    fs::write(&rhs_lines, format!("# b\n{}\n", lines.join("\n")))
+        .unwrap_or_else(|e| panic!("solve-test: {e}"));
+    fs::write(&rhs_csv, lines.join(", ")).unwrap_or_else(|e| panic!("solve-test: {e}"));
+    fs::write(&rhs_short, "1 2 3").unwrap_or_else(|e| panic!("solve-test: {e}"));
+
+    let exe = std::env::current_exe().unwrap_or_else(|e| panic!("solve-test: {e}"));
+    let run = |rhs: &Path, extra: &[&str]| {
+        let mut cmd = process::Command::new(&exe);
+        if let Some(backend) = backend {
+            cmd.args(["--backend", backend_arg(backend)]);
+        }
+        if let Some(index) = adapter_index {
+            cmd.args(["--adapter-index", &index.to_string()]);
+        }
+        let out = cmd
+            .args(["solve", "--matrix"])
+            .arg(&matrix)
+            .arg("--rhs")
+            .arg(rhs)
+            .args(extra)
+            .stderr(process::Stdio::null())
+            .output()
+            .unwrap_or_else(|e| panic!("solve-test: spawn: {e}"));
+        let json = serde_json::from_slice::<serde_json::Value>(&out.stdout).ok();
+        (out.status.code(), json)
+    };
+
+    let mut iterations = Vec::new();
+    let out_mtx = dir.join("x.mtx");
+    let out_txt = dir.join("x.txt");
+    let (out_mtx_arg, out_txt_arg) = (out_mtx.to_str().unwrap(), out_txt.to_str().unwrap());
+    for (rhs, extra, out, out_format) in [
+        (
+            &rhs_lines,
+            [
+                "--tol",
+                "1e-6",
+                "--preconditioner",
+                "block-jacobi",
+                "--out",
+                out_mtx_arg,
+            ],
+            &out_mtx,
+            VectorFormat::MatrixMarketArray,
+        ),
+        (
+            &rhs_csv,
+            [
+                "--tol",
+                "1e-6",
+                "--preconditioner",
+                "jacobi",
+                "--out",
+                out_txt_arg,
+            ],
+            &out_txt,
+            VectorFormat::PlainText,
+        ),
+    ] {
+        let (code, json) = run(rhs, &extra);
+        assert_eq!(code, Some(0), "solve-test failed: exit code for {extra:?}");
+        let json = json.expect("solve-test failed: stdout is not JSON");
+        assert_eq!(json["command"], "solve");
+        let solve = &json["solve"];
+        assert_eq!(solve["converged"], true, "solve-test failed: {solve}");
+        assert_eq!(solve["n"], n);
+        assert!(solve["wall_time_ms"].as_f64().is_some_and(|t| t >= 0.0));
+        let x: Vec<f32> = json["x"]
+            .as_array()
+            .expect("solve-test failed: x missing")
+            .iter()
+            .map(|v| v.as_f64().unwrap() as f32)
+            .collect();
+        assert_eq!(x.len(), n);
+        let written =
+            read_f32(out, out_format).unwrap_or_else(|e| panic!("solve-test failed: --out: {e}"));
+        assert_eq!(
+            written, x,
+            "solve-test failed: --out differs from the JSON x"
+        );
+
+        let mut ax = vec![0.0f32; n];
+        reference::spmv_csr(&a.row_ptr, &a.col_idx, &a.values, &x, &mut ax);
+        let r: f32 = ax
+            .iter()
+            .zip(&b)
+            .map(|(p, q)| (p - q).powi(2))
+            .sum::<f32>()
+            .sqrt();
+        let b_norm: f32 = b.iter().map(|v| v * v).sum::<f32>().sqrt();
+        assert!(
+            r <= 1e-5 * b_norm,
+            "solve-test failed: ||b - A x|| = {r:e} for {extra:?}"
+        );
+        iterations.push(solve["iterations"].as_u64().unwrap());
+    }
+
+    // --profile as for run-pcg-case (accurate falls back to f32 dots without SHADER_F64),
+    // with a flag on top.
+    let (code, json) = run(
+        &rhs_lines,
+        &[
+            "--tol",
+            "1e-6",
+            "--profile",
+            "accurate",
+            "--preconditioner",
+            "jacobi",
+        ],
+    );
+    assert_eq!(code, Some(0), "solve-test failed: exit code for --profile");
+    let solve = &json.expect("solve-test failed: stdout is not JSON")["solve"];
+    assert_eq!(solve["converged"], true, "solve-test failed: {solve}");
+    assert_eq!(solve["preconditioner"], "Jacobi");
+
+    // Not converged: metrics with the error and no x, exit code 1.
+    let (code, json) = run(&rhs_lines, &["--max-iter", "1"]);
+    assert_eq!(
+        code,
+        Some(1),
+        "solve-test failed: exit code without convergence"
+    );
+    let json = json.expect("solve-test failed: stdout is not JSON");
+    assert_eq!(json["solve"]["converged"], false);
+    assert!(json["solve"]["error"].is_string() && json["x"].is_null());
+    let not_written = dir.join("not_converged.txt");
+    run(
+        &rhs_lines,
+        &["--max-iter", "1", "--out", not_written.to_str().unwrap()],
+    );
+    assert!(
+        !not_written.exists(),
+        "solve-test failed: --out written without convergence"
+    );
+
+    // Bad input: no JSON, exit code 2.
+    let (code, json) = run(&rhs_short, &[]);
+    assert_eq!(
+        code,
+        Some(2),
+        "solve-test failed: exit code for a short rhs"
+    );
+    assert!(json.is_none(), "solve-test failed: JSON for a short rhs");
+    let _ = fs::remove_dir_all(&dir);
+
+    println!(
+        "SolveTest OK: n={n} solved from .mtx + text rhs (block Jacobi {} / Jacobi {} iterations), --profile accepted, failures reported",
+        iterations[0], iterations[1]
+    );
+}
+
+fn run_vector_io_test() {
+    let dir = std::env::temp_dir().join(format!("wgpu_solver_vector_io_{}", process::id()));
+    fs::create_dir_all(&dir).unwrap_or_else(|e| panic!("vector-io-test: {e}"));
+
+    // Round trip, bitwise, including the awkward values.
+    let mut rng = SplitMix64(0x10_f32);
+    let mut values: Vec<f32> = (0..200)
+        .map(|_| (rng.next_f32() - 0.5) * 10f32.powi(rng.below(60) as i32 - 30))
+        .collect();
+    values.extend([
+        0.0,
+        -0.0,
+        0.1,
+        f32::MIN_POSITIVE,
+        1e-45,
+        f32::MAX,
+        -f32::MAX,
+        f32::EPSILON,
+        f32::INFINITY,
+        f32::NEG_INFINITY,
+        f32::NAN,
+    ]);
+    let same = |a: &[f32], b: &[f32]| {
+        a.len() == b.len()
+            && a.iter()
+                .zip(b)
+                .all(|(x, y)| x.to_bits() == y.to_bits() || (x.is_nan() && y.is_nan()))
+    };
+    for (name, format) in [
+        ("x.txt", VectorFormat::PlainText),
+        ("x.mtx", VectorFormat::MatrixMarketArray),
+    ] {
+        let path = dir.join(name);
+        assert_eq!(VectorFormat::from_path(&path), format);
+        write_f32(&path, &values, format).unwrap_or_else(|e| panic!("vector-io-test: {e}"));
+        let back = read_f32(&path, format).unwrap_or_else(|e| panic!("vector-io-test: {e}"));
+        assert!(
+            same(&values, &back),
+            "vector-io-test failed: {format:?} does not round-trip"
+        );
+        let text = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        let body = match format {
+            VectorFormat::PlainText => &lines[..],
+            VectorFormat::MatrixMarketArray => {
+                assert_eq!(lines[0], "%%MatrixMarket matrix array real general");
+                assert_eq!(lines[1], format!("{} 1", values.len()));
+                &lines[2..]
+            }
+        };
+        assert_eq!(body.len(), values.len());
+        assert_eq!(&body[body.len() - 3..], ["inf", "-inf", "nan"]);
+    }
+    write_f32(&dir.join("empty.mtx"), &[], VectorFormat::MatrixMarketArray).unwrap();
+    assert!(
+        read_f32(&dir.join("empty.mtx"), VectorFormat::MatrixMarketArray)
+            .unwrap()
+            .is_empty()
+    );
+
+    // Files from elsewhere: comments, a row vector, integer fields, upper-case NaN.
+    let foreign = dir.join("foreign.mtx");
+    fs::write(
+        &foreign,
+        "%%MatrixMarket matrix array integer general\n% from a script\n1 3\n1\n-2\n3\n",
+    )
+    .unwrap();
+    assert_eq!(
+        read_f32(&foreign, VectorFormat::MatrixMarketArray).unwrap(),
+        [1.0, -2.0, 3.0]
+    );
+    let csv = dir.join("b.csv");
+    fs::write(&csv, "# b\n1.5, 2\n\nNaN -Infinity\n").unwrap();
+    let read = read_f32(&csv, VectorFormat::PlainText).unwrap();
+    assert!(read[..2] == [1.5, 2.0] && read[2].is_nan() && read[3] == f32::NEG_INFINITY);
+
+    // Malformed files name the line.
+    let mut rejected = 0;
+    for (name, contents, expect) in [
+        ("bad_value.txt", "1\n2\nx3\n", ".txt:3: invalid value 'x3'"),
+        (
+            "coordinate.mtx",
+            "%%MatrixMarket matrix coordinate real general\n2 1 1\n1 1 1\n",
+            ".mtx:1: expected",
+        ),
+        (
+            "matrix.mtx",
+            "%%MatrixMarket matrix array real general\n2 2\n1\n2\n3\n4\n",
+            ".mtx:2: size line needs",
+        ),
+        (
+            "short.mtx",
+            "%%MatrixMarket matrix array real general\n3 1\n1\n2\n",
+            "declares 3 values, file has 2",
+        ),
+    ] {
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        let err = read_f32(&path, VectorFormat::from_path(&path))
+            .err()
+            .unwrap_or_else(|| panic!("vector-io-test failed: {name} accepted"))
+            .to_string();
+        assert!(err.contains(expect), "vector-io-test failed: {name}: {err}");
+        rejected += 1;
+    }
+    assert!(matches!(
+        read_f32(&dir.join("missing.txt"), VectorFormat::PlainText),
+        Err(IoError::Io { what: "read", .. })
+    ));
+
+    // A device that takes nothing: a partial write, not a silent truncation.
+    let full = Path::new("/dev/full");
+    if full.exists() {
+        match write_f32(full, &values, VectorFormat::PlainText) {
+            Err(IoError::PartialWrite { written, total, .. }) => assert!(written < total),
+            other => panic!("vector-io-test failed: /dev/full gave {other:?}"),
+        }
+    }
+    let _ = fs::remove_dir_all(&dir);
+
+    println!(
+        "VectorIoTest OK: {} values round-trip bitwise as plain text and MatrixMarket array (NaN / Inf included), {rejected} malformed files rejected",
+        values.len()
+    );
+}
+
+/// Status word of a `serve` response frame.
+const SERVE_OK: u32 = 0;
+const SERVE_SOLVE_FAILED: u32 = 1;
+const SERVE_BAD_LENGTH: u32 = 2;
+
+/// The `serve` request loop: answer every request frame on `input` with a response
+/// frame on `output`, until end of input or a zero-length request. Returns the number of
+/// requests answered.
+///
+/// Wire format (all little-endian, no padding):
+/// - request: `u32 n`, then `n` f32: the right-hand side b (the `.bin` vector layout).
+///   `n` must be the matrix size; `n = 0` ends the session, as does EOF before a header.
+///   EOF inside a header or a payload is an error.
+/// - response: `u32 status`, `u32 iterations`, `f32 residual_norm`, `u32 len`, then
+///   `len` f32: the solution x. `status` is 0 (solved, `len = n`), 1 (the solve failed,
+///   e.g. no convergence) or 2 (request length != n); on failure `len = 0` and the reason
+///   goes to stderr. The process keeps serving after either: the payload of a request of
+///   the wrong length is skipped, not buffered.
+///
+/// Each response is flushed before the next request is read. With `warm_start` a solve
+/// starts from the last successful solution, otherwise from x0 = 0.
+fn serve_frames(
+    solver: &PcgSolver,
+    workspace: &mut SolverWorkspace,
+    input: &mut impl Read,
+    output: &mut impl Write,
+    warm_start: bool,
+) -> Result<usize, String> {
+    let n = solver.n();
+    let mut session = solver.with_workspace(workspace)?;
+    let mut x_prev = vec![0.0f32; n];
+    let mut requests = 0usize;
+
+    loop {
+        // EOF is only a clean end of session before the first header byte.
+        let mut header = [0u8; 4];
+        let mut got = 0;
+        while got < header.len() {
+            match input.read(&mut header[got..]) {
+                Ok(0) => break,
+                Ok(k) => got += k,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(format!("serve: request {requests}: read header: {e}")),
+            }
+        }
+        match got {
+            0 => break,
+            4 => {}
+            _ => {
+                return Err(format!(
+                    "serve: request {requests}: header truncated after {got} of 4 bytes"
+                ));
+            }
+        }
+        let len = u32::from_le_bytes(header) as usize;
+        if len == 0 {
+            break;
+        }
+
+        let (status, iterations, residual_norm, x) = if len != n {
+            eprintln!("serve: request {requests}: length {len}, matrix has n = {n}");
+            let expected = len as u64 * 4;
+            let skipped = std::io::copy(&mut input.take(expected), &mut std::io::sink())
+                .map_err(|e| format!("serve: request {requests}: skip {len} f32: {e}"))?;
+            if skipped < expected {
+                return Err(format!(
+                    "serve: request {requests}: payload truncated after {skipped} of {expected} bytes"
+                ));
+            }
+            (SERVE_BAD_LENGTH, 0, 0.0, Vec::new())
+        } else {
+            let mut payload = vec![0u8; len * 4];
+            input
+                .read_exact(&mut payload)
+                .map_err(|e| format!("serve: request {requests}: read {len} f32: {e}"))?;
+            let b: Vec<f32> = payload
+                .chunks_exact(4)
+                .map(|c| f32::from_le_bytes(c.try_into().unwrap()))
+                .collect();
+            let mut x = if warm_start {
+                x_prev.clone()
+            } else {
+                vec![0.0f32; n]
+            };
+            match session.solve(&b, &mut x) {
+                Ok(result) => {
+                    if warm_start {
+                        x_prev.copy_from_slice(&x);
+                    }
+                    (SERVE_OK, result.iterations as u32, result.residual_norm, x)
+                }
+                Err(e) => {
+                    eprintln!("serve: request {requests}: {e}");
+                    (SERVE_SOLVE_FAILED, 0, 0.0, Vec::new())
+                }
+            }
+        };
+
+        let mut frame = Vec::with_capacity(16 + x.len() * 4);
+        frame.extend_from_slice(&status.to_le_bytes());
+        frame.extend_from_slice(&iterations.to_le_bytes());
+        frame.extend_from_slice(&residual_norm.to_le_bytes());
+        frame.extend_from_slice(&(x.len() as u32).to_le_bytes());
+        for v in &x {
+            frame.extend_from_slice(&v.to_le_bytes());
+        }
+        output
+            .write_all(&frame)
+            .and_then(|()| output.flush())
+            .map_err(|e| format!("serve: request {requests}: write response: {e}"))?;
+        requests += 1;
+    }
+
+    Ok(requests)
+}
+
+fn run_serve_test(backend: Option<GpuBackend>, adapter_index: Option<usize>) {
+    // 2D Laplacian, 12 x 10 grid, as a general MatrixMarket file.
+    let a = laplacian_2d(12, 10);
+    let n = a.n_rows as usize;
+    let mut mtx = format!(
+        "%%MatrixMarket matrix coordinate real general\n{n} {n} {}\n",
+        a.nnz
+    );
+    for i in 0..n {
+        for k in a.row_ptr[i] as usize..a.row_ptr[i + 1] as usize {
+            mtx.push_str(&format!("{} {} {}\n", i + 1, a.col_idx[k] + 1, a.values[k]));
+        }
+    }
+    let path = std::env::temp_dir().join(format!("wgpu_solver_serve_{}.mtx", process::id()));
+    fs::write(&path, mtx).unwrap_or_else(|e| panic!("serve-test: {e}"));
+
+    let exe = std::env::current_exe().unwrap_or_else(|e| panic!("serve-test: {e}"));
+    // One `serve` process fed `request` on stdin, until it exits.
+    let serve = |request: &[u8]| {
+        let mut cmd = process::Command::new(&exe);
+        if let Some(backend) = backend {
+            cmd.args(["--backend", backend_arg(backend)]);
+        }
+        if let Some(index) = adapter_index {
+            cmd.args(["--adapter-index", &index.to_string()]);
+        }
+        let mut child = cmd
+            .args(["serve", "--matrix-market"])
+            .arg(&path)
+            .args(["--block-size", "4", "--rel-tol", "1e-6"])
+            .stdin(process::Stdio::piped())
+            .stdout(process::Stdio::piped())
+            .stderr(process::Stdio::null())
+            .spawn()
+            .unwrap_or_else(|e| panic!("serve-test: spawn: {e}"));
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(request)
+            .unwrap_or_else(|e| panic!("serve-test: write requests: {e}"));
+        child
+            .wait_with_output()
+            .unwrap_or_else(|e| panic!("serve-test: wait: {e}"))
+    };
+
+    // A right-hand side, a frame of the wrong length, a second right-hand side, then the
+    // end-of-session frame.
+    let rhs: [Vec<f32>; 2] = [
+        vec![1.0; n],
+        (0..n).map(|i| ((i % 7) as f32) - 3.0).collect(),
+    ];
+    let mut request = Vec::new();
+    for b in [rhs[0].as_slice(), &[1.0f32; 3], rhs[1].as_slice()] {
+        request.extend_from_slice(&(b.len() as u32).to_le_bytes());
+        for v in b {
+            request.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+    request.extend_from_slice(&0u32.to_le_bytes());
+    let output = serve(&request);
+    assert!(
+        output.status.success(),
+        "serve-test failed: {}",
+        output.status
+    );
+
+    let mut words = output
+        .stdout
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes(c.try_into().unwrap()));
+    let mut worst = 0.0f64;
+    for (k, b) in rhs.iter().enumerate() {
+        if k == 1 {
+            let bad: Vec<u32> = words.by_ref().take(4).collect();
+            assert_eq!(
+                bad,
+                vec![2, 0, 0, 0],
+                "serve-test failed: wrong-length request not answered with status 2"
+            );
+        }
+        let header: Vec<u32> = words.by_ref().take(4).collect();
+        assert_eq!(header.len(), 4, "serve-test failed: response {k} truncated");
+        assert_eq!(header[0], 0, "serve-test failed: response {k} status");
+        assert!(
+            header[1] > 0,
+            "serve-test failed: response {k} ran no iterations"
+        );
+        assert_eq!(
+            header[3] as usize, n,
+            "serve-test failed: response {k} length"
+        );
+        let x: Vec<f32> = words.by_ref().take(n).map(f32::from_bits).collect();
+
+        // True relative residual of the returned x.
+        let mut r2 = 0.0f64;
+        let mut b2 = 0.0f64;
+        for (&bi, row) in b.iter().zip(a.row_ptr.windows(2)) {
+            let ax: f64 = (row[0] as usize..row[1] as usize)
+                .map(|p| a.values[p] as f64 * x[a.col_idx[p] as usize] as f64)
+                .sum();
+            r2 += (bi as f64 - ax).powi(2);
+            b2 += (bi as f64).powi(2);
+        }
+        let rel = (r2 / b2).sqrt();
+        worst = worst.max(rel);
+        assert!(
+            rel <= 1e-5,
+            "serve-test failed: response {k} residual {rel:.2e}"
+        );
+    }
+    assert_eq!(
+        words.next(),
+        None,
+        "serve-test failed: trailing response data"
+    );
 
-    // Dot scalar (needs >= 7 slots for PCG)
-    let dot_scalar_exec = DotScalarExecutor::create(ctx, n, 7);
+    // A header cut short after 2 bytes is an error, not the end of the session.
+    let output = serve(&request[..2]);
+    let _ = fs::remove_file(&path);
+    assert!(
+        !output.status.success() && output.stdout.is_empty(),
+        "serve-test failed: truncated header gave {} and {} response bytes",
+        output.status,
+        output.stdout.len()
+    );
 
-    // Build GPU block-Jacobi from LU blocks.
-    //
-    // IMPORTANT:
-    // Your current BlockJacobiExecutor::create signature in CLI tests is:
-    //   create(ctx, n, &lu_blocks, &block_starts)
-    //
-    // That means: you need LU blocks on input.
-    //
-    // But our BIN case currently contains: CSR + block_starts, NOT lu_blocks.
-    //
-    // So we must build lu_blocks from CSR here (like you did earlier in webgpu).
-    //
-    // If you already have a helper in backend compute.rs to build lu_blocks:
-    //   build_lu_blocks_from_csr_block_starts_6(...)
-    // use it here.
-    //
-    // For now, we assume you have (or will add) a function:
-    //   wgpu_solver_backend::compute::block_jacobi_exec::build_lu_blocks_from_csr(...)
-    //
-    // I’ll show you exactly what to add below.
+    println!(
+        "ServeTest OK: two RHS frames answered by one resident process (max rel residual {worst:.2e}), bad frame skipped, truncated header rejected"
+    );
+}
 
-    let lu_blocks = build_lu_blocks_from_csr_block_starts_6(
-        case.a.n_rows as usize,
-        &case.a.row_ptr,
-        &case.a.col_idx,
-        &case.a.values,
-        &case.block_starts.starts,
+/// Load matrix + block_starts from `case_dir`, solve every column of `rhs_npy`, write
+/// the (n, k) solution block. Returns the iteration count per column.
+fn run_pcg_multi_rhs(
+    device: &SolverDevice,
+    case_dir: &str,
+    rhs_npy: &str,
+    max_iters: usize,
+    rel_tol: f32,
+    abs_tol: f32,
+    out_x_npy: &str,
+) -> Result<Vec<usize>, String> {
+    let case_dir = Path::new(case_dir);
+    let a = load_csr_matrix_bin(&case_dir.join("matrix.csr.bin"))?;
+    let block_starts = load_block_starts_bin(&case_dir.join("block_starts.bin"))?;
+
+    let rhs = read_npy_f32_columns(Path::new(rhs_npy), a.n_rows as usize)?;
+
+    let solved = device.pcg_block_jacobi_csr_multi_rhs(
+        &a,
+        &block_starts.starts,
+        &rhs,
+        &SolveConfig::default()
+            .max_iters(max_iters)
+            .tol(rel_tol)
+            .abs_tol(abs_tol),
     )?;
 
-    let block_jacobi_exec =
-        BlockJacobiExecutor::create(ctx, case.a.n_rows, &lu_blocks, &case.block_starts.starts);
-
-    let pcg_update_scalars_exec = PcgUpdateScalarsExecutor::create(ctx);
-
-    // Solve
-    let mut x = case.x0.values.clone();
-    let iters = pcg_block_jacobi_csr_wgpu(
-        n,
-        &case.b.values,
-        &mut x,
-        max_iters,
-        rel_tol,
-        abs_tol,
-        ctx,
-        &spmv_exec,
-        &vec_ops_exec,
-        &dot_scalar_exec,
-        &block_jacobi_exec,
-        &pcg_update_scalars_exec,
-    )?;
+    let (results, xs): (Vec<_>, Vec<_>) = solved.into_iter().unzip();
+    write_npy_f32_columns(Path::new(out_x_npy), &xs)?;
 
-    Ok((iters, x, case.a.n_rows, nnz))
+    Ok(results.iter().map(|r| r.iterations).collect())
 }
 
 fn read_f32_vec_bin(path: &str) -> Result<Vec<f32>, String> {
@@ -568,10 +11613,10 @@ fn read_f32_vec_bin(path: &str) -> Result<Vec<f32>, String> {
     let mut out = vec![0.0f32; n];
     let mut buf = [0u8; 4];
 
-    for i in 0..n {
+    for (i, v) in out.iter_mut().enumerate() {
         f.read_exact(&mut buf)
             .map_err(|e| format!("read f32[{i}] {path}: {e}"))?;
-        out[i] = f32::from_le_bytes(buf);
+        *v = f32::from_le_bytes(buf);
     }
 
     Ok(out)
@@ -718,31 +11763,83 @@ fn run_compare_x(
     }
 }
 
+fn run_diff(a_path: &str, b_path: &str) -> Result<Comparison, String> {
+    let (_, a) = read_npy_f32(Path::new(a_path))?;
+    let (_, b) = read_npy_f32(Path::new(b_path))?;
+    if a.len() != b.len() {
+        return Err(format!(
+            "Length mismatch: {a_path} has {} values, {b_path} has {}",
+            a.len(),
+            b.len()
+        ));
+    }
+
+    let c = compare(&a, &b);
+    println!("Diff:");
+    println!("  n                  : {}", c.n);
+    match c.max_abs_diff_index {
+        Some(i) => println!(
+            "  max_abs_diff       : {:.9e} at i={i} (a={:.9e}, b={:.9e})",
+            c.max_abs_diff, a[i], b[i]
+        ),
+        None => println!("  max_abs_diff       : 0 (identical)"),
+    }
+    println!("  ||a - b||_2        : {:.9e}", c.l2_diff);
+    println!("  ||a - b|| / ||a||  : {:.9e}", c.rel_l2_diff);
+
+    Ok(c)
+}
+
 fn main() {
     let cli = Cli::parse();
-    let gpu_backend = parse_backend(&cli.backend);
+    let gpu_backend = match cli.backend {
+        Some(backend) => {
+            // The library reads WGPU_SOLVER_BACKEND whenever it is handed Auto, so an
+            // explicit `--backend auto` only wins once the variable is gone.
+            // SAFETY: no other thread has been started yet.
+            unsafe { std::env::remove_var(BACKEND_ENV_VAR) };
+            backend
+        }
+        None => GpuBackend::Auto,
+    };
+    let adapter_index = cli.adapter_index;
 
     match cli.cmd {
         Cmd::Info => {
-            let ctx = executor::block_on(GpuContext::create(gpu_backend)).unwrap_or_else(|e| {
-                eprintln!("Failed to init GPU context: {e}");
-                process::exit(2);
-            });
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
 
             // Human-readable (nice in logs)
             println!("{}", ctx.describe());
+            let sample_n = 1 << 16;
+            let timings = sample_kernel_timings(&ctx, sample_n);
+            match &timings {
+                Some(timings) => {
+                    println!("kernel timings (n = {sample_n} sample):");
+                    for t in timings {
+                        println!("  {:<28} {:>10.3} us", t.label, t.elapsed_ns as f64 * 1e-3);
+                    }
+                }
+                None => println!("kernel timings: unavailable (no TIMESTAMP_QUERY)"),
+            }
 
             // Machine-readable (Slurm-friendly)
             let metrics = Metrics {
                 run_id: now_utc_rfc3339(),
                 command: "info".to_string(),
-                gpu: GpuMetrics {
-                    adapter_name: ctx.adapter_info.name.clone(),
-                    backend: format!("{:?}", ctx.adapter_info.backend),
-                    device_type: format!("{:?}", ctx.adapter_info.device_type),
-                    vendor: ctx.adapter_info.vendor,
-                    device: ctx.adapter_info.device,
-                },
+                gpu: adapter_metrics(&ctx.adapter_info),
+                kernel_timings: timings.map(|timings| {
+                    timings
+                        .into_iter()
+                        .map(|t| KernelTimingMetrics {
+                            pass: t.label,
+                            elapsed_ns: t.elapsed_ns,
+                        })
+                        .collect()
+                }),
                 build: BuildMetrics {
                     crate_version: env!("CARGO_PKG_VERSION").to_string(),
                     git_rev: option_env!("GIT_REV").map(|s| s.to_string()),
@@ -751,47 +11848,800 @@ fn main() {
 
             println!("{}", to_string_pretty(&metrics).unwrap());
         }
+        Cmd::ListAdapters { json } => run_list_adapters(gpu_backend, adapter_index, json),
+        Cmd::ListAdaptersTest => run_list_adapters_test(gpu_backend, adapter_index),
+        Cmd::GpuErrorTest => run_gpu_error_test(),
         Cmd::VecTest => {
-            let ctx: GpuContext = executor::block_on(GpuContext::create(gpu_backend))
-                .unwrap_or_else(|e| {
-                    eprintln!("Failed to init GPU context: {e}");
-                    process::exit(2);
-                });
+            let ctx: GpuContext = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
 
             run_vec_test(&ctx);
         }
         Cmd::DotTest => {
-            let ctx = executor::block_on(GpuContext::create(gpu_backend)).unwrap_or_else(|e| {
-                eprintln!("Failed to init GPU context: {e}");
-                process::exit(2);
-            });
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
 
             run_dot_test(&ctx);
         }
+        Cmd::DotReduceTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_dot_reduce_test(&ctx);
+        }
         Cmd::SpmvTest => {
-            let ctx = executor::block_on(GpuContext::create(gpu_backend)).unwrap_or_else(|e| {
-                eprintln!("Failed to init GPU context: {e}");
-                process::exit(2);
-            });
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
 
             run_spmv_test(&ctx);
         }
         Cmd::BlockJacobiTest => {
-            let ctx = executor::block_on(GpuContext::create(gpu_backend)).unwrap_or_else(|e| {
-                eprintln!("Failed to init GPU context: {e}");
-                process::exit(2);
-            });
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
 
             run_block_jacobi_test(&ctx);
         }
         Cmd::PcgUpdateScalarsTest => {
-            let ctx = executor::block_on(GpuContext::create(gpu_backend)).unwrap_or_else(|e| {
-                eprintln!("Failed to init GPU context: {e}");
-                process::exit(2);
-            });
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
 
             run_pcg_update_scalars_test(&ctx);
         }
+        Cmd::PcgTimingTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_pcg_timing_test(&ctx);
+        }
+        Cmd::PcgOccupancyTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_pcg_occupancy_test(&ctx);
+        }
+        Cmd::PcgWallClockTimingTest => {
+            let mut ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_pcg_wall_clock_timing_test(&mut ctx);
+        }
+        Cmd::SnapshotTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_snapshot_test(&ctx);
+        }
+        Cmd::MultiRhsTest => {
+            let device = executor::block_on(SolverDevice::create(
+                gpu_backend,
+                adapter_index,
+                cli.cpu_fallback,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_multi_rhs_test(&device);
+        }
+        Cmd::GershgorinTest => run_gershgorin_test(),
+        Cmd::EnvBackendTest => run_env_backend_test(),
+        Cmd::BackendVariantsTest => run_backend_variants_test(),
+        Cmd::L2NormTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_l2_norm_test(&ctx);
+        }
+        Cmd::NormalizeTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_normalize_test(&ctx);
+        }
+        Cmd::PrometheusFormatTest => run_prometheus_format_test(),
+        Cmd::CpuFallbackTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_cpu_fallback_test(&ctx);
+        }
+        Cmd::BlockJacobiAlignTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_block_jacobi_align_test(&ctx);
+        }
+        Cmd::BlockJacobiF16Test => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_block_jacobi_f16_test(&ctx);
+        }
+        Cmd::WorkgroupSizeTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_workgroup_size_test(&ctx);
+        }
+        Cmd::DirichletTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_dirichlet_test(&ctx);
+        }
+        Cmd::PrecisionFloorTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_precision_floor_test(&ctx);
+        }
+        Cmd::DeviceLostTest => {
+            let mut device = executor::block_on(SolverDevice::create(
+                gpu_backend,
+                adapter_index,
+                cli.cpu_fallback,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_device_lost_test(&mut device);
+        }
+        Cmd::ExactErrorTest => {
+            let device = executor::block_on(SolverDevice::create(
+                gpu_backend,
+                adapter_index,
+                cli.cpu_fallback,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_exact_error_test(&device);
+        }
+        Cmd::ReadbackBufferingTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_readback_buffering_test(&ctx);
+        }
+        Cmd::ReadF32BufferTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_read_f32_buffer_test(&ctx);
+        }
+        Cmd::WeightedNormTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_weighted_norm_test(&ctx);
+        }
+        Cmd::SubmissionWindowTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_submission_window_test(&ctx);
+        }
+        Cmd::SpmvFuzzTest => {
+            // GPU-gated: without an adapter there is nothing to fuzz.
+            match executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            )) {
+                Ok(ctx) => run_spmv_fuzz_test(&ctx),
+                Err(e) => println!("SpmvFuzzTest SKIPPED: no GPU context ({e})"),
+            }
+        }
+        Cmd::LaplacianTest => run_laplacian_test(),
+        Cmd::WgslValidateTest => run_wgsl_validate_test(),
+        Cmd::ConditionEstimateTest => {
+            let device = executor::block_on(SolverDevice::create(
+                gpu_backend,
+                adapter_index,
+                cli.cpu_fallback,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_condition_estimate_test(&device);
+        }
+        Cmd::RitzHistoryTest => {
+            let device = executor::block_on(SolverDevice::create(
+                gpu_backend,
+                adapter_index,
+                cli.cpu_fallback,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_ritz_history_test(&device);
+        }
+        Cmd::FixedPointDotTest => {
+            let device = executor::block_on(SolverDevice::create(
+                gpu_backend,
+                adapter_index,
+                cli.cpu_fallback,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_fixed_point_dot_test(&device);
+        }
+        Cmd::NegativeCurvatureTest => {
+            let device = executor::block_on(SolverDevice::create(
+                gpu_backend,
+                adapter_index,
+                cli.cpu_fallback,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_negative_curvature_test(&device);
+        }
+        Cmd::CustomMetricTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_custom_metric_test(&ctx);
+        }
+        Cmd::SpmvEmptyRowsTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_spmv_empty_rows_test(&ctx);
+        }
+        Cmd::CaptureMarkerTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_capture_marker_test(&ctx);
+        }
+        Cmd::ReorthTest => {
+            let device = executor::block_on(SolverDevice::create(
+                gpu_backend,
+                adapter_index,
+                cli.cpu_fallback,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_reorth_test(&device);
+        }
+        Cmd::PrecisionBenchTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_precision_bench_test(&ctx);
+        }
+        Cmd::AdditiveSchwarzTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_additive_schwarz_test(&ctx);
+        }
+        Cmd::Ilu0Test => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_ilu0_test(&ctx);
+        }
+        Cmd::SpmvStrategyTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_spmv_strategy_test(&ctx);
+        }
+        Cmd::SsorExecutorTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_ssor_executor_test(&ctx);
+        }
+        Cmd::SsorEisenstatTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_ssor_eisenstat_test(&ctx);
+        }
+        Cmd::GridImageTest => {
+            let device = executor::block_on(SolverDevice::create(
+                gpu_backend,
+                adapter_index,
+                cli.cpu_fallback,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_grid_image_test(&device);
+        }
+        Cmd::ResidualStrategyTest => {
+            let device = executor::block_on(SolverDevice::create(
+                gpu_backend,
+                adapter_index,
+                cli.cpu_fallback,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_residual_strategy_test(&device);
+        }
+        Cmd::DotPrecisionTest => {
+            let device = executor::block_on(SolverDevice::create(
+                gpu_backend,
+                adapter_index,
+                cli.cpu_fallback,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_dot_precision_test(&device);
+        }
+        Cmd::TraceFileTest => {
+            let device = executor::block_on(SolverDevice::create(
+                gpu_backend,
+                adapter_index,
+                cli.cpu_fallback,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_trace_file_test(&device);
+        }
+        Cmd::ScaledCopyTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_scaled_copy_test(&ctx);
+        }
+        Cmd::GmresOffloadTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_gmres_offload_test(&ctx);
+        }
+        Cmd::DiffTest => run_diff_test(),
+        Cmd::BlockJacobiBindGroupCacheTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_block_jacobi_bind_group_cache_test(&ctx);
+        }
+        Cmd::BlockJacobiFromCsrTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_block_jacobi_from_csr_test(&ctx);
+        }
+        Cmd::BlockJacobiSingularTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_block_jacobi_singular_test(&ctx);
+        }
+        Cmd::BlockLsqTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_block_lsq_test(&ctx);
+        }
+        Cmd::DotInterleavedTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_dot_interleaved_test(&ctx);
+        }
+        Cmd::ProfileTest => run_profile_test(),
+        Cmd::AdapterFilterTest => run_adapter_filter_test(gpu_backend),
+        Cmd::AdapterSelectorTest => run_adapter_selector_test(gpu_backend),
+        Cmd::ContextLimitsTest => run_context_limits_test(gpu_backend, adapter_index),
+        Cmd::BlockJacobiMaskTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_block_jacobi_mask_test(&ctx);
+        }
+        Cmd::CompositeOperatorTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_composite_operator_test(&ctx);
+        }
+        Cmd::Rank1UpdateTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_rank1_update_test(&ctx);
+        }
+        Cmd::ChecksumTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_checksum_test(&ctx);
+        }
+        Cmd::SubmitEveryTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_submit_every_test(&ctx);
+        }
+        Cmd::SubgroupDotTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_subgroup_dot_test(&ctx);
+        }
+        Cmd::IterSolveTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_iter_solve_test(&ctx);
+        }
+        Cmd::PrecomputedZ0Test => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_precomputed_z0_test(&ctx);
+        }
+        Cmd::TimestampPeriodTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_timestamp_period_test(&ctx);
+        }
+        Cmd::ReductionFactorTest => {
+            let device = executor::block_on(SolverDevice::create(
+                gpu_backend,
+                adapter_index,
+                cli.cpu_fallback,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_reduction_factor_test(&device);
+        }
+        Cmd::PerBlockResidualTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_per_block_residual_test(&ctx);
+        }
+        Cmd::TimerFallbackTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_timer_fallback_test(&ctx);
+        }
+        Cmd::PassTimingTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_pass_timing_test(&ctx);
+        }
+        Cmd::DiagonalJacobiTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_diagonal_jacobi_test(&ctx);
+        }
+        Cmd::WorkspaceTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_workspace_test(&ctx);
+        }
+        Cmd::SolveConfigTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_solve_config_test(&ctx);
+        }
+        Cmd::ObserverTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_observer_test(&ctx);
+        }
+        Cmd::BufferPoolTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_buffer_pool_test(&ctx);
+        }
+        Cmd::SolverBuffersTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_solver_buffers_test(&ctx);
+        }
+        Cmd::BlockStartsTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_block_starts_test(&ctx);
+        }
+        Cmd::BlockSizeTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_block_size_test(&ctx);
+        }
+        Cmd::TripletCsvTest => run_triplet_csv_test(),
+        Cmd::MatrixMarketTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_matrix_market_test(&ctx);
+        }
+        Cmd::CsrSortTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_csr_sort_test(&ctx);
+        }
+        Cmd::CsrDuplicatesTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_csr_duplicates_test(&ctx);
+        }
+        Cmd::PreconditionerTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_preconditioner_test(&ctx);
+        }
+        Cmd::SpmvApplyTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_spmv_apply_test(&ctx);
+        }
+        Cmd::AxpyRangeTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_axpy_range_test(&ctx);
+        }
+        Cmd::DotProductTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_dot_product_test(&ctx);
+        }
+        Cmd::BatchedDotTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_batched_dot_test(&ctx);
+        }
+        Cmd::HostReduceTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_host_reduce_test(&ctx);
+        }
+        Cmd::AxpyExecutorTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_axpy_executor_test(&ctx);
+        }
+        Cmd::ScaleExecutorTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_scale_executor_test(&ctx);
+        }
+        Cmd::XpbyExecutorTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_xpby_executor_test(&ctx);
+        }
+        Cmd::LabelPrefixTest => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            run_label_prefix_test(&ctx);
+        }
         Cmd::RunPcgCase {
             case_dir,
             max_iters,
@@ -799,32 +12649,88 @@ fn main() {
             abs_tol,
             out_x,
             out_metrics,
+            image,
+            grid,
+            timing,
+            timestamp_period_ns,
+            compute_units,
+            snapshot_interval,
+            snapshot_dir,
+            metrics_format,
+            device_lost_retries,
+            capture_at_iteration,
+            trace_file,
+            profile,
+            preconditioner,
+            residual_strategy,
+            reorth_window,
+            submit_every,
+            dot_workgroup_sizes,
         } => {
             use std::time::Instant;
 
+            let mut settings =
+                SolverSettings::resolve(profile, preconditioner, residual_strategy, reorth_window);
+
             let t0 = Instant::now();
             let t_load0 = Instant::now();
 
-            // Create ctx
+            // Create device (GPU, or the CPU pseudo-backend when requested / as fallback)
             let t_gpu0 = Instant::now();
-            let ctx = executor::block_on(GpuContext::create(gpu_backend)).unwrap_or_else(|e| {
-                eprintln!("Failed to init GPU context: {e}");
-                process::exit(2);
-            });
+            let mut device = executor::block_on(SolverDevice::create(
+                gpu_backend,
+                adapter_index,
+                cli.cpu_fallback,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
             let t_gpu = t_gpu0.elapsed();
+            if settings.fit_to_device(&device) {
+                eprintln!("The adapter lacks SHADER_F64: dot products accumulate in f32");
+            }
 
             // Solve (includes load inside run_pcg_case for now)
             let t_solve0 = Instant::now();
-            let result = run_pcg_case(&ctx, &case_dir, max_iters, rel_tol, abs_tol);
+            let options = PcgOptions {
+                timing,
+                timestamp_period_ns,
+                compute_units,
+                snapshot_interval,
+                snapshot_dir: snapshot_dir.into(),
+                capture_at_iteration,
+                trace_iterations: trace_file.is_some(),
+                submit_every,
+                dot_workgroup_sizes,
+                ..settings.apply_to(PcgOptions::default())
+            };
+            let result = run_pcg_case(
+                &mut device,
+                &case_dir,
+                max_iters,
+                rel_tol,
+                abs_tol,
+                settings.preconditioner,
+                &options,
+                device_lost_retries,
+            );
             let t_solve = t_solve0.elapsed();
 
-            let (iterations, x, n, nnz, converged, err) = match result {
-                Ok((iters, x, n, nnz)) => (Some(iters), x, n, nnz, true, None),
+            let (res, x, n, nnz, err) = match result {
+                Ok((res, x, n, nnz)) => {
+                    warn_if_below_precision_floor(&res, rel_tol, abs_tol);
+                    if res.timings.as_ref().is_some_and(|t| t.approximate) {
+                        eprintln!(
+                            "The device lacks timestamp queries: timings are wall-clock \
+                             approximations (GPU time included in readback)"
+                        );
+                    }
+                    (Some(res), x, n, nnz, None)
+                }
                 Err(e) => {
                     eprintln!("Solve failed: {e}");
-                    (None, Vec::new(), 0, 0, false, Some(e))
+                    (None, Vec::new(), 0, 0, Some(e))
                 }
             };
+            let converged = res.is_some();
 
             let t_write0 = Instant::now();
             if converged {
@@ -832,6 +12738,34 @@ fn main() {
                     eprintln!("Failed to write x.bin: {e}");
                     process::exit(2);
                 });
+                if let (Some(path), Some(grid)) = (&image, grid) {
+                    write_grid_png(Path::new(path), &x, grid.width, grid.height).unwrap_or_else(
+                        |e| {
+                            eprintln!("Failed to write image: {e}");
+                            process::exit(2);
+                        },
+                    );
+                }
+            }
+            if let Some(path) = &trace_file {
+                let summary = TraceLine::Summary {
+                    run_id: now_utc_rfc3339(),
+                    case_dir: case_dir.clone(),
+                    n,
+                    converged,
+                    iterations: res.as_ref().map(|r| r.iterations),
+                    final_residual_norm: res.as_ref().map(|r| r.residual_norm),
+                    error: err.clone(),
+                    solve_ms: t_solve.as_secs_f64() * 1e3,
+                };
+                let trace = res
+                    .as_ref()
+                    .and_then(|r| r.iteration_trace.as_deref())
+                    .unwrap_or_default();
+                write_trace_jsonl(path, trace, summary).unwrap_or_else(|e| {
+                    eprintln!("Failed to write trace: {e}");
+                    process::exit(2);
+                });
             }
             let t_write = t_write0.elapsed();
 
@@ -844,7 +12778,12 @@ fn main() {
                 max_iters,
                 rel_tol,
                 abs_tol,
-                iterations,
+                iterations: res.as_ref().map(|r| r.iterations),
+                final_residual_norm: res.as_ref().map(|r| r.residual_norm),
+                tolerance_below_precision_floor: res
+                    .as_ref()
+                    .map(|r| r.tolerance_below_precision_floor),
+                dispatches: res.as_ref().map(|r| r.dispatches),
                 converged,
                 error: err,
                 timings_ms: TimingsMs {
@@ -855,26 +12794,186 @@ fn main() {
                     solve: t_solve.as_millis(),
                     write_out: t_write.as_millis(),
                 },
-                gpu: GpuMetrics {
-                    adapter_name: ctx.adapter_info.name.clone(),
-                    backend: format!("{:?}", ctx.adapter_info.backend),
-                    device_type: format!("{:?}", ctx.adapter_info.device_type),
-                    vendor: ctx.adapter_info.vendor,
-                    device: ctx.adapter_info.device,
-                },
+                pcg_timings_ms: res
+                    .as_ref()
+                    .and_then(|r| r.timings.as_ref())
+                    .map(PcgTimingsMs::from),
+                timestamp_period_ns: res
+                    .as_ref()
+                    .and_then(|r| r.timings.as_ref())
+                    .map(|t| t.timestamp_period_ns),
+                workgroup_sizes: res
+                    .as_ref()
+                    .and_then(|r| r.workgroup_sizes.as_ref())
+                    .map(WorkgroupSizesMetrics::from),
+                gpu: solver_device_metrics(&device),
                 build: BuildMetrics {
                     crate_version: env!("CARGO_PKG_VERSION").to_string(),
                     git_rev: option_env!("GIT_REV").map(|s| s.to_string()),
                 },
             };
 
-            let json = to_string_pretty(&metrics).unwrap();
-            write_json(&out_metrics, &json).unwrap_or_else(|e| {
+            let text = match metrics_format {
+                MetricsFormat::Json => to_string_pretty(&metrics).unwrap(),
+                MetricsFormat::Prometheus => {
+                    render_prometheus(&metrics, OffsetDateTime::now_utc().unix_timestamp())
+                }
+            };
+            write_json(&out_metrics, &text).unwrap_or_else(|e| {
                 eprintln!("Failed to write metrics: {e}");
                 process::exit(2);
             });
 
-            println!("{json}");
+            println!("{text}");
+        }
+        Cmd::Serve {
+            case_dir,
+            matrix_market,
+            block_size,
+            max_iters,
+            rel_tol,
+            abs_tol,
+            warm_start,
+        } => {
+            let load = || -> Result<(Csr, Vec<u32>), String> {
+                match (case_dir, matrix_market) {
+                    (Some(dir), _) => {
+                        let dir = Path::new(&dir);
+                        let a = load_csr_matrix_bin(&dir.join("matrix.csr.bin"))?;
+                        let starts = load_block_starts_bin(&dir.join("block_starts.bin"))?;
+                        Ok((a, starts.starts))
+                    }
+                    (None, Some(path)) => {
+                        let a = read_coo(Path::new(&path))?.to_csr();
+                        let starts = uniform_block_starts(a.n_rows as usize, block_size);
+                        Ok((a, starts))
+                    }
+                    (None, None) => Err("serve: --case-dir or --matrix-market is required".into()),
+                }
+            };
+            let (a, block_starts) = load().unwrap_or_else(|e| {
+                eprintln!("Failed to load matrix: {e}");
+                process::exit(2);
+            });
+
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            let served = PcgSolver::new(
+                &ctx,
+                &a,
+                &block_starts,
+                &SolveConfig::default()
+                    .max_iters(max_iters)
+                    .tol(rel_tol)
+                    .abs_tol(abs_tol),
+            )
+            .and_then(|solver| {
+                let mut workspace = SolverWorkspace::new(&ctx, solver.n());
+                eprintln!("serve: n = {}, ready", solver.n());
+                serve_frames(
+                    &solver,
+                    &mut workspace,
+                    &mut BufReader::new(std::io::stdin().lock()),
+                    &mut BufWriter::new(std::io::stdout().lock()),
+                    warm_start,
+                )
+            });
+            match served {
+                Ok(requests) => eprintln!("serve: answered {requests} requests"),
+                Err(e) => {
+                    eprintln!("{e}");
+                    process::exit(2);
+                }
+            }
+        }
+        Cmd::ServeTest => run_serve_test(cli.backend, adapter_index),
+        Cmd::Solve {
+            matrix,
+            rhs,
+            out,
+            tol,
+            abs_tol,
+            max_iter,
+            profile,
+            preconditioner,
+            residual_strategy,
+            reorth_window,
+            block_size,
+        } => {
+            let mut settings =
+                SolverSettings::resolve(profile, preconditioner, residual_strategy, reorth_window);
+            let device = executor::block_on(SolverDevice::create(
+                gpu_backend,
+                adapter_index,
+                cli.cpu_fallback,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+            if settings.fit_to_device(&device) {
+                eprintln!("The adapter lacks SHADER_F64: dot products accumulate in f32");
+            }
+
+            let metrics = run_solve_command(
+                &device, &matrix, &rhs, tol, abs_tol, max_iter, settings, block_size,
+            )
+            .unwrap_or_else(|e| {
+                eprintln!("solve: {e}");
+                process::exit(2);
+            });
+            if let Some(e) = &metrics.solve.error {
+                eprintln!("Solve failed: {e}");
+            }
+            println!("{}", to_string_pretty(&metrics).unwrap());
+            if !metrics.solve.converged {
+                process::exit(1);
+            }
+            if let (Some(out), Some(x)) = (&out, &metrics.x) {
+                let out = Path::new(out);
+                write_f32(out, x, VectorFormat::from_path(out)).unwrap_or_else(|e| {
+                    eprintln!("solve: --out: {e}");
+                    process::exit(2);
+                });
+            }
+        }
+        Cmd::SolveTest => run_solve_test(cli.backend, adapter_index),
+        Cmd::VectorIoTest => run_vector_io_test(),
+        Cmd::RunPcgMultiRhs {
+            case_dir,
+            rhs_npy,
+            max_iters,
+            rel_tol,
+            abs_tol,
+            out_x_npy,
+        } => {
+            let device = executor::block_on(SolverDevice::create(
+                gpu_backend,
+                adapter_index,
+                cli.cpu_fallback,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            let iterations = run_pcg_multi_rhs(
+                &device, &case_dir, &rhs_npy, max_iters, rel_tol, abs_tol, &out_x_npy,
+            )
+            .unwrap_or_else(|e| {
+                eprintln!("Multi-RHS solve failed: {e}");
+                process::exit(2);
+            });
+
+            println!(
+                "Solved {} right-hand sides, iterations per column: {:?}",
+                iterations.len(),
+                iterations
+            );
+        }
+        Cmd::Diff { a, b } => {
+            if let Err(e) = run_diff(&a, &b) {
+                eprintln!("{e}");
+                process::exit(2);
+            }
         }
         Cmd::CompareX {
             x_ref,
@@ -888,5 +12987,172 @@ fn main() {
                 process::exit(2);
             }
         }
+        Cmd::SsorBench {
+            grid,
+            omega,
+            max_iters,
+            rel_tol,
+        } => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            let csr = laplacian_2d(grid, grid);
+            let n = csr.n_rows as usize;
+            let b = vec![1.0f32; n];
+            let b_norm = (n as f64).sqrt();
+            let rows: Vec<SsorBenchRowMetrics> = [false, true]
+                .into_iter()
+                .map(|eisenstat| {
+                    let mut x = vec![0.0f32; n];
+                    let options = SsorOptions { omega, eisenstat };
+                    let result = ssor_cg_csr_wgpu(
+                        &ctx,
+                        &csr,
+                        &b,
+                        &mut x,
+                        &SolveConfig::default().max_iters(max_iters).tol(rel_tol),
+                        &options,
+                    )
+                    .unwrap_or_else(|e| {
+                        eprintln!("{e}");
+                        process::exit(2);
+                    });
+                    SsorBenchRowMetrics {
+                        variant: if eisenstat { "eisenstat" } else { "ssor" }.to_string(),
+                        iterations: result.iterations,
+                        loop_ms: result.loop_ms,
+                        ms_per_iteration: result.loop_ms / result.iterations.max(1) as f64,
+                        dispatches_per_iteration: result.dispatches_per_iteration,
+                        final_rel_residual: result.residual_norm as f64 / b_norm,
+                    }
+                })
+                .collect();
+            let metrics = SsorBenchMetrics {
+                run_id: now_utc_rfc3339(),
+                command: "ssor-bench".to_string(),
+                gpu: GpuMetrics {
+                    adapter_name: ctx.adapter_info.name.clone(),
+                    backend: format!("{:?}", ctx.adapter_info.backend),
+                    device_type: format!("{:?}", ctx.adapter_info.device_type),
+                    vendor: ctx.adapter_info.vendor,
+                    device: ctx.adapter_info.device,
+                },
+                n: csr.n_rows,
+                nnz: csr.nnz,
+                omega,
+                rel_tol,
+                eisenstat_over_plain_per_iteration: rows[1].ms_per_iteration
+                    / rows[0].ms_per_iteration,
+                rows,
+            };
+            println!("{}", to_string_pretty(&metrics).unwrap());
+        }
+        Cmd::SpmvStrategyBench { nnz, reps } => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            let mut rng = SplitMix64(0x5eed_be4c);
+            let rows: Vec<SpmvStrategyBenchRowMetrics> = [4usize, 8, 16, 32, 64, 128, 256, 512]
+                .into_iter()
+                .map(|row_len| {
+                    let a = spmv_strategy_matrix((nnz / row_len).max(1), row_len, &mut rng);
+                    let [scalar_row_ms, warp_row_ms] =
+                        [SpmvStrategy::ScalarRow, SpmvStrategy::WarpRow].map(|strategy| {
+                            let spmv = SpmvExecutor::try_create_with_strategy(
+                                &ctx, a.n_rows, &a.row_ptr, &a.col_idx, &a.values, strategy,
+                            )
+                            .unwrap_or_else(|e| {
+                                eprintln!("{e}");
+                                process::exit(2);
+                            });
+                            time_spmv(&ctx, &spmv, reps)
+                        });
+                    SpmvStrategyBenchRowMetrics {
+                        row_len,
+                        n: a.n_rows,
+                        scalar_row_ms,
+                        warp_row_ms,
+                        warp_row_speedup: scalar_row_ms / warp_row_ms,
+                        auto: format!(
+                            "{:?}",
+                            SpmvStrategy::Auto.resolve(
+                                ctx.adapter_info.device_type,
+                                a.n_rows,
+                                a.nnz
+                            )
+                        ),
+                    }
+                })
+                .collect();
+            let metrics = SpmvStrategyBenchMetrics {
+                run_id: now_utc_rfc3339(),
+                command: "spmv-strategy-bench".to_string(),
+                gpu: GpuMetrics {
+                    adapter_name: ctx.adapter_info.name.clone(),
+                    backend: format!("{:?}", ctx.adapter_info.backend),
+                    device_type: format!("{:?}", ctx.adapter_info.device_type),
+                    vendor: ctx.adapter_info.vendor,
+                    device: ctx.adapter_info.device,
+                },
+                nnz,
+                reps,
+                auto_min_avg_nnz: SPMV_WARP_ROW_MIN_AVG_NNZ,
+                measured_crossover_row_len: rows
+                    .iter()
+                    .find(|row| row.warp_row_ms < row.scalar_row_ms)
+                    .map(|row| row.row_len),
+                rows,
+            };
+            println!("{}", to_string_pretty(&metrics).unwrap());
+        }
+        Cmd::PrecisionBench {
+            case_dir,
+            grid,
+            reps,
+            max_iters,
+            rel_tol,
+        } => {
+            let ctx = executor::block_on(GpuContext::create_with_adapter_index(
+                gpu_backend,
+                adapter_index,
+            ))
+            .unwrap_or_else(|e| exit_gpu_init_failed(&e));
+
+            let (csr, b) = match case_dir {
+                Some(dir) => {
+                    let case = load_case_dir(Path::new(&dir)).unwrap_or_else(|e| {
+                        eprintln!("Failed to load case: {e}");
+                        process::exit(2);
+                    });
+                    (case.a, case.b.values)
+                }
+                None => {
+                    let csr = laplacian_2d(grid, grid);
+                    let b = vec![1.0f32; csr.n_rows as usize];
+                    (csr, b)
+                }
+            };
+            let options = PrecisionBenchOptions {
+                reps,
+                max_iters,
+                rel_tol,
+            };
+
+            let report = run_precision_bench(&ctx, &csr, &b, &options).unwrap_or_else(|e| {
+                eprintln!("{e}");
+                process::exit(2);
+            });
+            if let Some(reason) = &report.f64_skipped {
+                eprintln!("f64 skipped: {reason}");
+            }
+            let metrics = precision_bench_metrics(&ctx, &csr, &options, &report);
+            println!("{}", to_string_pretty(&metrics).unwrap());
+        }
     }
 }